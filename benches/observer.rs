@@ -0,0 +1,92 @@
+//! Micro-benchmarks of the observer, for comparing global allocators.
+//!
+//! Run with `cargo bench`, adding `--features jemalloc` or `--features
+//! mimalloc` to measure those allocators.  Each benchmark feeds the observer
+//! a stream of states, and prints the mean time it took to observe each one:
+//!
+//! - `steady`: a handful of states, seen over and over, as in a long run
+//!   that has seen every state; this should allocate nothing;
+//! - `discovery`: a new state every iteration, as in a run's early stages or
+//!   a heavily fuzzed run; this allocates for every state.
+
+use phenolphthalein::{
+    api::{abs::Env as _, rust},
+    model::{self, manifest, slot},
+    run::{alloc, obs},
+};
+use std::{
+    hint::black_box,
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
+
+/// The number of variables in the benchmarked test.
+const VARS: usize = 4;
+
+fn main() {
+    println!("allocator\t{}", alloc::NAME);
+    report("steady", bench(1_000_000, |i| i % 4));
+    report("discovery", bench(200_000, |i| i));
+}
+
+/// Prints the mean time per observation of a benchmark taking `elapsed`
+/// over `iterations` observations.
+fn report(name: &str, (iterations, elapsed): (usize, Duration)) {
+    #[allow(clippy::cast_precision_loss)] // This is only for display.
+    let ns = elapsed.as_nanos() as f64 / iterations as f64;
+    println!("{name}\t{ns:.1} ns/observation\t({iterations} observations)");
+}
+
+/// Times `iterations` observations, filling every variable in the `i`th
+/// with `value(i)`.
+fn bench(iterations: usize, value: fn(usize) -> usize) -> (usize, Duration) {
+    let mut env: obs::Manifested<rust::env::Env> =
+        obs::Manifested::for_manifest(manifest()).expect("manifest should be valid");
+    let checker = model::Outcome::Pass;
+    let mut observer = obs::Observer::new();
+    let start = Instant::now();
+    for i in 0..iterations {
+        let v = i32::try_from(value(i)).unwrap_or(i32::MAX);
+        for index in 0..VARS {
+            env.env.set_i32(
+                slot::Slot {
+                    is_atomic: false,
+                    index,
+                },
+                v,
+            );
+        }
+        black_box(observer.observe(&mut env, &checker));
+    }
+    let elapsed = start.elapsed();
+    black_box(observer.into_report(model::schedule::Schedule::default()));
+    (iterations, elapsed)
+}
+
+/// Makes the manifest of a two-thread test with [VARS] non-atomic 32-bit
+/// integers.
+fn manifest() -> model::Manifest {
+    let i32s = (0..VARS)
+        .map(|index| {
+            let record = manifest::VarRecord {
+                initial_value: Some(0),
+                slot: slot::Slot {
+                    is_atomic: false,
+                    index,
+                },
+            };
+            (format!("x{index}").into(), record)
+        })
+        .collect();
+    model::Manifest {
+        n_threads: NonZeroUsize::new(2).unwrap(),
+        i32s,
+        bools: manifest::VarMap::new(),
+        f64s: manifest::VarMap::new(),
+        scalable_role: None,
+        symmetry: vec![],
+        invariants: model::invariant::Map::new(),
+        aux_flags: vec![],
+        declared: vec![],
+    }
+}
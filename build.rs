@@ -1,4 +1,10 @@
 fn main() {
     println!("cargo:rerun-if-changed=src/api/c/env.c");
-    cc::Build::new().file("src/api/c/env.c").compile("env");
+    let mut build = cc::Build::new();
+    build.file("src/api/c/env.c");
+    if std::env::var("CARGO_CFG_TARGET_ENV").as_deref() == Ok("msvc") {
+        // MSVC only supports the C11 atomics in env.c behind these flags.
+        build.flag("/std:c11").flag("/experimental:c11atomics");
+    }
+    build.compile("env");
 }
@@ -1,4 +1,5 @@
 //! The APIs used to communicate with concurrency tests.
 pub mod abs;
 pub mod c;
+pub mod dsl;
 pub mod rust;
@@ -3,13 +3,14 @@
 //! This module contains the various traits that the actual language APIs
 //! implement.
 use crate::{err, model};
+use std::sync::atomic::Ordering;
 
 pub mod check;
 
 #[cfg(test)]
 pub mod test_helpers;
 
-pub use check::Checker;
+pub use check::{Checker, ReportChecker};
 
 /// Trait of top-level tests.
 ///
@@ -38,8 +39,55 @@ pub trait Entry<'a>: Clone + 'a {
     /// Runs the entry point given a thread ID and handle to the environment.
     fn run(&self, tid: usize, e: &Self::Env);
 
+    /// Runs the entry point given a thread ID, the position of the current
+    /// iteration, and handle to the environment.
+    ///
+    /// The default implementation ignores the iteration and calls
+    /// [`Entry::run`]; entry points whose test bodies can vary their
+    /// behaviour by iteration should override it.
+    fn run_at(&self, tid: usize, iter: Iteration, e: &Self::Env) {
+        let _ = iter;
+        self.run(tid, e);
+    }
+
     /// Gets a checker for this entry point's environments.
     fn checker(&self) -> Box<dyn check::Checker<Self::Env> + 'a>;
+
+    /// Gets whether this entry point has a checker of its own.
+    ///
+    /// If not, [`Entry::checker`] returns a checker that always gives an
+    /// unknown outcome.
+    fn has_checker(&self) -> bool;
+
+    /// Gets a human-readable description of what this entry point's checker
+    /// checks, if it gives one.
+    ///
+    /// The default implementation gives none.
+    fn check_description(&self) -> Option<String> {
+        None
+    }
+
+    /// Gets the optional capabilities that this entry point has.
+    ///
+    /// The default implementation reports only whether the entry point has a
+    /// checker; entry points with other optional parts should override it.
+    fn capabilities(&self) -> model::capability::Set {
+        let mut caps = model::capability::Set::new();
+        if self.has_checker() {
+            caps.insert(model::capability::Capability::Check);
+        }
+        caps
+    }
+}
+
+/// The position of an iteration within a test run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Iteration {
+    /// The zero-based index of the iteration, counting from the start of the
+    /// test.
+    pub index: usize,
+    /// The zero-based index of the rotation containing the iteration.
+    pub rotation: usize,
 }
 
 /// Trait of medium-level handles to an observable test environment.
@@ -61,4 +109,195 @@ pub trait Env: Sized {
 
     /// Sets the 32-bit integer in the given slot to value v.
     fn set_i32(&mut self, slot: model::slot::Slot, v: i32);
+
+    /// Gets the Boolean in the given slot.
+    /// As with `get_i32`, out of bounds slots give valid but undefined results.
+    fn get_bool(&self, slot: model::slot::Slot) -> bool;
+
+    /// Sets the Boolean in the given slot to value v.
+    fn set_bool(&mut self, slot: model::slot::Slot, v: bool);
+
+    /// Gets the 64-bit float in the given slot.
+    /// As with `get_i32`, out of bounds slots give valid but undefined results.
+    fn get_f64(&self, slot: model::slot::Slot) -> f64;
+
+    /// Sets the 64-bit float in the given slot to value v.
+    fn set_f64(&mut self, slot: model::slot::Slot, v: f64);
+
+    /// Gets the orderings with which the test has accessed the 32-bit
+    /// integer in the given slot through [`AtomicEnv`].
+    ///
+    /// The default implementation, for environments that don't record
+    /// orderings, gives the empty set; so do non-atomic and out of bounds
+    /// slots.
+    fn i32_orderings(&self, slot: model::slot::Slot) -> model::ordering::Set {
+        let _ = slot;
+        model::ordering::Set::new()
+    }
+
+    /// Gets the orderings with which the test has accessed the Boolean in
+    /// the given slot; see [`Env::i32_orderings`].
+    fn bool_orderings(&self, slot: model::slot::Slot) -> model::ordering::Set {
+        let _ = slot;
+        model::ordering::Set::new()
+    }
+
+    /// Gets the orderings with which the test has accessed the 64-bit float
+    /// in the given slot; see [`Env::i32_orderings`].
+    fn f64_orderings(&self, slot: model::slot::Slot) -> model::ordering::Set {
+        let _ = slot;
+        model::ordering::Set::new()
+    }
+
+    /// Takes the auxiliary flags that the test has raised since they were
+    /// last taken, clearing them.
+    ///
+    /// Bit `i` of the result is set if the test raised its `i`th flag (see
+    /// [`model::manifest::Manifest::aux_flags`]).  The default
+    /// implementation, for environments without flags, gives no flags.
+    fn take_aux_flags(&mut self) -> u64 {
+        0
+    }
+}
+
+/// Trait of environments whose atomic slots support atomic operations from
+/// inside test bodies.
+///
+/// Unlike the [Env] setters, these operations only need a shared borrow, so
+/// test threads can use them on the environment while other threads run.
+/// They let tests express read-modify-write litmus tests, such as those built
+/// on compare-and-swap, with explicit memory orderings.
+///
+/// The operations only work on atomic slots.  As with [`Env::get_i32`],
+/// non-atomic and out of bounds slots give valid but undefined results (and
+/// are left alone).  Implementations should record the orderings of each
+/// access, to report through [`Env::i32_orderings`] and friends.
+pub trait AtomicEnv: Env {
+    /// Atomically loads the 32-bit integer in the given slot.
+    fn load_i32(&self, slot: model::slot::Slot, order: Ordering) -> i32;
+
+    /// Atomically stores `v` to the 32-bit integer in the given slot.
+    fn store_i32(&self, slot: model::slot::Slot, v: i32, order: Ordering);
+
+    /// Atomically replaces the 32-bit integer in the given slot with `v`,
+    /// returning the previous value.
+    fn swap_i32(&self, slot: model::slot::Slot, v: i32, order: Ordering) -> i32;
+
+    /// Atomically adds `v` (wrapping on overflow) to the 32-bit integer in
+    /// the given slot, returning the previous value.
+    fn fetch_add_i32(&self, slot: model::slot::Slot, v: i32, order: Ordering) -> i32;
+
+    /// Atomically replaces the 32-bit integer in the given slot with `new`
+    /// if it is `current`.
+    ///
+    /// The orderings are as in [`std::sync::atomic::AtomicI32::compare_exchange`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the value actually in the slot, as an error, if it wasn't
+    /// `current`.
+    fn compare_exchange_i32(
+        &self,
+        slot: model::slot::Slot,
+        current: i32,
+        new: i32,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<i32, i32>;
+
+    /// Atomically loads the Boolean in the given slot.
+    fn load_bool(&self, slot: model::slot::Slot, order: Ordering) -> bool;
+
+    /// Atomically stores `v` to the Boolean in the given slot.
+    fn store_bool(&self, slot: model::slot::Slot, v: bool, order: Ordering);
+
+    /// Atomically replaces the Boolean in the given slot with `v`, returning
+    /// the previous value.
+    fn swap_bool(&self, slot: model::slot::Slot, v: bool, order: Ordering) -> bool;
+
+    /// Atomically replaces the Boolean in the given slot with `new` if it is
+    /// `current`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the value actually in the slot, as an error, if it wasn't
+    /// `current`.
+    fn compare_exchange_bool(
+        &self,
+        slot: model::slot::Slot,
+        current: bool,
+        new: bool,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<bool, bool>;
+
+    /// Atomically loads the 64-bit float in the given slot.
+    fn load_f64(&self, slot: model::slot::Slot, order: Ordering) -> f64;
+
+    /// Atomically stores `v` to the 64-bit float in the given slot.
+    fn store_f64(&self, slot: model::slot::Slot, v: f64, order: Ordering);
+
+    /// Raises the test's auxiliary flag with index `flag`, to be counted
+    /// against the state of the current iteration.
+    ///
+    /// Flags past the last bit of the flag word are ignored.
+    fn raise_aux_flag(&self, flag: usize);
+}
+
+/// Iterates over the values of all of the variables in `manifest`, reading
+/// them from `env`.
+///
+/// This is not thread-safe until all test threads are synchronised.
+pub fn values<'a, E: Env>(
+    manifest: &'a model::manifest::Manifest,
+    env: &'a E,
+) -> impl Iterator<Item = (model::manifest::VarName, model::state::Value)> + 'a {
+    use model::state::Value;
+    let i32s = manifest
+        .i32s
+        .iter()
+        .map(|(n, r)| (n.clone(), Value::I32(env.get_i32(r.slot))));
+    let bools = manifest
+        .bools
+        .iter()
+        .map(|(n, r)| (n.clone(), Value::Bool(env.get_bool(r.slot))));
+    let f64s = manifest
+        .f64s
+        .iter()
+        .map(|(n, r)| (n.clone(), Value::F64(env.get_f64(r.slot))));
+    i32s.chain(bools).chain(f64s)
+}
+
+/// Writes the values in `state` into the variables of `env` that `manifest`
+/// names, inverting [values].
+///
+/// Variables that `state` leaves out keep their values, as do those whose
+/// values in `state` have the wrong type.
+///
+/// This is not thread-safe until all test threads are synchronised.
+pub fn load<E: Env>(
+    manifest: &model::manifest::Manifest,
+    env: &mut E,
+    state: &model::state::State,
+) {
+    use model::state::Value;
+    for (name, value) in state {
+        match value {
+            Value::I32(v) => {
+                if let Some(r) = manifest.i32s.get(name) {
+                    env.set_i32(r.slot, *v);
+                }
+            }
+            Value::Bool(v) => {
+                if let Some(r) = manifest.bools.get(name) {
+                    env.set_bool(r.slot, *v);
+                }
+            }
+            Value::F64(v) => {
+                if let Some(r) = manifest.f64s.get(name) {
+                    env.set_f64(r.slot, *v);
+                }
+            }
+        }
+    }
 }
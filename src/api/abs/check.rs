@@ -1,6 +1,8 @@
 //! The checker API.
 
-use crate::model;
+use crate::model::{self, aggregate, interest, manifest::Manifest, Outcome};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Trait of things that can check an environment.
 ///
@@ -9,6 +11,15 @@ use crate::model;
 pub trait Checker<E>: Sync + Send {
     /// Checks the current state of the environment.
     fn check(&self, env: &E) -> model::Outcome;
+
+    /// Checks the current state of the environment, recording in `parts` the
+    /// outcome of each named checker this checker combines.
+    ///
+    /// The default implementation combines nothing, and so records nothing.
+    fn check_parts(&self, env: &E, parts: &mut BTreeMap<String, Outcome>) -> Outcome {
+        let _ = parts;
+        self.check(env)
+    }
 }
 
 /// Function pointers are trivial checkers.
@@ -53,16 +64,168 @@ pub fn box_unknown<'a, E>() -> Box<dyn Checker<E> + 'a> {
     Box::new(model::Outcome::Unknown)
 }
 
+/// A named checker, as combined by [All] and [Any].
+pub type Part<'a, E> = (String, Box<dyn Checker<E> + 'a>);
+
+/// A checker that passes when all of its parts pass.
+///
+/// Parts combine with [`Outcome::and`], so any failing part fails the whole,
+/// and a checker with no parts passes.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::api::abs::check::{All, Checker};
+/// use phenolphthalein::model::Outcome;
+///
+/// let all = All::<()>(vec![
+///     ("a".to_owned(), Box::new(Outcome::Pass)),
+///     ("b".to_owned(), Box::new(Outcome::Fail)),
+/// ]);
+/// let mut parts = Default::default();
+/// assert_eq!(all.check_parts(&(), &mut parts), Outcome::Fail);
+/// assert_eq!(parts["a"], Outcome::Pass);
+/// ```
+pub struct All<'a, E>(pub Vec<Part<'a, E>>);
+
+impl<E> Checker<E> for All<'_, E> {
+    fn check(&self, env: &E) -> Outcome {
+        self.0
+            .iter()
+            .fold(Outcome::Pass, |o, (_, c)| o.and(c.check(env)))
+    }
+
+    fn check_parts(&self, env: &E, parts: &mut BTreeMap<String, Outcome>) -> Outcome {
+        check_each(&self.0, env, parts).fold(Outcome::Pass, Outcome::and)
+    }
+}
+
+/// A checker that passes when any of its parts pass.
+///
+/// Parts combine with [`Outcome::or`], so any passing part passes the whole,
+/// and a checker with no parts fails.
+pub struct Any<'a, E>(pub Vec<Part<'a, E>>);
+
+impl<E> Checker<E> for Any<'_, E> {
+    fn check(&self, env: &E) -> Outcome {
+        self.0
+            .iter()
+            .fold(Outcome::Fail, |o, (_, c)| o.or(c.check(env)))
+    }
+
+    fn check_parts(&self, env: &E, parts: &mut BTreeMap<String, Outcome>) -> Outcome {
+        check_each(&self.0, env, parts).fold(Outcome::Fail, Outcome::or)
+    }
+}
+
+/// Checks each part of a combined checker, recording its outcome.
+fn check_each<'p, E>(
+    checkers: &'p [Part<'_, E>],
+    env: &'p E,
+    parts: &'p mut BTreeMap<String, Outcome>,
+) -> impl Iterator<Item = Outcome> + 'p {
+    checkers.iter().map(move |(name, c)| {
+        let outcome = c.check(env);
+        parts.insert(name.clone(), outcome);
+        outcome
+    })
+}
+
+/// Ways of combining several checkers into one.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    /// States must pass every checker (see [All]).
+    #[default]
+    All,
+    /// States must pass at least one checker (see [Any]).
+    Any,
+}
+
+impl Mode {
+    /// Combines `parts` into one checker in this mode.
+    #[must_use]
+    pub fn combine<'a, E: 'a>(self, parts: Vec<Part<'a, E>>) -> Box<dyn Checker<E> + 'a> {
+        match self {
+            Self::All => Box::new(All(parts)),
+            Self::Any => Box::new(Any(parts)),
+        }
+    }
+}
+
+/// A checker that fails states matching any of a set of forbidden states.
+///
+/// Forbidden states are partial valuations, as with interesting states; the
+/// checker reads the environment through the manifest of the test.
+pub struct Forbidden {
+    spec: interest::Spec,
+    manifest: Manifest,
+}
+
+impl Forbidden {
+    /// Constructs a checker forbidding the states in `spec`, reading
+    /// environments laid out by `manifest`.
+    #[must_use]
+    pub fn new(spec: interest::Spec, manifest: Manifest) -> Self {
+        Self { spec, manifest }
+    }
+}
+
+impl<E: super::Env> Checker<E> for Forbidden {
+    fn check(&self, env: &E) -> Outcome {
+        let state = super::values(&self.manifest, env).collect();
+        Outcome::from_pass_bool(!self.spec.values().any(|m| m.matches(&state)))
+    }
+}
+
+/// Trait of things that can check a whole report at the end of a run.
+///
+/// Unlike a [Checker], which sees one state at a time, a report checker sees
+/// every state and how often it occurred, so can judge their distribution.
+/// Its outcome feeds into the report's overall outcome.
+pub trait ReportChecker: Sync + Send {
+    /// Checks `report`, whose states are final but whose overall outcome
+    /// doesn't yet include this check.
+    fn check_report(&self, report: &model::Report) -> Outcome;
+}
+
+/// Function pointers are trivial report checkers.
+impl ReportChecker for fn(&model::Report) -> Outcome {
+    fn check_report(&self, report: &model::Report) -> Outcome {
+        (self)(report)
+    }
+}
+
+/// Aggregate rules are report checkers.
+impl ReportChecker for aggregate::Rule {
+    fn check_report(&self, report: &model::Report) -> Outcome {
+        self.check(report)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::Checker;
-    use crate::model::Outcome;
+    use super::*;
 
     #[test]
     /// Outcomes return themselves when used as checks.
     fn test_outcome_as_check() {
-        for x in [Outcome::Unknown, Outcome::Fail, Outcome::Pass].iter() {
-            assert_eq!(*x, x.check(&()))
+        for x in &[Outcome::Unknown, Outcome::Fail, Outcome::Pass] {
+            assert_eq!(*x, x.check(&()));
         }
     }
+
+    /// Tests that [Any] records every part, even after one passes.
+    #[test]
+    fn test_any_records_parts() {
+        let any = Any::<()>(vec![
+            ("a".to_owned(), Box::new(Outcome::Pass)),
+            ("b".to_owned(), Box::new(Outcome::Unknown)),
+        ]);
+        let mut parts = BTreeMap::new();
+        assert_eq!(any.check_parts(&(), &mut parts), Outcome::Pass);
+        assert_eq!(any.check(&()), Outcome::Pass);
+        assert_eq!(parts["b"], Outcome::Unknown);
+        assert_eq!(Any::<()>(vec![]).check(&()), Outcome::Fail);
+    }
 }
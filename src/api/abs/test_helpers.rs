@@ -3,22 +3,159 @@ use crate::{
     err,
     model::slot::{Reservation, ReservationSet, Slot},
 };
-use std::iter::once;
+use std::{fmt::Debug, iter::once};
+
+/// The slot used by the getter/setter tests.
+fn test_slot(is_atomic: bool) -> Slot {
+    Slot {
+        index: 0,
+        is_atomic,
+    }
+}
+
+/// Generic testing function for environments' getter/setter pairs.
+///
+/// `reserve` installs a reservation for the test slot into a reservation set.
+///
+/// # Errors
+///
+/// Fails if the environment can't be constructed.
+///
+/// # Panics
+///
+/// Panics if the getter/setter pair doesn't round-trip.
+fn test_get_set<E: super::Env, T: Debug + Default + PartialEq + Copy>(
+    is_atomic: bool,
+    reserve: impl FnOnce(&mut ReservationSet, Reservation<T>),
+    get: impl Fn(&E, Slot) -> T,
+    set: impl FnOnce(&mut E, Slot, T),
+    value: T,
+) -> err::Result<()> {
+    let slot = test_slot(is_atomic);
+    let mut reservation = ReservationSet::default();
+    reserve(&mut reservation, Reservation::of_slots(once(slot)));
+    let mut env = E::of_reservations(reservation)?;
+
+    assert_eq!(T::default(), get(&env, slot));
+    set(&mut env, slot, value);
+    assert_eq!(value, get(&env, slot));
+
+    Ok(())
+}
 
 /// Generic testing function for environments' i32 getter/setters pairs.
+///
+/// # Errors
+///
+/// Fails if the environment can't be constructed.
+///
+/// # Panics
+///
+/// Panics if the getter/setter pair doesn't round-trip.
 pub fn test_i32_get_set<E: super::Env>(is_atomic: bool) -> err::Result<()> {
-    let slot = Slot {
-        index: 0,
+    test_get_set(is_atomic, |r, x| r.i32s = x, E::get_i32, E::set_i32, 42)
+}
+
+/// Generic testing function for environments' Boolean getter/setters pairs.
+///
+/// # Errors
+///
+/// Fails if the environment can't be constructed.
+///
+/// # Panics
+///
+/// Panics if the getter/setter pair doesn't round-trip.
+pub fn test_bool_get_set<E: super::Env>(is_atomic: bool) -> err::Result<()> {
+    test_get_set(
         is_atomic,
-    };
+        |r, x| r.bools = x,
+        E::get_bool,
+        E::set_bool,
+        true,
+    )
+}
+
+/// Generic testing function for environments' f64 getter/setters pairs.
+///
+/// # Errors
+///
+/// Fails if the environment can't be constructed.
+///
+/// # Panics
+///
+/// Panics if the getter/setter pair doesn't round-trip.
+pub fn test_f64_get_set<E: super::Env>(is_atomic: bool) -> err::Result<()> {
+    test_get_set(is_atomic, |r, x| r.f64s = x, E::get_f64, E::set_f64, -2.5)
+}
+
+/// Constructs an environment with one atomic slot for each type, returning
+/// it and the slot.
+fn atomic_env<E: super::Env>() -> err::Result<(E, Slot)> {
+    let slot = test_slot(true);
     let reservation = ReservationSet {
-        i32s: Reservation::of_slots(once(slot).into_iter()),
+        i32s: Reservation::of_slots(once(slot)),
+        bools: Reservation::of_slots(once(slot)),
+        f64s: Reservation::default(),
     };
-    let mut env = E::of_reservations(reservation)?;
+    Ok((E::of_reservations(reservation)?, slot))
+}
+
+/// Generic testing function for environments' i32 atomic operations.
+///
+/// # Errors
+///
+/// Fails if the environment can't be constructed.
+///
+/// # Panics
+///
+/// Panics if the operations don't behave like their `AtomicI32` namesakes.
+pub fn test_i32_atomic_ops<E: super::AtomicEnv>() -> err::Result<()> {
+    use std::sync::atomic::Ordering::{Acquire, Relaxed, Release, SeqCst};
+    let (env, slot) = atomic_env::<E>()?;
+
+    env.store_i32(slot, 1, Release);
+    assert_eq!(1, env.load_i32(slot, Acquire));
+    assert_eq!(1, env.fetch_add_i32(slot, 2, Relaxed));
+    assert_eq!(3, env.swap_i32(slot, i32::MAX, SeqCst));
+    assert_eq!(i32::MAX, env.fetch_add_i32(slot, 1, Relaxed));
+    assert_eq!(
+        Err(i32::MIN),
+        env.compare_exchange_i32(slot, 0, 5, SeqCst, Relaxed)
+    );
+    assert_eq!(
+        Ok(i32::MIN),
+        env.compare_exchange_i32(slot, i32::MIN, 5, SeqCst, Relaxed)
+    );
+    assert_eq!(5, env.get_i32(slot));
+
+    Ok(())
+}
+
+/// Generic testing function for environments' Boolean atomic operations.
+///
+/// # Errors
+///
+/// Fails if the environment can't be constructed.
+///
+/// # Panics
+///
+/// Panics if the operations don't behave like their `AtomicBool` namesakes.
+pub fn test_bool_atomic_ops<E: super::AtomicEnv>() -> err::Result<()> {
+    use std::sync::atomic::Ordering::{Acquire, Relaxed, Release, SeqCst};
+    let (env, slot) = atomic_env::<E>()?;
 
-    assert_eq!(0, env.get_i32(slot));
-    env.set_i32(slot, 42);
-    assert_eq!(42, env.get_i32(slot));
+    env.store_bool(slot, true, Release);
+    assert!(env.load_bool(slot, Acquire));
+    assert!(env.swap_bool(slot, false, SeqCst));
+    assert_eq!(
+        Err(false),
+        env.compare_exchange_bool(slot, true, true, SeqCst, Relaxed)
+    );
+    assert_eq!(
+        Ok(false),
+        env.compare_exchange_bool(slot, false, true, SeqCst, Relaxed)
+    );
+    assert!(env.get_bool(slot));
 
     Ok(())
 }
@@ -6,6 +6,14 @@
 
 mod entry;
 mod env;
+mod header;
 mod manifest;
+pub mod probe;
 
 pub use entry::{Checker, Entry, Test};
+pub use header::header;
+
+crate::features::register! {
+    "abi" => ["c"],
+    "c-manifests" => ["v1", "v2"],
+}
@@ -1,17 +1,106 @@
 //! C implementation of test entries and tests.
 use dlopen::symbor::{Container, Ref, SymBorApi, Symbol};
 
-use super::{env, manifest};
+use super::{env, manifest, probe};
 use crate::{api::abs, err, model};
-use std::path;
+use std::{path, time::Duration};
 
 /// Entry point for C-ABI tests coming from dynamically loaded libraries.
-#[derive(SymBorApi, Clone)]
+///
+/// Only one of `manifest` and `manifest_v2`, and one of `test`, `test_iter`,
+/// and `test_locals`, are essential; every other symbol is optional, and absent symbols fall back as described in
+/// [`model::capability`].  The invariant table needs both `invariants` and
+/// `n_invariants`, and is ignored if either is missing, as are thread locals
+/// without both `thread_locals` and `n_thread_locals`, and auxiliary flags
+/// without both `aux_flags` and `n_aux_flags`; `symmetry` must have one
+/// entry for each thread in the manifest.
+#[derive(Clone)]
 pub struct Entry<'a> {
-    manifest: Ref<'a, manifest::Manifest>,
+    /// The symbols that the library exports.
+    syms: Symbols<'a>,
+    /// Where each thread's locals sit in the environment.
+    locals: manifest::LocalsLayout,
+}
+
+/// The symbols that a test library exports; see [Entry].
+#[derive(SymBorApi, Clone)]
+struct Symbols<'a> {
+    manifest: Option<Ref<'a, manifest::Manifest>>,
+    manifest_v2: Option<Ref<'a, manifest::ManifestV2>>,
 
-    test: Symbol<'a, unsafe extern "C" fn(tid: libc::size_t, env: *mut env::UnsafeEnv)>,
+    test: Option<Symbol<'a, unsafe extern "C" fn(tid: libc::size_t, env: *mut env::UnsafeEnv)>>,
+    test_iter: Option<
+        Symbol<
+            'a,
+            unsafe extern "C" fn(tid: libc::size_t, iter: *const Iter, env: *mut env::UnsafeEnv),
+        >,
+    >,
+    test_locals: Option<
+        Symbol<
+            'a,
+            unsafe extern "C" fn(
+                tid: libc::size_t,
+                iter: *const Iter,
+                env: *mut env::UnsafeEnv,
+                locals: *const env::Locals,
+            ),
+        >,
+    >,
     check: Option<Symbol<'a, unsafe extern "C" fn(env: *const env::UnsafeEnv) -> bool>>,
+    check_description: Option<Symbol<'a, unsafe extern "C" fn() -> *const libc::c_char>>,
+    scalable_role: Option<Ref<'a, libc::size_t>>,
+    symmetry: Option<Ref<'a, libc::size_t>>,
+    invariants: Option<Ref<'a, manifest::Invariant>>,
+    n_invariants: Option<Ref<'a, libc::size_t>>,
+    thread_locals: Option<Ref<'a, manifest::Var>>,
+    n_thread_locals: Option<Ref<'a, libc::size_t>>,
+    aux_flags: Option<Ref<'a, *const libc::c_char>>,
+    n_aux_flags: Option<Ref<'a, libc::size_t>>,
+}
+
+impl Symbols<'_> {
+    /// Gets the thread locals that the test declares, if it declares any.
+    fn thread_locals(&self) -> Option<&[manifest::Var]> {
+        let (Some(locals), Some(n)) = (self.thread_locals, self.n_thread_locals) else {
+            return None;
+        };
+        Some(unsafe { manifest::vars(&raw const *locals, *n) })
+    }
+
+    /// Works out where each thread's locals sit in the environment.
+    ///
+    /// Only tests with version 2 manifests can have locals.
+    fn locals_layout(&self) -> manifest::LocalsLayout {
+        match (&self.manifest_v2, self.thread_locals()) {
+            (Some(m), Some(locals)) => m.locals_layout(locals),
+            _ => manifest::LocalsLayout::default(),
+        }
+    }
+
+    /// Gets the names of the auxiliary flags that the test declares, if it
+    /// declares any.
+    fn aux_flags(&self) -> Option<Vec<String>> {
+        let (Some(flags), Some(n)) = (self.aux_flags, self.n_aux_flags) else {
+            return None;
+        };
+        Some(unsafe { manifest::names(&raw const *flags, *n) })
+    }
+}
+
+/// The C-ABI view of an iteration position, as `struct iter` in `phenol.h`.
+#[repr(C)]
+pub struct Iter {
+    index: libc::size_t,
+    rotation: libc::size_t,
+}
+
+impl From<abs::Iteration> for Iter {
+    fn from(i: abs::Iteration) -> Self {
+        Self {
+            index: i.index,
+            rotation: i.rotation,
+        }
+    }
 }
 
 /// A checker for C-ABI test environments.
@@ -20,7 +109,7 @@ pub struct Checker<'a> {
     sym: Symbol<'a, unsafe extern "C" fn(env: *const env::UnsafeEnv) -> bool>,
 }
 
-impl<'a> abs::Checker<env::Env> for Checker<'a> {
+impl abs::Checker<env::Env> for Checker<'_> {
     fn check(&self, e: &env::Env) -> model::Outcome {
         model::Outcome::from_pass_bool(unsafe { (self.sym)(e.p) })
     }
@@ -30,35 +119,191 @@ impl<'a> abs::Entry<'a> for Entry<'a> {
     type Env = env::Env;
 
     fn run(&self, tid: usize, e: &Self::Env) {
-        unsafe { (self.test)(tid, e.p) }
+        self.run_at(tid, abs::Iteration::default(), e);
+    }
+
+    /// Runs `test_locals` if the test exports it, then `test_iter`, and
+    /// `test` otherwise.
+    fn run_at(&self, tid: usize, iter: abs::Iteration, e: &Self::Env) {
+        let syms = &self.syms;
+        if let Some(sym) = syms.test_locals {
+            let iter = Iter::from(iter);
+            let locals = e.locals(&self.locals, tid);
+            unsafe { sym(tid, &raw const iter, e.p, &raw const locals) }
+        } else if let Some(sym) = syms.test_iter {
+            let iter = Iter::from(iter);
+            unsafe { sym(tid, &raw const iter, e.p) }
+        } else if let Some(sym) = syms.test {
+            unsafe { sym(tid, e.p) }
+        }
     }
 
+    /// Reads `manifest_v2` if the test exports it, and `manifest` otherwise,
+    /// then adds each thread's copies of any thread locals.
+    ///
+    /// The result copies everything out of the library's data, so changes
+    /// that the test makes to that data later don't reach it.
     fn make_manifest(&self) -> err::Result<model::manifest::Manifest> {
-        self.manifest.to_manifest()
+        let syms = &self.syms;
+        let locals = syms.thread_locals();
+        let mut manifest = match (&syms.manifest_v2, &syms.manifest) {
+            (Some(m), _) => m.to_manifest(),
+            (None, Some(_)) if locals.is_some() => Err(err::Error::UnsupportedThreadLocals(
+                "has a version 1 `manifest` rather than a `manifest_v2`",
+            )),
+            (None, Some(m)) => m.to_manifest(),
+            (None, None) => Err(err::Error::NoManifest),
+        }?;
+        manifest.scalable_role = syms.scalable_role.map(|r| *r);
+        if let Some(locals) = locals {
+            // Extra threads would have no room for their locals.
+            if manifest.scalable_role.is_some() {
+                return Err(err::Error::UnsupportedThreadLocals("has a scalable role"));
+            }
+            manifest::add_thread_locals(&mut manifest, locals)?;
+        }
+        if let Some(symmetry) = syms.symmetry {
+            let n = manifest.n_threads.get();
+            manifest.symmetry =
+                unsafe { std::slice::from_raw_parts(&raw const *symmetry, n) }.to_vec();
+        }
+        if let (Some(invariants), Some(n)) = (syms.invariants, syms.n_invariants) {
+            let loaded = unsafe { manifest::invariants(&raw const *invariants, *n) }?;
+            // Rekeying shares the names with the manifest's variables.
+            manifest.invariants = loaded
+                .into_iter()
+                .map(|(var, bounds)| {
+                    manifest
+                        .var_name(&var)
+                        .map(|name| (name, bounds))
+                        .ok_or_else(|| err::Error::UnknownInvariantVar(var.to_string()))
+                })
+                .collect::<err::Result<_>>()?;
+        }
+        if let Some(flags) = syms.aux_flags() {
+            if model::manifest::MAX_AUX_FLAGS < flags.len() {
+                return Err(err::Error::TooManyAuxFlags(flags.len()));
+            }
+            manifest.aux_flags = flags;
+        }
+        Ok(manifest)
     }
 
     /// Gets a checker for this test.
     fn checker(&self) -> Box<dyn abs::Checker<Self::Env> + 'a> {
-        self.check
+        self.syms
+            .check
             .map_or_else(abs::check::box_unknown, |sym| Box::new(Checker { sym }))
     }
+
+    fn has_checker(&self) -> bool {
+        self.syms.check.is_some()
+    }
+
+    /// Calls `check_description` if the test exports it, treating a null
+    /// result as no description.
+    fn check_description(&self) -> Option<String> {
+        let sym = self.syms.check_description?;
+        let ptr = unsafe { sym() };
+        (!ptr.is_null()).then(|| {
+            unsafe { std::ffi::CStr::from_ptr(ptr) }
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+
+    fn capabilities(&self) -> model::capability::Set {
+        use model::capability::Capability;
+        let syms = &self.syms;
+        [
+            (Capability::Check, syms.check.is_some()),
+            (Capability::TestIter, syms.test_iter.is_some()),
+            (Capability::ScalableRole, syms.scalable_role.is_some()),
+            (Capability::Symmetry, syms.symmetry.is_some()),
+            (
+                Capability::Invariants,
+                syms.invariants.is_some() && syms.n_invariants.is_some(),
+            ),
+            (
+                Capability::CheckDescription,
+                syms.check_description.is_some(),
+            ),
+            (Capability::ThreadLocals, syms.thread_locals().is_some()),
+            (
+                Capability::AuxFlags,
+                syms.aux_flags.is_some() && syms.n_aux_flags.is_some(),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(cap, present)| present.then_some(cap))
+        .collect()
+    }
 }
 
+/// How long to wait before retrying a failed load of a library that looks
+/// loadable.
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
 /// A test that holds onto a dynamically loaded test library.
 pub struct Test {
-    c: Container<Entry<'static>>,
+    c: Container<Symbols<'static>>,
+    /// Where each thread's locals sit in the environment.
+    locals: manifest::LocalsLayout,
 }
 
 impl Test {
     /// Loads a test from a dynamic library at `file`.
     ///
+    /// Before loading the library, we check its header to make sure that it
+    /// is for this machine's architecture, as the dynamic loader's errors
+    /// for foreign libraries are cryptic.  If the load fails, we probe the library (see [`probe`]) to work out
+    /// why.  If the probe finds nothing wrong, the library may have been
+    /// caught halfway through being rebuilt, so we retry the load once
+    /// before giving up.
+    ///
     /// # Errors
     ///
-    /// Returns errors if the dynamic library fails to load, or any initial safety checks fail.
+    /// Returns errors if the dynamic library fails to load, or any initial
+    /// safety checks fail.  Failed loads give the most specific error that
+    /// probing the library can find.
     pub fn load(file: &path::Path) -> err::Result<Self> {
-        let c = unsafe { Container::load(file) }?;
-        // TODO(@MattWindsor91): perform basic safety checks.
-        Ok(Test { c })
+        probe::check_arch(file)?;
+        let c: Container<Symbols<'static>> = match unsafe { Container::load(file) } {
+            Ok(c) => c,
+            Err(e) => {
+                let probe = probe::Probe::of_file(file);
+                if !probe.looks_loadable() {
+                    return Err(probe.diagnose(e));
+                }
+                std::thread::sleep(RETRY_DELAY);
+                unsafe { Container::load(file) }
+                    .map_err(|e| probe::Probe::of_file(file).diagnose(e))?
+            }
+        };
+        // TODO(@MattWindsor91): perform more basic safety checks.
+        if c.manifest.is_none() && c.manifest_v2.is_none() {
+            return Err(err::Error::NoManifest);
+        }
+        if c.test.is_none() && c.test_iter.is_none() && c.test_locals.is_none() {
+            return Err(err::Error::NoTestBody);
+        }
+        let locals = c.locals_layout();
+        Ok(Test { c, locals })
+    }
+}
+
+impl Test {
+    /// Gets an entry point into the test that can't outlive the library.
+    ///
+    /// Unlike [`abs::Test::spawn`], this ties the entry point to the borrow
+    /// of the test, so the borrow checker makes sure that nothing still uses
+    /// the library when the test is dropped and the library unloaded.
+    #[must_use]
+    pub fn entry(&self) -> Entry<'_> {
+        Entry {
+            syms: (*self.c).clone(),
+            locals: self.locals,
+        }
     }
 }
 
@@ -66,6 +311,9 @@ impl<'a> abs::Test<'a> for Test {
     type Entry = Entry<'a>;
 
     fn spawn(&self) -> self::Entry<'a> {
-        self.c.clone()
+        Entry {
+            syms: self.c.clone(),
+            locals: self.locals,
+        }
     }
 }
@@ -1,24 +1,45 @@
 #include <stdatomic.h>
+#include <stdbool.h>
 #include <stdint.h>
 #include <stdlib.h>
 
 #include "env.h"
 #include "phenol.h"
 
+/* Allocates n elements of size sz, treating empty arrays as successful. */
+static void *
+alloc_slots(size_t n, size_t sz, bool *ok)
+{
+	void *p = calloc(n, sz);
+	if (p == NULL && n != 0) *ok = false;
+	return p;
+}
+
 struct env *
-alloc_env(size_t natomic_int32, size_t nint32)
+alloc_env(size_t natomic_int32, size_t nint32,
+          size_t natomic_bool, size_t nbool,
+          size_t natomic_double, size_t ndouble)
 {
+	bool ok = true;
 	struct env *e = calloc(1, sizeof(struct env));
 	if (e == NULL) return NULL;
 
 	e->natomic_int32 = natomic_int32;
-	e->atomic_int32 = calloc(natomic_int32, sizeof(_Atomic int32_t));
-	if (e->atomic_int32 == NULL) goto fail;
-
+	e->atomic_int32 = alloc_slots(natomic_int32, sizeof(_Atomic int32_t), &ok);
 	e->nint32 = nint32;
-	e->int32 = calloc(nint32, sizeof(int32_t));
-	if (e->int32 == NULL) goto fail;
-	
+	e->int32 = alloc_slots(nint32, sizeof(int32_t), &ok);
+
+	e->natomic_bool = natomic_bool;
+	e->atomic_bools = alloc_slots(natomic_bool, sizeof(_Atomic bool), &ok);
+	e->nbool = nbool;
+	e->bools = alloc_slots(nbool, sizeof(bool), &ok);
+
+	e->natomic_double = natomic_double;
+	e->atomic_doubles = alloc_slots(natomic_double, sizeof(_Atomic double), &ok);
+	e->ndouble = ndouble;
+	e->doubles = alloc_slots(ndouble, sizeof(double), &ok);
+
+	if (!ok) goto fail;
 	return e;
 fail:
 	free_env(e);
@@ -31,33 +52,123 @@ free_env(struct env *e)
 	if (e == NULL) return;
 	if (e->atomic_int32 != NULL) free(e->atomic_int32);
 	if (e->int32 != NULL) free(e->int32);
+	if (e->atomic_bools != NULL) free(e->atomic_bools);
+	if (e->bools != NULL) free(e->bools);
+	if (e->atomic_doubles != NULL) free(e->atomic_doubles);
+	if (e->doubles != NULL) free(e->doubles);
 	free(e);
 }
 
 int32_t
 get_int32(const struct env *e, size_t c)
 {
-	if (e->nint32 < c) return 0;
+	if (e->nint32 <= c) return 0;
 	return e->int32[c];
 }
 
 int32_t
 get_atomic_int32(const struct env *e, size_t c)
 {
-	if (e->natomic_int32 < c) return 0;
+	if (e->natomic_int32 <= c) return 0;
 	return e->atomic_int32[c];
 }
 
 void
 set_int32(struct env *e, size_t c, int32_t v)
 {
-	if (e->nint32 < c) return;
+	if (e->nint32 <= c) return;
 	e->int32[c] = v;
 }
 
 void
 set_atomic_int32(struct env *e, size_t c, int32_t v)
 {
-	if (e->natomic_int32 < c) return;
+	if (e->natomic_int32 <= c) return;
 	e->atomic_int32[c] = v;
 }
+
+bool
+get_bool(const struct env *e, size_t c)
+{
+	if (e->nbool <= c) return false;
+	return e->bools[c];
+}
+
+bool
+get_atomic_bool(const struct env *e, size_t c)
+{
+	if (e->natomic_bool <= c) return false;
+	return e->atomic_bools[c];
+}
+
+void
+set_bool(struct env *e, size_t c, bool v)
+{
+	if (e->nbool <= c) return;
+	e->bools[c] = v;
+}
+
+void
+set_atomic_bool(struct env *e, size_t c, bool v)
+{
+	if (e->natomic_bool <= c) return;
+	e->atomic_bools[c] = v;
+}
+
+double
+get_double(const struct env *e, size_t c)
+{
+	if (e->ndouble <= c) return 0.0;
+	return e->doubles[c];
+}
+
+double
+get_atomic_double(const struct env *e, size_t c)
+{
+	if (e->natomic_double <= c) return 0.0;
+	return e->atomic_doubles[c];
+}
+
+void
+set_double(struct env *e, size_t c, double v)
+{
+	if (e->ndouble <= c) return;
+	e->doubles[c] = v;
+}
+
+void
+set_atomic_double(struct env *e, size_t c, double v)
+{
+	if (e->natomic_double <= c) return;
+	e->atomic_doubles[c] = v;
+}
+
+/* Gets the address of the locals of the thread at offset tid * count from
+   base in the array at p, whose elements have size sz. */
+static void *
+locals_in(void *p, size_t sz, size_t base, size_t count, size_t tid)
+{
+	if (count == 0) return NULL;
+	return (char *)p + (base + tid * count) * sz;
+}
+
+void
+get_locals(struct env *e, const size_t *bases, const size_t *counts,
+           size_t tid, struct locals *l)
+{
+	l->atomic_int32 = locals_in(e->atomic_int32, sizeof(_Atomic int32_t),
+	                            bases[0], counts[0], tid);
+	l->int32 = locals_in(e->int32, sizeof(int32_t), bases[1], counts[1], tid);
+	l->atomic_bools = locals_in(e->atomic_bools, sizeof(_Atomic bool),
+	                            bases[2], counts[2], tid);
+	l->bools = locals_in(e->bools, sizeof(bool), bases[3], counts[3], tid);
+	l->atomic_doubles = locals_in(e->atomic_doubles, sizeof(_Atomic double),
+	                              bases[4], counts[4], tid);
+	l->doubles = locals_in(e->doubles, sizeof(double), bases[5], counts[5], tid);
+}
+
+uint64_t
+take_aux_flags(struct env *e)
+{
+	return atomic_exchange(&e->aux_flags, 0);
+}
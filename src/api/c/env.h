@@ -1,12 +1,16 @@
 #ifndef ENV_H
 #define ENV_H
 
+#include <stdbool.h>
+#include <stddef.h>
 #include <stdint.h>
 
 struct env;
 
 // Constructs a new environment with the given number of variable slots.
-struct env *alloc_env(size_t n_atomic_int32, size_t n_int32);
+struct env *alloc_env(size_t n_atomic_int32, size_t n_int32,
+                      size_t n_atomic_bool, size_t n_bool,
+                      size_t n_atomic_double, size_t n_double);
 
 // Frees the environment e.
 // Depending on the implementation of copy_env, this may or may not actually
@@ -37,4 +41,58 @@ void set_atomic_int32(struct env *e, size_t c, int32_t v);
 // Not guaranteed to be thread-safe.
 void set_int32(struct env *e, size_t c, int32_t v);
 
+// Gets the atomic bool at index c from env e.
+// Not guaranteed to be thread-safe.
+bool get_atomic_bool(const struct env *e, size_t c);
+
+// Gets the bool at index c from env e.
+// Not guaranteed to be thread-safe.
+bool get_bool(const struct env *e, size_t c);
+
+// Sets the atomic bool at index c of env e.
+// Not guaranteed to be thread-safe.
+void set_atomic_bool(struct env *e, size_t c, bool v);
+
+// Sets the bool at index c of env e.
+// Not guaranteed to be thread-safe.
+void set_bool(struct env *e, size_t c, bool v);
+
+// Gets the atomic double at index c from env e.
+// Not guaranteed to be thread-safe.
+double get_atomic_double(const struct env *e, size_t c);
+
+// Gets the double at index c from env e.
+// Not guaranteed to be thread-safe.
+double get_double(const struct env *e, size_t c);
+
+// Sets the atomic double at index c of env e.
+// Not guaranteed to be thread-safe.
+void set_atomic_double(struct env *e, size_t c, double v);
+
+// Sets the double at index c of env e.
+// Not guaranteed to be thread-safe.
+void set_double(struct env *e, size_t c, double v);
+
+/*
+ * Thread locals.
+ */
+
+struct locals;
+
+// Points l at thread tid's locals in env e.
+// bases and counts each have one entry per slot array of e, in order, giving
+// the number of other variables in the array and the number of locals each
+// thread has there; thread tid's locals start at index
+// bases[k] + tid * counts[k].  Arrays holding no locals get null pointers.
+void get_locals(struct env *e, const size_t *bases, const size_t *counts,
+                size_t tid, struct locals *l);
+
+/*
+ * Auxiliary flags.
+ */
+
+// Gets the auxiliary flags raised in env e since they were last taken, one
+// bit each, and clears them.
+uint64_t take_aux_flags(struct env *e);
+
 #endif /* ENV_H */
@@ -1,5 +1,6 @@
 use slot::ReservationSet;
 
+use super::manifest::LocalsLayout;
 use crate::{api::abs, err, model::slot};
 use std::ptr;
 
@@ -13,12 +14,66 @@ pub(super) struct UnsafeEnv {
 }
 
 extern "C" {
-    fn alloc_env(atomic_ints: libc::size_t, ints: libc::size_t) -> *mut UnsafeEnv;
+    fn alloc_env(
+        atomic_ints: libc::size_t,
+        ints: libc::size_t,
+        atomic_bools: libc::size_t,
+        bools: libc::size_t,
+        atomic_doubles: libc::size_t,
+        doubles: libc::size_t,
+    ) -> *mut UnsafeEnv;
     fn free_env(e: *mut UnsafeEnv);
     fn get_atomic_int32(e: *const UnsafeEnv, index: libc::size_t) -> i32;
     fn get_int32(e: *const UnsafeEnv, index: libc::size_t) -> i32;
     fn set_atomic_int32(e: *mut UnsafeEnv, index: libc::size_t, value: i32);
     fn set_int32(e: *mut UnsafeEnv, index: libc::size_t, value: i32);
+    fn get_atomic_bool(e: *const UnsafeEnv, index: libc::size_t) -> bool;
+    fn get_bool(e: *const UnsafeEnv, index: libc::size_t) -> bool;
+    fn set_atomic_bool(e: *mut UnsafeEnv, index: libc::size_t, value: bool);
+    fn set_bool(e: *mut UnsafeEnv, index: libc::size_t, value: bool);
+    fn get_atomic_double(e: *const UnsafeEnv, index: libc::size_t) -> f64;
+    fn get_double(e: *const UnsafeEnv, index: libc::size_t) -> f64;
+    fn set_atomic_double(e: *mut UnsafeEnv, index: libc::size_t, value: f64);
+    fn set_double(e: *mut UnsafeEnv, index: libc::size_t, value: f64);
+    fn get_locals(
+        e: *mut UnsafeEnv,
+        bases: *const libc::size_t,
+        counts: *const libc::size_t,
+        tid: libc::size_t,
+        l: *mut Locals,
+    );
+    fn take_aux_flags(e: *mut UnsafeEnv) -> u64;
+}
+
+/// The C-ABI view of a thread's locals, as `struct locals` in `phenol.h`.
+///
+/// Each pointer is to the thread's first local in the corresponding slot
+/// array of the environment, or null if it has none there.
+#[repr(C)]
+pub(super) struct Locals {
+    arrays: [*mut libc::c_void; 6],
+}
+
+impl Env {
+    /// Gets thread `tid`'s locals, which sit in this environment as `layout`
+    /// says.
+    ///
+    /// The environment must have room for `tid`'s locals.
+    pub(super) fn locals(&self, layout: &LocalsLayout, tid: usize) -> Locals {
+        let mut l = Locals {
+            arrays: [ptr::null_mut(); 6],
+        };
+        unsafe {
+            get_locals(
+                self.p,
+                layout.bases.as_ptr(),
+                layout.counts.as_ptr(),
+                tid,
+                &raw mut l,
+            );
+        }
+        l
+    }
 }
 
 /// Thin layer over the C environment struct.
@@ -50,12 +105,55 @@ impl abs::Env for Env {
         }
     }
 
+    fn get_bool(&self, slot: slot::Slot) -> bool {
+        if slot.is_atomic {
+            unsafe { get_atomic_bool(self.p, slot.index) }
+        } else {
+            unsafe { get_bool(self.p, slot.index) }
+        }
+    }
+
+    fn set_bool(&mut self, slot: slot::Slot, v: bool) {
+        if slot.is_atomic {
+            unsafe { set_atomic_bool(self.p, slot.index, v) }
+        } else {
+            unsafe { set_bool(self.p, slot.index, v) }
+        }
+    }
+
+    fn get_f64(&self, slot: slot::Slot) -> f64 {
+        if slot.is_atomic {
+            unsafe { get_atomic_double(self.p, slot.index) }
+        } else {
+            unsafe { get_double(self.p, slot.index) }
+        }
+    }
+
+    fn set_f64(&mut self, slot: slot::Slot, v: f64) {
+        if slot.is_atomic {
+            unsafe { set_atomic_double(self.p, slot.index, v) }
+        } else {
+            unsafe { set_double(self.p, slot.index, v) }
+        }
+    }
+
+    fn take_aux_flags(&mut self) -> u64 {
+        unsafe { take_aux_flags(self.p) }
+    }
+
     fn of_reservations(reservations: slot::ReservationSet) -> err::Result<Self> {
-        let ReservationSet { i32s } = reservations;
+        let ReservationSet { i32s, bools, f64s } = reservations;
 
         let mut e = Env { p: ptr::null_mut() };
         unsafe {
-            e.p = alloc_env(i32s.atomic, i32s.non_atomic);
+            e.p = alloc_env(
+                i32s.atomic,
+                i32s.non_atomic,
+                bools.atomic,
+                bools.non_atomic,
+                f64s.atomic,
+                f64s.non_atomic,
+            );
         }
         if e.p.is_null() {
             Err(err::Error::EnvAllocFailed)
@@ -77,7 +175,11 @@ impl Drop for Env {
 
 #[cfg(test)]
 mod tests {
-    use crate::{api::abs::test_helpers, err};
+    use crate::{
+        api::abs::{test_helpers, Env},
+        err,
+        model::slot,
+    };
 
     #[test]
     /// Tests getting and setting a 32-bit atomic integer.
@@ -90,4 +192,51 @@ mod tests {
     fn test_get_set_i32() -> err::Result<()> {
         test_helpers::test_i32_get_set::<super::Env>(false)
     }
+
+    #[test]
+    /// Tests getting and setting an atomic Boolean.
+    fn test_get_set_atomic_bool() -> err::Result<()> {
+        test_helpers::test_bool_get_set::<super::Env>(true)
+    }
+
+    #[test]
+    /// Tests getting and setting a Boolean.
+    fn test_get_set_bool() -> err::Result<()> {
+        test_helpers::test_bool_get_set::<super::Env>(false)
+    }
+
+    #[test]
+    /// Tests getting and setting an atomic 64-bit float.
+    fn test_get_set_atomic_f64() -> err::Result<()> {
+        test_helpers::test_f64_get_set::<super::Env>(true)
+    }
+
+    #[test]
+    /// Tests getting and setting a 64-bit float.
+    fn test_get_set_f64() -> err::Result<()> {
+        test_helpers::test_f64_get_set::<super::Env>(false)
+    }
+
+    #[test]
+    /// Tests that each thread's locals point into the right part of the
+    /// environment.
+    fn test_locals() -> err::Result<()> {
+        let mut reservations = slot::ReservationSet::default();
+        reservations.i32s.non_atomic = 7;
+        let env = super::Env::of_reservations(reservations)?;
+        let layout = super::LocalsLayout {
+            bases: [0, 1, 0, 0, 0, 0],
+            counts: [0, 2, 0, 0, 0, 0],
+        };
+
+        let locals = env.locals(&layout, 2);
+        assert!(locals.arrays[0].is_null());
+        unsafe { *locals.arrays[1].cast::<i32>().add(1) = 42 };
+        let slot = slot::Slot {
+            is_atomic: false,
+            index: 6,
+        };
+        assert_eq!(env.get_i32(slot), 42);
+        Ok(())
+    }
 }
@@ -0,0 +1,474 @@
+//! Generation of `phenol.h`, the header C tests include.
+//!
+//! The header describes the same structures as [`super::manifest::Manifest`],
+//! [`super::manifest::ManifestV2`], [`super::entry::Iter`], and
+//! [`super::env::Locals`], and the environment that `env.c` allocates.
+//! We generate it from the tables below, which follow the Rust definitions
+//! field by field, rather than maintaining it by hand; the checked-in copy
+//! next to this file is tested against the generated one.
+
+use super::manifest::TYPE_TAGS;
+use std::fmt::Write;
+
+/// A variable type in the C ABI.
+///
+/// Types appear in the same order as the slot arrays of `struct env`; only
+/// the first has fields in `struct manifest`, whose layout is frozen.
+struct Type {
+    /// The name of the type in C.
+    c_name: &'static str,
+    /// The name used for the type in manifest field names.
+    name: &'static str,
+    /// The description used in comments on `struct env`.
+    desc: &'static str,
+    /// The names of the atomic and non-atomic slot arrays in `struct env`.
+    env_arrays: [&'static str; 2],
+}
+
+/// The variable types, in order.
+const TYPES: [Type; 3] = [
+    Type {
+        c_name: "int32_t",
+        name: "int32",
+        desc: "32-bit integers",
+        env_arrays: ["atomic_int32", "int32"],
+    },
+    Type {
+        c_name: "bool",
+        name: "bool",
+        desc: "Booleans",
+        env_arrays: ["atomic_bools", "bools"],
+    },
+    Type {
+        c_name: "double",
+        name: "double",
+        desc: "doubles",
+        env_arrays: ["atomic_doubles", "doubles"],
+    },
+];
+
+/// The fields of `struct iter`, in the order of the Rust `Iter` structure.
+const ITER_FIELDS: [(&str, &str); 2] = [
+    ("index", "Zero-based index of this iteration."),
+    (
+        "rotation",
+        "Zero-based index of the rotation containing it.",
+    ),
+];
+
+/// The members of `union var_value`, in the order of the Rust `Value` union.
+const VALUE_FIELDS: [(&str, &str, &str); 3] = [
+    ("int32_t ", "int32", "For PHENOL_INT."),
+    ("bool    ", "boolean", "For PHENOL_BOOL."),
+    ("double  ", "float64", "For PHENOL_FLOAT."),
+];
+
+/// The fields of `struct var`, in the order of the Rust `Var` structure.
+const VAR_FIELDS: [(&str, &str, &str); 5] = [
+    ("const char     *", "name", "Name of the variable."),
+    ("uint32_t        ", "type", "Type tag of the variable."),
+    (
+        "uint32_t        ",
+        "width",
+        "Width of the variable, in bytes.",
+    ),
+    (
+        "bool            ",
+        "is_atomic",
+        "Whether the variable is atomic.",
+    ),
+    (
+        "union var_value ",
+        "initial",
+        "Initial value of the variable.",
+    ),
+];
+
+/// The fields of `struct manifest_v2`, in the order of the Rust `ManifestV2`
+/// structure.
+const MANIFEST_V2_FIELDS: [(&str, &str, &str); 3] = [
+    (
+        "size_t            ",
+        "n_threads",
+        "Number of threads in this test.",
+    ),
+    (
+        "size_t            ",
+        "n_vars",
+        "Number of variables in this test.",
+    ),
+    (
+        "const struct var *",
+        "vars",
+        "Descriptor for each variable.",
+    ),
+];
+
+/// The fields of `struct invariant`, in the order of the Rust `Invariant`
+/// structure.
+const INVARIANT_FIELDS: [(&str, &str, &str); 3] = [
+    ("const char *", "name", "Name of the variable."),
+    ("double      ", "min", "Smallest permitted value."),
+    ("double      ", "max", "Largest permitted value."),
+];
+
+const PREAMBLE: &str = "\
+#ifndef PHENOL_H
+#define PHENOL_H
+
+/* This file contains struct definitions for the phenolpthalein C ABI.
+
+   This header should be included by C tests using phenolpthalein; it makes
+   various details about the shared state environment (`struct env`) and test
+   manifest (`struct manifest`) transparent.
+
+   phenolphthalein generates this header: run `phph header` to get the copy
+   matching your version of phenolphthalein. */
+
+#include <stdatomic.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/* Marks a symbol for export from the test library.
+
+   Windows DLLs only export symbols marked `__declspec(dllexport)`, so tests
+   meant to build there should mark `manifest`, the test body, and any
+   optional symbols with `PHENOL_EXPORT`; elsewhere, it does nothing. */
+#if defined(_WIN32)
+#define PHENOL_EXPORT __declspec(dllexport)
+#else
+#define PHENOL_EXPORT
+#endif
+
+/* Private area for parts of the environment the test shouldn't modify. */
+struct env_priv;
+
+/* The environment structure.
+
+   The environment contains dynamically allocated arrays that line up with the
+   slots defined in `struct manifest`.  Tests should read from and write to
+   the appropriate indices in those arrays wherever they would normally read
+   from and write to the corresponding variables. */
+struct env {
+";
+
+const ITER_DOC: &str = "\
+/* The position of the current iteration within a test run.
+
+   Tests that want to vary their behaviour by iteration can export
+   `void test_iter(size_t tid, const struct iter *iter, struct env *e)`
+   instead of `void test(size_t tid, struct env *e)`; if a test exports both,
+   phenolphthalein calls `test_iter`. */
+struct iter {
+";
+
+const MANIFEST_DOC: &str = "\
+/* The manifest structure.
+
+   Tests must expose a `struct manifest` as a symbol with the name `manifest`,
+   or a `struct manifest_v2` (see below).  So that tests built against older
+   copies of this header still load, this structure never changes: it only
+   describes `int32_t` variables, and tests with variables of other types
+   must use `struct manifest_v2`.
+
+   Tests that can run with more threads than `n_threads` can also expose
+   `const size_t scalable_role`, naming the thread ID of a role that extra
+   threads replicate when run with `--threads N`.  Each extra thread gets its
+   own tid (from `n_threads` up to `N - 1`), and its own copy of each of the
+   role's registers (variables named `R:name`, where `R` is the role's tid).
+   The copies come after all existing variables of the same type and
+   atomicity: for extra thread `T`, the role's `i`th such register (in slot
+   order) is at index `count + (T - n_threads) * nregs + i`, where `count` is
+   the number of existing variables and `nregs` the number of registers.
+
+   Tests whose threads run the same body can expose
+   `const size_t symmetry[]`, with one entry for each of the `n_threads`
+   threads, giving their symmetry classes: threads with the same class are
+   interchangeable.  With `--fold-symmetric`, phenolphthalein then counts
+   states that differ only by swapping those threads' registers as the same
+   state.  Extra threads are always symmetric with the scalable role. */
+struct manifest {
+    size_t n_threads;  /* Number of threads in this test. */
+
+    /* For each type, the number of variables of that type followed by arrays
+       with size set to the respective number: */
+";
+
+const VALUE_DOC: &str = "\
+/* The initial value of a variable in `struct var`; the member in use is the
+   one for the variable's type tag. */
+union var_value {
+";
+
+const VAR_DOC: &str = "\
+/* A variable in `struct manifest_v2`.
+
+   `type` is one of the `PHENOL_*` type tags above, and `width` the size of
+   the variable in bytes.  At the moment, phenolphthalein supports only
+   `PHENOL_INT` with width 4 (`int32_t`), `PHENOL_BOOL` with width
+   `sizeof(bool)`, and `PHENOL_FLOAT` with width 8 (`double`), and refuses to
+   load tests declaring any other combination. */
+struct var {
+";
+
+const MANIFEST_V2_DOC: &str = "\
+/* The version 2 manifest structure.
+
+   Tests can expose a `struct manifest_v2` with the name `manifest_v2` instead
+   of a `struct manifest`; if a test exposes both, phenolphthalein reads
+   `manifest_v2`.  Rather than separate arrays for each type and atomicity, it
+   describes every variable with one `struct var`.  Each variable takes the
+   next free slot of its type and atomicity in `struct env`, in the order the
+   variables appear: the third atomic `PHENOL_INT`, say, is at
+   `atomic_int32[2]`.  Everything said above of `scalable_role` and
+   `symmetry` holds here too. */
+struct manifest_v2 {
+";
+
+const LOCALS_DOC: &str = "\
+/* A thread's local variables.
+
+   Tests exposing `manifest_v2` can also expose an array
+   `const struct var thread_locals[]`, with its length as
+   `const size_t n_thread_locals`, to declare variables of which each thread
+   has its own copy, such as scratch registers.  Thread `T`'s copy of local
+   `name` is the variable `T:name`, which phenolphthalein observes, resets,
+   and reports like any other.  The copies come after all of the variables in
+   `vars` of the same type and atomicity, thread by thread: thread `T`'s
+   `i`th local of a given type and atomicity is at index
+   `count + T * nlocals + i`, where `count` is the number of variables of
+   that type and atomicity in `vars`, and `nlocals` the number of such
+   locals.
+   Tests with thread locals can't have a scalable role.
+
+   Tests can export
+   `void test_locals(size_t tid, const struct iter *iter, struct env *e,
+                     const struct locals *l)`
+   instead of `test` or `test_iter`; `l` then points to the thread's first
+   local in each slot array of `e`, or is null in arrays where the thread has
+   no locals.  If a test exports several bodies, phenolphthalein calls
+   `test_locals`, then `test_iter`, in preference to `test`. */
+struct locals {
+";
+
+const INVARIANT_DOC: &str = "\
+/* Bounds on the values of a variable.
+
+   Tests can expose an array `const struct invariant invariants[]`, with its
+   length as `const size_t n_invariants`, to bound the values their variables
+   take.  phenolphthalein checks every bound whenever it observes the test's
+   state, failing (and reporting) any state breaking one, whether or not the
+   test has a checker.  Bounds are inclusive, and all values compare as
+   doubles, with Booleans as 0 and 1; use `-INFINITY` or `INFINITY` from
+   `<math.h>` to leave an end open. */
+struct invariant {
+";
+
+const AUX_FLAGS_DOC: &str = "\
+/* Raises auxiliary flag `flag` for the current iteration.
+
+   Tests can expose an array `const char *aux_flags[]`, with its length as
+   `const size_t n_aux_flags` (at most 64), to name flags that their bodies
+   raise on events the final state doesn't show, such as taking a retry loop
+   or a lock's slow path.  phenolphthalein takes and clears the raised flags
+   whenever it observes the test's state, and reports, for each state, how
+   many of its iterations raised each flag.  Flag `i` is named by
+   `aux_flags[i]`; raising flags past the last does nothing. */
+static inline void
+phenol_raise(struct env *e, unsigned flag)
+{
+    if (flag < 64)
+        atomic_fetch_or_explicit(&e->aux_flags, (uint64_t)1 << flag,
+                                 memory_order_relaxed);
+}
+
+";
+
+/// Generates the text of `phenol.h`.
+///
+/// # Examples
+///
+/// ```
+/// let h = phenolphthalein::api::c::header();
+/// assert!(h.contains("struct manifest {"));
+/// assert!(h.contains("const char    **atomic_int32_names;"));
+/// assert!(h.contains("struct manifest_v2 {"));
+/// assert!(h.contains("#define PHENOL_EXPORT __declspec(dllexport)"));
+/// assert!(h.contains("phenol_raise(struct env *e, unsigned flag)"));
+/// ```
+#[must_use]
+pub fn header() -> String {
+    let mut h = PREAMBLE.to_owned();
+    write_env(&mut h);
+    h.push_str(ITER_DOC);
+    for (name, doc) in ITER_FIELDS {
+        push_field(&mut h, "size_t ", &format!("{name};"), 10, doc);
+    }
+    h.push_str("};\n\n");
+    h.push_str(MANIFEST_DOC);
+    write_manifest_fields(&mut h);
+    h.push_str("};\n\n");
+    h.push_str("/* Type tags for `struct var`. */\n");
+    for (name, tag, _) in TYPE_TAGS {
+        let _ = writeln!(h, "#define {name:<12} {tag}");
+    }
+    h.push('\n');
+    h.push_str(VALUE_DOC);
+    for (ty, name, doc) in VALUE_FIELDS {
+        push_field(&mut h, ty, &format!("{name};"), 9, doc);
+    }
+    h.push_str("};\n\n");
+    h.push_str(VAR_DOC);
+    for (ty, name, doc) in VAR_FIELDS {
+        push_field(&mut h, ty, &format!("{name};"), 11, doc);
+    }
+    h.push_str("};\n\n");
+    h.push_str(MANIFEST_V2_DOC);
+    for (ty, name, doc) in MANIFEST_V2_FIELDS {
+        push_field(&mut h, ty, &format!("{name};"), 11, doc);
+    }
+    h.push_str("};\n\n");
+    h.push_str(LOCALS_DOC);
+    write_locals(&mut h);
+    h.push_str("};\n\n");
+    h.push_str(INVARIANT_DOC);
+    for (ty, name, doc) in INVARIANT_FIELDS {
+        push_field(&mut h, ty, &format!("{name};"), 6, doc);
+    }
+    h.push_str("};\n\n");
+    h.push_str(AUX_FLAGS_DOC);
+    h.push_str("#endif /* PHENOL_H */\n");
+    h
+}
+
+fn write_env(h: &mut String) {
+    for t in &TYPES {
+        for (is_atomic, array) in [true, false].into_iter().zip(t.env_arrays) {
+            let qualifier = if is_atomic { "atomic " } else { "non-atomic " };
+            let _ = writeln!(h, "    /* {qualifier}{} */", t.desc);
+            let atomic_name = format!("_Atomic {}", t.c_name);
+            let ty = if is_atomic { &atomic_name } else { t.c_name };
+            let count = format!("n{}", array.trim_end_matches('s'));
+            let _ = writeln!(h, "    {:<16} {count};", "size_t");
+            let _ = writeln!(h, "    {ty:<16}*{array};");
+            h.push('\n');
+        }
+    }
+    h.push_str("    struct env_priv *priv;  /* Private area */\n");
+    h.push_str(
+        "    _Atomic uint64_t aux_flags;  /* Raised auxiliary flags; see `phenol_raise` */\n};\n\n",
+    );
+}
+
+/// Writes one pointer for each slot array of `struct env`, following the
+/// Rust `Locals`.
+fn write_locals(h: &mut String) {
+    for t in &TYPES {
+        for (is_atomic, array) in [true, false].into_iter().zip(t.env_arrays) {
+            let atomic_name = format!("_Atomic {}", t.c_name);
+            let ty = if is_atomic { &atomic_name } else { t.c_name };
+            let _ = writeln!(h, "    {ty:<16}*{array};");
+        }
+    }
+}
+
+/// Writes the six fields for the first type, following the Rust
+/// `VarArrays`.
+fn write_manifest_fields(h: &mut String) {
+    let t = &TYPES[0];
+    for prefix in ["atomic_", ""] {
+        let name = format!("{prefix}{}", t.name);
+        let desc = if prefix.is_empty() {
+            t.c_name.to_owned()
+        } else {
+            format!("atomic {}", t.c_name)
+        };
+        let fields = [
+            (
+                format!("{:<16}", "size_t"),
+                "n_",
+                "",
+                "Number of",
+                "s in this test",
+            ),
+            (
+                format!("const {:<9}*", t.c_name),
+                "",
+                "_initials",
+                "Initial value for each",
+                "",
+            ),
+            (
+                "const char    **".to_owned(),
+                "",
+                "_names",
+                "Name of each",
+                "",
+            ),
+        ];
+        h.push('\n');
+        for (ty, before, after, doc, plural) in fields {
+            let field = format!("{before}{name}{after};");
+            push_field(h, &ty, &field, 23, &format!("{doc} {desc}{plural}."));
+        }
+    }
+}
+
+/// Writes a field declaration with a trailing comment, padding the name to
+/// `width` columns.
+fn push_field(h: &mut String, ty: &str, name: &str, width: usize, doc: &str) {
+    let _ = writeln!(h, "    {ty}{name:<width$} /* {doc} */");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the Rust structures have the fields the tables describe.
+    #[test]
+    fn test_tables_match_rust_layout() {
+        let word = std::mem::size_of::<usize>();
+        // n_threads, then six fields for 32-bit integers.
+        assert_eq!(
+            std::mem::size_of::<super::super::manifest::Manifest>(),
+            (1 + 6) * word
+        );
+        assert_eq!(
+            std::mem::size_of::<super::super::entry::Iter>(),
+            ITER_FIELDS.len() * word
+        );
+        // Two words and a pointer.
+        assert_eq!(
+            std::mem::size_of::<super::super::manifest::ManifestV2>(),
+            MANIFEST_V2_FIELDS.len() * word
+        );
+        // Each member of the value fits in a double.
+        assert_eq!(
+            std::mem::size_of::<super::super::manifest::Value>(),
+            std::mem::size_of::<f64>()
+        );
+        assert_eq!(VALUE_FIELDS.len(), TYPE_TAGS.len());
+        // A pointer, two 32-bit words, and a Boolean padded up to the value.
+        assert_eq!(
+            std::mem::size_of::<super::super::manifest::Var>(),
+            word + 2 * 4 + 8 + std::mem::size_of::<f64>()
+        );
+        // One pointer for each slot array.
+        assert_eq!(
+            std::mem::size_of::<super::super::env::Locals>(),
+            2 * TYPES.len() * word
+        );
+        // A pointer and two doubles.
+        assert_eq!(
+            std::mem::size_of::<super::super::manifest::Invariant>(),
+            word + 2 * std::mem::size_of::<f64>()
+        );
+    }
+
+    /// Tests that the checked-in header matches the generated one.
+    #[test]
+    fn test_checked_in_header_is_current() {
+        assert_eq!(include_str!("phenol.h"), header());
+    }
+}
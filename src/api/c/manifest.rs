@@ -1,72 +1,416 @@
 use crate::{
     err,
-    model::{manifest, slot},
+    model::{invariant, manifest, slot},
 };
-use std::{convert::TryFrom, ffi, num::NonZeroUsize};
+use std::{collections::HashSet, convert::TryFrom, ffi, num::NonZeroUsize};
 
 /// The raw manifest structure that the test implements to communicate auxiliary
 /// information to the test runner.
 ///
-/// This must line up with 'struct manifest' in phenol.h.
+/// This must line up with 'struct manifest' in phenol.h.  Tests built against
+/// any version of the header must still load, so its layout is frozen; it
+/// only holds 32-bit integers, and tests with variables of other types use
+/// [`ManifestV2`].
 #[repr(C)]
 #[derive(Clone)]
 pub(super) struct Manifest {
     /// Number of threads in this test.
     n_threads: libc::size_t,
-    /// Number of atomic 32-bit integers in this test.
-    n_atomic_i32: libc::size_t,
-    /// Initial value for each atomic_int.
-    atomic_i32_initials: *const i32,
-    /// Name of each atomic_int.
-    atomic_i32_names: *const *const libc::c_char,
-    /// Number of 32-bit integers in this test.
-    n_i32: libc::size_t,
-    /// Initial value for each int.
-    i32_initials: *const i32,
-    /// Name of each int.
-    i32_names: *const *const libc::c_char,
+    /// The 32-bit integers in this test.
+    i32s: VarArrays<i32>,
 }
 
-impl Manifest {
-    fn atomic_i32_name_vec(&self) -> Vec<String> {
-        unsafe { names(self.atomic_i32_names, self.n_atomic_i32) }
-    }
-
-    fn atomic_i32_initial_vec(&self) -> Vec<i32> {
-        unsafe { initials(self.atomic_i32_initials, self.n_atomic_i32) }
-    }
-
-    fn i32_name_vec(&self) -> Vec<String> {
-        unsafe { names(self.i32_names, self.n_i32) }
-    }
-
-    fn i32_initial_vec(&self) -> Vec<i32> {
-        unsafe { initials(self.i32_initials, self.n_i32) }
-    }
+/// The raw pairs of variable arrays for a particular type.
+///
+/// This must line up with the six fields after `n_threads` in
+/// 'struct manifest' in phenol.h.
+#[repr(C)]
+#[derive(Clone)]
+struct VarArrays<T> {
+    /// Number of atomic variables of this type.
+    n_atomic: libc::size_t,
+    /// Initial value for each atomic variable.
+    atomic_initials: *const T,
+    /// Name of each atomic variable.
+    atomic_names: *const *const libc::c_char,
+    /// Number of non-atomic variables of this type.
+    n_non_atomic: libc::size_t,
+    /// Initial value for each non-atomic variable.
+    non_atomic_initials: *const T,
+    /// Name of each non-atomic variable.
+    non_atomic_names: *const *const libc::c_char,
+}
 
-    fn i32_map(&self) -> manifest::VarMap<i32> {
-        let mut map = lift_to_var_map(self.i32_name_vec(), self.i32_initial_vec(), false);
+impl<T: Copy> VarArrays<T> {
+    /// Converts these arrays into a variable map.
+    ///
+    /// Unsafe because we can't check that the arrays are as long as the
+    /// counts say they are.
+    unsafe fn to_var_map(&self) -> manifest::VarMap<T> {
+        let mut map = lift_to_var_map(
+            names(self.non_atomic_names, self.n_non_atomic),
+            initials(self.non_atomic_initials, self.n_non_atomic),
+            false,
+        );
         map.extend(lift_to_var_map(
-            self.atomic_i32_name_vec(),
-            self.atomic_i32_initial_vec(),
+            names(self.atomic_names, self.n_atomic),
+            initials(self.atomic_initials, self.n_atomic),
             true,
         ));
         map
     }
+}
 
+impl Manifest {
     /// Tries to convert this C manifest to the standard structure.
+    ///
+    /// The manifest has no one order of declaration, so the variables count
+    /// as declared in the order of its arrays.
     pub(super) fn to_manifest(&self) -> err::Result<manifest::Manifest> {
         let n_threads =
             NonZeroUsize::try_from(self.n_threads).map_err(|_| err::Error::NotEnoughThreads)?;
+        let i32s = unsafe { self.i32s.to_var_map() };
+        let declared = array_order(&i32s).collect();
         Ok(manifest::Manifest {
             n_threads,
-            i32s: self.i32_map(),
+            i32s,
+            bools: manifest::VarMap::new(),
+            f64s: manifest::VarMap::new(),
+            scalable_role: None,
+            symmetry: vec![],
+            invariants: invariant::Map::new(),
+            aux_flags: vec![],
+            declared,
         })
     }
 }
 
+/// The raw version 2 manifest structure, which describes each variable with
+/// a single [Var] rather than spreading them over arrays by type and
+/// atomicity.
+///
+/// This must line up with `struct manifest_v2` in `phenol.h`.
+#[repr(C)]
+#[derive(Clone)]
+pub(super) struct ManifestV2 {
+    /// Number of threads in this test.
+    n_threads: libc::size_t,
+    /// Number of variables in this test.
+    n_vars: libc::size_t,
+    /// Descriptor for each variable.
+    vars: *const Var,
+}
+
+/// The raw descriptor of a variable in a version 2 manifest.
+///
+/// This must line up with 'struct var' in phenol.h.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(super) struct Var {
+    /// Name of the variable.
+    name: *const libc::c_char,
+    /// Type tag of the variable; one of [`TYPE_TAGS`].
+    type_tag: u32,
+    /// Width of the variable, in bytes.
+    width: u32,
+    /// Whether the variable is atomic.
+    is_atomic: bool,
+    /// Initial value of the variable, in the member given by `type_tag`.
+    initial: Value,
+}
+
+/// The raw initial value of a variable in a version 2 manifest.
+///
+/// This must line up with `union var_value` in `phenol.h`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(super) union Value {
+    int32: i32,
+    boolean: bool,
+    float64: f64,
+}
+
+/// Type tag for integers.
+const TYPE_INT: u32 = 0;
+/// Type tag for Booleans.
+const TYPE_BOOL: u32 = 1;
+/// Type tag for floating-point numbers.
+const TYPE_FLOAT: u32 = 2;
+
+/// The type tags, with their names in phenol.h and the widths that
+/// phenolphthalein supports for them.
+pub(super) const TYPE_TAGS: [(&str, u32, usize); 3] = [
+    ("PHENOL_INT", TYPE_INT, std::mem::size_of::<i32>()),
+    ("PHENOL_BOOL", TYPE_BOOL, std::mem::size_of::<bool>()),
+    ("PHENOL_FLOAT", TYPE_FLOAT, std::mem::size_of::<f64>()),
+];
+
+impl ManifestV2 {
+    /// Tries to convert this C manifest to the standard structure.
+    ///
+    /// Each variable takes the next free slot of its type and atomicity, in
+    /// the order the variables appear, which is also their declaration
+    /// order.
+    pub(super) fn to_manifest(&self) -> err::Result<manifest::Manifest> {
+        let n_threads =
+            NonZeroUsize::try_from(self.n_threads).map_err(|_| err::Error::NotEnoughThreads)?;
+        let mut m = manifest::Manifest {
+            n_threads,
+            i32s: manifest::VarMap::new(),
+            bools: manifest::VarMap::new(),
+            f64s: manifest::VarMap::new(),
+            scalable_role: None,
+            symmetry: vec![],
+            invariants: invariant::Map::new(),
+            aux_flags: vec![],
+            declared: vec![],
+        };
+        let vars = unsafe { vars(self.vars, self.n_vars) };
+        let mut seen = HashSet::new();
+        for var in vars {
+            let name = var.checked_name(&mut seen)?;
+            let name = var.push_to(&mut m, name);
+            m.declared.push(name);
+        }
+        Ok(m)
+    }
+
+    /// Works out where each thread's copies of the thread locals `locals`
+    /// go in the environment.
+    ///
+    /// This only counts variables, and so skips any that [`Self::to_manifest`]
+    /// or [`add_thread_locals`] would reject.
+    pub(super) fn locals_layout(&self, locals: &[Var]) -> LocalsLayout {
+        let mut layout = LocalsLayout::default();
+        for var in unsafe { vars(self.vars, self.n_vars) } {
+            if let Some(k) = var.kind() {
+                layout.bases[k] += 1;
+            }
+        }
+        for var in locals {
+            if let Some(k) = var.kind() {
+                layout.counts[k] += 1;
+            }
+        }
+        layout
+    }
+}
+
+impl Var {
+    /// Gets the name of this variable, checking that it has one, that
+    /// phenolphthalein supports its type and width, and that it isn't in
+    /// `seen` (to which we add it).
+    fn checked_name(&self, seen: &mut HashSet<String>) -> err::Result<String> {
+        if self.name.is_null() {
+            return Err(err::Error::BadManifestVar {
+                var: String::new(),
+                reason: "it has no name".to_owned(),
+            });
+        }
+        let name = unsafe { ffi::CStr::from_ptr(self.name) }
+            .to_string_lossy()
+            .into_owned();
+        let bad = |reason: String| err::Error::BadManifestVar {
+            var: name.clone(),
+            reason,
+        };
+        if !seen.insert(name.clone()) {
+            return Err(bad("it is declared twice".to_owned()));
+        }
+        let Some((tag, _, width)) = TYPE_TAGS.iter().find(|(_, t, _)| *t == self.type_tag) else {
+            return Err(bad(format!("unknown type tag {}", self.type_tag)));
+        };
+        if usize::try_from(self.width).ok() != Some(*width) {
+            return Err(bad(format!(
+                "{tag} variables must be {width} bytes wide, not {}",
+                self.width
+            )));
+        }
+        Ok(name)
+    }
+
+    /// Adds this variable to `m` as `name`, in the next free slot of its type
+    /// and atomicity, returning its name.
+    ///
+    /// The variable must already have passed [`Self::checked_name`].
+    fn push_to(&self, m: &mut manifest::Manifest, name: String) -> manifest::VarName {
+        // The tag says which member of the initial value is live.
+        match self.type_tag {
+            TYPE_INT => push_var(
+                &mut m.i32s,
+                name,
+                unsafe { self.initial.int32 },
+                self.is_atomic,
+            ),
+            TYPE_BOOL => push_var(
+                &mut m.bools,
+                name,
+                unsafe { self.initial.boolean },
+                self.is_atomic,
+            ),
+            _ => push_var(
+                &mut m.f64s,
+                name,
+                unsafe { self.initial.float64 },
+                self.is_atomic,
+            ),
+        }
+    }
+
+    /// Gets the index, in [`LocalsLayout`], of the slot array of `struct env`
+    /// holding this variable, if it has a known type.
+    fn kind(&self) -> Option<usize> {
+        TYPE_TAGS
+            .iter()
+            .position(|(_, t, _)| *t == self.type_tag)
+            .map(|i| 2 * i + usize::from(!self.is_atomic))
+    }
+}
+
+/// Where each thread's copies of a test's thread locals sit in the
+/// environment.
+///
+/// Both arrays follow the order of the slot arrays in `struct env` (atomic
+/// then non-atomic, for each type in turn).  For each slot array, thread
+/// `T`'s locals start at index `bases[k] + T * counts[k]`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(super) struct LocalsLayout {
+    /// The number of non-local variables in each slot array.
+    pub(super) bases: [libc::size_t; 6],
+    /// The number of locals that each thread has in each slot array.
+    pub(super) counts: [libc::size_t; 6],
+}
+
+/// Adds each thread's copies of the thread locals `locals` to `m`.
+///
+/// Thread `T`'s copy of the local `name` is the variable `T:name`.  The
+/// copies go thread by thread, and in order within each thread, so that they
+/// take slots as [`ManifestV2::locals_layout`] says, and are declared in the
+/// same order.
+///
+/// # Errors
+///
+/// Fails if any local is malformed, or any copy clashes with another
+/// variable.
+pub(super) fn add_thread_locals(m: &mut manifest::Manifest, locals: &[Var]) -> err::Result<()> {
+    let mut seen = HashSet::new();
+    let names = locals
+        .iter()
+        .map(|var| var.checked_name(&mut seen))
+        .collect::<err::Result<Vec<_>>>()?;
+    for tid in 0..m.n_threads.get() {
+        for (var, name) in locals.iter().zip(&names) {
+            let copy = format!("{tid}:{name}");
+            if m.has_var(&copy) {
+                return Err(err::Error::BadManifestVar {
+                    var: name.clone(),
+                    reason: format!("thread {tid}'s copy clashes with the variable {copy}"),
+                });
+            }
+            let copy = var.push_to(m, copy);
+            m.declared.push(copy);
+        }
+    }
+    Ok(())
+}
+
+/// Gets the `n` raw variable descriptors starting at `src`.
+///
+/// Unsafe because in general we don't know how src and n relate.
+pub(super) unsafe fn vars<'a>(src: *const Var, n: libc::size_t) -> &'a [Var] {
+    if n == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(src, n)
+    }
+}
+
+/// Adds a variable to `map`, in the next free slot of its atomicity,
+/// returning its name.
+fn push_var<T>(
+    map: &mut manifest::VarMap<T>,
+    name: String,
+    initial: T,
+    is_atomic: bool,
+) -> manifest::VarName {
+    let index = map
+        .values()
+        .filter(|r| r.slot.is_atomic == is_atomic)
+        .count();
+    let name = manifest::VarName::from(name);
+    map.insert(
+        name.clone(),
+        manifest::VarRecord {
+            initial_value: Some(initial),
+            slot: slot::Slot { is_atomic, index },
+        },
+    );
+    name
+}
+
+/// Gets the names of the variables in `map` in the order of a version 1
+/// manifest's arrays: atomic variables first, then non-atomic ones, each in
+/// slot order.
+fn array_order<T>(map: &manifest::VarMap<T>) -> impl Iterator<Item = manifest::VarName> {
+    let mut names: Vec<_> = map
+        .iter()
+        .map(|(name, r)| ((!r.slot.is_atomic, r.slot.index), name.clone()))
+        .collect();
+    names.sort();
+    names.into_iter().map(|(_, name)| name)
+}
+
+/// The raw bounds on a variable.
+///
+/// This must line up with 'struct invariant' in phenol.h.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(super) struct Invariant {
+    /// Name of the variable.
+    name: *const libc::c_char,
+    /// Smallest permitted value.
+    min: f64,
+    /// Largest permitted value.
+    max: f64,
+}
+
+/// Converts the `n` raw invariants starting at `src` to a map of bounds.
+///
+/// Unsafe because in general we don't know how src and n relate.
+///
+/// # Errors
+///
+/// Fails if an invariant has no variable name.
+pub(super) unsafe fn invariants(
+    src: *const Invariant,
+    n: libc::size_t,
+) -> err::Result<invariant::Map> {
+    if n == 0 {
+        return Ok(invariant::Map::new());
+    }
+    std::slice::from_raw_parts(src, n)
+        .iter()
+        .map(|i| {
+            if i.name.is_null() {
+                return Err(err::Error::BadManifestVar {
+                    var: String::new(),
+                    reason: "it has an invariant with no name".to_owned(),
+                });
+            }
+            let name = ffi::CStr::from_ptr(i.name).to_string_lossy();
+            Ok((
+                name.as_ref().into(),
+                invariant::Bounds {
+                    min: i.min,
+                    max: i.max,
+                },
+            ))
+        })
+        .collect()
+}
+
 /// Unsafe because in general we don't know how src and n relate.
-unsafe fn names(src: *const *const libc::c_char, n: libc::size_t) -> Vec<String> {
+pub(super) unsafe fn names(src: *const *const libc::c_char, n: libc::size_t) -> Vec<String> {
     if n == 0 {
         vec![]
     } else {
@@ -77,7 +421,8 @@ unsafe fn names(src: *const *const libc::c_char, n: libc::size_t) -> Vec<String>
     }
 }
 
-unsafe fn initials(src: *const libc::c_int, n: libc::size_t) -> Vec<i32> {
+/// Unsafe because in general we don't know how src and n relate.
+unsafe fn initials<T: Copy>(src: *const T, n: libc::size_t) -> Vec<T> {
     if n == 0 {
         vec![]
     } else {
@@ -93,5 +438,203 @@ fn lift_to_var_map<T>(names: Vec<String>, inits: Vec<T>, is_atomic: bool) -> man
             initial_value: Some(x),
             slot: slot::Slot { is_atomic, index },
         });
-    names.into_iter().zip(records).collect()
+    names.into_iter().map(Into::into).zip(records).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &ffi::CStr, type_tag: u32, width: usize, is_atomic: bool, initial: Value) -> Var {
+        Var {
+            name: name.as_ptr(),
+            type_tag,
+            width: u32::try_from(width).unwrap(),
+            is_atomic,
+            initial,
+        }
+    }
+
+    fn manifest_of(vars: &[Var]) -> ManifestV2 {
+        ManifestV2 {
+            n_threads: 2,
+            n_vars: vars.len(),
+            vars: vars.as_ptr(),
+        }
+    }
+
+    /// 'struct manifest' as the first version of phenol.h laid it out.
+    #[repr(C)]
+    struct BaselineManifest {
+        n_threads: libc::size_t,
+        n_atomic_int32: libc::size_t,
+        atomic_int32_initials: *const i32,
+        atomic_int32_names: *const *const libc::c_char,
+        n_int32: libc::size_t,
+        int32_initials: *const i32,
+        int32_names: *const *const libc::c_char,
+    }
+
+    /// Tests that version 1 manifests read only the fields that the first
+    /// version of phenol.h had, so that tests built against it still load.
+    #[test]
+    fn test_v1_baseline_layout() {
+        assert_eq!(
+            std::mem::size_of::<Manifest>(),
+            std::mem::size_of::<BaselineManifest>()
+        );
+        let atomic_initials = [1, 2];
+        let atomic_names = [c"x".as_ptr(), c"y".as_ptr()];
+        let initials = [3];
+        let names = [c"0:r0".as_ptr()];
+        // Boxing puts the manifest alone in its allocation, so that tools
+        // such as Miri catch any read past its end.
+        let baseline = Box::new(BaselineManifest {
+            n_threads: 2,
+            n_atomic_int32: atomic_initials.len(),
+            atomic_int32_initials: atomic_initials.as_ptr(),
+            atomic_int32_names: atomic_names.as_ptr(),
+            n_int32: initials.len(),
+            int32_initials: initials.as_ptr(),
+            int32_names: names.as_ptr(),
+        });
+        let raw = unsafe { &*(&raw const *baseline).cast::<Manifest>() };
+        let m = raw.to_manifest().unwrap();
+
+        let slot = |s: slot::Slot| (s.is_atomic, s.index);
+        assert_eq!(m.n_threads.get(), 2);
+        assert_eq!(slot(m.i32s["x"].slot), (true, 0));
+        assert_eq!(m.i32s["y"].initial_value, Some(2));
+        assert_eq!(slot(m.i32s["0:r0"].slot), (false, 0));
+        assert_eq!(m.i32s["0:r0"].initial_value, Some(3));
+        assert!(m.bools.is_empty() && m.f64s.is_empty());
+        assert_eq!(m.declared, ["x", "y", "0:r0"]);
+    }
+
+    /// Tests that variables take slots of their type and atomicity in
+    /// order, and keep their order of declaration.
+    #[test]
+    fn test_v2_to_manifest() {
+        let vars = [
+            var(c"x", TYPE_INT, 4, true, Value { int32: 1 }),
+            var(c"0:r0", TYPE_INT, 4, false, Value { int32: 2 }),
+            var(c"y", TYPE_INT, 4, true, Value { int32: 3 }),
+            var(
+                c"f",
+                TYPE_BOOL,
+                std::mem::size_of::<bool>(),
+                false,
+                Value { boolean: true },
+            ),
+            var(c"d", TYPE_FLOAT, 8, true, Value { float64: 0.5 }),
+        ];
+        let m = manifest_of(&vars).to_manifest().unwrap();
+        assert_eq!(m.n_threads.get(), 2);
+        let slot = |s: slot::Slot| (s.is_atomic, s.index);
+        assert_eq!(slot(m.i32s["x"].slot), (true, 0));
+        assert_eq!(slot(m.i32s["y"].slot), (true, 1));
+        assert_eq!(m.i32s["y"].initial_value, Some(3));
+        assert_eq!(slot(m.i32s["0:r0"].slot), (false, 0));
+        assert_eq!(m.bools["f"].initial_value, Some(true));
+        assert_eq!(slot(m.f64s["d"].slot), (true, 0));
+        assert_eq!(m.f64s["d"].initial_value, Some(0.5));
+        assert_eq!(m.declared, ["x", "0:r0", "y", "f", "d"]);
+    }
+
+    /// Tests that each thread gets its own copies of thread locals, after
+    /// the other variables of their type and atomicity, and that the layout
+    /// agrees with the slots they take.
+    #[test]
+    fn test_add_thread_locals() {
+        let vars = [
+            var(c"x", TYPE_INT, 4, true, Value { int32: 0 }),
+            var(c"0:r0", TYPE_INT, 4, false, Value { int32: 0 }),
+        ];
+        let locals = [
+            var(c"a", TYPE_INT, 4, false, Value { int32: 5 }),
+            var(c"b", TYPE_INT, 4, false, Value { int32: 6 }),
+            var(
+                c"c",
+                TYPE_BOOL,
+                std::mem::size_of::<bool>(),
+                true,
+                Value { boolean: true },
+            ),
+        ];
+        let v2 = manifest_of(&vars);
+        let mut m = v2.to_manifest().unwrap();
+        add_thread_locals(&mut m, &locals).unwrap();
+
+        let slot = |s: slot::Slot| (s.is_atomic, s.index);
+        assert_eq!(slot(m.i32s["0:a"].slot), (false, 1));
+        assert_eq!(slot(m.i32s["0:b"].slot), (false, 2));
+        assert_eq!(slot(m.i32s["1:a"].slot), (false, 3));
+        assert_eq!(m.i32s["1:b"].initial_value, Some(6));
+        assert_eq!(slot(m.bools["1:c"].slot), (true, 1));
+        assert_eq!(
+            m.declared,
+            ["x", "0:r0", "0:a", "0:b", "0:c", "1:a", "1:b", "1:c"]
+        );
+
+        let layout = v2.locals_layout(&locals);
+        assert_eq!(layout.bases, [1, 1, 0, 0, 0, 0]);
+        assert_eq!(layout.counts, [0, 2, 1, 0, 0, 0]);
+    }
+
+    /// Tests that thread locals whose copies clash with other variables are
+    /// rejected.
+    #[test]
+    fn test_add_thread_locals_clash() {
+        let vars = [var(c"1:r0", TYPE_INT, 4, false, Value { int32: 0 })];
+        let locals = [var(c"r0", TYPE_INT, 4, false, Value { int32: 0 })];
+        let mut m = manifest_of(&vars).to_manifest().unwrap();
+        assert!(matches!(
+            add_thread_locals(&mut m, &locals),
+            Err(err::Error::BadManifestVar { var, .. }) if var == "r0"
+        ));
+    }
+
+    /// Tests that unsupported or duplicate variables are rejected.
+    #[test]
+    fn test_v2_to_manifest_bad_vars() {
+        let bad = |vars: &[Var]| match manifest_of(vars).to_manifest() {
+            Err(err::Error::BadManifestVar { var, .. }) => var,
+            _ => panic!("expected a bad variable"),
+        };
+        let int = |name| var(name, TYPE_INT, 4, true, Value { int32: 0 });
+        assert_eq!(
+            bad(&[var(c"x", TYPE_INT, 8, true, Value { int32: 0 })]),
+            "x"
+        );
+        assert_eq!(bad(&[var(c"x", 7, 4, true, Value { int32: 0 })]), "x");
+        assert_eq!(bad(&[int(c"x"), int(c"y"), int(c"x")]), "x");
+        assert!(matches!(
+            ManifestV2 {
+                n_threads: 0,
+                n_vars: 0,
+                vars: std::ptr::null(),
+            }
+            .to_manifest(),
+            Err(err::Error::NotEnoughThreads)
+        ));
+    }
+
+    /// Tests that invariants load by name, and that nameless ones are
+    /// rejected rather than dereferenced.
+    #[test]
+    fn test_invariants() {
+        let invariant = |name: *const libc::c_char| Invariant {
+            name,
+            min: 0.0,
+            max: 1.0,
+        };
+        let good = [invariant(c"x".as_ptr())];
+        let loaded = unsafe { invariants(good.as_ptr(), good.len()) }.unwrap();
+        assert_eq!(loaded.len(), 1);
+        let bad = [invariant(c"x".as_ptr()), invariant(std::ptr::null())];
+        assert!(matches!(
+            unsafe { invariants(bad.as_ptr(), bad.len()) },
+            Err(err::Error::BadManifestVar { var, .. }) if var.is_empty()
+        ));
+    }
 }
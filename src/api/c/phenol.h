@@ -5,11 +5,27 @@
 
    This header should be included by C tests using phenolpthalein; it makes
    various details about the shared state environment (`struct env`) and test
-   manifest (`struct manifest`) transparent. */
+   manifest (`struct manifest`) transparent.
+
+   phenolphthalein generates this header: run `phph header` to get the copy
+   matching your version of phenolphthalein. */
 
 #include <stdatomic.h>
+#include <stdbool.h>
+#include <stddef.h>
 #include <stdint.h>
 
+/* Marks a symbol for export from the test library.
+
+   Windows DLLs only export symbols marked `__declspec(dllexport)`, so tests
+   meant to build there should mark `manifest`, the test body, and any
+   optional symbols with `PHENOL_EXPORT`; elsewhere, it does nothing. */
+#if defined(_WIN32)
+#define PHENOL_EXPORT __declspec(dllexport)
+#else
+#define PHENOL_EXPORT
+#endif
+
 /* Private area for parts of the environment the test shouldn't modify. */
 struct env_priv;
 
@@ -20,21 +36,69 @@ struct env_priv;
    the appropriate indices in those arrays wherever they would normally read
    from and write to the corresponding variables. */
 struct env {
-    /* 32-bit atomic integers */
+    /* atomic 32-bit integers */
     size_t           natomic_int32;
     _Atomic int32_t *atomic_int32;
 
-    /* 32-bit non-atomic integers */
+    /* non-atomic 32-bit integers */
     size_t           nint32;
     int32_t         *int32;
 
+    /* atomic Booleans */
+    size_t           natomic_bool;
+    _Atomic bool    *atomic_bools;
+
+    /* non-atomic Booleans */
+    size_t           nbool;
+    bool            *bools;
+
+    /* atomic doubles */
+    size_t           natomic_double;
+    _Atomic double  *atomic_doubles;
+
+    /* non-atomic doubles */
+    size_t           ndouble;
+    double          *doubles;
+
     struct env_priv *priv;  /* Private area */
+    _Atomic uint64_t aux_flags;  /* Raised auxiliary flags; see `phenol_raise` */
+};
+
+/* The position of the current iteration within a test run.
+
+   Tests that want to vary their behaviour by iteration can export
+   `void test_iter(size_t tid, const struct iter *iter, struct env *e)`
+   instead of `void test(size_t tid, struct env *e)`; if a test exports both,
+   phenolphthalein calls `test_iter`. */
+struct iter {
+    size_t index;     /* Zero-based index of this iteration. */
+    size_t rotation;  /* Zero-based index of the rotation containing it. */
 };
 
 /* The manifest structure.
 
-   Tests must expose a `struct manifest` as a symbol with the name `manifest`.
-*/
+   Tests must expose a `struct manifest` as a symbol with the name `manifest`,
+   or a `struct manifest_v2` (see below).  So that tests built against older
+   copies of this header still load, this structure never changes: it only
+   describes `int32_t` variables, and tests with variables of other types
+   must use `struct manifest_v2`.
+
+   Tests that can run with more threads than `n_threads` can also expose
+   `const size_t scalable_role`, naming the thread ID of a role that extra
+   threads replicate when run with `--threads N`.  Each extra thread gets its
+   own tid (from `n_threads` up to `N - 1`), and its own copy of each of the
+   role's registers (variables named `R:name`, where `R` is the role's tid).
+   The copies come after all existing variables of the same type and
+   atomicity: for extra thread `T`, the role's `i`th such register (in slot
+   order) is at index `count + (T - n_threads) * nregs + i`, where `count` is
+   the number of existing variables and `nregs` the number of registers.
+
+   Tests whose threads run the same body can expose
+   `const size_t symmetry[]`, with one entry for each of the `n_threads`
+   threads, giving their symmetry classes: threads with the same class are
+   interchangeable.  With `--fold-symmetric`, phenolphthalein then counts
+   states that differ only by swapping those threads' registers as the same
+   state.  Extra threads are always symmetric with the scalable role. */
 struct manifest {
     size_t n_threads;  /* Number of threads in this test. */
 
@@ -45,9 +109,116 @@ struct manifest {
     const int32_t  *atomic_int32_initials;  /* Initial value for each atomic int32_t. */
     const char    **atomic_int32_names;     /* Name of each atomic int32_t. */
 
-    size_t          n_int32;                /* Number of ints in this test. */
+    size_t          n_int32;                /* Number of int32_ts in this test. */
     const int32_t  *int32_initials;         /* Initial value for each int32_t. */
     const char    **int32_names;            /* Name of each int32_t. */
 };
 
+/* Type tags for `struct var`. */
+#define PHENOL_INT   0
+#define PHENOL_BOOL  1
+#define PHENOL_FLOAT 2
+
+/* The initial value of a variable in `struct var`; the member in use is the
+   one for the variable's type tag. */
+union var_value {
+    int32_t int32;    /* For PHENOL_INT. */
+    bool    boolean;  /* For PHENOL_BOOL. */
+    double  float64;  /* For PHENOL_FLOAT. */
+};
+
+/* A variable in `struct manifest_v2`.
+
+   `type` is one of the `PHENOL_*` type tags above, and `width` the size of
+   the variable in bytes.  At the moment, phenolphthalein supports only
+   `PHENOL_INT` with width 4 (`int32_t`), `PHENOL_BOOL` with width
+   `sizeof(bool)`, and `PHENOL_FLOAT` with width 8 (`double`), and refuses to
+   load tests declaring any other combination. */
+struct var {
+    const char     *name;       /* Name of the variable. */
+    uint32_t        type;       /* Type tag of the variable. */
+    uint32_t        width;      /* Width of the variable, in bytes. */
+    bool            is_atomic;  /* Whether the variable is atomic. */
+    union var_value initial;    /* Initial value of the variable. */
+};
+
+/* The version 2 manifest structure.
+
+   Tests can expose a `struct manifest_v2` with the name `manifest_v2` instead
+   of a `struct manifest`; if a test exposes both, phenolphthalein reads
+   `manifest_v2`.  Rather than separate arrays for each type and atomicity, it
+   describes every variable with one `struct var`.  Each variable takes the
+   next free slot of its type and atomicity in `struct env`, in the order the
+   variables appear: the third atomic `PHENOL_INT`, say, is at
+   `atomic_int32[2]`.  Everything said above of `scalable_role` and
+   `symmetry` holds here too. */
+struct manifest_v2 {
+    size_t            n_threads;  /* Number of threads in this test. */
+    size_t            n_vars;     /* Number of variables in this test. */
+    const struct var *vars;       /* Descriptor for each variable. */
+};
+
+/* A thread's local variables.
+
+   Tests exposing `manifest_v2` can also expose an array
+   `const struct var thread_locals[]`, with its length as
+   `const size_t n_thread_locals`, to declare variables of which each thread
+   has its own copy, such as scratch registers.  Thread `T`'s copy of local
+   `name` is the variable `T:name`, which phenolphthalein observes, resets,
+   and reports like any other.  The copies come after all of the variables in
+   `vars` of the same type and atomicity, thread by thread: thread `T`'s
+   `i`th local of a given type and atomicity is at index
+   `count + T * nlocals + i`, where `count` is the number of variables of
+   that type and atomicity in `vars`, and `nlocals` the number of such
+   locals.
+   Tests with thread locals can't have a scalable role.
+
+   Tests can export
+   `void test_locals(size_t tid, const struct iter *iter, struct env *e,
+                     const struct locals *l)`
+   instead of `test` or `test_iter`; `l` then points to the thread's first
+   local in each slot array of `e`, or is null in arrays where the thread has
+   no locals.  If a test exports several bodies, phenolphthalein calls
+   `test_locals`, then `test_iter`, in preference to `test`. */
+struct locals {
+    _Atomic int32_t *atomic_int32;
+    int32_t         *int32;
+    _Atomic bool    *atomic_bools;
+    bool            *bools;
+    _Atomic double  *atomic_doubles;
+    double          *doubles;
+};
+
+/* Bounds on the values of a variable.
+
+   Tests can expose an array `const struct invariant invariants[]`, with its
+   length as `const size_t n_invariants`, to bound the values their variables
+   take.  phenolphthalein checks every bound whenever it observes the test's
+   state, failing (and reporting) any state breaking one, whether or not the
+   test has a checker.  Bounds are inclusive, and all values compare as
+   doubles, with Booleans as 0 and 1; use `-INFINITY` or `INFINITY` from
+   `<math.h>` to leave an end open. */
+struct invariant {
+    const char *name;  /* Name of the variable. */
+    double      min;   /* Smallest permitted value. */
+    double      max;   /* Largest permitted value. */
+};
+
+/* Raises auxiliary flag `flag` for the current iteration.
+
+   Tests can expose an array `const char *aux_flags[]`, with its length as
+   `const size_t n_aux_flags` (at most 64), to name flags that their bodies
+   raise on events the final state doesn't show, such as taking a retry loop
+   or a lock's slow path.  phenolphthalein takes and clears the raised flags
+   whenever it observes the test's state, and reports, for each state, how
+   many of its iterations raised each flag.  Flag `i` is named by
+   `aux_flags[i]`; raising flags past the last does nothing. */
+static inline void
+phenol_raise(struct env *e, unsigned flag)
+{
+    if (flag < 64)
+        atomic_fetch_or_explicit(&e->aux_flags, (uint64_t)1 << flag,
+                                 memory_order_relaxed);
+}
+
 #endif /* PHENOL_H */
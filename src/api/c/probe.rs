@@ -0,0 +1,662 @@
+//! Diagnostic probing of test libraries.
+//!
+//! The dynamic loader's errors rarely say what is wrong with a library: a
+//! library built for the wrong architecture, one missing a dependency, and one
+//! that isn't a library at all can all fail with much the same message.  A
+//! [Probe] looks at the file itself (its object format, architecture, and
+//! exported symbols) so that failed loads can say why they failed.
+//!
+//! Probing only reads the file; it never loads it.  Symbols can only be read
+//! from ELF files with section headers.
+
+use crate::err;
+use std::{collections::BTreeSet, fmt::Display, io, path};
+
+/// Symbols of which every test library must export at least one, as its
+/// manifest.
+pub const MANIFESTS: &[&str] = &["manifest", "manifest_v2"];
+
+/// Symbols of which every test library must export at least one, as its
+/// test body.
+pub const BODIES: &[&str] = &["test", "test_iter", "test_locals"];
+
+/// Symbols that test libraries may export; see [`super::Entry`].
+pub const OPTIONAL: &[&str] = &[
+    "check",
+    "check_description",
+    "scalable_role",
+    "symmetry",
+    "invariants",
+    "n_invariants",
+    "thread_locals",
+    "n_thread_locals",
+    "aux_flags",
+    "n_aux_flags",
+];
+
+/// The number of bytes at the start of a file that hold all we need to know
+/// about its format.
+const HEADER_LEN: u64 = 64;
+
+/// Checks, before trying to load the file at `path`, that it isn't for
+/// another architecture.
+///
+/// This only reads the file's header.  Files that can't be read, or that
+/// have other problems, pass, and are left for the dynamic loader and
+/// [`Probe::diagnose`] to explain.
+///
+/// # Errors
+///
+/// Fails with [`err::Error::WrongTestLibraryArch`] if the file is an object
+/// for another architecture or platform.
+pub fn check_arch(path: &path::Path) -> err::Result<()> {
+    let Ok(format) = Format::of_file(path) else {
+        return Ok(());
+    };
+    match format.problem() {
+        Some(Problem::WrongArch) => Err(wrong_arch(path, &format)),
+        _ => Ok(()),
+    }
+}
+
+fn wrong_arch(path: &path::Path, format: &Format) -> err::Error {
+    err::Error::WrongTestLibraryArch {
+        path: path.to_owned(),
+        found: format.to_string(),
+        expected: std::env::consts::ARCH,
+    }
+}
+
+/// The results of probing a test library.
+#[derive(Debug)]
+pub struct Probe {
+    /// The file probed.
+    pub path: path::PathBuf,
+    /// What the file seems to be, or why it couldn't be read.
+    pub format: io::Result<Format>,
+    /// The test symbols (see [`MANIFESTS`], [`BODIES`], and [`OPTIONAL`])
+    /// that the file exports, if its symbols could be read.
+    pub symbols: Option<BTreeSet<&'static str>>,
+}
+
+/// The object format of a probed file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// An ELF object.
+    Elf {
+        /// The word size, in bits.
+        bits: u8,
+        /// Whether the object is big-endian.
+        big_endian: bool,
+        /// The kind of object.
+        kind: Kind,
+        /// The architecture for which the object was built.
+        arch: Arch,
+    },
+    /// A Mach-O object for a single architecture.
+    MachO {
+        /// The word size, in bits.
+        bits: u8,
+        /// The kind of object.
+        kind: Kind,
+        /// The architecture for which the object was built.
+        arch: Arch,
+    },
+    /// A universal Mach-O binary, holding objects for several
+    /// architectures.
+    FatMachO,
+    /// A Windows PE image.
+    Pe,
+    /// Something that isn't a recognised object file at all.
+    Unknown,
+}
+
+/// The kind of a probed object.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// A shared library (or position-independent executable), which can be
+    /// loaded.
+    Shared,
+    /// A relocatable object, which needs linking into a shared library.
+    Relocatable,
+    /// A fixed-position executable.
+    Executable,
+    /// Some other kind of object, such as a core dump.
+    Other,
+}
+
+/// The architecture for which a probed object was built.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Arch {
+    /// An architecture known by the name that Rust gives it (as in
+    /// [`std::env::consts::ARCH`]).
+    Known(&'static str),
+    /// An architecture known only by its machine code in the file.
+    Unknown(u32),
+}
+
+impl Probe {
+    /// Probes the file at `path`.
+    #[must_use]
+    pub fn of_file(path: &path::Path) -> Self {
+        let bytes = std::fs::read(path);
+        let format = bytes.as_deref().map(Format::of_bytes).map_err(clone_io);
+        let symbols = bytes.as_deref().ok().and_then(elf_symbols).map(|syms| {
+            MANIFESTS
+                .iter()
+                .chain(BODIES)
+                .chain(OPTIONAL)
+                .copied()
+                .filter(|s| syms.contains(*s))
+                .collect()
+        });
+        Self {
+            path: path.to_owned(),
+            format,
+            symbols,
+        }
+    }
+
+    /// Gets the test symbols that the file should export but doesn't, if its
+    /// symbols could be read.
+    ///
+    /// A missing manifest or test body shows up as its possible symbols
+    /// joined by `|`.
+    #[must_use]
+    pub fn missing(&self) -> Option<Vec<String>> {
+        let symbols = self.symbols.as_ref()?;
+        let missing = [MANIFESTS, BODIES]
+            .into_iter()
+            .filter(|alts| !alts.iter().any(|s| symbols.contains(s)))
+            .map(|alts| alts.join("|"))
+            .collect();
+        Some(missing)
+    }
+
+    /// Works out, as far as possible, why loading the file failed with
+    /// `cause`.
+    ///
+    /// If nothing in the file explains the failure, this falls back to
+    /// [`err::Error::DlopenFailed`].
+    #[must_use]
+    pub fn diagnose(&self, cause: dlopen::Error) -> err::Error {
+        let path = self.path.clone();
+        let format = match &self.format {
+            Ok(format) => format,
+            Err(e) => return err::Error::UnreadableTestLibrary(path, clone_io(e)),
+        };
+        match format.problem() {
+            Some(Problem::NotAnObject) => return err::Error::NotATestLibrary(path),
+            Some(Problem::WrongArch) => return wrong_arch(&path, format),
+            Some(Problem::NotShared(kind)) => {
+                return err::Error::NotSharedLibrary {
+                    path,
+                    kind: kind.to_string(),
+                }
+            }
+            None => (),
+        }
+        if let Some(missing) = self.missing().filter(|m| !m.is_empty()) {
+            return err::Error::MissingTestSymbols { path, missing };
+        }
+        let message = cause.to_string();
+        if let Some(dependency) = missing_dependency(&message) {
+            return err::Error::MissingLibraryDependency { path, dependency };
+        }
+        if let Some(symbol) = undefined_symbol(&message) {
+            return err::Error::UndefinedLibrarySymbol { path, symbol };
+        }
+        err::Error::DlopenFailed(cause)
+    }
+
+    /// Gets whether the probe found nothing wrong with the file.
+    #[must_use]
+    pub fn looks_loadable(&self) -> bool {
+        matches!(&self.format, Ok(f) if f.problem().is_none())
+            && self.missing().is_none_or(|m| m.is_empty())
+    }
+}
+
+/// Probes display as a short multi-line report, for verbose loading.
+impl Display for Probe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "probing test library {}:", self.path.display())?;
+        match &self.format {
+            Ok(format) => writeln!(f, "  format: {format} (host: {})", std::env::consts::ARCH)?,
+            Err(e) => writeln!(f, "  format: unreadable ({e})")?,
+        }
+        match &self.symbols {
+            Some(symbols) => {
+                let symbols: Vec<_> = symbols.iter().copied().collect();
+                writeln!(f, "  test symbols: {}", list_or_none(&symbols))?;
+            }
+            None => writeln!(f, "  test symbols: unknown (can't read symbol table)")?,
+        }
+        if let Some(missing) = self.missing() {
+            writeln!(f, "  missing symbols: {}", list_or_none(&missing))?;
+        }
+        Ok(())
+    }
+}
+
+fn list_or_none<T: AsRef<str>>(items: &[T]) -> String {
+    if items.is_empty() {
+        "none".to_owned()
+    } else {
+        items
+            .iter()
+            .map(AsRef::as_ref)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Something about a file's format that stops it loading as a test library.
+enum Problem {
+    NotAnObject,
+    WrongArch,
+    NotShared(Kind),
+}
+
+impl Format {
+    /// Works out the format of the file at `path` from its header.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be read.
+    pub fn of_file(path: &path::Path) -> io::Result<Self> {
+        use io::Read;
+        let mut header = vec![];
+        std::fs::File::open(path)?
+            .take(HEADER_LEN)
+            .read_to_end(&mut header)?;
+        Ok(Self::of_bytes(&header))
+    }
+
+    /// Works out the format of a file from its contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::api::c::probe::Format;
+    /// assert_eq!(Format::of_bytes(b"#!/bin/sh\n"), Format::Unknown);
+    /// assert_eq!(Format::of_bytes(b"MZ\x90\x00"), Format::Pe);
+    /// ```
+    #[must_use]
+    pub fn of_bytes(bytes: &[u8]) -> Self {
+        match bytes {
+            [0x7f, b'E', b'L', b'F', ..] => elf_format(bytes).unwrap_or(Self::Unknown),
+            [0xca, 0xfe, 0xba, 0xbe, ..] => Self::FatMachO,
+            [0xce | 0xcf, 0xfa, 0xed, 0xfe, ..] => macho_format(bytes).unwrap_or(Self::Unknown),
+            [b'M', b'Z', ..] => Self::Pe,
+            _ => Self::Unknown,
+        }
+    }
+
+    fn problem(&self) -> Option<Problem> {
+        let host_big_endian = cfg!(target_endian = "big");
+        match self {
+            Self::Unknown => Some(Problem::NotAnObject),
+            Self::Elf {
+                arch, big_endian, ..
+            } if !arch.is_host() || *big_endian != host_big_endian => Some(Problem::WrongArch),
+            Self::MachO { arch, .. } if !arch.is_host() => Some(Problem::WrongArch),
+            // Universal binaries may or may not hold the host architecture,
+            // and PE images belong to another platform entirely.
+            Self::Pe => Some(Problem::WrongArch),
+            Self::Elf { kind, .. } | Self::MachO { kind, .. } if *kind != Kind::Shared => {
+                Some(Problem::NotShared(*kind))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Formats display as a short description.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::api::c::probe::{Arch, Format, Kind};
+/// let f = Format::Elf {
+///     bits: 64,
+///     big_endian: false,
+///     kind: Kind::Shared,
+///     arch: Arch::Known("x86_64"),
+/// };
+/// assert_eq!(f.to_string(), "ELF 64-bit little-endian shared library for x86_64");
+/// ```
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Elf {
+                bits,
+                big_endian,
+                kind,
+                arch,
+            } => {
+                let endian = if *big_endian { "big" } else { "little" };
+                write!(f, "ELF {bits}-bit {endian}-endian {kind} for {arch}")
+            }
+            Self::MachO { bits, kind, arch } => write!(f, "Mach-O {bits}-bit {kind} for {arch}"),
+            Self::FatMachO => f.write_str("universal Mach-O binary"),
+            Self::Pe => f.write_str("Windows PE image"),
+            Self::Unknown => f.write_str("not an object file"),
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Shared => "shared library",
+            Self::Relocatable => "relocatable object",
+            Self::Executable => "executable",
+            Self::Other => "object",
+        })
+    }
+}
+
+impl Arch {
+    /// Gets whether this is the architecture on which we're running.
+    #[must_use]
+    pub fn is_host(self) -> bool {
+        self == Self::Known(std::env::consts::ARCH)
+    }
+}
+
+impl Display for Arch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Known(name) => f.write_str(name),
+            Self::Unknown(code) => write!(f, "unknown architecture {code:#x}"),
+        }
+    }
+}
+
+/// Reads integers from an object file of known endianness, failing on
+/// truncation.
+#[derive(Clone, Copy)]
+struct Reader<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes<const N: usize>(self, at: usize) -> Option<[u8; N]> {
+        let mut b: [u8; N] = self.bytes.get(at..at.checked_add(N)?)?.try_into().ok()?;
+        if !self.big_endian {
+            b.reverse();
+        }
+        Some(b)
+    }
+
+    fn u16(self, at: usize) -> Option<u16> {
+        self.bytes(at).map(u16::from_be_bytes)
+    }
+
+    fn u32(self, at: usize) -> Option<u32> {
+        self.bytes(at).map(u32::from_be_bytes)
+    }
+
+    fn u64(self, at: usize) -> Option<u64> {
+        self.bytes(at).map(u64::from_be_bytes)
+    }
+
+    /// Reads a word: 64 bits if `wide`, and 32 otherwise.
+    fn word(self, at: usize, wide: bool) -> Option<usize> {
+        if wide {
+            usize::try_from(self.u64(at)?).ok()
+        } else {
+            usize::try_from(self.u32(at)?).ok()
+        }
+    }
+
+    /// Reads a NUL-terminated string.
+    fn str(self, at: usize) -> Option<&'a str> {
+        let rest = self.bytes.get(at..)?;
+        let len = rest.iter().position(|&b| b == 0)?;
+        std::str::from_utf8(&rest[..len]).ok()
+    }
+}
+
+/// Gets a reader for an ELF file, and whether the file is 64-bit.
+fn elf_reader(bytes: &[u8]) -> Option<(Reader<'_>, bool)> {
+    let wide = match bytes.get(4)? {
+        1 => false,
+        2 => true,
+        _ => return None,
+    };
+    let big_endian = match bytes.get(5)? {
+        1 => false,
+        2 => true,
+        _ => return None,
+    };
+    Some((Reader { bytes, big_endian }, wide))
+}
+
+fn elf_format(bytes: &[u8]) -> Option<Format> {
+    let (r, wide) = elf_reader(bytes)?;
+    let kind = match r.u16(0x10)? {
+        1 => Kind::Relocatable,
+        2 => Kind::Executable,
+        3 => Kind::Shared,
+        _ => Kind::Other,
+    };
+    let machine = r.u16(0x12)?;
+    let arch = match (machine, wide) {
+        (3, _) => Arch::Known("x86"),
+        (62, _) => Arch::Known("x86_64"),
+        (40, _) => Arch::Known("arm"),
+        (183, _) => Arch::Known("aarch64"),
+        (243, true) => Arch::Known("riscv64"),
+        (243, false) => Arch::Known("riscv32"),
+        (20, _) => Arch::Known("powerpc"),
+        (21, _) => Arch::Known("powerpc64"),
+        (22, _) => Arch::Known("s390x"),
+        (8, true) => Arch::Known("mips64"),
+        (8, false) => Arch::Known("mips"),
+        (258, _) => Arch::Known("loongarch64"),
+        (m, _) => Arch::Unknown(m.into()),
+    };
+    Some(Format::Elf {
+        bits: if wide { 64 } else { 32 },
+        big_endian: r.big_endian,
+        kind,
+        arch,
+    })
+}
+
+fn macho_format(bytes: &[u8]) -> Option<Format> {
+    // We only recognise little-endian Mach-O, which covers every current
+    // Apple platform.
+    let r = Reader {
+        bytes,
+        big_endian: false,
+    };
+    let bits = if bytes[0] == 0xcf { 64 } else { 32 };
+    let arch = match r.u32(4)? {
+        7 => Arch::Known("x86"),
+        0x0100_0007 => Arch::Known("x86_64"),
+        12 => Arch::Known("arm"),
+        0x0100_000c => Arch::Known("aarch64"),
+        cpu => Arch::Unknown(cpu),
+    };
+    let kind = match r.u32(12)? {
+        1 => Kind::Relocatable,
+        2 => Kind::Executable,
+        6 | 8 => Kind::Shared,
+        _ => Kind::Other,
+    };
+    Some(Format::MachO { bits, kind, arch })
+}
+
+/// Gets the names of the symbols that an ELF file defines in its dynamic
+/// symbol table, if it has one that we can find.
+fn elf_symbols(bytes: &[u8]) -> Option<BTreeSet<&str>> {
+    const SHT_DYNSYM: u32 = 11;
+
+    if !bytes.starts_with(b"\x7fELF") {
+        return None;
+    }
+    let (r, wide) = elf_reader(bytes)?;
+    let (shoff, shentsize, shnum) = if wide {
+        (r.word(0x28, true)?, r.u16(0x3a)?, r.u16(0x3c)?)
+    } else {
+        (r.word(0x20, false)?, r.u16(0x2e)?, r.u16(0x30)?)
+    };
+    let section = |i: usize| shoff.checked_add(i.checked_mul(shentsize.into())?);
+    // Offsets within a section header of its type, offset, size, link, and
+    // entry size.
+    let (ty, offset, size, link, entsize) = if wide {
+        (4, 24, 32, 40, 56)
+    } else {
+        (4, 16, 20, 24, 36)
+    };
+
+    let dynsym = (0..shnum.into())
+        .filter_map(section)
+        .find(|&s| r.u32(s + ty) == Some(SHT_DYNSYM))?;
+    let strtab = section(usize::try_from(r.u32(dynsym + link)?).ok()?)?;
+    let str_base = r.word(strtab + offset, wide)?;
+
+    let sym_base = r.word(dynsym + offset, wide)?;
+    let sym_size = r.word(dynsym + size, wide)?;
+    let sym_entsize = r.word(dynsym + entsize, wide)?;
+    if sym_entsize == 0 {
+        return None;
+    }
+    // Offsets within a symbol of its binding/type byte and section index.
+    let (info, shndx) = if wide { (4, 6) } else { (12, 14) };
+    let symbols = (0..sym_size / sym_entsize)
+        .filter_map(|i| {
+            let sym = sym_base.checked_add(i.checked_mul(sym_entsize)?)?;
+            let binding = bytes.get(sym + info)? >> 4;
+            // Only defined global or weak symbols are exported.
+            let defined = r.u16(sym + shndx)? != 0;
+            if !defined || !matches!(binding, 1 | 2) {
+                return None;
+            }
+            let name = usize::try_from(r.u32(sym)?).ok()?;
+            r.str(str_base.checked_add(name)?)
+        })
+        .collect();
+    Some(symbols)
+}
+
+/// Extracts the name of a missing dependency from a loader error message,
+/// in the form glibc and musl give it.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::api::c::probe::missing_dependency;
+/// let msg = "libfoo.so.1: cannot open shared object file: No such file or directory";
+/// assert_eq!(missing_dependency(msg), Some("libfoo.so.1".to_owned()));
+/// assert_eq!(missing_dependency("something else"), None);
+/// ```
+#[must_use]
+pub fn missing_dependency(message: &str) -> Option<String> {
+    let (dependency, _) = message.split_once(": cannot open shared object file")?;
+    // The loader may prefix the message with the name of our own library.
+    let dependency = dependency.rsplit(": ").next().unwrap_or(dependency);
+    Some(dependency.trim().to_owned())
+}
+
+/// Extracts the name of an unresolved symbol from a loader error message.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::api::c::probe::undefined_symbol;
+/// let msg = "./test.so: undefined symbol: atomic_thing";
+/// assert_eq!(undefined_symbol(msg), Some("atomic_thing".to_owned()));
+/// ```
+#[must_use]
+pub fn undefined_symbol(message: &str) -> Option<String> {
+    let (_, symbol) = message.split_once("undefined symbol: ")?;
+    let symbol = symbol.split_whitespace().next()?;
+    Some(symbol.trim_end_matches(',').to_owned())
+}
+
+/// I/O errors aren't [Clone], so this rebuilds one from its kind and message.
+fn clone_io(e: &io::Error) -> io::Error {
+    io::Error::new(e.kind(), e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests probing the running executable, which is (usually) a
+    /// position-independent ELF for the host but not a test library.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_probe_self() {
+        let probe = Probe::of_file(&std::env::current_exe().unwrap());
+        let Ok(Format::Elf { arch, .. }) = probe.format else {
+            panic!("not an ELF: {probe}");
+        };
+        assert!(arch.is_host());
+        // Test binaries don't export test symbols.
+        if let Some(missing) = probe.missing() {
+            assert_eq!(
+                missing,
+                ["manifest|manifest_v2", "test|test_iter|test_locals"]
+            );
+        }
+    }
+
+    /// Tests that probes of unloadable files explain themselves.
+    #[test]
+    fn test_diagnose() {
+        let path = std::env::temp_dir().join(format!("phph-probe-{}.so", std::process::id()));
+        std::fs::write(&path, b"INPUT(-lfoo)\n").unwrap();
+        let probe = Probe::of_file(&path);
+        assert!(!probe.looks_loadable());
+        let cause = dlopen::Error::NullSymbol;
+        assert!(matches!(
+            probe.diagnose(cause),
+            err::Error::NotATestLibrary(p) if p == path
+        ));
+        std::fs::remove_file(&path).unwrap();
+
+        let probe = Probe::of_file(&path);
+        assert!(matches!(
+            probe.diagnose(dlopen::Error::NullSymbol),
+            err::Error::UnreadableTestLibrary(..)
+        ));
+    }
+
+    /// Tests that ELF objects for other machines are spotted.
+    #[test]
+    fn test_foreign_elf() {
+        let mut header = vec![0x7f, b'E', b'L', b'F', 2, 1, 1, 0];
+        header.resize(0x40, 0);
+        header[0x10] = 3;
+        // EM_S390 on little-endian x86_64, or EM_X86_64 elsewhere.
+        header[0x12] = if std::env::consts::ARCH == "x86_64" {
+            22
+        } else {
+            62
+        };
+        let format = Format::of_bytes(&header);
+        assert!(matches!(format.problem(), Some(Problem::WrongArch)));
+
+        let path = std::env::temp_dir().join(format!("phph-foreign-{}.so", std::process::id()));
+        std::fs::write(&path, &header).unwrap();
+        let result = check_arch(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result,
+            Err(err::Error::WrongTestLibraryArch { expected, .. }) if expected == std::env::consts::ARCH
+        ));
+
+        header[0x12] = 0;
+        header[0x13] = 0;
+        let Format::Elf { arch, kind, .. } = Format::of_bytes(&header) else {
+            panic!("not an ELF");
+        };
+        assert_eq!((arch, kind), (Arch::Unknown(0), Kind::Shared));
+    }
+}
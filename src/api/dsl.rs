@@ -0,0 +1,552 @@
+//! A tiny language for writing simple tests inline in the config.
+//!
+//! Each thread of an inline test is a sequence of instructions separated by
+//! semicolons or newlines, such as `store x 1; r0 = load y`:
+//!
+//! - `store VAR V` stores `V` to the shared variable `VAR`;
+//! - `REG = load VAR` loads `VAR` into the register `REG`;
+//! - `REG = swap VAR V` stores `V` to `VAR`, loading its old value;
+//! - `REG = add VAR V` adds `V` to `VAR`, loading its old value;
+//! - `REG = V` copies `V` into `REG`;
+//! - `fence` is a memory fence.
+//!
+//! Registers are named `r0`, `r1`, and so on, and belong to their thread;
+//! any other name is a shared variable.  Values are integer literals or
+//! registers.  Every variable is a 32-bit integer; shared variables are
+//! atomic and start at zero unless the test's `init` table says otherwise,
+//! and registers show up in states as `T:REG` for thread `T`.
+//!
+//! Memory accesses are relaxed, and fences sequentially consistent, unless
+//! the instruction has an ordering suffix: `.rlx`, `.acq`, `.rel`,
+//! `.acq_rel`, or `.sc` (as in `r0 = load.acq y`).
+//!
+//! Inline tests compile into an [Entry] that runs on the native Rust
+//! environment, so they need no C toolchain; this makes them handy for
+//! teaching and smoke tests.
+
+use super::{abs, rust};
+use crate::{err, model};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    num::NonZeroUsize,
+    sync::{atomic::Ordering, Arc},
+};
+
+crate::features::register! { "abi" => ["inline"] }
+
+/// The most registers that any one thread of an inline test can use.
+pub const MAX_REGISTERS: usize = 16;
+
+/// An inline test, as written in the config.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Spec {
+    /// The program of each thread, in thread order.
+    pub threads: Vec<String>,
+    /// Initial values of shared variables, which otherwise start at zero.
+    ///
+    /// This comes last because TOML serialises tables after plain values.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub init: BTreeMap<String, i32>,
+}
+
+impl Spec {
+    /// Compiles this test into an entry point.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::api::{abs::Entry, dsl::Spec};
+    /// let spec = Spec {
+    ///     threads: vec!["store x 1; r0 = load y".into(), "store y 1; r0 = load x".into()],
+    ///     init: Default::default(),
+    /// };
+    /// let manifest = spec.compile().unwrap().make_manifest().unwrap();
+    /// assert_eq!(manifest.n_threads.get(), 2);
+    /// assert!(["x", "y", "0:r0", "1:r0"].iter().all(|v| manifest.has_var(v)));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`err::Error::BadInlineTest`] if the test has no threads,
+    /// or a thread's program doesn't parse.
+    pub fn compile(&self) -> err::Result<Entry> {
+        let n_threads =
+            NonZeroUsize::new(self.threads.len()).ok_or_else(|| err::Error::BadInlineTest {
+                thread: None,
+                reason: "the test has no threads".to_owned(),
+            })?;
+        let parsed = self
+            .threads
+            .iter()
+            .enumerate()
+            .map(|(tid, source)| {
+                parse_thread(source).map_err(|reason| err::Error::BadInlineTest {
+                    thread: Some(tid),
+                    reason,
+                })
+            })
+            .collect::<err::Result<Vec<_>>>()?;
+
+        let mut globals: BTreeSet<&str> = self.init.keys().map(String::as_str).collect();
+        globals.extend(parsed.iter().flatten().filter_map(Parsed::global));
+        let global_slots: BTreeMap<&str, model::slot::Slot> = globals
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (*name, slot(true, index)))
+            .collect();
+
+        let mut manifest = model::Manifest {
+            n_threads,
+            i32s: model::manifest::VarMap::new(),
+            bools: model::manifest::VarMap::new(),
+            f64s: model::manifest::VarMap::new(),
+            scalable_role: None,
+            symmetry: vec![],
+            invariants: model::invariant::Map::new(),
+            aux_flags: vec![],
+            declared: vec![],
+        };
+        for (name, slot) in &global_slots {
+            let initial_value = Some(self.init.get(*name).copied().unwrap_or(0));
+            declare(&mut manifest, name, initial_value, *slot);
+        }
+        let mut threads = Vec::with_capacity(parsed.len());
+        for (tid, instructions) in parsed.iter().enumerate() {
+            let mut registers: Vec<&str> = vec![];
+            for reg in instructions.iter().filter_map(Parsed::register) {
+                if !registers.contains(&reg) {
+                    registers.push(reg);
+                }
+            }
+            if MAX_REGISTERS < registers.len() {
+                return Err(err::Error::BadInlineTest {
+                    thread: Some(tid),
+                    reason: format!("the thread uses more than {MAX_REGISTERS} registers"),
+                });
+            }
+            let mut slots = Vec::with_capacity(registers.len());
+            for reg in &registers {
+                let slot = slot(false, manifest.i32s.len() - global_slots.len());
+                declare(&mut manifest, &format!("{tid}:{reg}"), Some(0), slot);
+                slots.push(slot);
+            }
+            let reg = |name: &str| registers.iter().position(|r| *r == name).unwrap_or(0);
+            let global = |name: &str| global_slots[name];
+            threads.push(Program {
+                instructions: instructions
+                    .iter()
+                    .map(|p| p.resolve(&reg, &global))
+                    .collect(),
+                registers: slots,
+            });
+        }
+        Ok(Entry {
+            manifest,
+            threads: threads.into(),
+        })
+    }
+}
+
+/// Adds the 32-bit integer variable `name` to `manifest`.
+fn declare(
+    manifest: &mut model::Manifest,
+    name: &str,
+    initial_value: Option<i32>,
+    slot: model::slot::Slot,
+) {
+    let name: model::manifest::VarName = name.into();
+    manifest.declared.push(name.clone());
+    manifest.i32s.insert(
+        name,
+        model::manifest::VarRecord {
+            initial_value,
+            slot,
+        },
+    );
+}
+
+fn slot(is_atomic: bool, index: usize) -> model::slot::Slot {
+    model::slot::Slot { is_atomic, index }
+}
+
+/// Entry point for a compiled inline test.
+#[derive(Clone)]
+pub struct Entry {
+    manifest: model::Manifest,
+    threads: Arc<[Program]>,
+}
+
+/// Inline tests own everything they need, so they can run for any lifetime.
+impl abs::Entry<'_> for Entry {
+    type Env = rust::Env;
+
+    fn make_manifest(&self) -> err::Result<model::Manifest> {
+        Ok(self.manifest.clone())
+    }
+
+    fn run(&self, tid: usize, e: &Self::Env) {
+        if let Some(program) = self.threads.get(tid) {
+            program.run(e);
+        }
+    }
+
+    fn checker(&self) -> Box<dyn abs::Checker<Self::Env>> {
+        abs::check::box_unknown()
+    }
+
+    fn has_checker(&self) -> bool {
+        false
+    }
+}
+
+/// The compiled program of one thread.
+struct Program {
+    instructions: Vec<Instruction>,
+    /// The slot of each of the thread's registers, by register number.
+    registers: Vec<model::slot::Slot>,
+}
+
+impl Program {
+    fn run(&self, env: &rust::Env) {
+        use abs::AtomicEnv;
+        let mut regs = [0; MAX_REGISTERS];
+        for i in &self.instructions {
+            match *i {
+                Instruction::Store { var, value, order } => {
+                    env.store_i32(var, value.get(&regs), order);
+                }
+                Instruction::Load { reg, var, order } => regs[reg] = env.load_i32(var, order),
+                Instruction::Swap {
+                    reg,
+                    var,
+                    value,
+                    order,
+                } => regs[reg] = env.swap_i32(var, value.get(&regs), order),
+                Instruction::Add {
+                    reg,
+                    var,
+                    value,
+                    order,
+                } => regs[reg] = env.fetch_add_i32(var, value.get(&regs), order),
+                Instruction::Move { reg, value } => regs[reg] = value.get(&regs),
+                Instruction::Fence(order) => std::sync::atomic::fence(order),
+            }
+        }
+        for (slot, value) in self.registers.iter().zip(regs) {
+            if let Some(cell) = env.i32s.non_atomic.get(slot.index) {
+                // SAFETY: only this thread writes its registers, and nothing
+                // reads them until the test threads next synchronise.
+                unsafe { *cell.get() = value };
+            }
+        }
+    }
+}
+
+/// A compiled instruction.
+#[derive(Clone, Copy)]
+enum Instruction {
+    Store {
+        var: model::slot::Slot,
+        value: Operand,
+        order: Ordering,
+    },
+    Load {
+        reg: usize,
+        var: model::slot::Slot,
+        order: Ordering,
+    },
+    Swap {
+        reg: usize,
+        var: model::slot::Slot,
+        value: Operand,
+        order: Ordering,
+    },
+    Add {
+        reg: usize,
+        var: model::slot::Slot,
+        value: Operand,
+        order: Ordering,
+    },
+    Move {
+        reg: usize,
+        value: Operand,
+    },
+    Fence(Ordering),
+}
+
+/// A compiled value operand.
+#[derive(Clone, Copy)]
+enum Operand {
+    Int(i32),
+    Reg(usize),
+}
+
+impl Operand {
+    fn get(self, regs: &[i32; MAX_REGISTERS]) -> i32 {
+        match self {
+            Self::Int(k) => k,
+            Self::Reg(r) => regs[r],
+        }
+    }
+}
+
+/// An instruction as parsed, naming its variables and registers.
+#[derive(Debug, PartialEq)]
+enum Parsed<'a> {
+    Store(&'a str, Value<'a>, Ordering),
+    Load(&'a str, &'a str, Ordering),
+    Swap(&'a str, &'a str, Value<'a>, Ordering),
+    Add(&'a str, &'a str, Value<'a>, Ordering),
+    Move(&'a str, Value<'a>),
+    Fence(Ordering),
+}
+
+/// A value operand as parsed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Value<'a> {
+    Int(i32),
+    Reg(&'a str),
+}
+
+impl<'a> Parsed<'a> {
+    /// Gets the shared variable, if any, that this instruction accesses.
+    fn global(&self) -> Option<&'a str> {
+        match self {
+            Self::Store(var, ..)
+            | Self::Load(_, var, _)
+            | Self::Swap(_, var, ..)
+            | Self::Add(_, var, ..) => Some(var),
+            Self::Move(..) | Self::Fence(_) => None,
+        }
+    }
+
+    /// Gets the register, if any, that this instruction writes.
+    fn register(&self) -> Option<&'a str> {
+        match self {
+            Self::Load(reg, ..) | Self::Swap(reg, ..) | Self::Add(reg, ..) | Self::Move(reg, _) => {
+                Some(reg)
+            }
+            Self::Store(..) | Self::Fence(_) => None,
+        }
+    }
+
+    fn resolve(
+        &self,
+        reg: &impl Fn(&str) -> usize,
+        global: &impl Fn(&str) -> model::slot::Slot,
+    ) -> Instruction {
+        let value = |v: &Value| match v {
+            Value::Int(k) => Operand::Int(*k),
+            Value::Reg(r) => Operand::Reg(reg(r)),
+        };
+        match self {
+            Self::Store(var, v, order) => Instruction::Store {
+                var: global(var),
+                value: value(v),
+                order: *order,
+            },
+            Self::Load(r, var, order) => Instruction::Load {
+                reg: reg(r),
+                var: global(var),
+                order: *order,
+            },
+            Self::Swap(r, var, v, order) => Instruction::Swap {
+                reg: reg(r),
+                var: global(var),
+                value: value(v),
+                order: *order,
+            },
+            Self::Add(r, var, v, order) => Instruction::Add {
+                reg: reg(r),
+                var: global(var),
+                value: value(v),
+                order: *order,
+            },
+            Self::Move(r, v) => Instruction::Move {
+                reg: reg(r),
+                value: value(v),
+            },
+            Self::Fence(order) => Instruction::Fence(*order),
+        }
+    }
+}
+
+/// Parses the program of one thread.
+fn parse_thread(source: &str) -> Result<Vec<Parsed<'_>>, String> {
+    source
+        .split([';', '\n'])
+        .map(str::trim)
+        .filter(|i| !i.is_empty())
+        .map(|i| parse_instruction(i).map_err(|e| format!("in `{i}`: {e}")))
+        .collect()
+}
+
+fn parse_instruction(source: &str) -> Result<Parsed<'_>, String> {
+    let (dest, rhs) = match source.split_once('=') {
+        Some((dest, rhs)) => (Some(register(dest.trim())?), rhs),
+        None => (None, source),
+    };
+    let words: Vec<&str> = rhs.split_whitespace().collect();
+    let Some((op, args)) = words.split_first() else {
+        return Err("missing operation".to_owned());
+    };
+    let (name, suffix) = match op.split_once('.') {
+        Some((name, suffix)) => (name, Some(ordering(suffix)?)),
+        None => (*op, None),
+    };
+    let relaxed = suffix.unwrap_or(Ordering::Relaxed);
+    let parsed = match (name, dest, args) {
+        ("store", None, [var, v]) => Parsed::Store(global(var)?, value(v)?, relaxed),
+        ("load", Some(r), [var]) => Parsed::Load(r, global(var)?, relaxed),
+        ("swap", Some(r), [var, v]) => Parsed::Swap(r, global(var)?, value(v)?, relaxed),
+        ("add", Some(r), [var, v]) => Parsed::Add(r, global(var)?, value(v)?, relaxed),
+        ("fence", None, []) => Parsed::Fence(suffix.unwrap_or(Ordering::SeqCst)),
+        (_, Some(r), []) if suffix.is_none() => Parsed::Move(r, value(name)?),
+        ("store" | "fence", Some(_), _) => return Err(format!("{name} gives no value")),
+        ("load" | "swap" | "add", None, _) => {
+            return Err(format!("{name} needs a register to load into"))
+        }
+        ("store" | "load" | "swap" | "add" | "fence", _, _) => {
+            return Err(format!("wrong number of operands for {name}"))
+        }
+        _ => return Err(format!("unknown operation {op}")),
+    };
+    check_ordering(&parsed)?;
+    Ok(parsed)
+}
+
+/// Rejects orderings that the operation can't take.
+fn check_ordering(parsed: &Parsed) -> Result<(), String> {
+    let bad = match parsed {
+        Parsed::Store(.., order) => matches!(order, Ordering::Acquire | Ordering::AcqRel),
+        Parsed::Load(.., order) => matches!(order, Ordering::Release | Ordering::AcqRel),
+        Parsed::Fence(order) => *order == Ordering::Relaxed,
+        _ => false,
+    };
+    if bad {
+        Err("the operation can't take that ordering".to_owned())
+    } else {
+        Ok(())
+    }
+}
+
+fn ordering(suffix: &str) -> Result<Ordering, String> {
+    match suffix {
+        "rlx" => Ok(Ordering::Relaxed),
+        "acq" => Ok(Ordering::Acquire),
+        "rel" => Ok(Ordering::Release),
+        "acq_rel" => Ok(Ordering::AcqRel),
+        "sc" => Ok(Ordering::SeqCst),
+        _ => Err(format!("unknown ordering {suffix}")),
+    }
+}
+
+fn value(s: &str) -> Result<Value<'_>, String> {
+    if let Ok(k) = s.parse() {
+        Ok(Value::Int(k))
+    } else {
+        register(s).map(Value::Reg)
+    }
+}
+
+fn register(s: &str) -> Result<&str, String> {
+    if is_register(s) {
+        Ok(s)
+    } else {
+        Err(format!("{s} isn't a register (r0, r1, ...)"))
+    }
+}
+
+fn global(s: &str) -> Result<&str, String> {
+    let mut chars = s.chars();
+    let ident = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !ident {
+        Err(format!("{s} isn't a variable name"))
+    } else if is_register(s) {
+        Err(format!("{s} is a register, not a shared variable"))
+    } else {
+        Ok(s)
+    }
+}
+
+fn is_register(s: &str) -> bool {
+    s.strip_prefix('r')
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::{halt, sync, Builder};
+
+    /// Tests that instructions parse, with their orderings.
+    #[test]
+    fn test_parse() {
+        let parsed = parse_thread("store.rel x 1; r0 = load.acq y\nr1 = add z r0;fence").unwrap();
+        assert_eq!(
+            parsed,
+            [
+                Parsed::Store("x", Value::Int(1), Ordering::Release),
+                Parsed::Load("r0", "y", Ordering::Acquire),
+                Parsed::Add("r1", "z", Value::Reg("r0"), Ordering::Relaxed),
+                Parsed::Fence(Ordering::SeqCst),
+            ]
+        );
+        for bad in [
+            "store r0 1",
+            "load x",
+            "x = load y",
+            "r0 = load.rel x",
+            "fence.rlx",
+            "store x",
+            "jump x",
+        ] {
+            assert!(parse_thread(bad).is_err(), "{bad}");
+        }
+    }
+
+    /// Tests that an inline message-passing test runs end to end, and
+    /// never sees the flag without the data under release/acquire.
+    #[test]
+    fn test_run_message_passing() -> err::Result<()> {
+        let spec = Spec {
+            threads: vec![
+                "store x 1; store.rel y 1".to_owned(),
+                "r0 = load.acq y; r1 = load x".to_owned(),
+            ],
+            init: BTreeMap::from([("x".to_owned(), 0)]),
+        };
+        let cond = halt::Condition::EveryNIterations(NonZeroUsize::new(100).unwrap());
+        let report = Builder::new(spec.compile()?)
+            .with_sync(sync::make_barrier)
+            .add_halt_rules(std::iter::once(cond.exit()))
+            .build()?
+            .run()?;
+
+        let i32 = |s: &model::state::State, v: &str| match s[v] {
+            model::state::Value::I32(k) => k,
+            _ => panic!("{v} isn't an integer"),
+        };
+        assert!(!report.states.is_empty());
+        for s in &report.states {
+            assert_eq!((i32(&s.state, "x"), i32(&s.state, "y")), (1, 1));
+            assert!(i32(&s.state, "1:r0") <= i32(&s.state, "1:r1"));
+        }
+        Ok(())
+    }
+
+    /// Tests that tests without threads don't compile.
+    #[test]
+    fn test_no_threads() {
+        let result = Spec::default().compile();
+        assert!(matches!(
+            result,
+            Err(err::Error::BadInlineTest { thread: None, .. })
+        ));
+    }
+}
@@ -2,5 +2,10 @@
 //!
 //! Work in progress.
 
-mod entry;
-mod env;
+pub mod entry;
+pub mod env;
+
+pub use entry::Static;
+pub use env::Env;
+
+crate::features::register! { "abi" => ["rust"] }
@@ -11,7 +11,13 @@ use crate::{api::abs, model};
 pub struct Static {
     pub manifest: model::Manifest,
     pub test: fn(tid: usize, env: &env::Env),
+    /// An alternative test body that also receives the iteration position.
+    ///
+    /// If present, this runs instead of `test`.
+    pub test_at: Option<fn(tid: usize, iter: abs::Iteration, env: &env::Env)>,
     pub check: Option<fn(env: &env::Env) -> model::Outcome>,
+    /// A description of what `check` checks, if any.
+    pub check_description: Option<&'static str>,
 }
 
 impl abs::Entry<'static> for Static {
@@ -25,8 +31,50 @@ impl abs::Entry<'static> for Static {
         (self.test)(tid, e);
     }
 
+    fn run_at(&self, tid: usize, iter: abs::Iteration, e: &Self::Env) {
+        match self.test_at {
+            Some(f) => f(tid, iter, e),
+            None => self.run(tid, e),
+        }
+    }
+
     fn checker(&self) -> Box<dyn abs::Checker<Self::Env>> {
         self.check
             .map_or_else(abs::check::box_unknown, |f| Box::new(f))
     }
+
+    fn has_checker(&self) -> bool {
+        self.check.is_some()
+    }
+
+    fn check_description(&self) -> Option<String> {
+        self.check_description.map(ToOwned::to_owned)
+    }
+
+    fn capabilities(&self) -> model::capability::Set {
+        use model::capability::Capability;
+        let mut caps = model::capability::Set::new();
+        if self.check.is_some() {
+            caps.insert(Capability::Check);
+        }
+        if self.test_at.is_some() {
+            caps.insert(Capability::TestIter);
+        }
+        if self.manifest.scalable_role.is_some() {
+            caps.insert(Capability::ScalableRole);
+        }
+        if !self.manifest.symmetry.is_empty() {
+            caps.insert(Capability::Symmetry);
+        }
+        if !self.manifest.invariants.is_empty() {
+            caps.insert(Capability::Invariants);
+        }
+        if self.check_description.is_some() {
+            caps.insert(Capability::CheckDescription);
+        }
+        if !self.manifest.aux_flags.is_empty() {
+            caps.insert(Capability::AuxFlags);
+        }
+        caps
+    }
 }
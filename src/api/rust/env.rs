@@ -1,22 +1,72 @@
 //! Native-Rust shared environment and related types.
 
-use crate::{api::abs, err, model::slot};
+use crate::{
+    api::abs,
+    err,
+    model::{ordering, slot},
+};
 use std::{
     cell::UnsafeCell,
-    sync::{self, atomic::AtomicI32},
+    sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicU8, Ordering},
 };
 
 /// A native-Rust implementation of the environment.
+///
+/// Tests should access atomic variables through [`abs::AtomicEnv`], which
+/// records the orderings they use; accesses made directly on the slotsets
+/// go unrecorded.  Tests that need access patterns beyond those, such as
+/// inline assembly, can take raw pointers to the slots with
+/// [`Env::raw_parts`].
 pub struct Env {
     /// The 32-bit slots.
     pub i32s: Slotset<AtomicI32, i32>,
+    /// The Boolean slots.
+    pub bools: Slotset<AtomicBool, bool>,
+    /// The 64-bit float slots.
+    pub f64s: Slotset<AtomicF64, f64>,
+    /// The auxiliary flags raised since they were last taken, one bit each
+    /// (see [`abs::AtomicEnv::raise_aux_flag`]).
+    aux_flags: AtomicU64,
+}
+
+/// Raw pointers to the slots of an [Env] (see [`Env::raw_parts`]).
+#[derive(Copy, Clone)]
+pub struct RawEnv {
+    /// The 32-bit slots.
+    pub i32s: RawParts<AtomicI32, i32>,
+    /// The Boolean slots.
+    pub bools: RawParts<AtomicBool, bool>,
+    /// The 64-bit float slots.
+    pub f64s: RawParts<AtomicF64, f64>,
+}
+
+impl Env {
+    /// Gets raw pointers to the slots of each slotset in this environment.
+    ///
+    /// # Safety
+    ///
+    /// Accesses through the pointers must follow the rules given for
+    /// [`Slotset::raw_parts`].
+    #[must_use]
+    pub unsafe fn raw_parts(&self) -> RawEnv {
+        unsafe {
+            RawEnv {
+                i32s: self.i32s.raw_parts(),
+                bools: self.bools.raw_parts(),
+                f64s: self.f64s.raw_parts(),
+            }
+        }
+    }
 }
 
 impl abs::Env for Env {
     fn of_reservations(reservations: slot::ReservationSet) -> err::Result<Self> {
-        let slot::ReservationSet { i32s } = reservations;
+        let slot::ReservationSet { i32s, bools, f64s } = reservations;
         Ok(Env {
             i32s: Slotset::new(&i32s),
+            bools: Slotset::new(&bools),
+            f64s: Slotset::new(&f64s),
+            aux_flags: AtomicU64::new(0),
         })
     }
 
@@ -27,6 +77,126 @@ impl abs::Env for Env {
     fn set_i32(&mut self, slot: slot::Slot, v: i32) {
         self.i32s.set(slot, v);
     }
+
+    fn get_bool(&self, slot: slot::Slot) -> bool {
+        self.bools.get(slot)
+    }
+
+    fn set_bool(&mut self, slot: slot::Slot, v: bool) {
+        self.bools.set(slot, v);
+    }
+
+    fn get_f64(&self, slot: slot::Slot) -> f64 {
+        self.f64s.get(slot)
+    }
+
+    fn set_f64(&mut self, slot: slot::Slot, v: f64) {
+        self.f64s.set(slot, v);
+    }
+
+    fn i32_orderings(&self, slot: slot::Slot) -> ordering::Set {
+        self.i32s.orderings(slot)
+    }
+
+    fn bool_orderings(&self, slot: slot::Slot) -> ordering::Set {
+        self.bools.orderings(slot)
+    }
+
+    fn f64_orderings(&self, slot: slot::Slot) -> ordering::Set {
+        self.f64s.orderings(slot)
+    }
+
+    fn take_aux_flags(&mut self) -> u64 {
+        std::mem::take(self.aux_flags.get_mut())
+    }
+}
+
+impl abs::AtomicEnv for Env {
+    fn load_i32(&self, slot: slot::Slot, order: Ordering) -> i32 {
+        self.i32s.access(slot, order).map_or(0, |a| a.load(order))
+    }
+
+    fn store_i32(&self, slot: slot::Slot, v: i32, order: Ordering) {
+        if let Some(a) = self.i32s.access(slot, order) {
+            a.store(v, order);
+        }
+    }
+
+    fn swap_i32(&self, slot: slot::Slot, v: i32, order: Ordering) -> i32 {
+        self.i32s
+            .access(slot, order)
+            .map_or(0, |a| a.swap(v, order))
+    }
+
+    fn fetch_add_i32(&self, slot: slot::Slot, v: i32, order: Ordering) -> i32 {
+        self.i32s
+            .access(slot, order)
+            .map_or(0, |a| a.fetch_add(v, order))
+    }
+
+    fn compare_exchange_i32(
+        &self,
+        slot: slot::Slot,
+        current: i32,
+        new: i32,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<i32, i32> {
+        self.i32s.note(slot, failure);
+        self.i32s.access(slot, success).map_or(Err(0), |a| {
+            a.compare_exchange(current, new, success, failure)
+        })
+    }
+
+    fn load_bool(&self, slot: slot::Slot, order: Ordering) -> bool {
+        self.bools
+            .access(slot, order)
+            .is_some_and(|a| a.load(order))
+    }
+
+    fn store_bool(&self, slot: slot::Slot, v: bool, order: Ordering) {
+        if let Some(a) = self.bools.access(slot, order) {
+            a.store(v, order);
+        }
+    }
+
+    fn swap_bool(&self, slot: slot::Slot, v: bool, order: Ordering) -> bool {
+        self.bools
+            .access(slot, order)
+            .is_some_and(|a| a.swap(v, order))
+    }
+
+    fn compare_exchange_bool(
+        &self,
+        slot: slot::Slot,
+        current: bool,
+        new: bool,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<bool, bool> {
+        self.bools.note(slot, failure);
+        self.bools.access(slot, success).map_or(Err(false), |a| {
+            a.compare_exchange(current, new, success, failure)
+        })
+    }
+
+    fn load_f64(&self, slot: slot::Slot, order: Ordering) -> f64 {
+        self.f64s
+            .access(slot, order)
+            .map_or(0.0, |a| a.slot_load(order))
+    }
+
+    fn store_f64(&self, slot: slot::Slot, v: f64, order: Ordering) {
+        if let Some(a) = self.f64s.access(slot, order) {
+            a.slot_store(v, order);
+        }
+    }
+
+    fn raise_aux_flag(&self, flag: usize) {
+        if let Some(bit) = u32::try_from(flag).ok().and_then(|f| 1u64.checked_shl(f)) {
+            self.aux_flags.fetch_or(bit, Ordering::Relaxed);
+        }
+    }
 }
 
 /// A set of atomic and non-atomic slots for a particular type.
@@ -46,42 +216,201 @@ pub struct Slotset<A, T> {
     /// because thread-local environments aren't implemented and partly
     /// because we sometimes need global non-atomic variables in tests), and
     /// consequently the test writer must assert the safety of each use
-    /// themselves by unwrapping an [UnsafeCell].
+    /// themselves by unwrapping an [`UnsafeCell`].
     pub non_atomic: Vec<UnsafeCell<T>>,
+
+    /// Bitsets (see [`ordering::Ordering::bit`]) of the orderings with
+    /// which each atomic slot has been accessed through [`Slotset::access`].
+    ///
+    /// These survive resets, so they cover the whole run.
+    orderings: Vec<AtomicU8>,
 }
 
 impl<A: Default, T: Default> Slotset<A, T> {
     /// Constructs a new slotset from a slot reservation.
+    #[must_use]
     pub fn new(res: &slot::Reservation<T>) -> Self {
         Self {
             atomic: default_vec(res.atomic),
             non_atomic: default_vec(res.non_atomic),
+            orderings: default_vec(res.atomic),
+        }
+    }
+}
+
+/// Raw pointers to the slots of a [Slotset] (see [`Slotset::raw_parts`]).
+pub struct RawParts<A, T> {
+    /// The first atomic slot; the `i`th is at `atomic.add(i)`.
+    pub atomic: *const A,
+    /// The number of atomic slots.
+    pub atomic_len: usize,
+    /// The first non-atomic slot; the `i`th is at `non_atomic.add(i)`.
+    pub non_atomic: *mut T,
+    /// The number of non-atomic slots.
+    pub non_atomic_len: usize,
+}
+
+// Deriving these would needlessly require `A` and `T` to be `Copy`.
+impl<A, T> Clone for RawParts<A, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A, T> Copy for RawParts<A, T> {}
+
+impl<A, T> Slotset<A, T> {
+    /// Gets raw pointers to the atomic and non-atomic slots of this slotset,
+    /// for tests that need access patterns the slotset doesn't offer, such
+    /// as inline assembly.
+    ///
+    /// A variable's slot index (see [`slot::Slot`]) is its offset from the
+    /// pointer for its atomicity.  Each atomic type here has the same
+    /// in-memory representation as its value: [`AtomicI32`] as `i32`,
+    /// [`AtomicBool`] as `bool`, and [`AtomicF64`] as a `u64` holding the
+    /// bits of an `f64`; so `atomic.cast::<T>()`, or the address in an
+    /// assembly operand, reaches the value itself.  Accesses through the
+    /// pointers don't record orderings (see [`Slotset::access`]).
+    ///
+    /// # Safety
+    ///
+    /// The slots never move while the slotset lives, but the pointers don't
+    /// lift the rules that references to the slotset follow.  Callers must
+    /// make sure that:
+    ///
+    /// - they only access slots while a shared borrow of the slotset would
+    ///   be live: in a test, only during the call to the test body that
+    ///   received the environment, as phenolphthalein resets the slots
+    ///   through an exclusive borrow between iterations;
+    /// - they only access slots in range, as given by the lengths;
+    /// - threads only ever access atomic slots atomically, through the
+    ///   atomic types or instructions that the platform guarantees are
+    ///   atomic for aligned values of that size, as mixing atomic and plain
+    ///   accesses to a slot from different threads is a data race;
+    /// - no thread writes a non-atomic slot while another thread accesses it,
+    ///   as with the [`UnsafeCell`]s behind [`Slotset::non_atomic`];
+    /// - they only write valid values of `T` (for Booleans, only 0 or 1).
+    #[must_use]
+    pub unsafe fn raw_parts(&self) -> RawParts<A, T> {
+        RawParts {
+            atomic: self.atomic.as_ptr(),
+            atomic_len: self.atomic.len(),
+            non_atomic: UnsafeCell::raw_get(self.non_atomic.as_ptr()),
+            non_atomic_len: self.non_atomic.len(),
+        }
+    }
+
+    /// Gets the atomic behind `slot`, if it is an atomic slot in range.
+    ///
+    /// This doesn't record any ordering; see [`Slotset::access`].
+    #[must_use]
+    pub fn atomic_at(&self, slot: slot::Slot) -> Option<&A> {
+        if slot.is_atomic {
+            self.atomic.get(slot.index)
+        } else {
+            None
         }
     }
+
+    /// Gets the atomic behind `slot`, if it is an atomic slot in range,
+    /// recording that the test is accessing it with ordering `order`.
+    #[must_use]
+    pub fn access(&self, slot: slot::Slot, order: Ordering) -> Option<&A> {
+        self.note(slot, order);
+        self.atomic_at(slot)
+    }
+
+    /// Records that the test is accessing `slot` with ordering `order`.
+    ///
+    /// Only the first access with each ordering writes to the record, so
+    /// that recording costs later accesses no more than a relaxed load.
+    pub fn note(&self, slot: slot::Slot, order: Ordering) {
+        if !slot.is_atomic {
+            return;
+        }
+        let (Some(record), Some(order)) = (
+            self.orderings.get(slot.index),
+            ordering::Ordering::of_std(order),
+        ) else {
+            return;
+        };
+        let bit = order.bit();
+        if record.load(Ordering::Relaxed) & bit == 0 {
+            record.fetch_or(bit, Ordering::Relaxed);
+        }
+    }
+
+    /// Gets the orderings with which the test has accessed `slot`.
+    #[must_use]
+    pub fn orderings(&self, slot: slot::Slot) -> ordering::Set {
+        let bits = if slot.is_atomic {
+            self.orderings
+                .get(slot.index)
+                .map_or(0, |r| r.load(Ordering::Relaxed))
+        } else {
+            0
+        };
+        ordering::of_bits(bits)
+    }
 }
 
 /// Trait for things that can be loaded and stored, atomically, to a slot.
 ///
 /// This is mostly just a thin layer over the atomic load/stores in each atomic
-/// type, but forces relaxed semantics.
+/// type, with the ordering chosen by the caller.
 pub trait SlotAtomic<T> {
-    /// Atomically loads a `T` from this variable with relaxed ordering.
-    fn slot_load(&self) -> T;
-    /// Atomically stores a `T` to this variable with relaxed ordering.
-    fn slot_store(&self, val: T);
+    /// Atomically loads a `T` from this variable with ordering `order`.
+    fn slot_load(&self, order: Ordering) -> T;
+    /// Atomically stores a `T` to this variable with ordering `order`.
+    fn slot_store(&self, val: T, order: Ordering);
 }
 
 impl SlotAtomic<i32> for AtomicI32 {
-    fn slot_load(&self) -> i32 {
-        self.load(sync::atomic::Ordering::Relaxed)
+    fn slot_load(&self, order: Ordering) -> i32 {
+        self.load(order)
+    }
+
+    fn slot_store(&self, val: i32, order: Ordering) {
+        self.store(val, order);
+    }
+}
+
+impl SlotAtomic<bool> for AtomicBool {
+    fn slot_load(&self, order: Ordering) -> bool {
+        self.load(order)
+    }
+
+    fn slot_store(&self, val: bool, order: Ordering) {
+        self.store(val, order);
+    }
+}
+
+/// An atomic 64-bit float, implemented by transmuting to and from an
+/// [`AtomicU64`].
+///
+/// The default value is positive zero.  This has the same in-memory
+/// representation as [`AtomicU64`].
+#[derive(Default)]
+#[repr(transparent)]
+pub struct AtomicF64(AtomicU64);
+
+impl SlotAtomic<f64> for AtomicF64 {
+    fn slot_load(&self, order: Ordering) -> f64 {
+        f64::from_bits(self.0.load(order))
     }
 
-    fn slot_store(&self, val: i32) {
-        self.store(val, sync::atomic::Ordering::Relaxed);
+    fn slot_store(&self, val: f64, order: Ordering) {
+        self.0.store(val.to_bits(), order);
     }
 }
 
 impl<A: SlotAtomic<T>, T: Copy + Default> Slotset<A, T> {
+    /// Gets the value in `slot`, or the default if the slot is out of range.
+    ///
+    /// Atomic slots are loaded with relaxed ordering, and the load isn't
+    /// recorded: this is for phenolphthalein's own use while the test
+    /// threads are synchronised.
+    #[must_use]
     pub fn get(&self, slot: slot::Slot) -> T {
         if slot.is_atomic {
             self.get_atomic(slot.index)
@@ -91,7 +420,10 @@ impl<A: SlotAtomic<T>, T: Copy + Default> Slotset<A, T> {
     }
 
     fn get_atomic(&self, index: usize) -> T {
-        self.atomic.get(index).map(A::slot_load).unwrap_or_default()
+        self.atomic
+            .get(index)
+            .map(|a| a.slot_load(Ordering::Relaxed))
+            .unwrap_or_default()
     }
 
     fn get_non_atomic(&self, index: usize) -> T {
@@ -105,10 +437,14 @@ impl<A: SlotAtomic<T>, T: Copy + Default> Slotset<A, T> {
             .unwrap_or_default()
     }
 
+    /// Sets the value in `slot` to `v`, if the slot is in range.
+    ///
+    /// As with [`Slotset::get`], atomic slots are stored with relaxed
+    /// ordering, unrecorded.
     pub fn set(&mut self, slot: slot::Slot, v: T) {
         if slot.is_atomic {
             if let Some(s) = self.atomic.get(slot.index) {
-                s.slot_store(v);
+                s.slot_store(v, Ordering::Relaxed);
             }
         } else if let Some(s) = self.non_atomic.get_mut(slot.index) {
             *s.get_mut() = v;
@@ -125,7 +461,13 @@ fn default_vec<T: Default>(len: usize) -> Vec<T> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{api::abs::test_helpers, err};
+    use crate::{
+        api::abs::{test_helpers, AtomicEnv, Env},
+        err,
+        model::{self, slot},
+        run::testing::{capped_builder, entry_with_i32s},
+    };
+    use std::sync::atomic::Ordering;
 
     #[test]
     /// Tests getting and setting a 32-bit atomic integer.
@@ -138,4 +480,157 @@ mod tests {
     fn test_get_set_i32() -> err::Result<()> {
         test_helpers::test_i32_get_set::<super::Env>(false)
     }
+
+    #[test]
+    /// Tests getting and setting an atomic Boolean.
+    fn test_get_set_atomic_bool() -> err::Result<()> {
+        test_helpers::test_bool_get_set::<super::Env>(true)
+    }
+
+    #[test]
+    /// Tests getting and setting a Boolean.
+    fn test_get_set_bool() -> err::Result<()> {
+        test_helpers::test_bool_get_set::<super::Env>(false)
+    }
+
+    #[test]
+    /// Tests getting and setting an atomic 64-bit float.
+    fn test_get_set_atomic_f64() -> err::Result<()> {
+        test_helpers::test_f64_get_set::<super::Env>(true)
+    }
+
+    #[test]
+    /// Tests getting and setting a 64-bit float.
+    fn test_get_set_f64() -> err::Result<()> {
+        test_helpers::test_f64_get_set::<super::Env>(false)
+    }
+
+    #[test]
+    /// Tests atomic operations on 32-bit integers.
+    fn test_i32_atomic_ops() -> err::Result<()> {
+        test_helpers::test_i32_atomic_ops::<super::Env>()
+    }
+
+    #[test]
+    /// Tests atomic operations on Booleans.
+    fn test_bool_atomic_ops() -> err::Result<()> {
+        test_helpers::test_bool_atomic_ops::<super::Env>()
+    }
+
+    #[test]
+    /// Tests that atomic operations on non-atomic slots leave them alone.
+    fn test_atomic_ops_on_non_atomic() -> err::Result<()> {
+        let slot = slot::Slot {
+            is_atomic: false,
+            index: 0,
+        };
+        let reservation = slot::ReservationSet {
+            i32s: slot::Reservation::of_slots(std::iter::once(slot)),
+            ..slot::ReservationSet::default()
+        };
+        let mut env = super::Env::of_reservations(reservation)?;
+        env.set_i32(slot, 7);
+        assert_eq!(0, env.fetch_add_i32(slot, 1, Ordering::SeqCst));
+        assert_eq!(7, env.get_i32(slot));
+        Ok(())
+    }
+
+    #[test]
+    /// Tests that accesses through raw parts reach the slots, including
+    /// through pointers cast to the slots' value types.
+    fn test_raw_parts() -> err::Result<()> {
+        let atomic = slot::Slot {
+            is_atomic: true,
+            index: 1,
+        };
+        let plain = slot::Slot {
+            is_atomic: false,
+            index: 0,
+        };
+        let reservation = slot::ReservationSet {
+            i32s: slot::Reservation::of_slots([atomic, plain].into_iter()),
+            f64s: slot::Reservation::of_slots(std::iter::once(atomic)),
+            ..slot::ReservationSet::default()
+        };
+        let env = super::Env::of_reservations(reservation)?;
+        let raw = unsafe { env.raw_parts() };
+        assert_eq!((raw.i32s.atomic_len, raw.i32s.non_atomic_len), (2, 1));
+        assert_eq!((raw.bools.atomic_len, raw.bools.non_atomic_len), (0, 0));
+        unsafe {
+            (*raw.i32s.atomic.add(1)).store(5, Ordering::SeqCst);
+            raw.i32s.non_atomic.write(7);
+            raw.f64s.atomic.add(1).cast::<f64>().cast_mut().write(0.5);
+        }
+        assert_eq!(5, env.get_i32(atomic));
+        assert_eq!(7, env.get_i32(plain));
+        assert!((env.get_f64(atomic) - 0.5).abs() < f64::EPSILON);
+        Ok(())
+    }
+
+    #[test]
+    /// Tests that atomic operations record their orderings, but phph's own
+    /// accesses don't.
+    fn test_atomic_ops_record_orderings() -> err::Result<()> {
+        use crate::model::ordering::Ordering as O;
+        let slot = slot::Slot {
+            is_atomic: true,
+            index: 0,
+        };
+        let reservation = slot::ReservationSet {
+            bools: slot::Reservation::of_slots(std::iter::once(slot)),
+            ..slot::ReservationSet::default()
+        };
+        let mut env = super::Env::of_reservations(reservation)?;
+        env.set_bool(slot, true);
+        assert!(env.get_bool(slot));
+        assert!(env.bool_orderings(slot).is_empty());
+
+        env.store_bool(slot, false, Ordering::Release);
+        let _ = env.compare_exchange_bool(slot, false, true, Ordering::SeqCst, Ordering::Acquire);
+        let _ = env.load_bool(slot, Ordering::Acquire);
+        assert_eq!(
+            env.bool_orderings(slot),
+            [O::Acquire, O::Release, O::SeqCst].into()
+        );
+        assert!(env.i32_orderings(slot).is_empty());
+        Ok(())
+    }
+
+    /// Tests that read-modify-write operations on the environment don't lose
+    /// updates between threads.
+    #[test]
+    fn test_env_read_modify_writes() -> err::Result<()> {
+        let mut entry = entry_with_i32s(3, &[("x", true, Some(0)), ("y", true, Some(0))]);
+        entry.test = |_, env: &super::Env| {
+            let x = slot::Slot {
+                is_atomic: true,
+                index: 0,
+            };
+            let mut current = env.load_i32(x, Ordering::Relaxed);
+            while let Err(actual) = env.compare_exchange_i32(
+                x,
+                current,
+                current + 1,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                current = actual;
+            }
+            env.fetch_add_i32(slot::Slot { index: 1, ..x }, 1, Ordering::Relaxed);
+        };
+        let report = capped_builder(entry, 20).build()?.run()?;
+
+        assert_eq!(report.states.len(), 1);
+        let state = &report.states[0].state;
+        assert_eq!(state["x"], model::state::Value::I32(3));
+        assert_eq!(state["y"], model::state::Value::I32(3));
+
+        let (relaxed, acq_rel) = (
+            model::ordering::Ordering::Relaxed,
+            model::ordering::Ordering::AcqRel,
+        );
+        assert_eq!(report.orderings["x"], [relaxed, acq_rel].into());
+        assert_eq!(report.orderings["y"], [relaxed].into());
+        Ok(())
+    }
 }
@@ -1,43 +1,328 @@
 #[macro_use]
 extern crate clap;
 
-use std::{fs::File, io::Read, iter::once, path, str::FromStr};
+use std::{
+    fs::File,
+    io::Write,
+    iter::once,
+    num::NonZeroUsize,
+    path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use phenolphthalein::{
-    api::{self, abs::Test, c},
-    config, model, run,
+    api::{self, c},
+    config, err, model, run,
     ux::{self, out::Outputtable},
 };
 
-use clap::{App, Arg};
+use anyhow::Context;
+use clap::{App, AppSettings, Arg, SubCommand};
 
 fn main() {
-    if let Err(e) = run(app().get_matches()) {
-        eprintln!("{:#}", e);
+    let (format, result) = match app().get_matches_safe() {
+        Ok(matches) => match ux::clap::error_format(&matches) {
+            Ok(format) => (format, run(matches)),
+            Err(e) => (ux::diag::Format::default(), Err(e.into())),
+        },
+        Err(e) => (raw_error_format(), clap_error(e)),
+    };
+    if let Err(e) = result {
+        // If we can't even report the error, there's nothing left to do.
+        let _ = ux::diag::report(format, &e, std::io::stderr());
         std::process::exit(1)
     }
 }
 
+/// Handles a clap error, exiting immediately if it is a help or version
+/// request.
+fn clap_error(e: clap::Error) -> anyhow::Result<()> {
+    match e.kind {
+        clap::ErrorKind::HelpDisplayed | clap::ErrorKind::VersionDisplayed => e.exit(),
+        _ => Err(e.into()),
+    }
+}
+
+/// Scans the raw arguments for an error format, for use when clap itself
+/// failed to parse them.
+fn raw_error_format() -> ux::diag::Format {
+    let flag = format!("--{}", ux::clap::arg::ERROR_FORMAT);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match arg.strip_prefix(&flag) {
+            Some("") => args.next(),
+            Some(rest) => rest.strip_prefix('=').map(ToOwned::to_owned),
+            None => None,
+        };
+        if let Some(format) = value.and_then(|v| v.parse().ok()) {
+            return format;
+        }
+    }
+    ux::diag::Format::default()
+}
+
 fn app<'a, 'b>() -> App<'a, 'b> {
     App::new("phenolphthalein")
         .author(crate_authors!())
         .version(crate_version!())
         .about("Concurrency test runner")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .setting(AppSettings::VersionlessSubcommands)
         .arg(
-            Arg::with_name(ux::clap::arg::CHECK)
-                .help("Checking strategy to use")
-                .short("-C")
-                .long("--check")
-                .value_name("STRATEGY")
-                .possible_values(config::check::string::ALL),
+            Arg::with_name(ux::clap::arg::CONFIG)
+                .help("Load config from this file")
+                .long("--config")
+                .short("-c")
+                .value_name("FILE")
+                .global(true),
         )
         .arg(
-            Arg::with_name(ux::clap::arg::PERMUTE)
-                .help("Permuting strategy to use")
-                .short("-P")
-                .long("--permute")
-                .value_name("STRATEGY")
-                .possible_values(config::permute::string::ALL),
+            Arg::with_name(ux::clap::arg::ERROR_FORMAT)
+                .help("Format in which to report errors")
+                .long("--error-format")
+                .value_name("FORMAT")
+                .possible_values(ux::diag::string::ALL)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name(ux::clap::arg::LOG_LEVEL)
+                .help("Log runner events up to this level to stderr")
+                .long("--log-level")
+                .value_name("LEVEL")
+                .possible_values(ux::log::string::LEVELS)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name(ux::clap::arg::LOG_FORMAT)
+                .help("Format in which to log runner events")
+                .long("--log-format")
+                .value_name("FORMAT")
+                .possible_values(ux::log::string::FORMATS)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name(ux::clap::arg::VERBOSE_LOAD)
+                .help("Print what probing each test library finds before loading it")
+                .long("--verbose-load")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name(ux::clap::arg::PROFILE)
+                .help("Apply this named profile from the config file")
+                .long("--profile")
+                .value_name("NAME")
+                .global(true),
+        )
+        .subcommand(run_cmd())
+        .subcommand(config_cmd())
+        .subcommand(bench_cmd())
+        .subcommand(selftest_cmd())
+        .subcommand(sweep_cmd())
+        .subcommand(diff_cmd())
+        .subcommand(inspect_cmd())
+        .subcommand(merge_cmd())
+        .subcommand(recover_cmd())
+        .subcommand(
+            SubCommand::with_name(ux::clap::cmd::HEADER)
+                .about("Prints the phenol.h header matching this version's C ABI"),
+        )
+}
+
+/// The `run` subcommand, which runs a test.
+fn run_cmd<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(ux::clap::cmd::RUN)
+        .about("Runs a test")
+        .args(&test_args())
+        .args(&output_args())
+        .arg(
+            Arg::with_name(ux::clap::arg::DISCOVERY_LOG)
+                .help("Sample distinct-state counts into this file while running")
+                .long("--discovery-log")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name(ux::clap::arg::DISCOVERY_EVERY)
+                .help("Iterations between discovery log samples")
+                .long("--discovery-every")
+                .value_name("NUM")
+                .requires(ux::clap::arg::DISCOVERY_LOG),
+        )
+        .arg(
+            Arg::with_name(ux::clap::arg::EXPECT)
+                .help("Compare observed states against this list of model-allowed states (eg herd7 output)")
+                .long("--expect")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name(ux::clap::arg::SERVE)
+                .help("Stream progress and reports to clients of this socket (tcp:HOST:PORT or unix:PATH)")
+                .long("--serve")
+                .value_name("ADDR"),
+        )
+        .arg(
+            Arg::with_name(ux::clap::arg::SERVE_EVERY)
+                .help("Iterations between progress samples sent to clients")
+                .long("--serve-every")
+                .value_name("NUM")
+                .requires(ux::clap::arg::SERVE),
+        )
+        .arg(
+            Arg::with_name(ux::clap::arg::WATCH)
+                .help("Rerun the tests whenever their libraries change, until control-C")
+                .long("--watch")
+                .conflicts_with(ux::clap::arg::OUTPUT),
+        )
+        .arg(
+            Arg::with_name(ux::clap::arg::INPUT)
+                .help("The input files (.so, .dylib), directories, or patterns to use")
+                .required(true)
+                .multiple(true)
+                .index(1),
+        )
+}
+
+/// The `inspect` subcommand, which describes a test without running it.
+fn inspect_cmd<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(ux::clap::cmd::INSPECT)
+        .about("Describes a test's threads, variables, and checker without running it")
+        .args(&output_args())
+        .arg(
+            Arg::with_name(ux::clap::arg::INPUT)
+                .help("The input file (.so, .dylib) to inspect")
+                .required(true)
+                .index(1),
+        )
+}
+
+/// The `merge` subcommand, which merges reports from several runs.
+fn merge_cmd<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(ux::clap::cmd::MERGE)
+        .about("Merges JSON reports from several runs of the same test")
+        .args(&output_args())
+        .arg(
+            Arg::with_name(ux::clap::arg::INPUT)
+                .help("The JSON reports to merge")
+                .required(true)
+                .multiple(true)
+                .index(1),
+        )
+}
+
+/// The `recover` subcommand, which outputs the checkpoint of a run that died.
+fn recover_cmd<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(ux::clap::cmd::RECOVER)
+        .about("Outputs, then removes, the checkpoint left by a run of a test that died")
+        .args(&output_args())
+        .arg(
+            Arg::with_name(ux::clap::arg::CHECKPOINT_DIR)
+                .help("Look for the checkpoint in DIR")
+                .long("--checkpoint-dir")
+                .value_name("DIR"),
+        )
+        .arg(
+            Arg::with_name(ux::clap::arg::INPUT)
+                .help("The input file (.so, .dylib) whose checkpoint to recover")
+                .required(true)
+                .index(1),
+        )
+}
+
+/// The arguments selecting output formats and destinations.
+fn output_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name(ux::clap::arg::OUTPUT_TYPE)
+            .help("Type of output to return on stdout")
+            .long("--output-type")
+            .short("-O")
+            .value_name("TYPE")
+            .possible_values(&ux::out::registry::stream_names()),
+        Arg::with_name(ux::clap::arg::OUTPUT)
+            .help("Output as TYPE to PATH (or stdout); can be repeated")
+            .long("--output")
+            .short("-o")
+            .value_name("TYPE[:PATH]")
+            .multiple(true)
+            .number_of_values(1)
+            .conflicts_with(ux::clap::arg::OUTPUT_TYPE),
+        Arg::with_name(ux::clap::arg::PERCENT)
+            .help("Show each state's percentage of all occurrences in histograms")
+            .long("--percent"),
+        Arg::with_name(ux::clap::arg::CUMULATIVE)
+            .help("Show cumulative percentages in histograms, most common state first")
+            .long("--cumulative"),
+        Arg::with_name(ux::clap::arg::KEY_ORDER)
+            .help("Order in which to show the variables of states")
+            .long("--key-order")
+            .value_name("ORDER")
+            .possible_values(model::state::string::ALL),
+    ]
+}
+
+/// The `config` subcommand, which inspects the tester config.
+fn config_cmd<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(ux::clap::cmd::CONFIG)
+        .about("Inspects the tester config")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name(ux::clap::cmd::DUMP)
+                .about("Dumps the config, including any overrides given here")
+                .args(&test_args()),
+        )
+        .subcommand(
+            SubCommand::with_name(ux::clap::cmd::PATH)
+                .about("Dumps the path from which the config is loaded"),
+        )
+}
+
+/// The long help for the `--sync` argument, which explains the tradeoffs.
+const SYNC_HELP: &str = "Synchronisation strategy to use.
+
+Strategies that keep threads in tighter lockstep tend to show more weak \
+behaviour, but waste CPU time, and run badly when threads outnumber cores:
+
+- spinner (default): busy-waits on a counter; the config's [spinner] table \
+can disable the CPU spin hint (hint = false) for slightly tighter lockstep, \
+or yield every so many spins (yield_after) or park on a futex after so many \
+spins (park_after) for throughput when oversubscribed;
+- spin-barrier: busy-waits on a spinning barrier;
+- barrier: blocks on an OS barrier, which is gentlest on the machine but \
+slowest, and lets threads drift apart;
+- backoff: spins, then yields, then parks, as tuned by the config's \
+[backoff] table.
+
+`phph bench` measures each strategy's throughput on this machine.";
+
+/// The `bench` subcommand, which benchmarks synchronisers.
+fn bench_cmd<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(ux::clap::cmd::BENCH)
+        .about("Benchmarks each synchronisation strategy")
+        .arg(
+            Arg::with_name(ux::clap::arg::BENCH_THREADS)
+                .help("Number of threads to use when benchmarking")
+                .long("--threads")
+                .value_name("NUM"),
+        )
+        .arg(
+            Arg::with_name(ux::clap::arg::BENCH_DURATION)
+                .help("Seconds for which to benchmark each synchronisation strategy")
+                .long("--duration")
+                .value_name("SECS"),
+        )
+}
+
+/// The `selftest` subcommand, which runs the built-in litmus tests.
+fn selftest_cmd<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(ux::clap::cmd::SELFTEST)
+        .about("Runs built-in litmus tests, reporting which relaxed behaviours show up")
+        .arg(
+            Arg::with_name(ux::clap::arg::SELFTEST_ITERATIONS)
+                .help("Number of iterations for which to run each test")
+                .long("--iterations")
+                .value_name("NUM"),
         )
         .arg(
             Arg::with_name(ux::clap::arg::SYNC)
@@ -47,72 +332,348 @@ fn app<'a, 'b>() -> App<'a, 'b> {
                 .value_name("STRATEGY")
                 .possible_values(config::sync::string::ALL),
         )
+}
+
+/// The `sweep` subcommand, which runs a test under several profiles.
+fn sweep_cmd<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(ux::clap::cmd::SWEEP)
+        .about("Runs a test under several profiles, giving more time to those finding more states")
+        .args(&test_args())
+        .args(&output_args())
         .arg(
-            Arg::with_name(ux::clap::arg::ITERATIONS)
-                .help("Iterations to perform in total")
-                .short("-i")
-                .long("--iterations")
-                .value_name("NUM"),
-        )
-        .arg(
-            Arg::with_name(ux::clap::arg::PERIOD)
-                .help("rotate threads after each NUM iterations")
-                .short("-p")
-                .long("--period")
-                .value_name("NUM"),
+            Arg::with_name(ux::clap::arg::SWEEP_PROFILES)
+                .help("Profiles to sweep across (default: every profile in the config)")
+                .long("--profiles")
+                .value_name("NAME,...")
+                .use_delimiter(true)
+                .multiple(true),
         )
         .arg(
-            Arg::with_name(ux::clap::arg::CONFIG)
-                .help("Load config from this file")
-                .long("--config")
-                .short("-c"),
+            Arg::with_name(ux::clap::arg::SWEEP_TOTAL_TIME)
+                .help("Seconds for which to sweep in total")
+                .long("--total-time")
+                .value_name("SECS"),
         )
         .arg(
-            Arg::with_name(ux::clap::arg::DUMP_CONFIG)
-                .help("Dump config instead of testing")
-                .long("--dump-config"),
+            Arg::with_name(ux::clap::arg::SWEEP_SLICE_MS)
+                .help("Milliseconds in each slice of the sweep (default: a twentieth of the total)")
+                .long("--slice-ms")
+                .value_name("MS"),
         )
         .arg(
-            Arg::with_name(ux::clap::arg::DUMP_CONFIG_PATH)
-                .help("Dump config path instead of testing")
-                .conflicts_with(ux::clap::arg::DUMP_CONFIG)
-                .long("--dump-config-path"),
+            Arg::with_name(ux::clap::arg::SWEEP_POLICY)
+                .help("How to divide the time between profiles")
+                .long("--policy")
+                .value_name("POLICY")
+                .possible_values(run::sweep::string::ALL),
         )
         .arg(
-            Arg::with_name(ux::clap::arg::OUTPUT_TYPE)
-                .help("Type of output to return")
-                .long("--output-type")
-                .short("-O")
-                .value_name("TYPE")
-                .possible_values(ux::out::config::string::ALL),
+            Arg::with_name(ux::clap::arg::INPUT)
+                .help("The input file (.so, .dylib) to sweep")
+                .required(true)
+                .index(1),
         )
+}
+
+/// The `diff` subcommand, which runs two builds of a test alongside each
+/// other.
+fn diff_cmd<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(ux::clap::cmd::DIFF)
+        .about("Runs two builds of a test under the same permutations, reporting states only one showed")
+        .args(&test_args())
+        .args(&output_args())
         .arg(
             Arg::with_name(ux::clap::arg::INPUT)
-                .help("The input file (.so, .dylib) to use")
-                .conflicts_with_all(&[ux::clap::arg::DUMP_CONFIG, ux::clap::arg::DUMP_CONFIG_PATH])
+                .help("The two input files (.so, .dylib) to compare")
+                .required(true)
+                .min_values(2)
+                .max_values(2)
                 .index(1),
         )
 }
 
+/// Arguments that override test parameters in the config.
+fn test_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name(ux::clap::arg::CHECK)
+            .help("Checking strategy to use")
+            .short("-C")
+            .long("--check")
+            .value_name("STRATEGY")
+            .possible_values(config::check::string::ALL),
+        Arg::with_name(ux::clap::arg::CHECK_DESCRIPTION)
+            .help("Describe what the checks check as TEXT in reports")
+            .long("--check-description")
+            .value_name("TEXT"),
+        Arg::with_name(ux::clap::arg::EXPECT_OUTCOME)
+            .help("Fail the run unless its checks give OUTCOME: fail (something failed) or pass (nothing did)")
+            .long("--expect-outcome")
+            .value_name("OUTCOME")
+            .possible_values(model::expectation::string::ALL),
+        Arg::with_name(ux::clap::arg::CHECK_FREQUENCY)
+            .help("When to check new states: immediate, every-N iterations, or deferred to the end")
+            .long("--check-frequency")
+            .value_name("WHEN"),
+        Arg::with_name(ux::clap::arg::PERMUTE)
+            .help("Permuting strategy to use")
+            .short("-P")
+            .long("--permute")
+            .value_name("STRATEGY")
+            .possible_values(config::permute::string::ALL),
+        Arg::with_name(ux::clap::arg::REPLAY)
+            .help("Replay the permutation schedule recorded in this file")
+            .long("--replay")
+            .value_name("FILE")
+            .conflicts_with(ux::clap::arg::PERMUTE),
+        Arg::with_name(ux::clap::arg::SYNC)
+            .help("Synchronisation strategy to use")
+            .long_help(SYNC_HELP)
+            .short("-s")
+            .long("--sync")
+            .value_name("STRATEGY")
+            .possible_values(config::sync::string::ALL),
+        Arg::with_name(ux::clap::arg::OBSERVER_SEED)
+            .help("choose each iteration's observer pseudo-randomly from SEED")
+            .long("--observer-seed")
+            .value_name("SEED"),
+        Arg::with_name(ux::clap::arg::SEED)
+            .help("derive fuzzing, random permutations, skews, and sampling from SEED")
+            .long("--seed")
+            .value_name("SEED"),
+        Arg::with_name(ux::clap::arg::ALTERNATE_SYNC)
+            .help("alternate the synchronisation strategy with STRATEGY, comparing their states")
+            .long("--alternate-sync")
+            .value_name("STRATEGY")
+            .possible_values(config::sync::string::ALL),
+        Arg::with_name(ux::clap::arg::ALTERNATE_EVERY)
+            .help("switch synchronisation strategies after each NUM rotations")
+            .long("--alternate-every")
+            .value_name("NUM")
+            .requires(ux::clap::arg::ALTERNATE_SYNC),
+        Arg::with_name(ux::clap::arg::ITERATIONS)
+            .help("Iterations to perform in total")
+            .short("-i")
+            .long("--iterations")
+            .value_name("NUM"),
+        Arg::with_name(ux::clap::arg::PERIOD)
+            .help("rotate threads after each NUM iterations")
+            .short("-p")
+            .long("--period")
+            .value_name("NUM"),
+        Arg::with_name(ux::clap::arg::PERIOD_MS)
+            .help("rotate threads after each MS milliseconds")
+            .long("--period-ms")
+            .value_name("MS"),
+        Arg::with_name(ux::clap::arg::WARMUP)
+            .help("run K iterations before observing the test")
+            .long("--warmup")
+            .value_name("K"),
+        Arg::with_name(ux::clap::arg::MIN_RATE)
+            .help("exit if the test runs fewer than RATE iterations per second")
+            .long("--min-rate")
+            .value_name("RATE"),
+        Arg::with_name(ux::clap::arg::RATE_WINDOW_MS)
+            .help("measure the iteration rate over the last MS milliseconds")
+            .long("--rate-window-ms")
+            .value_name("MS")
+            .requires(ux::clap::arg::MIN_RATE),
+        Arg::with_name(ux::clap::arg::TIME_EVERY)
+            .help("time each thread's test body on every NUM iterations")
+            .long("--time-every")
+            .value_name("NUM"),
+        Arg::with_name(ux::clap::arg::PAUSE_EVERY)
+            .help("pause every thread after each NUM iterations, to keep the machine cool")
+            .long("--pause-every")
+            .value_name("NUM"),
+        Arg::with_name(ux::clap::arg::PAUSE_MS)
+            .help("pause for MS milliseconds each time (default 50)")
+            .long("--pause-ms")
+            .value_name("MS")
+            .requires(ux::clap::arg::PAUSE_EVERY),
+        Arg::with_name(ux::clap::arg::SAMPLE_EVERY)
+            .help("only observe every NUM iterations, approximating the state counts")
+            .long("--sample-every")
+            .value_name("NUM")
+            .conflicts_with(ux::clap::arg::SAMPLE_PROBABILITY),
+        Arg::with_name(ux::clap::arg::SAMPLE_PROBABILITY)
+            .help("only observe each iteration with probability P, approximating the state counts")
+            .long("--sample-probability")
+            .value_name("P"),
+        Arg::with_name(ux::clap::arg::SAMPLE_SEED)
+            .help("seed the random choices of --sample-probability with SEED")
+            .long("--sample-seed")
+            .value_name("SEED")
+            .requires(ux::clap::arg::SAMPLE_PROBABILITY),
+        Arg::with_name(ux::clap::arg::CANARY)
+            .help("fill non-atomic variables with canary values to detect torn reads")
+            .long("--canary"),
+        Arg::with_name(ux::clap::arg::EXAMPLES)
+            .help("record the context of the first iteration to show each state")
+            .long("--examples"),
+        Arg::with_name(ux::clap::arg::TRANSITIONS)
+            .help("record how often each state follows each other state")
+            .long("--transitions"),
+        Arg::with_name(ux::clap::arg::SEGMENT_ROTATIONS)
+            .help("also count how often each state occurs in each rotation")
+            .long("--segment-rotations"),
+        Arg::with_name(ux::clap::arg::AUDIT_ALLOCATIONS)
+            .help("count the allocations made between iterations (needs the alloc-audit feature)")
+            .long("--audit-allocations"),
+        Arg::with_name(ux::clap::arg::MARKERS)
+            .help("mark run and rotation boundaries in the kernel trace for profilers (Linux only)")
+            .long("--markers"),
+        Arg::with_name(ux::clap::arg::VERIFY_MANIFEST)
+            .help("fail if the test changes its manifest mid-run (checked between rotations)")
+            .long("--verify-manifest"),
+        Arg::with_name(ux::clap::arg::FOLD_SYMMETRIC)
+            .help("count states that differ only by swapping symmetric threads as one")
+            .long("--fold-symmetric"),
+        Arg::with_name(ux::clap::arg::EXIT_ON_INTERESTING)
+            .help("exit once every interesting state in the config has been seen")
+            .long("--exit-on-interesting"),
+        Arg::with_name(ux::clap::arg::OMIT_PLATFORM)
+            .help("leave the machine's CPU topology, OS, and architecture out of reports")
+            .long("--omit-platform"),
+        Arg::with_name(ux::clap::arg::SHRINK)
+            .help("after a run exits on a failure, look for a smaller budget that reproduces it")
+            .long("--shrink"),
+        Arg::with_name(ux::clap::arg::CHECKPOINT_MS)
+            .help("checkpoint observations every MS milliseconds, to recover if the run dies")
+            .long("--checkpoint-ms")
+            .value_name("MS"),
+        Arg::with_name(ux::clap::arg::CHECKPOINT_DIR)
+            .help("keep checkpoints in DIR, rather than the temporary directory")
+            .long("--checkpoint-dir")
+            .value_name("DIR"),
+        Arg::with_name(ux::clap::arg::LIMIT_CPU_SECS)
+            .help("stop the run once the process has used SECS of CPU time")
+            .long("--limit-cpu-secs")
+            .value_name("SECS"),
+        Arg::with_name(ux::clap::arg::LIMIT_MEMORY_BYTES)
+            .help("stop the run once the process's address space grows beyond BYTES")
+            .long("--limit-memory-bytes")
+            .value_name("BYTES"),
+        Arg::with_name(ux::clap::arg::GUARD)
+            .help("how to respond to tests that fork or spawn threads of their own")
+            .long("--guard")
+            .value_name("MODE")
+            .possible_values(config::guard::string::ALL),
+        Arg::with_name(ux::clap::arg::RESET)
+            .help("when to reset variables to their initial values")
+            .long("--reset")
+            .value_name("WHEN")
+            .possible_values(config::reset::string::ALL),
+        Arg::with_name(ux::clap::arg::SEQUENTIAL)
+            .help("run test threads one after another on one OS thread, in these orders")
+            .long("--sequential")
+            .value_name("MODE")
+            .possible_values(config::sequential::string::ALL),
+        Arg::with_name(ux::clap::arg::MASK)
+            .help("leave VAR out of observed states; can be repeated")
+            .long("--mask")
+            .value_name("VAR")
+            .multiple(true)
+            .number_of_values(1),
+        Arg::with_name(ux::clap::arg::THREADS)
+            .help("run tests with N threads; fails unless a test declares N or has a scalable role")
+            .long("--threads")
+            .alias("n-threads")
+            .value_name("N"),
+        Arg::with_name(ux::clap::arg::CPUS)
+            .help("restrict all test threads to CPUS, such as 0-3,6, without pinning each")
+            .long("--cpus")
+            .value_name("CPUS"),
+    ]
+}
+
 fn run(matches: clap::ArgMatches) -> anyhow::Result<()> {
-    use ux::clap::{Action, Clappable};
+    use ux::clap::{Action, Clappable, ConfigAction};
 
-    let cpath = ux::clap::config_file(&matches)?;
-    let config = load_config(&cpath)?.parse_clap(&matches)?;
+    if let Some((level, format)) = ux::clap::log_settings(&matches)? {
+        ux::log::init(level, format);
+    }
+    VERBOSE_LOAD.store(ux::clap::verbose_load(&matches), Ordering::Relaxed);
+    let (action, sub) = Action::from_clap(&matches)?;
+    let cpath = ux::clap::config_file(sub)?;
+    let config = load_config(&cpath, ux::clap::profile(sub))?.parse_clap(sub)?;
+    config.validate()?;
 
-    match ux::clap::Action::DumpConfig.parse_clap(&matches)? {
-        Action::DumpConfig => config.dump()?,
-        Action::DumpConfigPath => dump_config_path(&cpath),
-        Action::RunTest(path, outputter) => run_test(config, &path)?.output(outputter)?,
+    match action {
+        Action::Run(mut args) => {
+            args.output = configure_output(&config, args.output);
+            apply_limits(&config)?;
+            run_tests(&config, args)?;
+        }
+        Action::Config(ConfigAction::Dump) => config.dump()?,
+        Action::Config(ConfigAction::Path) => dump_config_path(&cpath),
+        Action::Bench(args) => bench_sync(&config, args.threads, args.duration)?,
+        Action::Selftest(args) => selftest(&config, args.iterations)?,
+        Action::Sweep(mut args) => {
+            args.output = configure_output(&config, args.output);
+            apply_limits(&config)?;
+            sweep(&cpath, sub, args)?;
+        }
+        Action::Diff(mut args) => {
+            args.output = configure_output(&config, args.output);
+            apply_limits(&config)?;
+            diff(&config, args)?;
+        }
+        Action::Inspect(args) => inspect(&args.input, configure_output(&config, args.output))?,
+        Action::Merge(args) => merge(
+            &config,
+            &args.inputs,
+            configure_output(&config, args.output),
+        )?,
+        Action::Recover(args) => {
+            recover(&config, &args.input, configure_output(&config, args.output))?
+        }
+        Action::Header => print!("{}", c::header()),
     };
     Ok(())
 }
 
-fn load_config(path: &path::Path) -> anyhow::Result<config::Config> {
+/// Fills in the parts of `output` that come from the tester config.
+fn configure_output(config: &config::Config, output: ux::out::Config) -> ux::out::Config {
+    let condition = ux::out::litmus::Condition::from_interesting(&config.interesting);
+    output
+        .with_formats(config.format.clone())
+        .or_key_order(config.key_order)
+        .with_litmus(ux::out::litmus::Metadata {
+            name: None,
+            condition,
+        })
+}
+
+/// Applies any resource limits in `config` to the process.
+///
+/// This must happen before loading any test library, so that the limits
+/// cover its code too.
+fn apply_limits(config: &config::Config) -> anyhow::Result<()> {
+    let limits = config.limits.to_limits();
+    if !limits.is_empty() {
+        limits.apply()?;
+    }
+    Ok(())
+}
+
+/// Whether to print probe results before loading each test library.
+static VERBOSE_LOAD: AtomicBool = AtomicBool::new(false);
+
+/// Loads the test library at `input`, first printing what probing it finds
+/// if `--verbose-load` is on.
+fn load_test(input: &path::Path) -> anyhow::Result<c::Test> {
+    if VERBOSE_LOAD.load(Ordering::Relaxed) {
+        eprint!("{}", c::probe::Probe::of_file(input));
+    }
+    Ok(c::Test::load(input)?)
+}
+
+fn load_config(path: &path::Path, profile: Option<&str>) -> anyhow::Result<config::Config> {
     if path.exists() {
-        let mut buf = String::new();
-        let _ = File::open(path)?.read_to_string(&mut buf)?;
-        Ok(config::Config::from_str(&buf)?)
+        Ok(config::Config::load(path, profile)?)
+    } else if let Some(name) = profile {
+        // There are no profiles if there is no config file.
+        Err(config::Error::UnknownProfile(name.to_owned()).into())
     } else {
         Ok(config::Config::default())
     }
@@ -122,27 +683,659 @@ fn dump_config_path(path: &path::Path) {
     println!("{}", path.to_string_lossy())
 }
 
-fn run_test(config: config::Config, input: &path::Path) -> anyhow::Result<model::Report> {
-    let test = c::Test::load(input)?;
-    run_entry(config, test.spawn())
+/// Benchmarks each synchronisation strategy, printing iterations per second.
+fn bench_sync(
+    config: &config::Config,
+    threads: NonZeroUsize,
+    duration: Duration,
+) -> anyhow::Result<()> {
+    let mut w = tabwriter::TabWriter::new(std::io::stdout()).padding(1);
+    writeln!(w, "allocator\t{}", run::alloc::NAME)?;
+    for strategy in config::sync::Strategy::all() {
+        let m = run::bench::measure(
+            strategy.to_factory(&config.spinner, &config.backoff),
+            threads,
+            duration,
+        )?;
+        writeln!(
+            w,
+            "{}\t{:.0} iter/s\t({} iters)",
+            strategy,
+            m.rate(),
+            m.iterations
+        )?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// Runs each built-in litmus test, printing how often it showed its weak
+/// behaviour.
+fn selftest(config: &config::Config, iterations: NonZeroUsize) -> anyhow::Result<()> {
+    let mut w = tabwriter::TabWriter::new(std::io::stdout()).padding(1);
+    for test in run::selftest::tests() {
+        let obs = test.run(config.sync_factory(), iterations)?;
+        let condition = ux::out::litmus::Condition::Exists(vec![test.weak])
+            .to_litmus()
+            .unwrap_or_default();
+        writeln!(
+            w,
+            "{}\t{}\t{}\t({} of {} iters)",
+            test.name,
+            condition,
+            if obs.is_weak() { "seen" } else { "not seen" },
+            obs.weak,
+            obs.iterations
+        )?;
+    }
+    w.flush()?;
+    Ok(())
 }
 
+/// Sweeps the test in `args` across profiles of the config at `cpath`,
+/// outputting a suite of one report per profile.
+///
+/// Each profile's config takes the same command-line overrides, from `sub`,
+/// as the base config.
+fn sweep(cpath: &path::Path, sub: &clap::ArgMatches, args: ux::clap::Sweep) -> anyhow::Result<()> {
+    use ux::clap::Clappable;
+
+    let names = if args.profiles.is_empty() {
+        config::Config::profiles(cpath)?
+    } else {
+        args.profiles
+    };
+    if names.is_empty() {
+        return Err(ux::err::Error::NoSweepProfiles.into());
+    }
+    let configs = names
+        .iter()
+        .map(|name| {
+            let config = load_config(cpath, Some(name))?.parse_clap(sub)?;
+            config.validate()?;
+            Ok(config)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let test = load_test(&args.input)?;
+    let mut sweep = run::sweep::Sweep::new(args.total_time).with_policy(args.policy.to_policy());
+    if let Some(slice) = args.slice {
+        sweep = sweep.with_slice(slice);
+    }
+    let outcome = sweep.run(names, |i, halt| {
+        run_builder(
+            &configs[i],
+            &args.input,
+            run::Builder::new(test.entry()),
+            halt,
+        )
+    })?;
+
+    let mut w = tabwriter::TabWriter::new(std::io::stderr()).padding(1);
+    writeln!(w, "profile\tslices\ttime\tstates")?;
+    for (stats, _) in &outcome.configs {
+        writeln!(
+            w,
+            "{}\t{}\t{:.2}s\t{}",
+            stats.name,
+            stats.slices,
+            stats.elapsed.as_secs_f64(),
+            stats.states
+        )?;
+    }
+    w.flush()?;
+    Ok(outcome.into_suite().output(args.output)?)
+}
+
+/// Runs the two builds of a test in `args` alongside each other, outputting
+/// how their states compare.
+///
+/// Both builds see the same thread permutations and draw the same fuzzed
+/// initial values.
+fn diff(config: &config::Config, args: ux::clap::Diff) -> anyhow::Result<()> {
+    let halt = setup_ctrlc()?;
+    let (left, right) = (load_test(&args.left)?, load_test(&args.right)?);
+    let fuzz_seed = Some(rand::random());
+    let left_runner = configure_builder(
+        config,
+        &args.left,
+        run::Builder::new(left.entry()).with_fuzz_seed(fuzz_seed),
+        halt.clone(),
+    )?
+    .build()?;
+    let right_runner = configure_builder(
+        config,
+        &args.right,
+        run::Builder::new(right.entry()).with_fuzz_seed(fuzz_seed),
+        halt,
+    )?
+    .build()?;
+    let (mut left_report, mut right_report) = left_runner.run_alongside(right_runner)?;
+    stamp_platform(config, &mut left_report);
+    stamp_platform(config, &mut right_report);
+
+    let mut names = (
+        ux::discover::test_name(&args.left),
+        ux::discover::test_name(&args.right),
+    );
+    // Builds of the same test often differ only by directory.
+    if names.0 == names.1 {
+        names = (
+            args.left.display().to_string(),
+            args.right.display().to_string(),
+        );
+    }
+    let comparison =
+        model::comparison::Comparison::new((names.0, left_report), (names.1, right_report));
+    Ok(comparison.output(args.output)?)
+}
+
+/// Describes the test at `input` without running it.
+fn inspect(input: &path::Path, outputter: ux::out::Config) -> anyhow::Result<()> {
+    use api::abs::Entry;
+
+    let test = load_test(input)?;
+    let entry = test.entry();
+    let manifest = entry.make_manifest()?;
+    let inspection = model::inspect::Inspection {
+        check_description: entry.check_description(),
+        ..model::inspect::Inspection::new(&manifest, entry.capabilities())
+    };
+    Ok(inspection.output(outputter)?)
+}
+
+/// Merges the JSON reports at `inputs`, outputting the merged report.
+fn merge(
+    config: &config::Config,
+    inputs: &[path::PathBuf],
+    outputter: ux::out::Config,
+) -> anyhow::Result<()> {
+    // Starting from the first report, rather than an empty one, keeps the
+    // metadata that every report shares.
+    let mut merged: Option<model::Report> = None;
+    for input in inputs {
+        let file = File::open(input).map_err(|e| ux::err::Error::ReportIo(input.to_owned(), e))?;
+        let report: model::Report = serde_json::from_reader(std::io::BufReader::new(file))
+            .map_err(|e| ux::err::Error::BadReport(input.to_owned(), e))?;
+        if model::report::schema::VERSION < report.schema_version {
+            return Err(
+                ux::err::Error::NewerReport(input.to_owned(), report.schema_version).into(),
+            );
+        }
+        match &mut merged {
+            Some(m) => m.merge(report),
+            None => merged = Some(report),
+        }
+    }
+    let mut merged = merged.unwrap_or_default();
+    // Merging drops each report's aggregate outcomes and projections, as
+    // they held only for that report's share of the states; the configured
+    // rules and projections redo them over the whole distribution.
+    for (name, rule) in &config.aggregate {
+        merged.record_aggregate(name.clone(), rule.check(&merged));
+    }
+    merged.project(&config.projections);
+    Ok(merged.output(outputter)?)
+}
+
+/// Outputs the checkpoint that a dead run of the test at `input` left, then
+/// removes it.
+fn recover(
+    config: &config::Config,
+    input: &path::Path,
+    mut outputter: ux::out::Config,
+) -> anyhow::Result<()> {
+    let name = ux::discover::test_name(input);
+    let path = config.checkpoint_path(&name);
+    let mut report =
+        run::checkpoint::read(&path)?.ok_or_else(|| ux::err::Error::NoCheckpoint(path.clone()))?;
+    // The dead run never got as far as its end-of-run checks.
+    for (name, rule) in &config.aggregate {
+        report.record_aggregate(name.clone(), rule.check(&report));
+    }
+    report.project(&config.projections);
+    outputter.litmus.name = Some(name);
+    report.output(outputter)?;
+    run::checkpoint::remove(&path).map_err(|e| ux::err::Error::CheckpointIo(path, e))?;
+    Ok(())
+}
+
+/// Runs the tests named in `args`, outputting either a single report or a
+/// suite of reports.
+fn run_tests(config: &config::Config, args: ux::clap::Run) -> anyhow::Result<()> {
+    let log = args.discovery_log.as_ref();
+    let observers = (log, args.expected.as_ref(), args.serve.as_ref());
+    let result = if args.watch {
+        watch_tests(config, &args, observers)
+    } else {
+        let inputs = (args.inputs.as_slice(), args.suite);
+        setup_ctrlc().and_then(|halt| run_inputs(config, inputs, halt, observers, args.output))
+    };
+    if let Some(server) = &args.serve {
+        server.finish();
+    }
+    let logged = log.map_or(Ok(()), ux::curve::Log::finish);
+    result?;
+    logged.context("couldn't write discovery log")
+}
+
+/// Runs the tests in `inputs` once, as a suite if `suite` is set.
+fn run_inputs(
+    config: &config::Config,
+    (inputs, suite): (&[path::PathBuf], bool),
+    halt: run::halt::Rule,
+    observers: Observers,
+    outputter: ux::out::Config,
+) -> anyhow::Result<()> {
+    if suite {
+        run_suite(config, inputs, &halt, observers, outputter)
+    } else {
+        run_single(config, inputs, halt, observers, outputter)
+    }
+}
+
+/// The discovery log, model expectations, and server, if any, applying to
+/// each test in a run.
+type Observers<'a> = (
+    Option<&'a ux::curve::Log>,
+    Option<&'a model::conformance::Expected>,
+    Option<&'a ux::serve::Server>,
+);
+
+/// Runs the tests named in `args` in campaigns, starting a fresh campaign
+/// whenever any of their libraries changes, until control-C.
+///
+/// A change during a campaign cuts it short.  Each campaign reloads the
+/// libraries from scratch and outputs its own report; a campaign that fails
+/// reports its error and waits for the next change.
+fn watch_tests(
+    config: &config::Config,
+    args: &ux::clap::Run,
+    observers: Observers,
+) -> anyhow::Result<()> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    // Raised for both control-C and library changes, to stop the campaign.
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let (interrupted, stop) = (interrupted.clone(), stop.clone());
+        ctrlc::set_handler(move || {
+            interrupted.store(true, Ordering::Release);
+            stop.store(true, Ordering::Release);
+        })?;
+    }
+    let halt = run::halt::Condition::OnSignal {
+        flag: stop.clone(),
+        name: "interrupt or change",
+    }
+    .exit();
+    let mut watcher = ux::watch::Watcher::new(args.inputs.clone());
+
+    for campaign in 1.. {
+        stop.store(interrupted.load(Ordering::Acquire), Ordering::Release);
+        eprintln!("== campaign {campaign} ==");
+        let done = AtomicBool::new(false);
+        let result = std::thread::scope(|s| {
+            s.spawn(|| watcher.notify(&stop, &done));
+            let inputs = (args.inputs.as_slice(), args.suite);
+            let result = run_inputs(
+                config,
+                inputs,
+                halt.clone(),
+                observers,
+                args.output.to_stdout(),
+            );
+            done.store(true, Ordering::Release);
+            result
+        });
+        if let Err(e) = result {
+            eprintln!("campaign {campaign} failed: {e:#}");
+        }
+        if interrupted.load(Ordering::Acquire) {
+            break;
+        }
+        eprintln!("== waiting for changes (control-C to stop) ==");
+        if !watcher.wait(&interrupted) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Runs the single test in `inputs`, outputting its report.
+///
+/// The run fails, after outputting the report, if the report doesn't meet
+/// any outcome that `config` expects.
+fn run_single(
+    config: &config::Config,
+    inputs: &[path::PathBuf],
+    halt: run::halt::Rule,
+    observers: Observers,
+    mut outputter: ux::out::Config,
+) -> anyhow::Result<()> {
+    let input = inputs.first().ok_or(ux::err::Error::NoInput)?;
+    outputter.litmus.name = Some(ux::discover::test_name(input));
+    match run_test(config, input, halt, observers) {
+        Ok(report) => {
+            let met = meet_expectation(config, &report);
+            report.output(outputter)?;
+            Ok(met?)
+        }
+        Err(e) => match split_partial(e) {
+            (cause, Some(report)) => Err(aborted(
+                cause,
+                report.output(outputter),
+                "test run aborted; report is partial",
+            )),
+            (cause, None) => Err(cause),
+        },
+    }
+}
+
+/// Runs each test in `inputs` in turn, outputting a suite of their reports.
+///
+/// If a test fails, this stops and outputs the reports gathered so far
+/// (including any partial report from the failing test).  Otherwise, the
+/// run fails, after outputting the suite, if any test's report doesn't meet
+/// any outcome that `config` expects.
+fn run_suite(
+    config: &config::Config,
+    inputs: &[path::PathBuf],
+    halt: &run::halt::Rule,
+    observers: Observers,
+    outputter: ux::out::Config,
+) -> anyhow::Result<()> {
+    let mut suite = model::Suite::default();
+    for input in inputs {
+        let name = ux::discover::test_name(input);
+        match run_test(config, input, halt.clone(), observers) {
+            Ok(report) => suite.insert(&name, report),
+            Err(e) => {
+                let (cause, partial) = split_partial(e);
+                if let Some(report) = partial {
+                    suite.insert(&name, report);
+                }
+                let cause = cause.context(format!("test {name} failed"));
+                return Err(aborted(
+                    cause,
+                    suite.output(outputter),
+                    "suite run aborted; report is partial",
+                ));
+            }
+        }
+    }
+    let met = suite.reports.iter().try_for_each(|(name, report)| {
+        meet_expectation(config, report).with_context(|| format!("test {name} failed"))
+    });
+    suite.output(outputter)?;
+    met
+}
+
+/// Checks that `report` meets the outcome that `config` expects, if any.
+fn meet_expectation(config: &config::Config, report: &model::Report) -> err::Result<()> {
+    config.expect_outcome.map_or(Ok(()), |expected| {
+        expected.check(report).map_err(err::Error::UnmetExpectation)
+    })
+}
+
+fn run_test(
+    config: &config::Config,
+    input: &path::Path,
+    halt: run::halt::Rule,
+    observers: Observers,
+) -> anyhow::Result<model::Report> {
+    if let Some(name) = ux::discover::inline_name(input) {
+        let entry = config.inline_test(name)?;
+        return run_entry(config, input, || entry.clone(), halt, observers);
+    }
+    let test = load_test(input)?;
+    run_entry(config, input, || test.entry(), halt, observers)
+}
+
+/// Runs the test named by `input`, getting entry points to it from `entry`.
 fn run_entry<'a, E: api::abs::Entry<'a>>(
-    config: config::Config,
-    entry: E,
+    config: &config::Config,
+    input: &path::Path,
+    entry: impl Fn() -> E,
+    halt: run::halt::Rule,
+    (log, expected, server): Observers<'a>,
 ) -> anyhow::Result<model::Report> {
-    Ok(run::Builder::new(entry)
-        .add_halt_rules(config.halt_rules().chain(once(setup_ctrlc()?)))
-        .with_checker(config.check.to_factory())
-        .with_permuter(config.permute.to_factory())
-        .with_sync(config.sync.to_factory())
+    let name = ux::discover::test_name(input);
+    let checkpoint = config.checkpoint(&name);
+    if let Some(rule) = checkpoint.as_ref().filter(|r| r.path.exists()) {
+        eprintln!(
+            "resuming test {name} from the checkpoint of a run that died ({})",
+            rule.path.display()
+        );
+    }
+    let mut builder = run::Builder::new(entry()).with_checkpoint(checkpoint);
+    if let Some(log) = log {
+        let name = name.clone();
+        builder = builder.with_hook(move |_, summary| log.record(&name, summary));
+    }
+    if let Some(server) = server {
+        let name = name.clone();
+        builder = builder.with_hook(move |_, summary| server.record(&name, summary));
+    }
+    let mut report = run_builder(config, input, builder, halt.clone())?;
+    if config.shrink {
+        report.reproduction = shrink(config, input, &entry, &report, &halt)?;
+    }
+    if let Some(expected) = expected {
+        report.conformance = Some(model::conformance::Conformance::classify(
+            expected.clone(),
+            &report,
+        ));
+    }
+    if let Some(server) = server {
+        server.report(&name, &report);
+    }
+    Ok(report)
+}
+
+/// Shrinks the run of the test behind `entry` that gave `report`, if it exited on a failure,
+/// to find a quicker way to reproduce the failure.
+///
+/// Each trial halts on the outcome-based rules in `config`, and on `halt`,
+/// but replaces its iteration-based rules with the trial's own.
+fn shrink<'a, E: api::abs::Entry<'a>>(
+    config: &config::Config,
+    input: &path::Path,
+    entry: &impl Fn() -> E,
+    report: &model::Report,
+    halt: &run::halt::Rule,
+) -> anyhow::Result<Option<model::reproduction::Reproduction>> {
+    let failed = model::ending::Cause::Outcome {
+        outcome: model::Outcome::Fail,
+    };
+    if report.ended_by.as_ref() != Some(&failed) {
+        return Ok(None);
+    }
+    // The run stopped on its first failure, so no shorter run saw one.
+    let Some(iterations) = report
+        .states
+        .iter()
+        .filter(|s| s.info.outcome == model::Outcome::Fail)
+        .map(|s| s.info.iteration)
+        .min()
+        .and_then(|i| NonZeroUsize::new(i.saturating_add(1)))
+    else {
+        return Ok(None);
+    };
+    let start = run::shrink::Trial {
+        iterations,
+        period: config.iter.strategy.period(),
+        seed: config.observer_seed.unwrap_or_else(rand::random),
+    };
+    eprintln!("shrinking failure found within {iterations} iterations");
+    let repro = run::shrink::Shrink::new().run(start, |trial| {
+        let rules = config.outcome_halt_rules().chain(once(halt.clone()));
+        let builder =
+            configure_builder_with_rules(config, input, run::Builder::new(entry()), rules)?;
+        let sync = config.sync.to_factory(&config.spinner, &config.backoff);
+        // Reproductions only give a seed for the main synchroniser.
+        trial
+            .apply(builder)
+            .with_sync(run::sync::random_observer_factory(sync, trial.seed))
+            .with_sync_alternation(None)
+            .build()?
+            .run()
+            .map_err(anyhow::Error::from)
+    })?;
+    Ok(Some(repro))
+}
+
+/// Splits off any partial report attached to the run error `e`.
+fn split_partial(e: anyhow::Error) -> (anyhow::Error, Option<model::Report>) {
+    match e.downcast::<err::Error>() {
+        Ok(e) => {
+            let (cause, report) = e.into_partial();
+            (cause.into(), report)
+        }
+        Err(e) => (e, None),
+    }
+}
+
+/// Builds the error for a run aborted by `cause`, given the result of
+/// outputting whatever partial report we had.
+fn aborted(
+    cause: anyhow::Error,
+    output: ux::out::err::Result<()>,
+    context: &'static str,
+) -> anyhow::Error {
+    match output {
+        Ok(()) => cause.context(context),
+        Err(oe) => anyhow::Error::new(oe).context(format!("{cause:#}")),
+    }
+}
+
+fn run_builder<'a, E: api::abs::Entry<'a>>(
+    config: &config::Config,
+    input: &path::Path,
+    builder: run::Builder<'a, E>,
+    halt: run::halt::Rule,
+) -> anyhow::Result<model::Report> {
+    let mut report = configure_builder(config, input, builder, halt)?
         .build()?
-        .run()?)
+        .run()?;
+    stamp_platform(config, &mut report);
+    Ok(report)
+}
+
+/// Stamps `report` with the platform, unless `config` says not to.
+fn stamp_platform(config: &config::Config, report: &mut model::Report) {
+    if !config.omit_platform {
+        report.platform = Some(model::Platform::collect());
+    }
+}
+
+/// Configures `builder` to run the test at `input` as `config` says, halting
+/// also on `halt`.
+fn configure_builder<'a, E: api::abs::Entry<'a>>(
+    config: &config::Config,
+    input: &path::Path,
+    builder: run::Builder<'a, E>,
+    halt: run::halt::Rule,
+) -> anyhow::Result<run::Builder<'a, E>> {
+    configure_builder_with_rules(
+        config,
+        input,
+        builder,
+        config.halt_rules().chain(once(halt)),
+    )
+}
+
+/// Configures `builder` to run the test at `input` as `config` says, but
+/// halting on `halt_rules` rather than the rules in `config`.
+fn configure_builder_with_rules<'a, E: api::abs::Entry<'a>>(
+    config: &config::Config,
+    input: &path::Path,
+    builder: run::Builder<'a, E>,
+    halt_rules: impl Iterator<Item = run::halt::Rule>,
+) -> anyhow::Result<run::Builder<'a, E>> {
+    use run::seed::{derive, Stream};
+
+    let builder = match config.threads {
+        Some(threads) => builder.with_threads(threads),
+        None => builder,
+    };
+    config.validate_vars(&builder.manifest()?)?;
+    let seed = config.seed.unwrap_or_else(run::seed::fresh);
+    let builder = match config.guard.to_response() {
+        Some(response) => builder.with_guard(response),
+        None => builder,
+    };
+    let builder = if config.check.is_disabled() {
+        builder
+    } else {
+        builder
+            .with_forbidden(config.forbidden.clone())
+            .with_aggregate(config.aggregate.clone())
+            .with_check_mode(config.check_mode)
+            .with_check_description(config.check_description.clone())
+            .with_checking(config.check_frequency.to_policy())
+    };
+    Ok(builder
+        .add_halt_rules(halt_rules)
+        .with_checker(config.check.to_factory())
+        .with_permuter(config.permute.to_factory(derive(seed, Stream::Permute))?)
+        .with_sync(config.sync_factory())
+        .with_sync_alternation(config.sync_alternation())
+        .with_sequential(config.sequential.to_order())
+        .with_fuzz(config.fuzz.clone())
+        .with_warmup(config.iter.warmup_iterations())
+        .with_timing(config.iter.time_every)
+        .with_pause(config.iter.pause())
+        .with_canary(config.canary)
+        .with_examples(config.examples)
+        .with_transitions(config.transitions)
+        .with_segment_rotations(config.segment_rotations)
+        .with_projections(config.projections.clone())
+        .with_alloc_audit(config.audit_allocations)
+        .with_markers(config.markers)
+        .with_verify_manifest(config.verify_manifest)
+        .with_limits(config.limits.to_limits())
+        .with_reset(config.reset.to_policy())
+        .with_sampling(config.iter.sampling(derive(seed, Stream::Sample)))
+        .with_fold_symmetric(config.fold_symmetric)
+        .with_interesting(config.interesting.clone())
+        .with_mask(config.mask.iter().cloned())
+        .with_skew(config.skew.clone())
+        .with_priority(config.priority.clone())
+        .with_cpus(config.cpus.clone())
+        .with_seed(Some(seed))
+        .with_provenance(provenance(config, input, seed)?))
+}
+
+/// Gets the provenance of runs of the test at `input` under `config`, with
+/// the run seed `seed`.
+///
+/// Inline tests hash their definition, rather than a library.
+fn provenance(
+    config: &config::Config,
+    input: &path::Path,
+    seed: u64,
+) -> anyhow::Result<model::provenance::Provenance> {
+    let spec = ux::discover::inline_name(input).and_then(|name| config.inline.get(name));
+    let input = match spec {
+        Some(spec) => {
+            let source = toml::to_string(spec).map_err(config::Error::from)?;
+            model::provenance::Input::new(input.to_string_lossy(), source.as_bytes())
+        }
+        None => model::provenance::Input::hash_file(input)
+            .map_err(|e| ux::err::Error::InputIo(input.to_owned(), e))?,
+    };
+    Ok(model::provenance::Provenance {
+        allocator: Some(run::alloc::NAME.to_owned()),
+        seed: Some(seed),
+        input: Some(input),
+        config: Some(config.to_string_with_seed(seed)?),
+        ..model::provenance::Provenance::default()
+    })
 }
 
 /// Creates a halt rule that exits the test if control-C is sent.
 fn setup_ctrlc() -> anyhow::Result<run::halt::Rule> {
-    let (rule, callback) = run::halt::Rule::on_callback(run::halt::Type::Exit);
+    let (rule, callback) = run::halt::Rule::on_callback(run::halt::Type::Exit, "interrupt");
     ctrlc::set_handler(callback)?;
     Ok(rule)
 }
@@ -6,11 +6,18 @@
 
 pub mod check;
 pub mod err;
+pub mod guard;
+pub mod include;
 pub mod io;
 pub mod iter;
+pub mod limit;
 pub mod permute;
+pub mod profile;
+pub mod reset;
+pub mod sequential;
 pub mod sync;
 pub mod top;
+pub mod units;
 
 pub use err::{Error, Result};
 pub use top::Config;
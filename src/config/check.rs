@@ -1,8 +1,13 @@
 //! Support for configuring how the tester approaches state checks.
 
 use super::err;
-use crate::{api::abs, model::outcome, run::halt};
+use crate::{
+    api::abs,
+    model::outcome,
+    run::{halt, obs},
+};
 use serde::{de::Visitor, Deserialize, Serialize};
+use std::num::NonZeroUsize;
 
 /// String representations of checking strategies.
 pub mod string {
@@ -24,6 +29,16 @@ pub mod string {
         "exit-on-fail",
         "exit-on-unknown",
     ];
+
+    /// String representation of the frequency that checks states as soon as
+    /// they are first seen.
+    pub const IMMEDIATE: &str = "immediate";
+    /// String representation of the frequency that checks all states at the
+    /// end of the run.
+    pub const DEFERRED: &str = "deferred";
+    /// String representation of the prefix of the every-N frequency.  This
+    /// gets prepended to iteration counts to form frequencies.
+    pub const EVERY_PREFIX: &str = "every-";
 }
 
 /// Enumeration of test checking strategies.
@@ -102,7 +117,7 @@ impl<'de> Deserialize<'de> for Strategy {
 
 struct StrategyVisitor;
 
-impl<'de> Visitor<'de> for StrategyVisitor {
+impl Visitor<'_> for StrategyVisitor {
     type Value = Strategy;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -178,6 +193,99 @@ impl Strategy {
     }
 }
 
+/// Enumeration of frequencies at which to check states.
+///
+/// Checks normally run as soon as the observer first sees a state; for
+/// expensive checkers, they can instead run in batches, or all at once when
+/// the run ends.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Frequency {
+    /// Check each state as soon as it is first seen.
+    #[default]
+    Immediate,
+    /// Check the states seen since the last batch after every this many
+    /// iterations.
+    Every(NonZeroUsize),
+    /// Check every state at the end of the run.
+    Deferred,
+}
+
+/// Tries to parse a [Frequency] from a string.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::config::check::Frequency;
+/// assert_eq!(Frequency::Immediate, "immediate".parse().unwrap());
+/// assert_eq!(Frequency::Deferred, "Deferred".parse().unwrap());
+/// assert_eq!(Frequency::Every(100.try_into().unwrap()), "every-100".parse().unwrap());
+/// assert!("every-0".parse::<Frequency>().is_err());
+/// ```
+impl std::str::FromStr for Frequency {
+    type Err = err::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        if let Some(n) = s.strip_prefix(string::EVERY_PREFIX) {
+            n.parse()
+                .map(Self::Every)
+                .map_err(|e| Self::Err::BadCheckFrequency(format!("{s}: {e}")))
+        } else {
+            match &*s {
+                string::IMMEDIATE => Ok(Self::Immediate),
+                string::DEFERRED => Ok(Self::Deferred),
+                _ => Err(Self::Err::BadCheckFrequency(s)),
+            }
+        }
+    }
+}
+
+/// Formats a [Frequency] by applying the inverse of `FromStr`.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::config::check::Frequency;
+/// assert_eq!(Frequency::Every(8.try_into().unwrap()).to_string(), "every-8");
+/// assert_eq!(Frequency::Deferred.to_string(), "deferred");
+/// ```
+impl std::fmt::Display for Frequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Immediate => f.write_str(string::IMMEDIATE),
+            Self::Every(n) => write!(f, "{}{n}", string::EVERY_PREFIX),
+            Self::Deferred => f.write_str(string::DEFERRED),
+        }
+    }
+}
+
+impl TryFrom<String> for Frequency {
+    type Error = err::Error;
+
+    fn try_from(s: String) -> err::Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Frequency> for String {
+    fn from(f: Frequency) -> Self {
+        f.to_string()
+    }
+}
+
+impl Frequency {
+    /// Gets the observer's checking policy for this frequency.
+    #[must_use]
+    pub fn to_policy(self) -> obs::Checking {
+        match self {
+            Self::Immediate => obs::Checking::Immediate,
+            Self::Every(n) => obs::Checking::Every(n),
+            Self::Deferred => obs::Checking::AtEnd,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -187,8 +295,11 @@ mod tests {
     /// each strategy in turn.
     #[test]
     fn test_all_strings_in_sync() {
-        let got_set: HashSet<String> = string::ALL.into_iter().map(|x| x.to_string()).collect();
+        let got_set: HashSet<String> = string::ALL
+            .iter()
+            .map(std::string::ToString::to_string)
+            .collect();
         let want_set = Strategy::all().map(|x| Strategy::to_string(&x)).collect();
-        assert_eq!(got_set, want_set)
+        assert_eq!(got_set, want_set);
     }
 }
@@ -1,4 +1,5 @@
 //! Errors that can occur during configuration.
+use crate::err::Coded;
 use thiserror::Error;
 
 /// A configuration error.
@@ -10,21 +11,148 @@ pub enum Error {
     /// The user supplied a bad outcome for the exit-on check strategy.
     #[error("couldn't parse outcome in 'exit-on' checking strategy: {0}")]
     BadCheckOutcome(String),
+    /// The user supplied a bad check frequency.
+    #[error("unsupported check frequency: {0}")]
+    BadCheckFrequency(String),
+    /// The user supplied a bad outcome for a run to expect.
+    #[error("couldn't parse expected outcome (should be pass or fail): {0}")]
+    BadExpectOutcome(String),
 
     /// The user supplied the given string, which was a bad permute strategy.
     #[error("unsupported thread permutation strategy: {0}")]
     BadPermuteStrategy(String),
+    /// The user supplied a thread permutation schedule we couldn't parse.
+    #[error("couldn't parse permutation schedule")]
+    BadSchedule(#[source] serde_json::Error),
 
     /// The user supplied the given string, which was a bad sync strategy.
     #[error("unsupported synchronisation strategy: {0}")]
     BadSyncStrategy(String),
 
+    /// The user supplied the given string, which was a bad guard mode.
+    #[error("unsupported guard mode: {0}")]
+    BadGuardMode(String),
+
+    /// The user supplied the given string, which was a bad reset strategy.
+    #[error("unsupported reset strategy: {0}")]
+    BadResetStrategy(String),
+
+    /// The user supplied the given string, which was a bad sequentialisation
+    /// mode.
+    #[error("unsupported sequentialisation mode: {0}")]
+    BadSequentialMode(String),
+
     /// The user supplied a bad iteration count.
     #[error("couldn't parse iteration count: {0}")]
-    BadIterationCount(std::num::ParseIntError),
+    BadIterationCount(super::units::Error),
     /// The user supplied a bad period.
     #[error("couldn't parse period: {0}")]
-    BadPeriod(std::num::ParseIntError),
+    BadPeriod(super::units::Error),
+    /// The user supplied a bad wall-clock period.
+    #[error("couldn't parse period duration: {0}")]
+    BadPeriodDuration(super::units::Error),
+
+    /// The user supplied a bad warmup iteration count.
+    #[error("couldn't parse warmup iteration count: {0}")]
+    BadWarmup(super::units::Error),
+
+    /// The user supplied a bad minimum iteration rate.
+    #[error("couldn't parse minimum iteration rate: {0}")]
+    BadMinRate(std::num::ParseFloatError),
+    /// The user supplied a bad rate window.
+    #[error("couldn't parse rate window: {0}")]
+    BadRateWindow(super::units::Error),
+    /// The user supplied a bad body timing interval.
+    #[error("couldn't parse body timing interval: {0}")]
+    BadTimeEvery(super::units::Error),
+    /// The user supplied a bad pause interval.
+    #[error("couldn't parse pause interval: {0}")]
+    BadPauseEvery(super::units::Error),
+    /// The user supplied a bad pause length.
+    #[error("couldn't parse pause length: {0}")]
+    BadPauseLength(super::units::Error),
+    /// The user supplied a bad sampling interval.
+    #[error("couldn't parse sampling interval: {0}")]
+    BadSampleEvery(super::units::Error),
+    /// The user supplied a bad sampling probability.
+    #[error("bad sampling probability: {0}")]
+    BadSampleProbability(String),
+    /// The user supplied a bad sampling seed.
+    #[error("couldn't parse sampling seed: {0}")]
+    BadSampleSeed(std::num::ParseIntError),
+    /// The user supplied a bad checkpoint interval.
+    #[error("couldn't parse checkpoint interval: {0}")]
+    BadCheckpointInterval(super::units::Error),
+
+    /// The user supplied a bad CPU time limit.
+    #[error("couldn't parse CPU time limit: {0}")]
+    BadCpuLimit(super::units::Error),
+    /// The user supplied a bad memory limit.
+    #[error("couldn't parse memory limit: {0}")]
+    BadMemoryLimit(super::units::Error),
+
+    /// The user supplied a bad thread count.
+    #[error("couldn't parse thread count: {0}")]
+    BadThreadCount(std::num::ParseIntError),
+    /// The user supplied a bad observer seed.
+    #[error("couldn't parse observer seed: {0}")]
+    BadObserverSeed(std::num::ParseIntError),
+    /// The user supplied a bad run seed.
+    #[error("couldn't parse run seed: {0}")]
+    BadSeed(std::num::ParseIntError),
+    /// The user supplied a bad number of rotations between synchronisers.
+    #[error("couldn't parse synchroniser alternation period: {0}")]
+    BadAlternateEvery(super::units::Error),
+    /// The user supplied a bad CPU set.
+    #[error("couldn't parse CPU set: {0}")]
+    BadCpus(String),
+    /// The user supplied a bad state key order.
+    #[error("unsupported key order: {0}")]
+    BadKeyOrder(String),
+
+    /// The user asked for a config profile that doesn't exist.
+    #[error("unknown config profile: {0}")]
+    UnknownProfile(String),
+    /// A config profile (or the profile table itself) wasn't a table.
+    #[error("malformed config profile: {0}")]
+    BadProfile(String),
+    /// A config profile inherits from itself, directly or indirectly.
+    #[error("config profile inherits from itself: {0}")]
+    ProfileCycle(String),
+
+    /// A config file's include list wasn't an array of paths, or an included
+    /// file wasn't a table.
+    #[error("malformed config include list")]
+    BadInclude,
+    /// A config file includes itself, directly or indirectly.
+    #[error("config file includes itself: {}", .0.display())]
+    IncludeCycle(std::path::PathBuf),
+    /// A config file refers to an unset environment variable.
+    #[error("environment variable is unset: {0}")]
+    UnsetVar(String),
+    /// A config string has a `${` without a closing `}`.
+    #[error("malformed variable reference in: {0}")]
+    BadInterpolation(String),
+    /// The user asked for an inline test that the config doesn't define.
+    #[error("unknown inline test: {0}")]
+    UnknownInlineTest(String),
+    /// A config value is inconsistent with the rest of the config, or with
+    /// the test it configures.
+    #[error("invalid config value at {key}: {reason}")]
+    Invalid {
+        /// The TOML key path of the offending value.
+        key: String,
+        /// Why the value is invalid.
+        reason: String,
+    },
+    /// An error occurred in a particular config file.
+    #[error("in config file {}", .path.display())]
+    InFile {
+        /// The offending file.
+        path: std::path::PathBuf,
+        /// The error.
+        source: Box<Error>,
+    },
 
     /// We couldn't deserialise the config from TOML.
     #[error("couldn't parse config")]
@@ -39,5 +167,119 @@ pub enum Error {
     Io(#[from] std::io::Error),
 }
 
+impl Coded for Error {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::BadCheckStrategy(_) => "config.bad-check-strategy",
+            Self::BadCheckOutcome(_) => "config.bad-check-outcome",
+            Self::BadExpectOutcome(_) => "config.bad-expect-outcome",
+            Self::BadCheckFrequency(_) => "config.bad-check-frequency",
+            Self::BadPermuteStrategy(_) => "config.bad-permute-strategy",
+            Self::BadSchedule(_) => "config.bad-schedule",
+            Self::BadSyncStrategy(_) => "config.bad-sync-strategy",
+            Self::BadGuardMode(_) => "config.bad-guard-mode",
+            Self::BadResetStrategy(_) => "config.bad-reset-strategy",
+            Self::BadSequentialMode(_) => "config.bad-sequential-mode",
+            Self::BadIterationCount(_) => "config.bad-iteration-count",
+            Self::BadPeriod(_) => "config.bad-period",
+            Self::BadPeriodDuration(_) => "config.bad-period-duration",
+            Self::BadWarmup(_) => "config.bad-warmup",
+            Self::BadMinRate(_) => "config.bad-min-rate",
+            Self::BadRateWindow(_) => "config.bad-rate-window",
+            Self::BadTimeEvery(_) => "config.bad-time-every",
+            Self::BadPauseEvery(_) => "config.bad-pause-every",
+            Self::BadPauseLength(_) => "config.bad-pause-length",
+            Self::BadSampleEvery(_) => "config.bad-sample-every",
+            Self::BadSampleProbability(_) => "config.bad-sample-probability",
+            Self::BadSampleSeed(_) => "config.bad-sample-seed",
+            Self::BadCheckpointInterval(_) => "config.bad-checkpoint-interval",
+            Self::BadCpuLimit(_) => "config.bad-cpu-limit",
+            Self::BadMemoryLimit(_) => "config.bad-memory-limit",
+            Self::BadThreadCount(_) => "config.bad-thread-count",
+            Self::BadObserverSeed(_) => "config.bad-observer-seed",
+            Self::BadSeed(_) => "config.bad-seed",
+            Self::BadAlternateEvery(_) => "config.bad-alternate-every",
+            Self::BadCpus(_) => "config.bad-cpus",
+            Self::BadKeyOrder(_) => "config.bad-key-order",
+            Self::UnknownProfile(_) => "config.unknown-profile",
+            Self::BadProfile(_) => "config.bad-profile",
+            Self::ProfileCycle(_) => "config.profile-cycle",
+            Self::BadInclude => "config.bad-include",
+            Self::IncludeCycle(_) => "config.include-cycle",
+            Self::UnsetVar(_) => "config.unset-var",
+            Self::BadInterpolation(_) => "config.bad-interpolation",
+            Self::UnknownInlineTest(_) => "config.unknown-inline-test",
+            Self::Invalid { .. } => "config.invalid",
+            Self::InFile { source, .. } => source.code(),
+            Self::Deserialize(_) => "config.deserialize",
+            Self::Serialize(_) => "config.serialize",
+            Self::Io(_) => "config.io",
+        }
+    }
+
+    fn context(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::BadCheckStrategy(s)
+            | Self::BadPermuteStrategy(s)
+            | Self::BadSyncStrategy(s)
+            | Self::BadGuardMode(s)
+            | Self::BadResetStrategy(s)
+            | Self::BadSequentialMode(s)
+            | Self::BadCheckOutcome(s)
+            | Self::BadExpectOutcome(s)
+            | Self::BadCheckFrequency(s)
+            | Self::BadKeyOrder(s)
+            | Self::BadInterpolation(s) => vec![("input", s.clone())],
+            Self::UnknownProfile(s) | Self::BadProfile(s) | Self::ProfileCycle(s) => {
+                vec![("profile", s.clone())]
+            }
+            Self::BadIterationCount(e)
+            | Self::BadPeriod(e)
+            | Self::BadPeriodDuration(e)
+            | Self::BadWarmup(e)
+            | Self::BadRateWindow(e)
+            | Self::BadTimeEvery(e)
+            | Self::BadPauseEvery(e)
+            | Self::BadPauseLength(e)
+            | Self::BadSampleEvery(e)
+            | Self::BadCheckpointInterval(e)
+            | Self::BadCpuLimit(e)
+            | Self::BadMemoryLimit(e)
+            | Self::BadAlternateEvery(e) => {
+                vec![("reason", e.to_string())]
+            }
+            Self::BadSampleSeed(e)
+            | Self::BadThreadCount(e)
+            | Self::BadObserverSeed(e)
+            | Self::BadSeed(e) => {
+                vec![("reason", e.to_string())]
+            }
+            Self::BadMinRate(e) => vec![("reason", e.to_string())],
+            Self::BadCpus(s) | Self::BadSampleProbability(s) => vec![("reason", s.clone())],
+            Self::BadInclude => vec![],
+            Self::IncludeCycle(path) => vec![("file", path.display().to_string())],
+            Self::UnsetVar(s) => vec![("var", s.clone())],
+            Self::UnknownInlineTest(s) => vec![("test", s.clone())],
+            Self::Invalid { key, reason } => vec![("key", key.clone()), ("reason", reason.clone())],
+            Self::InFile { path, source } => {
+                let mut ctx = vec![("file", path.display().to_string())];
+                ctx.extend(source.context());
+                ctx
+            }
+            Self::BadSchedule(e) => vec![("reason", e.to_string())],
+            Self::Deserialize(e) => {
+                let mut ctx = vec![("reason", e.to_string())];
+                if let Some((line, col)) = e.line_col() {
+                    ctx.push(("line", (line + 1).to_string()));
+                    ctx.push(("column", (col + 1).to_string()));
+                }
+                ctx
+            }
+            Self::Serialize(e) => vec![("reason", e.to_string())],
+            Self::Io(e) => vec![("reason", e.to_string())],
+        }
+    }
+}
+
 /// Results over [Error].
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,81 @@
+//! Config for guarding against tests that fork or spawn threads.
+
+use super::err;
+use crate::run::guard;
+use serde::{Deserialize, Serialize};
+
+/// String representations of each mode, used in the clap interface.
+pub mod string {
+    /// Name of the mode that doesn't guard.
+    pub const OFF: &str = "off";
+    /// Name of the mode that taints reports.
+    pub const TAINT: &str = "taint";
+    /// Name of the mode that aborts runs.
+    pub const ABORT: &str = "abort";
+    /// Names of all guard modes.
+    pub const ALL: &[&str] = &[OFF, TAINT, ABORT];
+}
+
+/// Enumeration of ways to guard against tests that fork or spawn threads.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    /// Don't look out for such tests.
+    #[default]
+    Off,
+    /// Mark the reports of such tests as tainted.
+    Taint,
+    /// Mark the reports of such tests as tainted, and stop their runs with
+    /// an error.
+    Abort,
+}
+
+/// Tries to parse a [Mode] from a string.
+impl std::str::FromStr for Mode {
+    type Err = err::Error;
+
+    fn from_str(s: &str) -> err::Result<Self> {
+        match s {
+            string::OFF => Ok(Self::Off),
+            string::TAINT => Ok(Self::Taint),
+            string::ABORT => Ok(Self::Abort),
+            s => Err(err::Error::BadGuardMode(s.to_owned())),
+        }
+    }
+}
+
+/// Formats a [Mode] by applying the inverse of `FromStr`.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::config::guard::Mode;
+/// for m in Mode::ALL {
+///     assert_eq!(m.to_string().parse::<Mode>().unwrap(), m);
+/// }
+/// ```
+impl std::fmt::Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Off => string::OFF,
+            Self::Taint => string::TAINT,
+            Self::Abort => string::ABORT,
+        })
+    }
+}
+
+impl Mode {
+    /// All modes, in order.
+    pub const ALL: [Self; 3] = [Self::Off, Self::Taint, Self::Abort];
+
+    /// Gets how the runner should respond to taints in this mode, if it
+    /// should guard at all.
+    #[must_use]
+    pub fn to_response(self) -> Option<guard::Response> {
+        match self {
+            Self::Off => None,
+            Self::Taint => Some(guard::Response::Taint),
+            Self::Abort => Some(guard::Response::Abort),
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Includes and environment variable interpolation in config files.
+//!
+//! A config file can name other config files in an `include` array; these
+//! load first, in order, with each overriding the last, and the including
+//! file then merges over them (as with profiles, tables merge recursively).
+//! This lets shared defaults sit under per-machine overrides.  Relative
+//! include paths are relative to the including file.
+//!
+//! Any string value in a config file can refer to environment variables as
+//! `${NAME}`, with `$$` standing for a literal `$`; any other `$` stands for
+//! itself, so strings such as `$5` need no escaping.  Interpolation happens
+//! before includes resolve, so include paths can use variables too.
+
+use super::{err, profile};
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+/// The key of the array of files to include.
+pub const INCLUDE_KEY: &str = "include";
+
+/// Loads the config file at `path` as TOML, interpolating variables and
+/// resolving includes.
+///
+/// # Errors
+///
+/// Fails if this file or any file it includes can't be read or parsed, uses
+/// an unset variable, or includes itself (directly or indirectly); the error
+/// names the offending file.
+pub fn load(path: &Path) -> err::Result<Value> {
+    load_file(path, &mut vec![])
+}
+
+/// Interpolates variables in, and resolves includes of, the config `value`,
+/// which has no file of its own; relative includes are relative to `dir`.
+///
+/// # Errors
+///
+/// Fails as [load] does.
+pub fn expand(value: Value, dir: &Path) -> err::Result<Value> {
+    expand_with(value, dir, &mut vec![])
+}
+
+fn load_file(path: &Path, stack: &mut Vec<PathBuf>) -> err::Result<Value> {
+    let in_file = |e: err::Error| match e {
+        // Errors from included files already name those files.
+        e @ err::Error::InFile { .. } => e,
+        e => err::Error::InFile {
+            path: path.to_owned(),
+            source: Box::new(e),
+        },
+    };
+    let canonical = path.canonicalize().map_err(|e| in_file(e.into()))?;
+    if stack.contains(&canonical) {
+        return Err(err::Error::IncludeCycle(path.to_owned()));
+    }
+    let text = std::fs::read_to_string(path).map_err(|e| in_file(e.into()))?;
+    let value = toml::from_str(&text).map_err(|e| in_file(err::Error::from(e)))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    stack.push(canonical);
+    let result = expand_with(value, dir, stack).map_err(in_file);
+    stack.pop();
+    result
+}
+
+fn expand_with(mut value: Value, dir: &Path, stack: &mut Vec<PathBuf>) -> err::Result<Value> {
+    interpolate(&mut value, |name| std::env::var(name).ok())?;
+    let Value::Table(mut table) = value else {
+        // Leave non-tables for the deserialiser to complain about.
+        return Ok(value);
+    };
+    let includes = match table.remove(INCLUDE_KEY) {
+        None => vec![],
+        Some(Value::Array(xs)) => xs
+            .into_iter()
+            .map(|x| match x {
+                Value::String(s) => Ok(dir.join(s)),
+                _ => Err(err::Error::BadInclude),
+            })
+            .collect::<err::Result<_>>()?,
+        Some(_) => return Err(err::Error::BadInclude),
+    };
+
+    let mut base = toml::value::Table::new();
+    for include in includes {
+        match load_file(&include, stack)? {
+            Value::Table(t) => profile::merge(&mut base, t),
+            _ => return Err(err::Error::BadInclude),
+        }
+    }
+    profile::merge(&mut base, table);
+    Ok(Value::Table(base))
+}
+
+/// Interpolates variables, looked up with `lookup`, into every string in
+/// `value`.
+///
+/// # Errors
+///
+/// Fails if a variable is unset, or a reference is missing its closing `}`.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::config::include::interpolate;
+/// let mut v: toml::Value = toml::from_str(r#"x = "${HOST}-$$1""#).unwrap();
+/// interpolate(&mut v, |n| (n == "HOST").then(|| "lab3".to_owned())).unwrap();
+/// assert_eq!(v["x"].as_str(), Some("lab3-$1"));
+/// ```
+pub fn interpolate(
+    value: &mut Value,
+    lookup: impl Fn(&str) -> Option<String> + Copy,
+) -> err::Result<()> {
+    match value {
+        Value::String(s) => *s = interpolate_str(s, lookup)?,
+        Value::Array(xs) => {
+            for x in xs {
+                interpolate(x, lookup)?;
+            }
+        }
+        Value::Table(t) => {
+            for (_, x) in t.iter_mut() {
+                interpolate(x, lookup)?;
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+fn interpolate_str(s: &str, lookup: impl Fn(&str) -> Option<String>) -> err::Result<String> {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('$') {
+        result.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        if let Some(r) = rest.strip_prefix('$') {
+            result.push('$');
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix('{') {
+            let (name, r) = r
+                .split_once('}')
+                .ok_or_else(|| err::Error::BadInterpolation(s.to_owned()))?;
+            let value = lookup(name).ok_or_else(|| err::Error::UnsetVar(name.to_owned()))?;
+            result.push_str(&value);
+            rest = r;
+        } else {
+            result.push('$');
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of config files that deletes itself on drop.
+    struct Dir(PathBuf);
+
+    impl Dir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("phph-{name}-{}", std::process::id()));
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, name: &str, contents: &str) -> PathBuf {
+            let path = self.0.join(name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        }
+    }
+
+    impl Drop for Dir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Tests that included files layer under the including file, in order.
+    #[test]
+    fn test_include_layers() {
+        let dir = Dir::new("include-layers");
+        dir.write(
+            "lab.toml",
+            "sync = \"spinner\"\n[iter]\niterations = 10\nperiod = 5\n",
+        );
+        dir.write("big.toml", "[iter]\niterations = 1000\n");
+        let top = dir.write(
+            "top.toml",
+            "include = [\"lab.toml\", \"big.toml\"]\nsync = \"barrier\"\n",
+        );
+        let got = load(&top).unwrap();
+        assert_eq!(got["sync"].as_str(), Some("barrier"));
+        assert_eq!(got["iter"]["iterations"].as_integer(), Some(1000));
+        assert_eq!(got["iter"]["period"].as_integer(), Some(5));
+        assert!(got.get(INCLUDE_KEY).is_none());
+    }
+
+    /// Tests that include cycles are caught, naming the file that closed the
+    /// cycle.
+    #[test]
+    fn test_include_cycle() {
+        let dir = Dir::new("include-cycle");
+        let a = dir.write("a.toml", "include = [\"b.toml\"]\n");
+        dir.write("b.toml", "include = [\"a.toml\"]\n");
+        match load(&a) {
+            Err(err::Error::InFile { path, source }) => {
+                assert_eq!(path, dir.0.join("b.toml"));
+                assert!(
+                    matches!(*source, err::Error::IncludeCycle(p) if p == dir.0.join("a.toml"))
+                );
+            }
+            Err(e) => panic!("wrong error: {e}"),
+            Ok(_) => panic!("cycle not caught"),
+        }
+    }
+
+    /// Tests that errors in included files name those files.
+    #[test]
+    fn test_include_error_names_file() {
+        let dir = Dir::new("include-error");
+        let top = dir.write("top.toml", "include = [\"bad.toml\"]\n");
+        dir.write("bad.toml", "sync = \n");
+        assert!(matches!(
+            load(&top),
+            Err(err::Error::InFile { path, source })
+                if path == dir.0.join("bad.toml") && matches!(*source, err::Error::Deserialize(_))
+        ));
+    }
+
+    /// Tests that unset and malformed variable references are rejected.
+    #[test]
+    fn test_interpolate_errors() {
+        let none = |_: &str| None;
+        assert!(matches!(
+            interpolate_str("${NOPE}", none),
+            Err(err::Error::UnsetVar(x)) if x == "NOPE"
+        ));
+        assert!(matches!(
+            interpolate_str("${NOPE", none),
+            Err(err::Error::BadInterpolation(_))
+        ));
+        assert_eq!(interpolate_str("no vars", none).unwrap(), "no vars");
+    }
+
+    /// Tests that a `$` starting neither `${NAME}` nor `$$` stands for itself.
+    #[test]
+    fn test_interpolate_lone_dollar() {
+        let none = |_: &str| None;
+        for (input, want) in [
+            ("$NOPE", "$NOPE"),
+            ("costs $5", "costs $5"),
+            ("ends in $", "ends in $"),
+            ("$$$", "$$"),
+            ("$${NOPE}", "${NOPE}"),
+        ] {
+            assert_eq!(interpolate_str(input, none).unwrap(), want);
+        }
+    }
+}
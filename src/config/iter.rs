@@ -1,14 +1,163 @@
 //! Config for the tester's iteration counts, periods, and so on.
 
-use crate::run::halt;
+use super::units;
+use crate::{model::sampling, run::halt};
 use serde::{Deserialize, Serialize};
-use std::num::NonZeroUsize;
+use std::{
+    num::{NonZeroU64, NonZeroUsize},
+    time::Duration,
+};
 
 /// The default number of iterations in total.
 const DEFAULT_ITERATIONS: usize = 1_000_000;
 /// The default number of iterations after which the
 const DEFAULT_PERIOD: usize = 100_000;
 
+/// The default window, in milliseconds, over which to measure the iteration
+/// rate for `min_rate`.
+const DEFAULT_RATE_WINDOW_MS: u64 = 1000;
+
+/// The default length, in milliseconds, of each pause for `pause_every`.
+const DEFAULT_PAUSE_MS: u64 = 50;
+
+/// Iteration config: an iteration strategy plus an optional wall-clock
+/// rotation period, warmup phase, minimum iteration rate, body timing
+/// interval, pause cycle, and observation sampling rule.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Config {
+    /// The strategy for iteration-based rotations and exits.
+    #[serde(flatten)]
+    pub strategy: Strategy,
+    /// If given, rotate after every this many milliseconds since the last
+    /// rotation, whatever the iteration count.
+    #[serde(
+        default,
+        deserialize_with = "units::de::option_millis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub period_ms: Option<NonZeroU64>,
+    /// If given, run this many iterations before observing the test.
+    #[serde(
+        default,
+        deserialize_with = "units::de::option_count",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub warmup: Option<NonZeroUsize>,
+    /// If given, exit once the test runs fewer than this many iterations per
+    /// second over the rate window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_rate: Option<f64>,
+    /// If given, the window in milliseconds over which to measure the
+    /// iteration rate for `min_rate`; if not, we use one second.
+    #[serde(
+        default,
+        deserialize_with = "units::de::option_millis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rate_window_ms: Option<NonZeroU64>,
+    /// If given, time how long each thread spends in the test body on every
+    /// this many iterations.
+    #[serde(
+        default,
+        deserialize_with = "units::de::option_count",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub time_every: Option<NonZeroUsize>,
+    /// If given, pause every test thread after every this many iterations.
+    #[serde(
+        default,
+        deserialize_with = "units::de::option_count",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pause_every: Option<NonZeroUsize>,
+    /// If given, the length in milliseconds of each pause for
+    /// `pause_every`; if not, we use 50 milliseconds.
+    #[serde(
+        default,
+        deserialize_with = "units::de::option_millis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pause_ms: Option<NonZeroU64>,
+    /// If given, only observe every this many iterations.
+    #[serde(
+        default,
+        deserialize_with = "units::de::option_count",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sample_every: Option<NonZeroUsize>,
+    /// If given, and `sample_every` isn't, observe each iteration with this
+    /// probability.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_probability: Option<f64>,
+    /// If given, the seed from which to draw the choices for
+    /// `sample_probability`; if not, we derive one from the run seed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_seed: Option<u64>,
+}
+
+impl Config {
+    /// Gets any halting rules implied by this iteration config.
+    pub fn halt_rules(&self) -> impl Iterator<Item = halt::Rule> {
+        let d_rule = self
+            .period_duration()
+            .map(|x| halt::Condition::EveryDuration(x).rotate());
+        let r_rule = self.min_rate.map(|iters_per_sec| {
+            halt::Condition::MinRate {
+                iters_per_sec,
+                window: self.rate_window(),
+            }
+            .exit()
+        });
+        self.strategy.halt_rules().chain(d_rule).chain(r_rule)
+    }
+
+    /// Gets the wall-clock rotation period, if any.
+    #[must_use]
+    pub fn period_duration(&self) -> Option<Duration> {
+        self.period_ms.map(|x| Duration::from_millis(x.get()))
+    }
+
+    /// Gets the window over which to measure the iteration rate.
+    #[must_use]
+    pub fn rate_window(&self) -> Duration {
+        Duration::from_millis(
+            self.rate_window_ms
+                .map_or(DEFAULT_RATE_WINDOW_MS, NonZeroU64::get),
+        )
+    }
+
+    /// Gets the pause rule, if any.
+    #[must_use]
+    pub fn pause(&self) -> Option<halt::Pause> {
+        self.pause_every.map(|every| halt::Pause {
+            every,
+            length: Duration::from_millis(self.pause_ms.map_or(DEFAULT_PAUSE_MS, NonZeroU64::get)),
+        })
+    }
+
+    /// Gets the observation sampling rule, if any.
+    ///
+    /// Random sampling without a seed of its own draws from `seed`.
+    #[must_use]
+    pub fn sampling(&self, seed: u64) -> Option<sampling::Rule> {
+        if let Some(n) = self.sample_every {
+            return Some(sampling::Rule::Every { n });
+        }
+        self.sample_probability
+            .map(|probability| sampling::Rule::Random {
+                probability,
+                seed: self.sample_seed.unwrap_or(seed),
+            })
+    }
+
+    /// Gets the number of warmup iterations, which is 0 if there is no
+    /// warmup.
+    #[must_use]
+    pub fn warmup_iterations(&self) -> usize {
+        self.warmup.map_or(0, NonZeroUsize::get)
+    }
+}
+
 /// The strategy used to handle iteration-based rotations and exits.
 #[non_exhaustive]
 #[derive(Serialize, Deserialize)]
@@ -17,11 +166,16 @@ pub enum Strategy {
     /// No halting based on iterations.
     NoHalt,
     /// Exit after the given number of iterations.
-    Exit { iterations: NonZeroUsize },
-    /// Exit after the given number of iterations, and rotate after each
-    /// multiple of the given period.
+    Exit {
+        #[serde(deserialize_with = "units::de::count")]
+        iterations: NonZeroUsize,
+    },
+    /// Exit after the given number of iterations, and rotate after every
+    /// period of iterations since the last rotation.
     ExitAndRotate {
+        #[serde(deserialize_with = "units::de::count")]
         iterations: NonZeroUsize,
+        #[serde(deserialize_with = "units::de::count")]
         period: NonZeroUsize,
     },
 }
@@ -41,9 +195,9 @@ impl Strategy {
             .map(|x| halt::Condition::EveryNIterations(x).exit());
         let p_rule = self
             .period()
-            .map(|x| halt::Condition::EveryNIterations(x).rotate());
+            .map(|x| halt::Condition::IterationsSinceRotation(x).rotate());
 
-        i_rule.into_iter().chain(p_rule.into_iter())
+        i_rule.into_iter().chain(p_rule)
     }
 
     /// Gets the number of iterations defined by this strategy.
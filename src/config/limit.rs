@@ -0,0 +1,48 @@
+//! Config for limiting the resources of the runner's process.
+
+use super::units;
+use crate::run::limit;
+use serde::{Deserialize, Serialize};
+use std::{num::NonZeroU64, time::Duration};
+
+/// Resource limits on the runner's process, as set in the config.
+///
+/// Any fields missing from a config file are unlimited.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    /// The CPU time, in seconds, that the process may use, if limited.
+    #[serde(
+        deserialize_with = "units::de::option_secs",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cpu_secs: Option<NonZeroU64>,
+    /// The address space, in bytes, that the process may use, if limited.
+    #[serde(
+        deserialize_with = "units::de::option_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub memory_bytes: Option<NonZeroU64>,
+}
+
+impl Limits {
+    /// Gets the limits that the runner applies and watches for.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::config::limit::Limits;
+    /// use std::{num::NonZeroU64, time::Duration};
+    ///
+    /// let l = Limits { cpu_secs: NonZeroU64::new(60), memory_bytes: None }.to_limits();
+    /// assert_eq!(l.cpu, Some(Duration::from_secs(60)));
+    /// assert!(l.memory.is_none());
+    /// ```
+    #[must_use]
+    pub fn to_limits(self) -> limit::Limits {
+        limit::Limits {
+            cpu: self.cpu_secs.map(|s| Duration::from_secs(s.get())),
+            memory: self.memory_bytes,
+        }
+    }
+}
@@ -1,8 +1,14 @@
 //! Config for thread permutation.
 
 use super::err;
-use crate::run::{self, permute};
-use serde::{Deserialize, Serialize};
+use crate::{
+    model::schedule::Schedule,
+    run::{self, permute},
+};
+use serde::{de::Visitor, Deserialize, Serialize};
+use std::path;
+
+crate::features::register! { "permute" => string::ALL }
 
 /// String representations of checking strategies
 pub mod string {
@@ -10,6 +16,9 @@ pub mod string {
     pub const RANDOM: &str = "random";
     /// String representation of the static permute strategy.
     pub const STATIC: &str = "static";
+    /// String representation of the prefix of the replay permute strategy.
+    /// This gets prepended to the schedule path to form a strategy.
+    pub const REPLAY_PREFIX: &str = "replay:";
     /// String representations of all checking strategies.
     ///
     /// This is unrolled into a single slice to make use in clap easier than
@@ -18,14 +27,18 @@ pub mod string {
 }
 
 /// Enumeration of thread permutation methods.
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum Strategy {
     /// Randomly permute thread-automaton assignments on each rotation.
     Random,
     /// Never permute.
     Static,
+    /// Replay the schedule recorded in the given file.
+    ///
+    /// The file can either be a JSON report from a previous run, or a bare
+    /// JSON array of thread assignments.
+    Replay(path::PathBuf),
 }
 
 /// The default permutation method is random permutation.
@@ -40,6 +53,9 @@ impl std::str::FromStr for Strategy {
     type Err = err::Error;
 
     fn from_str(s: &str) -> err::Result<Self> {
+        if let Some(path) = s.strip_prefix(string::REPLAY_PREFIX) {
+            return Ok(Self::Replay(path.into()));
+        }
         match s {
             string::RANDOM => Ok(Self::Random),
             string::STATIC => Ok(Self::Static),
@@ -51,19 +67,50 @@ impl std::str::FromStr for Strategy {
 /// Formats a [Strategy] by applying the inverse of `FromStr`.
 impl std::fmt::Display for Strategy {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Random => write!(f, "{}", string::RANDOM),
+            Self::Static => write!(f, "{}", string::STATIC),
+            Self::Replay(path) => write!(f, "{}{}", string::REPLAY_PREFIX, path.display()),
+        }
+    }
+}
+
+/// Serialize by stringification.
+impl Serialize for Strategy {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Deserialize by parsing.
+impl<'de> Deserialize<'de> for Strategy {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(StrategyVisitor)
+    }
+}
+
+struct StrategyVisitor;
+
+impl Visitor<'_> for StrategyVisitor {
+    type Value = Strategy;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
-            f,
-            "{}",
-            match self {
-                Self::Random => string::RANDOM,
-                Self::Static => string::STATIC,
-            }
+            formatter,
+            "one of: {}, or {}PATH",
+            string::ALL.join(", "),
+            string::REPLAY_PREFIX
         )
     }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> std::result::Result<Self::Value, E> {
+        v.parse().map_err(E::custom)
+    }
 }
 
 impl Strategy {
-    /// Gets an iterator of all available strategies.
+    /// Gets an iterator of all available strategies that don't take
+    /// parameters.
     ///
     /// # Examples
     ///
@@ -78,16 +125,67 @@ impl Strategy {
         vec![Self::Random, Self::Static].into_iter()
     }
 
-    /// Converts a permutation strategy to a factory.
-    #[must_use]
-    pub fn to_factory<T: run::permute::HasTid>(self) -> run::permute::Factory<T> {
-        match self {
-            Self::Random => permute::make_thread_rng,
-            Self::Static => permute::make_nop,
+    /// Converts a permutation strategy to a factory, whose random permuters
+    /// draw from `seed`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the strategy is a replay, and the schedule can't be loaded.
+    pub fn to_factory<'a, T: run::permute::HasTid + 'a>(
+        &self,
+        seed: u64,
+    ) -> err::Result<run::permute::Factory<'a, T>> {
+        Ok(match self {
+            Self::Random => permute::seeded_factory(seed),
+            Self::Static => Box::new(permute::make_nop),
+            Self::Replay(path) => permute::replay_factory(load_schedule(path)?),
+        })
+    }
+}
+
+/// The shapes of file from which we can load a schedule.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScheduleFile {
+    /// A bare schedule.
+    Bare(Schedule),
+    /// A report (or anything else) with a `schedule` field.
+    Report { schedule: Schedule },
+}
+
+impl From<ScheduleFile> for Schedule {
+    fn from(f: ScheduleFile) -> Self {
+        match f {
+            ScheduleFile::Bare(s) | ScheduleFile::Report { schedule: s } => s,
         }
     }
 }
 
+/// Parses a schedule from a JSON string, which may be either a report or a
+/// bare schedule.
+///
+/// # Errors
+///
+/// Fails if the string isn't a valid schedule or report.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::{config::permute::parse_schedule, model::schedule::Schedule};
+/// let want = Schedule(vec![vec![1, 0]]);
+/// assert_eq!(want, parse_schedule("[[1, 0]]").unwrap());
+/// assert_eq!(want, parse_schedule(r#"{"states": [], "schedule": [[1, 0]]}"#).unwrap());
+/// ```
+pub fn parse_schedule(s: &str) -> err::Result<Schedule> {
+    let file: ScheduleFile = serde_json::from_str(s).map_err(err::Error::BadSchedule)?;
+    Ok(file.into())
+}
+
+/// Loads a schedule from the file at `path`.
+fn load_schedule(path: &path::Path) -> err::Result<Schedule> {
+    parse_schedule(&std::fs::read_to_string(path)?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -96,10 +194,12 @@ mod tests {
     /// each strategy in turn.
     #[test]
     fn test_all_strings_in_sync() {
-        let got_set: std::collections::HashSet<String> =
-            string::ALL.into_iter().map(|x| x.to_string()).collect();
+        let got_set: std::collections::HashSet<String> = string::ALL
+            .iter()
+            .map(std::string::ToString::to_string)
+            .collect();
         let want_set: std::collections::HashSet<String> =
             Strategy::all().map(|x| Strategy::to_string(&x)).collect();
-        assert_eq!(got_set, want_set)
+        assert_eq!(got_set, want_set);
     }
 }
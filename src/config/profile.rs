@@ -0,0 +1,175 @@
+//! Named config profiles.
+//!
+//! A config file can contain a `profile` table, mapping profile names to
+//! partial configs.  Selecting a profile merges its partial config over the
+//! base config.  Profiles can themselves inherit from other profiles, using
+//! an `inherits` key naming the parent profile.
+
+use super::err;
+use toml::{value::Table, Value};
+
+/// The key of the table containing profiles.
+pub const PROFILES_KEY: &str = "profile";
+/// The key, inside a profile, naming the profile from which it inherits.
+pub const INHERITS_KEY: &str = "inherits";
+
+/// Strips the profile table out of `base`, and merges the profile `name` (if
+/// any) over the rest of `base`.
+///
+/// # Errors
+///
+/// Fails if `name` isn't a profile in `base`, a profile isn't a table, or the
+/// profile inheritance chain is cyclic.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::config::profile::resolve;
+/// let base: toml::Value = toml::from_str(r#"
+///     check = "report"
+///     sync = "spinner"
+///     [profile.quick]
+///     sync = "barrier"
+///     [profile.quicker]
+///     inherits = "quick"
+///     check = "disable"
+/// "#).unwrap();
+///
+/// let quicker = resolve(base, Some("quicker")).unwrap();
+/// assert_eq!(quicker["sync"].as_str(), Some("barrier"));
+/// assert_eq!(quicker["check"].as_str(), Some("disable"));
+/// assert!(quicker.get("profile").is_none());
+/// ```
+pub fn resolve(base: Value, name: Option<&str>) -> err::Result<Value> {
+    let mut base = match base {
+        Value::Table(t) => t,
+        // Leave non-tables for the deserialiser to complain about.
+        v => return Ok(v),
+    };
+    let profiles = match base.remove(PROFILES_KEY) {
+        Some(Value::Table(t)) => t,
+        Some(_) => return Err(err::Error::BadProfile(PROFILES_KEY.to_owned())),
+        None => Table::new(),
+    };
+    if let Some(name) = name {
+        for overlay in chain(&profiles, name)?.into_iter().rev() {
+            merge(&mut base, overlay);
+        }
+    }
+    Ok(Value::Table(base))
+}
+
+/// Gets the names of the profiles in `base`, in name order.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::config::profile::names;
+/// let base: toml::Value = toml::from_str("[profile.b]\n[profile.a]\n").unwrap();
+/// assert_eq!(names(&base), ["a", "b"]);
+/// ```
+#[must_use]
+pub fn names(base: &Value) -> Vec<String> {
+    let mut names: Vec<_> = base
+        .get(PROFILES_KEY)
+        .and_then(Value::as_table)
+        .map(|t| t.keys().cloned().collect())
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Gets the chain of profile tables from `name` up to its furthest ancestor,
+/// with each table's `inherits` key removed.
+fn chain(profiles: &Table, name: &str) -> err::Result<Vec<Table>> {
+    let mut seen = vec![];
+    let mut result = vec![];
+    let mut next = Some(name.to_owned());
+
+    while let Some(name) = next.take() {
+        if seen.contains(&name) {
+            return Err(err::Error::ProfileCycle(name));
+        }
+        let mut table = match profiles.get(&name) {
+            Some(Value::Table(t)) => t.clone(),
+            Some(_) => return Err(err::Error::BadProfile(name)),
+            None => return Err(err::Error::UnknownProfile(name)),
+        };
+        next = match table.remove(INHERITS_KEY) {
+            Some(Value::String(parent)) => Some(parent),
+            Some(_) => return Err(err::Error::BadProfile(name)),
+            None => None,
+        };
+        seen.push(name);
+        result.push(table);
+    }
+
+    Ok(result)
+}
+
+/// Merges `overlay` into `base`, recursively merging tables.
+pub(super) fn merge(base: &mut Table, overlay: Table) {
+    for (k, v) in overlay {
+        match (base.get_mut(&k), v) {
+            (Some(Value::Table(bt)), Value::Table(ot)) => merge(bt, ot),
+            (_, v) => {
+                base.insert(k, v);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Value {
+        toml::from_str(s).unwrap()
+    }
+
+    /// Tests that nested tables are merged, not replaced.
+    #[test]
+    fn test_merge_nested() {
+        let base = parse(
+            r#"
+            [iter]
+            action = "exit-and-rotate"
+            iterations = 10
+            period = 5
+            [profile.soak.iter]
+            iterations = 1000
+            "#,
+        );
+        let got = resolve(base, Some("soak")).unwrap();
+        assert_eq!(got["iter"]["iterations"].as_integer(), Some(1000));
+        assert_eq!(got["iter"]["period"].as_integer(), Some(5));
+    }
+
+    /// Tests that selecting no profile leaves the base alone.
+    #[test]
+    fn test_no_profile() {
+        let base = parse("sync = \"spinner\"\n[profile.quick]\nsync = \"barrier\"\n");
+        let got = resolve(base, None).unwrap();
+        assert_eq!(got["sync"].as_str(), Some("spinner"));
+    }
+
+    /// Tests that unknown profiles are rejected.
+    #[test]
+    fn test_unknown_profile() {
+        let base = parse("[profile.quick]\nsync = \"barrier\"\n");
+        assert!(matches!(
+            resolve(base, Some("slow")),
+            Err(err::Error::UnknownProfile(x)) if x == "slow"
+        ));
+    }
+
+    /// Tests that inheritance cycles are rejected.
+    #[test]
+    fn test_cycle() {
+        let base = parse("[profile.a]\ninherits = \"b\"\n[profile.b]\ninherits = \"a\"\n");
+        assert!(matches!(
+            resolve(base, Some("a")),
+            Err(err::Error::ProfileCycle(_))
+        ));
+    }
+}
@@ -0,0 +1,79 @@
+//! Config for when the runner resets the test's environment.
+
+use super::err;
+use crate::run::shared;
+use serde::{Deserialize, Serialize};
+
+/// String representations of each strategy, used in the clap interface.
+pub mod string {
+    /// Name of the strategy that resets after every iteration.
+    pub const PER_ITERATION: &str = "per-iteration";
+    /// Name of the strategy that resets between rotations.
+    pub const PER_ROTATION: &str = "per-rotation";
+    /// Name of the strategy that never resets.
+    pub const NEVER: &str = "never";
+    /// Names of all reset strategies.
+    pub const ALL: &[&str] = &[PER_ITERATION, PER_ROTATION, NEVER];
+}
+
+/// Enumeration of strategies for resetting the environment.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// Reset after every iteration.
+    #[default]
+    PerIteration,
+    /// Reset only between rotations.
+    PerRotation,
+    /// Never reset once the run starts.
+    Never,
+}
+
+/// Tries to parse a [Strategy] from a string.
+impl std::str::FromStr for Strategy {
+    type Err = err::Error;
+
+    fn from_str(s: &str) -> err::Result<Self> {
+        match s {
+            string::PER_ITERATION => Ok(Self::PerIteration),
+            string::PER_ROTATION => Ok(Self::PerRotation),
+            string::NEVER => Ok(Self::Never),
+            s => Err(err::Error::BadResetStrategy(s.to_owned())),
+        }
+    }
+}
+
+/// Formats a [Strategy] by applying the inverse of `FromStr`.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::config::reset::Strategy;
+/// for s in Strategy::ALL {
+///     assert_eq!(s.to_string().parse::<Strategy>().unwrap(), s);
+/// }
+/// ```
+impl std::fmt::Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::PerIteration => string::PER_ITERATION,
+            Self::PerRotation => string::PER_ROTATION,
+            Self::Never => string::NEVER,
+        })
+    }
+}
+
+impl Strategy {
+    /// All strategies, in order.
+    pub const ALL: [Self; 3] = [Self::PerIteration, Self::PerRotation, Self::Never];
+
+    /// Gets the runner's reset policy for this strategy.
+    #[must_use]
+    pub fn to_policy(self) -> shared::Reset {
+        match self {
+            Self::PerIteration => shared::Reset::PerIteration,
+            Self::PerRotation => shared::Reset::PerRotation,
+            Self::Never => shared::Reset::Never,
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Config for sequentialising runs onto one OS thread.
+
+use super::err;
+use crate::run::sequential;
+use serde::{Deserialize, Serialize};
+
+/// String representations of each mode, used in the clap interface.
+pub mod string {
+    /// Name of the mode that runs threads concurrently.
+    pub const OFF: &str = "off";
+    /// Name of the mode that runs threads in rotating order.
+    pub const ROUND_ROBIN: &str = "round-robin";
+    /// Name of the mode that runs threads in every order in turn.
+    pub const ENUMERATE: &str = "enumerate";
+    /// Names of all sequentialisation modes.
+    pub const ALL: &[&str] = &[OFF, ROUND_ROBIN, ENUMERATE];
+}
+
+/// Enumeration of ways to sequentialise a run, if at all.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    /// Run the test threads concurrently, as usual.
+    #[default]
+    Off,
+    /// Run the test threads one after another, starting each iteration
+    /// with the next thread along.
+    RoundRobin,
+    /// Run the test threads one after another, going through every order of
+    /// the threads in turn.
+    Enumerate,
+}
+
+/// Tries to parse a [Mode] from a string.
+impl std::str::FromStr for Mode {
+    type Err = err::Error;
+
+    fn from_str(s: &str) -> err::Result<Self> {
+        match s {
+            string::OFF => Ok(Self::Off),
+            string::ROUND_ROBIN => Ok(Self::RoundRobin),
+            string::ENUMERATE => Ok(Self::Enumerate),
+            s => Err(err::Error::BadSequentialMode(s.to_owned())),
+        }
+    }
+}
+
+/// Formats a [Mode] by applying the inverse of `FromStr`.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::config::sequential::Mode;
+/// for m in Mode::ALL {
+///     assert_eq!(m.to_string().parse::<Mode>().unwrap(), m);
+/// }
+/// ```
+impl std::fmt::Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Off => string::OFF,
+            Self::RoundRobin => string::ROUND_ROBIN,
+            Self::Enumerate => string::ENUMERATE,
+        })
+    }
+}
+
+impl Mode {
+    /// All modes, in order.
+    pub const ALL: [Self; 3] = [Self::Off, Self::RoundRobin, Self::Enumerate];
+
+    /// Gets the order in which the runner should run the test threads in
+    /// this mode, if it should sequentialise them at all.
+    #[must_use]
+    pub fn to_order(self) -> Option<sequential::Order> {
+        match self {
+            Self::Off => None,
+            Self::RoundRobin => Some(sequential::Order::RoundRobin),
+            Self::Enumerate => Some(sequential::Order::Enumerate),
+        }
+    }
+}
@@ -1,8 +1,11 @@
 //! Config for the tester's synchronisation methods.
 
-use super::err;
+use super::{err, units};
 use crate::run::sync;
 use serde::{Deserialize, Serialize};
+use std::num::NonZeroUsize;
+
+crate::features::register! { "sync" => string::ALL }
 
 /// String representations of each strategy, used in the clap interface.
 pub mod string {
@@ -12,13 +15,15 @@ pub mod string {
     pub const SPIN_BARRIER: &str = "spin-barrier";
     /// Name of the `Barrier` synchronisation strategy.
     pub const BARRIER: &str = "barrier";
+    /// Name of the `Backoff` synchronisation strategy.
+    pub const BACKOFF: &str = "backoff";
     /// Names of all synchronisation strategies.
-    pub const ALL: &[&str] = &[SPINNER, SPIN_BARRIER, BARRIER];
+    pub const ALL: &[&str] = &[SPINNER, SPIN_BARRIER, BARRIER, BACKOFF];
 }
 
 /// Enumeration of synchronisation strategy exported by the phenolphthalein
 /// toplevel.
-#[derive(Copy, Clone, Serialize, Deserialize)]
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 #[non_exhaustive]
 pub enum Strategy {
@@ -28,6 +33,8 @@ pub enum Strategy {
     SpinBarrier,
     /// Represents the barrier synchronisation strategy.
     Barrier,
+    /// Represents the adaptive spin-then-park synchronisation strategy.
+    Backoff,
 }
 
 /// The default synchronisation strategy is the spinner.
@@ -46,6 +53,7 @@ impl std::str::FromStr for Strategy {
             string::SPINNER => Ok(Self::Spinner),
             string::SPIN_BARRIER => Ok(Self::SpinBarrier),
             string::BARRIER => Ok(Self::Barrier),
+            string::BACKOFF => Ok(Self::Backoff),
             s => Err(err::Error::BadSyncStrategy(s.to_owned())),
         }
     }
@@ -61,6 +69,7 @@ impl std::fmt::Display for Strategy {
                 Self::Spinner => string::SPINNER,
                 Self::SpinBarrier => string::SPIN_BARRIER,
                 Self::Barrier => string::BARRIER,
+                Self::Backoff => string::BACKOFF,
             }
         )
     }
@@ -68,17 +77,124 @@ impl std::fmt::Display for Strategy {
 
 impl Strategy {
     pub fn all() -> impl Iterator<Item = Self> {
-        vec![Self::Spinner, Self::SpinBarrier, Self::Barrier].into_iter()
+        vec![
+            Self::Spinner,
+            Self::SpinBarrier,
+            Self::Barrier,
+            Self::Backoff,
+        ]
+        .into_iter()
     }
 
     /// Gets the correct factory method for the synchronisation primitive
     /// requested in this argument set.
+    ///
+    /// The spinner and backoff parameters apply only to the `Spinner` and
+    /// `Backoff` strategies respectively.
     #[must_use]
-    pub fn to_factory(self) -> sync::Factory {
+    pub fn to_factory(self, spinner: &Spinner, backoff: &Backoff) -> sync::Factory {
         match self {
-            Self::Barrier => sync::make_barrier,
-            Self::SpinBarrier => sync::make_spin_barrier,
-            Self::Spinner => sync::make_spinner,
+            Self::Barrier => Box::new(sync::make_barrier),
+            Self::SpinBarrier => Box::new(sync::make_spin_barrier),
+            Self::Spinner => sync::spinner_factory(spinner.to_params()),
+            Self::Backoff => sync::backoff_factory(backoff.to_params()),
+        }
+    }
+}
+
+/// A request to alternate the main synchronisation strategy with another
+/// within one run.
+///
+/// The run starts with the main strategy, and switches between the two every
+/// `every` rotations, so that its report can compare the states seen under
+/// each.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Alternate {
+    /// The strategy with which to alternate.
+    pub with: Strategy,
+    /// The number of rotations for which each strategy stays active.
+    #[serde(default = "one", deserialize_with = "units::de::count")]
+    pub every: NonZeroUsize,
+}
+
+/// Gets the default alternation period.
+fn one() -> NonZeroUsize {
+    NonZeroUsize::MIN
+}
+
+/// Tunable parameters for the `Spinner` synchronisation strategy.
+///
+/// Any fields missing from a config file take their default values.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Spinner {
+    /// Whether to hint to the CPU that each spin is a spin.
+    pub hint: bool,
+    /// If nonzero, yield to the OS scheduler after every this many spins.
+    pub yield_after: usize,
+    /// If nonzero, park after this many spins until the last thread arrives.
+    pub park_after: usize,
+}
+
+/// The default spinner parameters are those of the runner.
+impl Default for Spinner {
+    fn default() -> Self {
+        let params = sync::SpinnerParams::default();
+        Self {
+            hint: params.hint,
+            yield_after: params.yield_after.map_or(0, NonZeroUsize::get),
+            park_after: params.park_after.map_or(0, NonZeroUsize::get),
+        }
+    }
+}
+
+impl Spinner {
+    /// Converts these config parameters into runner parameters.
+    #[must_use]
+    pub fn to_params(&self) -> sync::SpinnerParams {
+        sync::SpinnerParams {
+            hint: self.hint,
+            yield_after: NonZeroUsize::new(self.yield_after),
+            park_after: NonZeroUsize::new(self.park_after),
+        }
+    }
+}
+
+/// Tunable parameters for the `Backoff` synchronisation strategy.
+///
+/// Any fields missing from a config file take their default values.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Backoff {
+    /// The number of exponentially growing rounds of spinning before yielding.
+    pub spins: u32,
+    /// The number of times to yield to the OS scheduler before parking.
+    pub yields: u32,
+    /// The longest, in microseconds, that a parked thread sleeps before
+    /// rechecking whether it can proceed.
+    pub park_timeout_us: u64,
+}
+
+/// The default backoff parameters are those of the runner.
+impl Default for Backoff {
+    fn default() -> Self {
+        let params = sync::BackoffParams::default();
+        Self {
+            spins: params.spins,
+            yields: params.yields,
+            park_timeout_us: u64::try_from(params.park_timeout.as_micros()).unwrap_or(u64::MAX),
+        }
+    }
+}
+
+impl Backoff {
+    /// Converts these config parameters into runner parameters.
+    #[must_use]
+    pub fn to_params(&self) -> sync::BackoffParams {
+        sync::BackoffParams {
+            spins: self.spins,
+            yields: self.yields,
+            park_timeout: std::time::Duration::from_micros(self.park_timeout_us),
         }
     }
 }
@@ -92,8 +208,11 @@ mod tests {
     /// each strategy in turn.
     #[test]
     fn test_all_strings_in_sync() {
-        let got_set: HashSet<String> = string::ALL.into_iter().map(|x| x.to_string()).collect();
+        let got_set: HashSet<String> = string::ALL
+            .iter()
+            .map(std::string::ToString::to_string)
+            .collect();
         let want_set = Strategy::all().map(|x| Strategy::to_string(&x)).collect();
-        assert_eq!(got_set, want_set)
+        assert_eq!(got_set, want_set);
     }
 }
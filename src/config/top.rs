@@ -1,29 +1,333 @@
 //! The top-level config structure.
 use std::str::FromStr;
 
-use super::{check, err, iter, permute, sync};
-use crate::run::halt;
+use super::{
+    check, err, guard, include, iter, limit, permute, profile, reset, sequential, sync, units,
+};
+use crate::{
+    api::{abs, dsl},
+    model::{
+        aggregate, cpu, expectation, fuzz, interest, manifest, priority, projection, skew, state,
+    },
+    run::{checkpoint, halt},
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
 /// The top-level config structure.
+///
+/// Any fields missing from a config file take their default values.
+///
+/// TOML requires plain values to come before tables, so table-valued fields
+/// must come last.
+#[allow(clippy::struct_excessive_bools)] // These mirror command-line flags.
 pub struct Config {
     /// The strategy for thread permutation that the runner should take.
     pub permute: permute::Strategy,
     /// The synchronisation strategy.
     pub sync: sync::Strategy,
+    /// If given, the seed from which to choose each iteration's observer
+    /// pseudo-randomly, rather than letting the synchroniser choose.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub observer_seed: Option<u64>,
+    /// If given, the run seed from which to derive the run's other
+    /// pseudo-random choices: fuzzing, random permutations, skews, and random
+    /// sampling without its own seed.  If not, each run draws a fresh one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
     /// The strategy for checking that the runner should take.
     pub check: check::Strategy,
-    /// The test iteration strategy.
-    pub iter: iter::Strategy,
+    /// How to combine the test's own checker with forbidden states.
+    pub check_mode: abs::check::Mode,
+    /// How often to check states seen for the first time.
+    pub check_frequency: check::Frequency,
+    /// A description of what the checks check, overriding any the test
+    /// gives.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check_description: Option<String>,
+    /// The outcome that runs expect their checks to give, if any; a run
+    /// whose report doesn't meet the expectation fails.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expect_outcome: Option<expectation::Expectation>,
+    /// The number of threads to which to scale tests, if not their own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threads: Option<std::num::NonZeroUsize>,
+    /// The CPUs to which to restrict all test threads, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<cpu::Set>,
+    /// Whether to fill non-atomic variables with canary values to detect
+    /// torn reads.
+    pub canary: bool,
+    /// Whether to record, for each distinct state, the context of the first
+    /// iteration that showed it.
+    pub examples: bool,
+    /// Whether to record how often each state follows each other state in
+    /// successive observed iterations.
+    pub transitions: bool,
+    /// Whether to segment reports' histograms by rotation.
+    pub segment_rotations: bool,
+    /// Whether to count the allocations made while handling each observed
+    /// iteration, in builds that can.
+    pub audit_allocations: bool,
+    /// Whether to mark the start and end of the run, and of each rotation,
+    /// in the kernel's trace buffer for profilers.
+    pub markers: bool,
+    /// Whether to check, at each rotation boundary, that the test hasn't
+    /// changed its manifest.
+    pub verify_manifest: bool,
+    /// Whether to fold together states that differ only by swapping the
+    /// registers of symmetric threads.
+    pub fold_symmetric: bool,
+    /// Whether to exit once every interesting state has been seen.
+    pub exit_on_interesting: bool,
+    /// Whether to leave the machine's platform out of reports.
+    pub omit_platform: bool,
+    /// Whether to follow a run that exits on a failure by shrinking it, to
+    /// find a quicker way to reproduce the failure.
+    pub shrink: bool,
+    /// If given, checkpoint the observations every this many milliseconds,
+    /// so that a later run can recover them if this one dies.
+    #[serde(
+        deserialize_with = "units::de::option_millis",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub checkpoint_ms: Option<std::num::NonZeroU64>,
+    /// The directory in which to keep checkpoints, if not a
+    /// `phenolphthalein` directory under the system's temporary directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checkpoint_dir: Option<std::path::PathBuf>,
+    /// How to guard against tests that fork or spawn threads of their own.
+    pub guard: guard::Mode,
+    /// When to reset the environment to its initial values.
+    pub reset: reset::Strategy,
+    /// Whether, and in which orders, to run the test threads one after
+    /// another on one OS thread, rather than concurrently.
+    pub sequential: sequential::Mode,
+    /// Variables to leave out of observed states.
+    pub mask: Vec<String>,
+    /// The order in which outputs show the variables of states.
+    pub key_order: state::KeyOrder,
+    /// The test iteration strategy and rotation periods.
+    pub iter: iter::Config,
+    /// Parameters for the spinner synchronisation strategy.
+    pub spinner: sync::Spinner,
+    /// Parameters for the backoff synchronisation strategy.
+    pub backoff: sync::Backoff,
+    /// If given, another synchronisation strategy with which to alternate
+    /// the main one, comparing the states seen under each.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alternate_sync: Option<sync::Alternate>,
+    /// Limits on the CPU time and memory of the runner's process.
+    pub limits: limit::Limits,
+    /// Distributions from which to draw fuzzed initial values, keyed by
+    /// variable.
+    pub fuzz: fuzz::Spec,
+    /// Display formats for variables in reports, keyed by variable.
+    pub format: state::Formats,
+    /// Interesting states to look out for, keyed by name.
+    pub interesting: interest::Spec,
+    /// States that fail their check, keyed by name, in addition to any the
+    /// test's own checker fails.
+    pub forbidden: interest::Spec,
+    /// Bounds on how often states occur over the whole run, keyed by name;
+    /// these are checked at the end of the run.
+    pub aggregate: aggregate::Spec,
+    /// Groups of variables onto which to project reports, keyed by name.
+    pub projections: projection::Spec,
+    /// Delays with which threads enter each iteration, keyed by thread ID.
+    pub skew: skew::Spec,
+    /// Scheduling priorities of threads, keyed by thread ID or `*`.
+    pub priority: priority::Spec,
+    /// Tests written in the inline test language, keyed by name; these run
+    /// as inputs of the form `inline:NAME`.
+    pub inline: std::collections::BTreeMap<String, dsl::Spec>,
 }
 
 impl Config {
     /// Gets the halting rules requested in this argument set.
     pub fn halt_rules(&self) -> impl Iterator<Item = halt::Rule> {
-        let i_rules = self.iter.halt_rules();
+        self.iter.halt_rules().chain(self.outcome_halt_rules())
+    }
+
+    /// Gets the halting rules requested in this argument set that depend on
+    /// what the test observes, rather than on iterations or time.
+    pub fn outcome_halt_rules(&self) -> impl Iterator<Item = halt::Rule> {
         let c_rules = self.check.halt_rules();
-        i_rules.chain(c_rules)
+        let s_rule = (self.exit_on_interesting && !self.interesting.is_empty())
+            .then(|| halt::Condition::AllInteresting.exit());
+        c_rules.chain(s_rule)
+    }
+
+    /// Checks that the values in this config are consistent with each other.
+    ///
+    /// Loading a config checks it, but anything that changes it afterwards,
+    /// such as command-line overrides, should check it again.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`err::Error::Invalid`], naming the TOML key of the first
+    /// inconsistent value found.
+    pub fn validate(&self) -> err::Result<()> {
+        if let (Some(iterations), Some(period)) =
+            (self.iter.strategy.iterations(), self.iter.strategy.period())
+        {
+            if iterations <= period {
+                let reason = format!("period {period} must be below the {iterations} iterations");
+                return Err(invalid(&["iter", "period"], reason));
+            }
+        }
+        if let Some(p) = self.iter.sample_probability {
+            if !(0.0 < p && p <= 1.0) {
+                let reason = format!("{p} is not above 0 and at most 1");
+                return Err(invalid(&["iter", "sample_probability"], reason));
+            }
+        }
+        if let Some(rate) = self.iter.min_rate {
+            if !(rate.is_finite() && 0.0 < rate) {
+                let reason = format!("{rate} is not a positive rate");
+                return Err(invalid(&["iter", "min_rate"], reason));
+            }
+        }
+        if self.cpus.as_ref().is_some_and(cpu::Set::is_empty) {
+            return Err(invalid(&["cpus"], "the set names no CPUs"));
+        }
+        for (var, dist) in &self.fuzz {
+            if !dist.is_well_formed() {
+                let reason = "the bounds are out of order or not finite";
+                return Err(invalid(&["fuzz", var], reason));
+            }
+        }
+        for (name, rule) in &self.aggregate {
+            validate_aggregate(name, rule)?;
+        }
+        if self.check_frequency != check::Frequency::Immediate {
+            let clash = if !self.mask.is_empty() {
+                Some("mask")
+            } else if self.fold_symmetric {
+                Some("fold_symmetric")
+            } else if self.check.halt_outcome().is_some() {
+                Some("check")
+            } else {
+                None
+            };
+            if let Some(key) = clash {
+                let reason = format!("checks can't be deferred alongside {key}");
+                return Err(invalid(&["check_frequency"], reason));
+            }
+        }
+        if let Some(alternate) = &self.alternate_sync {
+            if alternate.with == self.sync {
+                let reason = format!("{} can't be compared with itself", self.sync);
+                return Err(invalid(&["alternate_sync", "with"], reason));
+            }
+            if !self
+                .iter
+                .halt_rules()
+                .any(|r| r.halt_type == halt::Type::Rotate)
+            {
+                let reason = "synchronisers only change between rotations, so iter must rotate";
+                return Err(invalid(&["alternate_sync"], reason));
+            }
+        }
+        for name in self.inline.keys() {
+            self.inline_test(name)?;
+        }
+        Ok(())
+    }
+
+    /// Compiles the inline test `name`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`err::Error::UnknownInlineTest`] if there is no such test,
+    /// or [`err::Error::Invalid`] if it doesn't compile.
+    pub fn inline_test(&self, name: &str) -> err::Result<dsl::Entry> {
+        let spec = self
+            .inline
+            .get(name)
+            .ok_or_else(|| err::Error::UnknownInlineTest(name.to_owned()))?;
+        spec.compile()
+            .map_err(|e| invalid(&["inline", name, "threads"], e.to_string()))
+    }
+
+    /// Checks that the variables named in this config's fuzzing, forbidden
+    /// states, and mask exist in `manifest`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`err::Error::Invalid`], naming the TOML key of the first
+    /// unknown variable found.
+    pub fn validate_vars(&self, manifest: &manifest::Manifest) -> err::Result<()> {
+        let unknown = |var: &str| format!("the test has no variable {var}");
+        if let Some(var) = self.fuzz.keys().find(|v| !manifest.has_var(v)) {
+            return Err(invalid(&["fuzz", var], unknown(var)));
+        }
+        for (name, matcher) in &self.forbidden {
+            if let Some(var) = matcher.0.keys().find(|v| !manifest.has_var(v)) {
+                return Err(invalid(&["forbidden", name, var], unknown(var)));
+            }
+        }
+        if let Some(var) = self.mask.iter().find(|v| !manifest.has_var(v)) {
+            return Err(invalid(&["mask"], unknown(var)));
+        }
+        for (name, vars) in &self.projections {
+            if let Some(var) = vars.iter().find(|v| !manifest.has_var(v)) {
+                return Err(invalid(&["projections", name], unknown(var)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the path of the checkpoint file for the test named `name`.
+    ///
+    /// This doesn't depend on whether checkpointing is on, so that the
+    /// checkpoints of earlier runs can be found whatever this run does.
+    #[must_use]
+    pub fn checkpoint_path(&self, name: &str) -> std::path::PathBuf {
+        self.checkpoint_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("phenolphthalein"))
+            .join(format!("{name}.checkpoint.json"))
+    }
+
+    /// Gets the checkpointing rule, if any, for the test named `name`.
+    #[must_use]
+    pub fn checkpoint(&self, name: &str) -> Option<checkpoint::Rule> {
+        self.checkpoint_ms.map(|ms| checkpoint::Rule {
+            path: self.checkpoint_path(name),
+            every: std::time::Duration::from_millis(ms.get()),
+        })
+    }
+
+    /// Gets the synchroniser factory requested in this config.
+    #[must_use]
+    pub fn sync_factory(&self) -> crate::run::sync::Factory {
+        self.strategy_factory(self.sync)
+    }
+
+    /// Gets the alternation of synchronisers requested in this config, if
+    /// any, starting with the main strategy.
+    #[must_use]
+    pub fn sync_alternation(&self) -> Option<crate::run::sync::Alternation> {
+        self.alternate_sync
+            .map(|alternate| crate::run::sync::Alternation {
+                arms: [self.sync, alternate.with]
+                    .into_iter()
+                    .map(|s| (s.to_string(), self.strategy_factory(s)))
+                    .collect(),
+                every: alternate.every,
+            })
+    }
+
+    /// Gets the factory for `strategy`, with this config's parameters and
+    /// observer seed.
+    fn strategy_factory(&self, strategy: sync::Strategy) -> crate::run::sync::Factory {
+        let factory = strategy.to_factory(&self.spinner, &self.backoff);
+        match self.observer_seed {
+            Some(seed) => crate::run::sync::random_observer_factory(factory, seed),
+            None => factory,
+        }
     }
 
     /// Tries to dump a config to a string.
@@ -35,6 +339,73 @@ impl Config {
         Ok(toml::to_string_pretty(self)?)
     }
 
+    /// Tries to dump a config to a string, as [`Self::to_string`] does, but
+    /// with `seed` as its run seed.
+    ///
+    /// This lets runs record the seed they drew in their snapshot of the
+    /// config.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the underlying TOML conversion fails, or `seed` is too large
+    /// for TOML.
+    pub fn to_string_with_seed(&self, seed: u64) -> err::Result<String> {
+        let mut value = toml::Value::try_from(self)?;
+        if let toml::Value::Table(table) = &mut value {
+            table.insert("seed".to_owned(), toml::Value::try_from(seed)?);
+        }
+        Ok(toml::to_string_pretty(&value)?)
+    }
+
+    /// Tries to load a config from a string, applying the profile `name` if
+    /// given.
+    ///
+    /// Any includes are relative to the current directory (see [include]).
+    ///
+    /// # Errors
+    ///
+    /// Fails if the TOML is malformed, an include or variable can't be
+    /// resolved, or the profile can't be resolved.
+    pub fn from_str_with_profile(s: &str, name: Option<&str>) -> err::Result<Self> {
+        let value = include::expand(toml::from_str(s)?, std::path::Path::new(""))?;
+        Self::from_value_with_profile(value, name)
+    }
+
+    /// Tries to load a config from the file at `path`, resolving its includes
+    /// and applying the profile `name` if given.
+    ///
+    /// Profiles can come from the file or anything it includes.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file, or anything it includes, can't be loaded (see
+    /// [`include::load`]), or the profile can't be resolved.
+    pub fn load(path: &std::path::Path, name: Option<&str>) -> err::Result<Self> {
+        Self::from_value_with_profile(include::load(path)?, name).map_err(|e| match e {
+            e @ err::Error::InFile { .. } => e,
+            e => err::Error::InFile {
+                path: path.to_owned(),
+                source: Box::new(e),
+            },
+        })
+    }
+
+    /// Gets the names of the profiles available in the config file at
+    /// `path`, in name order.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file, or anything it includes, can't be loaded.
+    pub fn profiles(path: &std::path::Path) -> err::Result<Vec<String>> {
+        Ok(profile::names(&include::load(path)?))
+    }
+
+    fn from_value_with_profile(value: toml::Value, name: Option<&str>) -> err::Result<Self> {
+        let config: Self = profile::resolve(value, name)?.try_into()?;
+        config.validate()?;
+        Ok(config)
+    }
+
     /// Dumps the configuration to stdout.
     ///
     /// # Errors
@@ -46,11 +417,579 @@ impl Config {
     }
 }
 
+/// Makes an [`err::Error::Invalid`] for the value at the TOML key path
+/// `keys`, quoting any keys that aren't bare.
+fn invalid(keys: &[&str], reason: impl Into<String>) -> err::Error {
+    let bare = |k: &str| {
+        !k.is_empty()
+            && k.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    };
+    let key = keys
+        .iter()
+        .map(|k| {
+            if bare(k) {
+                (*k).to_owned()
+            } else {
+                format!("{k:?}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".");
+    err::Error::Invalid {
+        key,
+        reason: reason.into(),
+    }
+}
+
+/// Checks that the aggregate rule `name` has bounds in order.
+fn validate_aggregate(name: &str, rule: &aggregate::Rule) -> err::Result<()> {
+    for (key, fraction) in [
+        ("min_fraction", rule.min_fraction),
+        ("max_fraction", rule.max_fraction),
+    ] {
+        if let Some(f) = fraction.filter(|f| !(0.0..=1.0).contains(f)) {
+            let reason = format!("{f} is not a fraction between 0 and 1");
+            return Err(invalid(&["aggregate", name, key], reason));
+        }
+    }
+    let out_of_order =
+        |min: Option<f64>, max: Option<f64>| min.zip(max).is_some_and(|(l, h)| h < l);
+    #[allow(clippy::cast_precision_loss)]
+    let counts = (rule.min.map(|x| x as f64), rule.max.map(|x| x as f64));
+    if out_of_order(counts.0, counts.1) || out_of_order(rule.min_fraction, rule.max_fraction) {
+        return Err(invalid(
+            &["aggregate", name],
+            "the minimum is above the maximum",
+        ));
+    }
+    Ok(())
+}
+
 impl FromStr for Config {
     type Err = err::Error;
 
-    /// Tries to load a config from a string.
+    /// Tries to load a config from a string, ignoring any profiles.
     fn from_str(s: &str) -> err::Result<Self> {
-        Ok(toml::from_str(s)?)
+        Self::from_str_with_profile(s, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::{NonZeroU64, NonZeroUsize};
+
+    /// Tests that the default config survives a round trip through TOML.
+    #[test]
+    fn test_default_round_trip() -> err::Result<()> {
+        let dumped = Config::default().to_string()?;
+        let config: Config = dumped.parse()?;
+        assert_eq!(config.to_string()?, dumped);
+        Ok(())
+    }
+
+    /// Tests that fuzzing specs parse from TOML tables.
+    #[test]
+    fn test_parse_fuzz() -> err::Result<()> {
+        let config: Config =
+            "[fuzz]\nx = { min = 0, max = 4 }\ny = { min = 0.0, max = 1.0 }".parse()?;
+        assert_eq!(config.fuzz["x"], fuzz::Distribution::Int { min: 0, max: 4 });
+        assert_eq!(
+            config.fuzz["y"],
+            fuzz::Distribution::Float { min: 0.0, max: 1.0 }
+        );
+        Ok(())
+    }
+
+    /// Tests that wall-clock periods sit alongside the iteration strategy.
+    #[test]
+    fn test_parse_period_ms() -> err::Result<()> {
+        let config: Config =
+            "[iter]\naction = \"exit\"\niterations = 10\nperiod_ms = 250\nwarmup = 5".parse()?;
+        assert_eq!(
+            config.iter.strategy.iterations().map(NonZeroUsize::get),
+            Some(10)
+        );
+        assert_eq!(
+            config.iter.period_duration(),
+            Some(std::time::Duration::from_millis(250))
+        );
+
+        assert_eq!(config.iter.warmup_iterations(), 5);
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.iter.period_ms, config.iter.period_ms);
+        assert_eq!(dumped.iter.warmup, config.iter.warmup);
+        Ok(())
+    }
+
+    /// Tests that counts and durations can be strings with units, and that
+    /// they dump back as plain integers.
+    #[test]
+    fn test_parse_units() -> err::Result<()> {
+        let config: Config = "checkpoint_ms = \"1m\"\n[iter]\naction = \"exit-and-rotate\"\niterations = \"5M\"\nperiod = \"100k\"\nperiod_ms = \"2m30s\"\nwarmup = 5".parse()?;
+        assert_eq!(
+            config.iter.strategy.iterations().map(NonZeroUsize::get),
+            Some(5_000_000)
+        );
+        assert_eq!(
+            config.iter.strategy.period().map(NonZeroUsize::get),
+            Some(100_000)
+        );
+        assert_eq!(
+            config.iter.period_duration(),
+            Some(std::time::Duration::from_secs(150))
+        );
+        assert_eq!(config.checkpoint_ms.map(NonZeroU64::get), Some(60_000));
+        assert_eq!(config.iter.warmup_iterations(), 5);
+
+        let dumped = config.to_string()?;
+        assert!(dumped.contains("period_ms = 150000"), "{dumped}");
+        let dumped: Config = dumped.parse()?;
+        assert_eq!(dumped.iter.strategy.period(), config.iter.strategy.period());
+
+        for bad in [
+            "[iter]\naction = \"exit\"\niterations = \"5m\"",
+            "[iter]\naction = \"exit\"\niterations = 0",
+            "[iter]\nperiod_ms = \"1500us\"",
+            "[iter]\nwarmup = -1",
+        ] {
+            assert!(bad.parse::<Config>().is_err(), "{bad}");
+        }
+        Ok(())
+    }
+
+    /// Tests that pause cycles parse, with a default length, and aren't halt
+    /// rules.
+    #[test]
+    fn test_parse_pause() -> err::Result<()> {
+        let config: Config = "[iter]\naction = \"no-halt\"\npause_every = 10000".parse()?;
+        assert_eq!(
+            config.iter.pause(),
+            Some(halt::Pause {
+                every: NonZeroUsize::new(10000).unwrap(),
+                length: std::time::Duration::from_millis(50)
+            })
+        );
+        assert_eq!(config.iter.halt_rules().count(), 0);
+
+        let config: Config =
+            "[iter]\naction = \"no-halt\"\npause_every = 5\npause_ms = 20".parse()?;
+        assert_eq!(
+            config.iter.pause().map(|p| p.length),
+            Some(std::time::Duration::from_millis(20))
+        );
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.iter.pause(), config.iter.pause());
+        Ok(())
+    }
+
+    /// Tests that sampling rules parse, with interval sampling taking
+    /// precedence over random sampling.
+    #[test]
+    fn test_parse_sampling() -> err::Result<()> {
+        let config: Config =
+            "[iter]\naction = \"no-halt\"\nsample_probability = 0.25\nsample_seed = 7".parse()?;
+        assert_eq!(
+            config.iter.sampling(8),
+            Some(crate::model::sampling::Rule::Random {
+                probability: 0.25,
+                seed: 7
+            })
+        );
+
+        let config: Config =
+            "[iter]\naction = \"no-halt\"\nsample_every = 8\nsample_probability = 0.25".parse()?;
+        assert_eq!(
+            config.iter.sampling(8),
+            Some(crate::model::sampling::Rule::Every {
+                n: NonZeroUsize::new(8).unwrap()
+            })
+        );
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.iter.sampling(8), config.iter.sampling(8));
+
+        let config: Config = "[iter]\naction = \"no-halt\"\nsample_probability = 0.25".parse()?;
+        assert_eq!(
+            config.iter.sampling(8),
+            Some(crate::model::sampling::Rule::Random {
+                probability: 0.25,
+                seed: 8
+            })
+        );
+        Ok(())
+    }
+
+    /// Tests that check frequencies parse, and survive a round trip.
+    #[test]
+    fn test_parse_check_frequency() -> err::Result<()> {
+        let config: Config = "check_frequency = \"every-50\"".parse()?;
+        assert_eq!(
+            config.check_frequency,
+            check::Frequency::Every(NonZeroUsize::new(50).unwrap())
+        );
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.check_frequency, config.check_frequency);
+
+        let result = "check_frequency = \"sometimes\"".parse::<Config>();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    /// Tests that skew delays parse from strings keyed by thread ID, and
+    /// survive a round trip.
+    #[test]
+    fn test_parse_skew() -> err::Result<()> {
+        let config: Config = "[skew]\n\"0\" = \"0..500ns\"\n\"1\" = \"2us\"".parse()?;
+        assert_eq!(
+            config.skew["0"],
+            skew::Delay {
+                min: std::time::Duration::ZERO,
+                max: std::time::Duration::from_nanos(500)
+            }
+        );
+        assert_eq!(
+            config.skew["1"],
+            skew::Delay::fixed(std::time::Duration::from_micros(2))
+        );
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.skew, config.skew);
+
+        assert!("[skew]\n\"0\" = \"500\"".parse::<Config>().is_err());
+        Ok(())
+    }
+
+    /// Tests that priorities parse, keyed by thread ID or `*`, and survive a
+    /// round trip.
+    #[test]
+    fn test_parse_priority() -> err::Result<()> {
+        let config: Config = "[priority]\n\"*\" = \"nice:10\"\n\"1\" = \"fifo:20\"".parse()?;
+        assert_eq!(config.priority["*"], priority::Priority::Nice(10));
+        assert_eq!(config.priority["1"], priority::Priority::Fifo(20));
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.priority, config.priority);
+
+        assert!("[priority]\n\"0\" = \"nice:99\"".parse::<Config>().is_err());
+        Ok(())
+    }
+
+    /// Tests that a minimum rate becomes an exit rule over the rate window.
+    #[test]
+    fn test_parse_min_rate() -> err::Result<()> {
+        let config: Config = "[iter]\naction = \"no-halt\"\nmin_rate = 50.0".parse()?;
+        assert_eq!(config.iter.min_rate, Some(50.0));
+        assert_eq!(config.iter.rate_window(), std::time::Duration::from_secs(1));
+        let rules: Vec<_> = config.iter.halt_rules().collect();
+        assert!(matches!(
+            rules.as_slice(),
+            [halt::Rule {
+                condition: halt::Condition::MinRate { window, .. },
+                halt_type: halt::Type::Exit,
+            }] if *window == std::time::Duration::from_secs(1)
+        ));
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.iter.min_rate, config.iter.min_rate);
+        Ok(())
+    }
+
+    /// Tests that interesting states parse as partial valuations and survive
+    /// dumping.
+    #[test]
+    fn test_parse_interesting() -> err::Result<()> {
+        let config: Config =
+            "exit_on_interesting = true\n[interesting]\nweak = { \"0:r0\" = 0, \"1:r0\" = 0 }"
+                .parse()?;
+        let weak = &config.interesting["weak"];
+        assert_eq!(weak.0["0:r0"], interest::Literal::Int(0));
+        let others = config.iter.halt_rules().count() + config.check.halt_rules().count();
+        assert_eq!(config.halt_rules().count(), others + 1);
+        // Shrinking keeps the rule, as it doesn't depend on iterations.
+        assert_eq!(
+            config.outcome_halt_rules().count(),
+            config.check.halt_rules().count() + 1
+        );
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.interesting, config.interesting);
+        Ok(())
+    }
+
+    /// Tests that forbidden states and the check mode parse.
+    #[test]
+    fn test_parse_forbidden() -> err::Result<()> {
+        let config: Config = "check_mode = \"any\"\n[forbidden]\nweak = { x = 1 }".parse()?;
+        assert_eq!(config.check_mode, abs::check::Mode::Any);
+        assert_eq!(config.forbidden["weak"].0["x"], interest::Literal::Int(1));
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.forbidden, config.forbidden);
+        assert_eq!(dumped.check_mode, config.check_mode);
+        Ok(())
+    }
+
+    /// Tests that aggregate rules parse and survive dumping.
+    #[test]
+    fn test_parse_aggregate() -> err::Result<()> {
+        let config: Config = "[aggregate.seen]\nstate = { x = 1 }\nmin = 1".parse()?;
+        assert_eq!(config.aggregate["seen"].min, Some(1));
+        assert_eq!(
+            config.aggregate["seen"].state.0["x"],
+            interest::Literal::Int(1)
+        );
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.aggregate, config.aggregate);
+        Ok(())
+    }
+
+    /// Tests that observer seeds survive dumping alongside tables.
+    #[test]
+    fn test_dump_observer_seed() -> err::Result<()> {
+        let config = Config {
+            observer_seed: Some(3),
+            ..Config::default()
+        };
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.observer_seed, Some(3));
+        Ok(())
+    }
+
+    /// Tests that dumps with a run seed carry it, overriding any in the
+    /// config.
+    #[test]
+    fn test_dump_with_seed() -> err::Result<()> {
+        let config = Config {
+            seed: Some(3),
+            observer_seed: Some(4),
+            ..Config::default()
+        };
+        let dumped: Config = config.to_string_with_seed(5)?.parse()?;
+        assert_eq!((dumped.seed, dumped.observer_seed), (Some(5), Some(4)));
+        Ok(())
+    }
+
+    /// Tests that check descriptions survive dumping alongside tables.
+    #[test]
+    fn test_dump_check_description() -> err::Result<()> {
+        let config: Config = "check_description = \"forbid x=0\"\n[format]\nx = \"hex\"".parse()?;
+        assert_eq!(config.check_description.as_deref(), Some("forbid x=0"));
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.check_description, config.check_description);
+        Ok(())
+    }
+
+    /// Tests that expected outcomes survive dumping alongside tables.
+    #[test]
+    fn test_dump_expect_outcome() -> err::Result<()> {
+        let config: Config = "expect_outcome = \"fail\"\n[format]\nx = \"hex\"".parse()?;
+        assert_eq!(config.expect_outcome, Some(expectation::Expectation::Fail));
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.expect_outcome, config.expect_outcome);
+        assert!(Config::default().expect_outcome.is_none());
+        Ok(())
+    }
+
+    /// Tests that checkpoint settings survive dumping alongside tables, and
+    /// that checkpoints sit in their directory by test name.
+    #[test]
+    fn test_dump_checkpoint() -> err::Result<()> {
+        let config: Config =
+            "checkpoint_ms = 500\ncheckpoint_dir = \"ckpt\"\n[format]\nx = \"hex\"".parse()?;
+        let rule = config.checkpoint("sb").expect("checkpointing should be on");
+        assert_eq!(rule.every, std::time::Duration::from_millis(500));
+        assert_eq!(rule.path, std::path::Path::new("ckpt/sb.checkpoint.json"));
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.checkpoint_ms, config.checkpoint_ms);
+        assert_eq!(dumped.checkpoint_dir, config.checkpoint_dir);
+        assert!(Config::default().checkpoint("sb").is_none());
+        Ok(())
+    }
+
+    /// Tests that variable masks parse alongside tables.
+    #[test]
+    fn test_parse_mask() -> err::Result<()> {
+        let config: Config = "mask = [\"scratch\"]\n[format]\nx = \"hex\"".parse()?;
+        assert_eq!(config.mask, ["scratch"]);
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.mask, config.mask);
+        Ok(())
+    }
+
+    /// Tests that thread count overrides survive dumping.
+    #[test]
+    fn test_parse_threads() -> err::Result<()> {
+        let config: Config = "threads = 4".parse()?;
+        assert_eq!(config.threads.map(NonZeroUsize::get), Some(4));
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.threads, config.threads);
+        Ok(())
+    }
+
+    /// Tests that CPU sets parse from strings, and survive dumping.
+    #[test]
+    fn test_parse_cpus() -> err::Result<()> {
+        let config: Config = "cpus = \"0-1,3\"".parse()?;
+        let cpus = config.cpus.as_ref().expect("should have CPUs");
+        assert_eq!(cpus.iter().collect::<Vec<_>>(), [0, 1, 3]);
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.cpus, config.cpus);
+        assert!("cpus = \"2-1\"".parse::<Config>().is_err());
+        Ok(())
+    }
+
+    /// Tests that inline tests parse, compile, and survive dumping.
+    #[test]
+    fn test_parse_inline() -> err::Result<()> {
+        let config: Config =
+            "[inline.sb]\nthreads = [\"store x 1; r0 = load y\", \"store y 1; r0 = load x\"]\ninit = { x = 0 }"
+                .parse()?;
+        let manifest = abs::Entry::make_manifest(&config.inline_test("sb")?).unwrap();
+        assert_eq!(manifest.n_threads.get(), 2);
+        assert!(matches!(
+            config.inline_test("mp"),
+            Err(err::Error::UnknownInlineTest(_))
+        ));
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.inline, config.inline);
+        Ok(())
+    }
+
+    /// Tests that alternating synchronisers starts with the main one, and
+    /// names each by its strategy.
+    #[test]
+    fn test_parse_alternate_sync() -> err::Result<()> {
+        let config: Config = "sync = \"barrier\"\n[alternate_sync]\nwith = \"spinner\"".parse()?;
+        let alternation = config.sync_alternation().unwrap();
+        assert_eq!(alternation.names(), ["barrier", "spinner"]);
+        assert_eq!(alternation.every.get(), 1);
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert!(dumped.alternate_sync.is_some());
+        assert!(Config::default().sync_alternation().is_none());
+        Ok(())
+    }
+
+    /// Tests that resource limits parse with units, and that zero limits
+    /// are rejected.
+    #[test]
+    fn test_parse_limits() -> err::Result<()> {
+        let config: Config = "[limits]\ncpu_secs = \"2m\"\nmemory_bytes = \"512MiB\"".parse()?;
+        let limits = config.limits.to_limits();
+        assert_eq!(limits.cpu, Some(std::time::Duration::from_mins(2)));
+        assert_eq!(limits.memory.map(NonZeroU64::get), Some(512 << 20));
+
+        let dumped: Config = config.to_string()?.parse()?;
+        assert_eq!(dumped.limits, config.limits);
+        assert!(Config::default().limits.to_limits().is_empty());
+        assert!("[limits]\nmemory_bytes = 0".parse::<Config>().is_err());
+        Ok(())
+    }
+
+    /// Gets the TOML key of the validation error from parsing `s`, if any.
+    fn invalid_key(s: &str) -> Option<String> {
+        match s.parse::<Config>() {
+            Err(err::Error::Invalid { key, .. }) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Tests that loading a config rejects inconsistent values, naming
+    /// their keys.
+    #[test]
+    fn test_validate() {
+        let cases = [
+            (
+                "[iter]\naction = \"exit-and-rotate\"\niterations = 10\nperiod = 10",
+                "iter.period",
+            ),
+            (
+                "[iter]\naction = \"no-halt\"\nsample_probability = 1.5",
+                "iter.sample_probability",
+            ),
+            ("[fuzz]\n\"0:r0\" = { min = 4, max = 0 }", "fuzz.\"0:r0\""),
+            (
+                "[aggregate.rare]\nmin = 10\nmax = 1\nstate = { x = 1 }",
+                "aggregate.rare",
+            ),
+            (
+                "[aggregate.rare]\nmax_fraction = 2.0\nstate = { x = 1 }",
+                "aggregate.rare.max_fraction",
+            ),
+            (
+                "check_frequency = \"deferred\"\nmask = [\"x\"]",
+                "check_frequency",
+            ),
+            (
+                "[inline.sb]\nthreads = [\"store r0 1\"]",
+                "inline.sb.threads",
+            ),
+            (
+                "[alternate_sync]\nwith = \"spinner\"",
+                "alternate_sync.with",
+            ),
+            (
+                "[iter]\naction = \"exit\"\niterations = 10\n[alternate_sync]\nwith = \"barrier\"",
+                "alternate_sync",
+            ),
+        ];
+        for (toml, key) in cases {
+            assert_eq!(invalid_key(toml).as_deref(), Some(key), "{toml}");
+        }
+        assert_eq!(invalid_key("check_frequency = \"deferred\""), None);
+    }
+
+    /// Tests that checking a config against a manifest rejects unknown
+    /// variables, naming their keys.
+    #[test]
+    fn test_validate_vars() -> err::Result<()> {
+        let mut m = manifest::Manifest {
+            n_threads: NonZeroUsize::new(1).unwrap(),
+            i32s: manifest::VarMap::new(),
+            bools: manifest::VarMap::new(),
+            f64s: manifest::VarMap::new(),
+            scalable_role: None,
+            symmetry: vec![0],
+            invariants: std::collections::BTreeMap::default(),
+            aux_flags: vec![],
+            declared: vec![],
+        };
+        m.i32s.insert(
+            "x".into(),
+            manifest::VarRecord {
+                initial_value: None,
+                slot: crate::model::slot::Slot {
+                    is_atomic: false,
+                    index: 0,
+                },
+            },
+        );
+
+        let config: Config = "mask = [\"x\"]\n[forbidden]\none = { x = 1 }".parse()?;
+        config.validate_vars(&m)?;
+        let config: Config = "[forbidden]\nweak = { x = 1, y = 0 }".parse()?;
+        let result = config.validate_vars(&m);
+        assert!(
+            matches!(result, Err(err::Error::Invalid { key, .. }) if key == "forbidden.weak.y")
+        );
+        let config: Config = "[fuzz]\nz = { min = 0, max = 1 }".parse()?;
+        let result = config.validate_vars(&m);
+        assert!(matches!(result, Err(err::Error::Invalid { key, .. }) if key == "fuzz.z"));
+        let config: Config = "[projections]\nflags = [\"x\", \"y\"]".parse()?;
+        let result = config.validate_vars(&m);
+        assert!(
+            matches!(result, Err(err::Error::Invalid { key, .. }) if key == "projections.flags")
+        );
+        Ok(())
     }
 }
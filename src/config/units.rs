@@ -0,0 +1,574 @@
+//! Human-friendly counts, sizes, and durations.
+//!
+//! Counts can carry a `k`, `M`, or `G` suffix (so `5M` is five million), can
+//! separate digit groups with `_`, and can have a decimal part as long as the
+//! whole comes out to a whole number (so `2.5k` is fine, but `2.5` isn't).
+//!
+//! Sizes, in bytes, are the same but with binary suffixes: `K`, `M`, `G`, and
+//! `T`, or `KiB`, `MiB`, `GiB`, and `TiB`, are powers of 1024, and `B` is a
+//! byte (so `1.5G` is 1536 MiB).
+//!
+//! Durations are sequences of amounts with units, such as `2m30s` or `1.5s`,
+//! taking the units `ns`, `us`, `ms`, `s`, `m`, and `h`.  A bare number with
+//! no unit takes the unit that the option it configures has always taken, so
+//! that `period_ms = 500` still means half a second.
+//!
+//! The command line parses options through [count], [bytes], and [duration]; the config
+//! file parses the same options through the deserialisers in [de], which
+//! accept either a TOML integer or a string in the formats above.
+
+use std::{num::NonZeroUsize, time::Duration};
+use thiserror::Error;
+
+/// An error parsing a count or duration.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    /// There was nothing to parse.
+    #[error("no value given")]
+    Empty,
+    /// We expected a number at the start of this part of the input.
+    #[error("expected a number at {0:?}")]
+    NotANumber(String),
+    /// A duration gave some of its numbers units, but not all.
+    #[error("{0:?} needs a unit after every number")]
+    MissingUnit(String),
+    /// A number had a unit or suffix we don't recognise.
+    #[error("unknown unit {unit:?}; expected one of {expected}")]
+    UnknownUnit {
+        /// The offending unit.
+        unit: String,
+        /// The units we would have accepted.
+        expected: &'static str,
+    },
+    /// A count didn't come out to a whole number.
+    #[error("{0:?} is not a whole number")]
+    NotWhole(String),
+    /// The input was too large to represent.
+    #[error("{0:?} is too large")]
+    Overflow(String),
+    /// A duration didn't come out to a whole number of the smallest unit
+    /// that the option can hold.
+    #[error("{input:?} is not a whole number of {unit}")]
+    Fractional {
+        /// The offending input.
+        input: String,
+        /// The smallest unit that the option can hold.
+        unit: &'static str,
+    },
+    /// The input was zero, and the option needs something above zero.
+    #[error("{0:?} must be above zero")]
+    Zero(String),
+}
+
+/// Results over [Error].
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The suffixes a count can take, and their multipliers.
+const COUNT_SUFFIXES: &[(&str, u128)] = &[
+    ("", 1),
+    ("k", 1_000),
+    ("K", 1_000),
+    ("M", 1_000_000),
+    ("G", 1_000_000_000),
+];
+
+/// The suffixes a size can take, and their multipliers.
+const SIZE_SUFFIXES: &[(&str, u128)] = &[
+    ("", 1),
+    ("B", 1),
+    ("K", 1 << 10),
+    ("KiB", 1 << 10),
+    ("M", 1 << 20),
+    ("MiB", 1 << 20),
+    ("G", 1 << 30),
+    ("GiB", 1 << 30),
+    ("T", 1 << 40),
+    ("TiB", 1 << 40),
+];
+
+/// The units a duration can take, and their lengths in nanoseconds.
+const DURATION_UNITS: &[(&str, u128)] = &[
+    ("ns", 1),
+    ("us", 1_000),
+    ("µs", 1_000),
+    ("ms", 1_000_000),
+    ("s", 1_000_000_000),
+    ("m", 60_000_000_000),
+    ("h", 3_600_000_000_000),
+];
+
+/// Parses a count, which may be zero.
+///
+/// # Errors
+///
+/// Fails if `s` isn't a number with an optional `k`, `M`, or `G` suffix, or
+/// if it doesn't come out to a whole number that fits in a `usize`.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::config::units::{count, Error};
+///
+/// assert_eq!(count("1_000"), Ok(1000));
+/// assert_eq!(count("5M"), Ok(5_000_000));
+/// assert_eq!(count("2.5k"), Ok(2500));
+/// assert!(matches!(count("2.5"), Err(Error::NotWhole(_))));
+/// assert!(matches!(count("5m"), Err(Error::UnknownUnit { .. })));
+/// ```
+pub fn count(s: &str) -> Result<usize> {
+    let s = s.trim();
+    let whole = scaled(s, COUNT_SUFFIXES, "k, M, or G")?;
+    usize::try_from(whole).map_err(|_| Error::Overflow(s.to_owned()))
+}
+
+/// Parses a count that must be above zero.
+///
+/// # Errors
+///
+/// Fails as [count] does, and also if the count is zero.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::config::units::{nonzero_count, Error};
+///
+/// assert_eq!(nonzero_count("10k").map(|n| n.get()), Ok(10_000));
+/// assert_eq!(nonzero_count("0k"), Err(Error::Zero("0k".to_owned())));
+/// ```
+pub fn nonzero_count(s: &str) -> Result<NonZeroUsize> {
+    NonZeroUsize::new(count(s)?).ok_or_else(|| Error::Zero(s.trim().to_owned()))
+}
+
+/// Parses a size in bytes.
+///
+/// # Errors
+///
+/// Fails if `s` isn't a number with an optional size suffix, or if it
+/// doesn't come out to a whole number of bytes that fits in a `u64`.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::config::units::{bytes, Error};
+///
+/// assert_eq!(bytes("4096"), Ok(4096));
+/// assert_eq!(bytes("512MiB"), Ok(512 << 20));
+/// assert_eq!(bytes("1.5G"), Ok(1536 << 20));
+/// assert!(matches!(bytes("1.5B"), Err(Error::NotWhole(_))));
+/// assert!(matches!(bytes("2GB"), Err(Error::UnknownUnit { .. })));
+/// ```
+pub fn bytes(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let whole = scaled(s, SIZE_SUFFIXES, "B, K, M, G, T, KiB, MiB, GiB, or TiB")?;
+    u64::try_from(whole).map_err(|_| Error::Overflow(s.to_owned()))
+}
+
+/// Parses a number with one of `suffixes`, which it must come out to a whole
+/// multiple of; `expected` lists the suffixes for errors.
+fn scaled(s: &str, suffixes: &[(&str, u128)], expected: &'static str) -> Result<u128> {
+    let (n, suffix) = split_number(s)?;
+    let Some(&(_, mul)) = suffixes.iter().find(|(x, _)| *x == suffix) else {
+        return Err(Error::UnknownUnit {
+            unit: suffix.to_owned(),
+            expected,
+        });
+    };
+    n.times(mul, s)?
+        .ok_or_else(|| Error::NotWhole(s.to_owned()))
+}
+
+/// Parses a duration, reading a bare number as a multiple of `bare`.
+///
+/// # Errors
+///
+/// Fails if `s` is neither a bare number nor a sequence of numbers with
+/// units, or if it doesn't come out to a whole number of nanoseconds that
+/// fits in a [Duration].
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::config::units::duration;
+/// use std::time::Duration;
+///
+/// let secs = Duration::from_secs(1);
+/// assert_eq!(duration("2m30s", secs), Ok(Duration::from_secs(150)));
+/// assert_eq!(duration("250ms", secs), Ok(Duration::from_millis(250)));
+/// assert_eq!(duration("1.5h", secs), Ok(Duration::from_secs(5400)));
+/// assert_eq!(duration("90", secs), Ok(Duration::from_secs(90)));
+/// assert!(duration("2x", secs).is_err());
+/// ```
+pub fn duration(s: &str, bare: Duration) -> Result<Duration> {
+    let s = s.trim();
+    let (n, unit) = split_number(s)?;
+    if unit.is_empty() {
+        let nanos = n.times(bare.as_nanos(), s)?.ok_or_else(|| fractional(s))?;
+        return from_nanos(nanos, s);
+    }
+
+    let mut nanos: u128 = 0;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let (n, tail) = split_number(rest)?;
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        if unit.is_empty() {
+            return Err(Error::MissingUnit(s.to_owned()));
+        }
+        let Some(&(_, scale)) = DURATION_UNITS.iter().find(|(x, _)| *x == unit) else {
+            return Err(Error::UnknownUnit {
+                unit: unit.to_owned(),
+                expected: "ns, us, ms, s, m, or h",
+            });
+        };
+        let part = n.times(scale, s)?.ok_or_else(|| fractional(s))?;
+        nanos = nanos
+            .checked_add(part)
+            .ok_or_else(|| Error::Overflow(s.to_owned()))?;
+        rest = tail;
+    }
+    from_nanos(nanos, s)
+}
+
+/// Parses a duration as a whole number of milliseconds, reading a bare
+/// number as milliseconds.
+///
+/// # Errors
+///
+/// Fails as [duration] does, and also if the duration isn't a whole number
+/// of milliseconds.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::config::units::millis;
+///
+/// assert_eq!(millis("500"), Ok(500));
+/// assert_eq!(millis("2m30s"), Ok(150_000));
+/// assert!(millis("1500us").is_err());
+/// ```
+pub fn millis(s: &str) -> Result<u64> {
+    let d = duration(s, Duration::from_millis(1))?;
+    if d.subsec_nanos() % 1_000_000 != 0 {
+        return Err(Error::Fractional {
+            input: s.trim().to_owned(),
+            unit: "milliseconds",
+        });
+    }
+    u64::try_from(d.as_millis()).map_err(|_| Error::Overflow(s.trim().to_owned()))
+}
+
+/// Parses a duration as a whole number of seconds, reading a bare number as
+/// seconds.
+///
+/// # Errors
+///
+/// Fails as [duration] does, and also if the duration isn't a whole number
+/// of seconds.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::config::units::secs;
+///
+/// assert_eq!(secs("90"), Ok(90));
+/// assert_eq!(secs("1h"), Ok(3600));
+/// assert!(secs("1500ms").is_err());
+/// ```
+pub fn secs(s: &str) -> Result<u64> {
+    let d = duration(s, Duration::from_secs(1))?;
+    if d.subsec_nanos() != 0 {
+        return Err(Error::Fractional {
+            input: s.trim().to_owned(),
+            unit: "seconds",
+        });
+    }
+    Ok(d.as_secs())
+}
+
+/// A decimal number as written, before applying any unit.
+struct Decimal {
+    /// The digits of the number, ignoring the decimal point.
+    digits: u128,
+    /// The number of digits after the decimal point.
+    places: u32,
+}
+
+impl Decimal {
+    /// Multiplies this number by `scale`, returning `None` if the result
+    /// isn't whole.
+    ///
+    /// `input` is the number as written, for error messages.
+    fn times(&self, scale: u128, input: &str) -> Result<Option<u128>> {
+        let overflow = || Error::Overflow(input.to_owned());
+        let product = self.digits.checked_mul(scale).ok_or_else(overflow)?;
+        let divisor = 10u128.checked_pow(self.places).ok_or_else(overflow)?;
+        Ok((product % divisor == 0).then(|| product / divisor))
+    }
+}
+
+/// Splits a decimal number off the front of `s`, returning it along with the
+/// rest of `s`.
+fn split_number(s: &str) -> Result<(Decimal, &str)> {
+    if s.is_empty() {
+        return Err(Error::Empty);
+    }
+    let end = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '_' || c == '.'))
+        .unwrap_or(s.len());
+    let (number, rest) = s.split_at(end);
+    let not_a_number = || Error::NotANumber(s.to_owned());
+
+    let (whole, frac) = number.split_once('.').unwrap_or((number, ""));
+    if !whole.starts_with(|c: char| c.is_ascii_digit()) || frac.contains('.') {
+        return Err(not_a_number());
+    }
+    let mut n = Decimal {
+        digits: 0,
+        places: 0,
+    };
+    for c in whole.chars().chain(frac.chars()).filter(|&c| c != '_') {
+        let digit = u128::from(c.to_digit(10).ok_or_else(not_a_number)?);
+        n.digits = n
+            .digits
+            .checked_mul(10)
+            .and_then(|x| x.checked_add(digit))
+            .ok_or_else(|| Error::Overflow(s.to_owned()))?;
+    }
+    n.places = u32::try_from(frac.chars().filter(|&c| c != '_').count())
+        .map_err(|_| Error::Overflow(s.to_owned()))?;
+    Ok((n, rest))
+}
+
+/// Makes an error for a duration that isn't a whole number of nanoseconds.
+fn fractional(input: &str) -> Error {
+    Error::Fractional {
+        input: input.to_owned(),
+        unit: "nanoseconds",
+    }
+}
+
+/// Converts a number of nanoseconds parsed from `input` into a [Duration].
+fn from_nanos(nanos: u128, input: &str) -> Result<Duration> {
+    let secs =
+        u64::try_from(nanos / 1_000_000_000).map_err(|_| Error::Overflow(input.to_owned()))?;
+    // This is always below a billion, so it always fits.
+    #[allow(clippy::cast_possible_truncation)]
+    let subsec = (nanos % 1_000_000_000) as u32;
+    Ok(Duration::new(secs, subsec))
+}
+
+/// Deserialisers that accept either a TOML integer or a string in the
+/// formats above, for use with `#[serde(deserialize_with = ...)]`.
+pub mod de {
+    use serde::{de, Deserializer};
+    use std::{
+        fmt,
+        num::{NonZeroU64, NonZeroUsize},
+    };
+
+    /// A count or duration as written in the config.
+    enum Raw {
+        /// A plain integer, in the option's own unit.
+        Int(u64),
+        /// A string, to parse with a unit or suffix.
+        Str(String),
+    }
+
+    struct RawVisitor;
+
+    impl de::Visitor<'_> for RawVisitor {
+        type Value = Raw;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a non-negative integer, or a string such as \"5M\" or \"2m30s\"")
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<Raw, E> {
+            Ok(Raw::Int(v))
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<Raw, E> {
+            u64::try_from(v)
+                .map(Raw::Int)
+                .map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Raw, E> {
+            Ok(Raw::Str(v.to_owned()))
+        }
+    }
+
+    fn raw<'de, D: Deserializer<'de>>(d: D) -> Result<Raw, D::Error> {
+        d.deserialize_any(RawVisitor)
+    }
+
+    /// Deserialises a count that must be above zero.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the value is neither an integer nor a string that parses as
+    /// a count, or if it is zero.
+    pub fn count<'de, D: Deserializer<'de>>(d: D) -> Result<NonZeroUsize, D::Error> {
+        let n = match raw(d)? {
+            Raw::Int(n) => usize::try_from(n).map_err(|_| super::Error::Overflow(n.to_string())),
+            Raw::Str(s) => super::count(&s),
+        }
+        .map_err(de::Error::custom)?;
+        NonZeroUsize::new(n).ok_or_else(|| de::Error::custom(super::Error::Zero(n.to_string())))
+    }
+
+    /// Deserialises an optional count that must be above zero.
+    ///
+    /// # Errors
+    ///
+    /// Fails as [count] does.
+    pub fn option_count<'de, D: Deserializer<'de>>(d: D) -> Result<Option<NonZeroUsize>, D::Error> {
+        count(d).map(Some)
+    }
+
+    /// Deserialises an optional duration, in milliseconds, that must be
+    /// above zero.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the value is neither an integer nor a string that parses as
+    /// a whole number of milliseconds, or if it is zero.
+    pub fn option_millis<'de, D: Deserializer<'de>>(d: D) -> Result<Option<NonZeroU64>, D::Error> {
+        option_nonzero(d, super::millis)
+    }
+
+    /// Deserialises an optional duration, in seconds, that must be above
+    /// zero.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the value is neither an integer nor a string that parses as
+    /// a whole number of seconds, or if it is zero.
+    pub fn option_secs<'de, D: Deserializer<'de>>(d: D) -> Result<Option<NonZeroU64>, D::Error> {
+        option_nonzero(d, super::secs)
+    }
+
+    /// Deserialises an optional size, in bytes, that must be above zero.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the value is neither an integer nor a string that parses as
+    /// a size, or if it is zero.
+    pub fn option_bytes<'de, D: Deserializer<'de>>(d: D) -> Result<Option<NonZeroU64>, D::Error> {
+        option_nonzero(d, super::bytes)
+    }
+
+    /// Deserialises an integer, or a string parsed with `parse`, that must
+    /// be above zero.
+    fn option_nonzero<'de, D: Deserializer<'de>>(
+        d: D,
+        parse: fn(&str) -> super::Result<u64>,
+    ) -> Result<Option<NonZeroU64>, D::Error> {
+        let n = match raw(d)? {
+            Raw::Int(n) => n,
+            Raw::Str(s) => parse(&s).map_err(de::Error::custom)?,
+        };
+        NonZeroU64::new(n)
+            .map(Some)
+            .ok_or_else(|| de::Error::custom(super::Error::Zero(n.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts accept digit separators, suffixes, and whole decimals.
+    #[test]
+    fn test_count() {
+        assert_eq!(count("0"), Ok(0));
+        assert_eq!(count(" 100k "), Ok(100_000));
+        assert_eq!(count("1_000_000"), Ok(1_000_000));
+        assert_eq!(count("1.25M"), Ok(1_250_000));
+        assert_eq!(count("3G"), Ok(3_000_000_000));
+    }
+
+    /// Bad counts say precisely what went wrong.
+    #[test]
+    fn test_count_errors() {
+        assert_eq!(count(""), Err(Error::Empty));
+        assert_eq!(count("k"), Err(Error::NotANumber("k".to_owned())));
+        assert_eq!(count("1.2.3"), Err(Error::NotANumber("1.2.3".to_owned())));
+        assert_eq!(
+            count("5T"),
+            Err(Error::UnknownUnit {
+                unit: "T".to_owned(),
+                expected: "k, M, or G"
+            })
+        );
+        assert_eq!(count("1.0001k"), Err(Error::NotWhole("1.0001k".to_owned())));
+        assert!(matches!(
+            count("99999999999999999999999999G"),
+            Err(Error::Overflow(_))
+        ));
+    }
+
+    /// Sizes take binary suffixes.
+    #[test]
+    fn test_bytes() {
+        assert_eq!(bytes("0"), Ok(0));
+        assert_eq!(bytes("10B"), Ok(10));
+        assert_eq!(bytes("2K"), bytes("2KiB"));
+        assert_eq!(bytes("3M"), Ok(3 << 20));
+        assert_eq!(bytes("1_024G"), Ok(1 << 40));
+        assert_eq!(bytes("0.5TiB"), Ok(1 << 39));
+        assert_eq!(
+            bytes("99999999999T"),
+            Err(Error::Overflow("99999999999T".to_owned()))
+        );
+        assert_eq!(bytes("1.0001K"), Err(Error::NotWhole("1.0001K".to_owned())));
+    }
+
+    /// Durations sum their parts, and bare numbers take the given unit.
+    #[test]
+    fn test_duration() {
+        let ms = Duration::from_millis(1);
+        assert_eq!(duration("1h2m3s", ms), Ok(Duration::from_secs(3723)));
+        assert_eq!(duration("1s500ms", ms), Ok(Duration::from_millis(1500)));
+        assert_eq!(duration("100us", ms), Ok(Duration::from_micros(100)));
+        assert_eq!(duration("7ns", ms), Ok(Duration::from_nanos(7)));
+        assert_eq!(duration("1.5", ms), Ok(Duration::from_micros(1500)));
+        assert_eq!(duration("0", ms), Ok(Duration::ZERO));
+    }
+
+    /// Bad durations say precisely what went wrong.
+    #[test]
+    fn test_duration_errors() {
+        let s = Duration::from_secs(1);
+        assert_eq!(duration("  ", s), Err(Error::Empty));
+        assert_eq!(
+            duration("2ms!", s).unwrap_err().to_string(),
+            "unknown unit \"ms!\"; expected one of ns, us, ms, s, m, or h"
+        );
+        assert_eq!(
+            duration("1m30", s),
+            Err(Error::MissingUnit("1m30".to_owned()))
+        );
+        assert_eq!(duration("1m.s", s), Err(Error::NotANumber(".s".to_owned())));
+        assert!(matches!(
+            duration("1.5ns", s),
+            Err(Error::Fractional { .. })
+        ));
+    }
+
+    /// Milliseconds reject anything finer than a millisecond.
+    #[test]
+    fn test_millis() {
+        assert_eq!(millis("1.5s"), Ok(1500));
+        assert_eq!(
+            millis("1500us"),
+            Err(Error::Fractional {
+                input: "1500us".to_owned(),
+                unit: "milliseconds"
+            })
+        );
+    }
+}
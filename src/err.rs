@@ -1,3 +1,5 @@
+use crate::model;
+use std::{any::Any, fmt::Display};
 use thiserror::Error;
 
 /// Enumeration of errors that can happen in phenolphthalein.
@@ -21,12 +23,235 @@ pub enum Error {
     #[error("couldn't dynamically load the test library")]
     DlopenFailed(#[from] dlopen::Error),
 
+    /// A test library couldn't be read at all.
+    #[error("couldn't read test library {0}")]
+    UnreadableTestLibrary(std::path::PathBuf, #[source] std::io::Error),
+
+    /// A test library isn't an object file.
+    #[error("{0} isn't a shared library")]
+    NotATestLibrary(std::path::PathBuf),
+
+    /// A test library was built for another architecture or platform.
+    #[error(
+        "test library {path} was built as {found}, but this machine is {expected}; \
+         recompile it for {expected}, or run it on a machine that matches"
+    )]
+    WrongTestLibraryArch {
+        /// The library.
+        path: std::path::PathBuf,
+        /// A description of the library's format and architecture.
+        found: String,
+        /// The architecture of this machine.
+        expected: &'static str,
+    },
+
+    /// A test library is an object file, but not a shared library.
+    #[error("test library {path} is a {kind}, not a shared library")]
+    NotSharedLibrary {
+        /// The library.
+        path: std::path::PathBuf,
+        /// The kind of object file that the library is.
+        kind: String,
+    },
+
+    /// A test library doesn't export the symbols that every test needs.
+    #[error("test library {path} doesn't export {}", .missing.join(", "))]
+    MissingTestSymbols {
+        /// The library.
+        path: std::path::PathBuf,
+        /// The missing symbols; alternatives are joined by `|`.
+        missing: Vec<String>,
+    },
+
+    /// A test library depends on a library that can't be found.
+    #[error("test library {path} needs {dependency}, which can't be found")]
+    MissingLibraryDependency {
+        /// The library.
+        path: std::path::PathBuf,
+        /// The dependency, as the loader names it.
+        dependency: String,
+    },
+
+    /// A test library refers to a symbol that nothing defines.
+    #[error("test library {path} refers to undefined symbol {symbol}")]
+    UndefinedLibrarySymbol {
+        /// The library.
+        path: std::path::PathBuf,
+        /// The undefined symbol.
+        symbol: String,
+    },
+
+    /// A test library exports no test body.
+    #[error("test library exports none of `test`, `test_iter`, and `test_locals`")]
+    NoTestBody,
+
+    /// A test library exports no manifest.
+    #[error("test library exports neither `manifest` nor `manifest_v2`")]
+    NoManifest,
+
+    /// A variable in a test's manifest is malformed.
+    #[error("bad manifest entry for variable {var:?}: {reason}")]
+    BadManifestVar {
+        /// The name of the variable, or the empty string if it has none.
+        var: String,
+        /// Why the entry is bad.
+        reason: String,
+    },
+
+    /// A test declares thread locals, but in a way that can't work.
+    #[error("test declares thread locals, but {0}")]
+    UnsupportedThreadLocals(&'static str),
+
+    /// An inline test (see [`crate::api::dsl`]) doesn't compile.
+    #[error("bad inline test{}: {reason}", thread.map_or_else(String::new, |t| format!(" (thread {t})")))]
+    BadInlineTest {
+        /// The thread whose program is bad, if the problem is in one thread.
+        thread: Option<usize>,
+        /// Why the test doesn't compile.
+        reason: String,
+    },
+
+    /// A run asked to audit allocations, but this build can't count them.
+    #[error(
+        "can't audit allocations: phenolphthalein was built without the `alloc-audit` feature"
+    )]
+    AllocAuditUnavailable,
+
+    /// A run asked to mark its phases for profilers, but can't open the
+    /// kernel's trace marker file.
+    #[error("can't open the trace marker file for profiler markers")]
+    MarkersUnavailable(#[source] std::io::Error),
+
+    /// Checks can't be deferred in this configuration.
+    #[error("can't defer checks: {0}")]
+    BadCheckFrequency(&'static str),
+
+    /// Synchronisers can't be alternated in this configuration.
+    #[error("can't alternate synchronisers: {0}")]
+    BadSyncAlternation(&'static str),
+
+    /// A fuzzing spec doesn't fit the test.
+    #[error("can't fuzz variable {var}: {reason}")]
+    BadFuzz {
+        /// The variable whose fuzzing spec is bad.
+        var: String,
+        /// Why the spec is bad.
+        reason: String,
+    },
+
+    /// A skew spec doesn't fit the test.
+    #[error("can't skew thread {thread}: {reason}")]
+    BadSkew {
+        /// The thread, as named in the skew spec.
+        thread: String,
+        /// Why the spec is bad.
+        reason: String,
+    },
+
+    /// A priority spec doesn't fit the test, or the platform won't grant it.
+    #[error("can't set the priority of thread {thread}: {reason}")]
+    BadPriority {
+        /// The thread, as named in the priority spec.
+        thread: String,
+        /// Why the spec is bad.
+        reason: String,
+    },
+
+    /// The test threads can't be restricted to the requested CPUs.
+    #[error("can't restrict test threads to CPUs {cpus}: {reason}")]
+    BadCpus {
+        /// The requested CPUs.
+        cpus: crate::model::cpu::Set,
+        /// Why the restriction is impossible.
+        reason: String,
+    },
+
+    /// A resource limit can't be applied to the process.
+    #[error("can't limit {resource}: {reason}")]
+    BadLimit {
+        /// The resource to limit.
+        resource: crate::run::limit::Resource,
+        /// Why the limit can't be applied.
+        reason: String,
+    },
+
+    /// The process exceeded one of its resource limits.
+    #[error("process exceeded its {} limit: {0}", .0.resource())]
+    LimitExceeded(crate::run::limit::Exceeded),
+
+    /// The runner can't write checkpoints where it was asked to.
+    #[error("can't write checkpoints to {path}: {reason}")]
+    BadCheckpoint {
+        /// The path of the checkpoint file.
+        path: std::path::PathBuf,
+        /// Why the runner can't write there.
+        reason: String,
+    },
+
+    /// A variable mask names a variable that isn't in the test.
+    #[error("can't mask variable {0}: the test has no such variable")]
+    UnknownMaskedVar(String),
+
+    /// A test declares an invariant on a variable that it doesn't have.
+    #[error("test declares an invariant on {0}, but has no such variable")]
+    UnknownInvariantVar(String),
+
+    /// A test declares more auxiliary flags than the environment has room for.
+    #[error("test declares {0} auxiliary flags, but can have at most {max}", max = crate::model::manifest::MAX_AUX_FLAGS)]
+    TooManyAuxFlags(usize),
+
+    /// A projection names a variable that the test doesn't have.
+    #[error("can't project onto {var} in {projection}: the test has no such variable")]
+    UnknownProjectedVar {
+        /// The name of the projection.
+        projection: String,
+        /// The variable it names.
+        var: String,
+    },
+
+    /// A test can't be scaled to the requested thread count.
+    #[error("can't run test with {threads} threads: {reason}")]
+    BadThreadCount {
+        /// The requested thread count.
+        threads: std::num::NonZeroUsize,
+        /// Why the test can't be scaled.
+        reason: crate::model::manifest::ScaleError,
+    },
+
+    /// Two tests run alongside each other don't have matching manifests.
+    #[error("can't compare tests with different manifests: {0}")]
+    MismatchedManifests(String),
+
+    /// A test changed its manifest after phenolphthalein read it.
+    #[error("test changed its manifest during the run: {0}")]
+    ManifestChanged(String),
+
     #[error("lock poisoned")]
     LockPoisoned,
 
-    /// A thread panicked (we don't yet try to recover the specific error).
-    #[error("thread panicked (FIXME: error unavailable)")]
-    ThreadPanic,
+    /// A thread panicked.
+    #[error("{0}")]
+    ThreadPanic(Panic),
+
+    /// A test body forked or spawned threads, and the run was guarding
+    /// against this.
+    #[error("test tainted the run: {0}")]
+    Tainted(model::taint::Taint),
+
+    /// A run's report didn't meet the run's expectation of its outcome.
+    #[error("run didn't meet its expectation: {0}")]
+    UnmetExpectation(model::expectation::Unmet),
+
+    /// A test run was aborted by another error, but produced a partial
+    /// report beforehand.
+    #[error("test run aborted")]
+    Aborted {
+        /// The error that caused the abort.
+        #[source]
+        cause: Box<Error>,
+        /// The report of observations made before the abort.
+        report: Box<model::Report>,
+    },
 
     /// Miscellaneous I/O error.
     #[error("I/O error")]
@@ -34,9 +259,231 @@ pub enum Error {
 }
 pub type Result<T> = std::result::Result<T, Error>;
 
+impl Error {
+    /// Splits off any partial report attached to this error.
+    ///
+    /// If this error is [`Error::Aborted`], this returns the cause and the
+    /// report; otherwise, it returns the error unchanged and no report.
+    #[must_use]
+    pub fn into_partial(self) -> (Self, Option<model::Report>) {
+        match self {
+            Self::Aborted { cause, report } => (*cause, Some(*report)),
+            e => (e, None),
+        }
+    }
+}
+
+/// Trait of errors that have stable, machine-readable codes.
+///
+/// Codes take the form `package.kind`, where `kind` is a kebab-case name for
+/// the error; tooling can rely on codes not changing meaning between
+/// versions.  Errors that wrap other coded errors should forward their code.
+pub trait Coded {
+    /// Gets this error's code.
+    fn code(&self) -> &'static str;
+
+    /// Gets any structured context for this error, as key-value pairs.
+    fn context(&self) -> Vec<(&'static str, String)> {
+        vec![]
+    }
+}
+
+impl Coded for Error {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::EnvAllocFailed => "run.env-alloc-failed",
+            Self::NotEnoughThreads => "run.not-enough-threads",
+            Self::TooManyThreadsForSpinner(_) => "run.too-many-threads",
+            Self::LockReleaseFailed => "run.lock-release-failed",
+            Self::DlopenFailed(_) => "run.dlopen-failed",
+            Self::UnreadableTestLibrary(..) => "run.unreadable-test-library",
+            Self::NotATestLibrary(_) => "run.not-a-test-library",
+            Self::WrongTestLibraryArch { .. } => "run.wrong-test-library-arch",
+            Self::NotSharedLibrary { .. } => "run.not-shared-library",
+            Self::MissingTestSymbols { .. } => "run.missing-test-symbols",
+            Self::MissingLibraryDependency { .. } => "run.missing-library-dependency",
+            Self::UndefinedLibrarySymbol { .. } => "run.undefined-library-symbol",
+            Self::NoTestBody => "run.no-test-body",
+            Self::NoManifest => "run.no-manifest",
+            Self::BadManifestVar { .. } => "run.bad-manifest-var",
+            Self::UnsupportedThreadLocals(_) => "run.unsupported-thread-locals",
+            Self::AllocAuditUnavailable => "run.alloc-audit-unavailable",
+            Self::MarkersUnavailable(_) => "run.markers-unavailable",
+            Self::BadCheckFrequency(_) => "run.bad-check-frequency",
+            Self::BadSyncAlternation(_) => "run.bad-sync-alternation",
+            Self::BadInlineTest { .. } => "run.bad-inline-test",
+            Self::BadFuzz { .. } => "run.bad-fuzz",
+            Self::BadSkew { .. } => "run.bad-skew",
+            Self::BadPriority { .. } => "run.bad-priority",
+            Self::BadCpus { .. } => "run.bad-cpus",
+            Self::BadLimit { .. } => "run.bad-limit",
+            Self::LimitExceeded(_) => "run.limit-exceeded",
+            Self::BadCheckpoint { .. } => "run.bad-checkpoint",
+            Self::UnknownMaskedVar(_) => "run.unknown-masked-var",
+            Self::UnknownInvariantVar(_) => "run.unknown-invariant-var",
+            Self::TooManyAuxFlags(_) => "run.too-many-aux-flags",
+            Self::UnknownProjectedVar { .. } => "run.unknown-projected-var",
+            Self::BadThreadCount { .. } => "run.bad-thread-count",
+            Self::MismatchedManifests(_) => "run.mismatched-manifests",
+            Self::ManifestChanged(_) => "run.manifest-changed",
+            Self::LockPoisoned => "run.lock-poisoned",
+            Self::ThreadPanic(_) => "run.thread-panic",
+            Self::Tainted(_) => "run.tainted",
+            Self::UnmetExpectation(_) => "run.unmet-expectation",
+            Self::Aborted { .. } => "run.aborted",
+            Self::IoError(_) => "run.io",
+        }
+    }
+
+    fn context(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::UnreadableTestLibrary(path, e) => {
+                vec![("path", path_string(path)), ("reason", e.to_string())]
+            }
+            Self::NotATestLibrary(path) => vec![("path", path_string(path))],
+            Self::WrongTestLibraryArch {
+                path,
+                found,
+                expected,
+            } => vec![
+                ("path", path_string(path)),
+                ("found", found.clone()),
+                ("expected", (*expected).to_owned()),
+            ],
+            Self::NotSharedLibrary { path, kind } => {
+                vec![("path", path_string(path)), ("kind", kind.clone())]
+            }
+            Self::MissingTestSymbols { path, missing } => {
+                vec![("path", path_string(path)), ("missing", missing.join(","))]
+            }
+            Self::MissingLibraryDependency { path, dependency } => vec![
+                ("path", path_string(path)),
+                ("dependency", dependency.clone()),
+            ],
+            Self::UndefinedLibrarySymbol { path, symbol } => {
+                vec![("path", path_string(path)), ("symbol", symbol.clone())]
+            }
+            Self::BadFuzz { var, reason } | Self::BadManifestVar { var, reason } => {
+                vec![("var", var.clone()), ("reason", reason.clone())]
+            }
+            Self::BadSkew { thread, reason } | Self::BadPriority { thread, reason } => {
+                vec![("thread", thread.clone()), ("reason", reason.clone())]
+            }
+            Self::BadCpus { cpus, reason } => {
+                vec![("cpus", cpus.to_string()), ("reason", reason.clone())]
+            }
+            Self::BadLimit { resource, reason } => {
+                vec![
+                    ("resource", resource.to_string()),
+                    ("reason", reason.clone()),
+                ]
+            }
+            Self::LimitExceeded(e) => e.context(),
+            Self::BadCheckpoint { path, reason } => vec![
+                ("path", path.to_string_lossy().into_owned()),
+                ("reason", reason.clone()),
+            ],
+            Self::UnknownMaskedVar(var) | Self::UnknownInvariantVar(var) => {
+                vec![("var", var.clone())]
+            }
+            Self::MarkersUnavailable(e) => vec![("reason", e.to_string())],
+            Self::TooManyAuxFlags(n) => vec![("flags", n.to_string())],
+            Self::UnknownProjectedVar { projection, var } => {
+                vec![("projection", projection.clone()), ("var", var.clone())]
+            }
+            Self::MismatchedManifests(reason) | Self::ManifestChanged(reason) => {
+                vec![("reason", reason.clone())]
+            }
+            Self::BadInlineTest { thread, reason } => {
+                let mut ctx: Vec<_> = thread
+                    .map(|t| ("thread", t.to_string()))
+                    .into_iter()
+                    .collect();
+                ctx.push(("reason", reason.clone()));
+                ctx
+            }
+            Self::UnsupportedThreadLocals(reason)
+            | Self::BadCheckFrequency(reason)
+            | Self::BadSyncAlternation(reason) => {
+                vec![("reason", (*reason).to_owned())]
+            }
+            Self::ThreadPanic(p) => p.context(),
+            Self::Tainted(t) => vec![
+                ("taint", t.cause.to_string()),
+                ("iteration", t.iteration.to_string()),
+                ("rotation", t.rotation.to_string()),
+            ],
+            Self::UnmetExpectation(u) => vec![
+                ("expected", u.expected.to_string()),
+                ("failures", u.failures.to_string()),
+            ],
+            Self::Aborted { cause, report } => {
+                let mut ctx = vec![("cause", cause.code().to_owned())];
+                ctx.extend(cause.context());
+                ctx.push(("partial_states", report.states.len().to_string()));
+                ctx
+            }
+            _ => vec![],
+        }
+    }
+}
+
+fn path_string(path: &std::path::Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
 impl<T> From<std::sync::PoisonError<T>> for Error {
     fn from(_: std::sync::PoisonError<T>) -> Self {
         // TODO(@MattWindsor91): use the error somehow?
         Self::LockPoisoned
     }
 }
+
+/// Information about a panic in a thread.
+#[derive(Clone, Debug)]
+pub struct Panic {
+    /// The ID of the test thread that panicked, if the panic came from one.
+    pub tid: Option<usize>,
+    /// The panic message, if we could recover one.
+    pub message: String,
+}
+
+impl Panic {
+    /// Constructs a [Panic] from a panic payload.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::err::Panic;
+    /// let payload = std::panic::catch_unwind(|| panic!("oh no")).unwrap_err();
+    /// let p = Panic::from_payload(Some(1), &*payload);
+    /// assert_eq!(p.to_string(), "thread P1 panicked: oh no");
+    /// ```
+    #[must_use]
+    pub fn from_payload(tid: Option<usize>, payload: &(dyn Any + Send)) -> Self {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|x| (*x).to_owned())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "(unknown panic payload)".to_owned());
+        Self { tid, message }
+    }
+}
+
+impl Panic {
+    fn context(&self) -> Vec<(&'static str, String)> {
+        let tid = self.tid.map(|t| ("tid", t.to_string()));
+        tid.into_iter()
+            .chain(std::iter::once(("panic", self.message.clone())))
+            .collect()
+    }
+}
+
+impl Display for Panic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.tid {
+            Some(tid) => write!(f, "thread P{tid} panicked: {}", self.message),
+            None => write!(f, "thread panicked: {}", self.message),
+        }
+    }
+}
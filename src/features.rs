@@ -0,0 +1,168 @@
+//! Runtime descriptions of what this build of phenolphthalein supports.
+//!
+//! What a build can do depends on its Cargo features (`sqlite`,
+//! `alloc-audit`, the allocator features), its platform (CPU affinity and
+//! trace markers are Linux-only), and which outputters library users have
+//! registered.  Rather than guessing from version numbers, downstream tools
+//! can ask [capabilities] for a [Capabilities] value listing each group of
+//! features (such as `sync` strategies or `outputters`) and its members.
+//!
+//! Each module that provides features registers them with `register!`, next
+//! to the code that implements them; [capabilities] collects every
+//! registration.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The version of the [Capabilities] format.
+///
+/// This changes whenever a group changes meaning, or loses members for
+/// reasons other than the build or platform; adding groups or members
+/// doesn't change it.
+pub const VERSION: u32 = 1;
+
+/// A description of the features of this build of phenolphthalein.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// The version of this format (see [VERSION]).
+    pub version: u32,
+    /// The version of phenolphthalein.
+    pub phenolphthalein: String,
+    /// Each group of features, keyed by name, with its members in
+    /// registration order.
+    pub features: BTreeMap<String, Vec<String>>,
+}
+
+impl Capabilities {
+    /// Gets whether the group `group` has the member `feature`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let caps = phenolphthalein::capabilities();
+    /// assert!(caps.has("sync", "spinner"));
+    /// assert!(caps.has("outputters", "json"));
+    /// assert!(!caps.has("sync", "telepathy"));
+    /// assert!(!caps.has("telepathy", "spinner"));
+    /// ```
+    #[must_use]
+    pub fn has(&self, group: &str, feature: &str) -> bool {
+        self.features
+            .get(group)
+            .is_some_and(|members| members.iter().any(|m| m == feature))
+    }
+}
+
+/// The groups of features that one module registers, with their members.
+pub(crate) type Registration = Vec<(&'static str, Vec<String>)>;
+
+/// Registers the features that the calling module provides.
+///
+/// This takes a list of `"group" => members` pairs, where `members` is
+/// anything iterable over displayable items, and defines a function
+/// `features` returning the registration; an `Option` registers its member
+/// only if present.  Several modules can register members of the same group.
+/// Each module that registers features must also appear in the list that
+/// [capabilities] collects.
+macro_rules! register {
+    ($($group:literal => $members:expr),+ $(,)?) => {
+        /// Gets the features that this module registers with
+        /// [`crate::capabilities`].
+        pub(crate) fn features() -> $crate::features::Registration {
+            vec![$((
+                $group,
+                $members.into_iter().map(|m| m.to_string()).collect(),
+            )),+]
+        }
+    };
+}
+pub(crate) use register;
+
+/// Each module's registration, in the order in which [capabilities]
+/// collects them.
+const REGISTRATIONS: &[fn() -> Registration] = &[
+    crate::api::c::features,
+    crate::api::dsl::features,
+    crate::api::rust::features,
+    crate::model::capability::features,
+    crate::config::sync::features,
+    crate::config::permute::features,
+    crate::run::affinity::features,
+    crate::run::priority::features,
+    crate::run::marker::features,
+    crate::run::alloc::features,
+    crate::ux::out::registry::features,
+];
+
+/// Describes the features of this build of phenolphthalein.
+///
+/// The groups are currently:
+///
+/// - `abi`: the kinds of test that phenolphthalein can load (`c`, `inline`,
+///   and `rust`);
+/// - `c-manifests`: the versions of C manifest structure that it reads;
+/// - `test-capabilities`: the optional exports of tests that it understands
+///   (see [`crate::model::capability`]);
+/// - `sync` and `permute`: the synchronisation and thread permutation
+///   strategies;
+/// - `runtime`: optional runtime support, such as `cpu-affinity`,
+///   `thread-priority`, `trace-markers`, and `alloc-audit`;
+/// - `allocator`: the global allocator;
+/// - `outputters`: the outputters, including any that library users have
+///   registered so far.
+///
+/// # Examples
+///
+/// ```
+/// let caps = phenolphthalein::capabilities();
+/// assert_eq!(caps.version, phenolphthalein::features::VERSION);
+/// assert!(caps.has("abi", "c"));
+/// assert!(caps.has("c-manifests", "v2"));
+/// assert_eq!(cfg!(target_os = "linux"), caps.has("runtime", "cpu-affinity"));
+/// assert_eq!(cfg!(feature = "sqlite"), caps.has("outputters", "sqlite"));
+/// ```
+#[must_use]
+pub fn capabilities() -> Capabilities {
+    let mut features: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for registration in REGISTRATIONS {
+        for (group, members) in registration() {
+            features
+                .entry(group.to_owned())
+                .or_default()
+                .extend(members);
+        }
+    }
+    Capabilities {
+        version: VERSION,
+        phenolphthalein: env!("CARGO_PKG_VERSION").to_owned(),
+        features,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the groups that every build has are present, with
+    /// members.
+    #[test]
+    fn test_capabilities_groups() {
+        let caps = capabilities();
+        for group in [
+            "abi",
+            "c-manifests",
+            "test-capabilities",
+            "sync",
+            "permute",
+            "allocator",
+            "outputters",
+        ] {
+            assert!(
+                caps.features.get(group).is_some_and(|m| !m.is_empty()),
+                "group {group} should have members"
+            );
+        }
+        assert_eq!(caps.features["allocator"], [crate::run::alloc::NAME]);
+        assert!(caps.has("outputters", crate::ux::out::config::string::HISTOGRAM));
+    }
+}
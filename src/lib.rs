@@ -7,6 +7,9 @@ extern crate dlopen_derive;
 pub mod api;
 pub mod config;
 pub mod err;
+pub mod features;
 pub mod model;
 pub mod run;
 pub mod ux;
+
+pub use features::capabilities;
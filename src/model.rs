@@ -1,11 +1,43 @@
 //! Various model types used within the test framework.
 
+pub mod aggregate;
+pub mod allocation;
+pub mod alternation;
+pub mod canary;
+pub mod capability;
+pub mod comparison;
+pub mod conformance;
+pub mod cpu;
+pub mod discovery;
+pub mod ending;
+pub mod example;
+pub mod expectation;
+pub mod fuzz;
+pub mod inspect;
+pub mod interest;
+pub mod invariant;
 pub mod manifest;
+pub mod ordering;
 pub mod outcome;
+pub mod platform;
+pub mod priority;
+pub mod projection;
+pub mod provenance;
 pub mod report;
+pub mod reproduction;
+pub mod rotation;
+pub mod sampling;
+pub mod schedule;
+pub mod skew;
 pub mod slot;
 pub mod state;
+pub mod suite;
+pub mod taint;
+pub mod timing;
+pub mod transition;
 
 pub use manifest::Manifest;
 pub use outcome::Outcome;
+pub use platform::Platform;
 pub use report::Report;
+pub use suite::Suite;
@@ -0,0 +1,143 @@
+//! Models for checks on the distribution of a run's states.
+//!
+//! Per-state checkers judge each state on its own, but some properties are
+//! about how often states occur: a litmus test might demand that some weak
+//! state shows up at least once, or that a slow path is taken on no more
+//! than 1% of iterations.  A [Rule] bounds the number, or fraction, of
+//! iterations whose states match a partial valuation, and is checked against
+//! the whole report at the end of a run.
+
+use super::{interest, report, Outcome};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A map from names of aggregate rules to the rules.
+pub type Spec = BTreeMap<String, Rule>;
+
+/// Bounds on how often states matching a partial valuation occur.
+///
+/// Bounds that are missing don't constrain anything, and fractions are of
+/// every observed iteration.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// The fewest iterations that may match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<usize>,
+    /// The most iterations that may match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<usize>,
+    /// The smallest fraction of iterations that may match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_fraction: Option<f64>,
+    /// The largest fraction of iterations that may match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fraction: Option<f64>,
+    /// The partial valuation that states must match to count.
+    ///
+    /// This comes last because TOML serialises tables after plain values.
+    pub state: interest::Matcher,
+}
+
+impl Rule {
+    /// Checks this rule against the states of `report`.
+    ///
+    /// Fraction bounds are unknown if the report observed no iterations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{aggregate::Rule, interest, report, state, Outcome};
+    ///
+    /// let mut r = report::Report::default();
+    /// for (x, occurs) in [(0, 99), (1, 1)] {
+    ///     let mut info = state::Info::new(Outcome::Pass, 0, 0);
+    ///     info.occurs = occurs;
+    ///     r.insert(report::State {
+    ///         state: [("x".into(), state::Value::I32(x))].into(),
+    ///         info,
+    ///         initials: vec![],
+    ///         interesting: vec![],
+    ///         checks: Default::default(),
+    ///         aux_flags: Default::default(),
+    ///         hash: String::new(),
+    ///     });
+    /// }
+    /// let weak = interest::Matcher([("x".to_owned(), interest::Literal::Int(1))].into());
+    /// let seen = Rule { state: weak.clone(), min: Some(1), ..Rule::default() };
+    /// assert_eq!(seen.check(&r), Outcome::Pass);
+    /// let rare = Rule { state: weak, max_fraction: Some(0.005), ..Rule::default() };
+    /// assert_eq!(rare.check(&r), Outcome::Fail);
+    /// ```
+    #[must_use]
+    pub fn check(&self, report: &report::Report) -> Outcome {
+        let (matched, total) = report.states.iter().fold((0, 0), |(m, t), s| {
+            let occurs: usize = s.info.occurs;
+            let m = if self.state.matches(&s.state) {
+                m + occurs
+            } else {
+                m
+            };
+            (m, t + occurs)
+        });
+        let counts = [
+            self.min.map(|min| min <= matched),
+            self.max.map(|max| matched <= max),
+        ];
+        #[allow(clippy::cast_precision_loss)]
+        let fraction = (total != 0).then(|| matched as f64 / total as f64);
+        let fractions = [
+            self.min_fraction.map(|min| fraction.map(|f| min <= f)),
+            self.max_fraction.map(|max| fraction.map(|f| f <= max)),
+        ];
+        counts
+            .into_iter()
+            .flatten()
+            .map(Outcome::from_pass_bool)
+            .chain(
+                fractions
+                    .into_iter()
+                    .flatten()
+                    .map(|f| f.map_or(Outcome::Unknown, Outcome::from_pass_bool)),
+            )
+            .fold(Outcome::Pass, Outcome::and)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::state;
+
+    /// Tests that fraction bounds on an empty report are unknown, but count
+    /// bounds are still decided.
+    #[test]
+    fn test_check_empty() {
+        let r = report::Report::default();
+        let rule = Rule {
+            min_fraction: Some(0.5),
+            ..Rule::default()
+        };
+        assert_eq!(rule.check(&r), Outcome::Unknown);
+        let rule = Rule {
+            min: Some(1),
+            ..rule
+        };
+        assert_eq!(rule.check(&r), Outcome::Fail);
+        assert_eq!(Rule::default().check(&r), Outcome::Pass);
+    }
+
+    /// Tests that rules parse from TOML, rejecting unknown bounds.
+    #[test]
+    fn test_parse() {
+        let spec: Spec =
+            toml::from_str("[seen]\nstate = { x = 1 }\nmin = 1\nmax_fraction = 0.1").unwrap();
+        let rule = &spec["seen"];
+        assert_eq!(rule.min, Some(1));
+        assert_eq!(rule.max_fraction, Some(0.1));
+        assert!(rule
+            .state
+            .matches(&[("x".into(), state::Value::I32(1))].into()));
+        assert!(toml::from_str::<Spec>("[seen]\nstate = {}\nmost = 1").is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! Audits of the heap allocations the runner makes between iterations.
+//!
+//! Once the runner has seen every state a test shows, handling an iteration
+//! should allocate nothing: allocations there churn the allocator, and cost
+//! iteration rate on machines with many cores.  Runs built with the
+//! `alloc-audit` feature can count the allocations made while handling each
+//! observed iteration, to find the places that still allocate.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Counts of the allocations made while handling observed iterations.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Audit {
+    /// The number of audited iterations.
+    pub iterations: usize,
+    /// The number of allocations made over all audited iterations.
+    pub allocations: usize,
+    /// The number of audited iterations that allocated at all.
+    pub allocating: usize,
+    /// The most allocations made handling any one iteration.
+    pub max: usize,
+    /// The index of the last iteration that allocated, if any did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last: Option<usize>,
+}
+
+impl Audit {
+    /// Records that handling the iteration with index `iteration` made
+    /// `allocations` allocations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::allocation::Audit;
+    ///
+    /// let mut a = Audit::default();
+    /// a.record(0, 3);
+    /// a.record(1, 0);
+    /// assert_eq!(a, Audit { iterations: 2, allocations: 3, allocating: 1, max: 3, last: Some(0) });
+    /// ```
+    pub fn record(&mut self, iteration: usize, allocations: usize) {
+        self.iterations = self.iterations.saturating_add(1);
+        if allocations == 0 {
+            return;
+        }
+        self.allocations = self.allocations.saturating_add(allocations);
+        self.allocating = self.allocating.saturating_add(1);
+        self.max = self.max.max(allocations);
+        self.last = Some(iteration);
+    }
+
+    /// Merges `other`, from another run, into this audit.
+    ///
+    /// Iteration numbers restart on every run, so the merged audit's last
+    /// allocating iteration is the later of the two.
+    pub fn merge(&mut self, other: &Self) {
+        self.iterations = self.iterations.saturating_add(other.iterations);
+        self.allocations = self.allocations.saturating_add(other.allocations);
+        self.allocating = self.allocating.saturating_add(other.allocating);
+        self.max = self.max.max(other.max);
+        self.last = self.last.max(other.last);
+    }
+}
+
+/// Audits display as a summary of their counts.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::allocation::Audit;
+///
+/// let mut a = Audit::default();
+/// a.record(0, 3);
+/// a.record(1, 0);
+/// assert_eq!(a.to_string(), "3 over 1 of 2 iterations (at most 3 in one, last in iteration 0)");
+/// a = Audit::default();
+/// a.record(0, 0);
+/// assert_eq!(a.to_string(), "none over 1 iterations");
+/// ```
+impl fmt::Display for Audit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(last) = self.last else {
+            return write!(f, "none over {} iterations", self.iterations);
+        };
+        write!(
+            f,
+            "{} over {} of {} iterations (at most {} in one, last in iteration {last})",
+            self.allocations, self.allocating, self.iterations, self.max
+        )
+    }
+}
@@ -0,0 +1,230 @@
+//! Comparisons between synchronisers alternated within one run.
+//!
+//! The way the runner synchronises its threads between iterations can itself
+//! bias which states a test shows: a barrier that parks threads releases them
+//! at different times from a spinner that keeps them hot.  A run can
+//! alternate between synchronisers every so many rotations, keeping
+//! everything else the same, and then break down its states by the
+//! synchroniser under which they occurred.  The further apart the resulting
+//! distributions are, the more the choice of synchroniser is shaping what the
+//! test shows.
+
+use super::report::Occurrence;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, num::NonZeroUsize};
+
+/// A breakdown of a run's states by the synchroniser that was active when
+/// they occurred.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Alternation {
+    /// The number of rotations for which each synchroniser stays active
+    /// before the next takes over.
+    pub every: NonZeroUsize,
+    /// The synchronisers, in the order in which they took turns.
+    pub arms: Vec<Arm>,
+}
+
+/// The states seen under one synchroniser in an [Alternation].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Arm {
+    /// The name of the synchroniser.
+    pub sync: String,
+    /// The number of rotations for which this synchroniser was active.
+    pub rotations: usize,
+    /// The states seen under this synchroniser, in report order, and how
+    /// often.
+    pub states: Vec<Occurrence>,
+}
+
+impl Alternation {
+    /// Breaks down occurrences of states by the synchroniser, named in
+    /// `syncs`, that was active in each of `rotations` rotations, switching
+    /// every `every` rotations.
+    ///
+    /// `counts` holds, for each state in a report (in the same order), the
+    /// number of times that state occurred in each rotation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::alternation::Alternation;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let counts = [
+    ///     BTreeMap::from([(0, 3), (1, 1), (2, 3)]),
+    ///     BTreeMap::from([(1, 2)]),
+    /// ];
+    /// let syncs = vec!["spinner".to_owned(), "barrier".to_owned()];
+    /// let a = Alternation::new(syncs, 1.try_into().unwrap(), 3, &counts);
+    /// assert_eq!(a.arms[0].rotations, 2);
+    /// assert_eq!(a.arms[0].iterations(), 6);
+    /// assert_eq!(a.arms[1].rotations, 1);
+    /// assert_eq!(a.arms[1].states[1].occurs, 2);
+    /// ```
+    #[must_use]
+    pub fn new(
+        syncs: Vec<String>,
+        every: NonZeroUsize,
+        rotations: usize,
+        counts: &[BTreeMap<usize, usize>],
+    ) -> Self {
+        let mut arms: Vec<_> = syncs
+            .into_iter()
+            .map(|sync| Arm {
+                sync,
+                rotations: 0,
+                states: vec![],
+            })
+            .collect();
+        let Some(n) = NonZeroUsize::new(arms.len()) else {
+            return Self { every, arms };
+        };
+        for rotation in 0..rotations {
+            arms[arm_index(rotation, every, n)].rotations += 1;
+        }
+        for (state, counts) in counts.iter().enumerate() {
+            let mut by_arm = vec![0usize; n.get()];
+            for (&rotation, &occurs) in counts {
+                let m = &mut by_arm[arm_index(rotation, every, n)];
+                *m = m.saturating_add(occurs);
+            }
+            for (arm, occurs) in arms.iter_mut().zip(by_arm) {
+                if occurs != 0 {
+                    arm.states.push(Occurrence { state, occurs });
+                }
+            }
+        }
+        Self { every, arms }
+    }
+
+    /// Gets the index of the arm that was active in `rotation`, if there are
+    /// any arms.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::alternation::Alternation;
+    ///
+    /// let syncs = vec!["spinner".to_owned(), "barrier".to_owned()];
+    /// let a = Alternation::new(syncs, 2.try_into().unwrap(), 0, &[]);
+    /// let arms: Vec<_> = (0..5).filter_map(|r| a.arm_of(r)).collect();
+    /// assert_eq!(arms, [0, 0, 1, 1, 0]);
+    /// ```
+    #[must_use]
+    pub fn arm_of(&self, rotation: usize) -> Option<usize> {
+        let n = NonZeroUsize::new(self.arms.len())?;
+        Some(arm_index(rotation, self.every, n))
+    }
+
+    /// Gets the largest total variation distance between the state
+    /// distributions of any two arms.
+    ///
+    /// This is half the sum, over all states, of the difference between the
+    /// fractions of each arm's iterations that showed the state: 0 if the
+    /// arms showed the same states in the same proportions, and 1 if they
+    /// showed no states in common.  Arms with no iterations don't count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::alternation::Alternation;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let syncs = || vec!["spinner".to_owned(), "barrier".to_owned()];
+    /// let same = [BTreeMap::from([(0, 5), (1, 5)])];
+    /// let a = Alternation::new(syncs(), 1.try_into().unwrap(), 2, &same);
+    /// assert_eq!(a.distance(), 0.0);
+    ///
+    /// let skewed = [BTreeMap::from([(0, 3), (1, 1)]), BTreeMap::from([(0, 1), (1, 3)])];
+    /// let a = Alternation::new(syncs(), 1.try_into().unwrap(), 2, &skewed);
+    /// assert_eq!(a.distance(), 0.5);
+    /// ```
+    #[must_use]
+    pub fn distance(&self) -> f64 {
+        let dists: Vec<_> = self.arms.iter().filter_map(Arm::distribution).collect();
+        let mut max: f64 = 0.0;
+        for (i, p) in dists.iter().enumerate() {
+            for q in &dists[i + 1..] {
+                max = max.max(total_variation(p, q));
+            }
+        }
+        max
+    }
+
+    /// Merges another breakdown of a run of the same test into this one,
+    /// using `remap` to map the state indices in `other` to those in this
+    /// one's report.
+    ///
+    /// Breakdowns only merge if they alternated the same synchronisers at the
+    /// same period; otherwise, this returns `false` and leaves this breakdown
+    /// as it was.
+    pub fn merge(&mut self, other: Self, remap: &[usize]) -> bool {
+        let syncs = |a: &Self| a.arms.iter().map(|a| a.sync.clone()).collect::<Vec<_>>();
+        if self.every != other.every || syncs(self) != syncs(&other) {
+            return false;
+        }
+        for (mine, theirs) in self.arms.iter_mut().zip(other.arms) {
+            mine.rotations = mine.rotations.saturating_add(theirs.rotations);
+            for mut o in theirs.states {
+                o.state = remap.get(o.state).copied().unwrap_or(o.state);
+                match mine.states.iter_mut().find(|x| x.state == o.state) {
+                    Some(x) => x.occurs = x.occurs.saturating_add(o.occurs),
+                    None => mine.states.push(o),
+                }
+            }
+            mine.states.sort_by_key(|o| o.state);
+        }
+        true
+    }
+}
+
+impl Arm {
+    /// Gets the number of iterations observed under this synchroniser.
+    #[must_use]
+    pub fn iterations(&self) -> usize {
+        self.states.iter().map(|o| o.occurs).sum()
+    }
+
+    /// Gets the fraction of this arm's iterations that showed the state with
+    /// index `state`, or `None` if the arm has no iterations.
+    #[must_use]
+    pub fn frequency(&self, state: usize) -> Option<f64> {
+        let total = self.iterations();
+        let occurs = self
+            .states
+            .iter()
+            .find(|o| o.state == state)
+            .map_or(0, |o| o.occurs);
+        #[allow(clippy::cast_precision_loss)]
+        (total != 0).then(|| occurs as f64 / total as f64)
+    }
+
+    /// Gets the fraction of this arm's iterations that showed each state it
+    /// saw, or `None` if the arm has no iterations.
+    fn distribution(&self) -> Option<BTreeMap<usize, f64>> {
+        let total = self.iterations();
+        #[allow(clippy::cast_precision_loss)]
+        (total != 0).then(|| {
+            self.states
+                .iter()
+                .map(|o| (o.state, o.occurs as f64 / total as f64))
+                .collect()
+        })
+    }
+}
+
+/// Gets the index of the arm active in `rotation`, out of `n` arms that
+/// switch every `every` rotations.
+fn arm_index(rotation: usize, every: NonZeroUsize, n: NonZeroUsize) -> usize {
+    (rotation / every) % n
+}
+
+/// Gets the total variation distance between two distributions over states.
+fn total_variation(p: &BTreeMap<usize, f64>, q: &BTreeMap<usize, f64>) -> f64 {
+    let states: std::collections::BTreeSet<_> = p.keys().chain(q.keys()).collect();
+    let sum: f64 = states
+        .into_iter()
+        .map(|s| (p.get(s).unwrap_or(&0.0) - q.get(s).unwrap_or(&0.0)).abs())
+        .sum();
+    sum / 2.0
+}
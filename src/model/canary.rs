@@ -0,0 +1,116 @@
+//! Canary values, for detecting torn reads on non-atomic variables.
+//!
+//! In canary mode, the runner fills every non-atomic integer and float
+//! variable that has no initial value with a recognisable byte pattern before
+//! each iteration.  If the test observes such a variable holding a value in
+//! which some, but not all, of its aligned 16-bit chunks still hold the
+//! pattern, that value most likely arose from a torn read or a partial
+//! write, rather than from any single write the test made.
+//!
+//! This is a heuristic: a test that legitimately writes values containing
+//! the pattern will see false positives.
+
+use super::{manifest::VarName, state::Value};
+use serde::{Deserialize, Serialize};
+
+/// The byte from which canary values are built.
+pub const BYTE: u8 = 0xA5;
+
+/// The canary value for 32-bit integers.
+pub const I32: i32 = i32::from_ne_bytes([BYTE; 4]);
+
+/// The bit pattern of the canary value for 64-bit floats.
+pub const F64_BITS: u64 = u64::from_ne_bytes([BYTE; 8]);
+
+/// Gets the canary value for 64-bit floats.
+#[must_use]
+pub fn f64() -> f64 {
+    f64::from_bits(F64_BITS)
+}
+
+/// Gets whether `value` looks like the result of tearing a write over a
+/// canary value.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::{canary, state::Value};
+///
+/// assert!(!canary::is_torn(Value::I32(canary::I32)));
+/// assert!(!canary::is_torn(Value::I32(0x1234_5678)));
+/// let half = i32::from_ne_bytes([1, 0, canary::BYTE, canary::BYTE]);
+/// assert!(canary::is_torn(Value::I32(half)));
+/// ```
+#[must_use]
+pub fn is_torn(value: Value) -> bool {
+    match value {
+        Value::I32(x) => is_torn_bytes(&x.to_ne_bytes()),
+        Value::F64(x) => is_torn_bytes(&x.to_bits().to_ne_bytes()),
+        Value::Bool(_) => false,
+    }
+}
+
+fn is_torn_bytes(bytes: &[u8]) -> bool {
+    let chunks = bytes.len() / 2;
+    let canaries = bytes
+        .chunks_exact(2)
+        .filter(|c| c.iter().all(|&b| b == BYTE))
+        .count();
+    canaries != 0 && canaries != chunks
+}
+
+/// A record of a torn value observed on a canaried variable.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TornRead {
+    /// The name of the variable.
+    pub var: VarName,
+    /// The torn value.
+    pub value: Value,
+    /// The number of times this torn value occurred.
+    pub occurs: usize,
+    /// The (zero-based) iteration at which this torn value first occurred.
+    pub iteration: usize,
+    /// The (zero-based) rotation in which this torn value first occurred.
+    pub rotation: usize,
+}
+
+impl TornRead {
+    /// Constructs a record of a first torn read of `value` on `var`.
+    #[must_use]
+    pub fn new(var: VarName, value: Value, iteration: usize, rotation: usize) -> Self {
+        Self {
+            var,
+            value,
+            occurs: 1,
+            iteration,
+            rotation,
+        }
+    }
+
+    /// Merges another record of the same torn value into this one, keeping
+    /// the earliest first occurrence.
+    pub fn merge(&mut self, other: &TornRead) {
+        self.occurs = self.occurs.saturating_add(other.occurs);
+        if other.iteration < self.iteration {
+            self.iteration = other.iteration;
+            self.rotation = other.rotation;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests tearing detection on floats.
+    #[test]
+    fn test_is_torn_f64() {
+        assert!(!is_torn(Value::F64(f64())));
+        assert!(!is_torn(Value::F64(1.5)));
+        let mut bytes = F64_BITS.to_ne_bytes();
+        bytes[..4].copy_from_slice(&[0; 4]);
+        assert!(is_torn(Value::F64(f64::from_bits(u64::from_ne_bytes(
+            bytes
+        )))));
+    }
+}
@@ -0,0 +1,99 @@
+//! Optional capabilities of test entries.
+//!
+//! Beyond a manifest and a test body, everything a test exports is optional,
+//! so that older or minimal tests still load.  Each [Capability] names one
+//! such optional export; tests lacking one run with a documented fallback
+//! (see [`Capability::fallback`]).
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fmt::Display};
+
+crate::features::register! { "test-capabilities" => Capability::ALL }
+
+/// The set of capabilities that a test has.
+pub type Set = BTreeSet<Capability>;
+
+/// An optional capability of a test.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// The test has its own checker (`check`).
+    Check,
+    /// The test has a test body that takes the iteration (`test_iter`).
+    TestIter,
+    /// The test names a role that extra threads can replicate
+    /// (`scalable_role`).
+    ScalableRole,
+    /// The test declares which of its threads are symmetric (`symmetry`).
+    Symmetry,
+    /// The test declares bounds on its variables (`invariants` and
+    /// `n_invariants`).
+    Invariants,
+    /// The test describes what its checker checks (`check_description`).
+    CheckDescription,
+    /// The test gives each thread its own copies of some variables
+    /// (`thread_locals` and `n_thread_locals`).
+    ThreadLocals,
+    /// The test declares auxiliary flags that it can raise (`aux_flags` and
+    /// `n_aux_flags`).
+    AuxFlags,
+}
+
+impl Capability {
+    /// All capabilities, in order.
+    pub const ALL: [Self; 8] = [
+        Self::Check,
+        Self::TestIter,
+        Self::ScalableRole,
+        Self::Symmetry,
+        Self::Invariants,
+        Self::CheckDescription,
+        Self::ThreadLocals,
+        Self::AuxFlags,
+    ];
+
+    /// Gets the name of this capability, which is also the name of the C
+    /// symbol that provides it.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Check => "check",
+            Self::TestIter => "test_iter",
+            Self::ScalableRole => "scalable_role",
+            Self::Symmetry => "symmetry",
+            Self::Invariants => "invariants",
+            Self::CheckDescription => "check_description",
+            Self::ThreadLocals => "thread_locals",
+            Self::AuxFlags => "aux_flags",
+        }
+    }
+
+    /// Describes what happens when a test lacks this capability.
+    #[must_use]
+    pub fn fallback(self) -> &'static str {
+        match self {
+            Self::Check => "states have unknown outcomes",
+            Self::TestIter => "the test body doesn't see the iteration",
+            Self::ScalableRole => "the thread count can't change",
+            Self::Symmetry => "only extra threads are symmetric",
+            Self::Invariants => "no bounds are checked",
+            Self::CheckDescription => "reports don't say what the checker checks",
+            Self::ThreadLocals => "threads have no variables of their own",
+            Self::AuxFlags => "states have no flag counts",
+        }
+    }
+}
+
+/// Capabilities display as their names.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::capability::Capability;
+/// assert_eq!(Capability::TestIter.to_string(), "test_iter");
+/// ```
+impl Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
@@ -0,0 +1,106 @@
+//! Comparisons between runs of two builds of the same test.
+//!
+//! Running two builds of a test (for instance, one compiled at `-O0` and one
+//! at `-O3`) side by side, under the same thread permutations and fuzzed
+//! initial values, and then comparing their states, shows what building the
+//! test differently changed about its behaviour: a state that only one build
+//! showed suggests that the compiler reordered or merged its accesses.
+
+use super::{report, state, Report};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// A comparison between the reports of two builds of a test.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Comparison {
+    /// The first build.
+    pub left: Side,
+    /// The second build.
+    pub right: Side,
+}
+
+/// One build in a [Comparison].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Side {
+    /// The name of the build, usually that of its library.
+    pub name: String,
+    /// The report of the build's run.
+    pub report: Report,
+    /// The states that this build showed and the other didn't, in the order
+    /// of this build's report.
+    pub only: Vec<report::State>,
+}
+
+impl Comparison {
+    /// Compares the report of the build named `left.0` against that of the
+    /// build named `right.0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{comparison::Comparison, report, state, Outcome, Report};
+    ///
+    /// let report = |xs: &[i32]| {
+    ///     let mut r = Report::default();
+    ///     for &x in xs {
+    ///         r.insert(report::State {
+    ///             state: [("x".into(), state::Value::I32(x))].into(),
+    ///             info: state::Info::new(Outcome::Pass, 0, 0),
+    ///             initials: vec![],
+    ///             interesting: vec![],
+    ///             checks: Default::default(),
+    ///             aux_flags: Default::default(),
+    ///             hash: String::new(),
+    ///         });
+    ///     }
+    ///     r
+    /// };
+    /// let c = Comparison::new(
+    ///     ("O0".to_owned(), report(&[0, 1])),
+    ///     ("O3".to_owned(), report(&[1, 2, 3])),
+    /// );
+    /// assert_eq!(c.left.only.len(), 1);
+    /// assert_eq!(c.right.only.len(), 2);
+    /// assert!(!c.is_same());
+    /// ```
+    #[must_use]
+    pub fn new(left: (String, Report), right: (String, Report)) -> Self {
+        let left_only = only(&left.1, &right.1);
+        let right_only = only(&right.1, &left.1);
+        Self {
+            left: Side {
+                name: left.0,
+                report: left.1,
+                only: left_only,
+            },
+            right: Side {
+                name: right.0,
+                report: right.1,
+                only: right_only,
+            },
+        }
+    }
+
+    /// Gets whether both builds showed the same states.
+    #[must_use]
+    pub fn is_same(&self) -> bool {
+        self.left.only.is_empty() && self.right.only.is_empty()
+    }
+
+    /// Gets both sides, left first.
+    #[must_use]
+    pub fn sides(&self) -> [&Side; 2] {
+        [&self.left, &self.right]
+    }
+}
+
+/// Gets the states in `report` that aren't in `other`.
+fn only(report: &Report, other: &Report) -> Vec<report::State> {
+    let seen: HashSet<&state::State> = other.states.iter().map(|s| &s.state).collect();
+    report
+        .states
+        .iter()
+        .filter(|s| !seen.contains(&s.state))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,236 @@
+//! Conformance of observed states against a memory model's predictions.
+//!
+//! Axiomatic simulators such as herd7 list the final states a model allows
+//! for a litmus test.  Given such a list, we classify each observed state as
+//! allowed, if the model predicts it, or forbidden, if not; expected states
+//! that never showed up are unobserved.  A forbidden state means that the
+//! machine (or compiler) is weaker than the model; unobserved states just
+//! mean the run didn't happen to see them.
+//!
+//! Expected states are partial valuations (see [`interest::Matcher`]), as
+//! herd7 only lists the locations that its condition mentions.  Since herd7
+//! prints Booleans as integers, integer literals 0 and 1 also match Booleans.
+
+use super::{interest, report, state};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// A set of states that a model allows.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Expected(pub Vec<interest::Matcher>);
+
+impl Expected {
+    /// Gets whether `state` agrees with any of the expected states.
+    #[must_use]
+    pub fn allows(&self, state: &state::State) -> bool {
+        self.0.iter().any(|m| agrees(m, state))
+    }
+}
+
+/// Parses a list of expected states, from either herd7 output or a plain
+/// list.
+///
+/// In herd7 output, the states are the lines following the `States N`
+/// header.  Otherwise, each non-blank line not starting with `#` is a state.
+/// Either way, a state is a sequence of `name=value` bindings separated by
+/// `;` or whitespace; square brackets around names (as in `[x]=1`) are
+/// dropped.
+///
+/// # Errors
+///
+/// Fails, describing the problem, if a binding is malformed or a `States`
+/// header is followed by fewer states than it claims.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::conformance::Expected;
+///
+/// let herd = "Test SB Allowed\nStates 2\n0:r0=0; 1:r0=1;\n0:r0=1; 1:r0=1;\nOk\nWitnesses\n";
+/// let e: Expected = herd.parse().unwrap();
+/// assert_eq!(e.0.len(), 2);
+///
+/// let plain: Expected = "# SB under SC\nx=0 y=1\nx=1 y=1\nx=1 y=0\n".parse().unwrap();
+/// assert_eq!(plain.0.len(), 3);
+/// ```
+impl FromStr for Expected {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const HEADER: &str = "States ";
+        let lines: Vec<_> = s.lines().map(str::trim).collect();
+        let states: Result<Vec<_>, _> = match lines.iter().position(|l| l.starts_with(HEADER)) {
+            Some(i) => {
+                let n: usize = lines[i][HEADER.len()..]
+                    .trim()
+                    .parse()
+                    .map_err(|e| format!("bad state count in {:?}: {e}", lines[i]))?;
+                let body = &lines[i + 1..];
+                if body.len() < n {
+                    return Err(format!("expected {n} states, found {}", body.len()));
+                }
+                body[..n].iter().map(|l| parse_state(l)).collect()
+            }
+            None => lines
+                .iter()
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(|l| parse_state(l))
+                .collect(),
+        };
+        states.map(Self)
+    }
+}
+
+fn parse_state(line: &str) -> Result<interest::Matcher, String> {
+    line.split(|c: char| c == ';' || c.is_whitespace())
+        .filter(|b| !b.is_empty())
+        .map(|binding| {
+            let (name, value) = binding
+                .split_once('=')
+                .ok_or_else(|| format!("bad binding {binding:?} in {line:?}"))?;
+            let name = name.trim_start_matches('[').trim_end_matches(']');
+            Ok((name.to_owned(), parse_literal(value)?))
+        })
+        .collect::<Result<_, String>>()
+        .map(interest::Matcher)
+}
+
+fn parse_literal(s: &str) -> Result<interest::Literal, String> {
+    match s {
+        "true" => Ok(interest::Literal::Bool(true)),
+        "false" => Ok(interest::Literal::Bool(false)),
+        s => s
+            .parse()
+            .map(interest::Literal::Int)
+            .or_else(|_| s.parse().map(interest::Literal::Float))
+            .map_err(|_| format!("bad value {s:?}")),
+    }
+}
+
+/// Checks whether `state` agrees with `m`, letting integers 0 and 1 stand
+/// for Booleans.
+fn agrees(m: &interest::Matcher, state: &state::State) -> bool {
+    m.0.iter().all(|(var, lit)| {
+        state.get(var.as_str()).is_some_and(|v| match (lit, v) {
+            (interest::Literal::Int(i), state::Value::Bool(b)) => *i == i64::from(*b),
+            (lit, v) => lit.matches(*v),
+        })
+    })
+}
+
+/// The verdict of a model on an observed state.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    /// The model predicts the state.
+    Allowed,
+    /// The model doesn't predict the state.
+    Forbidden,
+}
+
+/// The classification of a report's states against a model.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Conformance {
+    /// The states the model allows.
+    pub expected: Expected,
+    /// The verdict on each state in the report, in report order.
+    pub verdicts: Vec<Verdict>,
+    /// The expected states that no observed state agrees with.
+    pub unobserved: Vec<interest::Matcher>,
+}
+
+impl Conformance {
+    /// Classifies the states of `report` against `expected`.
+    #[must_use]
+    pub fn classify(expected: Expected, report: &report::Report) -> Self {
+        let verdicts = report
+            .states
+            .iter()
+            .map(|s| {
+                if expected.allows(&s.state) {
+                    Verdict::Allowed
+                } else {
+                    Verdict::Forbidden
+                }
+            })
+            .collect();
+        let unobserved = expected
+            .0
+            .iter()
+            .filter(|m| !report.states.iter().any(|s| agrees(m, &s.state)))
+            .cloned()
+            .collect();
+        Self {
+            expected,
+            verdicts,
+            unobserved,
+        }
+    }
+
+    /// Counts the observed states with verdict `verdict`.
+    #[must_use]
+    pub fn count(&self, verdict: Verdict) -> usize {
+        self.verdicts.iter().filter(|v| **v == verdict).count()
+    }
+
+    /// Gets whether every observed state is one the model allows.
+    #[must_use]
+    pub fn conforms(&self) -> bool {
+        self.count(Verdict::Forbidden) == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(x: i32, flag: bool) -> report::State {
+        report::State {
+            state: [
+                ("x".into(), state::Value::I32(x)),
+                ("flag".into(), state::Value::Bool(flag)),
+            ]
+            .into(),
+            info: state::Info::new(super::super::Outcome::Pass, 0, 0),
+            initials: vec![],
+            interesting: vec![],
+            checks: std::collections::BTreeMap::new(),
+            aux_flags: std::collections::BTreeMap::new(),
+            hash: String::new(),
+        }
+    }
+
+    /// Tests classifying states, with herd7-style integer Booleans.
+    #[test]
+    fn test_classify() {
+        let expected: Expected = "x=0; flag=0;\nx=1; flag=1;\nx=2; flag=1;\n"
+            .parse()
+            .unwrap();
+        let report = report::Report {
+            states: vec![state(0, false), state(1, true), state(1, false)],
+            ..report::Report::default()
+        };
+        let c = Conformance::classify(expected, &report);
+        assert_eq!(
+            c.verdicts,
+            [Verdict::Allowed, Verdict::Allowed, Verdict::Forbidden]
+        );
+        assert_eq!(
+            (c.count(Verdict::Allowed), c.count(Verdict::Forbidden)),
+            (2, 1)
+        );
+        assert!(!c.conforms());
+        assert_eq!(c.unobserved.len(), 1);
+        assert_eq!(c.unobserved[0].0["x"], interest::Literal::Int(2));
+    }
+
+    /// Tests that malformed expectations are rejected.
+    #[test]
+    fn test_parse_errors() {
+        assert!("x=0 y".parse::<Expected>().is_err());
+        assert!("x=zero".parse::<Expected>().is_err());
+        assert!("States 2\nx=0;\n".parse::<Expected>().is_err());
+        assert!("States two\n".parse::<Expected>().is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! Models for sets of CPUs to which a run's test threads are restricted.
+//!
+//! Restricting all of a test's threads to fewer CPUs than it has threads
+//! oversubscribes them, so that the operating system's scheduler interleaves
+//! them rather than the hardware; this changes which weak behaviours show up,
+//! and how often.  The threads aren't pinned to particular CPUs, so can still
+//! migrate within the set.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fmt::Display, str::FromStr};
+
+/// A non-empty set of CPU indices, written as a comma-separated list of
+/// indices and inclusive ranges, such as `0-3,6`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Set(BTreeSet<usize>);
+
+impl Set {
+    /// Gets the number of CPUs in this set.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Gets whether this set is empty; parsed sets never are.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Gets whether this set contains the CPU with index `cpu`.
+    #[must_use]
+    pub fn contains(&self, cpu: usize) -> bool {
+        self.0.contains(&cpu)
+    }
+
+    /// Gets whether every CPU in this set is also in `other`.
+    #[must_use]
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.0.is_subset(&other.0)
+    }
+
+    /// Iterates over the CPU indices in this set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+impl FromIterator<usize> for Set {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// Sets display with runs of consecutive CPUs as ranges, so that they parse
+/// back exactly.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::cpu::Set;
+/// let s: Set = [0, 1, 2, 3, 6, 8, 9].into_iter().collect();
+/// assert_eq!(s.to_string(), "0-3,6,8-9");
+/// ```
+impl Display for Set {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut runs: Vec<(usize, usize)> = vec![];
+        for cpu in self.iter() {
+            match runs.last_mut() {
+                Some((_, end)) if *end + 1 == cpu => *end = cpu,
+                _ => runs.push((cpu, cpu)),
+            }
+        }
+        for (i, (start, end)) in runs.into_iter().enumerate() {
+            if i != 0 {
+                f.write_str(",")?;
+            }
+            if start == end {
+                write!(f, "{start}")?;
+            } else {
+                write!(f, "{start}-{end}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Set {
+    type Err = String;
+
+    /// Parses a CPU set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::cpu::Set;
+    ///
+    /// let s: Set = "0-3".parse().unwrap();
+    /// assert_eq!(s.iter().collect::<Vec<_>>(), [0, 1, 2, 3]);
+    /// let s: Set = "4, 1-2".parse().unwrap();
+    /// assert_eq!(s.iter().collect::<Vec<_>>(), [1, 2, 4]);
+    /// assert!("".parse::<Set>().is_err());
+    /// assert!("3-1".parse::<Set>().is_err());
+    /// assert!("0-x".parse::<Set>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut set = BTreeSet::new();
+        for item in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let (start, end) = item.split_once('-').unwrap_or((item, item));
+            let start = index(start)?;
+            let end = index(end)?;
+            if end < start {
+                return Err(format!("CPU range {item} ends before it starts"));
+            }
+            set.extend(start..=end);
+        }
+        if set.is_empty() {
+            return Err(format!("CPU set {s:?} names no CPUs"));
+        }
+        Ok(Self(set))
+    }
+}
+
+impl TryFrom<String> for Set {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Set> for String {
+    fn from(s: Set) -> Self {
+        s.to_string()
+    }
+}
+
+fn index(s: &str) -> Result<usize, String> {
+    s.trim()
+        .parse()
+        .map_err(|e| format!("bad CPU index {s:?}: {e}"))
+}
@@ -0,0 +1,55 @@
+//! Distinct-state discovery curves.
+//!
+//! A discovery curve records the iteration at which a test first observed
+//! each of its distinct states, giving a cumulative count of distinct states
+//! against iterations.  Flatter curves suggest the test has stopped finding
+//! new behaviour; comparing curves across synchronisation or permutation
+//! strategies shows which ones find behaviour fastest.
+
+use serde::{Deserialize, Serialize};
+
+/// A point on a discovery curve.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Point {
+    /// The (zero-based) iteration at which a new state was discovered.
+    pub iteration: usize,
+    /// The number of distinct states discovered up to and including this
+    /// iteration.
+    pub states: usize,
+}
+
+/// A discovery curve, containing one point per distinct state in order of
+/// discovery.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Curve(pub Vec<Point>);
+
+impl Curve {
+    /// Records the discovery of a new distinct state at `iteration`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::discovery::{Curve, Point};
+    /// let mut c = Curve::default();
+    /// c.discover(0);
+    /// c.discover(42);
+    /// assert_eq!(c.last(), Some(&Point { iteration: 42, states: 2 }));
+    /// ```
+    pub fn discover(&mut self, iteration: usize) {
+        let states = self.0.len().saturating_add(1);
+        self.0.push(Point { iteration, states });
+    }
+
+    /// Gets the most recent point on the curve, if any.
+    #[must_use]
+    pub fn last(&self) -> Option<&Point> {
+        self.0.last()
+    }
+
+    /// Gets whether the curve is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
@@ -0,0 +1,102 @@
+//! Why a run ended.
+//!
+//! Runs end when one of their exit rules fires (see `run::halt`), when
+//! something outside the run cancels it through its handle, or when the test
+//! misbehaves.  Reports record which, so that a run that stopped on an
+//! interrupt or a time limit isn't mistaken for one that used up its
+//! iteration budget.
+
+use super::Outcome;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// The cause of a run's end.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cause", rename_all = "snake_case")]
+pub enum Cause {
+    /// The run reached a multiple of its iteration budget.
+    Iterations {
+        /// The budget.
+        iterations: usize,
+    },
+    /// One rotation of the run reached an iteration budget.
+    RotationIterations {
+        /// The budget.
+        iterations: usize,
+    },
+    /// One rotation of the run reached a time limit.
+    Duration {
+        /// The limit, in milliseconds.
+        ms: u64,
+    },
+    /// A signal from outside the test fired, such as an interrupt.
+    Signal {
+        /// The name of the signal.
+        name: String,
+    },
+    /// The run saw a state with a particular outcome.
+    Outcome {
+        /// The outcome.
+        outcome: Outcome,
+    },
+    /// The run saw every interesting state it was looking for.
+    AllInteresting,
+    /// The run's iteration rate fell below a minimum.
+    MinRate {
+        /// The minimum, in iterations per second.
+        iters_per_sec: f64,
+    },
+    /// Something outside the run cancelled it through its handle.
+    Cancelled,
+    /// The test tainted the run, and the run was guarding against taints by
+    /// aborting.
+    Tainted,
+    /// A test body, or a checker or hook observing it, panicked.
+    Panicked,
+    /// The process exceeded one of its resource limits.
+    LimitExceeded {
+        /// The name of the resource, such as `cpu` or `memory`.
+        resource: String,
+    },
+}
+
+/// Causes display as a short explanation.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::{ending::Cause, Outcome};
+/// assert_eq!(
+///     Cause::Iterations { iterations: 1000 }.to_string(),
+///     "reached 1000 iterations"
+/// );
+/// assert_eq!(
+///     Cause::Signal { name: "interrupt".to_owned() }.to_string(),
+///     "signalled: interrupt"
+/// );
+/// assert_eq!(
+///     Cause::Outcome { outcome: Outcome::Fail }.to_string(),
+///     "saw a fail state"
+/// );
+/// ```
+impl Display for Cause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Iterations { iterations } => write!(f, "reached {iterations} iterations"),
+            Self::RotationIterations { iterations } => {
+                write!(f, "reached {iterations} iterations in one rotation")
+            }
+            Self::Duration { ms } => write!(f, "ran for {ms}ms in one rotation"),
+            Self::Signal { name } => write!(f, "signalled: {name}"),
+            Self::Outcome { outcome } => write!(f, "saw a {outcome} state"),
+            Self::AllInteresting => f.write_str("saw every interesting state"),
+            Self::MinRate { iters_per_sec } => {
+                write!(f, "fell below {iters_per_sec} iterations per second")
+            }
+            Self::Cancelled => f.write_str("cancelled"),
+            Self::Tainted => f.write_str("tainted"),
+            Self::Panicked => f.write_str("a test thread panicked"),
+            Self::LimitExceeded { resource } => write!(f, "exceeded its {resource} limit"),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Examples of the contexts in which states occurred.
+//!
+//! A state that shows up once in a billion iterations is hard to act on
+//! without knowing when it showed up.  Runs can record, for each distinct
+//! state, the context of the first iteration that showed it: where that
+//! iteration fell in the run, which thread permutation was active, and the
+//! values of any randomised noise, such as fuzzed initial values and skew
+//! delays, that it drew.  The skew delays are fixed delays in the same form
+//! as the config that sets skew, so they can go straight back into a config
+//! to retry that context.
+
+use super::{schedule, skew, state};
+use serde::{Deserialize, Serialize};
+
+/// The context of an iteration that showed a state.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Example {
+    /// The index of the state in the report's `states`.
+    pub state: usize,
+    /// The number of the iteration.
+    pub iteration: usize,
+    /// The number of the rotation in which the iteration ran.
+    pub rotation: usize,
+    /// The thread permutation active in that rotation, if known.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permutation: schedule::Assignment,
+    /// The fuzzed initial values from which the iteration started, if the
+    /// run fuzzed any.
+    #[serde(default, skip_serializing_if = "state::State::is_empty")]
+    pub initials: state::State,
+    /// The delay with which each skewed thread entered the iteration, keyed
+    /// by thread ID, if the run skewed any.
+    #[serde(default, skip_serializing_if = "skew::Spec::is_empty")]
+    pub skew: skew::Spec,
+}
+
+/// Merges the examples in `others`, from another report, into `mine`.
+///
+/// `remap` maps the state indices in `others` to those in `mine`'s report,
+/// and `offset` is the number of rotations that `mine`'s report ran before
+/// `others`'s.  Each state keeps the example in `mine`, if it has one, as
+/// that came from the earlier run.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::example::{merge, Example};
+///
+/// let ex = |state, rotation| Example {
+///     state,
+///     iteration: 0,
+///     rotation,
+///     permutation: vec![],
+///     initials: Default::default(),
+///     skew: Default::default(),
+/// };
+/// let mut mine = vec![ex(0, 1)];
+/// merge(&mut mine, vec![ex(0, 0), ex(1, 2)], &[1, 0], 3);
+/// assert_eq!(mine, [ex(0, 1), ex(1, 3)]);
+/// ```
+pub fn merge(mine: &mut Vec<Example>, others: Vec<Example>, remap: &[usize], offset: usize) {
+    for mut e in others {
+        e.state = remap.get(e.state).copied().unwrap_or(e.state);
+        e.rotation = e.rotation.saturating_add(offset);
+        if !mine.iter().any(|m| m.state == e.state) {
+            mine.push(e);
+        }
+    }
+    mine.sort_by_key(|e| e.state);
+}
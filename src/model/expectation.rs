@@ -0,0 +1,215 @@
+//! Expectations of how a whole run turns out.
+//!
+//! Usually, checks only inform a run's report: a run that sees failing
+//! states still succeeds.  Some runs want a verdict instead.  A negative test
+//! runs a test whose checker fails on a known weak behaviour, to confirm that
+//! the platform still shows it; such a run should fail if it never sees a
+//! failing state.  An [Expectation] is a policy over a finished report that
+//! decides whether the run as a whole met it.
+
+use super::{outcome, report::Report, Outcome};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, str::FromStr};
+
+/// String representations of expectations, used in the clap interface.
+pub mod string {
+    use super::outcome::string::{FAIL, PASS};
+
+    /// Names of all expectations.
+    pub const ALL: &[&str] = &[PASS, FAIL];
+}
+
+/// What a run expects its checks to say.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Expectation {
+    /// The run expects nothing to fail.
+    Pass,
+    /// The run expects something to fail.
+    Fail,
+}
+
+/// Expectations display as the outcome they expect.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::expectation::Expectation;
+/// assert_eq!(Expectation::Pass.to_string(), "pass");
+/// assert_eq!(Expectation::Fail.to_string(), "fail");
+/// ```
+impl Display for Expectation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Outcome::from(*self).fmt(f)
+    }
+}
+
+/// Expectations parse from the outcome they expect, which can't be unknown.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::expectation::Expectation;
+/// assert_eq!("FAIL".parse::<Expectation>(), Ok(Expectation::Fail));
+/// assert_eq!("unknown".parse::<Expectation>(), Err("unknown".to_owned()));
+/// ```
+impl FromStr for Expectation {
+    /// Errors just take ownership of the invalid string.
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse()? {
+            Outcome::Pass => Ok(Self::Pass),
+            Outcome::Fail => Ok(Self::Fail),
+            _ => Err(s.to_lowercase()),
+        }
+    }
+}
+
+impl From<Expectation> for Outcome {
+    fn from(e: Expectation) -> Self {
+        match e {
+            Expectation::Pass => Self::Pass,
+            Expectation::Fail => Self::Fail,
+        }
+    }
+}
+
+impl Expectation {
+    /// Checks whether `report` meets this expectation.
+    ///
+    /// A report fails if any of its states, or any of its end-of-run checks,
+    /// failed; unknown outcomes neither pass nor fail it.
+    ///
+    /// # Errors
+    ///
+    /// Fails, counting the failures, if the report doesn't meet the
+    /// expectation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{expectation::Expectation, Outcome, Report};
+    ///
+    /// let mut report = Report::default();
+    /// assert!(Expectation::Pass.check(&report).is_ok());
+    /// assert!(Expectation::Fail.check(&report).is_err());
+    ///
+    /// report.record_aggregate("fairness".to_owned(), Outcome::Fail);
+    /// assert_eq!(Expectation::Pass.check(&report).unwrap_err().failures, 1);
+    /// assert!(Expectation::Fail.check(&report).is_ok());
+    /// ```
+    pub fn check(self, report: &Report) -> Result<(), Unmet> {
+        let failures = failures(report);
+        let met = match self {
+            Self::Pass => failures == 0,
+            Self::Fail => failures != 0,
+        };
+        if met {
+            Ok(())
+        } else {
+            Err(Unmet {
+                expected: self,
+                failures,
+            })
+        }
+    }
+}
+
+/// Counts the failing states and end-of-run checks in `report`.
+fn failures(report: &Report) -> usize {
+    let states = report
+        .states
+        .iter()
+        .filter(|s| s.info.outcome == Outcome::Fail)
+        .count();
+    let checks = report
+        .aggregate
+        .values()
+        .filter(|o| **o == Outcome::Fail)
+        .count();
+    states.saturating_add(checks)
+}
+
+/// A report that didn't meet its run's expectation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Unmet {
+    /// What the run expected.
+    pub expected: Expectation,
+    /// The number of failing states and end-of-run checks in the report.
+    pub failures: usize,
+}
+
+/// Unmet expectations display as a short explanation.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::expectation::{Expectation, Unmet};
+/// let fail = Unmet { expected: Expectation::Fail, failures: 0 };
+/// assert_eq!(fail.to_string(), "expected a failure, but nothing failed");
+/// let pass = Unmet { expected: Expectation::Pass, failures: 2 };
+/// assert_eq!(pass.to_string(), "expected nothing to fail, but saw 2 failures");
+/// ```
+impl Display for Unmet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.expected {
+            Expectation::Pass => {
+                let noun = if self.failures == 1 {
+                    "failure"
+                } else {
+                    "failures"
+                };
+                write!(
+                    f,
+                    "expected nothing to fail, but saw {} {noun}",
+                    self.failures
+                )
+            }
+            Expectation::Fail => f.write_str("expected a failure, but nothing failed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{report, state};
+
+    /// Makes a report with one state for each outcome in `outcomes`.
+    fn report_of(outcomes: &[Outcome]) -> Report {
+        let mut r = Report::default();
+        for (i, outcome) in outcomes.iter().enumerate() {
+            let x = i32::try_from(i).unwrap();
+            r.insert(report::State {
+                state: [("x".into(), state::Value::I32(x))].into(),
+                info: state::Info::new(*outcome, i, 0),
+                initials: vec![],
+                interesting: vec![],
+                checks: std::collections::BTreeMap::default(),
+                aux_flags: std::collections::BTreeMap::default(),
+                hash: String::new(),
+            });
+        }
+        r
+    }
+
+    /// Tests that failing states fail reports, whatever else the report
+    /// holds, but unknown states don't.
+    #[test]
+    fn test_check_states() {
+        let failing = report_of(&[Outcome::Pass, Outcome::Unknown, Outcome::Fail]);
+        assert!(Expectation::Fail.check(&failing).is_ok());
+        assert_eq!(
+            Expectation::Pass.check(&failing),
+            Err(Unmet {
+                expected: Expectation::Pass,
+                failures: 1
+            })
+        );
+
+        let unknown = report_of(&[Outcome::Pass, Outcome::Unknown]);
+        assert!(Expectation::Pass.check(&unknown).is_ok());
+        assert!(Expectation::Fail.check(&unknown).is_err());
+    }
+}
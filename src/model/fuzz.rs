@@ -0,0 +1,58 @@
+//! Models for initial-value fuzzing.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A map from variable names to the distributions from which their initial
+/// values should be drawn.
+pub type Spec = BTreeMap<String, Distribution>;
+
+/// A distribution from which to draw initial values for a variable.
+///
+/// Distributions are distinguished by the types of their bounds, so that
+/// `{ min = 0, max = 4 }` is an integer range and `{ min = 0.0, max = 1.0 }`
+/// is a floating-point range.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Distribution {
+    /// A uniform distribution over an inclusive integer range.
+    ///
+    /// This can fuzz 32-bit integers, 64-bit floats (which then take only
+    /// integral values), and Booleans (where any non-zero value is true).
+    Int {
+        /// The minimum value.
+        min: i32,
+        /// The maximum value (inclusive).
+        max: i32,
+    },
+    /// A uniform distribution over a half-open floating-point range.
+    ///
+    /// This can fuzz only 64-bit floats.  If `min` and `max` are equal, the
+    /// distribution always yields `min`.
+    Float {
+        /// The minimum value.
+        min: f64,
+        /// The maximum value (exclusive).
+        max: f64,
+    },
+}
+
+impl Distribution {
+    /// Checks whether this distribution's bounds are in order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::fuzz::Distribution;
+    /// assert!(Distribution::Int { min: 0, max: 0 }.is_well_formed());
+    /// assert!(!Distribution::Int { min: 1, max: 0 }.is_well_formed());
+    /// assert!(!Distribution::Float { min: 0.0, max: f64::NAN }.is_well_formed());
+    /// ```
+    #[must_use]
+    pub fn is_well_formed(&self) -> bool {
+        match self {
+            Self::Int { min, max } => min <= max,
+            Self::Float { min, max } => min.is_finite() && max.is_finite() && min <= max,
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! The [Inspection] type, describing a test without running it.
+
+use super::{capability, invariant, manifest, state};
+use serde::Serialize;
+
+/// A description of a test's manifest and entry points.
+#[derive(Clone, Debug, Serialize)]
+pub struct Inspection {
+    /// The number of threads in the test.
+    pub threads: usize,
+    /// Whether the test supplies its own checker.
+    pub has_checker: bool,
+    /// What the test's checker checks, if the test describes it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check_description: Option<String>,
+    /// The optional capabilities that the test has.
+    pub capabilities: capability::Set,
+    /// The thread ID of the role that extra threads replicate, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scalable_role: Option<usize>,
+    /// The groups of threads that the test declares symmetric.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub symmetric: Vec<Vec<usize>>,
+    /// The variables in the test, grouped by type and then ordered by name.
+    pub vars: Vec<Var>,
+    /// The auxiliary flags that the test can raise, in bit order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aux_flags: Vec<String>,
+}
+
+/// A description of a variable in a test manifest.
+#[derive(Clone, Debug, Serialize)]
+pub struct Var {
+    /// The name of the variable.
+    pub name: manifest::VarName,
+    /// The name of the variable's type.
+    #[serde(rename = "type")]
+    pub ty: &'static str,
+    /// Whether the variable is atomic.
+    pub is_atomic: bool,
+    /// The variable's initial value, if it has one.
+    pub initial_value: Option<state::Value>,
+    /// The bounds the test declares on the variable's values, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<invariant::Bounds>,
+}
+
+impl Inspection {
+    /// Describes the test with the given manifest and capabilities.
+    #[must_use]
+    pub fn new(manifest: &manifest::Manifest, capabilities: capability::Set) -> Self {
+        let mut vars = vec![];
+        vars.extend(describe(&manifest.i32s, "i32", state::Value::I32));
+        vars.extend(describe(&manifest.bools, "bool", state::Value::Bool));
+        vars.extend(describe(&manifest.f64s, "f64", state::Value::F64));
+        for v in &mut vars {
+            v.bounds = manifest.invariants.get(&v.name).copied();
+        }
+        Self {
+            threads: manifest.n_threads.get(),
+            has_checker: capabilities.contains(&capability::Capability::Check),
+            check_description: None,
+            capabilities,
+            scalable_role: manifest.scalable_role,
+            symmetric: manifest.symmetric_groups(),
+            vars,
+            aux_flags: manifest.aux_flags.clone(),
+        }
+    }
+}
+
+fn describe<'a, T: Copy>(
+    map: &'a manifest::VarMap<T>,
+    ty: &'static str,
+    lift: impl Fn(T) -> state::Value + 'a,
+) -> impl Iterator<Item = Var> + 'a {
+    map.iter().map(move |(name, r)| Var {
+        name: name.clone(),
+        ty,
+        is_atomic: r.slot.is_atomic,
+        initial_value: r.initial_value.map(&lift),
+        bounds: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::slot::Slot;
+    use std::num::NonZeroUsize;
+
+    fn record<T>(initial_value: Option<T>, is_atomic: bool) -> manifest::VarRecord<T> {
+        manifest::VarRecord {
+            initial_value,
+            slot: Slot {
+                is_atomic,
+                index: 0,
+            },
+        }
+    }
+
+    /// Tests that inspections describe variables of every type.
+    #[test]
+    fn test_inspection_new() {
+        let manifest = manifest::Manifest {
+            n_threads: NonZeroUsize::new(2).unwrap(),
+            i32s: std::iter::once(("x".into(), record(Some(3), true))).collect(),
+            bools: std::iter::once(("b".into(), record(None, false))).collect(),
+            f64s: manifest::VarMap::new(),
+            scalable_role: None,
+            symmetry: vec![],
+            invariants: std::iter::once(("b".into(), invariant::Bounds { min: 0.0, max: 0.0 }))
+                .collect(),
+            declared: vec![],
+            aux_flags: vec!["retried".to_owned()],
+        };
+
+        let caps = std::iter::once(capability::Capability::Invariants).collect();
+        let i = Inspection::new(&manifest, caps);
+        assert_eq!(i.threads, 2);
+        assert!(!i.has_checker);
+        assert_eq!(i.capabilities.len(), 1);
+        assert_eq!(i.vars.len(), 2);
+        assert_eq!(i.aux_flags, ["retried"]);
+        assert_eq!((i.vars[0].name.as_str(), i.vars[0].ty), ("x", "i32"));
+        assert!(i.vars[0].is_atomic);
+        assert_eq!(i.vars[0].initial_value, Some(state::Value::I32(3)));
+        assert_eq!((i.vars[1].name.as_str(), i.vars[1].ty), ("b", "bool"));
+        assert_eq!(i.vars[1].initial_value, None);
+        assert_eq!(i.vars[0].bounds, None);
+        assert_eq!(
+            i.vars[1].bounds,
+            Some(invariant::Bounds { min: 0.0, max: 0.0 })
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Models for tagging interesting states.
+//!
+//! Interesting states are those a test author particularly wants to see, such
+//! as the weak behaviours a litmus test exists to expose.  Each is described
+//! by a [Matcher]: a partial valuation that matches every state agreeing with
+//! it on the variables it mentions.  Matchers thus sit below states in the
+//! usual information order on valuations, and one matcher can tag many
+//! states.
+
+use super::state::{State, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A map from names of interesting states to their matchers.
+pub type Spec = BTreeMap<String, Matcher>;
+
+/// A partial valuation, matching any state that agrees with it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Matcher(pub BTreeMap<String, Literal>);
+
+impl Matcher {
+    /// Checks whether `state` agrees with this matcher on every variable the
+    /// matcher mentions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{interest::{Literal, Matcher}, state::{State, Value}};
+    ///
+    /// let m = Matcher([("x".to_owned(), Literal::Int(0))].into());
+    /// let s = State::from([("x".into(), Value::I32(0)), ("y".into(), Value::I32(1))]);
+    /// assert!(m.matches(&s));
+    /// assert!(!Matcher([("y".to_owned(), Literal::Int(0))].into()).matches(&s));
+    /// assert!(!Matcher([("z".to_owned(), Literal::Int(0))].into()).matches(&s));
+    /// ```
+    #[must_use]
+    pub fn matches(&self, state: &State) -> bool {
+        self.0
+            .iter()
+            .all(|(var, lit)| state.get(var.as_str()).is_some_and(|v| lit.matches(*v)))
+    }
+}
+
+/// A literal value in a matcher.
+///
+/// Literals are distinguished by their TOML types: integers match 32-bit
+/// integers, Booleans match Booleans, and floats match 64-bit floats.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Literal {
+    /// A Boolean literal.
+    Bool(bool),
+    /// An integer literal.
+    Int(i64),
+    /// A floating-point literal.
+    Float(f64),
+}
+
+impl Literal {
+    /// Checks whether `value` is this literal.
+    #[must_use]
+    pub fn matches(self, value: Value) -> bool {
+        match (self, value) {
+            (Self::Bool(l), Value::Bool(v)) => l == v,
+            (Self::Int(l), Value::I32(v)) => l == i64::from(v),
+            // Float literals are exact, just as float states are bitwise.
+            (Self::Float(l), Value::F64(v)) => l.to_bits() == v.to_bits(),
+            _ => false,
+        }
+    }
+}
+
+/// Gets the names of the interesting states in `spec` that `state` matches.
+pub fn matching<'a>(spec: &'a Spec, state: &'a State) -> impl Iterator<Item = &'a str> + 'a {
+    spec.iter()
+        .filter(|(_, m)| m.matches(state))
+        .map(|(name, _)| name.as_str())
+}
+
+/// A record of when an interesting state was first seen.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sighting {
+    /// The name of the interesting state.
+    pub name: String,
+    /// The (zero-based) iteration at which a matching state was first seen,
+    /// if one was seen at all.
+    pub iteration: Option<usize>,
+}
+
+impl Sighting {
+    /// Merges another sighting of the same interesting state into this one,
+    /// keeping the earliest iteration.
+    pub fn merge(&mut self, other: &Sighting) {
+        self.iteration = match (self.iteration, other.iteration) {
+            (Some(x), Some(y)) => Some(x.min(y)),
+            (x, y) => x.or(y),
+        };
+    }
+}
@@ -0,0 +1,114 @@
+//! Invariants that tests declare over their variables.
+//!
+//! A test can bound the values each of its variables may take.  The runner
+//! checks every bound on every observation, failing any state that breaks
+//! one, so that corrupted states show up even if the test has no checker of
+//! its own.
+
+use super::{manifest::VarName, state::Value};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Display};
+
+/// Inclusive bounds on the values of a variable.
+///
+/// Values of every type compare as 64-bit floats, with Booleans as 0 and 1;
+/// infinite bounds leave that end open.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bounds {
+    /// The smallest permitted value.
+    pub min: f64,
+    /// The largest permitted value.
+    pub max: f64,
+}
+
+/// Bounds, keyed by variable name.
+pub type Map = BTreeMap<VarName, Bounds>;
+
+impl Bounds {
+    /// Gets whether `value` lies within these bounds.
+    ///
+    /// A `NaN` lies within no bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{invariant::Bounds, state::Value};
+    /// let b = Bounds { min: 0.0, max: 2.0 };
+    /// assert!(b.contains(Value::I32(2)));
+    /// assert!(!b.contains(Value::I32(-1)));
+    /// assert!(b.contains(Value::Bool(true)));
+    /// assert!(!b.contains(Value::F64(f64::NAN)));
+    /// ```
+    #[must_use]
+    pub fn contains(&self, value: Value) -> bool {
+        let x = match value {
+            Value::I32(x) => f64::from(x),
+            Value::Bool(x) => f64::from(u8::from(x)),
+            Value::F64(x) => x,
+        };
+        (self.min..=self.max).contains(&x)
+    }
+}
+
+/// Bounds display as an interval.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::invariant::Bounds;
+/// assert_eq!(Bounds { min: 0.0, max: f64::INFINITY }.to_string(), "[0, inf]");
+/// ```
+impl Display for Bounds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}, {}]", self.min, self.max)
+    }
+}
+
+/// A record of a value observed outside its variable's bounds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Violation {
+    /// The name of the variable.
+    pub var: VarName,
+    /// The out-of-bounds value.
+    pub value: Value,
+    /// The bounds that the value broke.
+    pub bounds: Bounds,
+    /// The number of times this value occurred.
+    pub occurs: usize,
+    /// The (zero-based) iteration at which this value first occurred.
+    pub iteration: usize,
+    /// The (zero-based) rotation in which this value first occurred.
+    pub rotation: usize,
+}
+
+impl Violation {
+    /// Constructs a record of a first violation of `bounds` by `value` on
+    /// `var`.
+    #[must_use]
+    pub fn new(
+        var: VarName,
+        value: Value,
+        bounds: Bounds,
+        iteration: usize,
+        rotation: usize,
+    ) -> Self {
+        Self {
+            var,
+            value,
+            bounds,
+            occurs: 1,
+            iteration,
+            rotation,
+        }
+    }
+
+    /// Merges another record of the same violation into this one, keeping
+    /// the earliest first occurrence.
+    pub fn merge(&mut self, other: &Violation) {
+        self.occurs = self.occurs.saturating_add(other.occurs);
+        if other.iteration < self.iteration {
+            self.iteration = other.iteration;
+            self.rotation = other.rotation;
+        }
+    }
+}
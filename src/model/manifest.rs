@@ -1,6 +1,18 @@
 //! Test manifests.
-use super::slot::{Reservation, ReservationSet, Slot};
-use std::{collections::BTreeMap, num::NonZeroUsize};
+use super::{
+    invariant,
+    slot::{Reservation, ReservationSet, Slot},
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, HashSet},
+    fmt::{Debug, Display},
+    num::NonZeroUsize,
+    ops::Deref,
+    sync::Arc,
+};
+use thiserror::Error;
 
 /// A test manifest, describing properties of a test.
 #[derive(Clone)]
@@ -10,8 +22,41 @@ pub struct Manifest {
     pub n_threads: NonZeroUsize,
     /// Ordered map of int variables declared in the test.
     pub i32s: VarMap<i32>,
+    /// Ordered map of Boolean variables declared in the test.
+    pub bools: VarMap<bool>,
+    /// Ordered map of 64-bit float variables declared in the test.
+    pub f64s: VarMap<f64>,
+    /// The thread ID of the role, if any, that extra threads can replicate
+    /// when scaling the test up (see [`Manifest::scale`]).
+    pub scalable_role: Option<usize>,
+    /// The symmetry class of each thread, indexed by thread ID, if the test
+    /// declares any.
+    ///
+    /// Threads in the same class run the same body, so states that differ
+    /// only by swapping those threads' registers are equivalent (see
+    /// [`Manifest::symmetric_groups`]).
+    pub symmetry: Vec<usize>,
+    /// Bounds on the values of the test's variables, which the runner checks
+    /// on every observation.
+    pub invariants: invariant::Map,
+    /// The names of the test's variables in the order it declared them, or
+    /// nothing if it gave no order.
+    ///
+    /// Outputs can show states in this order (see
+    /// [`super::state::KeyOrder::Declared`]).
+    pub declared: Vec<VarName>,
+    /// The names of the auxiliary flags that the test can raise, indexed by
+    /// their bits in the environment's flag word (see
+    /// [`crate::api::abs::Env::take_aux_flags`]).
+    ///
+    /// There can be at most [`MAX_AUX_FLAGS`] flags.
+    pub aux_flags: Vec<String>,
 }
 
+/// The most auxiliary flags that a test can declare: one for each bit of the
+/// environment's flag word.
+pub const MAX_AUX_FLAGS: usize = 64;
+
 impl Manifest {
     /// Constructs a slot reservation wide enough for the variables in this
     /// manifest.
@@ -19,17 +64,612 @@ impl Manifest {
     pub fn reserve(&self) -> ReservationSet {
         ReservationSet {
             i32s: reserve_var_map(&self.i32s),
+            bools: reserve_var_map(&self.bools),
+            f64s: reserve_var_map(&self.f64s),
+        }
+    }
+
+    /// Gets this manifest's name for the variable `name`, if it declares
+    /// one.
+    ///
+    /// The result shares storage with the manifest's own name.
+    #[must_use]
+    pub fn var_name(&self, name: &str) -> Option<VarName> {
+        let key = |k: &VarName| k.clone();
+        (self.i32s.get_key_value(name).map(|(k, _)| key(k)))
+            .or_else(|| self.bools.get_key_value(name).map(|(k, _)| key(k)))
+            .or_else(|| self.f64s.get_key_value(name).map(|(k, _)| key(k)))
+    }
+
+    /// Gets the groups of at least two threads that share a symmetry class,
+    /// each in thread order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::manifest::{Manifest, VarMap};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// let m = Manifest {
+    ///     n_threads: NonZeroUsize::new(4).unwrap(),
+    ///     i32s: VarMap::new(),
+    ///     bools: VarMap::new(),
+    ///     f64s: VarMap::new(),
+    ///     scalable_role: None,
+    ///     symmetry: vec![7, 3, 7, 5],
+    ///     invariants: Default::default(),
+    ///     aux_flags: vec![],
+    ///     declared: vec![],
+    /// };
+    /// assert_eq!(m.symmetric_groups(), [vec![0, 2]]);
+    /// ```
+    #[must_use]
+    pub fn symmetric_groups(&self) -> Vec<Vec<usize>> {
+        let mut classes: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (tid, class) in self.symmetry.iter().enumerate() {
+            classes.entry(*class).or_default().push(tid);
+        }
+        let mut groups: Vec<_> = classes.into_values().filter(|g| 1 < g.len()).collect();
+        groups.sort();
+        groups
+    }
+
+    /// Gets whether this manifest declares no variables at all.
+    ///
+    /// Tests without variables can still check side effects that they keep
+    /// to themselves, but every iteration shows the same, empty, state.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.i32s.is_empty() && self.bools.is_empty() && self.f64s.is_empty()
+    }
+
+    /// Gets whether this manifest declares a variable called `name`.
+    #[must_use]
+    pub fn has_var(&self, name: &str) -> bool {
+        self.i32s.contains_key(name)
+            || self.bools.contains_key(name)
+            || self.f64s.contains_key(name)
+    }
+
+    /// Scales this manifest up to `n_threads` threads.
+    ///
+    /// Each extra thread replicates the scalable role, and gets its own copy
+    /// of each of that role's register variables (those named `R:name`,
+    /// where `R` is the role's thread ID) as `T:name`, where `T` is the extra
+    /// thread's ID.  Copies go after all of the existing slots of the same
+    /// type and atomicity: the `i`th such register of the role, in slot
+    /// order, is at slot `base + (T - n) * count + i` for extra thread `T`,
+    /// where `base` is the number of existing slots, `n` the original thread
+    /// count, and `count` the number of such registers in the role.  Copies
+    /// of registers keep any invariants on the originals, and are declared
+    /// after every existing variable, by thread and then in the order of the
+    /// originals; extra threads are symmetric with the role.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the test has no scalable role, its scalable role isn't one
+    /// of its threads, or `n_threads` is fewer than the test's threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{manifest::{Manifest, ScaleError, VarMap, VarRecord}, slot::Slot};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// let var = |index| VarRecord { initial_value: None, slot: Slot { is_atomic: false, index } };
+    /// let mut i32s = VarMap::new();
+    /// i32s.insert("0:r0".into(), var(0));
+    /// i32s.insert("1:r0".into(), var(1));
+    /// let m = Manifest {
+    ///     n_threads: NonZeroUsize::new(2).unwrap(),
+    ///     i32s,
+    ///     bools: VarMap::new(),
+    ///     f64s: VarMap::new(),
+    ///     scalable_role: Some(1),
+    ///     symmetry: vec![],
+    ///     invariants: Default::default(),
+    ///     aux_flags: vec![],
+    ///     declared: vec!["1:r0".into(), "0:r0".into()],
+    /// };
+    ///
+    /// let scaled = m.scale(NonZeroUsize::new(4).unwrap()).unwrap();
+    /// assert_eq!(scaled.n_threads.get(), 4);
+    /// assert_eq!(scaled.i32s["2:r0"].slot.index, 2);
+    /// assert_eq!(scaled.i32s["3:r0"].slot.index, 3);
+    /// assert_eq!(scaled.symmetric_groups(), [vec![1, 2, 3]]);
+    /// assert_eq!(scaled.declared, ["1:r0", "0:r0", "2:r0", "3:r0"]);
+    ///
+    /// let m = Manifest { scalable_role: Some(2), ..m };
+    /// let result = m.scale(NonZeroUsize::new(4).unwrap());
+    /// assert!(matches!(result, Err(ScaleError::BadRole { role: 2, .. })));
+    /// ```
+    pub fn scale(&self, n_threads: NonZeroUsize) -> Result<Self, ScaleError> {
+        let role = self.scalable_role_for(n_threads)?;
+        let extra = self.n_threads.get()..n_threads.get();
+        let i32s = scale_var_map(&self.i32s, role, extra.clone());
+        let bools = scale_var_map(&self.bools, role, extra.clone());
+        let f64s = scale_var_map(&self.f64s, role, extra.clone());
+        let mut names: Names = i32s.keys().chain(bools.keys()).chain(f64s.keys()).collect();
+        let declared = scale_declared(&self.declared, role, extra.clone(), &mut names);
+        Ok(Self {
+            n_threads,
+            i32s,
+            bools,
+            f64s,
+            scalable_role: self.scalable_role,
+            symmetry: self.scale_symmetry(role, extra.clone()),
+            invariants: scale_invariants(&self.invariants, role, extra, &mut names),
+            aux_flags: self.aux_flags.clone(),
+            declared,
+        })
+    }
+
+    /// Gets the declared thread whose role each of `n_threads` threads plays
+    /// when this manifest is scaled up to them.
+    ///
+    /// Each of the test's own threads plays itself, and each extra thread the
+    /// scalable role.
+    ///
+    /// # Errors
+    ///
+    /// Fails as [`Self::scale`] would, except that any test can keep its own
+    /// thread count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::manifest::{Manifest, VarMap};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// let n = |n| NonZeroUsize::new(n).unwrap();
+    /// let mut m = Manifest {
+    ///     n_threads: n(2),
+    ///     i32s: VarMap::new(),
+    ///     bools: VarMap::new(),
+    ///     f64s: VarMap::new(),
+    ///     scalable_role: None,
+    ///     symmetry: vec![],
+    ///     invariants: Default::default(),
+    ///     aux_flags: vec![],
+    ///     declared: vec![],
+    /// };
+    /// assert_eq!(m.roles(n(2)).unwrap(), [0, 1]);
+    /// assert!(m.roles(n(3)).is_err());
+    ///
+    /// m.scalable_role = Some(1);
+    /// assert_eq!(m.roles(n(4)).unwrap(), [0, 1, 1, 1]);
+    /// assert!(m.roles(n(1)).is_err());
+    /// ```
+    pub fn roles(&self, n_threads: NonZeroUsize) -> Result<Vec<usize>, ScaleError> {
+        let declared = 0..self.n_threads.get();
+        if n_threads == self.n_threads {
+            return Ok(declared.collect());
+        }
+        let role = self.scalable_role_for(n_threads)?;
+        let extra = n_threads.get() - self.n_threads.get();
+        Ok(declared.chain(std::iter::repeat_n(role, extra)).collect())
+    }
+
+    /// Describes the first way, if any, in which `other` has a different
+    /// thread count, or different variables, from this manifest.
+    ///
+    /// Two builds of the same test have matching manifests, so their states
+    /// can be compared, even if (say) they start from different initial
+    /// values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::manifest::{Manifest, VarMap};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// let m = Manifest {
+    ///     n_threads: NonZeroUsize::new(2).unwrap(),
+    ///     i32s: VarMap::new(),
+    ///     bools: VarMap::new(),
+    ///     f64s: VarMap::new(),
+    ///     scalable_role: None,
+    ///     symmetry: vec![],
+    ///     invariants: Default::default(),
+    ///     aux_flags: vec![],
+    ///     declared: vec![],
+    /// };
+    /// assert_eq!(m.mismatch(&m.clone()), None);
+    ///
+    /// let wider = Manifest { n_threads: NonZeroUsize::new(3).unwrap(), ..m.clone() };
+    /// assert_eq!(m.mismatch(&wider).unwrap(), "2 threads against 3");
+    /// ```
+    #[must_use]
+    pub fn mismatch(&self, other: &Self) -> Option<String> {
+        if self.n_threads != other.n_threads {
+            return Some(format!(
+                "{} threads against {}",
+                self.n_threads, other.n_threads
+            ));
+        }
+        [
+            ("int", keys(&self.i32s), keys(&other.i32s)),
+            ("Boolean", keys(&self.bools), keys(&other.bools)),
+            ("float", keys(&self.f64s), keys(&other.f64s)),
+        ]
+        .into_iter()
+        .find(|(_, mine, theirs)| mine != theirs)
+        .map(|(ty, mine, theirs)| {
+            format!(
+                "{ty} variables {} against {}",
+                mine.join(","),
+                theirs.join(",")
+            )
+        })
+    }
+
+    /// Describes the first way, if any, in which `other` differs from this
+    /// manifest at all.
+    ///
+    /// Unlike [`Manifest::mismatch`], this also compares initial values,
+    /// scalable roles, symmetry, invariants, auxiliary flags, and declaration
+    /// orders, so that it can tell whether a test's manifest has changed
+    /// since it was read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{manifest::{Manifest, ScaleError, VarMap, VarRecord}, slot::Slot};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// let slot = Slot { is_atomic: true, index: 0 };
+    /// let record = |v| VarRecord { initial_value: Some(v), slot };
+    /// let m = Manifest {
+    ///     n_threads: NonZeroUsize::new(2).unwrap(),
+    ///     i32s: VarMap::from([("x".into(), record(0))]),
+    ///     bools: VarMap::new(),
+    ///     f64s: VarMap::new(),
+    ///     scalable_role: None,
+    ///     symmetry: vec![],
+    ///     invariants: Default::default(),
+    ///     aux_flags: vec![],
+    ///     declared: vec![],
+    /// };
+    /// assert_eq!(m.difference(&m.clone()), None);
+    ///
+    /// let moved = Manifest { i32s: VarMap::from([("x".into(), record(1))]), ..m.clone() };
+    /// assert_eq!(m.mismatch(&moved), None);
+    /// assert_eq!(m.difference(&moved).unwrap(), "initial value of x");
+    /// ```
+    #[must_use]
+    pub fn difference(&self, other: &Self) -> Option<String> {
+        if let Some(reason) = self.mismatch(other) {
+            return Some(reason);
+        }
+        let changed = changed_initial(&self.i32s, &other.i32s, |x, y| x == y)
+            .or_else(|| changed_initial(&self.bools, &other.bools, |x, y| x == y))
+            // Comparing bits means that a NaN initial value stays the same.
+            .or_else(|| {
+                changed_initial(&self.f64s, &other.f64s, |x, y| x.to_bits() == y.to_bits())
+            });
+        if let Some(var) = changed {
+            return Some(format!("initial value of {var}"));
+        }
+        [
+            ("scalable role", self.scalable_role != other.scalable_role),
+            ("symmetry", self.symmetry != other.symmetry),
+            ("invariants", self.invariants != other.invariants),
+            ("auxiliary flags", self.aux_flags != other.aux_flags),
+            ("declaration order", self.declared != other.declared),
+        ]
+        .into_iter()
+        .find_map(|(what, changed)| changed.then(|| what.to_owned()))
+    }
+
+    /// Gets the scalable role, checking that it can scale this manifest up
+    /// to `n_threads` threads.
+    fn scalable_role_for(&self, n_threads: NonZeroUsize) -> Result<usize, ScaleError> {
+        let role = self
+            .scalable_role
+            .ok_or(ScaleError::NotScalable(self.n_threads))?;
+        if self.n_threads.get() <= role {
+            return Err(ScaleError::BadRole {
+                role,
+                n_threads: self.n_threads,
+            });
+        }
+        if n_threads < self.n_threads {
+            return Err(ScaleError::TooFewThreads(self.n_threads));
+        }
+        Ok(role)
+    }
+
+    /// Gets the symmetry classes of this manifest's threads, followed by the
+    /// class of `role` for each extra thread in `extra`.
+    ///
+    /// Threads of tests that declare no symmetry each get their own class.
+    fn scale_symmetry(&self, role: usize, extra: std::ops::Range<usize>) -> Vec<usize> {
+        let mut symmetry = if self.symmetry.is_empty() {
+            (0..self.n_threads.get()).collect()
+        } else {
+            self.symmetry.clone()
+        };
+        let class = symmetry.get(role).copied().unwrap_or(role);
+        symmetry.extend(extra.map(|_| class));
+        symmetry
+    }
+}
+
+/// Errors that can occur when scaling a [Manifest].
+#[derive(Debug, Error)]
+pub enum ScaleError {
+    /// The test has no role to replicate, and so must run with the thread
+    /// count its manifest declares.
+    #[error("test declares {0} threads, and has no scalable role to replicate")]
+    NotScalable(NonZeroUsize),
+    /// The test's scalable role isn't one of its threads.
+    #[error("scalable role {role} isn't one of the test's {n_threads} threads")]
+    BadRole {
+        /// The scalable role.
+        role: usize,
+        /// The number of threads that the test declares.
+        n_threads: NonZeroUsize,
+    },
+    /// The test already has more threads than requested.
+    #[error("test needs at least {0} threads")]
+    TooFewThreads(NonZeroUsize),
+}
+
+/// Adds copies of the register variables in `map` belonging to `role` for
+/// each thread in `extra`.
+fn scale_var_map<T: Clone + Default>(
+    map: &VarMap<T>,
+    role: usize,
+    extra: std::ops::Range<usize>,
+) -> VarMap<T> {
+    let prefix = format!("{role}:");
+    let base = reserve_var_map(map);
+    let mut scaled = map.clone();
+    for is_atomic in [false, true] {
+        let mut regs: Vec<_> = map
+            .iter()
+            .filter(|(k, v)| v.slot.is_atomic == is_atomic && k.starts_with(&prefix))
+            .collect();
+        regs.sort_by_key(|(_, v)| v.slot.index);
+        let start = if is_atomic {
+            base.atomic
+        } else {
+            base.non_atomic
+        };
+        for (j, tid) in extra.clone().enumerate() {
+            for (i, (name, record)) in regs.iter().enumerate() {
+                let slot = Slot {
+                    is_atomic,
+                    index: start + j * regs.len() + i,
+                };
+                scaled.insert(
+                    format!("{tid}:{}", &name[prefix.len()..]).into(),
+                    VarRecord {
+                        initial_value: record.initial_value.clone(),
+                        slot,
+                    },
+                );
+            }
         }
     }
+    scaled
+}
+
+/// Adds copies of the invariants in `map` on registers belonging to `role`
+/// for each thread in `extra`, taking the copies' names from `names`.
+fn scale_invariants(
+    map: &invariant::Map,
+    role: usize,
+    extra: std::ops::Range<usize>,
+    names: &mut Names,
+) -> invariant::Map {
+    let prefix = format!("{role}:");
+    let mut scaled = map.clone();
+    for (name, bounds) in map {
+        if let Some(reg) = name.strip_prefix(&prefix) {
+            for tid in extra.clone() {
+                scaled.insert(names.intern(&format!("{tid}:{reg}")), *bounds);
+            }
+        }
+    }
+    scaled
+}
+
+/// Adds the names of copies of the registers in `declared` belonging to
+/// `role` for each thread in `extra`, taking the copies' names from `names`.
+fn scale_declared(
+    declared: &[VarName],
+    role: usize,
+    extra: std::ops::Range<usize>,
+    names: &mut Names,
+) -> Vec<VarName> {
+    let prefix = format!("{role}:");
+    let regs: Vec<_> = declared
+        .iter()
+        .filter_map(|name| name.strip_prefix(&prefix))
+        .collect();
+    let mut scaled = declared.to_vec();
+    for tid in extra {
+        scaled.extend(regs.iter().map(|reg| names.intern(&format!("{tid}:{reg}"))));
+    }
+    scaled
 }
 
 /// Type alias for ordered variable maps.
-pub type VarMap<T> = BTreeMap<String, VarRecord<T>>;
+pub type VarMap<T> = BTreeMap<VarName, VarRecord<T>>;
+
+/// The name of a variable in a test.
+///
+/// Names share their storage, so cloning one never allocates; this lets the
+/// observer key every state it builds with the manifest's own names.  Names
+/// borrow as, compare with, and serialise as plain strings.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::manifest::VarName;
+/// use std::collections::BTreeMap;
+///
+/// let x = VarName::from("0:r0");
+/// assert_eq!(x, "0:r0");
+/// assert_eq!(x.clone().as_str(), "0:r0");
+///
+/// let map = BTreeMap::from([(x, 1)]);
+/// assert_eq!(map["0:r0"], 1);
+/// ```
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VarName(Arc<str>);
+
+impl VarName {
+    /// Gets this name as a string slice.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Gets whether this name shares its storage with `other`.
+    #[must_use]
+    pub fn shares_with(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for VarName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for VarName {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for VarName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for VarName {
+    fn from(name: &str) -> Self {
+        Self(name.into())
+    }
+}
+
+impl From<String> for VarName {
+    fn from(name: String) -> Self {
+        Self(name.into())
+    }
+}
+
+impl PartialEq<str> for VarName {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for VarName {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl Display for VarName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Debug for VarName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&*self.0, f)
+    }
+}
+
+impl Serialize for VarName {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for VarName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
+/// A cache of variable names, handing out one shared [`VarName`] for each
+/// distinct string.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::manifest::Names;
+///
+/// let mut names = Names::default();
+/// let x = names.intern("x");
+/// assert!(x.shares_with(&names.intern("x")));
+/// assert!(!x.shares_with(&names.intern("y")));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Names(HashSet<VarName>);
+
+impl Names {
+    /// Gets the cached name for `name`, caching a new one if there is none.
+    pub fn intern(&mut self, name: &str) -> VarName {
+        if let Some(n) = self.0.get(name) {
+            return n.clone();
+        }
+        let n = VarName::from(name);
+        self.0.insert(n.clone());
+        n
+    }
+
+    /// Gets the cached name equal to `name`, caching `name` if there is none.
+    pub fn intern_name(&mut self, name: VarName) -> VarName {
+        if let Some(n) = self.0.get(name.as_str()) {
+            return n.clone();
+        }
+        self.0.insert(name.clone());
+        name
+    }
+}
+
+impl<'a> FromIterator<&'a VarName> for Names {
+    fn from_iter<I: IntoIterator<Item = &'a VarName>>(iter: I) -> Self {
+        Self(iter.into_iter().cloned().collect())
+    }
+}
 
 fn reserve_var_map<T: Default>(map: &VarMap<T>) -> Reservation<T> {
     Reservation::of_slots(map.values().map(|x| x.slot))
 }
 
+/// Gets the names of the variables in `map`, in order.
+fn keys<T>(map: &VarMap<T>) -> Vec<&str> {
+    map.keys().map(|k| &**k).collect()
+}
+
+/// Gets the name of the first variable in `mine` whose initial value isn't
+/// `same` as its value in `theirs`, which has the same variables.
+fn changed_initial<'a, T: Copy>(
+    mine: &'a VarMap<T>,
+    theirs: &VarMap<T>,
+    same: impl Fn(T, T) -> bool,
+) -> Option<&'a str> {
+    mine.iter()
+        .zip(theirs.values())
+        .find(|((_, m), t)| match (m.initial_value, t.initial_value) {
+            (Some(x), Some(y)) => !same(x, y),
+            (x, y) => x.is_some() != y.is_some(),
+        })
+        .map(|((name, _), _)| &**name)
+}
+
 /// A variable record in a test manifest.
 #[derive(Clone)]
 pub struct VarRecord<T> {
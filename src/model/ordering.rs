@@ -0,0 +1,106 @@
+//! Memory orderings of test accesses to atomic variables.
+//!
+//! The same litmus test can allow or forbid a weak behaviour depending on the
+//! orderings of its accesses, so reports record which orderings each atomic
+//! variable was accessed with: a report of a weak behaviour means little
+//! without knowing whether the test asked for it.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fmt::Display, sync::atomic};
+
+/// A memory ordering, as in C11 and [`std::sync::atomic::Ordering`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ordering {
+    /// Relaxed ordering: atomicity only.
+    Relaxed,
+    /// Acquire ordering, for loads.
+    Acquire,
+    /// Release ordering, for stores.
+    Release,
+    /// Both acquire and release ordering, for read-modify-writes.
+    AcqRel,
+    /// Sequentially consistent ordering.
+    SeqCst,
+}
+
+/// A set of orderings.
+pub type Set = BTreeSet<Ordering>;
+
+impl Ordering {
+    /// Every ordering, from weakest to strongest.
+    pub const ALL: [Self; 5] = [
+        Self::Relaxed,
+        Self::Acquire,
+        Self::Release,
+        Self::AcqRel,
+        Self::SeqCst,
+    ];
+
+    /// Gets the ordering corresponding to the standard library ordering
+    /// `order`, if there is one.
+    #[must_use]
+    pub fn of_std(order: atomic::Ordering) -> Option<Self> {
+        match order {
+            atomic::Ordering::Relaxed => Some(Self::Relaxed),
+            atomic::Ordering::Acquire => Some(Self::Acquire),
+            atomic::Ordering::Release => Some(Self::Release),
+            atomic::Ordering::AcqRel => Some(Self::AcqRel),
+            atomic::Ordering::SeqCst => Some(Self::SeqCst),
+            _ => None,
+        }
+    }
+
+    /// Gets the bit standing for this ordering in a bitset of orderings.
+    ///
+    /// Environments can keep such bitsets in a single atomic byte, and
+    /// convert them with [`of_bits`].
+    #[must_use]
+    pub fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Gets the set of orderings whose bits (see [`Ordering::bit`]) are in
+/// `bits`.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::ordering::{of_bits, Ordering};
+/// let bits = Ordering::Acquire.bit() | Ordering::SeqCst.bit();
+/// assert_eq!(
+///     of_bits(bits).into_iter().collect::<Vec<_>>(),
+///     [Ordering::Acquire, Ordering::SeqCst]
+/// );
+/// assert!(of_bits(0).is_empty());
+/// ```
+#[must_use]
+pub fn of_bits(bits: u8) -> Set {
+    Ordering::ALL
+        .into_iter()
+        .filter(|o| bits & o.bit() != 0)
+        .collect()
+}
+
+/// Orderings display as their C11 names, without the `memory_order_` prefix.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::ordering::Ordering;
+/// assert_eq!(Ordering::Relaxed.to_string(), "relaxed");
+/// assert_eq!(Ordering::AcqRel.to_string(), "acq_rel");
+/// assert_eq!(Ordering::SeqCst.to_string(), "seq_cst");
+/// ```
+impl Display for Ordering {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Relaxed => "relaxed",
+            Self::Acquire => "acquire",
+            Self::Release => "release",
+            Self::AcqRel => "acq_rel",
+            Self::SeqCst => "seq_cst",
+        })
+    }
+}
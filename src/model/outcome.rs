@@ -123,7 +123,130 @@ impl Outcome {
             Self::Fail
         }
     }
+
+    /// Conjoins two outcomes, treating unknown outcomes as possibly either.
+    ///
+    /// This is three-valued conjunction: a failure of either side fails
+    /// the conjunction, even if the other side is unknown.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::Outcome;
+    /// assert_eq!(Outcome::Pass.and(Outcome::Pass), Outcome::Pass);
+    /// assert_eq!(Outcome::Pass.and(Outcome::Unknown), Outcome::Unknown);
+    /// assert_eq!(Outcome::Unknown.and(Outcome::Fail), Outcome::Fail);
+    /// ```
+    #[must_use]
+    pub fn and(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Fail, _) | (_, Self::Fail) => Self::Fail,
+            (Self::Unknown, _) | (_, Self::Unknown) => Self::Unknown,
+            _ => Self::Pass,
+        }
+    }
+
+    /// Disjoins two outcomes, treating unknown outcomes as possibly either.
+    ///
+    /// This is three-valued disjunction: a pass of either side passes the
+    /// disjunction, even if the other side is unknown.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::Outcome;
+    /// assert_eq!(Outcome::Fail.or(Outcome::Fail), Outcome::Fail);
+    /// assert_eq!(Outcome::Fail.or(Outcome::Unknown), Outcome::Unknown);
+    /// assert_eq!(Outcome::Unknown.or(Outcome::Pass), Outcome::Pass);
+    /// ```
+    #[must_use]
+    pub fn or(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Pass, _) | (_, Self::Pass) => Self::Pass,
+            (Self::Unknown, _) | (_, Self::Unknown) => Self::Unknown,
+            _ => Self::Fail,
+        }
+    }
+}
+
+/// Counts of the outcomes of a run's iterations.
+///
+/// Runners tally outcomes for tests without variables, whose one state
+/// can't tell the iterations that passed from those that failed.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tally {
+    /// The number of passing iterations.
+    pub pass: usize,
+    /// The number of failing iterations.
+    pub fail: usize,
+    /// The number of iterations with unknown outcomes.
+    pub unknown: usize,
+}
+
+impl Tally {
+    /// Records an iteration with outcome `outcome`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::outcome::{Outcome, Tally};
+    ///
+    /// let mut t = Tally::default();
+    /// t.record(Outcome::Pass);
+    /// t.record(Outcome::Fail);
+    /// t.record(Outcome::Pass);
+    /// assert_eq!(t, Tally { pass: 2, fail: 1, unknown: 0 });
+    /// assert_eq!(t.total(), 3);
+    /// ```
+    pub fn record(&mut self, outcome: Outcome) {
+        let count = match outcome {
+            Outcome::Pass => &mut self.pass,
+            Outcome::Fail => &mut self.fail,
+            Outcome::Unknown => &mut self.unknown,
+        };
+        *count = count.saturating_add(1);
+    }
+
+    /// Merges `other`, from another run, into this tally.
+    pub fn merge(&mut self, other: &Self) {
+        self.pass = self.pass.saturating_add(other.pass);
+        self.fail = self.fail.saturating_add(other.fail);
+        self.unknown = self.unknown.saturating_add(other.unknown);
+    }
+
+    /// Gets the number of iterations tallied.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.pass
+            .saturating_add(self.fail)
+            .saturating_add(self.unknown)
+    }
 }
+
+/// Tallies display as their counts, by outcome.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::outcome::Tally;
+/// let t = Tally { pass: 2, fail: 1, unknown: 0 };
+/// assert_eq!(t.to_string(), "2 pass, 1 fail, 0 unknown");
+/// ```
+impl Display for Tally {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}, {} {}, {} {}",
+            self.pass,
+            string::PASS,
+            self.fail,
+            string::FAIL,
+            self.unknown,
+            string::UNKNOWN
+        )
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::Outcome;
@@ -132,34 +255,34 @@ mod test {
     /// `max` of an empty iterator should return `None`.
     fn test_max_empty() {
         let v: std::vec::Vec<Outcome> = vec![];
-        assert_eq!(v.into_iter().max(), None)
+        assert_eq!(v.into_iter().max(), None);
     }
 
     #[test]
     /// `max` of an iterator of passes should return a pass.
     fn test_max_passes() {
         let v = vec![Outcome::Pass, Outcome::Pass, Outcome::Pass];
-        assert_eq!(v.into_iter().max(), Some(Outcome::Pass))
+        assert_eq!(v.into_iter().max(), Some(Outcome::Pass));
     }
 
     #[test]
     /// `max` of an iterator of v should return a fail.
     fn test_max_fail() {
         let v = vec![Outcome::Fail, Outcome::Fail, Outcome::Fail];
-        assert_eq!(v.into_iter().max(), Some(Outcome::Fail))
+        assert_eq!(v.into_iter().max(), Some(Outcome::Fail));
     }
 
     #[test]
     /// `max` of a mixed determinate iterator should return a fail.
     fn test_max_mixed() {
         let v = vec![Outcome::Pass, Outcome::Fail, Outcome::Pass];
-        assert_eq!(v.into_iter().max(), Some(Outcome::Fail))
+        assert_eq!(v.into_iter().max(), Some(Outcome::Fail));
     }
 
     #[test]
     /// `max` of an iterator with one unknown should return an unknown.
     fn test_max_unknown() {
         let v = vec![Outcome::Unknown, Outcome::Fail, Outcome::Pass];
-        assert_eq!(v.into_iter().max(), Some(Outcome::Unknown))
+        assert_eq!(v.into_iter().max(), Some(Outcome::Unknown));
     }
 }
@@ -0,0 +1,176 @@
+//! Models of the machine on which a test ran.
+//!
+//! Reports carry a [Platform] so that result files collected from different
+//! machines say where they came from.  Weak behaviours depend heavily on the
+//! processor and on how threads share cores, so we record the CPU topology
+//! as well as the OS and architecture.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// A description of the machine on which a test ran.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Platform {
+    /// The operating system, as named by Rust (for example, `linux`).
+    pub os: String,
+    /// The operating system family, as named by Rust (`unix` or `windows`).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub family: String,
+    /// The CPU architecture, as named by Rust (for example, `x86_64`).
+    pub arch: String,
+    /// The number of logical CPUs available to the runner.
+    pub cpus: usize,
+    /// The CPU topology, if the runner could find it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topology: Option<Topology>,
+}
+
+/// The layout of logical CPUs onto cores and packages.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Topology {
+    /// The CPU model name, if the OS reports one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// The number of physical packages (sockets).
+    pub packages: usize,
+    /// The number of physical cores across all packages.
+    pub cores: usize,
+    /// The number of logical CPUs across all packages.
+    pub logical: usize,
+}
+
+impl Topology {
+    /// Gets the number of hardware threads per core, rounding down.
+    ///
+    /// A value above 1 means that the CPUs use simultaneous multithreading
+    /// (hyperthreading).
+    #[must_use]
+    pub fn threads_per_core(&self) -> usize {
+        self.logical.checked_div(self.cores).unwrap_or(0)
+    }
+
+    /// Parses a topology from the contents of a Linux `/proc/cpuinfo`.
+    ///
+    /// Returns `None` if `cpuinfo` lists no processors.  If it lacks physical
+    /// and core IDs, as on some virtual machines and architectures, we
+    /// assume that each logical CPU is its own core in a single package.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::platform::Topology;
+    ///
+    /// let info = "processor\t: 0\nmodel name\t: Widget\nphysical id\t: 0\ncore id\t: 0\n\n\
+    ///             processor\t: 1\nmodel name\t: Widget\nphysical id\t: 0\ncore id\t: 0\n";
+    /// let t = Topology::from_cpuinfo(info).unwrap();
+    /// assert_eq!(t.model.as_deref(), Some("Widget"));
+    /// assert_eq!((t.packages, t.cores, t.logical), (1, 1, 2));
+    /// assert_eq!(t.threads_per_core(), 2);
+    /// ```
+    #[must_use]
+    pub fn from_cpuinfo(cpuinfo: &str) -> Option<Self> {
+        let mut model = None;
+        let mut logical = 0;
+        let mut packages = BTreeSet::new();
+        let mut cores = BTreeSet::new();
+        // Core IDs are only unique within their package.
+        let mut package = None;
+
+        for line in cpuinfo.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "processor" => {
+                    logical += 1;
+                    package = None;
+                }
+                "model name" if model.is_none() => model = Some(value.to_owned()),
+                "physical id" => {
+                    package = Some(value.to_owned());
+                    packages.insert(value.to_owned());
+                }
+                "core id" => {
+                    cores.insert((package.clone(), value.to_owned()));
+                }
+                _ => {}
+            }
+        }
+
+        (logical != 0).then(|| Self {
+            model,
+            packages: packages.len().max(1),
+            cores: if cores.is_empty() {
+                logical
+            } else {
+                cores.len()
+            },
+            logical,
+        })
+    }
+}
+
+impl Platform {
+    /// Collects a description of the machine on which we're running.
+    ///
+    /// This never fails; anything we can't find out is left out.
+    #[must_use]
+    pub fn collect() -> Self {
+        Self {
+            os: std::env::consts::OS.to_owned(),
+            family: std::env::consts::FAMILY.to_owned(),
+            arch: std::env::consts::ARCH.to_owned(),
+            cpus: std::thread::available_parallelism().map_or(0, std::num::NonZeroUsize::get),
+            topology: Topology::collect(),
+        }
+    }
+}
+
+impl Topology {
+    /// Collects the topology of the machine on which we're running, if the
+    /// OS exposes it.
+    ///
+    /// Only Linux does, through `/proc/cpuinfo`; elsewhere, this is `None`.
+    #[cfg(target_os = "linux")]
+    #[must_use]
+    pub fn collect() -> Option<Self> {
+        std::fs::read_to_string("/proc/cpuinfo")
+            .ok()
+            .and_then(|s| Self::from_cpuinfo(&s))
+    }
+
+    /// Collects the topology of the machine on which we're running, if the
+    /// OS exposes it.
+    ///
+    /// Only Linux does, through `/proc/cpuinfo`; elsewhere, this is `None`.
+    #[cfg(not(target_os = "linux"))]
+    #[must_use]
+    pub fn collect() -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that core IDs are distinguished by package.
+    #[test]
+    fn test_from_cpuinfo_packages() {
+        let cpu = |i: usize| format!("processor : {i}\nphysical id : {}\ncore id : 0\n\n", i / 2);
+        let info: Vec<_> = (0..4).map(cpu).collect();
+        let t = Topology::from_cpuinfo(&info.concat()).unwrap();
+        assert_eq!((t.packages, t.cores, t.logical), (2, 2, 4));
+        assert_eq!(t.model, None);
+    }
+
+    /// Tests that cpuinfo without IDs falls back to one core per logical CPU.
+    #[test]
+    fn test_from_cpuinfo_no_ids() {
+        let t = Topology::from_cpuinfo("processor : 0\n\nprocessor : 1\n").unwrap();
+        assert_eq!((t.packages, t.cores, t.logical), (1, 2, 2));
+        assert_eq!(t.threads_per_core(), 1);
+        assert_eq!(Topology::from_cpuinfo(""), None);
+    }
+}
@@ -0,0 +1,135 @@
+//! Models for the scheduling priorities of test threads.
+//!
+//! Running some threads at a different priority from others is a classic
+//! way to expose rare interleavings: the scheduler then favours some threads
+//! over the others whenever they compete for a CPU, so that (for instance)
+//! one thread's writes land in bursts between another's reads.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+/// A map from thread IDs (as strings, as they are TOML keys) to the
+/// priorities at which to run those threads.
+///
+/// The key [`ALL_THREADS`] sets the priority of every thread that has no
+/// key of its own.
+pub type Spec = BTreeMap<String, Priority>;
+
+/// The key, in a [Spec], that stands for every thread.
+pub const ALL_THREADS: &str = "*";
+
+/// The least and most favourable nice levels.
+const NICE_RANGE: std::ops::RangeInclusive<i8> = -20..=19;
+
+/// The least and most favourable realtime priorities.
+const REALTIME_RANGE: std::ops::RangeInclusive<u8> = 1..=99;
+
+/// A scheduling priority for a thread, written as `nice:N`, `fifo:N`, or
+/// `rr:N`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Priority {
+    /// A nice level under the normal (time-sharing) scheduling class, from
+    /// -20 (most favoured) to 19 (least favoured).
+    ///
+    /// Anyone can make a thread nicer, but making it less nice usually needs
+    /// privileges.
+    Nice(i8),
+    /// A priority, from 1 to 99, under the first-in-first-out realtime
+    /// scheduling class, where a thread runs until it blocks or yields.
+    Fifo(u8),
+    /// A priority, from 1 to 99, under the round-robin realtime scheduling
+    /// class, where threads of the same priority take turns.
+    RoundRobin(u8),
+}
+
+/// Priorities display in the same form that they parse from.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::priority::Priority;
+/// assert_eq!(Priority::Nice(-5).to_string(), "nice:-5");
+/// assert_eq!(Priority::RoundRobin(10).to_string(), "rr:10");
+/// ```
+impl Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Nice(n) => write!(f, "nice:{n}"),
+            Self::Fifo(p) => write!(f, "fifo:{p}"),
+            Self::RoundRobin(p) => write!(f, "rr:{p}"),
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    /// Parses a priority.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::priority::Priority;
+    ///
+    /// assert_eq!("nice:10".parse(), Ok(Priority::Nice(10)));
+    /// assert_eq!("fifo:50".parse(), Ok(Priority::Fifo(50)));
+    /// assert!("nice:20".parse::<Priority>().is_err());
+    /// assert!("rr:0".parse::<Priority>().is_err());
+    /// assert!("10".parse::<Priority>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (class, level) = s
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| format!("priority {s:?} needs a class, like nice:10 or fifo:10"))?;
+        let bad = |e: &dyn Display| format!("bad {class} priority {level:?}: {e}");
+        match class.trim() {
+            "nice" => {
+                let n: i8 = level.trim().parse().map_err(|e| bad(&e))?;
+                in_range(n, &NICE_RANGE)
+                    .map(Self::Nice)
+                    .map_err(|e| bad(&e))
+            }
+            "fifo" | "rr" => {
+                let p: u8 = level.trim().parse().map_err(|e| bad(&e))?;
+                let p = in_range(p, &REALTIME_RANGE).map_err(|e| bad(&e))?;
+                Ok(if class.trim() == "fifo" {
+                    Self::Fifo(p)
+                } else {
+                    Self::RoundRobin(p)
+                })
+            }
+            c => Err(format!("unknown priority class {c:?}")),
+        }
+    }
+}
+
+impl TryFrom<String> for Priority {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Priority> for String {
+    fn from(p: Priority) -> Self {
+        p.to_string()
+    }
+}
+
+fn in_range<T: PartialOrd + Display>(
+    x: T,
+    range: &std::ops::RangeInclusive<T>,
+) -> Result<T, String> {
+    if range.contains(&x) {
+        Ok(x)
+    } else {
+        Err(format!(
+            "must be between {} and {}",
+            range.start(),
+            range.end()
+        ))
+    }
+}
@@ -0,0 +1,104 @@
+//! Projections of reports onto named groups of variables.
+//!
+//! Tests often have more variables than matter to any one question: a test
+//! may only care how two flags end up, whatever its registers did.  A
+//! [Spec] names groups of variables, and projecting a report onto each group
+//! gives the group's marginal histogram: each distinct valuation of the
+//! group's variables, with how often, and how badly, the states showing it
+//! occurred.  Projections derive from the report's states, so reports
+//! recompute them rather than merging them.
+
+use super::{report, state, Outcome};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// A map from names of projections to the variables they keep.
+pub type Spec = BTreeMap<String, Vec<String>>;
+
+/// The marginal histogram of a report over a group of variables.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Projection {
+    /// The name of the projection.
+    pub name: String,
+    /// The variables that the projection keeps, as configured.
+    pub vars: Vec<String>,
+    /// Each distinct valuation of the variables, in the order the report's
+    /// states first show them.
+    pub states: Vec<Marginal>,
+}
+
+/// One valuation in a [Projection].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Marginal {
+    /// The valuation of the projection's variables.
+    ///
+    /// Variables missing from a state are missing here too.
+    pub state: state::State,
+    /// The total occurrences of the states showing this valuation.
+    pub occurs: usize,
+    /// The worst outcome of the states showing this valuation.
+    pub outcome: Outcome,
+}
+
+impl Projection {
+    /// Projects `report` onto the variables `vars`, calling the result
+    /// `name`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{projection::Projection, report, state, Outcome};
+    ///
+    /// let mut r = report::Report::default();
+    /// for (x, y, occurs, outcome) in [(0, 0, 3, Outcome::Pass), (0, 1, 2, Outcome::Fail), (1, 0, 1, Outcome::Pass)] {
+    ///     let mut info = state::Info::new(outcome, 0, 0);
+    ///     info.occurs = occurs;
+    ///     r.insert(report::State {
+    ///         state: [("x".into(), state::Value::I32(x)), ("y".into(), state::Value::I32(y))].into(),
+    ///         info,
+    ///         initials: vec![],
+    ///         interesting: vec![],
+    ///         checks: Default::default(),
+    ///         aux_flags: Default::default(),
+    ///         hash: String::new(),
+    ///     });
+    /// }
+    /// let p = Projection::of("xs".to_owned(), vec!["x".to_owned()], &r);
+    /// let got: Vec<_> = p.states.iter().map(|m| (m.state["x"], m.occurs, m.outcome)).collect();
+    /// assert_eq!(got, [(state::Value::I32(0), 5, Outcome::Fail), (state::Value::I32(1), 1, Outcome::Pass)]);
+    /// ```
+    #[must_use]
+    pub fn of(name: String, vars: Vec<String>, report: &report::Report) -> Self {
+        let mut index: HashMap<state::State, usize> = HashMap::new();
+        let mut states: Vec<Marginal> = vec![];
+        for s in &report.states {
+            let projected: state::State = s
+                .state
+                .iter()
+                .filter(|(k, _)| vars.iter().any(|v| v == k.as_str()))
+                .map(|(k, v)| (k.clone(), *v))
+                .collect();
+            if let Some(&i) = index.get(&projected) {
+                let m = &mut states[i];
+                m.occurs = m.occurs.saturating_add(s.info.occurs);
+                m.outcome = m.outcome.max(s.info.outcome);
+            } else {
+                index.insert(projected.clone(), states.len());
+                states.push(Marginal {
+                    state: projected,
+                    occurs: s.info.occurs,
+                    outcome: s.info.outcome,
+                });
+            }
+        }
+        Self { name, vars, states }
+    }
+}
+
+/// Projects `report` onto every group in `spec`, in name order.
+#[must_use]
+pub fn project(spec: &Spec, report: &report::Report) -> Vec<Projection> {
+    spec.iter()
+        .map(|(name, vars)| Projection::of(name.clone(), vars.clone(), report))
+        .collect()
+}
@@ -0,0 +1,147 @@
+//! Models for where a report came from.
+//!
+//! When collecting thousands of result files, their filenames and
+//! timestamps soon stop being enough to tell which test, sampled by which
+//! build under which config, each one holds.  A [Provenance] stamps all of
+//! this into the report itself: a unique ID for the run, the version of
+//! phenolphthalein that ran it, and, where the binary knows them, its global
+//! allocator, the config, the seed, and a SHA-256 hash of the test library.
+
+use serde::{Deserialize, Serialize};
+use std::{fmt::Write, io, path::Path};
+
+pub(super) mod sha256;
+
+/// Where a run, and so its report, came from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// A randomly generated (version 4) UUID, unique to the run.
+    pub run_id: String,
+    /// The version of phenolphthalein that performed the run.
+    pub version: String,
+    /// The global allocator of the build that performed the run, if known.
+    ///
+    /// Allocators can change iteration rates, so this helps compare rates
+    /// across builds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocator: Option<String>,
+    /// The run seed, if known, from which the run derived its pseudo-random
+    /// choices.
+    ///
+    /// This covers fuzzing, random permutations, skews, and random sampling
+    /// without its own seed; the config records any seeds given separately,
+    /// such as that for choosing observers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// The test library that the run loaded, if it loaded one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<Input>,
+    /// A snapshot of the run's config, as TOML, if it had one.
+    ///
+    /// This has any overrides from the command line applied, so it can be
+    /// passed back as `--config` to repeat the run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<String>,
+}
+
+/// Provenances default to a fresh run ID and the current version.
+impl Default for Provenance {
+    fn default() -> Self {
+        Self {
+            run_id: new_run_id(),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            allocator: None,
+            seed: None,
+            input: None,
+            config: None,
+        }
+    }
+}
+
+impl Provenance {
+    /// Copies this provenance for a new run, with its own run ID.
+    #[must_use]
+    pub fn for_new_run(&self) -> Self {
+        Self {
+            run_id: new_run_id(),
+            ..self.clone()
+        }
+    }
+}
+
+/// A test library, identified by its contents as well as its path.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Input {
+    /// The path from which the library was loaded.
+    pub path: String,
+    /// The SHA-256 hash of the library's contents, in lowercase hex.
+    pub sha256: String,
+}
+
+impl Input {
+    /// Identifies the library with the contents `bytes`, loaded from `path`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::provenance::Input;
+    /// let input = Input::new("test.so", b"abc");
+    /// assert_eq!(
+    ///     input.sha256,
+    ///     "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn new(path: impl Into<String>, bytes: &[u8]) -> Self {
+        Self {
+            path: path.into(),
+            sha256: hex(&sha256::digest(bytes)),
+        }
+    }
+
+    /// Identifies the library at `path` by reading and hashing it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the library can't be read.
+    pub fn hash_file(path: &Path) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Ok(Self::new(path.to_string_lossy(), &bytes))
+    }
+}
+
+/// Generates a random (version 4) UUID, in its usual hyphenated form.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::provenance::new_run_id;
+/// let id = new_run_id();
+/// assert_eq!(id.len(), 36);
+/// assert_eq!(&id[14..15], "4");
+/// assert_ne!(id, new_run_id());
+/// ```
+#[must_use]
+pub fn new_run_id() -> String {
+    let mut bytes = rand::random::<u128>().to_be_bytes();
+    // Set the version (4, random) and variant (RFC 4122) bits.
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex(&bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+pub(super) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        // Writing to a string can't fail.
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
@@ -1,40 +1,885 @@
-//! The [Report] type.
+//! The [Report] type, and its JSON [schema].
 
-use super::{outcome, state};
+pub mod hash;
+pub mod schema;
+
+use super::{
+    allocation, alternation, canary, capability, conformance, cpu, discovery, ending, example,
+    interest, invariant, manifest, ordering, outcome, platform, projection, provenance,
+    reproduction, rotation, sampling, schedule, state, taint, timing, transition,
+};
 use serde::{Deserialize, Serialize};
-use std::vec::Vec;
+use std::{
+    collections::{BTreeMap, HashMap},
+    vec::Vec,
+};
 
 /// A final report of observations coming from a test run.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Report {
+    /// The version of the [schema] of the JSON from which this report was
+    /// read, or 0 if that JSON predates versioning.
+    ///
+    /// Reports always serialise with the current [`schema::VERSION`].
+    #[serde(default, serialize_with = "schema::serialize_version")]
+    pub schema_version: u32,
+
     /// The overall outcome of checks performed on states on this run.
     ///
     /// This is an option, to disambiguate between an unknown outcome and an
     /// outcome where there were no states in the report.
     pub outcome: Option<outcome::Outcome>,
 
+    /// A human-readable description of what the test's checker checks, if
+    /// the test or its config gave one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check_description: Option<String>,
+
+    /// Why the run ended, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_by: Option<ending::Cause>,
+
+    /// How the run sampled its observations, if it didn't observe every
+    /// iteration.
+    ///
+    /// The occurrences of states in a sampled report only count the sampled
+    /// iterations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<sampling::Sampling>,
+
+    /// The smallest configuration found to reproduce the run's failure, if
+    /// the run failed and was shrunk.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reproduction: Option<reproduction::Reproduction>,
+
     /// Reports for each state observed.
     ///
     /// This is a vector to ease serialisation and deserialisation, rather than
     /// for any deep purpose.
     pub states: Vec<State>,
+
+    /// The hash of the set of the states in this report (see [hash]).
+    ///
+    /// Reports with the same set of states have the same hash, whatever the
+    /// states' counts and order.  This is empty if the report's states
+    /// haven't been hashed.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub states_hash: String,
+
+    /// The outcome of each observed iteration, tallied, if the test had no
+    /// variables.
+    ///
+    /// Such tests show the same state on every iteration, so their runners
+    /// check every iteration, and the state's outcome is the worst of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcomes: Option<outcome::Tally>,
+
+    /// The thread permutation schedule that produced this report.
+    ///
+    /// This can be fed back into the runner to replay the same permutations.
+    #[serde(default)]
+    pub schedule: schedule::Schedule,
+
+    /// The curve of distinct states against the iterations at which they
+    /// were discovered.
+    #[serde(default)]
+    pub discovery: discovery::Curve,
+
+    /// Breakdown of state occurrences by the thread permutation under
+    /// which they occurred.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permutations: Vec<Permutation>,
+
+    /// Breakdown of state occurrences by the synchroniser under which they
+    /// occurred, if the run alternated synchronisers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alternation: Option<alternation::Alternation>,
+
+    /// Breakdown of state occurrences by the rotation in which they
+    /// occurred, if the run segmented its histogram by rotation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rotations: Vec<rotation::Section>,
+
+    /// The context of the first iteration to show each state, ordered by
+    /// state, if the run captured examples.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<example::Example>,
+
+    /// How often each state followed each other state in successive
+    /// observed iterations, ordered by state, if the run recorded
+    /// transitions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<transition::Transition>,
+
+    /// Torn values observed on canaried variables, ordered by variable and
+    /// then by first iteration.
+    ///
+    /// This is empty unless the test ran in canary mode.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub torn: Vec<canary::TornRead>,
+
+    /// Values observed outside the bounds that the test declared for their
+    /// variables, ordered by variable and then by first iteration.
+    ///
+    /// Every state observed alongside such a value fails.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<invariant::Violation>,
+
+    /// When each interesting state was first seen, ordered by name.
+    ///
+    /// This is empty unless the run was looking for interesting states.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interesting: Vec<interest::Sighting>,
+
+    /// The variables left out of the observed states, in name order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub masked: Vec<String>,
+
+    /// The test's variables in the order it declared them, for outputs that
+    /// show states in that order (see [`state::KeyOrder::Declared`]).
+    ///
+    /// This is empty if the test gave no order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub var_order: Vec<String>,
+
+    /// The groups of symmetric threads whose states were folded together,
+    /// each in thread order.
+    ///
+    /// States that differ only by swapping the registers of threads in one
+    /// group appear once, under a canonical representative.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub folded: Vec<Vec<usize>>,
+
+    /// The memory orderings with which the test accessed each of its atomic
+    /// variables, keyed by variable.
+    ///
+    /// This is empty unless the test's environment records orderings, as
+    /// the native Rust one does.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub orderings: BTreeMap<String, ordering::Set>,
+
+    /// The machine on which the test ran, if recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<platform::Platform>,
+
+    /// Where the run came from, if recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<provenance::Provenance>,
+
+    /// The CPUs to which the test threads were restricted, if they were.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<cpu::Set>,
+
+    /// The declared thread whose role each test thread played, indexed by
+    /// thread ID, if the run overrode the test's thread count.
+    ///
+    /// Each of the test's own threads plays itself, and each extra thread
+    /// plays the test's scalable role.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<usize>,
+
+    /// The optional capabilities that the test had.
+    #[serde(default, skip_serializing_if = "capability::Set::is_empty")]
+    pub capabilities: capability::Set,
+
+    /// How the observed states compare with a model's predictions, if the
+    /// run had any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conformance: Option<conformance::Conformance>,
+
+    /// The outcome of each end-of-run check on the whole report, by name.
+    ///
+    /// These judge the distribution of states rather than any one state, and
+    /// feed into the overall outcome.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aggregate: BTreeMap<String, outcome::Outcome>,
+
+    /// The marginal histogram of each configured group of variables, ordered
+    /// by name (see [`projection`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub projections: Vec<projection::Projection>,
+
+    /// The first sign of each kind of taint that the run saw, if it was
+    /// guarding against tests breaking the runner's assumptions.
+    ///
+    /// States in a tainted report may not mean what they seem to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tainted: Vec<taint::Taint>,
+
+    /// Histograms of the time each thread spent in the test body, in thread
+    /// order.
+    ///
+    /// This is empty unless the run timed the test body.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub timing: Vec<timing::Histogram>,
+
+    /// The allocations made while handling observed iterations, if the run
+    /// audited them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocations: Option<allocation::Audit>,
 }
 
 impl Report {
+    /// Gets the total number of occurrences of every state, which is the
+    /// number of iterations that the report covers.
+    ///
+    /// Under sampling, this counts only sampled iterations; see
+    /// [`Report::sampling_or_exact`] for how many ran.
+    #[must_use]
+    pub fn iterations(&self) -> usize {
+        self.states.iter().map(|s| s.info.occurs).sum()
+    }
+
+    /// Ranks the states by how often they occurred, most often first, with
+    /// ties in report order.
+    ///
+    /// Each state comes with the running total of occurrences up to and
+    /// including it, for cumulative shares of [`Report::iterations`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{report::{Report, State}, state::Info, Outcome};
+    ///
+    /// let mut report = Report::default();
+    /// for occurs in [1, 5, 3] {
+    ///     let mut info = Info::new(Outcome::Pass, 0, 0);
+    ///     info.occurs = occurs;
+    ///     report.insert(State { state: Default::default(), info, initials: vec![], interesting: vec![], checks: Default::default(), aux_flags: Default::default(), hash: String::new() });
+    /// }
+    /// let ranked: Vec<_> = report.ranked().map(|(s, total)| (s.info.occurs, total)).collect();
+    /// assert_eq!(ranked, [(5, 5), (3, 8), (1, 9)]);
+    /// assert_eq!(report.iterations(), 9);
+    /// ```
+    pub fn ranked(&self) -> impl Iterator<Item = (&State, usize)> + '_ {
+        let mut states: Vec<_> = self.states.iter().collect();
+        states.sort_by_key(|s| std::cmp::Reverse(s.info.occurs));
+        states.into_iter().scan(0usize, |total, s| {
+            *total = total.saturating_add(s.info.occurs);
+            Some((s, *total))
+        })
+    }
+
     /// Adds a state to the report, updating aggregates accordingly.
     pub fn insert(&mut self, state: State) {
         self.outcome = self.outcome.max(Some(state.info.outcome));
         self.states.push(state);
     }
+
+    /// Records that the end-of-run check `name` had outcome `outcome` on
+    /// this report, conjoining it into the overall outcome.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{Outcome, Report};
+    ///
+    /// let mut report = Report { outcome: Some(Outcome::Unknown), ..Report::default() };
+    /// report.record_aggregate("seen".to_owned(), Outcome::Fail);
+    /// assert_eq!(report.outcome, Some(Outcome::Fail));
+    /// assert_eq!(report.aggregate["seen"], Outcome::Fail);
+    /// ```
+    pub fn record_aggregate(&mut self, name: String, outcome: outcome::Outcome) {
+        self.outcome = Some(self.outcome.map_or(outcome, |o| o.and(outcome)));
+        self.aggregate.insert(name, outcome);
+    }
+
+    /// Hashes each of this report's states, and the set of them, replacing
+    /// any hashes they already have.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{report, state, Outcome};
+    ///
+    /// let mut r = report::Report::default();
+    /// r.insert(report::State {
+    ///     state: [("x".into(), state::Value::I32(1))].into(),
+    ///     hash: String::new(),
+    ///     info: state::Info::new(Outcome::Pass, 0, 0),
+    ///     initials: vec![],
+    ///     interesting: vec![],
+    ///     checks: Default::default(),
+    ///     aux_flags: Default::default(),
+    /// });
+    /// r.hash_states();
+    /// assert_eq!(r.states[0].hash, report::hash::of_state(&r.states[0].state));
+    /// assert_eq!(r.states_hash, report::hash::of_set([r.states[0].hash.as_str()]));
+    /// ```
+    pub fn hash_states(&mut self) {
+        for s in &mut self.states {
+            s.hash = hash::of_state(&s.state);
+        }
+        self.states_hash = hash::of_set(self.states.iter().map(|s| s.hash.as_str()));
+    }
+
+    /// Projects this report onto each group of variables in `spec`,
+    /// replacing any projections it already has.
+    pub fn project(&mut self, spec: &projection::Spec) {
+        self.projections = projection::project(spec, self);
+    }
+
+    /// Gets how this report was sampled, treating an unsampled report as
+    /// having sampled all of its iterations under no rule.
+    #[must_use]
+    pub fn sampling_or_exact(&self) -> sampling::Sampling {
+        self.sampling.unwrap_or_else(|| {
+            let iterations = self.iterations();
+            sampling::Sampling {
+                rule: None,
+                iterations,
+                sampled: iterations,
+            }
+        })
+    }
+
+    /// Merges `other`, a report of another run of the same test, into this
+    /// report.
+    ///
+    /// Occurrences of the same state are summed, keeping the earliest first
+    /// iteration, and the outcome is recomputed.  The other report's
+    /// schedule goes after this one's, with its rotation numbers shifted to
+    /// match; as iteration numbers restart on every run, the discovery curve
+    /// is rebuilt from each state's earliest first iteration.  The check
+    /// description, platform, CPU restriction, thread roles, and reproduction
+    /// survive only if both reports agree on them, the provenance never survives, and
+    /// only capabilities that both reports have survive.  The variable order
+    /// is this report's, unless it has none.  If either report was sampled,
+    /// so is the merged report, counting the other's iterations as sampled
+    /// if it wasn't.  Any model
+    /// conformance is reclassified over
+    /// the merged states, the merged report keeps the earliest taint of each
+    /// kind, and each variable's orderings are those of either report.
+    /// Breakdowns by synchroniser survive only if both runs alternated the
+    /// same synchronisers at the same period, each state keeps its example
+    /// from this report if it has one, per-rotation sections follow on from
+    /// this report's, transitions sum their occurrences,
+    /// outcome tallies and allocation audits combine, and the merged states
+    /// are hashed afresh.  End-of-run
+    /// checks judged each run on its own, and projections covered each run's
+    /// own states, so neither survives merging; see
+    /// [`Report::record_aggregate`] and [`Report::project`] for redoing them
+    /// on the merged report.
+    pub fn merge(&mut self, other: Report) {
+        // This must happen before merging the states changes our iterations.
+        if self.sampling.is_some() || other.sampling.is_some() {
+            let mut mine = self.sampling_or_exact();
+            mine.merge(&other.sampling_or_exact());
+            self.sampling = Some(mine);
+        }
+        let offset = self.schedule.len();
+        let mut index: HashMap<state::State, usize> = self
+            .states
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.state.clone(), i))
+            .collect();
+
+        // So that merged states share names, as observed states do.
+        let mut names: manifest::Names = self.states.iter().flat_map(|s| s.state.keys()).collect();
+        let mut remap = Vec::with_capacity(other.states.len());
+        for mut s in other.states {
+            s.state = s
+                .state
+                .into_iter()
+                .map(|(var, value)| (names.intern_name(var), value))
+                .collect();
+            s.info.rotation = s.info.rotation.saturating_add(offset);
+            s.info.last_rotation = s.info.last_rotation.saturating_add(offset);
+            if let Some(&i) = index.get(&s.state) {
+                self.states[i].merge(s);
+                remap.push(i);
+            } else {
+                index.insert(s.state.clone(), self.states.len());
+                remap.push(self.states.len());
+                self.states.push(s);
+            }
+        }
+
+        self.outcome = self.states.iter().map(|s| s.info.outcome).max();
+        self.aggregate.clear();
+        self.projections.clear();
+        self.schedule.0.extend(other.schedule.0);
+        self.merge_permutations(other.permutations, &remap);
+        self.merge_alternation(other.alternation, &remap);
+        example::merge(&mut self.examples, other.examples, &remap, offset);
+        rotation::merge(&mut self.rotations, other.rotations, &remap, offset);
+        transition::merge(&mut self.transitions, other.transitions, &remap);
+        self.merge_torn(other.torn, offset);
+        self.merge_violations(other.violations, offset);
+        self.merge_tainted(other.tainted, offset);
+        self.merge_interesting(&other.interesting);
+        union_sorted(&mut self.masked, other.masked);
+        union_sorted(&mut self.folded, other.folded);
+        for (var, orders) in other.orderings {
+            self.orderings.entry(var).or_default().extend(orders);
+        }
+        self.merge_counts(
+            &other.timing,
+            other.outcomes.as_ref(),
+            other.allocations.as_ref(),
+        );
+        if self.ended_by != other.ended_by {
+            // A merged report can only say why its runs ended if they agree.
+            self.ended_by = None;
+        }
+        if self.reproduction != other.reproduction {
+            self.reproduction = None;
+        }
+        if self.check_description != other.check_description {
+            self.check_description = None;
+        }
+        if self.platform != other.platform {
+            // A merged report can only vouch for one machine.
+            self.platform = None;
+        }
+        if self.cpus != other.cpus {
+            self.cpus = None;
+        }
+        if self.roles != other.roles {
+            self.roles.clear();
+        }
+        if self.var_order.is_empty() {
+            self.var_order = other.var_order;
+        }
+        // Each run has its own ID, so no one run's provenance covers both.
+        self.provenance = None;
+        // Likewise, it can only vouch for capabilities that every run had.
+        self.capabilities = self
+            .capabilities
+            .intersection(&other.capabilities)
+            .copied()
+            .collect();
+        // The merged states need classifying afresh.
+        if let Some(c) = self.conformance.take().or(other.conformance) {
+            self.conformance = Some(conformance::Conformance::classify(c.expected, self));
+        }
+
+        let mut firsts: Vec<_> = self.states.iter().map(|s| s.info.iteration).collect();
+        firsts.sort_unstable();
+        self.discovery = discovery::Curve::default();
+        for iteration in firsts {
+            self.discovery.discover(iteration);
+        }
+        self.hash_states();
+    }
+
+    /// Merges permutation breakdowns, using `remap` to map the state indices
+    /// in `others` to those in this report.
+    fn merge_permutations(&mut self, others: Vec<Permutation>, remap: &[usize]) {
+        for mut p in others {
+            for o in &mut p.states {
+                o.state = remap.get(o.state).copied().unwrap_or(o.state);
+            }
+            match self
+                .permutations
+                .iter_mut()
+                .find(|q| q.assignment == p.assignment)
+            {
+                Some(q) => q.merge(p),
+                None => self.permutations.push(p),
+            }
+        }
+        self.permutations
+            .sort_by(|p, q| p.assignment.cmp(&q.assignment));
+    }
+
+    /// Merges breakdowns by synchroniser, using `remap` to map the state
+    /// indices in `other` to those in this report.
+    fn merge_alternation(&mut self, other: Option<alternation::Alternation>, remap: &[usize]) {
+        self.alternation = self
+            .alternation
+            .take()
+            .zip(other)
+            .and_then(|(mut a, b)| a.merge(b, remap).then_some(a));
+    }
+
+    /// Merges torn reads, shifting their rotations by `offset`.
+    fn merge_torn(&mut self, others: Vec<canary::TornRead>, offset: usize) {
+        for mut t in others {
+            t.rotation = t.rotation.saturating_add(offset);
+            match self
+                .torn
+                .iter_mut()
+                .find(|u| u.var == t.var && u.value == t.value)
+            {
+                Some(u) => u.merge(&t),
+                None => self.torn.push(t),
+            }
+        }
+        self.sort_torn();
+    }
+
+    /// Merges invariant violations, shifting their rotations by `offset`.
+    fn merge_violations(&mut self, others: Vec<invariant::Violation>, offset: usize) {
+        for mut v in others {
+            v.rotation = v.rotation.saturating_add(offset);
+            match self
+                .violations
+                .iter_mut()
+                .find(|u| u.var == v.var && u.value == v.value)
+            {
+                Some(u) => u.merge(&v),
+                None => self.violations.push(v),
+            }
+        }
+        self.sort_violations();
+    }
+
+    /// Merges the counts that runs keep besides those of their states: body
+    /// timing histograms (thread by thread), outcome tallies, and allocation
+    /// audits.
+    fn merge_counts(
+        &mut self,
+        timing: &[timing::Histogram],
+        outcomes: Option<&outcome::Tally>,
+        allocations: Option<&allocation::Audit>,
+    ) {
+        if self.timing.len() < timing.len() {
+            self.timing.resize_with(timing.len(), Default::default);
+        }
+        for (mine, theirs) in self.timing.iter_mut().zip(timing) {
+            mine.merge(theirs);
+        }
+        if let Some(theirs) = outcomes {
+            self.outcomes.get_or_insert_default().merge(theirs);
+        }
+        if let Some(theirs) = allocations {
+            self.allocations.get_or_insert_default().merge(theirs);
+        }
+    }
+
+    /// Merges taints, shifting their rotations by `offset`.
+    fn merge_tainted(&mut self, others: Vec<taint::Taint>, offset: usize) {
+        for mut t in others {
+            if !self.tainted.iter().any(|u| u.cause.same_kind(&t.cause)) {
+                t.rotation = t.rotation.saturating_add(offset);
+                self.tainted.push(t);
+            }
+        }
+    }
+
+    /// Merges sightings of interesting states.
+    fn merge_interesting(&mut self, others: &[interest::Sighting]) {
+        for s in others {
+            match self.interesting.iter_mut().find(|t| t.name == s.name) {
+                Some(t) => t.merge(s),
+                None => self.interesting.push(s.clone()),
+            }
+        }
+        self.interesting.sort_by(|s, t| s.name.cmp(&t.name));
+    }
+
+    /// Sorts invariant violations by variable and then by first iteration.
+    pub fn sort_violations(&mut self) {
+        self.violations
+            .sort_by(|v, u| v.var.cmp(&u.var).then(v.iteration.cmp(&u.iteration)));
+    }
+
+    /// Sorts torn reads by variable and then by first iteration.
+    pub fn sort_torn(&mut self) {
+        self.torn
+            .sort_by(|t, u| t.var.cmp(&u.var).then(t.iteration.cmp(&u.iteration)));
+    }
+
+    /// Fills in the per-permutation breakdown of this report.
+    ///
+    /// `counts` holds, for each state in `states` (in the same order), the
+    /// number of times that state occurred in each rotation.  We use the
+    /// report's schedule to find the permutation used in each rotation;
+    /// rotations with no recorded permutation are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{
+    ///     report::{Report, State},
+    ///     schedule::Schedule,
+    ///     state, Outcome,
+    /// };
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut report = Report::default();
+    /// report.schedule = Schedule(vec![vec![0, 1], vec![1, 0], vec![0, 1]]);
+    /// report.insert(State {
+    ///     state: state::State::new(),
+    ///     info: state::Info::new(Outcome::Pass, 0, 0),
+    ///     initials: vec![],
+    ///     interesting: vec![],
+    ///     checks: Default::default(),
+    ///     aux_flags: Default::default(),
+    ///     hash: String::new(),
+    /// });
+    /// report.break_down(&[BTreeMap::from([(0, 2), (1, 5), (2, 3)])]);
+    ///
+    /// assert_eq!(report.permutations.len(), 2);
+    /// assert_eq!(report.permutations[0].assignment, [0, 1]);
+    /// assert_eq!(report.permutations[0].rotations, 2);
+    /// assert_eq!(report.permutations[0].states[0].occurs, 5);
+    /// assert_eq!(report.permutations[1].states[0].occurs, 5);
+    /// ```
+    pub fn break_down(&mut self, counts: &[BTreeMap<usize, usize>]) {
+        let mut groups: BTreeMap<&[usize], BTreeMap<usize, usize>> = BTreeMap::new();
+        for (state, counts) in counts.iter().enumerate() {
+            for (&rotation, &occurs) in counts {
+                if let Some(assignment) = self.schedule.get(rotation) {
+                    let n = groups
+                        .entry(assignment)
+                        .or_default()
+                        .entry(state)
+                        .or_default();
+                    *n = n.saturating_add(occurs);
+                }
+            }
+        }
+
+        self.permutations = groups
+            .into_iter()
+            .map(|(assignment, states)| Permutation {
+                assignment: assignment.to_vec(),
+                rotations: self.schedule.0.iter().filter(|a| *a == assignment).count(),
+                outcome: states
+                    .keys()
+                    .filter_map(|&i| self.states.get(i))
+                    .map(|s| s.info.outcome)
+                    .max(),
+                states: states
+                    .into_iter()
+                    .map(|(state, occurs)| Occurrence { state, occurs })
+                    .collect(),
+            })
+            .collect();
+    }
+}
+
+/// The occurrences of states under a single thread permutation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Permutation {
+    /// The thread assignment making up this permutation.
+    pub assignment: schedule::Assignment,
+
+    /// The number of rotations that used this permutation.
+    pub rotations: usize,
+
+    /// The overall outcome of the states seen under this permutation.
+    pub outcome: Option<outcome::Outcome>,
+
+    /// The states seen under this permutation, and how often.
+    pub states: Vec<Occurrence>,
+}
+
+impl Permutation {
+    /// Merges another breakdown for the same permutation into this one.
+    fn merge(&mut self, other: Permutation) {
+        self.rotations = self.rotations.saturating_add(other.rotations);
+        self.outcome = self.outcome.max(other.outcome);
+        for o in other.states {
+            match self.states.iter_mut().find(|x| x.state == o.state) {
+                Some(x) => x.occurs = x.occurs.saturating_add(o.occurs),
+                None => self.states.push(o),
+            }
+        }
+        self.states.sort_by_key(|o| o.state);
+    }
+}
+
+/// A count of occurrences of one state.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Occurrence {
+    /// The index of the state in the report's `states`.
+    pub state: usize,
+
+    /// The number of times the state occurred.
+    pub occurs: usize,
 }
 
 /// A report for a single state, containing both the valuation and metadata.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct State {
     /// The valuation for the state.
     pub state: state::State,
 
+    /// The hash of the valuation (see [hash]), or empty if it hasn't been
+    /// hashed.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub hash: String,
+
     /// The metadata for the stage.
     #[serde(flatten)]
     pub info: state::Info,
+
+    /// The distinct fuzzed initial valuations that led to this state.
+    ///
+    /// Each valuation covers only the fuzzed variables; this is empty if
+    /// fuzzing was disabled.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub initials: Vec<state::State>,
+
+    /// The names of the interesting states this state matches.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interesting: Vec<String>,
+
+    /// The outcome of each checker, by name, if the state's outcome combines
+    /// several checkers.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checks: BTreeMap<String, outcome::Outcome>,
+
+    /// The number of observed iterations showing this state that raised each
+    /// of the test's auxiliary flags, by name.
+    ///
+    /// Flags that no such iteration raised don't appear.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aux_flags: BTreeMap<String, usize>,
+}
+
+impl State {
+    /// Merges another report of the same state into this one.
+    fn merge(&mut self, other: State) {
+        self.info = self.info.merge(&other.info);
+        for initial in other.initials {
+            if !self.initials.contains(&initial) {
+                self.initials.push(initial);
+            }
+        }
+        for name in other.interesting {
+            if !self.interesting.contains(&name) {
+                self.interesting.push(name);
+            }
+        }
+        self.interesting.sort();
+        for (name, outcome) in other.checks {
+            let o = self.checks.entry(name).or_insert(outcome);
+            *o = (*o).max(outcome);
+        }
+        for (name, count) in other.aux_flags {
+            let c = self.aux_flags.entry(name).or_default();
+            *c = c.saturating_add(count);
+        }
+    }
+}
+
+/// Adds the items of `others` missing from `items`, then sorts `items`.
+fn union_sorted<T: Ord>(items: &mut Vec<T>, others: Vec<T>) {
+    for x in others {
+        if !items.contains(&x) {
+            items.push(x);
+        }
+    }
+    items.sort();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Outcome;
+
+    fn report(xs: &[(i32, Outcome, usize)], rotations: usize) -> Report {
+        let mut r = Report::default();
+        for &(x, outcome, iteration) in xs {
+            let state = state::State::from([("x".into(), state::Value::I32(x))]);
+            r.insert(State {
+                state,
+                info: state::Info::new(outcome, iteration, 0),
+                initials: vec![],
+                interesting: vec![],
+                checks: BTreeMap::new(),
+                aux_flags: BTreeMap::new(),
+                hash: String::new(),
+            });
+        }
+        r.schedule = schedule::Schedule(vec![vec![0, 1]; rotations]);
+        let counts: Vec<_> = xs.iter().map(|_| BTreeMap::from([(0, 1)])).collect();
+        r.break_down(&counts);
+        r
+    }
+
+    /// Tests merging reports with overlapping states.
+    #[test]
+    fn test_merge() {
+        let torn = |iteration, rotation| {
+            canary::TornRead::new("r".into(), state::Value::I32(1), iteration, rotation)
+        };
+        let mut a = report(&[(0, Outcome::Pass, 4), (1, Outcome::Pass, 0)], 2);
+        a.torn.push(torn(3, 1));
+        let mut b = report(&[(1, Outcome::Pass, 2), (2, Outcome::Fail, 1)], 1);
+        b.torn.push(torn(1, 0));
+        a.platform = Some(platform::Platform::collect());
+        a.cpus = "0-1".parse().ok();
+        a.roles = vec![0, 1, 1];
+        b.var_order = vec!["x".to_owned()];
+        a.provenance = Some(provenance::Provenance::default());
+        a.check_description = Some("no weak states".to_owned());
+        a.reproduction = Some(reproduction::Reproduction {
+            iterations: std::num::NonZeroUsize::MIN,
+            period: None,
+            seed: 0,
+            tries: 1,
+            reruns: 1,
+            failures: 1,
+        });
+        a.capabilities = capability::Capability::ALL.into();
+        b.capabilities = std::iter::once(capability::Capability::Check).collect();
+        a.orderings
+            .insert("x".to_owned(), [ordering::Ordering::Acquire].into());
+        b.orderings
+            .insert("x".to_owned(), [ordering::Ordering::Release].into());
+        a.merge(b);
+        assert_eq!(a.platform, None);
+        assert_eq!(a.cpus, None);
+        assert!(a.roles.is_empty());
+        assert_eq!(a.var_order, ["x"]);
+        assert_eq!(a.provenance, None);
+        assert_eq!(a.check_description, None);
+        assert_eq!(a.reproduction, None);
+        assert_eq!(a.capabilities.len(), 1);
+        assert_eq!(
+            a.orderings["x"],
+            [ordering::Ordering::Acquire, ordering::Ordering::Release].into()
+        );
+
+        assert_eq!(a.states.len(), 3);
+        assert_eq!(a.outcome, Some(Outcome::Fail));
+        let one = &a.states[1].info;
+        assert_eq!((one.occurs, one.iteration), (2, 0));
+        assert_eq!((a.states[2].info.rotation, a.schedule.len()), (2, 3));
+
+        let firsts: Vec<_> = a.discovery.0.iter().map(|p| p.iteration).collect();
+        assert_eq!(firsts, [0, 1, 4]);
+
+        assert_eq!(a.permutations.len(), 1);
+        let p = &a.permutations[0];
+        assert_eq!(p.rotations, 3);
+        let occurs: Vec<_> = p.states.iter().map(|o| (o.state, o.occurs)).collect();
+        assert_eq!(occurs, [(0, 1), (1, 2), (2, 1)]);
+
+        assert_eq!(a.torn.len(), 1);
+        let t = &a.torn[0];
+        assert_eq!((t.occurs, t.iteration, t.rotation), (2, 1, 2));
+
+        // The state new to `a` takes its names from `a`'s states.
+        let name = |i: usize| a.states[i].state.keys().next().unwrap().clone();
+        assert!(name(2).shares_with(&name(0)));
+    }
+
+    /// Tests that merging a sampled report with an unsampled one gives a
+    /// sampled report counting the unsampled one's iterations in full.
+    #[test]
+    fn test_merge_sampling() {
+        let mut a = report(&[(0, Outcome::Pass, 0)], 1);
+        a.sampling = Some(sampling::Sampling {
+            rule: Some(sampling::Rule::Every {
+                n: std::num::NonZeroUsize::new(10).unwrap(),
+            }),
+            iterations: 10,
+            sampled: 1,
+        });
+        let b = report(&[(0, Outcome::Pass, 0), (1, Outcome::Pass, 1)], 1);
+        a.merge(b);
+        let s = a.sampling.unwrap();
+        assert_eq!((s.rule, s.iterations, s.sampled), (None, 12, 3));
+
+        let mut c = report(&[(0, Outcome::Pass, 0)], 1);
+        c.merge(report(&[(1, Outcome::Pass, 0)], 1));
+        assert_eq!(c.sampling, None);
+    }
 }
@@ -0,0 +1,93 @@
+//! Stable hashes of states, and of sets of states.
+//!
+//! Comparing the states of two reports by valuation means parsing both in
+//! full; hashes let external tools tell whether runs saw the same states, or
+//! deduplicate states across runs in a database, by comparing strings.  The
+//! hashes are SHA-256 digests, in lowercase hex, of a canonical encoding that
+//! depends only on the valuation, so they hold across runs, machines, and
+//! versions of phenolphthalein.
+//!
+//! A state encodes as each of its variables, in name order, as the length of
+//! the variable's name (8 bytes, little-endian), the name in UTF-8, a type
+//! tag byte (0 for `i32`, 1 for `bool`, 2 for `f64`), and the value's
+//! little-endian bytes (a byte of 0 or 1 for Booleans, and the IEEE 754 bits
+//! for floats).  A set of states hashes as the concatenation of its states'
+//! hashes, in sorted order, so that it doesn't depend on the order in which
+//! a run found them.
+
+use super::super::{provenance, state};
+
+/// Hashes the valuation `state`.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::{report::hash, state::{State, Value}};
+///
+/// let a = State::from([("x".into(), Value::I32(1)), ("y".into(), Value::Bool(true))]);
+/// let b = State::from([("y".into(), Value::Bool(true)), ("x".into(), Value::I32(1))]);
+/// let c = State::from([("x".into(), Value::I32(1)), ("y".into(), Value::Bool(false))]);
+/// assert_eq!(hash::of_state(&a), hash::of_state(&b));
+/// assert_ne!(hash::of_state(&a), hash::of_state(&c));
+/// assert_eq!(hash::of_state(&a).len(), 64);
+/// ```
+#[must_use]
+pub fn of_state(state: &state::State) -> String {
+    let mut bytes = vec![];
+    for (name, value) in state {
+        let name = name.as_str().as_bytes();
+        bytes.extend_from_slice(&(name.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(name);
+        match *value {
+            state::Value::I32(i) => {
+                bytes.push(0);
+                bytes.extend_from_slice(&i.to_le_bytes());
+            }
+            state::Value::Bool(b) => {
+                bytes.push(1);
+                bytes.push(u8::from(b));
+            }
+            state::Value::F64(f) => {
+                bytes.push(2);
+                bytes.extend_from_slice(&f.to_bits().to_le_bytes());
+            }
+        }
+    }
+    provenance::hex(&provenance::sha256::digest(&bytes))
+}
+
+/// Hashes the set of states whose hashes are `hashes`, in any order.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::report::hash;
+///
+/// assert_eq!(hash::of_set(["a", "b"]), hash::of_set(["b", "a"]));
+/// assert_ne!(hash::of_set(["a", "b"]), hash::of_set(["a"]));
+/// ```
+#[must_use]
+pub fn of_set<'a>(hashes: impl IntoIterator<Item = &'a str>) -> String {
+    let mut hashes: Vec<_> = hashes.into_iter().collect();
+    hashes.sort_unstable();
+    provenance::hex(&provenance::sha256::digest(hashes.concat().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that state hashes follow the documented encoding, so that they
+    /// stay stable across versions.
+    #[test]
+    fn test_of_state_is_stable() {
+        let s = state::State::from([
+            ("x".into(), state::Value::I32(1)),
+            ("y".into(), state::Value::Bool(true)),
+        ]);
+        assert_eq!(
+            of_state(&s),
+            "7a88f8b19fb49880e2481f7106623610acbacbece47f1a1c5c27865ae628af73"
+        );
+    }
+}
@@ -0,0 +1,336 @@
+//! The JSON schema of reports, and its version.
+//!
+//! Reports serialise, through `serde`, as JSON objects whose top level is
+//! [`super::Report`]'s fields, in declaration order:
+//!
+//! - `schema_version` (integer): the version of this schema that the report
+//!   follows; reports from before versioning lack it, and read as version 0;
+//! - `outcome` (string or null), `check_description` (string), `ended_by`
+//!   (object tagged by `cause`), `sampling` (object, whose `rule` is tagged
+//!   by `kind`), and `reproduction` (object);
+//! - `states` (array): each state's valuation as `state`, an object mapping
+//!   each variable to a `{"type", "value"}` pair, and its stable `hash` (see
+//!   [`super::hash`]), alongside the state's
+//!   iteration, rotation, and occurrence counts and its `outcome`, and, if
+//!   its iterations raised any auxiliary flags, their counts as `aux_flags`;
+//! - `states_hash` (string): the stable hash of the set of states;
+//! - `outcomes` (object): how many iterations passed, failed, and had
+//!   unknown outcomes, if the test had no variables;
+//! - `schedule`, `discovery`, and `permutations` (arrays): how threads were
+//!   assigned over rotations, and how states were discovered under them;
+//! - `alternation` (object): how often each state occurred under each of
+//!   the synchronisers that the run alternated, if it did;
+//! - `rotations` (array): how often each state occurred in each rotation,
+//!   if the run segmented its histogram by rotation;
+//! - `examples` (array): the context of the first iteration to show each
+//!   state, if the run captured examples;
+//! - `transitions` (array): how often each state followed each other state
+//!   in successive observed iterations, if the run recorded transitions;
+//! - `torn`, `violations`, `interesting`, `masked`, `var_order`, `folded`,
+//!   `orderings`, `platform`, `provenance`, `cpus`, `capabilities`,
+//!   `conformance`, `aggregate`, `tainted`, `timing`, and `allocations`: run
+//!   metadata, each present only if the run recorded any;
+//! - `projections` (array): the marginal histogram of each configured group
+//!   of variables, if the run had any.
+//!
+//! [`shape`] flattens a report into one line per path and JSON type, which
+//! spells out the rest; the tests hold the shape of a report with every
+//! field filled in, and fail if it changes while [`VERSION`] doesn't.
+//!
+//! Within a version, the shape is a contract: fields don't move, change
+//! type, or disappear, and the same fields are omitted when empty.  Any such
+//! change bumps the version.  Readers should ignore fields they don't know,
+//! as phenolphthalein itself does, but can refuse versions newer than they
+//! understand.
+
+use serde::Serializer;
+use std::collections::BTreeSet;
+
+/// The version of the report schema that this build writes.
+pub const VERSION: u32 = 1;
+
+/// Serialises a report's schema version.
+///
+/// Reports in memory always have the current shape, however old the JSON
+/// they were read from, so this always writes [`VERSION`].
+///
+/// # Errors
+///
+/// Propagates errors from `s`.
+#[allow(clippy::trivially_copy_pass_by_ref)] // serde passes fields by reference.
+pub(super) fn serialize_version<S: Serializer>(_: &u32, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_u32(VERSION)
+}
+
+/// Gets the shape of the JSON value `value`, as a sorted list of lines of
+/// the form `path: type`.
+///
+/// Paths join object keys with `.`, and mark array elements with `[]`;
+/// elements of the same array share a path, and so merge their shapes.
+/// Types are `null`, `boolean`, `integer`, `number`, and `string`, or
+/// `array` and `object` for empty ones.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::report::schema::shape;
+/// let v = serde_json::json!({"a": [{"b": 1}, {"b": 2.5, "c": null}], "d": {}});
+/// assert_eq!(
+///     shape(&v),
+///     ["a[].b: integer", "a[].b: number", "a[].c: null", "d: object"]
+/// );
+/// ```
+#[must_use]
+pub fn shape(value: &serde_json::Value) -> Vec<String> {
+    let mut lines = BTreeSet::new();
+    walk(value, "", &mut lines);
+    lines.into_iter().collect()
+}
+
+fn walk(value: &serde_json::Value, path: &str, lines: &mut BTreeSet<String>) {
+    use serde_json::Value;
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, v) in map {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                walk(v, &path, lines);
+            }
+        }
+        Value::Array(xs) if !xs.is_empty() => {
+            let path = format!("{path}[]");
+            for x in xs {
+                walk(x, &path, lines);
+            }
+        }
+        v => {
+            let path = if path.is_empty() { "." } else { path };
+            lines.insert(format!("{path}: {}", type_name(v)));
+        }
+    }
+}
+
+fn type_name(value: &serde_json::Value) -> &'static str {
+    use serde_json::Value;
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::*, *};
+    use crate::model::{interest::Matcher, skew, state::Value};
+
+    /// A report with every field, and every optional field of its parts,
+    /// filled in.
+    ///
+    /// This lists every field, so that new fields can't slip past the shape
+    /// test by being left empty.  Maps keyed by variable, name, or thread use
+    /// `<var>`, `<name>`, and `<thread>` as keys.
+    #[allow(clippy::too_many_lines)] // One line per field, by design.
+    fn full() -> Report {
+        let var = || manifest::VarName::from("<var>");
+        let state: state::State = [(var(), Value::I32(1))].into();
+        let matcher = Matcher([("<var>".to_owned(), interest::Literal::Int(1))].into());
+        let mut info = state::Info::new(outcome::Outcome::Pass, 0, 0);
+        info.occurs = 2;
+        let mut discovery = discovery::Curve::default();
+        discovery.discover(0);
+        Report {
+            schema_version: VERSION,
+            outcome: Some(outcome::Outcome::Pass),
+            check_description: Some("<description>".to_owned()),
+            ended_by: Some(ending::Cause::Iterations { iterations: 10 }),
+            sampling: Some(sampling::Sampling {
+                rule: Some(sampling::Rule::Random {
+                    probability: 0.5,
+                    seed: 1,
+                }),
+                iterations: 10,
+                sampled: 5,
+            }),
+            reproduction: Some(reproduction::Reproduction {
+                iterations: std::num::NonZeroUsize::MIN,
+                period: Some(std::num::NonZeroUsize::MIN),
+                seed: 1,
+                tries: 1,
+                reruns: 1,
+                failures: 1,
+            }),
+            states: vec![State {
+                state: state.clone(),
+                info,
+                initials: vec![state],
+                interesting: vec!["<name>".to_owned()],
+                checks: [("<name>".to_owned(), outcome::Outcome::Pass)].into(),
+                aux_flags: [("<name>".to_owned(), 1)].into(),
+                hash: "<hash>".to_owned(),
+            }],
+            states_hash: "<hash>".to_owned(),
+            outcomes: Some(outcome::Tally {
+                pass: 1,
+                fail: 1,
+                unknown: 0,
+            }),
+            schedule: schedule::Schedule(vec![vec![0, 1]]),
+            discovery,
+            permutations: vec![Permutation {
+                assignment: vec![0, 1],
+                rotations: 1,
+                outcome: Some(outcome::Outcome::Pass),
+                states: vec![Occurrence {
+                    state: 0,
+                    occurs: 2,
+                }],
+            }],
+            alternation: Some(alternation::Alternation {
+                every: std::num::NonZeroUsize::MIN,
+                arms: vec![alternation::Arm {
+                    sync: "<sync>".to_owned(),
+                    rotations: 1,
+                    states: vec![Occurrence {
+                        state: 0,
+                        occurs: 2,
+                    }],
+                }],
+            }),
+            rotations: vec![rotation::Section {
+                rotation: 0,
+                assignment: vec![0, 1],
+                outcome: Some(outcome::Outcome::Pass),
+                states: vec![Occurrence {
+                    state: 0,
+                    occurs: 2,
+                }],
+            }],
+            examples: vec![example::Example {
+                state: 0,
+                iteration: 0,
+                rotation: 0,
+                permutation: vec![0, 1],
+                initials: [(var(), Value::I32(1))].into(),
+                skew: [(
+                    "<thread>".to_owned(),
+                    skew::Delay::fixed(std::time::Duration::from_nanos(100)),
+                )]
+                .into(),
+            }],
+            transitions: vec![transition::Transition {
+                from: 0,
+                to: 0,
+                occurs: 2,
+            }],
+            torn: vec![canary::TornRead::new(var(), Value::I32(1), 0, 0)],
+            violations: vec![invariant::Violation::new(
+                var(),
+                Value::F64(1.5),
+                invariant::Bounds { min: 0.0, max: 1.0 },
+                0,
+                0,
+            )],
+            interesting: vec![interest::Sighting {
+                name: "<name>".to_owned(),
+                iteration: Some(0),
+            }],
+            masked: vec!["<var>".to_owned()],
+            var_order: vec!["<var>".to_owned()],
+            folded: vec![vec![0, 1]],
+            orderings: [("<var>".to_owned(), [ordering::Ordering::Relaxed].into())].into(),
+            platform: Some(platform::Platform {
+                os: "linux".to_owned(),
+                family: "unix".to_owned(),
+                arch: "x86_64".to_owned(),
+                cpus: 2,
+                topology: Some(platform::Topology {
+                    model: Some("<model>".to_owned()),
+                    packages: 1,
+                    cores: 2,
+                    logical: 2,
+                }),
+            }),
+            provenance: Some(provenance::Provenance {
+                run_id: "<uuid>".to_owned(),
+                version: "<version>".to_owned(),
+                allocator: Some("<allocator>".to_owned()),
+                seed: Some(1),
+                input: Some(provenance::Input::new("<path>", b"")),
+                config: Some("<toml>".to_owned()),
+            }),
+            cpus: "0-1".parse().ok(),
+            roles: vec![0, 1],
+            capabilities: [capability::Capability::Check].into(),
+            conformance: Some(conformance::Conformance {
+                expected: conformance::Expected(vec![matcher.clone()]),
+                verdicts: vec![conformance::Verdict::Allowed],
+                unobserved: vec![matcher],
+            }),
+            aggregate: [("<name>".to_owned(), outcome::Outcome::Pass)].into(),
+            projections: vec![projection::Projection {
+                name: "<name>".to_owned(),
+                vars: vec!["<var>".to_owned()],
+                states: vec![projection::Marginal {
+                    state: [(var(), Value::I32(1))].into(),
+                    occurs: 2,
+                    outcome: outcome::Outcome::Pass,
+                }],
+            }],
+            tainted: vec![taint::Taint {
+                cause: taint::Cause::Fork,
+                iteration: 0,
+                rotation: 0,
+            }],
+            timing: vec![timing::Histogram {
+                samples: 1,
+                total_ns: 100,
+                max_ns: 100,
+                buckets: vec![1],
+            }],
+            allocations: Some(allocation::Audit {
+                iterations: 2,
+                allocations: 1,
+                allocating: 1,
+                max: 1,
+                last: Some(0),
+            }),
+        }
+    }
+
+    /// Tests that the serialised shape of reports is the one recorded for
+    /// the current schema version.
+    ///
+    /// If this fails after a deliberate change to the report's shape, bump
+    /// [`VERSION`] (unless the current version is yet to be released), and
+    /// record the new shape over the old one.
+    #[test]
+    fn test_shape_matches_version() {
+        let got = shape(&serde_json::to_value(full()).unwrap());
+        let want: Vec<_> = include_str!("schema/report.shape").lines().collect();
+        assert_eq!(got, want, "report shape changed without a version bump");
+    }
+
+    /// Tests that reports always serialise with the current version, and
+    /// that unversioned reports read as version 0.
+    #[test]
+    fn test_version_round_trip() {
+        let old: Report = serde_json::from_str(r#"{"outcome": null, "states": []}"#).unwrap();
+        assert_eq!(old.schema_version, 0);
+        let json = serde_json::to_value(&old).unwrap();
+        assert_eq!(json["schema_version"], VERSION);
+
+        let again: Report = serde_json::from_value(serde_json::to_value(full()).unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_value(again).unwrap(),
+            serde_json::to_value(full()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Instructions for reproducing a failing run quickly.
+//!
+//! A run that exits on a failure can be followed by a shrink (see
+//! `run::shrink`), which retries the test with smaller iteration budgets and
+//! fresh seeds until it finds the smallest budget that still fails.  Reports
+//! record what it found, so that whoever reads them can rerun the failure
+//! without waiting out the original budget.
+
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, num::NonZeroUsize};
+
+/// The smallest configuration found to reproduce a failure, and how
+/// reliably it does so.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reproduction {
+    /// The iteration budget.
+    pub iterations: NonZeroUsize,
+    /// The number of iterations between rotations, if the run rotates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<NonZeroUsize>,
+    /// The seed from which to choose each iteration's observer.
+    pub seed: u64,
+    /// The number of runs the shrink tried in finding this configuration.
+    pub tries: usize,
+    /// The number of reruns of this configuration made to check it.
+    pub reruns: usize,
+    /// The number of those reruns that failed.
+    pub failures: usize,
+}
+
+impl Reproduction {
+    /// Gets the arguments that make `phph run` use this configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::reproduction::Reproduction;
+    /// let r = Reproduction {
+    ///     iterations: 40.try_into().unwrap(),
+    ///     period: None,
+    ///     seed: 7,
+    ///     tries: 9,
+    ///     reruns: 3,
+    ///     failures: 2,
+    /// };
+    /// assert_eq!(r.args(), ["--iterations=40", "--period=0", "--observer-seed=7"]);
+    /// ```
+    #[must_use]
+    pub fn args(&self) -> Vec<String> {
+        vec![
+            format!("--iterations={}", self.iterations),
+            format!("--period={}", self.period.map_or(0, NonZeroUsize::get)),
+            format!("--observer-seed={}", self.seed),
+        ]
+    }
+}
+
+/// Reproductions display as their arguments, followed by how reliable they
+/// are.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::reproduction::Reproduction;
+/// let r = Reproduction {
+///     iterations: 40.try_into().unwrap(),
+///     period: 10.try_into().ok(),
+///     seed: 7,
+///     tries: 9,
+///     reruns: 3,
+///     failures: 2,
+/// };
+/// assert_eq!(
+///     r.to_string(),
+///     "--iterations=40 --period=10 --observer-seed=7 (failed 2 of 3 reruns)"
+/// );
+/// ```
+impl Display for Reproduction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (failed {} of {} reruns)",
+            self.args().join(" "),
+            self.failures,
+            self.reruns
+        )
+    }
+}
@@ -0,0 +1,158 @@
+//! Per-rotation sections of a run's histogram.
+//!
+//! A run's histogram sums over every rotation, which hides any difference
+//! between them: a transient effect that only shows in a few rotations, or a
+//! thread permutation that shifts the distribution, washes out in the total.
+//! Segmenting the histogram gives one [Section] per rotation, counting
+//! occurrences as if the observer had reset its counts at the start of each
+//! rotation, alongside the usual totals.
+
+use super::{
+    outcome::Outcome,
+    report::{self, Occurrence},
+    schedule,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The states seen in one rotation of a run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Section {
+    /// The index of the rotation.
+    pub rotation: usize,
+    /// The thread assignment that the rotation used, if known.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assignment: schedule::Assignment,
+    /// The overall outcome of the states seen in this rotation.
+    pub outcome: Option<Outcome>,
+    /// The states seen in this rotation, in report order, and how often.
+    pub states: Vec<Occurrence>,
+}
+
+impl Section {
+    /// Gets the number of observed iterations in this rotation.
+    #[must_use]
+    pub fn iterations(&self) -> usize {
+        self.states
+            .iter()
+            .fold(0, |n, o| n.saturating_add(o.occurs))
+    }
+}
+
+/// Segments occurrences of `states` by rotation, giving one section for
+/// every rotation in `schedule` (and any later rotation with occurrences).
+///
+/// `counts` holds, for each state in `states` (in the same order), the
+/// number of times that state occurred in each rotation.  Rotations in which
+/// the run observed nothing, such as during warmup, have empty sections.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::{report, rotation, schedule::Schedule, state, Outcome};
+/// use std::collections::BTreeMap;
+///
+/// let states: Vec<_> = [Outcome::Pass, Outcome::Fail]
+///     .into_iter()
+///     .enumerate()
+///     .map(|(i, outcome)| report::State {
+///         state: [("x".into(), state::Value::I32(i as i32))].into(),
+///         info: state::Info::new(outcome, 0, 0),
+///         initials: vec![],
+///         interesting: vec![],
+///         checks: Default::default(),
+///         aux_flags: Default::default(),
+///         hash: String::new(),
+///     })
+///     .collect();
+/// let counts = [BTreeMap::from([(0, 3), (2, 1)]), BTreeMap::from([(2, 2)])];
+/// let schedule = Schedule(vec![vec![0, 1], vec![1, 0], vec![0, 1]]);
+///
+/// let sections = rotation::segment(&counts, &schedule, &states);
+/// assert_eq!(sections.len(), 3);
+/// assert_eq!(sections[0].iterations(), 3);
+/// assert_eq!(sections[0].outcome, Some(Outcome::Pass));
+/// assert!(sections[1].states.is_empty());
+/// assert_eq!(sections[1].assignment, [1, 0]);
+/// assert_eq!(sections[2].iterations(), 3);
+/// assert_eq!(sections[2].outcome, Some(Outcome::Fail));
+/// ```
+#[must_use]
+pub fn segment(
+    counts: &[BTreeMap<usize, usize>],
+    schedule: &schedule::Schedule,
+    states: &[report::State],
+) -> Vec<Section> {
+    let last = counts
+        .iter()
+        .filter_map(|c| c.keys().next_back())
+        .max()
+        .map_or(0, |&r| r.saturating_add(1));
+    let mut sections: Vec<_> = (0..schedule.len().max(last))
+        .map(|rotation| Section {
+            rotation,
+            assignment: schedule
+                .get(rotation)
+                .map(<[usize]>::to_vec)
+                .unwrap_or_default(),
+            outcome: None,
+            states: vec![],
+        })
+        .collect();
+    for (state, counts) in counts.iter().enumerate() {
+        let outcome = states.get(state).map(|s| s.info.outcome);
+        for (&rotation, &occurs) in counts {
+            let section = &mut sections[rotation];
+            section.outcome = section.outcome.max(outcome);
+            section.states.push(Occurrence { state, occurs });
+        }
+    }
+    sections
+}
+
+/// Merges the sections `others` of another run into `sections`, using
+/// `remap` to map state indices, and shifting rotations by `offset`.
+///
+/// As the other run's rotations follow this run's, its sections follow
+/// these.
+pub fn merge(sections: &mut Vec<Section>, others: Vec<Section>, remap: &[usize], offset: usize) {
+    for mut s in others {
+        s.rotation = s.rotation.saturating_add(offset);
+        for o in &mut s.states {
+            o.state = remap.get(o.state).copied().unwrap_or(o.state);
+        }
+        s.states.sort_by_key(|o| o.state);
+        sections.push(s);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(rotation: usize, states: &[(usize, usize)]) -> Section {
+        Section {
+            rotation,
+            assignment: vec![],
+            outcome: None,
+            states: states
+                .iter()
+                .map(|&(state, occurs)| Occurrence { state, occurs })
+                .collect(),
+        }
+    }
+
+    /// Tests that merging shifts and remaps the other run's sections.
+    #[test]
+    fn test_merge() {
+        let mut sections = vec![section(0, &[(0, 2)]), section(1, &[(1, 1)])];
+        let others = vec![section(0, &[(0, 1), (1, 3)])];
+        merge(&mut sections, others, &[2, 0], 2);
+        assert_eq!(sections.len(), 3);
+        let last = &sections[2];
+        assert_eq!(last.rotation, 2);
+        let states: Vec<_> = last.states.iter().map(|o| (o.state, o.occurs)).collect();
+        assert_eq!(states, [(0, 3), (2, 1)]);
+        assert_eq!(last.iterations(), 4);
+    }
+}
@@ -0,0 +1,114 @@
+//! Sampling of observations.
+//!
+//! Observing an iteration means reading, hashing, and recording its state,
+//! which can cost more than the iteration itself; a sampled run observes
+//! only some iterations, so that the histogram approximates the distribution
+//! of states at a fraction of the observer's overhead.  Sampled runs still
+//! count every iteration, so iteration budgets, rotations, and rates are
+//! exact, but each state's occurrences only cover the sampled iterations.
+//! Reports record how they were sampled, so that nobody mistakes their
+//! counts for exact ones.
+
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, num::NonZeroUsize};
+
+/// A rule for choosing which iterations to observe.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Rule {
+    /// Observe every `n`th iteration, starting with the first.
+    Every {
+        /// The interval between observed iterations.
+        n: NonZeroUsize,
+    },
+    /// Observe each iteration independently with a fixed probability.
+    Random {
+        /// The probability, between 0 and 1, of observing each iteration.
+        probability: f64,
+        /// The seed from which to draw the choices.
+        seed: u64,
+    },
+}
+
+/// Rules display as a short description.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::sampling::Rule;
+/// let every = Rule::Every { n: 10.try_into().unwrap() };
+/// assert_eq!(every.to_string(), "one in 10");
+/// let random = Rule::Random { probability: 0.05, seed: 42 };
+/// assert_eq!(random.to_string(), "5% at random (seed 42)");
+/// ```
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Every { n } => write!(f, "one in {n}"),
+            Self::Random { probability, seed } => {
+                write!(f, "{}% at random (seed {seed})", probability * 100.0)
+            }
+        }
+    }
+}
+
+/// How a report's observations were sampled.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Sampling {
+    /// The rule by which iterations were sampled, if every run behind the
+    /// report used the same one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<Rule>,
+    /// The number of iterations that ran, sampled or not.
+    pub iterations: usize,
+    /// The number of iterations that were sampled, and so counted in the
+    /// report's states.
+    pub sampled: usize,
+}
+
+/// Samplings display as the iterations sampled, and how.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::sampling::{Rule, Sampling};
+/// let rule = Rule::Every { n: 4.try_into().unwrap() };
+/// let s = Sampling { rule: Some(rule), iterations: 10, sampled: 3 };
+/// assert_eq!(s.to_string(), "3 of 10 iterations, one in 4");
+/// let s = Sampling { rule: None, ..s };
+/// assert_eq!(s.to_string(), "3 of 10 iterations");
+/// ```
+impl Display for Sampling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} of {} iterations", self.sampled, self.iterations)?;
+        match &self.rule {
+            Some(rule) => write!(f, ", {rule}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Sampling {
+    /// Merges the sampling of another report into this one.
+    ///
+    /// The rule survives only if both reports used it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::sampling::{Rule, Sampling};
+    /// let rule = Rule::Every { n: 2.try_into().unwrap() };
+    /// let mut s = Sampling { rule: Some(rule), iterations: 10, sampled: 5 };
+    /// s.merge(&Sampling { rule: Some(rule), iterations: 4, sampled: 2 });
+    /// assert_eq!(s, Sampling { rule: Some(rule), iterations: 14, sampled: 7 });
+    /// s.merge(&Sampling { rule: None, iterations: 1, sampled: 1 });
+    /// assert_eq!(s.rule, None);
+    /// ```
+    pub fn merge(&mut self, other: &Self) {
+        if self.rule != other.rule {
+            self.rule = None;
+        }
+        self.iterations = self.iterations.saturating_add(other.iterations);
+        self.sampled = self.sampled.saturating_add(other.sampled);
+    }
+}
@@ -0,0 +1,53 @@
+//! Thread permutation schedules.
+//!
+//! A schedule records, for each rotation of a test, the order in which the
+//! runner handed thread IDs to the threader.  Schedules can be fed back into
+//! the runner (see `crate::run::permute::Replay`) to re-execute a campaign
+//! with the same permutations.
+
+use serde::{Deserialize, Serialize};
+
+/// The thread assignment for a single rotation.
+///
+/// Each element is a thread ID; the position of the ID in the vector is the
+/// order in which the thread was spawned.
+pub type Assignment = Vec<usize>;
+
+/// A full schedule, containing one assignment per rotation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Schedule(pub Vec<Assignment>);
+
+impl Schedule {
+    /// Appends an assignment to the end of this schedule.
+    pub fn push(&mut self, assignment: Assignment) {
+        self.0.push(assignment);
+    }
+
+    /// Gets the assignment for the rotation with index `rotation`, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::schedule::Schedule;
+    /// let s = Schedule(vec![vec![1, 0], vec![0, 1]]);
+    /// assert_eq!(s.get(0), Some(&[1, 0][..]));
+    /// assert_eq!(s.get(2), None);
+    /// ```
+    #[must_use]
+    pub fn get(&self, rotation: usize) -> Option<&[usize]> {
+        self.0.get(rotation).map(Vec::as_slice)
+    }
+
+    /// Gets the number of rotations recorded in this schedule.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Gets whether this schedule is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
@@ -0,0 +1,132 @@
+//! Models for per-thread iteration skew.
+//!
+//! Skewing delays selected threads' entry into each iteration, biasing the
+//! interleavings that a test sees towards those where the delayed threads run
+//! late.  Each delay is either fixed or drawn uniformly from a range on every
+//! iteration.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Display, str::FromStr, time::Duration};
+
+/// A map from thread IDs (as strings, as they are TOML keys) to the delays
+/// to apply to those threads.
+pub type Spec = BTreeMap<String, Delay>;
+
+/// An inclusive range of delays, written as `250ns` when fixed and as
+/// `0..500ns` when not.
+///
+/// Delays take the units `ns`, `us`, `ms`, and `s`; a range's start takes its
+/// end's unit if it has none of its own.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Delay {
+    /// The shortest delay.
+    pub min: Duration,
+    /// The longest delay.
+    pub max: Duration,
+}
+
+impl Delay {
+    /// Constructs a fixed delay.
+    #[must_use]
+    pub fn fixed(d: Duration) -> Self {
+        Self { min: d, max: d }
+    }
+
+    /// Gets whether this delay is fixed.
+    #[must_use]
+    pub fn is_fixed(&self) -> bool {
+        self.min == self.max
+    }
+}
+
+/// Delays display in nanoseconds, so that they parse back exactly.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::skew::Delay;
+/// let d: Delay = "1..2us".parse().unwrap();
+/// assert_eq!(d.to_string(), "1000ns..2000ns");
+/// assert_eq!("3ms".parse::<Delay>().unwrap().to_string(), "3000000ns");
+/// ```
+impl Display for Delay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_fixed() {
+            write!(f, "{}ns", self.min.as_nanos())
+        } else {
+            write!(f, "{}ns..{}ns", self.min.as_nanos(), self.max.as_nanos())
+        }
+    }
+}
+
+impl FromStr for Delay {
+    type Err = String;
+
+    /// Parses a delay.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::skew::Delay;
+    /// use std::time::Duration;
+    ///
+    /// let d: Delay = "0..500ns".parse().unwrap();
+    /// assert_eq!((d.min, d.max), (Duration::ZERO, Duration::from_nanos(500)));
+    /// let d: Delay = "1ms..2s".parse().unwrap();
+    /// assert_eq!((d.min, d.max), (Duration::from_millis(1), Duration::from_secs(2)));
+    /// assert!("500".parse::<Delay>().is_err());
+    /// assert!("2..1us".parse::<Delay>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (start, end) = s.split_once("..").map_or((None, s), |(a, b)| (Some(a), b));
+        let (n, unit) = split_unit(end);
+        let unit = unit.ok_or_else(|| format!("missing unit in delay {s}"))?;
+        let max = duration(n, unit)?;
+        let min = start.map_or(Ok(max), |start| {
+            let (n, start_unit) = split_unit(start);
+            duration(n, start_unit.unwrap_or(unit))
+        })?;
+        if max < min {
+            return Err(format!("delay range {s} ends before it starts"));
+        }
+        Ok(Self { min, max })
+    }
+}
+
+impl TryFrom<String> for Delay {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Delay> for String {
+    fn from(d: Delay) -> Self {
+        d.to_string()
+    }
+}
+
+/// Splits `s` into its number and its unit, if it has one.
+fn split_unit(s: &str) -> (&str, Option<&str>) {
+    let s = s.trim();
+    match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => (&s[..i], Some(&s[i..])),
+        None => (s, None),
+    }
+}
+
+fn duration(n: &str, unit: &str) -> Result<Duration, String> {
+    let n: u64 = n
+        .parse()
+        .map_err(|e| format!("bad delay amount {n:?}: {e}"))?;
+    match unit {
+        "ns" => Ok(Duration::from_nanos(n)),
+        "us" => Ok(Duration::from_micros(n)),
+        "ms" => Ok(Duration::from_millis(n)),
+        "s" => Ok(Duration::from_secs(n)),
+        _ => Err(format!("unknown delay unit {unit:?}")),
+    }
+}
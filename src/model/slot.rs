@@ -53,7 +53,12 @@ impl<T: Default> Reservation<T> {
 }
 
 /// A set of slot reservations.
+#[derive(Default)]
 pub struct ReservationSet {
     /// The reservations for 32-bit integers.
     pub i32s: Reservation<i32>,
+    /// The reservations for Booleans.
+    pub bools: Reservation<bool>,
+    /// The reservations for 64-bit floats.
+    pub f64s: Reservation<f64>,
 }
@@ -1,48 +1,360 @@
 //! Models for states.
 
-use super::outcome;
+use super::{manifest, outcome};
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, fmt::Display};
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
 
 /// An observed state.
 ///
 /// `State` is an ordered map; the order should be consistent between each state
 /// observed in a test.
-pub type State = BTreeMap<String, Value>;
+pub type State = BTreeMap<manifest::VarName, Value>;
 
 /// A value in a state.
 ///
 /// Values are marked non-exhaustive as phenolphthalein may add new value types
 /// in future.
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// Equality and hashing on floating-point values is bitwise, so that each
+/// distinct bit pattern (including each `NaN`) is a distinct state.  For the
+/// same reason, floating-point values serialise losslessly: see [`f64_repr`].
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case", tag = "type", content = "value")]
 #[non_exhaustive]
 pub enum Value {
     /// A 32-bit signed integer.
     I32(i32),
+    /// A Boolean.
+    Bool(bool),
+    /// A 64-bit floating-point number.
+    F64(#[serde(with = "f64_repr")] f64),
+}
+
+/// Lossless serialisation of floating-point values.
+///
+/// Finite values serialise as numbers.  JSON has no numbers for the
+/// non-finite values, so these serialise as the strings `inf`, `-inf`, and
+/// `NaN`; a `NaN` other than [`f64::NAN`] serialises as `NaN:` followed by
+/// its bits in hexadecimal, such as `NaN:0xfff8000000000000`.
+pub mod f64_repr {
+    use serde::{de, Deserializer, Serializer};
+    use std::fmt;
+
+    /// The prefix of a `NaN` given by its bits.
+    const NAN_BITS: &str = "NaN:0x";
+
+    /// Serialises `v` losslessly.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the serialiser does.
+    #[allow(clippy::trivially_copy_pass_by_ref)] // as serde requires
+    pub fn serialize<S: Serializer>(v: &f64, s: S) -> Result<S::Ok, S::Error> {
+        if v.is_finite() {
+            s.serialize_f64(*v)
+        } else if v.is_nan() && v.to_bits() != f64::NAN.to_bits() {
+            s.serialize_str(&format!("{NAN_BITS}{:016x}", v.to_bits()))
+        } else {
+            s.collect_str(v)
+        }
+    }
+
+    /// Deserialises a value serialised by [`serialize`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the value is neither a number nor one of the strings that
+    /// [`serialize`] writes.
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+        d.deserialize_any(Visitor)
+    }
+
+    struct Visitor;
+
+    impl de::Visitor<'_> for Visitor {
+        type Value = f64;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a number, or one of \"inf\", \"-inf\", or \"NaN\"")
+        }
+
+        fn visit_f64<E: de::Error>(self, v: f64) -> Result<f64, E> {
+            Ok(v)
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<f64, E> {
+            Ok(v as f64)
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<f64, E> {
+            Ok(v as f64)
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<f64, E> {
+            let parsed = match v.strip_prefix(NAN_BITS) {
+                Some(bits) => u64::from_str_radix(bits, 16)
+                    .ok()
+                    .map(f64::from_bits)
+                    .filter(|f| f.is_nan()),
+                None => v.parse().ok().filter(|f: &f64| !f.is_finite()),
+            };
+            parsed.ok_or_else(|| E::invalid_value(de::Unexpected::Str(v), &self))
+        }
+    }
+}
+
+/// Values are compared bitwise.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::state::Value;
+/// assert!(Value::F64(f64::NAN) == Value::F64(f64::NAN));
+/// assert!(Value::F64(0.0) != Value::F64(-0.0));
+/// assert!(Value::I32(1) != Value::Bool(true));
+/// ```
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::I32(x), Self::I32(y)) => x == y,
+            (Self::Bool(x), Self::Bool(y)) => x == y,
+            (Self::F64(x), Self::F64(y)) => x.to_bits() == y.to_bits(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+/// Values are hashed consistently with their bitwise equality.
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::I32(v) => v.hash(state),
+            Self::Bool(v) => v.hash(state),
+            Self::F64(v) => v.to_bits().hash(state),
+        }
+    }
 }
 
 /// We display values, by default, without any type annotation.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::state::Value;
+/// assert_eq!(Value::I32(-4).to_string(), "-4");
+/// assert_eq!(Value::Bool(true).to_string(), "true");
+/// assert_eq!(Value::F64(0.5).to_string(), "0.5");
+/// ```
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Self::I32(v) => v,
-            }
-        )
+        match self {
+            Self::I32(v) => write!(f, "{v}"),
+            Self::Bool(v) => write!(f, "{v}"),
+            Self::F64(v) => write!(f, "{v}"),
+        }
     }
 }
 
-/// A record of information about an observed stae.
+/// A hint for how to display a variable's values.
+///
+/// Formats only affect integers; other values always display as usual.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Signed decimal (the default).
+    #[default]
+    Decimal,
+    /// Hexadecimal, showing the two's complement bits of negative numbers.
+    Hex,
+    /// Binary, showing the two's complement bits of negative numbers.
+    Binary,
+    /// A quoted Unicode character, falling back to decimal if the value
+    /// isn't a valid code point.
+    Char,
+}
+
+/// Display formats for variables, keyed by variable name.
+pub type Formats = BTreeMap<String, Format>;
+
+impl Value {
+    /// Displays this value in the format `fmt`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::state::{Format, Value};
+    /// assert_eq!(Value::I32(255).display(Format::Hex), "0xff");
+    /// assert_eq!(Value::I32(-1).display(Format::Hex), "0xffffffff");
+    /// assert_eq!(Value::I32(5).display(Format::Binary), "0b101");
+    /// assert_eq!(Value::I32(65).display(Format::Char), "'A'");
+    /// assert_eq!(Value::I32(-1).display(Format::Char), "-1");
+    /// assert_eq!(Value::Bool(true).display(Format::Hex), "true");
+    /// ```
+    #[must_use]
+    pub fn display(&self, fmt: Format) -> String {
+        match (self, fmt) {
+            (Self::I32(v), Format::Hex) => format!("{:#x}", v.cast_unsigned()),
+            (Self::I32(v), Format::Binary) => format!("{:#b}", v.cast_unsigned()),
+            (Self::I32(v), Format::Char) => u32::try_from(*v)
+                .ok()
+                .and_then(char::from_u32)
+                .map_or_else(|| v.to_string(), |c| format!("{c:?}")),
+            _ => self.to_string(),
+        }
+    }
+}
+
+/// String representations of each key order, used in the clap interface.
+pub mod string {
+    /// Name of the lexicographic key order.
+    pub const LEXICAL: &str = "lexical";
+    /// Name of the declaration key order.
+    pub const DECLARED: &str = "declared";
+    /// Name of the thread-major key order.
+    pub const THREAD: &str = "thread";
+    /// Names of all key orders.
+    pub const ALL: &[&str] = &[LEXICAL, DECLARED, THREAD];
+}
+
+/// An order in which to show the variables of a state.
+///
+/// States themselves always hold their variables in lexicographic order;
+/// other orders only affect how outputs show them.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyOrder {
+    /// Lexicographic order of names (the default).
+    #[default]
+    Lexical,
+    /// The order in which the test declared its variables, with any the test
+    /// didn't declare in order last.
+    Declared,
+    /// Registers (variables named `T:name`) first, by thread ID `T` and then
+    /// by name, followed by the other variables by name, as litmus tests
+    /// usually show them.
+    Thread,
+}
+
+/// Tries to parse a [`KeyOrder`] from a string.
+impl std::str::FromStr for KeyOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            string::LEXICAL => Ok(Self::Lexical),
+            string::DECLARED => Ok(Self::Declared),
+            string::THREAD => Ok(Self::Thread),
+            s => Err(s.to_owned()),
+        }
+    }
+}
+
+/// Formats a [`KeyOrder`] by applying the inverse of `FromStr`.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::state::KeyOrder;
+/// for o in [KeyOrder::Lexical, KeyOrder::Declared, KeyOrder::Thread] {
+///     assert_eq!(o.to_string().parse::<KeyOrder>().unwrap(), o);
+/// }
+/// ```
+impl Display for KeyOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Lexical => string::LEXICAL,
+            Self::Declared => string::DECLARED,
+            Self::Thread => string::THREAD,
+        })
+    }
+}
+
+impl KeyOrder {
+    /// Gets a key that sorts the variable `name` into this order, where
+    /// `declared` lists the test's variables in the order it declared them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::state::KeyOrder;
+    ///
+    /// let declared = ["y", "1:r0", "0:r1", "10:r0", "0:r0"];
+    /// let sorted = |order: KeyOrder| {
+    ///     let mut names = ["0:r0", "0:r1", "10:r0", "1:r0", "y"];
+    ///     names.sort_by_key(|n| order.key(n, &declared));
+    ///     names
+    /// };
+    /// assert_eq!(sorted(KeyOrder::Lexical), ["0:r0", "0:r1", "10:r0", "1:r0", "y"]);
+    /// assert_eq!(sorted(KeyOrder::Declared), ["y", "1:r0", "0:r1", "10:r0", "0:r0"]);
+    /// assert_eq!(sorted(KeyOrder::Thread), ["0:r0", "0:r1", "1:r0", "10:r0", "y"]);
+    /// ```
+    #[must_use]
+    pub fn key<'a, S: AsRef<str>>(self, name: &'a str, declared: &[S]) -> (usize, &'a str) {
+        match self {
+            Self::Lexical => (0, name),
+            Self::Declared => (
+                declared
+                    .iter()
+                    .position(|d| d.as_ref() == name)
+                    .unwrap_or(usize::MAX),
+                name,
+            ),
+            Self::Thread => name
+                .split_once(':')
+                .and_then(|(tid, reg)| Some((tid.parse().ok()?, reg)))
+                .unwrap_or((usize::MAX, name)),
+        }
+    }
+
+    /// Gets the variables of `state`, with their values, in this order (see
+    /// [`KeyOrder::key`]).
+    #[must_use]
+    pub fn entries<'a, S: AsRef<str>>(
+        self,
+        state: &'a State,
+        declared: &[S],
+    ) -> Vec<(&'a manifest::VarName, &'a Value)> {
+        let mut entries: Vec<_> = state.iter().collect();
+        if self != Self::Lexical {
+            entries.sort_by_cached_key(|(k, _)| self.key(k, declared));
+        }
+        entries
+    }
+}
+
+/// A record of information about an observed state.
 ///
 /// An observation aggregates the various times a tester has seen a particular
 /// state.
-#[derive(Copy, Clone, Serialize, Deserialize)]
+///
+/// Rotation numbers index into the report's permutation schedule, so the
+/// thread permutation live when a state was first seen is the schedule's
+/// assignment for [`Info::rotation`].
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct Info {
     /// The number of the cycle where this observation first occurred.
     pub iteration: usize,
+    /// The number of the cycle where this observation last occurred.
+    #[serde(default)]
+    pub last_iteration: usize,
+    /// The number of the rotation where this observation first occurred.
+    #[serde(default)]
+    pub rotation: usize,
+    /// The number of the rotation where this observation last occurred.
+    #[serde(default)]
+    pub last_rotation: usize,
+    /// The number of distinct rotations in which this observation occurred.
+    #[serde(default)]
+    pub rotations: usize,
     /// The number of times this state has occurred.
     pub occurs: usize,
     /// The result of asking the test to check this state.
@@ -50,24 +362,117 @@ pub struct Info {
 }
 
 impl Info {
-    /// Creates a new [Info] with the given outcome and iteration, and with
-    /// an occurs count of 1.
+    /// Creates a new [Info] with the given outcome, iteration, and rotation,
+    /// and with an occurs count of 1.
     #[must_use]
-    pub fn new(outcome: outcome::Outcome, iteration: usize) -> Self {
+    pub fn new(outcome: outcome::Outcome, iteration: usize, rotation: usize) -> Self {
         Self {
             occurs: 1,
             outcome,
             iteration,
+            last_iteration: iteration,
+            rotation,
+            last_rotation: rotation,
+            rotations: 1,
         }
     }
 
-    /// Computes the [Info] resulting from increasing this [Info]'s
-    /// occurs count by 1.
+    /// Computes the [Info] resulting from seeing this [Info]'s state again at
+    /// the given iteration and rotation.
+    ///
+    /// Rotations must not decrease between sightings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{state::Info, Outcome};
+    /// let info = Info::new(Outcome::Pass, 2, 0).inc(5, 0).inc(9, 3);
+    /// assert_eq!(info.occurs, 3);
+    /// assert_eq!((info.iteration, info.last_iteration), (2, 9));
+    /// assert_eq!((info.rotation, info.last_rotation), (0, 3));
+    /// assert_eq!(info.rotations, 2);
+    /// ```
     #[must_use]
-    pub fn inc(&self) -> Info {
+    pub fn inc(&self, iteration: usize, rotation: usize) -> Info {
+        let rotations = if rotation == self.last_rotation {
+            self.rotations
+        } else {
+            self.rotations.saturating_add(1)
+        };
         Info {
             occurs: self.occurs.saturating_add(1),
+            last_iteration: iteration,
+            last_rotation: rotation,
+            rotations,
             ..*self
         }
     }
+
+    /// Combines this [Info] with another for the same state, taken from a
+    /// separate run whose rotations don't overlap with this one's.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{state::Info, Outcome};
+    /// let a = Info::new(Outcome::Pass, 2, 0).inc(5, 1);
+    /// let b = Info::new(Outcome::Pass, 1, 3);
+    /// let info = a.merge(&b);
+    /// assert_eq!(info.occurs, 3);
+    /// assert_eq!((info.iteration, info.last_iteration), (1, 5));
+    /// assert_eq!((info.rotation, info.last_rotation), (0, 3));
+    /// assert_eq!(info.rotations, 3);
+    /// ```
+    #[must_use]
+    pub fn merge(&self, other: &Info) -> Info {
+        Info {
+            iteration: self.iteration.min(other.iteration),
+            last_iteration: self.last_iteration.max(other.last_iteration),
+            rotation: self.rotation.min(other.rotation),
+            last_rotation: self.last_rotation.max(other.last_rotation),
+            rotations: self.rotations.saturating_add(other.rotations),
+            occurs: self.occurs.saturating_add(other.occurs),
+            outcome: self.outcome.max(other.outcome),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that floating-point values, including non-finite ones, survive
+    /// a round trip through JSON bit for bit.
+    #[test]
+    fn test_f64_json_round_trip() {
+        let values = [
+            0.5,
+            -0.0,
+            f64::MAX,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NAN,
+            -f64::NAN,
+            f64::from_bits(0x7ff0_0000_0000_0001),
+        ];
+        for v in values {
+            let json = serde_json::to_string(&Value::F64(v)).unwrap();
+            let back: Value = serde_json::from_str(&json).unwrap();
+            assert_eq!(back, Value::F64(v), "{json}");
+        }
+        assert_eq!(
+            serde_json::to_string(&Value::F64(f64::NEG_INFINITY)).unwrap(),
+            r#"{"type":"f64","value":"-inf"}"#
+        );
+    }
+
+    /// Tests that strings other than non-finite values don't parse as
+    /// floating-point values.
+    #[test]
+    fn test_f64_json_rejects_finite_strings() {
+        for json in [r#""1.5""#, r#""NaN:0x0""#, r#""frog""#] {
+            let json = format!(r#"{{"type":"f64","value":{json}}}"#);
+            assert!(serde_json::from_str::<Value>(&json).is_err(), "{json}");
+        }
+    }
 }
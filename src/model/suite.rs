@@ -0,0 +1,75 @@
+//! The [Suite] type, for reports over several tests.
+
+use super::{outcome, report::Report};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Reports for several tests run in one go, keyed by test name.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Suite {
+    /// The report for each test.
+    pub reports: BTreeMap<String, Report>,
+}
+
+impl Suite {
+    /// Adds a report for the test named `name`.
+    ///
+    /// If a test with this name already exists, the new test gets a numeric
+    /// suffix to keep it distinct.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{Report, Suite};
+    /// let mut suite = Suite::default();
+    /// suite.insert("sb", Report::default());
+    /// suite.insert("sb", Report::default());
+    /// let names: Vec<_> = suite.reports.keys().cloned().collect();
+    /// assert_eq!(names, ["sb", "sb~1"]);
+    /// ```
+    pub fn insert(&mut self, name: &str, report: Report) {
+        let mut key = name.to_owned();
+        let mut suffix = 0;
+        while self.reports.contains_key(&key) {
+            suffix += 1;
+            key = format!("{name}~{suffix}");
+        }
+        self.reports.insert(key, report);
+    }
+
+    /// Summarises each test in the suite, in name order.
+    ///
+    /// Iteration counts include iterations that ran but weren't sampled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::{sampling::Sampling, Report, Suite};
+    /// let mut suite = Suite::default();
+    /// let sampling = Sampling { rule: None, iterations: 10, sampled: 0 };
+    /// suite.insert("sb", Report { sampling: Some(sampling), ..Report::default() });
+    /// let summary: Vec<_> = suite.summary().collect();
+    /// assert_eq!(summary[0].iterations, 10);
+    /// ```
+    pub fn summary(&self) -> impl Iterator<Item = Summary<'_>> + '_ {
+        self.reports.iter().map(|(name, r)| Summary {
+            name,
+            outcome: r.outcome,
+            states: r.states.len(),
+            iterations: r.sampling_or_exact().iterations,
+        })
+    }
+}
+
+/// A one-line summary of a test in a suite.
+#[derive(Debug, Serialize)]
+pub struct Summary<'a> {
+    /// The name of the test.
+    pub name: &'a str,
+    /// The overall outcome of the test, if known.
+    pub outcome: Option<outcome::Outcome>,
+    /// The number of distinct states observed.
+    pub states: usize,
+    /// The number of iterations that ran, sampled or not.
+    pub iterations: usize,
+}
@@ -0,0 +1,77 @@
+//! Taints: evidence that a test broke the runner's assumptions.
+//!
+//! The runner assumes that a test's threads are the only ones running its
+//! body, and that they stay in one process.  A test body that forks, or
+//! spawns threads of its own, breaks these assumptions silently: its states
+//! may no longer mean what they seem to.  The runner can look out for this
+//! (see `run::guard`), and reports record each kind of taint it saw.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Why a run is tainted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "cause", rename_all = "snake_case")]
+pub enum Cause {
+    /// The process forked while the test ran.
+    Fork,
+    /// The process had more threads than the runner started.
+    Threads {
+        /// The number of threads in the process as the test started.
+        expected: usize,
+        /// The number of threads seen.
+        seen: usize,
+    },
+}
+
+impl Cause {
+    /// Gets whether this cause is of the same kind as `other`, ignoring any
+    /// details.
+    #[must_use]
+    pub fn same_kind(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+/// Causes display as a short explanation.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::taint::Cause;
+/// assert_eq!(Cause::Fork.to_string(), "the process forked");
+/// let threads = Cause::Threads { expected: 3, seen: 4 };
+/// assert_eq!(threads.to_string(), "the process had 4 threads, not 3");
+/// ```
+impl Display for Cause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fork => f.write_str("the process forked"),
+            Self::Threads { expected, seen } => {
+                write!(f, "the process had {seen} threads, not {expected}")
+            }
+        }
+    }
+}
+
+/// The first sign of one kind of taint in a run.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Taint {
+    /// What tainted the run.
+    #[serde(flatten)]
+    pub cause: Cause,
+    /// The iteration at which the runner noticed the taint.
+    pub iteration: usize,
+    /// The rotation in which the runner noticed the taint.
+    pub rotation: usize,
+}
+
+impl Display for Taint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (by iter {}, rot {})",
+            self.cause, self.iteration, self.rotation
+        )
+    }
+}
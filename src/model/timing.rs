@@ -0,0 +1,178 @@
+//! Histograms of how long each thread spends in the test body.
+//!
+//! Timing a run shows when one thread's body takes far longer than the
+//! others', so that its work mostly happens while the others have already
+//! finished: such runs rarely see the interleavings that weak behaviours
+//! need.  Histograms bucket times by powers of two, which is coarse but
+//! cheap to record on every timed iteration.
+
+use serde::{Deserialize, Serialize};
+
+/// A histogram of the times, in nanoseconds, that one thread spent in the
+/// test body.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Histogram {
+    /// The number of timed iterations.
+    pub samples: u64,
+    /// The total time spent over the timed iterations.
+    pub total_ns: u64,
+    /// The longest time spent on any timed iteration.
+    pub max_ns: u64,
+    /// The number of times falling into each bucket; see [`bucket`].
+    ///
+    /// Trailing empty buckets are left out.
+    pub buckets: Vec<u64>,
+}
+
+/// Gets the bucket into which a time of `ns` nanoseconds falls.
+///
+/// Bucket 0 holds times of 0ns, and bucket `i` above that holds times from
+/// `2^(i-1)` up to, but not including, `2^i` nanoseconds.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::timing::bucket;
+/// assert_eq!(bucket(0), 0);
+/// assert_eq!(bucket(1), 1);
+/// assert_eq!(bucket(1023), 10);
+/// assert_eq!(bucket(1024), 11);
+/// ```
+#[must_use]
+pub fn bucket(ns: u64) -> usize {
+    (u64::BITS - ns.leading_zeros()) as usize
+}
+
+/// The number of buckets needed to hold any time.
+pub const BUCKETS: usize = u64::BITS as usize + 1;
+
+impl Histogram {
+    /// Records a time of `ns` nanoseconds.
+    pub fn record(&mut self, ns: u64) {
+        let i = bucket(ns);
+        if self.buckets.len() <= i {
+            self.buckets.resize(i + 1, 0);
+        }
+        self.buckets[i] += 1;
+        self.samples += 1;
+        self.total_ns = self.total_ns.saturating_add(ns);
+        self.max_ns = self.max_ns.max(ns);
+    }
+
+    /// Merges `other` into this histogram.
+    pub fn merge(&mut self, other: &Self) {
+        if self.buckets.len() < other.buckets.len() {
+            self.buckets.resize(other.buckets.len(), 0);
+        }
+        for (mine, theirs) in self.buckets.iter_mut().zip(&other.buckets) {
+            *mine += theirs;
+        }
+        self.samples += other.samples;
+        self.total_ns = self.total_ns.saturating_add(other.total_ns);
+        self.max_ns = self.max_ns.max(other.max_ns);
+    }
+
+    /// Gets the mean time, in nanoseconds, if there were any samples.
+    #[must_use]
+    pub fn mean_ns(&self) -> Option<f64> {
+        #[allow(clippy::cast_precision_loss)]
+        (self.samples != 0).then(|| self.total_ns as f64 / self.samples as f64)
+    }
+
+    /// Estimates the `q`th quantile (from 0 to 1) of the times, in
+    /// nanoseconds, if there were any samples.
+    ///
+    /// The estimate is the upper bound of the bucket containing the
+    /// quantile, capped at the longest time seen, so it is never an
+    /// underestimate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::model::timing::Histogram;
+    /// let mut h = Histogram::default();
+    /// for ns in [100, 110, 120, 5000] {
+    ///     h.record(ns);
+    /// }
+    /// assert_eq!(h.quantile_ns(0.5), Some(127));
+    /// assert_eq!(h.quantile_ns(1.0), Some(5000));
+    /// assert_eq!(Histogram::default().quantile_ns(0.5), None);
+    /// ```
+    #[must_use]
+    pub fn quantile_ns(&self, q: f64) -> Option<u64> {
+        if self.samples == 0 {
+            return None;
+        }
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss
+        )]
+        let rank = ((q.clamp(0.0, 1.0) * self.samples as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        let i = self.buckets.iter().position(|n| {
+            seen += n;
+            rank <= seen
+        })?;
+        let bound = u32::try_from(i)
+            .ok()
+            .and_then(|i| 1_u64.checked_shl(i))
+            .map_or(u64::MAX, |b| b - 1);
+        Some(bound.min(self.max_ns))
+    }
+}
+
+/// Gets the share of the total timed body time that each thread in
+/// `threads` took, if any time was spent at all.
+///
+/// A thread with a far greater share than the others dominates the run.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::timing::{shares, Histogram};
+/// let mut fast = Histogram::default();
+/// fast.record(100);
+/// let mut slow = Histogram::default();
+/// slow.record(300);
+/// assert_eq!(shares(&[fast, slow]), Some(vec![0.25, 0.75]));
+/// ```
+#[must_use]
+pub fn shares(threads: &[Histogram]) -> Option<Vec<f64>> {
+    let total: u64 = threads.iter().map(|h| h.total_ns).sum();
+    #[allow(clippy::cast_precision_loss)]
+    (total != 0).then(|| {
+        threads
+            .iter()
+            .map(|h| h.total_ns as f64 / total as f64)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that merging is the same as recording everything in one
+    /// histogram.
+    #[test]
+    fn test_merge() {
+        let mut a = Histogram::default();
+        let mut b = Histogram::default();
+        let mut both = Histogram::default();
+        for ns in [3, 40, 500] {
+            a.record(ns);
+            both.record(ns);
+        }
+        for ns in [0, 6000] {
+            b.record(ns);
+            both.record(ns);
+        }
+        a.merge(&b);
+        assert_eq!(a, both);
+        assert_eq!(a.samples, 5);
+        assert_eq!(a.max_ns, 6000);
+        assert_eq!(a.buckets.len(), bucket(6000) + 1);
+        assert!(bucket(u64::MAX) < BUCKETS);
+    }
+}
@@ -0,0 +1,72 @@
+//! Transitions between the states of successive iterations.
+//!
+//! Most runs only care about which states occur, and how often; tests of
+//! protocol-like behaviour can also care about the order in which they occur.
+//! Runs can record, for each pair of states, how often an observed iteration
+//! showing the first state was followed by one showing the second.  These
+//! counts form a directed graph over the report's states, which the `dot`
+//! outputter draws.
+//!
+//! Only observed iterations take part, so a sampled run records transitions
+//! between the iterations it sampled, and runs merged together have no
+//! transitions across the join.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How often an observed iteration showing one state was followed by one
+/// showing another.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transition {
+    /// The index of the earlier state in the report's `states`.
+    pub from: usize,
+    /// The index of the later state in the report's `states`.
+    pub to: usize,
+    /// The number of times the transition occurred.
+    pub occurs: usize,
+}
+
+/// Makes transitions from counts keyed by the indices of their states,
+/// ordered by those indices.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::transition::{from_counts, Transition};
+///
+/// let ts = from_counts([((1, 0), 2), ((0, 1), 3)]);
+/// assert_eq!(ts, [Transition { from: 0, to: 1, occurs: 3 }, Transition { from: 1, to: 0, occurs: 2 }]);
+/// ```
+pub fn from_counts(counts: impl IntoIterator<Item = ((usize, usize), usize)>) -> Vec<Transition> {
+    let counts: BTreeMap<_, _> = counts.into_iter().collect();
+    counts
+        .into_iter()
+        .map(|((from, to), occurs)| Transition { from, to, occurs })
+        .collect()
+}
+
+/// Merges the transitions in `others`, from another report, into `mine`.
+///
+/// `remap` maps the state indices in `others` to those in `mine`'s report.
+/// Transitions between the same states sum their occurrences.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::model::transition::{merge, Transition};
+///
+/// let t = |from, to, occurs| Transition { from, to, occurs };
+/// let mut mine = vec![t(0, 1, 3)];
+/// merge(&mut mine, vec![t(1, 0, 2), t(0, 0, 1)], &[1, 0]);
+/// assert_eq!(mine, [t(0, 1, 5), t(1, 1, 1)]);
+/// ```
+pub fn merge(mine: &mut Vec<Transition>, others: Vec<Transition>, remap: &[usize]) {
+    let remap = |i: usize| remap.get(i).copied().unwrap_or(i);
+    let mut counts: BTreeMap<_, usize> =
+        mine.drain(..).map(|t| ((t.from, t.to), t.occurs)).collect();
+    for t in others {
+        let occurs = counts.entry((remap(t.from), remap(t.to))).or_default();
+        *occurs = occurs.saturating_add(t.occurs);
+    }
+    *mine = from_counts(counts);
+}
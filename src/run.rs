@@ -1,14 +1,41 @@
 //! The test runner itself, including controls over how it synchronises and
 //! halts.
+pub mod affinity;
+mod aggregate;
+pub mod alloc;
+mod atomics;
+pub mod bench;
+pub mod checkpoint;
 mod fsa;
+pub mod fuzz;
+pub mod guard;
 pub mod halt;
+mod handle;
 mod instance;
-mod obs;
+pub mod limit;
+pub mod marker;
+pub mod obs;
 pub mod permute;
+pub mod priority;
 pub mod runner;
-mod shared;
+pub mod seed;
+pub mod selftest;
+pub mod sequential;
+pub mod shared;
+pub mod shrink;
+pub mod skew;
+pub mod snapshot;
+pub mod stats;
+pub mod sweep;
 pub mod sync;
+mod task;
+#[cfg(test)]
+pub(crate) mod testing;
 mod thread;
+pub mod timing;
+mod verify;
 
+pub use handle::Handle;
 pub use permute::Permuter;
 pub use runner::{Builder, Runner};
+pub use task::Task;
@@ -0,0 +1,132 @@
+//! Restricting test threads to sets of CPUs.
+//!
+//! Each test thread restricts itself to the run's [`cpu::Set`] before it
+//! enters the automaton loop, so the whole set is shared between threads
+//! rather than each thread being pinned to one CPU.  Only Linux supports
+//! this at the moment.
+
+use crate::model::cpu;
+use std::io;
+
+crate::features::register! {
+    "runtime" => cfg!(target_os = "linux").then_some("cpu-affinity"),
+}
+
+/// Restricts the calling thread to run only on the CPUs in `cpus`.
+///
+/// # Errors
+///
+/// Fails if the operating system refuses the restriction, or if the
+/// platform doesn't support it.
+#[cfg(target_os = "linux")]
+pub fn restrict(cpus: &cpu::Set) -> io::Result<()> {
+    let mut set = unsafe { std::mem::zeroed::<libc::cpu_set_t>() };
+    let capacity = std::mem::size_of::<libc::cpu_set_t>() * 8;
+    for cpu in cpus.iter() {
+        if capacity <= cpu {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("CPU {cpu} is beyond the {capacity} that the platform can address"),
+            ));
+        }
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+    // Thread ID 0 means the calling thread.
+    let result = unsafe { libc::sched_setaffinity(0, std::mem::size_of_val(&set), &raw const set) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Restricts the calling thread to run only on the CPUs in `cpus`.
+///
+/// # Errors
+///
+/// Always fails, as the platform doesn't support restriction.
+#[cfg(not(target_os = "linux"))]
+pub fn restrict(_cpus: &cpu::Set) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "this platform can't restrict threads to CPUs",
+    ))
+}
+
+/// Gets the CPUs on which the calling thread may run, if the platform lets
+/// us.
+#[cfg(target_os = "linux")]
+#[must_use]
+pub fn available() -> Option<cpu::Set> {
+    let mut set = unsafe { std::mem::zeroed::<libc::cpu_set_t>() };
+    let result = unsafe { libc::sched_getaffinity(0, std::mem::size_of_val(&set), &raw mut set) };
+    let capacity = std::mem::size_of::<libc::cpu_set_t>() * 8;
+    (result == 0).then(|| {
+        (0..capacity)
+            .filter(|&cpu| unsafe { libc::CPU_ISSET(cpu, &set) })
+            .collect()
+    })
+}
+
+/// Gets the CPUs on which the calling thread may run, if the platform lets
+/// us.
+#[cfg(not(target_os = "linux"))]
+#[must_use]
+pub fn available() -> Option<cpu::Set> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::rust,
+        err,
+        run::testing::{capped_builder, nop_entry},
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Tests that a thread can restrict itself to one of its CPUs, and then
+    /// sees only that CPU as available.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_restrict() {
+        let first = available()
+            .and_then(|s| s.iter().next())
+            .expect("should have a CPU");
+        let cpus: cpu::Set = std::iter::once(first).collect();
+        std::thread::spawn(move || {
+            restrict(&cpus).expect("restriction should succeed");
+            assert_eq!(available(), Some(cpus));
+        })
+        .join()
+        .unwrap();
+    }
+
+    /// Tests that test threads run restricted to the requested CPUs, and that
+    /// reports record them.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_with_cpus_restricts_threads() -> err::Result<()> {
+        static UNRESTRICTED: AtomicUsize = AtomicUsize::new(0);
+
+        let first = available()
+            .and_then(|s| s.iter().next())
+            .expect("should have a CPU");
+        let cpus: cpu::Set = std::iter::once(first).collect();
+        let mut entry = nop_entry(2);
+        entry.test = |_, _: &rust::Env| {
+            if available().map_or(0, |s| s.len()) != 1 {
+                UNRESTRICTED.fetch_add(1, Ordering::Relaxed);
+            }
+        };
+        let report = capped_builder(entry, 10)
+            .with_cpus(Some(cpus.clone()))
+            .build()?
+            .run()?;
+
+        assert_eq!(UNRESTRICTED.load(Ordering::Relaxed), 0);
+        assert_eq!(report.cpus, Some(cpus));
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+//! Aggregation of observations off the observer's critical path.
+//!
+//! The observer only does per-iteration work that the halt rules and hooks
+//! need to see straight away.  It hands the rest (breaking occurrences down
+//! by rotation, and collecting the fuzzed initial valuations behind each
+//! state) to an [Aggregator], which can run on a separate consumer thread
+//! fed in batches through a bounded channel.  Test threads then spend less
+//! time waiting on the observer.  The thread hands emptied batches back for
+//! reuse, so that sending a batch needn't allocate the next.
+//!
+//! Records carry fuzzed initial valuations as identifiers, which the observer
+//! interns through [Initials]; this keeps the valuations, which only change
+//! when the fuzzer redraws them, from being cloned into every record.
+
+use crate::model::state;
+use crossbeam::channel;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    mem, thread,
+};
+
+/// The number of records the aggregator sends to its thread at once.
+const BATCH: usize = 1024;
+
+/// The number of batches that can be in flight before the observer blocks
+/// on the aggregator thread.
+const CAPACITY: usize = 8;
+
+/// A record of one observation, for aggregating.
+#[derive(Copy, Clone)]
+pub(super) struct Record {
+    /// The observer's identifier for the observed state.
+    pub id: usize,
+    /// The rotation in which the observation happened.
+    pub rotation: usize,
+    /// The identifier, from [Initials], of the fuzzed initial valuation
+    /// behind the observation, if any.
+    pub initials: Option<usize>,
+}
+
+/// An interner of fuzzed initial valuations.
+#[derive(Default)]
+pub(super) struct Initials {
+    /// The identifier of each valuation interned so far.
+    ids: HashMap<state::State, usize>,
+    /// The valuations interned so far, indexed by identifier.
+    valuations: Vec<state::State>,
+    /// The identifier of the valuation last interned, if any.
+    last: Option<usize>,
+}
+
+impl Initials {
+    /// Gets the identifier of `valuation`, interning it if it's new.
+    ///
+    /// Successive observations usually share a valuation, so this checks the
+    /// last one first, and only clones valuations it hasn't seen before.
+    pub fn intern(&mut self, valuation: &state::State) -> usize {
+        if let Some(id) = self.last.filter(|&id| self.valuations[id] == *valuation) {
+            return id;
+        }
+        let id = if let Some(&id) = self.ids.get(valuation) {
+            id
+        } else {
+            let id = self.valuations.len();
+            self.valuations.push(valuation.clone());
+            self.ids.insert(valuation.clone(), id);
+            id
+        };
+        self.last = Some(id);
+        id
+    }
+
+    /// Gets the valuations with identifiers `ids`.
+    pub fn resolve(&self, ids: impl IntoIterator<Item = usize>) -> Vec<state::State> {
+        ids.into_iter()
+            .map(|id| self.valuations[id].clone())
+            .collect()
+    }
+}
+
+/// The aggregated form of a stream of [Record]s, indexed by state identifier.
+#[derive(Default)]
+pub(super) struct Aggregate {
+    /// The number of times each state occurred in each rotation.
+    by_rotation: Vec<BTreeMap<usize, usize>>,
+    /// The identifiers of the distinct fuzzed initial valuations that led to
+    /// each state.
+    initials: Vec<BTreeSet<usize>>,
+}
+
+impl Aggregate {
+    fn add(&mut self, record: Record) {
+        if self.by_rotation.len() <= record.id {
+            self.by_rotation.resize_with(record.id + 1, BTreeMap::new);
+        }
+        let n = self.by_rotation[record.id]
+            .entry(record.rotation)
+            .or_default();
+        *n = n.saturating_add(1);
+
+        if let Some(initials) = record.initials {
+            if self.initials.len() <= record.id {
+                self.initials.resize_with(record.id + 1, BTreeSet::new);
+            }
+            self.initials[record.id].insert(initials);
+        }
+    }
+
+    /// Takes the per-rotation counts and the identifiers of the initial
+    /// valuations of state `id`.
+    pub fn take(&mut self, id: usize) -> (BTreeMap<usize, usize>, Vec<usize>) {
+        let counts = self.by_rotation.get_mut(id).map(mem::take);
+        let initials = self.initials.get_mut(id).map(mem::take);
+        (
+            counts.unwrap_or_default(),
+            initials
+                .map(|x| x.into_iter().collect())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+/// Something that aggregates [Record]s, either inline or on its own thread.
+pub(super) enum Aggregator {
+    /// Aggregating on the calling thread.
+    Inline(Aggregate),
+    /// Aggregating on a separate thread.
+    Threaded {
+        /// The records not yet sent to the thread.
+        batch: Vec<Record>,
+        /// The channel to the thread.
+        tx: channel::Sender<Vec<Record>>,
+        /// The channel on which the thread hands back emptied batches.
+        spare: channel::Receiver<Vec<Record>>,
+        /// The thread, which returns its aggregate once the channel closes.
+        thread: thread::JoinHandle<Aggregate>,
+    },
+}
+
+impl Default for Aggregator {
+    fn default() -> Self {
+        Self::Inline(Aggregate::default())
+    }
+}
+
+impl Aggregator {
+    /// Constructs an aggregator on its own thread.
+    ///
+    /// Falls back to aggregating inline if we can't spawn the thread.
+    pub fn spawn() -> Self {
+        let (tx, rx) = channel::bounded::<Vec<Record>>(CAPACITY);
+        let (spare_tx, spare) = channel::bounded::<Vec<Record>>(CAPACITY);
+        let spawned = thread::Builder::new()
+            .name("phph-aggregate".to_owned())
+            .spawn(move || {
+                let mut aggregate = Aggregate::default();
+                for mut batch in rx {
+                    for record in batch.drain(..) {
+                        aggregate.add(record);
+                    }
+                    // If the observer already has enough spares, drop this.
+                    let _ = spare_tx.try_send(batch);
+                }
+                aggregate
+            });
+        spawned.map_or_else(
+            |_| Self::default(),
+            |thread| Self::Threaded {
+                batch: Vec::with_capacity(BATCH),
+                tx,
+                spare,
+                thread,
+            },
+        )
+    }
+
+    /// Aggregates `record`.
+    pub fn record(&mut self, record: Record) {
+        match self {
+            Self::Inline(aggregate) => aggregate.add(record),
+            Self::Threaded {
+                batch, tx, spare, ..
+            } => {
+                batch.push(record);
+                if BATCH <= batch.len() {
+                    let next = spare
+                        .try_recv()
+                        .unwrap_or_else(|_| Vec::with_capacity(BATCH));
+                    // If the thread has gone, it panicked, and `finish` will
+                    // pass on the panic.
+                    let _ = tx.send(mem::replace(batch, next));
+                }
+            }
+        }
+    }
+
+    /// Finishes aggregating, returning the aggregate.
+    ///
+    /// # Panics
+    ///
+    /// Passes on any panic from the aggregator thread.
+    pub fn finish(self) -> Aggregate {
+        match self {
+            Self::Inline(aggregate) => aggregate,
+            Self::Threaded {
+                batch, tx, thread, ..
+            } => {
+                let _ = tx.send(batch);
+                drop(tx);
+                thread
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> impl Iterator<Item = Record> {
+        (0..BATCH * 3 + 7).map(|i| Record {
+            id: i % 3,
+            rotation: i % 2,
+            initials: (i % 3 == 0).then_some(i % 4),
+        })
+    }
+
+    /// Tests that threaded and inline aggregation agree, including on records
+    /// left over from the last full batch.
+    #[test]
+    fn test_threaded_matches_inline() {
+        let mut inline = Aggregator::default();
+        let mut threaded = Aggregator::spawn();
+        assert!(matches!(threaded, Aggregator::Threaded { .. }));
+        for (a, b) in records().zip(records()) {
+            inline.record(a);
+            threaded.record(b);
+        }
+        let mut inline = inline.finish();
+        let mut threaded = threaded.finish();
+        for id in 0..4 {
+            assert_eq!(inline.take(id), threaded.take(id));
+        }
+        let (counts, initials) = inline.take(0);
+        assert!(counts.is_empty(), "take should empty the aggregate");
+        assert!(initials.is_empty());
+        let (counts, _) = threaded.take(1);
+        assert!(counts.is_empty());
+    }
+
+    /// Tests that interning gives each distinct valuation one identifier,
+    /// whether or not it was the last one interned.
+    #[test]
+    fn test_initials_intern() {
+        let valuation =
+            |x| -> state::State { std::iter::once(("x".into(), state::Value::I32(x))).collect() };
+        let mut initials = Initials::default();
+        let ids: Vec<_> = [1, 1, 2, 1, 2, 2]
+            .into_iter()
+            .map(|x| initials.intern(&valuation(x)))
+            .collect();
+        assert_eq!(ids, [0, 0, 1, 0, 1, 1]);
+        assert_eq!(initials.resolve([1, 0]), [valuation(2), valuation(1)]);
+    }
+}
@@ -0,0 +1,138 @@
+//! The global allocator, and counting of heap allocations, for auditing the
+//! runner's hot path (see [`crate::model::allocation`]).
+//!
+//! By default, phenolphthalein uses the system allocator.  The `jemalloc`
+//! and `mimalloc` features swap it for those allocators, which can handle
+//! the observer's allocations faster on some platforms; [NAME] says which
+//! one this build uses, and `cargo bench` measures the difference.
+//!
+//! With the `alloc-audit` feature, phenolphthalein installs [Counting] as the
+//! global allocator, which counts the allocations that each thread makes
+//! before passing them on to the selected allocator; runners can then count
+//! the allocations made while handling each iteration, by taking the
+//! difference in the observing thread's count.  Counting costs a
+//! thread-local increment on every allocation, so the feature is for
+//! debugging builds only.
+
+#[cfg(all(feature = "jemalloc", feature = "mimalloc"))]
+compile_error!("the `jemalloc` and `mimalloc` features are mutually exclusive");
+
+/// The name of the global allocator that this build uses.
+pub const NAME: &str = if cfg!(feature = "jemalloc") {
+    "jemalloc"
+} else if cfg!(feature = "mimalloc") {
+    "mimalloc"
+} else {
+    "system"
+};
+
+/// Whether this build counts allocations.
+pub const AVAILABLE: bool = cfg!(feature = "alloc-audit");
+
+crate::features::register! {
+    "allocator" => [NAME],
+    "runtime" => AVAILABLE.then_some("alloc-audit"),
+}
+
+#[cfg(feature = "jemalloc")]
+use tikv_jemallocator::Jemalloc as Selected;
+
+#[cfg(all(feature = "mimalloc", not(feature = "jemalloc")))]
+use mimalloc::MiMalloc as Selected;
+
+#[cfg(all(
+    feature = "alloc-audit",
+    not(any(feature = "jemalloc", feature = "mimalloc"))
+))]
+use std::alloc::System as Selected;
+
+#[cfg(all(
+    not(feature = "alloc-audit"),
+    any(feature = "jemalloc", feature = "mimalloc")
+))]
+#[global_allocator]
+static ALLOCATOR: Selected = Selected;
+
+/// Gets the number of allocations that the current thread has made so far.
+///
+/// This is always 0 unless this build counts allocations (see
+/// [`AVAILABLE`]); only differences between counts mean anything.
+#[must_use]
+pub fn allocations() -> usize {
+    #[cfg(feature = "alloc-audit")]
+    {
+        counting::ALLOCATIONS
+            .try_with(std::cell::Cell::get)
+            .unwrap_or(0)
+    }
+    #[cfg(not(feature = "alloc-audit"))]
+    {
+        0
+    }
+}
+
+#[cfg(feature = "alloc-audit")]
+pub use counting::Counting;
+
+#[cfg(feature = "alloc-audit")]
+mod counting {
+    use super::Selected;
+    use std::{
+        alloc::{GlobalAlloc, Layout},
+        cell::Cell,
+    };
+
+    thread_local! {
+        /// The number of allocations that this thread has made so far.
+        ///
+        /// This needs no allocation of its own to set up, and no destructor,
+        /// so the allocator can touch it at any point in a thread's life.
+        pub(super) static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// A global allocator that counts each thread's allocations, passing them
+    /// on to the selected allocator (see [`super::NAME`]).
+    ///
+    /// Reallocations count as allocations, as they can move the block.
+    pub struct Counting;
+
+    #[global_allocator]
+    static ALLOCATOR: Counting = Counting;
+
+    fn count() {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get().wrapping_add(1)));
+    }
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count();
+            unsafe { Selected.alloc(layout) }
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            count();
+            unsafe { Selected.alloc_zeroed(layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count();
+            unsafe { Selected.realloc(ptr, layout, new_size) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { Selected.dealloc(ptr, layout) }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        /// Tests that allocating on this thread bumps its count.
+        #[test]
+        fn test_counts_allocations() {
+            let before = super::super::allocations();
+            let v = std::hint::black_box(vec![1u8; 16]);
+            assert!(before < super::super::allocations());
+            drop(v);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! The atomics layer beneath the runner's own synchronisation.
+//!
+//! The synchronisers, halt signals, and automata reach their atomics, locks,
+//! and shared cells through this module rather than `std`.  Building with the
+//! `loom` feature swaps these for `loom`'s model-checked versions, so that
+//! `loom` can explore the interleavings of the runner's unsafe handoffs of
+//! shared state between threads.
+//!
+//! Under `loom`, these primitives only work inside `loom::model`, so builds
+//! with the feature should only run the `loom` tests:
+//!
+//! ```text
+//! cargo test --release --features loom loom_
+//! ```
+
+#[cfg(feature = "loom")]
+pub use loom::{
+    hint::spin_loop,
+    sync::{
+        atomic::{AtomicIsize, AtomicU32, AtomicU8, AtomicUsize},
+        Condvar, Mutex,
+    },
+    thread::yield_now,
+};
+#[cfg(not(feature = "loom"))]
+pub use std::{
+    hint::spin_loop,
+    sync::{
+        atomic::{AtomicIsize, AtomicU32, AtomicU8, AtomicUsize},
+        Condvar, Mutex,
+    },
+    thread::yield_now,
+};
+
+/// A cell whose contents the runner shares between threads, relying on its
+/// own synchronisation for exclusive access.
+///
+/// Access goes through closures, rather than raw pointers, so that `loom`
+/// can tell when each access begins and ends.
+pub struct UnsafeCell<T>(
+    #[cfg(feature = "loom")] loom::cell::UnsafeCell<T>,
+    #[cfg(not(feature = "loom"))] std::cell::UnsafeCell<T>,
+);
+
+impl<T> UnsafeCell<T> {
+    /// Constructs a cell holding `value`.
+    pub fn new(value: T) -> Self {
+        #[cfg(feature = "loom")]
+        return Self(loom::cell::UnsafeCell::new(value));
+        #[cfg(not(feature = "loom"))]
+        Self(std::cell::UnsafeCell::new(value))
+    }
+
+    /// Takes the contents out of the cell.
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+
+    /// Gives `f` shared access to the contents of the cell.
+    ///
+    /// # Safety
+    ///
+    /// Nothing may mutate the contents while `f` runs.
+    pub unsafe fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        #[cfg(feature = "loom")]
+        return self.0.with(|p| f(&*p));
+        #[cfg(not(feature = "loom"))]
+        f(&*self.0.get())
+    }
+
+    /// Gives `f` exclusive access to the contents of the cell.
+    ///
+    /// # Safety
+    ///
+    /// Nothing else may access the contents while `f` runs.
+    pub unsafe fn with_mut<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        #[cfg(feature = "loom")]
+        return self.0.with_mut(|p| f(&mut *p));
+        #[cfg(not(feature = "loom"))]
+        f(&mut *self.0.get())
+    }
+}
@@ -0,0 +1,111 @@
+//! Benchmarking of synchroniser overhead.
+//!
+//! The benchmark runs a no-op test body through the usual FSA machinery for
+//! a fixed duration, and measures how many iterations it gets through.  As the
+//! test body does nothing, this is mostly the cost of synchronisation and
+//! observation.
+
+use super::{halt, sync, Builder};
+use crate::{api::rust, err, model};
+use std::{
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
+
+/// The result of benchmarking a synchroniser.
+#[derive(Copy, Clone, Debug)]
+pub struct Measurement {
+    /// The number of iterations completed.
+    pub iterations: usize,
+    /// The wall-clock time taken to complete the iterations.
+    pub elapsed: Duration,
+}
+
+impl Measurement {
+    /// Gets the number of iterations per second in this measurement.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::run::bench::Measurement;
+    /// use std::time::Duration;
+    /// let m = Measurement { iterations: 500, elapsed: Duration::from_millis(250) };
+    /// assert!((m.rate() - 2000.0).abs() < f64::EPSILON);
+    /// ```
+    #[must_use]
+    pub fn rate(&self) -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        let iterations = self.iterations as f64;
+        iterations / self.elapsed.as_secs_f64()
+    }
+}
+
+/// Makes a no-op test entry with `n_threads` threads and no variables.
+#[must_use]
+pub fn nop_entry(n_threads: NonZeroUsize) -> rust::Static {
+    rust::Static {
+        manifest: model::Manifest {
+            n_threads,
+            i32s: model::manifest::VarMap::new(),
+            bools: model::manifest::VarMap::new(),
+            f64s: model::manifest::VarMap::new(),
+            scalable_role: None,
+            symmetry: vec![],
+            invariants: model::invariant::Map::new(),
+            aux_flags: vec![],
+            declared: vec![],
+        },
+        test: |_, _| {},
+        test_at: None,
+        check: None,
+        check_description: None,
+    }
+}
+
+/// Benchmarks the synchroniser made by `sync` over `n_threads` threads, for
+/// roughly `duration`.
+///
+/// # Errors
+///
+/// Fails if the runner fails to build or run.
+pub fn measure(
+    sync: sync::Factory,
+    n_threads: NonZeroUsize,
+    duration: Duration,
+) -> err::Result<Measurement> {
+    let (rule, mut callback) = halt::Rule::on_callback(halt::Type::Exit, "timeout");
+    let runner = Builder::new(nop_entry(n_threads))
+        .with_sync(sync)
+        .add_halt_rules(std::iter::once(rule))
+        .build()?;
+
+    let start = Instant::now();
+    let timer = std::thread::spawn(move || {
+        std::thread::sleep(duration);
+        callback();
+    });
+    let report = runner.run()?;
+    let elapsed = start.elapsed();
+    timer
+        .join()
+        .map_err(|p| err::Error::ThreadPanic(err::Panic::from_payload(None, &*p)))?;
+
+    Ok(Measurement {
+        iterations: report.states.iter().map(|s| s.info.occurs).sum(),
+        elapsed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that a short benchmark completes at least one iteration.
+    #[test]
+    fn test_measure_barrier() -> err::Result<()> {
+        let n = NonZeroUsize::new(2).unwrap();
+        let m = measure(Box::new(sync::make_barrier), n, Duration::from_millis(10))?;
+        assert!(0 < m.iterations);
+        Ok(())
+    }
+}
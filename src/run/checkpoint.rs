@@ -0,0 +1,304 @@
+//! Periodic checkpoints of a run's observations.
+//!
+//! A run that dies without producing a report, whether to a `SIGKILL` or to
+//! a test body that crashes the whole process, would otherwise lose
+//! everything it observed.  With checkpointing on, the observer writes a
+//! partial report (see [`super::obs::Observer::partial_report`]) to a file
+//! every so often, in the same JSON form as any other report, so that a
+//! later invocation can pick it up and merge it (see
+//! [`model::Report::merge`]) into its own report.
+//!
+//! A run that produces a report, even a partial one from an aborted run,
+//! removes its checkpoint; a leftover checkpoint always means a run died.
+//! A run that finds a leftover checkpoint at its own checkpoint path resumes
+//! from it: its checkpoints, and its final report, fold in everything that
+//! the dead run observed.
+
+use crate::{err, model};
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// A rule for checkpointing a run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    /// The file to which to write checkpoints.
+    pub path: PathBuf,
+    /// How long to leave between checkpoints.
+    pub every: Duration,
+}
+
+impl Rule {
+    /// Makes sure that the directory for the checkpoint file exists.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the directory doesn't exist and can't be created.
+    pub fn prepare(&self) -> err::Result<()> {
+        let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(dir).map_err(|e| err::Error::BadCheckpoint {
+            path: self.path.clone(),
+            reason: e.to_string(),
+        })
+    }
+}
+
+/// Reads the checkpoint, if any, that a dead run left at `path`.
+///
+/// # Errors
+///
+/// Fails if there is a checkpoint, but it can't be read, isn't a valid
+/// report, or follows a newer schema than this build understands.
+pub fn read(path: &Path) -> err::Result<Option<model::Report>> {
+    let bad = |reason: String| err::Error::BadCheckpoint {
+        path: path.to_owned(),
+        reason,
+    };
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(bad(format!("couldn't read leftover checkpoint: {e}"))),
+    };
+    let report: model::Report = serde_json::from_reader(io::BufReader::new(file))
+        .map_err(|e| bad(format!("couldn't parse leftover checkpoint: {e}")))?;
+    if model::report::schema::VERSION < report.schema_version {
+        return Err(bad(format!(
+            "leftover checkpoint has newer schema version {}",
+            report.schema_version
+        )));
+    }
+    Ok(Some(report))
+}
+
+/// Removes the checkpoint, if any, at `path`.
+///
+/// # Errors
+///
+/// Fails if there is a checkpoint, but it can't be removed.
+pub fn remove(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Writes checkpoints for a run, as its rule says.
+pub struct Writer {
+    /// The rule for the checkpoints.
+    rule: Rule,
+    /// The parts of the report that the runner, rather than the observer,
+    /// fills in, ready to stamp onto each checkpoint.
+    stamp: model::Report,
+    /// The checkpoint, if any, of a dead run from which this run resumes.
+    resumed: Option<model::Report>,
+    /// When the last checkpoint was written, or the run began.
+    last: Instant,
+}
+
+impl Writer {
+    /// Constructs a writer for `rule`, which stamps the metadata in `stamp`
+    /// onto each checkpoint, and folds each into `resumed` if given.
+    ///
+    /// The first checkpoint falls due one interval from now.
+    #[must_use]
+    pub fn new(rule: Rule, stamp: model::Report, resumed: Option<model::Report>) -> Self {
+        Self {
+            rule,
+            stamp,
+            resumed,
+            last: Instant::now(),
+        }
+    }
+
+    /// Gets the path of the checkpoint file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.rule.path
+    }
+
+    /// Gets whether the next checkpoint is due.
+    #[must_use]
+    pub fn is_due(&self) -> bool {
+        self.rule.every <= self.last.elapsed()
+    }
+
+    /// Writes `report`, stamped with the runner's metadata, as the latest
+    /// checkpoint.
+    ///
+    /// Failing to write a checkpoint doesn't stop the run, so this only logs
+    /// failures.
+    pub fn write(&mut self, mut report: model::Report) {
+        self.last = Instant::now();
+        report.capabilities.clone_from(&self.stamp.capabilities);
+        report
+            .check_description
+            .clone_from(&self.stamp.check_description);
+        report.cpus.clone_from(&self.stamp.cpus);
+        report.roles.clone_from(&self.stamp.roles);
+        report.var_order.clone_from(&self.stamp.var_order);
+        report.provenance.clone_from(&self.stamp.provenance);
+        let report = self.resume(report);
+        match self.replace(&report) {
+            Ok(()) => tracing::debug!(states = report.states.len(), "checkpoint written"),
+            Err(e) => tracing::warn!(
+                path = %self.rule.path.display(),
+                error = %e,
+                "couldn't write checkpoint"
+            ),
+        }
+    }
+
+    /// Folds the run's final report, `report`, into any resumed checkpoint,
+    /// and removes the checkpoint file, as a report now exists.
+    #[must_use]
+    pub fn finish(self, report: model::Report) -> model::Report {
+        self.remove();
+        match self.resumed {
+            Some(resumed) => resumed_with(resumed, report),
+            None => report,
+        }
+    }
+
+    /// Folds `report` into a copy of the resumed checkpoint, if any.
+    fn resume(&self, report: model::Report) -> model::Report {
+        match &self.resumed {
+            Some(resumed) => resumed_with(resumed.clone(), report),
+            None => report,
+        }
+    }
+
+    /// Removes the checkpoint file, if any.
+    fn remove(&self) {
+        if let Err(e) = remove(&self.rule.path) {
+            tracing::warn!(
+                path = %self.rule.path.display(),
+                error = %e,
+                "couldn't remove checkpoint"
+            );
+        }
+    }
+
+    /// Replaces the checkpoint file with `report`.
+    ///
+    /// This writes to a temporary file first, so that dying mid-write leaves
+    /// the previous checkpoint intact.
+    fn replace(&self, report: &model::Report) -> io::Result<()> {
+        let mut temp = self.rule.path.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+        let file = std::fs::File::create(&temp)?;
+        let mut w = io::BufWriter::new(file);
+        serde_json::to_writer(&mut w, report)?;
+        w.flush()?;
+        std::fs::rename(&temp, &self.rule.path)
+    }
+}
+
+/// Merges `report` into `resumed`, the checkpoint of a dead run.
+///
+/// The merged report stands for this run, extended by the dead one, so it
+/// keeps this run's provenance and reason for ending.
+fn resumed_with(mut resumed: model::Report, report: model::Report) -> model::Report {
+    let provenance = report.provenance.clone();
+    let ended_by = report.ended_by.clone();
+    resumed.merge(report);
+    resumed.provenance = provenance;
+    resumed.ended_by = ended_by;
+    resumed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::rust,
+        run::{
+            testing::{every, nop_entry},
+            Builder,
+        },
+    };
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    /// Tests that runs write checkpoints of their observations as they go,
+    /// stamped like the final report, and remove them once they finish.
+    #[test]
+    fn test_with_checkpoint() -> err::Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "phph-test-{}.checkpoint.json",
+            model::provenance::new_run_id()
+        ));
+        let checkpointed = Arc::new(AtomicUsize::new(0));
+        let entry = nop_entry(1);
+        let entry = rust::Static {
+            check_description: Some("nothing"),
+            ..entry
+        };
+        let cond = every(10);
+        let report = {
+            let (path, checkpointed) = (path.clone(), checkpointed.clone());
+            Builder::new(entry)
+                .add_halt_rules(std::iter::once(cond.exit()))
+                .with_checkpoint(Some(Rule {
+                    path: path.clone(),
+                    every: Duration::ZERO,
+                }))
+                .with_hook(move |_, summary| {
+                    // Each observation checkpoints after its hooks, so this
+                    // sees the checkpoint of the one before.
+                    if summary.iterations == 5 {
+                        let file = std::fs::File::open(&path).expect("checkpoint should exist");
+                        let report: model::Report =
+                            serde_json::from_reader(file).expect("checkpoint should parse");
+                        assert_eq!(report.check_description.as_deref(), Some("nothing"));
+                        let n: usize = report.states.iter().map(|s| s.info.occurs).sum();
+                        checkpointed.store(n, Ordering::Relaxed);
+                    }
+                })
+                .build()?
+                .run()?
+        };
+        assert_eq!(checkpointed.load(Ordering::Relaxed), 4);
+        assert_eq!(
+            report.states.iter().map(|s| s.info.occurs).sum::<usize>(),
+            10
+        );
+        assert!(
+            !path.exists(),
+            "finished runs should remove their checkpoints"
+        );
+        Ok(())
+    }
+
+    /// Tests that runs resume from checkpoints that dead runs left, merging
+    /// them into their reports.
+    #[test]
+    fn test_resume_from_checkpoint() -> err::Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "phph-test-{}.checkpoint.json",
+            model::provenance::new_run_id()
+        ));
+        let entry = nop_entry(1);
+        let cond = every(10);
+        let builder = Builder::new(entry).add_halt_rules(std::iter::once(cond.exit()));
+        let occurs = |r: &model::Report| r.states.iter().map(|s| s.info.occurs).sum::<usize>();
+
+        let dead = builder.build()?.run()?;
+        std::fs::write(&path, serde_json::to_string(&dead).unwrap())?;
+        let builder = builder.with_checkpoint(Some(Rule {
+            path: path.clone(),
+            every: Duration::from_secs(30),
+        }));
+        let report = builder.build()?.run()?;
+        assert_eq!(occurs(&report), 20);
+        assert_eq!(report.ended_by, dead.ended_by);
+        assert!(!path.exists(), "resumed runs should remove the checkpoint");
+        Ok(())
+    }
+}
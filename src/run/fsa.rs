@@ -1,8 +1,20 @@
 //! The main testing finite state automaton, and helper functions for it.
 
-use super::{halt, permute::HasTid, shared, sync};
-use crate::{api::abs::Entry, err};
-use std::{cell::UnsafeCell, sync::Arc};
+use super::{
+    atomics::{Mutex, UnsafeCell},
+    guard, halt,
+    permute::HasTid,
+    priority, shared, skew, sync,
+};
+use crate::{
+    api::abs::Entry,
+    err,
+    model::{cpu, priority::Priority},
+};
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::Arc,
+};
 
 /// An automaton, parametrised over its current state's phantom type.
 pub struct Automaton<'entry, S: State, T: Entry<'entry>> {
@@ -25,11 +37,27 @@ pub struct Automaton<'entry, S: State, T: Entry<'entry>> {
     /// Points to the synchroniser used to keep automata in valid states.
     sync: Arc<dyn sync::Synchroniser>,
 
+    /// The delays with which each thread enters each iteration.
+    skew: Arc<skew::Table>,
+
+    /// The CPUs, if any, to which the automaton's thread should restrict
+    /// itself.
+    cpus: Option<Arc<cpu::Set>>,
+
+    /// The scheduling priorities that each thread should give itself.
+    priorities: Arc<priority::Table>,
+
     /// Set to rotate when an observer thread has decided the test should
     /// rotate its threads, and exit when it decides the test should
     /// be stopped; once set to either, all threads will stop the test the next
     /// time they try to run the test.
     halt_signal: Arc<halt::Signal>,
+
+    /// Holds the first panic caught from any automaton's test body or
+    /// observation.
+    ///
+    /// Once this is set, the automata stop observing and head for the exit.
+    panic: Arc<Mutex<Option<err::Panic>>>,
 }
 
 /// Automata always have a thread ID associated.
@@ -45,6 +73,30 @@ impl<'entry, S: State, E: Entry<'entry>> Automaton<'entry, S, E> {
         self.halt_signal.clone()
     }
 
+    /// Takes the first panic caught from a test body or observation, if any.
+    pub fn take_panic(&self) -> Option<err::Panic> {
+        // If the lock is poisoned, we're in deeper trouble than a test panic.
+        self.panic.lock().ok().and_then(|mut p| p.take())
+    }
+
+    /// Gets whether any automaton has caught a panic.
+    fn has_panicked(&self) -> bool {
+        self.panic.lock().map_or(true, |p| p.is_some())
+    }
+
+    /// Records a panic caught from `what` (for instance, the test body).
+    ///
+    /// This doesn't set the halt signal: a thread that saw it mid-round would
+    /// leave without synchronising, and the panicking thread would wait for
+    /// it forever.  Instead, the next observer sees the panic and exits.
+    fn record_panic(&self, what: &str, payload: &(dyn std::any::Any + Send)) {
+        let panic = err::Panic::from_payload(Some(self.tid), payload);
+        tracing::warn!(tid = self.tid, what, %panic, "caught panic");
+        if let Ok(mut slot) = self.panic.lock() {
+            slot.get_or_insert(panic);
+        }
+    }
+
     /// Pulls the tester state out of an inner handle.
     ///
     /// This is safe, but can fail if more than one `Inner` exists at this
@@ -56,12 +108,16 @@ impl<'entry, S: State, E: Entry<'entry>> Automaton<'entry, S, E> {
 
     unsafe fn change_state<T: State>(self) -> Automaton<'entry, T, E> {
         Automaton {
-            state: std::marker::PhantomData::default(),
+            state: std::marker::PhantomData,
             tid: self.tid,
             tester_state: self.tester_state,
             entry: self.entry,
             sync: self.sync,
+            skew: self.skew,
+            cpus: self.cpus,
+            priorities: self.priorities,
             halt_signal: self.halt_signal,
+            panic: self.panic,
         }
     }
 }
@@ -117,15 +173,71 @@ impl<'entry, E: Entry<'entry>> ReadyAutomaton<'entry, E> {
         sync: Arc<dyn sync::Synchroniser>,
     ) -> Self {
         Automaton {
-            state: std::marker::PhantomData::default(),
+            state: std::marker::PhantomData,
             tid,
             sync,
+            skew: Arc::new(skew::Table::default()),
+            cpus: None,
+            priorities: Arc::new(priority::Table::default()),
             halt_signal: Arc::new(halt::Signal::default()),
+            panic: Arc::new(Mutex::new(None)),
             tester_state: Arc::new(UnsafeCell::new(tester_state)),
             entry,
         }
     }
 
+    /// Makes this automaton, and any cloned from it, delay entering each
+    /// iteration according to `skew`.
+    #[must_use]
+    pub fn with_skew(self, skew: skew::Table) -> Self {
+        Self {
+            skew: Arc::new(skew),
+            ..self
+        }
+    }
+
+    /// Makes this automaton, and any cloned from it, synchronise through
+    /// `sync`.
+    ///
+    /// As the automata cloned from this one share its synchroniser, this
+    /// should only happen while no others are running.
+    #[must_use]
+    pub fn with_sync(self, sync: Arc<dyn sync::Synchroniser>) -> Self {
+        Self { sync, ..self }
+    }
+
+    /// Makes this automaton, and any cloned from it, ask to have its thread
+    /// restricted to `cpus`, if given.
+    #[must_use]
+    pub fn with_cpus(self, cpus: Option<cpu::Set>) -> Self {
+        Self {
+            cpus: cpus.map(Arc::new),
+            ..self
+        }
+    }
+
+    /// Makes this automaton, and any cloned from it, ask to have its thread
+    /// run at the priority that `priorities` gives its thread ID.
+    #[must_use]
+    pub fn with_priorities(self, priorities: priority::Table) -> Self {
+        Self {
+            priorities: Arc::new(priorities),
+            ..self
+        }
+    }
+
+    /// Gets the scheduling priority, if any, that this automaton's thread
+    /// should give itself before starting.
+    pub fn priority(&self) -> Option<Priority> {
+        self.priorities.get(self.tid)
+    }
+
+    /// Gets the CPUs, if any, to which this automaton's thread should
+    /// restrict itself before starting.
+    pub fn cpus(&self) -> Option<Arc<cpu::Set>> {
+        self.cpus.clone()
+    }
+
     /// Produces a vector of automata with thread IDs from 0 up to this
     /// automaton's thread ID.
     ///
@@ -150,7 +262,11 @@ impl<'entry, E: Entry<'entry>> ReadyAutomaton<'entry, E> {
             state: self.state,
             tid: new_tid,
             sync: self.sync.clone(),
+            skew: self.skew.clone(),
+            cpus: self.cpus.clone(),
+            priorities: self.priorities.clone(),
             halt_signal: self.halt_signal.clone(),
+            panic: self.panic.clone(),
             tester_state: self.tester_state.clone(),
             entry: self.entry.clone(),
         }
@@ -190,13 +306,23 @@ impl<'entry, E: Entry<'entry>> Automaton<'entry, Running, E> {
     /// Runs a single iteration of this automaton.
     fn step(self) -> RunOutcome<'entry, E> {
         if let Some(halt_type) = self.halt_signal.get() {
+            tracing::debug!(tid = self.tid, r#type = %halt_type, "thread halted");
             return RunOutcome::Done(Done {
                 tid: self.tid,
                 halt_type,
             });
         }
 
-        unsafe { self.run_entry() };
+        unsafe { self.sit_out_pause() };
+        self.skew.delay(self.tid);
+        // If the test body panics, we still need to take part in
+        // synchronisation, or the other threads will wait on us forever.
+        if let Err(payload) = catch_unwind(AssertUnwindSafe(|| unsafe { self.run_entry() })) {
+            self.record_panic("test body", &*payload);
+        }
+        // A body that forked may have returned in the child, which has no
+        // other threads to synchronise with.
+        guard::leave_if_child();
         match self.sync.run() {
             sync::Role::Observer => RunOutcome::Observe(unsafe { self.change_state() }),
             sync::Role::Waiter => RunOutcome::Wait(unsafe { self.change_state() }),
@@ -209,9 +335,83 @@ impl<'entry, E: Entry<'entry>> Automaton<'entry, Running, E> {
     /// by safe code (in [Observing]s), and we rely on the [Inner]'s owning
     /// state structs (eg [Running]) to implement the right form of
     /// synchronisation.
+    ///
+    /// The iteration position comes from the observer, which only changes it
+    /// while all threads are synchronised.
+    ///
+    /// Timing doesn't include any skew delay, and skips warmup iterations.
     unsafe fn run_entry(&self) {
-        let env = &(*self.tester_state.get()).env.env;
-        self.entry.run(self.tid, env);
+        self.tester_state.with(|state| {
+            let position = state.observer.position();
+            let timer = state
+                .timing
+                .as_ref()
+                .filter(|_| !state.observer.is_warming_up())
+                .and_then(|t| Some((t, t.start(position.index)?)));
+            self.entry.run_at(self.tid, position, &state.env.env);
+            if let Some((recorder, start)) = timer {
+                recorder.record(self.tid, start.elapsed());
+            }
+        });
+    }
+
+    /// Sleeps until the end of any pause that the last observation began.
+    ///
+    /// Unsafe for the same reasons as [`Self::run_entry`]; the observer
+    /// only sets the pause while all threads are synchronised.
+    unsafe fn sit_out_pause(&self) {
+        let left = self.tester_state.with(|state| {
+            state
+                .paused_until
+                .and_then(|until| until.checked_duration_since(std::time::Instant::now()))
+        });
+        if let Some(left) = left {
+            std::thread::sleep(left);
+        }
+    }
+}
+
+/// Runs `automata` to completion on the current thread, without any
+/// synchroniser.
+///
+/// Each iteration runs the automata's test bodies one after another, in the
+/// order of thread IDs that `order` gives for that iteration's index, then
+/// observes the shared state.  This means each iteration shows one
+/// sequentially consistent interleaving of the test's threads.  Skew delays
+/// don't apply, as no thread can run late with respect to the others.
+///
+/// Returns `None` if there are no automata.
+pub fn run_in_sequence<'entry, E: Entry<'entry>>(
+    automata: impl IntoIterator<Item = ReadyAutomaton<'entry, E>>,
+    mut order: impl FnMut(usize, usize) -> Vec<usize>,
+) -> Option<Done> {
+    let mut automata: Vec<_> = automata.into_iter().map(ReadyAutomaton::start).collect();
+    automata.sort_by_key(|a| a.tid);
+    let first = automata.first()?;
+    loop {
+        if let Some(halt_type) = first.halt_signal.get() {
+            tracing::debug!(r#type = %halt_type, "sequence halted");
+            return Some(Done {
+                tid: first.tid,
+                halt_type,
+            });
+        }
+        // Nothing else touches the shared state while we hold every
+        // automaton on this thread.
+        unsafe { first.sit_out_pause() };
+        let index = unsafe { first.tester_state.with(|s| s.observer.position().index) };
+        for tid in order(index, automata.len()) {
+            let Some(automaton) = automata.get(tid) else {
+                continue;
+            };
+            if let Err(payload) =
+                catch_unwind(AssertUnwindSafe(|| unsafe { automaton.run_entry() }))
+            {
+                automaton.record_panic("test body", &*payload);
+            }
+            guard::leave_if_child();
+        }
+        unsafe { first.observe_shared_state() };
     }
 }
 
@@ -249,22 +449,14 @@ impl State for Observing {}
 
 impl<'entry, E: Entry<'entry>> Automaton<'entry, Observing, E> {
     /// Observes the shared state, returning back to a Running state.
-    fn observe(mut self) -> Automaton<'entry, Running, E> {
-        if let Some(kill_type) = self.shared_state().observe() {
-            self.halt(kill_type);
-        }
-        self.relinquish()
-    }
-
-    /// Borrows access to the shared state exposed by this `Observing`.
-    fn shared_state(&mut self) -> &mut shared::State<'entry, E::Env> {
+    fn observe(self) -> Automaton<'entry, Running, E> {
         /* This is safe provided that the FSA's synchroniser correctly
         guarantees only one automaton can be in the Observing state
-        at any given time, and remains in it for the duration of this
-        mutable borrow (note that relinquishing Observing requires
-        taking ownership of it). */
-
-        unsafe { &mut *self.tester_state.get() }
+        at any given time, and remains in it for the duration of the
+        observation (note that relinquishing Observing requires taking
+        ownership of it). */
+        unsafe { self.observe_shared_state() };
+        self.relinquish()
     }
 
     /// Relinquishes the ability to observe the environment, and returns to a
@@ -273,11 +465,31 @@ impl<'entry, E: Entry<'entry>> Automaton<'entry, Observing, E> {
         self.sync.obs();
         unsafe { self.change_state() }
     }
+}
 
-    /// Handles a halting condition by marking the test as halted
-    /// (halt state `state`).
-    fn halt(&mut self, state: halt::Type) {
-        self.halt_signal.set(state);
+impl<'entry, S: State, E: Entry<'entry>> Automaton<'entry, S, E> {
+    /// Observes the shared state, raising the halt signal if the observation
+    /// (or an earlier panic) calls for it.
+    ///
+    /// Unsafe because this borrows the shared state mutably; the caller must
+    /// make sure that no other automaton is using it, either by being in the
+    /// [Observing] state or by running every automaton on one thread.
+    unsafe fn observe_shared_state(&self) {
+        // The state after a panic is likely garbage, so we don't record it.
+        if !self.has_panicked() {
+            // Checkers, hooks, and checkpoints can panic too; if we let that
+            // unwind, the other threads would wait on us forever.
+            match catch_unwind(AssertUnwindSafe(|| {
+                self.tester_state.with_mut(shared::State::observe)
+            })) {
+                Ok(Some(kill_type)) => self.halt_signal.set(kill_type),
+                Ok(None) => {}
+                Err(payload) => self.record_panic("observation", &*payload),
+            }
+        }
+        if self.has_panicked() {
+            self.halt_signal.set(halt::Type::Exit);
+        }
     }
 }
 
@@ -294,3 +506,40 @@ impl HasTid for Done {
         self.tid
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::rust,
+        model,
+        run::testing::{capped_builder, entry_with_i32s, every},
+    };
+    use std::sync::atomic::Ordering;
+
+    /// Tests that iteration-aware test bodies see the position of each
+    /// iteration.
+    #[test]
+    fn test_run_at_sees_position() -> err::Result<()> {
+        let mut entry = entry_with_i32s(1, &[("x", true, None)]);
+        entry.test_at = Some(|_, iter, env: &rust::Env| {
+            let x = iter.rotation * 100 + iter.index;
+            env.i32s.atomic[0].store(i32::try_from(x).unwrap(), Ordering::Relaxed);
+        });
+
+        let report = capped_builder(entry, 6)
+            .add_halt_rules(std::iter::once(every(3).rotate()))
+            .build()?
+            .run()?;
+
+        let mut states = report.states;
+        states.sort_by_key(|s| s.info.iteration);
+        let xs: Vec<_> = states.iter().map(|s| s.state["x"]).collect();
+        let want: Vec<_> = [0, 1, 2, 103, 104, 105]
+            .into_iter()
+            .map(model::state::Value::I32)
+            .collect();
+        assert_eq!(xs, want);
+        Ok(())
+    }
+}
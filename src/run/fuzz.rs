@@ -0,0 +1,233 @@
+//! Randomised fuzzing of initial values.
+//!
+//! A [Fuzzer] redraws the initial values of selected variables in a manifest
+//! each time the runner rotates, so that each rotation can start from a
+//! different initial valuation.
+
+use crate::{
+    err,
+    model::{fuzz, manifest, state},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Draws initial values for variables from their distributions.
+pub struct Fuzzer {
+    /// The distributions to draw from, paired with the manifest's names for
+    /// their variables.
+    spec: Vec<(manifest::VarName, fuzz::Distribution)>,
+    /// The random number generator used to draw values.
+    rng: StdRng,
+}
+
+impl Fuzzer {
+    /// Constructs a fuzzer for `spec`, checking it against `manifest`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `spec` mentions a variable that isn't in `manifest`, or gives
+    /// a variable a distribution that is malformed or can't produce values of
+    /// its type.
+    pub fn new(spec: fuzz::Spec, manifest: &manifest::Manifest) -> err::Result<Self> {
+        let mut vars = Vec::with_capacity(spec.len());
+        for (var, dist) in spec {
+            check(&var, dist, manifest)?;
+            vars.extend(manifest.var_name(&var).map(|name| (name, dist)));
+        }
+        Ok(Self {
+            spec: vars,
+            rng: StdRng::from_entropy(),
+        })
+    }
+
+    /// Makes this fuzzer draw from a generator seeded with `seed`, so that
+    /// fuzzers with the same seed draw the same values in the same order.
+    #[must_use]
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+
+    /// Redraws the initial values in `manifest` for each fuzzed variable.
+    ///
+    /// Returns the drawn valuation, which covers only the fuzzed variables.
+    pub fn fuzz(&mut self, manifest: &mut manifest::Manifest) -> state::State {
+        let mut initials = state::State::new();
+        for (var, dist) in &self.spec {
+            if let Some(value) = draw(&mut self.rng, var, *dist, manifest) {
+                initials.insert(var.clone(), value);
+            }
+        }
+        initials
+    }
+}
+
+fn check(var: &str, dist: fuzz::Distribution, manifest: &manifest::Manifest) -> err::Result<()> {
+    let bad = |reason: &str| {
+        Err(err::Error::BadFuzz {
+            var: var.to_owned(),
+            reason: reason.to_owned(),
+        })
+    };
+
+    if !dist.is_well_formed() {
+        return bad("minimum must not exceed maximum");
+    }
+    let is_int = matches!(dist, fuzz::Distribution::Int { .. });
+    if manifest.f64s.contains_key(var) {
+        Ok(())
+    } else if manifest.i32s.contains_key(var) || manifest.bools.contains_key(var) {
+        if is_int {
+            Ok(())
+        } else {
+            bad("only 64-bit float variables can take float ranges")
+        }
+    } else {
+        bad("no such variable in the test")
+    }
+}
+
+fn draw(
+    rng: &mut impl Rng,
+    var: &str,
+    dist: fuzz::Distribution,
+    manifest: &mut manifest::Manifest,
+) -> Option<state::Value> {
+    if let Some(r) = manifest.f64s.get_mut(var) {
+        let x = match dist {
+            fuzz::Distribution::Int { min, max } => f64::from(rng.gen_range(min..=max)),
+            fuzz::Distribution::Float { min, max } if min < max => rng.gen_range(min..max),
+            fuzz::Distribution::Float { min, .. } => min,
+        };
+        r.initial_value = Some(x);
+        return Some(state::Value::F64(x));
+    }
+
+    let fuzz::Distribution::Int { min, max } = dist else {
+        return None;
+    };
+    let x = rng.gen_range(min..=max);
+    if let Some(r) = manifest.i32s.get_mut(var) {
+        r.initial_value = Some(x);
+        Some(state::Value::I32(x))
+    } else if let Some(r) = manifest.bools.get_mut(var) {
+        r.initial_value = Some(x != 0);
+        Some(state::Value::Bool(x != 0))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::{manifest::VarRecord, slot::Slot},
+        run::testing::{capped_builder, entry_with_i32s, every},
+    };
+    use std::num::NonZeroUsize;
+
+    fn record<T>(index: usize) -> VarRecord<T> {
+        VarRecord {
+            initial_value: None,
+            slot: Slot {
+                is_atomic: false,
+                index,
+            },
+        }
+    }
+
+    fn manifest() -> manifest::Manifest {
+        manifest::Manifest {
+            n_threads: NonZeroUsize::new(1).unwrap(),
+            i32s: std::iter::once(("x".into(), record(0))).collect(),
+            bools: std::iter::once(("b".into(), record(0))).collect(),
+            f64s: std::iter::once(("f".into(), record(0))).collect(),
+            scalable_role: None,
+            symmetry: vec![],
+            invariants: crate::model::invariant::Map::new(),
+            aux_flags: vec![],
+            declared: vec![],
+        }
+    }
+
+    /// Tests that fuzzing draws values within the requested ranges, and
+    /// writes them back into the manifest.
+    #[test]
+    fn test_fuzz_in_range() -> err::Result<()> {
+        let mut m = manifest();
+        let spec: fuzz::Spec = vec![
+            ("x".to_owned(), fuzz::Distribution::Int { min: 2, max: 4 }),
+            ("b".to_owned(), fuzz::Distribution::Int { min: 1, max: 1 }),
+            (
+                "f".to_owned(),
+                fuzz::Distribution::Float { min: 0.0, max: 0.5 },
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let mut fuzzer = Fuzzer::new(spec, &m)?;
+
+        for _ in 0..100 {
+            let initials = fuzzer.fuzz(&mut m);
+            assert_eq!(initials.len(), 3);
+
+            let x = m.i32s["x"].initial_value.unwrap();
+            assert!((2..=4).contains(&x));
+            assert_eq!(initials["x"], state::Value::I32(x));
+            assert_eq!(m.bools["b"].initial_value, Some(true));
+            let f = m.f64s["f"].initial_value.unwrap();
+            assert!((0.0..0.5).contains(&f));
+        }
+        Ok(())
+    }
+
+    /// Tests that fuzzers reject specs that don't fit the manifest.
+    #[test]
+    fn test_fuzz_rejects_bad_specs() {
+        let m = manifest();
+        let bad = [
+            ("y", fuzz::Distribution::Int { min: 0, max: 1 }),
+            ("x", fuzz::Distribution::Int { min: 1, max: 0 }),
+            ("x", fuzz::Distribution::Float { min: 0.0, max: 1.0 }),
+        ];
+        for (var, dist) in bad {
+            let spec = std::iter::once((var.to_owned(), dist)).collect();
+            assert!(matches!(
+                Fuzzer::new(spec, &m),
+                Err(err::Error::BadFuzz { .. })
+            ));
+        }
+    }
+
+    /// Tests that fuzzing draws initial values once per rotation, with resets
+    /// inside the rotation restoring its draw.
+    #[test]
+    fn test_fuzz_draws_per_rotation() -> err::Result<()> {
+        let entry = entry_with_i32s(1, &[("x", false, Some(0))]);
+        let spec = [(
+            "x".to_owned(),
+            fuzz::Distribution::Int {
+                min: 0,
+                max: 1_000_000,
+            },
+        )]
+        .into();
+        let report = capped_builder(entry, 20)
+            .add_halt_rules(std::iter::once(every(5).rotate()))
+            .with_fuzz(spec)
+            .with_fuzz_seed(Some(8))
+            .build()?
+            .run()?;
+
+        assert_eq!(report.schedule.len(), 4);
+        assert_eq!(report.states.len(), 4);
+        for s in &report.states {
+            assert_eq!((s.info.occurs, s.info.rotations), (5, 1));
+            let drawn: state::State = [("x".into(), s.state["x"])].into();
+            assert_eq!(s.initials, [drawn]);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+//! Guards against test bodies that fork or spawn threads of their own.
+//!
+//! The runner's synchronisation assumes that the test's threads are the only
+//! ones running the test body, all in one process.  A [Guard] looks out for
+//! bodies that break this assumption, tainting the run (see
+//! [`model::taint`]) when it notices them:
+//!
+//! - forks show up through a `pthread_atfork` handler, which the guard
+//!   installs the first time it is made;
+//! - threads show up by sampling the number of threads in the process, which
+//!   should only ever be what it was when the runner was built, plus the
+//!   test's own threads.  This is only possible on Linux, and can't tell the
+//!   test's threads from any that the rest of the process starts while the
+//!   test runs.
+//!
+//! A forked child that returns from the test body into the runner exits
+//! immediately, rather than spinning forever waiting for threads that it
+//! doesn't have.
+
+use crate::model::taint;
+use std::{
+    num::NonZeroUsize,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// The number of iterations between samples of the process's thread count.
+pub const SAMPLE_EVERY: usize = 1024;
+
+/// The status with which forked children exit on returning to the runner.
+pub const CHILD_EXIT_STATUS: i32 = 125;
+
+/// What the runner does when its guard notices a taint.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Response {
+    /// Record the taint in the report, and carry on.
+    Taint,
+    /// Record the taint, then stop the run with an error carrying the
+    /// partial report.
+    Abort,
+}
+
+/// A guard for one run.
+#[derive(Clone, Debug)]
+pub struct Guard {
+    /// What to do on noticing a taint.
+    response: Response,
+    /// The number of forks in the process as of the last check.
+    forks: usize,
+    /// The number of threads the process should have, if we can count them.
+    threads: Option<usize>,
+    /// The first taint of each kind seen so far.
+    tainted: Vec<taint::Taint>,
+}
+
+impl Guard {
+    /// Makes a guard for a test with `n_threads` threads, responding to
+    /// taints with `response`.
+    ///
+    /// This should happen just before the runner starts the test's threads,
+    /// as it takes the process's current thread count as a baseline.
+    #[must_use]
+    pub fn new(response: Response, n_threads: NonZeroUsize) -> Self {
+        install();
+        Self {
+            response,
+            forks: FORKS.load(Ordering::Acquire),
+            threads: thread_count().map(|n| n.saturating_add(n_threads.get())),
+            tainted: vec![],
+        }
+    }
+
+    /// Checks for new taints just after observing `iteration` during
+    /// `rotation`.
+    ///
+    /// Returns whether the run should abort because of a taint first noticed
+    /// here.
+    pub fn check(&mut self, iteration: usize, rotation: usize) -> bool {
+        let mut fresh = false;
+        let forks = FORKS.load(Ordering::Acquire);
+        if forks != self.forks {
+            self.forks = forks;
+            fresh |= self.taint(taint::Cause::Fork, iteration, rotation);
+        }
+        if iteration.is_multiple_of(SAMPLE_EVERY) {
+            if let (Some(expected), Some(seen)) = (self.threads, thread_count()) {
+                if expected < seen {
+                    let cause = taint::Cause::Threads { expected, seen };
+                    fresh |= self.taint(cause, iteration, rotation);
+                }
+            }
+        }
+        fresh && self.response == Response::Abort
+    }
+
+    /// Records `cause`, returning whether it is the first of its kind.
+    fn taint(&mut self, cause: taint::Cause, iteration: usize, rotation: usize) -> bool {
+        if self.tainted.iter().any(|t| t.cause.same_kind(&cause)) {
+            return false;
+        }
+        let taint = taint::Taint {
+            cause,
+            iteration,
+            rotation,
+        };
+        tracing::warn!(%taint, "test tainted the run");
+        self.tainted.push(taint);
+        true
+    }
+
+    /// Gets the first taint of each kind seen so far.
+    #[must_use]
+    pub fn tainted(&self) -> &[taint::Taint] {
+        &self.tainted
+    }
+
+    /// Gets the taint, if any, for which the run should abort.
+    #[must_use]
+    pub fn abort_cause(&self) -> Option<taint::Taint> {
+        match self.response {
+            Response::Taint => None,
+            Response::Abort => self.tainted.first().copied(),
+        }
+    }
+}
+
+/// The number of forks that the process has made since the fork handler
+/// was installed.
+static FORKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Set in forked children, whose test threads can't carry on.
+#[cfg(unix)]
+static IN_CHILD: AtomicBool = AtomicBool::new(false);
+
+/// Installs the fork handlers, if they aren't already installed.
+fn install() {
+    #[cfg(unix)]
+    {
+        static INSTALL: std::sync::Once = std::sync::Once::new();
+        INSTALL.call_once(|| {
+            extern "C" fn prepare() {
+                FORKS.fetch_add(1, Ordering::AcqRel);
+            }
+            extern "C" fn child() {
+                IN_CHILD.store(true, Ordering::Release);
+            }
+            // Failure only means we can't see forks.
+            let _ = unsafe { libc::pthread_atfork(Some(prepare), None, Some(child)) };
+        });
+    }
+}
+
+/// Exits at once if this is a forked child of the runner's process.
+///
+/// Test threads call this on returning from the test body.
+pub fn leave_if_child() {
+    #[cfg(unix)]
+    if IN_CHILD.load(Ordering::Acquire) {
+        // Exiting normally would run the parent's exit handlers twice.
+        unsafe { libc::_exit(CHILD_EXIT_STATUS) }
+    }
+}
+
+/// Counts the threads in the process, if the platform lets us.
+///
+/// This reads the `num_threads` field of `/proc/self/stat` into a buffer on
+/// the stack, so that sampling allocates nothing on the runner's hot path.
+#[cfg(target_os = "linux")]
+fn thread_count() -> Option<usize> {
+    use std::io::Read;
+    let mut buf = [0u8; 1024];
+    let mut file = std::fs::File::open("/proc/self/stat").ok()?;
+    let len = file.read(&mut buf).ok()?;
+    let stat = std::str::from_utf8(&buf[..len]).ok()?;
+    // The command name comes in parentheses, and can hold anything, so
+    // count fields from its end; `num_threads` is the 20th field in all.
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_ascii_whitespace().nth(17)?.parse().ok()
+}
+
+/// Counts the threads in the process, if the platform lets us.
+#[cfg(not(target_os = "linux"))]
+fn thread_count() -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::rust,
+        err, model,
+        run::{
+            testing::{capped_builder, nop_entry},
+            Runner,
+        },
+    };
+
+    /// Tests that forks taint the run once, and abort it if asked.
+    #[cfg(unix)]
+    #[test]
+    fn test_fork_taints() {
+        let n = NonZeroUsize::new(1).unwrap();
+        let mut taint = Guard::new(Response::Taint, n);
+        let mut abort = Guard::new(Response::Abort, n);
+        assert!(abort.abort_cause().is_none());
+
+        match unsafe { libc::fork() } {
+            0 => unsafe { libc::_exit(0) },
+            pid => unsafe {
+                libc::waitpid(pid, std::ptr::null_mut(), 0);
+            },
+        }
+
+        assert!(!taint.check(1, 0));
+        assert!(abort.check(1, 0));
+        assert!(!abort.check(2, 0));
+        for g in [&taint, &abort] {
+            let forks: Vec<_> = g
+                .tainted()
+                .iter()
+                .filter(|t| t.cause == taint::Cause::Fork)
+                .collect();
+            assert_eq!(forks.len(), 1);
+            assert_eq!(forks[0].iteration, 1);
+        }
+        // Only every SAMPLE_EVERY-th iteration samples threads.
+        assert_eq!(
+            abort.abort_cause().map(|t| t.cause),
+            Some(taint::Cause::Fork)
+        );
+    }
+
+    /// Tests that threads that outlive their spawning taint the run.
+    ///
+    /// Other tests' threads come and go meanwhile, so this spawns plenty.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_threads_taint() {
+        let mut guard = Guard::new(Response::Taint, NonZeroUsize::new(1).unwrap());
+        let (done, wait) = std::sync::mpsc::channel::<()>();
+        let wait = std::sync::Arc::new(std::sync::Mutex::new(wait));
+        let spawned: Vec<_> = (0..16)
+            .map(|_| {
+                let wait = wait.clone();
+                std::thread::spawn(move || {
+                    let _ = wait.lock().map(|w| w.recv());
+                })
+            })
+            .collect();
+        guard.check(0, 0);
+        drop(done);
+        for t in spawned {
+            t.join().unwrap();
+        }
+
+        let threads = guard
+            .tainted()
+            .iter()
+            .find(|t| matches!(t.cause, taint::Cause::Threads { .. }));
+        assert!(threads.is_some());
+    }
+
+    /// Tests that guarded runs abort with a partial report when the test
+    /// body forks, and that the child leaves on returning to the runner.
+    #[cfg(unix)]
+    #[test]
+    fn test_guard_aborts_on_fork() {
+        static STATUS: AtomicUsize = AtomicUsize::new(0);
+
+        let mut entry = nop_entry(1);
+        entry.test = |_, _: &rust::Env| {
+            if STATUS.load(Ordering::Relaxed) != 0 {
+                return;
+            }
+            let pid = unsafe { libc::fork() };
+            if pid != 0 {
+                let mut status = 0;
+                unsafe { libc::waitpid(pid, &raw mut status, 0) };
+                let code = libc::WEXITSTATUS(status);
+                STATUS.store(usize::try_from(code).unwrap() + 1, Ordering::Relaxed);
+            }
+        };
+        let result = capped_builder(entry, 1000)
+            .with_guard(Response::Abort)
+            .build()
+            .and_then(Runner::run);
+
+        let (cause, report) = result.expect_err("run should fail").into_partial();
+        assert!(matches!(
+            cause,
+            err::Error::Tainted(taint::Taint {
+                cause: taint::Cause::Fork,
+                iteration: 0,
+                ..
+            })
+        ));
+        let report = report.expect("should have partial report");
+        assert!(report.tainted.iter().any(|t| t.cause == taint::Cause::Fork));
+        assert_eq!(report.ended_by, Some(model::ending::Cause::Tainted));
+        let status = STATUS.load(Ordering::Relaxed) - 1;
+        assert_eq!(status, usize::try_from(CHILD_EXIT_STATUS).unwrap());
+    }
+}
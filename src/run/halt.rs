@@ -1,13 +1,14 @@
 //! Ways to temporarily or permanently halt a running test.
 
 use super::obs;
-use crate::model::Outcome;
+use crate::model::{ending, Outcome};
 use std::{
     num::NonZeroUsize,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
+    time::Duration,
 };
 
 /// A pair of halt condition and halt type.
@@ -21,8 +22,11 @@ pub struct Rule {
 
 impl Rule {
     /// Constructs a halting rule that occurs when a callback is called.
-    pub fn on_callback(ty: Type) -> (Self, impl FnMut()) {
-        let (cond, cb) = Condition::on_callback();
+    ///
+    /// Reports of runs that this rule ends say that the signal `name` ended
+    /// them.
+    pub fn on_callback(ty: Type, name: &'static str) -> (Self, impl FnMut()) {
+        let (cond, cb) = Condition::on_callback(name);
         (cond.halt_with(ty), cb)
     }
 
@@ -30,7 +34,17 @@ impl Rule {
     /// and the most recent observation os.
     #[must_use]
     pub fn exit_type(&self, os: &obs::Summary) -> Option<Type> {
-        self.condition.check(os).then(|| self.halt_type)
+        self.condition.check(os).then_some(self.halt_type)
+    }
+
+    /// Gets the sort of exit, if any, that should occur given this condition
+    /// when there is no observation to check (for instance, during warmup).
+    ///
+    /// Only signal conditions can hold without an observation.
+    #[must_use]
+    pub fn unobserved_exit_type(&self) -> Option<Type> {
+        matches!(&self.condition, Condition::OnSignal { flag, .. } if flag.load(Ordering::Acquire))
+            .then_some(self.halt_type)
     }
 }
 
@@ -40,10 +54,47 @@ pub enum Condition {
     /// The test should halt when the iteration count reaches this
     /// a multiple of this number.
     EveryNIterations(NonZeroUsize),
-    /// The test should halt when this flag goes high.
-    OnSignal(Arc<AtomicBool>),
+    /// The test should halt when at least this many iterations have passed
+    /// since the current rotation began.
+    ///
+    /// Unlike [`Condition::EveryNIterations`], this counts from the last
+    /// rotation, whatever caused it, so rotation periods don't drift when
+    /// other rules rotate the test mid-period.
+    IterationsSinceRotation(NonZeroUsize),
+    /// The test should halt when at least this much wall-clock time has
+    /// passed since the current rotation began.
+    ///
+    /// As a rotation rule, this rotates the test every so often regardless of
+    /// how quickly it iterates; as an exit rule, it bounds the test's running
+    /// time.
+    EveryDuration(Duration),
+    /// The test should halt when a flag goes high.
+    OnSignal {
+        /// The flag.
+        flag: Arc<AtomicBool>,
+        /// The name of the signal that raises the flag, for reports.
+        name: &'static str,
+    },
     /// The test should halt when the first outcome of this type occurs.
+    ///
+    /// If the observer is sampling, only sampled iterations count.
     OnOutcome(Outcome),
+    /// The test should halt once it has seen every interesting state it was
+    /// looking for.
+    AllInteresting,
+    /// The test should halt when, over the last `window` of wall-clock time,
+    /// it has observed fewer than `iters_per_sec` iterations per second.
+    ///
+    /// This lets stuck or pathologically slow configurations end early.  The
+    /// condition never holds until the test has been observed for a full
+    /// window.  Runners measure the rate over the longest window of any of
+    /// their rules, so rules with shorter windows see a smoothed rate.
+    MinRate {
+        /// The minimum acceptable iteration rate.
+        iters_per_sec: f64,
+        /// The wall-clock time over which to measure the rate.
+        window: Duration,
+    },
 }
 
 impl Condition {
@@ -68,22 +119,106 @@ impl Condition {
         self.halt_with(Type::Rotate)
     }
 
-    /// Constructs a halting condition that occurs when a callback is called.
-    pub fn on_callback() -> (Self, impl FnMut()) {
-        let signal = Arc::new(AtomicBool::new(false));
-        let c = Self::OnSignal(signal.clone());
-        (c, move || signal.store(true, Ordering::Release))
+    /// Constructs a halting condition, for the signal `name`, that occurs
+    /// when a callback is called.
+    pub fn on_callback(name: &'static str) -> (Self, impl FnMut()) {
+        let flag = Arc::new(AtomicBool::new(false));
+        let c = Self::OnSignal {
+            flag: flag.clone(),
+            name,
+        };
+        (c, move || flag.store(true, Ordering::Release))
     }
 
     /// Checks to see if this condition holds over `obs`.
     #[must_use]
     pub fn check(&self, os: &obs::Summary) -> bool {
         match self {
-            Self::EveryNIterations(n) => os.iterations % n.get() == 0,
-            Self::OnSignal(s) => s.load(Ordering::Acquire),
-            Self::OnOutcome(o) => os.info.outcome == *o,
+            Self::EveryNIterations(n) => os.iterations.is_multiple_of(n.get()),
+            Self::IterationsSinceRotation(n) => n.get() <= os.rotation_iterations,
+            Self::EveryDuration(d) => *d <= os.rotation_elapsed,
+            Self::OnSignal { flag, .. } => flag.load(Ordering::Acquire),
+            Self::OnOutcome(o) => os.info.is_some_and(|i| i.outcome == *o),
+            Self::AllInteresting => os.interesting_left == 0,
+            Self::MinRate {
+                iters_per_sec,
+                window,
+            } => os
+                .rate
+                .is_some_and(|r| *window <= r.elapsed && r.per_sec() < *iters_per_sec),
         }
     }
+
+    /// Gets the cause to report when this condition ends a run.
+    #[must_use]
+    pub fn cause(&self) -> ending::Cause {
+        match self {
+            Self::EveryNIterations(n) => ending::Cause::Iterations {
+                iterations: n.get(),
+            },
+            Self::IterationsSinceRotation(n) => ending::Cause::RotationIterations {
+                iterations: n.get(),
+            },
+            Self::EveryDuration(d) => ending::Cause::Duration {
+                ms: u64::try_from(d.as_millis()).unwrap_or(u64::MAX),
+            },
+            Self::OnSignal { name, .. } => ending::Cause::Signal {
+                name: (*name).to_owned(),
+            },
+            Self::OnOutcome(outcome) => ending::Cause::Outcome { outcome: *outcome },
+            Self::AllInteresting => ending::Cause::AllInteresting,
+            Self::MinRate { iters_per_sec, .. } => ending::Cause::MinRate {
+                iters_per_sec: *iters_per_sec,
+            },
+        }
+    }
+
+    /// Gets the window over which this condition needs the iteration rate, if
+    /// it needs one.
+    #[must_use]
+    pub fn rate_window(&self) -> Option<Duration> {
+        match self {
+            Self::MinRate { window, .. } => Some(*window),
+            _ => None,
+        }
+    }
+}
+
+/// A rule that pauses every thread of the test for a while, every so many
+/// iterations, without rotating or exiting it.
+///
+/// Pausing duty-cycles long runs, so that machines that throttle when hot
+/// (such as laptops) don't change speed, and so behaviour, mid-run.  Paused
+/// time counts towards wall-clock rules such as
+/// [`Condition::EveryDuration`] and [`Condition::MinRate`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Pause {
+    /// The number of iterations between pauses.
+    pub every: NonZeroUsize,
+    /// How long each pause lasts.
+    pub length: Duration,
+}
+
+impl Pause {
+    /// Gets how long to pause for, if at all, once the test has observed
+    /// `iterations` iterations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::run::halt::Pause;
+    /// use std::{num::NonZeroUsize, time::Duration};
+    ///
+    /// let p = Pause { every: NonZeroUsize::new(10).unwrap(), length: Duration::from_millis(5) };
+    /// assert_eq!(p.length_after(20), Some(Duration::from_millis(5)));
+    /// assert_eq!(p.length_after(21), None);
+    /// ```
+    #[must_use]
+    pub fn length_after(&self, iterations: usize) -> Option<Duration> {
+        iterations
+            .is_multiple_of(self.every.get())
+            .then_some(self.length)
+    }
 }
 
 /// Enumeration of ways the test can be halted.
@@ -97,13 +232,22 @@ pub enum Type {
     Exit,
 }
 
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Rotate => "rotate",
+            Self::Exit => "exit",
+        })
+    }
+}
+
 /// An atomic signal that conveys a halt type.
-pub struct Signal(std::sync::atomic::AtomicU8);
+pub struct Signal(super::atomics::AtomicU8);
 
 /// The default signal is a cleared one.
 impl Default for Signal {
     fn default() -> Self {
-        Self(std::sync::atomic::AtomicU8::default())
+        Self(super::atomics::AtomicU8::new(0))
     }
 }
 
@@ -115,19 +259,164 @@ impl Signal {
 
     /// Sets the halt signal's value to `ty`.
     pub fn set(&self, ty: Type) {
-        let value = match ty {
-            Type::Rotate => 1,
-            Type::Exit => 2,
-        };
-        self.0.store(value, Ordering::Release);
+        self.0.store(encode(ty), Ordering::Release);
+    }
+
+    /// Raises the halt signal to `ty`, unless it is already at a greater
+    /// halt type.
+    pub fn raise(&self, ty: Type) {
+        self.0.fetch_max(encode(ty), Ordering::AcqRel);
     }
 
     /// Gets the halt signal, if any.
+    #[must_use]
     pub fn get(&self) -> Option<Type> {
-        match self.0.load(Ordering::Acquire) {
-            1 => Some(Type::Rotate),
-            2 => Some(Type::Exit),
-            _ => None,
+        decode(self.0.load(Ordering::Acquire))
+    }
+
+    /// Gets the halt signal, if any, and clears it.
+    #[must_use]
+    pub fn take(&self) -> Option<Type> {
+        decode(self.0.swap(0, Ordering::AcqRel))
+    }
+}
+
+/// Encodes a halt type for storage in a [Signal].
+///
+/// The encoding preserves the ordering on types.
+fn encode(ty: Type) -> u8 {
+    match ty {
+        Type::Rotate => 1,
+        Type::Exit => 2,
+    }
+}
+
+/// Decodes a halt type stored in a [Signal].
+fn decode(value: u8) -> Option<Type> {
+    match value {
+        1 => Some(Type::Rotate),
+        2 => Some(Type::Exit),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        err,
+        model::{self, state::Info},
+        run::testing::{capped_builder, entry_with_i32s, x_counts_iterations},
+    };
+
+    fn summary(iterations: usize, rotation_iterations: usize) -> obs::Summary {
+        timed_summary(iterations, rotation_iterations, Duration::ZERO)
+    }
+
+    fn timed_summary(
+        iterations: usize,
+        rotation_iterations: usize,
+        rotation_elapsed: Duration,
+    ) -> obs::Summary {
+        obs::Summary {
+            iterations,
+            rotation_iterations,
+            rotation_elapsed,
+            distinct_states: 0,
+            interesting_left: 0,
+            rate: None,
+            info: Some(Info::new(Outcome::Pass, 0, 0)),
         }
     }
+
+    /// Tests that per-rotation quotas count from the last rotation, not from
+    /// the start of the test.
+    #[test]
+    fn test_iterations_since_rotation() {
+        let c = Condition::IterationsSinceRotation(NonZeroUsize::new(3).unwrap());
+        assert!(!c.check(&summary(3, 2)));
+        assert!(c.check(&summary(5, 3)));
+        assert!(c.check(&summary(5, 4)));
+
+        let g = Condition::EveryNIterations(NonZeroUsize::new(3).unwrap());
+        assert!(!g.check(&summary(5, 3)));
+    }
+
+    /// Tests that raising a signal never lowers it, and taking it clears it.
+    #[test]
+    fn test_signal_raise_take() {
+        let s = Signal::default();
+        s.raise(Type::Exit);
+        s.raise(Type::Rotate);
+        assert_eq!(s.take(), Some(Type::Exit));
+        assert_eq!(s.get(), None);
+        s.raise(Type::Rotate);
+        assert_eq!(s.take(), Some(Type::Rotate));
+    }
+
+    /// Tests that duration-based rules fire once the rotation is old enough.
+    #[test]
+    fn test_every_duration() {
+        let c = Condition::EveryDuration(Duration::from_millis(10));
+        assert!(!c.check(&timed_summary(5, 5, Duration::from_millis(9))));
+        assert!(c.check(&timed_summary(5, 5, Duration::from_millis(10))));
+        assert!(c.check(&timed_summary(5, 1, Duration::from_secs(1))));
+    }
+
+    /// Tests that minimum-rate rules wait for a full window, then fire only
+    /// when the rate is too low.
+    #[test]
+    fn test_min_rate() {
+        let c = Condition::MinRate {
+            iters_per_sec: 100.0,
+            window: Duration::from_secs(1),
+        };
+        let rated = |iterations, ms| obs::Summary {
+            rate: Some(obs::Rate {
+                iterations,
+                elapsed: Duration::from_millis(ms),
+            }),
+            ..summary(iterations, iterations)
+        };
+        assert!(!c.check(&summary(0, 0)));
+        assert!(!c.check(&rated(0, 500)));
+        assert!(c.check(&rated(99, 1000)));
+        assert!(!c.check(&rated(100, 1000)));
+        assert_eq!(c.rate_window(), Some(Duration::from_secs(1)));
+        assert_eq!(Condition::AllInteresting.rate_window(), None);
+    }
+
+    /// Tests that runs can exit as soon as they have seen every interesting
+    /// state, and that reports record the sightings.
+    #[test]
+    fn test_exit_on_all_interesting() -> err::Result<()> {
+        let entry = entry_with_i32s(1, &[("x", false, Some(0))]);
+        let x = |n| {
+            model::interest::Matcher([("x".to_owned(), model::interest::Literal::Int(n))].into())
+        };
+        let spec =
+            model::interest::Spec::from([("one".to_owned(), x(1)), ("three".to_owned(), x(3))]);
+
+        let report = capped_builder(entry, 1000)
+            .add_halt_rules(std::iter::once(Condition::AllInteresting.exit()))
+            .with_hook(x_counts_iterations)
+            .with_interesting(spec)
+            .build()?
+            .run()?;
+
+        assert_eq!(report.states.len(), 4);
+        let sightings: Vec<_> = report
+            .interesting
+            .iter()
+            .map(|s| (s.name.as_str(), s.iteration))
+            .collect();
+        assert_eq!(sightings, [("one", Some(1)), ("three", Some(3))]);
+        let tagged = report
+            .states
+            .iter()
+            .find(|s| s.state["x"] == model::state::Value::I32(3))
+            .map(|s| s.interesting.clone());
+        assert_eq!(tagged, Some(vec!["three".to_owned()]));
+        Ok(())
+    }
 }
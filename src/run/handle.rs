@@ -0,0 +1,79 @@
+//! Handles for controlling a running test from other threads.
+
+use super::halt;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// A handle for halting a test run from outside of it.
+///
+/// Get a handle from [`super::Runner::handle`] before running the test; the
+/// handle can be cloned and sent to other threads, which can then cancel the
+/// run while [`super::Runner::run`] blocks.
+#[derive(Clone, Default)]
+pub struct Handle {
+    /// Halts requested through this handle, yet to be picked up by the test.
+    requests: Arc<halt::Signal>,
+    /// Whether the run has finished.
+    finished: Arc<AtomicBool>,
+}
+
+impl Handle {
+    /// Asks the test to halt with type `halt_type`.
+    ///
+    /// The test picks up the request the next time it observes an iteration;
+    /// a request to rotate rotates the test once, and a request to exit
+    /// overrides any pending request to rotate.
+    pub fn cancel(&self, halt_type: halt::Type) {
+        self.requests.raise(halt_type);
+    }
+
+    /// Gets whether the run has finished, successfully or otherwise.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+
+    /// Gets the signal through which the test picks up requests.
+    pub(super) fn requests(&self) -> Arc<halt::Signal> {
+        self.requests.clone()
+    }
+
+    /// Marks the run as finished.
+    pub(super) fn finish(&self) {
+        self.finished.store(true, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        err, model,
+        run::{sync, testing::nop_entry, Builder},
+    };
+
+    /// Tests that a handle can cancel an otherwise endless run from another
+    /// thread.
+    #[test]
+    fn test_handle_cancels_run() -> err::Result<()> {
+        let entry = nop_entry(2);
+        let runner = Builder::new(entry).with_sync(sync::make_barrier).build()?;
+        let handle = runner.handle();
+        assert!(!handle.is_finished());
+
+        let canceller = handle.clone();
+        let t = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            canceller.cancel(halt::Type::Exit);
+        });
+        let report = runner.run()?;
+        t.join().unwrap();
+
+        assert!(handle.is_finished());
+        assert_eq!(report.states.len(), 1);
+        assert_eq!(report.ended_by, Some(model::ending::Cause::Cancelled));
+        Ok(())
+    }
+}
@@ -1,12 +1,24 @@
 //! Test instances.
-use super::{fsa, halt, permute::Permuter, shared, sync, thread::Threader};
-use crate::{api::abs, err};
+use super::{
+    fsa, halt,
+    marker::{Markers, Phase},
+    permute::{HasTid, Permuter},
+    priority, shared, skew, sync,
+    thread::Threader,
+};
+use crate::{api::abs, err, model};
+use std::sync::Arc;
 
 /// A single instance of a test, ready to be permuted and run.
 ///
 /// An [Instance] manages multiple finite state automata (see
 /// `super::fsa`), and allows controlled running of them over particular
 /// `super::thread::Threader`s.
+///
+/// Dropping an instance tells its automata to exit, so that cutting
+/// [`Instance::run`] short, whether by a panic unwinding through it or by an
+/// early return on error, never leaves threads spinning on the synchroniser;
+/// the thread scope that spawned them can then join them.
 pub struct Instance<'entry, E: abs::Entry<'entry>> {
     /// A persistent copy of the ready automaton with the highest thread ID.
     ///
@@ -14,6 +26,28 @@ pub struct Instance<'entry, E: abs::Entry<'entry>> {
     /// and other useful things; it is also used to clone out the full set of
     /// automata when the instance is spawned.
     top: fsa::ReadyAutomaton<'entry, E>,
+    /// Requests an exit when the instance goes away.
+    ///
+    /// This lives apart from `top` so that an exiting instance can still
+    /// move `top` out to recover the shared state.
+    _exit_on_drop: ExitOnDrop,
+    /// Where to mark the start and end of the run and its rotations.
+    markers: Markers,
+    /// The index of the next rotation that this instance will run.
+    rotation: usize,
+}
+
+/// Requests an exit when dropped.
+///
+/// This goes through the same request signal as a [`super::Handle`], rather
+/// than the automata's own halt signal, so that the threads all see the exit
+/// at the same synchronisation point and none is left waiting for the others.
+struct ExitOnDrop(Arc<halt::Signal>);
+
+impl Drop for ExitOnDrop {
+    fn drop(&mut self) {
+        self.0.raise(halt::Type::Exit);
+    }
 }
 
 impl<'entry, E: abs::Entry<'entry>> Instance<'entry, E> {
@@ -34,9 +68,15 @@ impl<'entry, E: abs::Entry<'entry>> Instance<'entry, E> {
         threader: &'scope R,
         permuter: &mut P,
     ) -> err::Result<Outcome<'entry, E>> {
+        if self.rotation == 0 {
+            self.markers.start(Phase::Run);
+        }
+        self.markers.start(Phase::Rotation(self.rotation));
         let vec = self.make_vec(permuter);
         let handles = threader.spawn_all(vec)?;
-        self.into_outcome(threader.join_all(handles)?.halt_type)
+        let halt_type = threader.join_all(handles)?.halt_type;
+        self.markers.end(Phase::Rotation(self.rotation));
+        self.into_outcome(halt_type)
     }
 
     /// Makes a permuted vector of ready automata.
@@ -55,30 +95,78 @@ impl<'entry, E: abs::Entry<'entry>> Instance<'entry, E> {
                 // If we don't do this, then threads will spawn, immediately
                 // think they need to rotate again, and fail to advance.
                 self.top.halt_signal().clear();
-                Outcome::Rotate(self)
+                Outcome::Rotate(Self {
+                    rotation: self.rotation.saturating_add(1),
+                    ..self
+                })
             }
             halt::Type::Exit => {
+                self.markers.end(Phase::Run);
+                let panic = self.top.take_panic();
                 // The reference count for the tester state should be 1, as top
                 // should be the only automaton left on this state.
-                Outcome::Exit(self.top.into_shared_state()?)
+                let state = self.top.into_shared_state()?;
+                match panic {
+                    Some(panic) => Outcome::Panic(state, panic),
+                    None => Outcome::Exit(state),
+                }
             }
         })
     }
 
+    /// Makes this instance synchronise its next rotations through a new
+    /// synchroniser from `sync`.
+    ///
+    /// This can only happen between rotations, when none of the instance's
+    /// automata are running.
+    pub(super) fn resync(self, sync: &sync::Factory) -> err::Result<Self> {
+        let nthreads = std::num::NonZeroUsize::MIN.saturating_add(self.top.tid());
+        Ok(Self {
+            top: self.top.with_sync(sync(nthreads)?),
+            ..self
+        })
+    }
+
+    /// Makes this instance mark the phases of its run in `markers`.
+    #[must_use]
+    pub(super) fn with_markers(self, markers: Markers) -> Self {
+        Self { markers, ..self }
+    }
+
     /// Constructs an instance from a test entry point, synchronisation factory,
-    /// and shared state.
+    /// shared state, skew spec and the seed (if any) from which to draw skews,
+    /// priority spec, and CPU restriction.
     ///
     /// This function relies on the various inputs matching up; it
     /// presently relies on the rest of the runner infrastructure ensuring this.
     pub(super) fn new(
         entry: E,
-        sync: sync::Factory,
-        tester_state: shared::State<'entry, E::Env>,
+        sync: &sync::Factory,
+        mut tester_state: shared::State<'entry, E::Env>,
+        skew: &model::skew::Spec,
+        skew_seed: Option<u64>,
+        priority: &model::priority::Spec,
+        cpus: Option<&model::cpu::Set>,
     ) -> err::Result<Self> {
         let nthreads = tester_state.env.manifest.n_threads;
         let sync = sync(nthreads)?;
+        let skew = skew::Table::new(skew, nthreads)?;
+        let skew = match skew_seed {
+            Some(seed) => skew.with_seed(seed),
+            None => skew,
+        };
+        tester_state.env.skew = skew.clone();
+        let priorities = priority::Table::new(priority, nthreads)?;
+        let exit_on_drop = ExitOnDrop(tester_state.requests.clone());
+        let top = fsa::Automaton::new(nthreads.get() - 1, tester_state, entry, sync)
+            .with_skew(skew)
+            .with_priorities(priorities)
+            .with_cpus(cpus.cloned());
         Ok(Self {
-            top: fsa::Automaton::new(nthreads.get() - 1, tester_state, entry, sync),
+            top,
+            _exit_on_drop: exit_on_drop,
+            markers: Markers::none(),
+            rotation: 0,
         })
     }
 }
@@ -91,4 +179,8 @@ pub enum Outcome<'entry, E: abs::Entry<'entry>> {
     /// The test has exited, and the tester state passed outwards for
     /// inspection.
     Exit(shared::State<'entry, E::Env>),
+    /// The test has exited because a test thread panicked, either in the test
+    /// body or while observing it; the tester state is passed outwards
+    /// alongside the panic for partial inspection.
+    Panic(shared::State<'entry, E::Env>, err::Panic),
 }
@@ -0,0 +1,349 @@
+//! Resource limits on the runner's process.
+//!
+//! A test runs its library's code inside the runner's own process, so a test
+//! that runs away with CPU time or memory takes the runner with it.  [Limits]
+//! cap both through `setrlimit`, and runners watch for the test reaching them
+//! (see [`super::Builder::with_limits`]), so that they can stop with a
+//! partial report and an [`err::Error::LimitExceeded`] before the kernel
+//! steps in:
+//!
+//! - the CPU limit caps the total user and system time of the process, and
+//!   the kernel kills the process [`CPU_GRACE`] after it;
+//! - the memory limit caps the address space of the process, and allocations
+//!   start failing [`MEMORY_HEADROOM`] beyond it.
+//!
+//! Runners sample usage every [`SAMPLE_EVERY`] iterations, so a test that
+//! hangs, or gets through the slack within one iteration, meets the kernel
+//! instead.  The limits cover the whole process, and can't be raised again
+//! once applied, so they should be applied once, before loading any test
+//! library.  Only Unix-like platforms support limits, and only Linux can
+//! sample memory use.
+
+use crate::err;
+use std::{fmt::Display, num::NonZeroU64, time::Duration};
+
+/// The number of iterations between samples of the process's usage.
+pub const SAMPLE_EVERY: usize = 1024;
+
+/// How much CPU time past its limit the process gets before the kernel
+/// kills it.
+pub const CPU_GRACE: Duration = Duration::from_secs(5);
+
+/// How much address space past its limit the process gets before
+/// allocations start failing.
+pub const MEMORY_HEADROOM: u64 = 64 << 20;
+
+/// Limits on the resources that the process can use.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The total CPU time, if limited.
+    pub cpu: Option<Duration>,
+    /// The size of the address space in bytes, if limited.
+    pub memory: Option<NonZeroU64>,
+}
+
+impl Limits {
+    /// Gets whether these limits limit nothing.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.cpu.is_none() && self.memory.is_none()
+    }
+
+    /// Applies these limits to the current process, leaving the slack
+    /// described in the module documentation.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`err::Error::BadLimit`] if the platform can't limit a
+    /// resource, or the process may not lower its limit as far as asked,
+    /// and with [`err::Error::LimitExceeded`] if the process has already
+    /// used more than a limit allows.
+    pub fn apply(&self) -> err::Result<()> {
+        if let Some(cpu) = self.cpu {
+            let kernel = cpu.saturating_add(CPU_GRACE);
+            // The kernel counts whole seconds.
+            let secs = kernel
+                .as_secs()
+                .saturating_add(u64::from(kernel.subsec_nanos() != 0));
+            set(Resource::Cpu, secs)?;
+        }
+        if let Some(memory) = self.memory {
+            set(
+                Resource::Memory,
+                memory.get().saturating_add(MEMORY_HEADROOM),
+            )?;
+        }
+        self.exceeded()
+            .map_or(Ok(()), |e| Err(err::Error::LimitExceeded(e)))
+    }
+
+    /// Samples the process's usage, returning how it exceeds these limits,
+    /// if it does.
+    ///
+    /// Usage that the platform can't sample never exceeds its limit.
+    #[must_use]
+    pub fn exceeded(&self) -> Option<Exceeded> {
+        let cpu = self.cpu.and_then(|limit| {
+            let used = cpu_time()?;
+            (limit < used).then_some(Exceeded::Cpu { limit, used })
+        });
+        cpu.or_else(|| {
+            let limit = self.memory?.get();
+            let used = address_space()?;
+            (limit < used).then_some(Exceeded::Memory { limit, used })
+        })
+    }
+}
+
+/// A resource that [Limits] can limit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Resource {
+    /// CPU time.
+    Cpu,
+    /// Address space.
+    Memory,
+}
+
+/// Resources display as their names in configs and reports.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::run::limit::Resource;
+/// assert_eq!(Resource::Cpu.to_string(), "cpu");
+/// assert_eq!(Resource::Memory.to_string(), "memory");
+/// ```
+impl Display for Resource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Cpu => "cpu",
+            Self::Memory => "memory",
+        })
+    }
+}
+
+/// A way in which the process exceeded its [Limits].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Exceeded {
+    /// The process used more CPU time than its limit.
+    Cpu {
+        /// The limit.
+        limit: Duration,
+        /// The CPU time used.
+        used: Duration,
+    },
+    /// The process's address space grew beyond its limit.
+    Memory {
+        /// The limit, in bytes.
+        limit: u64,
+        /// The size of the address space, in bytes.
+        used: u64,
+    },
+}
+
+impl Exceeded {
+    /// Gets the resource whose limit was exceeded.
+    #[must_use]
+    pub fn resource(&self) -> Resource {
+        match self {
+            Self::Cpu { .. } => Resource::Cpu,
+            Self::Memory { .. } => Resource::Memory,
+        }
+    }
+
+    /// Gets the limit and usage as key-value pairs, for error contexts.
+    #[must_use]
+    pub fn context(&self) -> Vec<(&'static str, String)> {
+        let (limit, used) = match self {
+            Self::Cpu { limit, used } => (limit.as_millis(), used.as_millis()),
+            Self::Memory { limit, used } => (u128::from(*limit), u128::from(*used)),
+        };
+        let unit = match self {
+            Self::Cpu { .. } => "ms",
+            Self::Memory { .. } => "bytes",
+        };
+        vec![
+            ("resource", self.resource().to_string()),
+            ("limit", format!("{limit}{unit}")),
+            ("used", format!("{used}{unit}")),
+        ]
+    }
+}
+
+/// Excesses display as a short explanation.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::run::limit::Exceeded;
+/// use std::time::Duration;
+///
+/// let e = Exceeded::Cpu { limit: Duration::from_secs(1), used: Duration::from_millis(1500) };
+/// assert_eq!(e.to_string(), "used 1.5s of CPU time, over the limit of 1s");
+/// let e = Exceeded::Memory { limit: 1024, used: 4096 };
+/// assert_eq!(e.to_string(), "used 4096 bytes of address space, over the limit of 1024");
+/// ```
+impl Display for Exceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cpu { limit, used } => {
+                write!(f, "used {used:?} of CPU time, over the limit of {limit:?}")
+            }
+            Self::Memory { limit, used } => write!(
+                f,
+                "used {used} bytes of address space, over the limit of {limit}"
+            ),
+        }
+    }
+}
+
+/// Lowers both the soft and hard limits on `resource` to `value`.
+#[cfg(unix)]
+fn set(resource: Resource, value: u64) -> err::Result<()> {
+    let bad = |reason: String| err::Error::BadLimit { resource, reason };
+    let which = match resource {
+        Resource::Cpu => libc::RLIMIT_CPU,
+        Resource::Memory => libc::RLIMIT_AS,
+    };
+    let mut old = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(which, &raw mut old) } != 0 {
+        return Err(bad(std::io::Error::last_os_error().to_string()));
+    }
+    let value: libc::rlim_t = value;
+    if old.rlim_max != libc::RLIM_INFINITY && old.rlim_max < value {
+        // Raising the hard limit needs privileges, and wouldn't limit much.
+        return Err(bad(format!(
+            "the process is already limited to {}",
+            old.rlim_max
+        )));
+    }
+    let new = libc::rlimit {
+        rlim_cur: value,
+        rlim_max: value,
+    };
+    if unsafe { libc::setrlimit(which, &raw const new) } != 0 {
+        return Err(bad(std::io::Error::last_os_error().to_string()));
+    }
+    Ok(())
+}
+
+/// Lowers both the soft and hard limits on `resource` to `value`.
+#[cfg(not(unix))]
+fn set(resource: Resource, _value: u64) -> err::Result<()> {
+    Err(err::Error::BadLimit {
+        resource,
+        reason: "this platform can't limit resources".to_owned(),
+    })
+}
+
+/// Gets the total user and system CPU time of the process, if the platform
+/// lets us.
+#[cfg(unix)]
+fn cpu_time() -> Option<Duration> {
+    let mut usage = unsafe { std::mem::zeroed::<libc::rusage>() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &raw mut usage) } != 0 {
+        return None;
+    }
+    let time = |t: libc::timeval| {
+        let secs = u64::try_from(t.tv_sec).ok()?;
+        let micros = u64::try_from(t.tv_usec).ok()?;
+        Some(Duration::from_secs(secs) + Duration::from_micros(micros))
+    };
+    Some(time(usage.ru_utime)? + time(usage.ru_stime)?)
+}
+
+/// Gets the total user and system CPU time of the process, if the platform
+/// lets us.
+#[cfg(not(unix))]
+fn cpu_time() -> Option<Duration> {
+    None
+}
+
+/// Gets the size of the process's address space in bytes, if the platform
+/// lets us.
+#[cfg(target_os = "linux")]
+fn address_space() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().next()?.parse().ok()?;
+    let page_size = u64::try_from(unsafe { libc::sysconf(libc::_SC_PAGESIZE) }).ok()?;
+    pages.checked_mul(page_size)
+}
+
+/// Gets the size of the process's address space in bytes, if the platform
+/// lets us.
+#[cfg(not(target_os = "linux"))]
+fn address_space() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model,
+        run::{
+            testing::{every, nop_entry},
+            Builder,
+        },
+    };
+
+    /// Tests that the process exceeds tiny limits, and not huge ones.
+    ///
+    /// This only samples usage; applying limits would hamper the other
+    /// tests in the process.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_exceeded() {
+        let tiny = Limits {
+            cpu: Some(Duration::from_nanos(1)),
+            memory: NonZeroU64::new(1),
+        };
+        assert!(matches!(tiny.exceeded(), Some(Exceeded::Cpu { .. })));
+        let memory_only = Limits { cpu: None, ..tiny };
+        assert_eq!(
+            memory_only.exceeded().map(|e| e.resource()),
+            Some(Resource::Memory)
+        );
+        let huge = Limits {
+            cpu: Some(Duration::from_secs(1 << 40)),
+            memory: NonZeroU64::new(u64::MAX),
+        };
+        assert_eq!(huge.exceeded(), None);
+        assert_eq!(Limits::default().exceeded(), None);
+    }
+
+    /// Tests that runs watching for limits abort with a partial report once
+    /// the process exceeds one.
+    #[cfg(unix)]
+    #[test]
+    fn test_with_limits_aborts() {
+        let cond = every(10_000);
+        let limits = Limits {
+            cpu: Some(Duration::from_nanos(1)),
+            memory: None,
+        };
+        let result = Builder::new(nop_entry(2))
+            .with_limits(limits)
+            .add_halt_rules(std::iter::once(cond.exit()))
+            .build()
+            .unwrap()
+            .run();
+        let Err(err::Error::Aborted { cause, report }) = result else {
+            panic!("the run should abort");
+        };
+        assert!(matches!(
+            *cause,
+            err::Error::LimitExceeded(Exceeded::Cpu { .. })
+        ));
+        assert_eq!(report.iterations(), 1);
+        assert_eq!(
+            report.ended_by,
+            Some(model::ending::Cause::LimitExceeded {
+                resource: "cpu".to_owned()
+            })
+        );
+    }
+}
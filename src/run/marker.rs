@@ -0,0 +1,151 @@
+//! Markers that line up profiles with the phases of a run.
+//!
+//! Hardware-counter profiles of a run mix its setup, its rotations, and the
+//! observer's work between them.  With markers on, each test instance (see
+//! `super::instance`) marks the start and end of the run and of each
+//! rotation in the kernel's trace buffer, by writing to ftrace's
+//! `trace_marker` file, so that `perf record -e ftrace:print` or
+//! `trace-cmd` can record them alongside the profile.  Each marker is a line
+//! such as `phenolphthalein: rotation 3 start`.
+//!
+//! Only Linux has `trace_marker`; elsewhere, markers can't be opened.
+//! Instances without markers use [`Markers::none`], which marks nothing.
+
+use std::{fs, io};
+
+crate::features::register! {
+    "runtime" => cfg!(target_os = "linux").then_some("trace-markers"),
+}
+
+/// The prefix of every marker, to pick ours out of the trace.
+const PREFIX: &str = "phenolphthalein";
+
+/// The places that `trace_marker` may be, depending on where the tracing
+/// filesystem is mounted.
+#[cfg(target_os = "linux")]
+const PATHS: &[&str] = &[
+    "/sys/kernel/tracing/trace_marker",
+    "/sys/kernel/debug/tracing/trace_marker",
+];
+
+/// A sink for run phase markers.
+#[derive(Debug, Default)]
+pub struct Markers {
+    /// The open `trace_marker` file, if marking.
+    file: Option<fs::File>,
+}
+
+/// A phase of a run that markers can mark.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// The run as a whole.
+    Run,
+    /// The rotation with the given index.
+    Rotation(usize),
+}
+
+impl Markers {
+    /// Makes a sink that marks nothing.
+    #[must_use]
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Opens the kernel's trace marker file for marking.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no trace marker file can be opened for writing; this needs
+    /// the tracing filesystem mounted, and usually privileges.  Always fails
+    /// on platforms other than Linux.
+    #[cfg(target_os = "linux")]
+    pub fn open() -> io::Result<Self> {
+        let mut last = io::Error::from(io::ErrorKind::NotFound);
+        for path in PATHS {
+            match fs::OpenOptions::new().write(true).open(path) {
+                Ok(file) => return Ok(Self { file: Some(file) }),
+                Err(e) => last = e,
+            }
+        }
+        Err(last)
+    }
+
+    /// Opens the kernel's trace marker file for marking.
+    ///
+    /// # Errors
+    ///
+    /// Always fails, as the platform has no trace marker file.
+    #[cfg(not(target_os = "linux"))]
+    pub fn open() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this platform has no trace marker file",
+        ))
+    }
+
+    /// Gets whether this sink marks anything.
+    #[must_use]
+    pub fn is_marking(&self) -> bool {
+        self.file.is_some()
+    }
+
+    /// Marks the start of `phase`.
+    pub fn start(&self, phase: Phase) {
+        self.mark(phase, "start");
+    }
+
+    /// Marks the end of `phase`.
+    pub fn end(&self, phase: Phase) {
+        self.mark(phase, "end");
+    }
+
+    fn mark(&self, phase: Phase, edge: &str) {
+        use io::Write;
+        let Some(mut file) = self.file.as_ref() else {
+            return;
+        };
+        let line = match phase {
+            Phase::Run => format!("{PREFIX}: run {edge}\n"),
+            Phase::Rotation(n) => format!("{PREFIX}: rotation {n} {edge}\n"),
+        };
+        // Markers are a debugging aid, so losing one shouldn't stop the run.
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that markers write one line per mark, naming the phase.
+    #[test]
+    fn test_mark_lines() {
+        let path = std::env::temp_dir().join(format!("phph-markers-{}", std::process::id()));
+        let markers = Markers {
+            file: Some(fs::File::create(&path).unwrap()),
+        };
+        assert!(markers.is_marking());
+        markers.start(Phase::Run);
+        markers.start(Phase::Rotation(0));
+        markers.end(Phase::Rotation(0));
+        markers.end(Phase::Run);
+        let got = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(
+            got,
+            "phenolphthalein: run start\n\
+             phenolphthalein: rotation 0 start\n\
+             phenolphthalein: rotation 0 end\n\
+             phenolphthalein: run end\n"
+        );
+    }
+
+    /// Tests that the no-op sink marks nothing.
+    #[test]
+    fn test_none_marks_nothing() {
+        let markers = Markers::none();
+        assert!(!markers.is_marking());
+        markers.start(Phase::Run);
+        markers.end(Phase::Run);
+    }
+}
@@ -1,88 +1,767 @@
+use super::{
+    aggregate::{self, Aggregator},
+    fuzz, skew, snapshot,
+};
 use crate::{
     api::abs,
     err,
-    model::{self, state},
+    model::{self, manifest::VarName, state},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    num::NonZeroUsize,
+    time::{Duration, Instant},
 };
-
-/* TODO(@MattWindsor91): morally, a State should only borrow the variable names,
-   as they are held by the parent Observer's Manifest for the entire scope that
-   States are available; trying to get this to work with borrowck has proven a
-   little difficult.
-*/
 
 /// An observer for the outcomes of a test.
-#[derive(Default)]
+///
+/// The observer keys states by snapshot (see [snapshot]), so observing a state
+/// it has seen before allocates nothing; it can also hand some bookkeeping to a
+/// separate thread (see [`Observer::aggregate_in_background`]).
 pub struct Observer {
-    /// The observations that this observer has made so far.
-    pub obs: std::collections::HashMap<state::State, state::Info>,
+    /// The identifiers of the states observed so far, keyed by snapshot.
+    known: HashMap<Box<[u64]>, usize>,
+
+    /// The states observed so far, indexed by identifier.
+    states: Vec<Known>,
+
+    /// The buffer into which the observer reads each snapshot.
+    snapshot: Vec<u64>,
+
+    /// The aggregator for per-rotation counts and fuzzed initial valuations.
+    aggregator: Aggregator,
+
+    /// The interner for the fuzzed initial valuations sent to the aggregator.
+    initials: aggregate::Initials,
 
     /// The number of iterations this observer has seen so far.
     iterations: usize,
+
+    /// The number of the current rotation.
+    rotation: usize,
+
+    /// The number of iterations this observer has seen in the current
+    /// rotation.
+    rotation_iterations: usize,
+
+    /// When the current rotation began.
+    rotation_started: Instant,
+
+    /// The iterations at which each distinct state was first observed.
+    discovery: model::discovery::Curve,
+
+    /// The torn values observed on canaried variables, keyed by variable and
+    /// value.
+    torn: HashMap<(VarName, state::Value), model::canary::TornRead>,
+
+    /// The values observed outside their variables' invariants, keyed by
+    /// variable and value.
+    violations: HashMap<(VarName, state::Value), model::invariant::Violation>,
+
+    /// The interesting states to look out for.
+    interesting: model::interest::Spec,
+
+    /// The iteration, if any, at which each interesting state was first seen.
+    sightings: BTreeMap<String, Option<usize>>,
+
+    /// The number of warmup iterations left before the observer starts
+    /// recording observations.
+    warmup: usize,
+
+    /// The sliding window, if any, over which the observer measures its
+    /// iteration rate.
+    rate: Option<RateWindow>,
+
+    /// The sampler, if any, that chooses which iterations to observe.
+    sampler: Option<Sampler>,
+
+    /// When to check the states seen for the first time.
+    checking: Checking,
+
+    /// The identifiers of states seen but not yet checked, if checks are
+    /// deferred.
+    pending: Vec<usize>,
+
+    /// The names of the synchronisers, if any, that the run alternates, and
+    /// the number of rotations each stays active.
+    alternation: Option<(Vec<String>, std::num::NonZeroUsize)>,
+
+    /// The context of the first iteration to show each state, indexed by
+    /// identifier, if the observer is capturing examples.
+    examples: Option<Vec<model::example::Example>>,
+
+    /// The transitions between the states of successive observed
+    /// iterations, if the observer is recording them.
+    transitions: Option<Transitions>,
+
+    /// Whether to segment the report's histogram by rotation.
+    segment_rotations: bool,
+
+    /// The names of the auxiliary flags that the test can raise, indexed by
+    /// their bits; empty if the observer isn't tracking flags.
+    aux_flags: Vec<String>,
+
+    /// The outcome of each observed iteration, tallied, if the observer is
+    /// checking every iteration.
+    tally: Option<model::outcome::Tally>,
+}
+
+impl Default for Observer {
+    fn default() -> Self {
+        Self {
+            known: HashMap::default(),
+            states: Vec::new(),
+            snapshot: Vec::new(),
+            aggregator: Aggregator::default(),
+            initials: aggregate::Initials::default(),
+            iterations: 0,
+            rotation: 0,
+            rotation_iterations: 0,
+            rotation_started: Instant::now(),
+            discovery: model::discovery::Curve::default(),
+            torn: HashMap::default(),
+            violations: HashMap::default(),
+            interesting: model::interest::Spec::default(),
+            sightings: BTreeMap::default(),
+            warmup: 0,
+            rate: None,
+            sampler: None,
+            checking: Checking::default(),
+            pending: Vec::new(),
+            alternation: None,
+            examples: None,
+            transitions: None,
+            segment_rotations: false,
+            aux_flags: Vec::new(),
+            tally: None,
+        }
+    }
 }
 
 impl Observer {
+    /// Constructs a new, empty observer.
+    #[must_use]
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Constructs a new, empty observer that ignores the first `warmup`
+    /// iterations.
+    ///
+    /// Warmup iterations don't count towards the iteration count, and so see
+    /// the position of the first recorded iteration.
+    #[must_use]
+    pub fn with_warmup(warmup: usize) -> Self {
+        Self {
+            warmup,
+            ..Self::default()
+        }
+    }
+
+    /// Makes the observer look out for the interesting states in `spec`.
+    pub fn watch(&mut self, spec: model::interest::Spec) {
+        self.sightings = spec.keys().map(|k| (k.clone(), None)).collect();
+        self.interesting = spec;
+    }
+
+    /// Makes the observer measure its iteration rate over the last `window`
+    /// of wall-clock time, reporting it in each [Summary].
+    pub fn track_rate(&mut self, window: Duration) {
+        self.rate = Some(RateWindow::new(window));
+    }
+
+    /// Makes the observer only observe the iterations that `rule` samples.
+    ///
+    /// Unsampled iterations still count towards the iteration count, and so
+    /// towards halt rules and the iteration rate, but record nothing; their
+    /// summaries carry no [`Summary::info`].
+    pub fn sample(&mut self, rule: model::sampling::Rule) {
+        self.sampler = Some(Sampler::new(rule));
+    }
+
+    /// Makes the observer check the states it sees for the first time as
+    /// `checking` says.
+    ///
+    /// Deferred checks load each state into the environment, so they only
+    /// see the state's own variables; they need every observation to see
+    /// the whole environment, unmasked and unfolded.  States awaiting their
+    /// checks have an unknown outcome.
+    pub fn set_checking(&mut self, checking: Checking) {
+        self.checking = checking;
+    }
+
+    /// Tells the observer that the run alternates between the synchronisers
+    /// named in `syncs`, switching every `every` rotations, so that its
+    /// report can break down states by synchroniser.
+    pub fn alternate(&mut self, syncs: Vec<String>, every: std::num::NonZeroUsize) {
+        self.alternation = Some((syncs, every));
+    }
+
+    /// Makes the observer capture, for each distinct state, the context of
+    /// the first iteration that showed it (see [`model::example`]).
+    pub fn capture_examples(&mut self) {
+        self.examples = Some(Vec::new());
+    }
+
+    /// Makes the observer record how often each state follows each other
+    /// state in successive observed iterations (see [`model::transition`]).
+    pub fn record_transitions(&mut self) {
+        self.transitions = Some(Transitions::default());
+    }
+
+    /// Makes the observer's report segment its histogram by rotation (see
+    /// [`model::rotation`]).
+    pub fn segment_rotations(&mut self) {
+        self.segment_rotations = true;
+    }
+
+    /// Makes the observer count, for each state, the iterations showing it
+    /// that raised each of the auxiliary flags named in `names` (see
+    /// [`abs::AtomicEnv::raise_aux_flag`]).
+    pub fn track_aux_flags(&mut self, names: Vec<String>) {
+        self.aux_flags = names;
+    }
+
+    /// Makes the observer check every iteration it observes, not just the
+    /// first to show each state, and tally their outcomes.
+    ///
+    /// This is for tests without variables, whose one state can't tell the
+    /// iterations that passed from those that failed: the state's outcome
+    /// becomes the worst of its iterations' outcomes.  Deferred checks would
+    /// check nothing that the iteration itself didn't leave behind, so this
+    /// checks immediately, whatever the observer's checking policy.
+    pub fn tally_outcomes(&mut self) {
+        self.checking = Checking::Immediate;
+        self.tally = Some(model::outcome::Tally::default());
+    }
+
+    /// Makes the observer aggregate per-rotation counts and fuzzed initial
+    /// valuations on a separate thread.
+    ///
+    /// This takes work off the path between iterations, at the cost of a
+    /// thread; it only helps if there is a spare core to run that thread.
+    pub fn aggregate_in_background(&mut self) {
+        let old = std::mem::replace(&mut self.aggregator, Aggregator::spawn());
+        debug_assert!(matches!(old, Aggregator::Inline(_)));
+    }
+
+    /// Gets the number of interesting states not yet seen.
+    #[must_use]
+    pub fn interesting_left(&self) -> usize {
+        self.sightings.values().filter(|x| x.is_none()).count()
+    }
+
+    /// Gets whether the observer is still ignoring warmup iterations.
+    #[must_use]
+    pub fn is_warming_up(&self) -> bool {
+        self.warmup != 0
+    }
+
     /// Observes a test environment into this runner's observations.
-    pub fn observe<'a, E: abs::Env>(
+    ///
+    /// Returns `None`, recording nothing, if this is a warmup iteration.  If
+    /// the observer is sampling, and doesn't sample this iteration, it only
+    /// counts the iteration.  Either way, it takes any auxiliary flags the
+    /// iteration raised, so that they don't carry over into the next.
+    pub fn observe<E: abs::Env>(
         &mut self,
         env: &mut Manifested<E>,
-        checker: &'a dyn abs::Checker<E>,
-    ) -> Summary {
-        let info = self.observe_state(env, checker);
+        checker: &dyn abs::Checker<E>,
+    ) -> Option<Summary> {
+        let raised = if self.aux_flags.is_empty() {
+            0
+        } else {
+            env.env.take_aux_flags()
+        };
+        if self.is_warming_up() {
+            self.warmup -= 1;
+            if !self.is_warming_up() {
+                // Don't count the warmup against the first rotation's time.
+                self.rotation_started = Instant::now();
+                tracing::debug!("warmup finished");
+            }
+            return None;
+        }
+        let iteration = self.iterations;
+        let info = self
+            .sampler
+            .as_mut()
+            .is_none_or(|s| s.take(iteration))
+            .then(|| self.observe_sampled(env, checker, raised));
         self.iterations = self.iterations.saturating_add(1);
-        Summary {
+        self.rotation_iterations = self.rotation_iterations.saturating_add(1);
+        if let Checking::Every(n) = self.checking {
+            if self.iterations.is_multiple_of(n.get()) {
+                self.check_pending(env, checker);
+            }
+        }
+        let iterations = self.iterations;
+        let rate = self
+            .rate
+            .as_mut()
+            .and_then(|r| r.record(Instant::now(), iterations));
+        Some(Summary {
             iterations: self.iterations,
+            rotation_iterations: self.rotation_iterations,
+            rotation_elapsed: self.rotation_started.elapsed(),
+            distinct_states: self.states.len(),
+            interesting_left: self.interesting_left(),
+            rate,
             info,
-        }
+        })
     }
 
-    fn observe_state<'a, E: abs::Env>(
+    /// Observes an iteration that the observer has chosen to sample, and
+    /// that raised the auxiliary flags with bits set in `raised`.
+    fn observe_sampled<E: abs::Env>(
         &mut self,
         env: &mut Manifested<E>,
-        checker: &'a dyn abs::Checker<E>,
+        checker: &dyn abs::Checker<E>,
+        raised: u64,
     ) -> model::state::Info {
-        let state = current_state(env);
-        let info = self.obs.get(&state).map_or_else(
-            || self.observe_state_for_first_time(&env.env, checker),
-            model::state::Info::inc,
+        let violated = self.observe_violations(env);
+        let info = self.observe_state(env, checker, violated, raised);
+        self.observe_torn(env);
+        tracing::trace!(
+            iteration = self.iterations,
+            outcome = %info.outcome,
+            occurs = info.occurs,
+            "observed"
         );
-        self.obs.insert(state, info);
         info
     }
 
-    fn observe_state_for_first_time<'a, E: abs::Env>(
+    /// Observes the current state, failing it if `violated` says that the
+    /// environment breaks an invariant, and counting against it the
+    /// auxiliary flags with bits set in `raised`.
+    fn observe_state<E: abs::Env>(
+        &mut self,
+        env: &mut Manifested<E>,
+        checker: &dyn abs::Checker<E>,
+        violated: bool,
+        raised: u64,
+    ) -> model::state::Info {
+        env.layout.read(&env.env, &mut self.snapshot);
+        let id = if let Some(&id) = self.known.get(self.snapshot.as_slice()) {
+            let known = &mut self.states[id];
+            known.info = known.info.inc(self.iterations, self.rotation);
+            if let Some(tally) = &mut self.tally {
+                let outcome = checker.check(&env.env);
+                tally.record(outcome);
+                known.info.outcome = known.info.outcome.max(outcome);
+            }
+            id
+        } else {
+            let state = env.layout.state_of(&self.snapshot);
+            self.discovery.discover(self.iterations);
+            self.sight_interesting(&state);
+            let known = self.observe_state_for_first_time(state, &env.env, checker);
+            if let Some(tally) = &mut self.tally {
+                tally.record(known.info.outcome);
+            }
+            let id = self.states.len();
+            if self.checking != Checking::Immediate {
+                self.pending.push(id);
+            }
+            self.known.insert(self.snapshot.as_slice().into(), id);
+            self.states.push(known);
+            if let Some(examples) = &mut self.examples {
+                examples.push(model::example::Example {
+                    state: id,
+                    iteration: self.iterations,
+                    rotation: self.rotation,
+                    // Only the runner knows the permutation; see `into_report`.
+                    permutation: vec![],
+                    initials: env.initials.clone(),
+                    skew: env.skew.drawn(),
+                });
+            }
+            id
+        };
+        if let Some(transitions) = &mut self.transitions {
+            transitions.record(id);
+        }
+        self.aggregator.record(aggregate::Record {
+            id,
+            rotation: self.rotation,
+            initials: (!env.initials.is_empty()).then(|| self.initials.intern(&env.initials)),
+        });
+        let known = &mut self.states[id];
+        for (i, count) in known.aux_flags.iter_mut().enumerate() {
+            if raised & (1 << i) != 0 {
+                *count = count.saturating_add(1);
+            }
+        }
+        let info = &mut known.info;
+        if violated {
+            // Masked variables can break invariants without changing the
+            // state, so this can happen after the state's first observation.
+            info.outcome = model::Outcome::Fail;
+        }
+        *info
+    }
+
+    fn sight_interesting(&mut self, state: &state::State) {
+        for name in model::interest::matching(&self.interesting, state) {
+            if let Some(slot @ None) = self.sightings.get_mut(name) {
+                *slot = Some(self.iterations);
+                tracing::info!(name, iteration = self.iterations, "interesting state seen");
+            }
+        }
+    }
+
+    /// Records any values in `env` that break their invariants, returning
+    /// whether there were any.
+    fn observe_violations<E: abs::Env>(&mut self, env: &Manifested<E>) -> bool {
+        let mut violated = false;
+        for (var, value, bounds) in env.violations() {
+            violated = true;
+            self.violations
+                .entry((var.clone(), value))
+                .and_modify(|v| v.occurs = v.occurs.saturating_add(1))
+                .or_insert_with(|| {
+                    model::invariant::Violation::new(
+                        var.clone(),
+                        value,
+                        bounds,
+                        self.iterations,
+                        self.rotation,
+                    )
+                });
+        }
+        violated
+    }
+
+    fn observe_torn<E: abs::Env>(&mut self, env: &Manifested<E>) {
+        for (var, value) in env.torn_values() {
+            self.torn
+                .entry((var.clone(), value))
+                .and_modify(|t| t.occurs = t.occurs.saturating_add(1))
+                .or_insert_with(|| {
+                    model::canary::TornRead::new(var.clone(), value, self.iterations, self.rotation)
+                });
+        }
+    }
+
+    fn observe_state_for_first_time<E: abs::Env>(
         &self,
+        state: state::State,
         env: &E,
-        checker: &'a dyn abs::Checker<E>,
-    ) -> model::state::Info {
-        let outcome = checker.check(env);
-        model::state::Info::new(outcome, self.iterations)
+        checker: &dyn abs::Checker<E>,
+    ) -> Known {
+        let mut checks = BTreeMap::new();
+        let outcome = if self.checking == Checking::Immediate {
+            checker.check_parts(env, &mut checks)
+        } else {
+            model::Outcome::Unknown
+        };
+        Known {
+            state,
+            info: model::state::Info::new(outcome, self.iterations, self.rotation),
+            checks,
+            aux_flags: vec![0; self.aux_flags.len()],
+        }
+    }
+
+    /// Checks any states whose checks were deferred, by loading each into
+    /// the environment in turn.
+    ///
+    /// The environment holds its own values again afterwards.  States that
+    /// broke an invariant stay failed, whatever their checks say.
+    pub fn check_pending<E: abs::Env>(
+        &mut self,
+        env: &mut Manifested<E>,
+        checker: &dyn abs::Checker<E>,
+    ) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let saved: state::State = abs::values(&env.manifest, &env.env).collect();
+        for id in std::mem::take(&mut self.pending) {
+            let known = &mut self.states[id];
+            abs::load(&env.manifest, &mut env.env, &known.state);
+            let outcome = checker.check_parts(&env.env, &mut known.checks);
+            if known.info.outcome != model::Outcome::Fail {
+                known.info.outcome = outcome;
+            }
+        }
+        abs::load(&env.manifest, &mut env.env, &saved);
+    }
+
+    /// Gets the position of the next iteration this observer will see.
+    #[must_use]
+    pub fn position(&self) -> abs::Iteration {
+        abs::Iteration {
+            index: self.iterations,
+            rotation: self.rotation,
+        }
+    }
+
+    /// Tells the observer that the test is moving onto a new rotation.
+    pub fn rotate(&mut self) {
+        self.rotation = self.rotation.saturating_add(1);
+        self.rotation_iterations = 0;
+        self.rotation_started = Instant::now();
     }
 
     /// Consumes this Observer and returns a summary of its state.
-    pub fn into_report(self) -> model::report::Report {
+    ///
+    /// The thread permutation `schedule` lets the report break down
+    /// occurrences by permutation, and fills in the permutation of each
+    /// example.
+    #[must_use]
+    pub fn into_report(self, schedule: model::schedule::Schedule) -> model::report::Report {
+        let sampling = self.sampling();
         let mut report = model::report::Report {
+            schema_version: model::report::schema::VERSION,
             outcome: None,
-            states: Vec::with_capacity(self.obs.len()),
+            states: Vec::with_capacity(self.states.len()),
+            states_hash: String::new(),
+            schedule,
+            discovery: self.discovery,
+            permutations: vec![],
+            alternation: None,
+            rotations: vec![],
+            torn: self.torn.into_values().collect(),
+            violations: self.violations.into_values().collect(),
+            interesting: self
+                .sightings
+                .into_iter()
+                .map(|(name, iteration)| model::interest::Sighting { name, iteration })
+                .collect(),
+            masked: vec![],
+            var_order: vec![],
+            folded: vec![],
+            orderings: BTreeMap::new(),
+            platform: None,
+            provenance: None,
+            cpus: None,
+            roles: vec![],
+            capabilities: model::capability::Set::new(),
+            conformance: None,
+            aggregate: BTreeMap::new(),
+            projections: vec![],
+            tainted: vec![],
+            timing: vec![],
+            ended_by: None,
+            sampling,
+            reproduction: None,
+            check_description: None,
+            examples: vec![],
+            transitions: self
+                .transitions
+                .as_ref()
+                .map(Transitions::to_model)
+                .unwrap_or_default(),
+            outcomes: self.tally,
+            allocations: None,
         };
+        report.sort_torn();
+        report.sort_violations();
 
-        for (state, info) in self.obs {
-            report.insert(model::report::State { state, info });
+        let mut aggregate = self.aggregator.finish();
+        let mut counts = Vec::with_capacity(self.states.len());
+        for (id, known) in self.states.into_iter().enumerate() {
+            let (by_rotation, initials) = aggregate.take(id);
+            counts.push(by_rotation);
+            let interesting = model::interest::matching(&self.interesting, &known.state)
+                .map(ToOwned::to_owned)
+                .collect();
+            let aux_flags = aux_flag_counts(&self.aux_flags, &known.aux_flags);
+            report.insert(model::report::State {
+                state: known.state,
+                info: known.info,
+                initials: self.initials.resolve(initials),
+                interesting,
+                checks: known.checks,
+                aux_flags,
+                hash: String::new(),
+            });
         }
+        report.break_down(&counts);
+        if self.segment_rotations {
+            report.rotations = model::rotation::segment(&counts, &report.schedule, &report.states);
+        }
+        report.alternation = self.alternation.map(|(syncs, every)| {
+            model::alternation::Alternation::new(syncs, every, report.schedule.len(), &counts)
+        });
+        report.examples = self.examples.unwrap_or_default();
+        for e in &mut report.examples {
+            if let Some(p) = report.schedule.get(e.rotation) {
+                e.permutation = p.to_vec();
+            }
+        }
+        report.hash_states();
+        report
+    }
 
+    /// Copies the observations made so far into a partial report, without
+    /// stopping the observer.
+    ///
+    /// The partial report has no schedule, and so no per-rotation breakdown,
+    /// no fuzzed initial valuations, as these live in the aggregator until the
+    /// run finishes, and no permutations in its examples; everything else is
+    /// as [`Self::into_report`] would give now.
+    #[must_use]
+    pub fn partial_report(&self) -> model::report::Report {
+        let mut report = model::report::Report {
+            schema_version: model::report::schema::VERSION,
+            states: Vec::with_capacity(self.states.len()),
+            discovery: self.discovery.clone(),
+            torn: self.torn.values().cloned().collect(),
+            violations: self.violations.values().cloned().collect(),
+            interesting: self
+                .sightings
+                .iter()
+                .map(|(name, &iteration)| model::interest::Sighting {
+                    name: name.clone(),
+                    iteration,
+                })
+                .collect(),
+            sampling: self.sampling(),
+            outcomes: self.tally,
+            examples: self.examples.clone().unwrap_or_default(),
+            transitions: self
+                .transitions
+                .as_ref()
+                .map(Transitions::to_model)
+                .unwrap_or_default(),
+            ..model::report::Report::default()
+        };
+        report.sort_torn();
+        report.sort_violations();
+        for known in &self.states {
+            let interesting = model::interest::matching(&self.interesting, &known.state)
+                .map(ToOwned::to_owned)
+                .collect();
+            report.insert(model::report::State {
+                state: known.state.clone(),
+                info: known.info,
+                initials: vec![],
+                interesting,
+                checks: known.checks.clone(),
+                aux_flags: aux_flag_counts(&self.aux_flags, &known.aux_flags),
+                hash: String::new(),
+            });
+        }
+        report.hash_states();
         report
     }
+
+    /// Gets how the observer has sampled its observations so far, if it is
+    /// sampling.
+    fn sampling(&self) -> Option<model::sampling::Sampling> {
+        self.sampler.as_ref().map(|s| model::sampling::Sampling {
+            rule: Some(s.rule),
+            iterations: self.iterations,
+            sampled: s.sampled,
+        })
+    }
 }
 
-/// Gets the current state of the environment.
-/// Note that this is not thread-safe until all test threads are synchronised.
-fn current_state<T: abs::Env>(env: &Manifested<T>) -> model::state::State {
-    let mut s = model::state::State::new();
-    s.extend(env.values());
-    s
+/// Names the nonzero counts in `counts`, which are of the auxiliary flags
+/// named in `names`.
+fn aux_flag_counts(names: &[String], counts: &[usize]) -> BTreeMap<String, usize> {
+    names
+        .iter()
+        .zip(counts)
+        .filter(|(_, &count)| count != 0)
+        .map(|(name, &count)| (name.clone(), count))
+        .collect()
+}
+
+/// When an [Observer] checks the states it sees for the first time.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Checking {
+    /// Check each state as soon as it is first seen.
+    #[default]
+    Immediate,
+    /// Check the states first seen since the last batch of checks after
+    /// every this many iterations, and at the end of the run.
+    Every(NonZeroUsize),
+    /// Check every state at the end of the run.
+    AtEnd,
+}
+
+/// Chooses which iterations an [Observer] observes.
+struct Sampler {
+    /// The rule by which to choose.
+    rule: model::sampling::Rule,
+    /// The source of random choices, which only random rules use.
+    rng: StdRng,
+    /// The number of iterations sampled so far.
+    sampled: usize,
+}
+
+impl Sampler {
+    fn new(rule: model::sampling::Rule) -> Self {
+        let seed = match rule {
+            model::sampling::Rule::Every { .. } => 0,
+            model::sampling::Rule::Random { seed, .. } => seed,
+        };
+        Self {
+            rule,
+            rng: StdRng::seed_from_u64(seed),
+            sampled: 0,
+        }
+    }
+
+    /// Decides whether to sample the iteration with index `iteration`.
+    fn take(&mut self, iteration: usize) -> bool {
+        let take = match self.rule {
+            model::sampling::Rule::Every { n } => iteration.is_multiple_of(n.get()),
+            model::sampling::Rule::Random { probability, .. } => {
+                self.rng.gen::<f64>() < probability
+            }
+        };
+        if take {
+            self.sampled = self.sampled.saturating_add(1);
+        }
+        take
+    }
+}
+
+/// The transitions an [Observer] has seen between the states of successive
+/// observed iterations.
+#[derive(Default)]
+struct Transitions {
+    /// The identifier of the state of the last observed iteration, if any.
+    previous: Option<usize>,
+    /// The number of times each transition occurred, keyed by the
+    /// identifiers of its states.
+    counts: HashMap<(usize, usize), usize>,
+}
+
+impl Transitions {
+    /// Records that the last observed iteration showed the state `id`.
+    fn record(&mut self, id: usize) {
+        if let Some(from) = self.previous.replace(id) {
+            let occurs = self.counts.entry((from, id)).or_default();
+            *occurs = occurs.saturating_add(1);
+        }
+    }
+
+    fn to_model(&self) -> Vec<model::transition::Transition> {
+        model::transition::from_counts(self.counts.iter().map(|(&k, &v)| (k, v)))
+    }
+}
+
+/// A state that an [Observer] has seen.
+struct Known {
+    /// The state itself.
+    state: state::State,
+
+    /// The information recorded about the state so far.
+    info: state::Info,
+
+    /// The outcome of each combined checker on the state, if its checker
+    /// combines several.
+    checks: BTreeMap<String, model::Outcome>,
+
+    /// The number of iterations showing the state that raised each auxiliary
+    /// flag, indexed by the flag's bit.
+    aux_flags: Vec<usize>,
 }
 
 /// A summary of the observer's current state, useful for calculating test
@@ -93,8 +772,107 @@ pub struct Summary {
     /// this one.  This number will saturate at usize.MAX.
     pub iterations: usize,
 
-    /// The information from the current observation.
-    pub info: model::state::Info,
+    /// The number of iterations the observer has seen since the current
+    /// rotation began, including this one.  This number will saturate at
+    /// usize.MAX.
+    pub rotation_iterations: usize,
+
+    /// The wall-clock time elapsed between the start of the current rotation
+    /// and this observation.
+    pub rotation_elapsed: Duration,
+
+    /// The number of distinct states the observer has seen so far, including
+    /// any discovered in this observation.
+    pub distinct_states: usize,
+
+    /// The number of interesting states the observer has yet to see.
+    pub interesting_left: usize,
+
+    /// The iteration rate over the observer's rate window, if it is
+    /// tracking one and the window has filled.
+    pub rate: Option<Rate>,
+
+    /// The information from the current observation, if the observer
+    /// sampled it.
+    pub info: Option<model::state::Info>,
+}
+
+/// A number of iterations observed over some wall-clock time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rate {
+    /// The number of iterations observed.
+    pub iterations: usize,
+    /// The wall-clock time over which they were observed.
+    pub elapsed: Duration,
+}
+
+impl Rate {
+    /// Gets the number of iterations per second.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::run::obs::Rate;
+    /// use std::time::Duration;
+    /// let r = Rate { iterations: 500, elapsed: Duration::from_millis(250) };
+    /// assert!((r.per_sec() - 2000.0).abs() < f64::EPSILON);
+    /// ```
+    #[must_use]
+    pub fn per_sec(&self) -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        let iterations = self.iterations as f64;
+        iterations / self.elapsed.as_secs_f64()
+    }
+}
+
+/// The number of samples a [`RateWindow`] takes over each window.
+///
+/// This bounds the window's memory use, whatever the iteration rate, at the
+/// cost of the window overrunning by up to this fraction of itself.
+const SAMPLES_PER_WINDOW: u32 = 16;
+
+/// A sliding window over the iteration counts of recent observations.
+struct RateWindow {
+    /// The length of the window.
+    window: Duration,
+    /// Times paired with the iteration counts observed by then, oldest first.
+    samples: VecDeque<(Instant, usize)>,
+}
+
+impl RateWindow {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            samples: VecDeque::with_capacity(SAMPLES_PER_WINDOW as usize + 1),
+        }
+    }
+
+    /// Records that `iterations` iterations had been observed by `now`.
+    ///
+    /// Returns the rate over the window, or `None` if we've not yet been
+    /// recording for a full window.
+    fn record(&mut self, now: Instant, iterations: usize) -> Option<Rate> {
+        let step = self.window / SAMPLES_PER_WINDOW;
+        let age = |t: &Instant| now.saturating_duration_since(*t);
+        if self.samples.back().is_none_or(|(t, _)| step <= age(t)) {
+            self.samples.push_back((now, iterations));
+        }
+        // Keep the newest sample from before the window, so that the rate
+        // covers all of it.
+        while self
+            .samples
+            .get(1)
+            .is_some_and(|(t, _)| self.window <= age(t))
+        {
+            self.samples.pop_front();
+        }
+        let (start, start_iterations) = self.samples.front()?;
+        let elapsed = age(start);
+        (self.window <= elapsed).then(|| Rate {
+            iterations: iterations - start_iterations,
+            elapsed,
+        })
+    }
 }
 
 /// An environment combined with a manifest.
@@ -110,35 +888,574 @@ pub struct Manifested<E> {
 
     /// The environment being interpreted by the manifest.
     pub env: E,
+
+    /// The fuzzer, if any, used to redraw initial values on each rotation.
+    pub fuzzer: Option<fuzz::Fuzzer>,
+
+    /// The fuzzed initial valuation from the last redraw.
+    ///
+    /// This is empty if there is no fuzzer.
+    pub initials: state::State,
+
+    /// The skew table with which the test threads enter each iteration,
+    /// through which observers see the delays they drew.
+    pub skew: skew::Table,
+
+    /// Whether to fill non-atomic variables without initial values with
+    /// canary values on each reset, so that torn reads can be detected.
+    pub canary: bool,
+
+    /// The variables to leave out of observed states.
+    mask: BTreeSet<String>,
+
+    /// Whether to fold together states of symmetric threads.
+    fold_symmetric: bool,
+
+    /// The layout of snapshots of the unmasked variables.
+    layout: snapshot::Layout,
 }
 
 impl<E: abs::Env> Manifested<E> {
     /// Resets the environment to the initial values in the manifest.
-    pub fn reset(&mut self) {
+    ///
+    /// If `redraw` is set and there is a fuzzer, it first redraws the
+    /// initial values of any fuzzed variables; otherwise, they keep the
+    /// values last drawn.
+    pub fn reset(&mut self, redraw: bool) {
+        if let Some(f) = self.fuzzer.as_mut().filter(|_| redraw) {
+            self.initials = f.fuzz(&mut self.manifest);
+        }
+        let canary = self.canary;
         for r in self.manifest.i32s.values() {
-            self.env.set_i32(r.slot, r.initial_value.unwrap_or(0));
+            let v = if is_canaried(canary, r) {
+                model::canary::I32
+            } else {
+                r.initial_value.unwrap_or_default()
+            };
+            self.env.set_i32(r.slot, v);
         }
+        for r in self.manifest.bools.values() {
+            self.env
+                .set_bool(r.slot, r.initial_value.unwrap_or_default());
+        }
+        for r in self.manifest.f64s.values() {
+            let v = if is_canaried(canary, r) {
+                model::canary::f64()
+            } else {
+                r.initial_value.unwrap_or_default()
+            };
+            self.env.set_f64(r.slot, v);
+        }
+    }
+
+    /// Iterates over all of the unmasked variables in the environment.
+    pub fn values(&self) -> impl Iterator<Item = (VarName, model::state::Value)> + '_ {
+        abs::values(&self.manifest, &self.env).filter(|(n, _)| !self.mask.contains(n.as_str()))
     }
 
-    // Iterates over all of the variables in the environment.
-    pub fn values(&self) -> impl Iterator<Item = (String, model::state::Value)> + '_ {
-        // Space for rent.
-        self.i32_values()
+    /// Gets the variables left out of observed states.
+    #[must_use]
+    pub fn mask(&self) -> &BTreeSet<String> {
+        &self.mask
     }
 
-    // Iterates over all of the 32-bit integer variables in the environment.
-    fn i32_values(&self) -> impl Iterator<Item = (String, model::state::Value)> + '_ {
-        self.manifest.i32s.iter().map(move |(n, r)| {
-            (
-                n.to_string(),
-                model::state::Value::I32(self.env.get_i32(r.slot)),
-            )
+    /// Excludes the variables in `mask` from observed states.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `mask` names a variable that isn't in the manifest.
+    pub fn set_mask(&mut self, mask: BTreeSet<String>) -> err::Result<()> {
+        if let Some(var) = mask.iter().find(|v| !self.manifest.has_var(v)) {
+            return Err(err::Error::UnknownMaskedVar(var.clone()));
+        }
+        self.mask = mask;
+        self.relayout();
+        Ok(())
+    }
+
+    /// Sets whether observed states that differ only by swapping the
+    /// registers of symmetric threads count as the same state (see
+    /// [`snapshot::Layout::fold_symmetric`]).
+    pub fn set_fold_symmetric(&mut self, fold: bool) {
+        self.fold_symmetric = fold;
+        self.relayout();
+    }
+
+    /// Gets the orderings with which the test has accessed each atomic
+    /// variable, leaving out those with no recorded orderings.
+    #[must_use]
+    pub fn orderings(&self) -> BTreeMap<String, model::ordering::Set> {
+        let m = &self.manifest;
+        let e = &self.env;
+        let i32s = m.i32s.iter().map(|(n, r)| (n, e.i32_orderings(r.slot)));
+        let bools = m.bools.iter().map(|(n, r)| (n, e.bool_orderings(r.slot)));
+        let f64s = m.f64s.iter().map(|(n, r)| (n, e.f64_orderings(r.slot)));
+        i32s.chain(bools)
+            .chain(f64s)
+            .filter(|(_, orders)| !orders.is_empty())
+            .map(|(n, orders)| (n.to_string(), orders))
+            .collect()
+    }
+
+    /// Gets the groups of symmetric threads whose states are being folded
+    /// together.
+    #[must_use]
+    pub fn folded(&self) -> Vec<Vec<usize>> {
+        self.layout.folded()
+    }
+
+    fn relayout(&mut self) {
+        self.layout = snapshot::Layout::new(&self.manifest, &self.mask);
+        if self.fold_symmetric {
+            self.layout.fold_symmetric(&self.manifest);
+        }
+    }
+
+    /// Gets the current value of the variable `name`, if there is one.
+    fn value_of(&self, name: &str) -> Option<model::state::Value> {
+        let m = &self.manifest;
+        let e = &self.env;
+        m.i32s
+            .get(name)
+            .map(|r| model::state::Value::I32(e.get_i32(r.slot)))
+            .or_else(|| {
+                m.bools
+                    .get(name)
+                    .map(|r| model::state::Value::Bool(e.get_bool(r.slot)))
+            })
+            .or_else(|| {
+                m.f64s
+                    .get(name)
+                    .map(|r| model::state::Value::F64(e.get_f64(r.slot)))
+            })
+    }
+
+    /// Iterates over the variables whose current values break the manifest's
+    /// invariants, whether or not they are masked.
+    pub fn violations(
+        &self,
+    ) -> impl Iterator<Item = (&VarName, model::state::Value, model::invariant::Bounds)> + '_ {
+        self.manifest.invariants.iter().filter_map(|(var, bounds)| {
+            let value = self.value_of(var)?;
+            (!bounds.contains(value)).then_some((var, value, *bounds))
         })
     }
 
+    /// Iterates over the canaried variables whose current values look torn.
+    pub fn torn_values(&self) -> impl Iterator<Item = (&VarName, model::state::Value)> + '_ {
+        let canary = self.canary;
+        let i32s = self
+            .manifest
+            .i32s
+            .iter()
+            .filter(move |(_, r)| is_canaried(canary, r))
+            .map(|(n, r)| (n, model::state::Value::I32(self.env.get_i32(r.slot))));
+        let f64s = self
+            .manifest
+            .f64s
+            .iter()
+            .filter(move |(_, r)| is_canaried(canary, r))
+            .map(|(n, r)| (n, model::state::Value::F64(self.env.get_f64(r.slot))));
+        i32s.chain(f64s).filter(|(_, v)| model::canary::is_torn(*v))
+    }
+
     /// Constructs a manifested environment for a given manifest.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the environment can't be allocated from the manifest's
+    /// reservations.
     pub fn for_manifest(manifest: model::manifest::Manifest) -> err::Result<Manifested<E>> {
         let env = E::of_reservations(manifest.reserve())?;
-        Ok(Self { manifest, env })
+        let layout = snapshot::Layout::new(&manifest, &BTreeSet::new());
+        Ok(Self {
+            manifest,
+            env,
+            fuzzer: None,
+            initials: state::State::new(),
+            skew: skew::Table::default(),
+            canary: false,
+            mask: BTreeSet::new(),
+            fold_symmetric: false,
+            layout,
+        })
+    }
+}
+
+/// Gets whether, with canary mode set to `canary`, the variable in `record`
+/// is filled with a canary value on reset.
+fn is_canaried<T>(canary: bool, record: &model::manifest::VarRecord<T>) -> bool {
+    canary && !record.slot.is_atomic && record.initial_value.is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::rust,
+        run::{
+            halt,
+            testing::{capped_builder, entry_with_i32s, every, nop_entry, x_counts_iterations},
+            Builder,
+        },
+    };
+
+    /// Tests that the rate window only reports once full, and then only
+    /// counts iterations inside the window.
+    #[test]
+    fn test_rate_window() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut w = RateWindow::new(Duration::from_millis(160));
+        assert_eq!(w.record(at(0), 0), None);
+        assert_eq!(w.record(at(100), 1000), None);
+        let r = w.record(at(160), 1100).unwrap();
+        assert_eq!(
+            (r.iterations, r.elapsed),
+            (1100, Duration::from_millis(160))
+        );
+        // The sample at 0ms drops out; the one at 100ms stays, being the
+        // newest from before the window.
+        let r = w.record(at(300), 1200).unwrap();
+        assert_eq!((r.iterations, r.elapsed), (200, Duration::from_millis(200)));
+    }
+
+    /// Tests that states record the span of iterations and rotations in which
+    /// they appeared.
+    #[test]
+    fn test_provenance() -> err::Result<()> {
+        let report = capped_builder(nop_entry(2), 6)
+            .add_halt_rules(std::iter::once(every(2).rotate()))
+            .build()?
+            .run()?;
+
+        assert_eq!(report.states.len(), 1);
+        let info = report.states[0].info;
+        assert_eq!((info.iteration, info.last_iteration), (0, 5));
+        assert_eq!((info.rotation, info.last_rotation), (0, 2));
+        assert_eq!(info.rotations, 3);
+        assert_eq!(report.schedule.len(), 3);
+        assert_eq!(report.permutations.len(), 1);
+        assert_eq!(report.permutations[0].rotations, 3);
+        assert_eq!(report.permutations[0].states[0].occurs, 6);
+        Ok(())
+    }
+
+    /// Tests that canary mode reports partial writes to non-atomic
+    /// variables as torn reads.
+    #[test]
+    fn test_canary_reports_torn_reads() -> err::Result<()> {
+        let mut entry = entry_with_i32s(1, &[("half", false, None), ("whole", false, None)]);
+        // Simulates a torn write by writing only the first half of `half`.
+        entry.test = |_, env: &rust::Env| unsafe {
+            env.i32s.non_atomic[0].get().cast::<u16>().write(1);
+            env.i32s.non_atomic[1].get().write(2);
+        };
+
+        let report = capped_builder(entry, 4).with_canary(true).build()?.run()?;
+
+        assert_eq!(report.torn.len(), 1);
+        let torn = &report.torn[0];
+        assert_eq!(torn.var, "half");
+        assert_eq!((torn.occurs, torn.iteration), (4, 0));
+        assert!(model::canary::is_torn(torn.value));
+        Ok(())
+    }
+
+    /// Tests that masked variables don't distinguish states, and that the
+    /// mask must name variables in the test.
+    #[test]
+    fn test_mask_merges_states() -> err::Result<()> {
+        let entry = entry_with_i32s(1, &[("x", false, Some(0)), ("scratch", false, Some(0))]);
+        let builder = capped_builder(entry, 4).with_hook(|env, summary| {
+            if let Some(s) = env.manifest.i32s.get_mut("scratch") {
+                s.initial_value = i32::try_from(summary.iterations).ok();
+            }
+        });
+
+        let report = builder.with_mask(["scratch".to_owned()]).build()?.run()?;
+        assert_eq!(report.states.len(), 1);
+        assert_eq!(report.states[0].info.occurs, 4);
+        assert!(!report.states[0].state.contains_key("scratch"));
+        assert_eq!(report.masked, ["scratch"]);
+
+        let entry = nop_entry(1);
+        let result = Builder::new(entry).with_mask(["nope".to_owned()]).build();
+        assert!(matches!(result, Err(err::Error::UnknownMaskedVar(v)) if v == "nope"));
+        Ok(())
+    }
+
+    /// Tests that states breaking the manifest's invariants fail and are
+    /// reported, even without a checker.
+    #[test]
+    fn test_invariant_violations_fail() -> err::Result<()> {
+        let mut entry = entry_with_i32s(1, &[("x", false, Some(0))]);
+        entry
+            .manifest
+            .invariants
+            .insert("x".into(), model::invariant::Bounds { min: 0.0, max: 2.0 });
+        let report = capped_builder(entry, 6)
+            .with_hook(x_counts_iterations)
+            .build()?
+            .run()?;
+
+        for s in &report.states {
+            let broken = !matches!(s.state["x"], model::state::Value::I32(0..=2));
+            assert_eq!(s.info.outcome == model::Outcome::Fail, broken);
+        }
+        assert!(!report.violations.is_empty());
+        assert!(report
+            .violations
+            .iter()
+            .all(|v| v.var == "x" && v.occurs == 1 && !v.bounds.contains(v.value)));
+        Ok(())
+    }
+
+    /// Tests that folding counts states differing only by swapping the
+    /// registers of symmetric threads as one state.
+    #[test]
+    fn test_fold_symmetric_merges_states() -> err::Result<()> {
+        let mut entry = entry_with_i32s(2, &[("0:r0", false, Some(0)), ("1:r0", false, Some(0))]);
+        entry.manifest.symmetry = vec![0, 0];
+        let run = |fold| {
+            capped_builder(entry.clone(), 4)
+                .with_hook(|env, summary| {
+                    // Alternate between 0:r0=1, 1:r0=0 and the reverse.
+                    let odd = i32::from(summary.iterations % 2 == 1);
+                    for (tid, value) in [(0, odd), (1, 1 - odd)] {
+                        if let Some(r) = env.manifest.i32s.get_mut(&*format!("{tid}:r0")) {
+                            r.initial_value = Some(value);
+                        }
+                    }
+                })
+                .with_fold_symmetric(fold)
+                .build()?
+                .run()
+        };
+
+        let unfolded = run(false)?;
+        assert_eq!(unfolded.states.len(), 3);
+        assert!(unfolded.folded.is_empty());
+
+        let folded = run(true)?;
+        assert_eq!(folded.states.len(), 2);
+        assert_eq!(folded.folded, [vec![0, 1]]);
+        let occurs: Vec<_> = folded.states.iter().map(|s| s.info.occurs).collect();
+        assert_eq!(occurs, [1, 3]);
+        Ok(())
+    }
+
+    /// Tests that sampled runs keep exact iteration counts, but only count
+    /// sampled iterations in their states.
+    #[test]
+    fn test_sampling() -> err::Result<()> {
+        let rule = model::sampling::Rule::Every {
+            n: NonZeroUsize::new(4).unwrap(),
+        };
+        let report = capped_builder(nop_entry(2), 10)
+            .with_sampling(Some(rule))
+            .build()?
+            .run()?;
+
+        let sampling = report.sampling.unwrap();
+        assert_eq!(sampling.rule, Some(rule));
+        assert_eq!((sampling.iterations, sampling.sampled), (10, 3));
+        assert_eq!(report.iterations(), 3);
+        let info = report.states[0].info;
+        assert_eq!((info.iteration, info.last_iteration), (0, 8));
+        Ok(())
+    }
+
+    /// Tests that deferred and batched checks judge states as immediate
+    /// checks would, and that deferring checks while halting on an outcome
+    /// fails to build.
+    #[test]
+    fn test_check_frequency() -> err::Result<()> {
+        let mut entry = entry_with_i32s(1, &[("x", false, Some(0))]);
+        entry.check = Some(|_| model::Outcome::Pass);
+        let forbidden = model::interest::Spec::from([(
+            "two".to_owned(),
+            model::interest::Matcher([("x".to_owned(), model::interest::Literal::Int(2))].into()),
+        )]);
+        let builder = |checking| {
+            capped_builder(entry.clone(), 5)
+                .with_checker(abs::Entry::checker)
+                .with_forbidden(forbidden.clone())
+                .with_checking(checking)
+                .with_hook(x_counts_iterations)
+        };
+
+        for checking in [
+            Checking::AtEnd,
+            Checking::Every(NonZeroUsize::new(2).unwrap()),
+        ] {
+            let report = builder(checking).build()?.run()?;
+            assert_eq!(report.states.len(), 5);
+            assert_eq!(report.outcome, Some(model::Outcome::Fail));
+            for s in &report.states {
+                let is_two = s.state["x"] == model::state::Value::I32(2);
+                assert_eq!(s.info.outcome, model::Outcome::from_pass_bool(!is_two));
+                assert_eq!(s.checks["forbidden"], s.info.outcome);
+            }
+        }
+
+        let fail = halt::Condition::OnOutcome(model::Outcome::Fail);
+        let result = builder(Checking::AtEnd)
+            .add_halt_rules(std::iter::once(fail.exit()))
+            .build();
+        assert!(matches!(result, Err(err::Error::BadCheckFrequency(_))));
+        Ok(())
+    }
+
+    /// Tests that capturing examples records the context of each state's
+    /// first iteration, including the permutation and any skew drawn.
+    #[test]
+    fn test_with_examples() -> err::Result<()> {
+        let delay = model::skew::Delay {
+            min: std::time::Duration::from_nanos(10),
+            max: std::time::Duration::from_nanos(20),
+        };
+        let report = Builder::new(nop_entry(2))
+            .with_examples(true)
+            .with_skew([("1".to_owned(), delay)].into())
+            .add_halt_rules(vec![every(20).exit(), every(5).rotate()])
+            .build()?
+            .run()?;
+
+        let [example] = report.examples.as_slice() else {
+            panic!("expected one example, got {:?}", report.examples);
+        };
+        assert_eq!(
+            (example.state, example.iteration, example.rotation),
+            (0, 0, 0)
+        );
+        assert_eq!(Some(example.permutation.as_slice()), report.schedule.get(0));
+        let drawn = example.skew["1"];
+        assert!(drawn.is_fixed() && delay.min <= drawn.min && drawn.min <= delay.max);
+        assert!(!example.skew.contains_key("0"));
+        Ok(())
+    }
+
+    /// Tests that runs don't capture examples unless asked to.
+    #[test]
+    fn test_without_examples() -> err::Result<()> {
+        let cond = every(10);
+        let report = Builder::new(nop_entry(2))
+            .add_halt_rules(std::iter::once(cond.exit()))
+            .build()?
+            .run()?;
+        assert!(report.examples.is_empty());
+        assert!(report.transitions.is_empty());
+        assert!(report.rotations.is_empty());
+        Ok(())
+    }
+
+    /// Tests that recording transitions counts one between each pair of
+    /// successive iterations, across rotations.
+    #[test]
+    fn test_with_transitions() -> err::Result<()> {
+        let report = Builder::new(nop_entry(2))
+            .with_transitions(true)
+            .add_halt_rules(vec![every(20).exit(), every(5).rotate()])
+            .build()?
+            .run()?;
+        assert_eq!(
+            report.transitions,
+            [model::transition::Transition {
+                from: 0,
+                to: 0,
+                occurs: report.iterations() - 1
+            }]
+        );
+        Ok(())
+    }
+
+    /// Tests that each state counts the iterations showing it that raised
+    /// each auxiliary flag, ignoring flags the test doesn't name.
+    #[test]
+    fn test_aux_flags() -> err::Result<()> {
+        use crate::api::abs::AtomicEnv;
+        let mut entry = nop_entry(2);
+        entry.manifest.aux_flags = vec!["odd".to_owned(), "always".to_owned()];
+        entry.test_at = Some(|tid, iter, env| {
+            if tid == 0 && iter.index % 2 == 1 {
+                env.raise_aux_flag(0);
+            }
+            env.raise_aux_flag(1);
+            env.raise_aux_flag(2);
+            env.raise_aux_flag(64);
+        });
+        let cond = every(20);
+        let report = Builder::new(entry)
+            .add_halt_rules(std::iter::once(cond.exit()))
+            .build()?
+            .run()?;
+        let [state] = report.states.as_slice() else {
+            panic!("a test without variables should have one state");
+        };
+        assert_eq!(
+            state.aux_flags,
+            [("always".to_owned(), 20), ("odd".to_owned(), 10)].into()
+        );
+        assert!(report
+            .capabilities
+            .contains(&model::capability::Capability::AuxFlags));
+        Ok(())
+    }
+
+    /// Tests that runs of tests without variables check every iteration,
+    /// and tally their outcomes.
+    #[test]
+    fn test_tally_outcomes() -> err::Result<()> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static LAST: AtomicUsize = AtomicUsize::new(0);
+        let mut entry = nop_entry(2);
+        entry.test_at = Some(|tid, iter, _| {
+            if tid == 0 {
+                LAST.store(iter.index, Ordering::Relaxed);
+            }
+        });
+        entry.check =
+            Some(|_| model::Outcome::from_pass_bool(LAST.load(Ordering::Relaxed) % 4 != 3));
+        let cond = every(20);
+        let report = Builder::new(entry)
+            .with_checker(abs::Entry::checker)
+            .add_halt_rules(std::iter::once(cond.exit()))
+            .build()?
+            .run()?;
+        assert_eq!(
+            report.outcomes,
+            Some(model::outcome::Tally {
+                pass: 15,
+                fail: 5,
+                unknown: 0
+            })
+        );
+        assert_eq!(report.outcome, Some(model::Outcome::Fail));
+        Ok(())
+    }
+
+    /// Tests that segmenting by rotation gives a section for each rotation,
+    /// whose counts sum to the report's.
+    #[test]
+    fn test_with_segment_rotations() -> err::Result<()> {
+        let report = Builder::new(nop_entry(2))
+            .with_segment_rotations(true)
+            .add_halt_rules(vec![every(20).exit(), every(5).rotate()])
+            .build()?
+            .run()?;
+        assert_eq!(report.rotations.len(), report.schedule.len());
+        for (i, section) in report.rotations.iter().enumerate() {
+            assert_eq!(section.rotation, i);
+            assert_eq!(Some(section.assignment.as_slice()), report.schedule.get(i));
+            assert_eq!(section.iterations(), 5);
+        }
+        let total: usize = report
+            .rotations
+            .iter()
+            .map(crate::model::rotation::Section::iterations)
+            .sum();
+        assert_eq!(total, report.iterations());
+        Ok(())
     }
 }
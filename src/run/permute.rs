@@ -1,6 +1,7 @@
 //! Traits for thread permutation.
 
-use rand::{prelude::SliceRandom, thread_rng};
+use crate::model::schedule::Schedule;
+use rand::{prelude::SliceRandom, rngs::StdRng, thread_rng, Rng, SeedableRng};
 
 /// Trait of things that have thread identifiers.
 pub trait HasTid {
@@ -18,7 +19,7 @@ pub trait Permuter<T: HasTid> {
 }
 
 /// Any random number generator can be turned into a permuter.
-impl<'a, R: rand::Rng + ?Sized, T: HasTid> Permuter<T> for R {
+impl<R: rand::Rng + ?Sized, T: HasTid> Permuter<T> for R {
     fn permute(&mut self, threads: &mut [T]) {
         threads.shuffle(self);
     }
@@ -31,17 +32,147 @@ impl<T: HasTid> Permuter<T> for Nop {
     fn permute(&mut self, _: &mut [T]) {}
 }
 
+/// A permuter that replays the assignments from a recorded [Schedule].
+///
+/// Each call to `permute` consumes the next assignment in the schedule.
+/// Once the schedule is exhausted, the replay wraps around to the start.
+/// Threads whose IDs don't appear in an assignment are moved to the end,
+/// in their original order.
+pub struct Replay {
+    /// The schedule being replayed.
+    schedule: Schedule,
+    /// The index of the next assignment to replay.
+    next: usize,
+}
+
+impl Replay {
+    /// Constructs a replaying permuter over `schedule`.
+    #[must_use]
+    pub fn new(schedule: Schedule) -> Self {
+        Self { schedule, next: 0 }
+    }
+}
+
+impl<T: HasTid> Permuter<T> for Replay {
+    fn permute(&mut self, threads: &mut [T]) {
+        if self.schedule.is_empty() {
+            return;
+        }
+        if let Some(assignment) = self.schedule.get(self.next) {
+            threads.sort_by_key(|t| {
+                assignment
+                    .iter()
+                    .position(|x| *x == t.tid())
+                    .unwrap_or(usize::MAX)
+            });
+        }
+        self.next = (self.next + 1) % self.schedule.len();
+    }
+}
+
+/// A permuter that records the assignments made by another permuter into a
+/// [Schedule].
+pub struct Recorder<'a, P: ?Sized> {
+    /// The permuter doing the actual permuting.
+    pub inner: &'a mut P,
+    /// The schedule into which we record assignments.
+    pub schedule: &'a mut Schedule,
+}
+
+impl<T: HasTid, P: Permuter<T> + ?Sized> Permuter<T> for Recorder<'_, P> {
+    fn permute(&mut self, threads: &mut [T]) {
+        self.inner.permute(threads);
+        self.schedule
+            .push(threads.iter().map(HasTid::tid).collect());
+    }
+}
+
 /// Type alias of functions that return fully wrapped permuters.
-pub type Factory<T> = fn() -> Box<dyn Permuter<T>>;
+///
+/// Factories are boxed closures, so that they can capture data such as
+/// schedules to replay.
+pub type Factory<'a, T> = Box<dyn Fn() -> Box<dyn Permuter<T> + Send> + 'a>;
 
-/// Makes a boxed permuter from the thread RNG.
+/// Makes a boxed permuter from a generator seeded by the thread RNG.
 #[must_use]
-pub fn make_thread_rng<T: HasTid>() -> Box<dyn Permuter<T>> {
-    Box::new(thread_rng())
+pub fn make_thread_rng<T: HasTid>() -> Box<dyn Permuter<T> + Send> {
+    Box::new(StdRng::seed_from_u64(thread_rng().gen()))
+}
+
+/// Makes a factory of permuters that draw from generators seeded with
+/// `seed`.
+///
+/// Each permuter starts from the same seed, and so makes the same
+/// permutations in the same order.
+#[must_use]
+pub fn seeded_factory<'a, T: HasTid>(seed: u64) -> Factory<'a, T> {
+    Box::new(move || Box::new(StdRng::seed_from_u64(seed)))
 }
 
 /// Makes a no-operation boxed permuter.
 #[must_use]
-pub fn make_nop<T: HasTid>() -> Box<dyn Permuter<T>> {
+pub fn make_nop<T: HasTid>() -> Box<dyn Permuter<T> + Send> {
     Box::new(Nop)
 }
+
+/// Makes a factory that replays `schedule` from the start each time it is
+/// called.
+#[must_use]
+pub fn replay_factory<'a, T: HasTid + 'a>(schedule: Schedule) -> Factory<'a, T> {
+    Box::new(move || Box::new(Replay::new(schedule.clone())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl HasTid for usize {
+        fn tid(&self) -> usize {
+            *self
+        }
+    }
+
+    /// Tests that recording a replay reproduces the schedule being replayed.
+    #[test]
+    fn test_replay_round_trip() {
+        let want = Schedule(vec![vec![2, 0, 1], vec![1, 2, 0], vec![0, 1, 2]]);
+        let mut replay = Replay::new(want.clone());
+        let mut got = Schedule::default();
+
+        for _ in 0..want.len() {
+            let mut threads = vec![0, 1, 2];
+            Recorder {
+                inner: &mut replay,
+                schedule: &mut got,
+            }
+            .permute(&mut threads);
+        }
+
+        assert_eq!(got, want);
+    }
+
+    /// Tests that permuters from a seeded factory make the same permutations.
+    #[test]
+    fn test_seeded_factory_repeats() {
+        let factory = seeded_factory::<usize>(8);
+        let (mut left, mut right) = (factory(), factory());
+        for _ in 0..4 {
+            let mut want: Vec<usize> = (0..8).collect();
+            let mut got = want.clone();
+            left.permute(&mut want);
+            right.permute(&mut got);
+            assert_eq!(got, want);
+        }
+    }
+
+    /// Tests that replays wrap around once the schedule is exhausted.
+    #[test]
+    fn test_replay_wraps() {
+        let mut replay = Replay::new(Schedule(vec![vec![1, 0]]));
+        for _ in 0..2 {
+            let mut threads = vec![0, 1];
+            replay.permute(&mut threads);
+            assert_eq!(threads, vec![1, 0]);
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! Setting the scheduling priorities of test threads.
+//!
+//! A [Table] holds the priority, if any, of each thread (see
+//! [`model::priority`]), and each test thread sets its own priority before it
+//! enters the automaton loop.  Only Linux supports this at the moment.
+
+use crate::{
+    err,
+    model::{self, priority::Priority},
+};
+use std::{io, num::NonZeroUsize};
+
+crate::features::register! {
+    "runtime" => cfg!(target_os = "linux").then_some("thread-priority"),
+}
+
+/// The priorities to give each thread, indexed by thread ID.
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    priorities: Vec<Option<Priority>>,
+}
+
+impl Table {
+    /// Builds a table from `spec` for a test with `n_threads` threads.
+    ///
+    /// Threads not named in `spec` take the priority for
+    /// [`model::priority::ALL_THREADS`], if there is one.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `spec` names a thread that isn't a thread ID of the test.
+    pub fn new(spec: &model::priority::Spec, n_threads: NonZeroUsize) -> err::Result<Self> {
+        let all = spec.get(model::priority::ALL_THREADS).copied();
+        let mut priorities = vec![all; n_threads.get()];
+        for (thread, priority) in spec {
+            if thread == model::priority::ALL_THREADS {
+                continue;
+            }
+            let bad = |reason: String| err::Error::BadPriority {
+                thread: thread.clone(),
+                reason,
+            };
+            let tid: usize = thread
+                .parse()
+                .map_err(|_| bad("not a thread ID".to_owned()))?;
+            let slot = priorities
+                .get_mut(tid)
+                .ok_or_else(|| bad(format!("the test has only {n_threads} threads")))?;
+            *slot = Some(*priority);
+        }
+        Ok(Self { priorities })
+    }
+
+    /// Gets the priority for thread `tid`, if any.
+    #[must_use]
+    pub fn get(&self, tid: usize) -> Option<Priority> {
+        self.priorities.get(tid).copied().flatten()
+    }
+}
+
+/// Checks whether threads can take the priority `priority`, by setting it on
+/// a throwaway thread.
+///
+/// # Errors
+///
+/// Fails as [`set`] would.
+pub fn check(priority: Priority) -> io::Result<()> {
+    std::thread::spawn(move || set(priority))
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("priority check panicked")))
+}
+
+/// Sets the scheduling priority of the calling thread to `priority`.
+///
+/// # Errors
+///
+/// Fails if the operating system refuses the priority, for instance because
+/// raising it needs privileges, or if the platform doesn't support it.
+#[cfg(target_os = "linux")]
+pub fn set(priority: Priority) -> io::Result<()> {
+    match priority {
+        Priority::Nice(n) => set_nice(n),
+        Priority::Fifo(p) => set_realtime(libc::SCHED_FIFO, p),
+        Priority::RoundRobin(p) => set_realtime(libc::SCHED_RR, p),
+    }
+}
+
+/// Sets the scheduling priority of the calling thread to `priority`.
+///
+/// # Errors
+///
+/// Always fails, as the platform doesn't support setting priorities.
+#[cfg(not(target_os = "linux"))]
+pub fn set(_priority: Priority) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "this platform can't set thread priorities",
+    ))
+}
+
+/// Sets the nice level of the calling thread to `nice`.
+#[cfg(target_os = "linux")]
+fn set_nice(nice: i8) -> io::Result<()> {
+    // On Linux, nice levels are per-thread, and this sets the calling
+    // thread's alone.
+    let tid = libc::id_t::try_from(unsafe { libc::gettid() }).map_err(io::Error::other)?;
+    match unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, libc::c_int::from(nice)) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Moves the calling thread to the realtime scheduling class `policy` at
+/// priority `priority`.
+#[cfg(target_os = "linux")]
+fn set_realtime(policy: libc::c_int, priority: u8) -> io::Result<()> {
+    let param = libc::sched_param {
+        sched_priority: libc::c_int::from(priority),
+    };
+    // This returns the error number, rather than setting errno.
+    match unsafe { libc::pthread_setschedparam(libc::pthread_self(), policy, &raw const param) } {
+        0 => Ok(()),
+        e => Err(io::Error::from_raw_os_error(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::rust,
+        run::testing::{capped_builder, nop_entry},
+    };
+    use std::sync::atomic::{AtomicI32, Ordering};
+
+    /// Tests that per-thread priorities override the one for all threads.
+    #[test]
+    fn test_table() {
+        let spec: model::priority::Spec = [
+            ("*".to_owned(), Priority::Nice(5)),
+            ("1".to_owned(), Priority::Fifo(10)),
+        ]
+        .into();
+        let table = Table::new(&spec, NonZeroUsize::new(3).unwrap()).unwrap();
+        assert_eq!(table.get(0), Some(Priority::Nice(5)));
+        assert_eq!(table.get(1), Some(Priority::Fifo(10)));
+        assert_eq!(table.get(2), Some(Priority::Nice(5)));
+
+        let spec: model::priority::Spec = [("3".to_owned(), Priority::Nice(5))].into();
+        assert!(Table::new(&spec, NonZeroUsize::new(3).unwrap()).is_err());
+    }
+
+    /// Tests that a thread can make itself nicer without touching its
+    /// parent.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_set_nice() {
+        let nice =
+            || unsafe { libc::getpriority(libc::PRIO_PROCESS, libc::gettid().try_into().unwrap()) };
+        let before = nice();
+        let after = std::thread::spawn(move || {
+            set(Priority::Nice(19)).expect("anyone can be nicer");
+            nice()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(after, 19);
+        assert_eq!(nice(), before);
+    }
+
+    /// Tests that threads take the priorities given for them, with thread
+    /// IDs overriding `*`.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_with_priority_sets_threads() -> err::Result<()> {
+        static NICE: [AtomicI32; 2] = [AtomicI32::new(0), AtomicI32::new(0)];
+
+        let mut entry = nop_entry(2);
+        entry.test = |tid, _: &rust::Env| {
+            let nice = unsafe {
+                libc::getpriority(libc::PRIO_PROCESS, libc::gettid().try_into().unwrap())
+            };
+            NICE[tid].store(nice, Ordering::Relaxed);
+        };
+        let spec = [
+            ("*".to_owned(), Priority::Nice(19)),
+            ("1".to_owned(), Priority::Nice(18)),
+        ]
+        .into();
+        capped_builder(entry, 10)
+            .with_priority(spec)
+            .build()?
+            .run()?;
+
+        assert_eq!(NICE[0].load(Ordering::Relaxed), 19);
+        assert_eq!(NICE[1].load(Ordering::Relaxed), 18);
+        Ok(())
+    }
+}
@@ -1,13 +1,18 @@
 //! The high-level test runner.
 //!
 use super::{
-    fsa, halt, instance, obs,
+    affinity, alloc, checkpoint, fsa, fuzz, guard, halt,
+    handle::Handle,
+    instance, limit, marker, obs,
     permute::{self, Permuter},
-    shared, sync,
+    priority, seed, sequential, shared, stats, sync,
+    task::Task,
+    timing, verify,
 };
 use crate::{api::abs, err, model};
 
 /// A builder for tests.
+#[allow(clippy::struct_excessive_bools)] // These mirror config flags.
 pub struct Builder<'entry, E: abs::Entry<'entry>> {
     // TODO(@MattWindsor91): use the actual builder pattern here.
     /// The halting rules that should be applied to tests run by this runner.
@@ -23,48 +28,703 @@ pub struct Builder<'entry, E: abs::Entry<'entry>> {
     checker: abs::check::Factory<'entry, E, E::Env>,
 
     /// The permuter to use for permuting threads.
-    permuter: permute::Factory<fsa::ReadyAutomaton<'entry, E>>,
+    permuter: permute::Factory<'entry, fsa::ReadyAutomaton<'entry, E>>,
+
+    /// The hooks to run after each observation.
+    hooks: Vec<shared::Hook<'entry, E::Env>>,
+
+    /// The distributions from which to draw fuzzed initial values.
+    fuzz: model::fuzz::Spec,
+
+    /// The seed, if any, from which to draw fuzzed initial values.
+    fuzz_seed: Option<u64>,
+
+    /// The run seed, if any, from which to derive the seeds of fuzzing and
+    /// skews not given their own.
+    seed: Option<u64>,
+
+    /// The handle, if any, that built runners should share.
+    handle: Option<Handle>,
+
+    /// The thread count, if any, to which to scale the test.
+    threads: Option<std::num::NonZeroUsize>,
+
+    /// The number of iterations to run before observing the test.
+    warmup: usize,
+
+    /// Whether to fill variables with canary values to detect torn reads.
+    canary: bool,
+
+    /// Whether to record an example context for each distinct state.
+    examples: bool,
+
+    /// Whether to record transitions between the states of successive
+    /// iterations.
+    transitions: bool,
+
+    /// Whether to segment the report's histogram by rotation.
+    segment_rotations: bool,
+
+    /// Whether to count the allocations made while handling each iteration.
+    alloc_audit: bool,
+
+    /// Whether to mark the phases of the run in the kernel's trace buffer.
+    markers: bool,
+
+    /// Whether to check, at each rotation boundary, that the test hasn't
+    /// changed its manifest.
+    verify_manifest: bool,
+
+    /// The interesting states to look out for.
+    interesting: model::interest::Spec,
+
+    /// Further named checkers to combine with the checker from `checker`.
+    checkers: Vec<(String, PartFactory<'entry, E>)>,
+
+    /// How to combine the checker from `checker` with any in `checkers`.
+    check_mode: abs::check::Mode,
+
+    /// The description, if any, of what the checkers check, overriding the
+    /// entry's own.
+    check_description: Option<String>,
+
+    /// The named checkers to run on the whole report at the end of a run.
+    report_checkers: Vec<(String, std::sync::Arc<dyn abs::ReportChecker + 'entry>)>,
+
+    /// The groups of variables onto which to project reports.
+    projections: model::projection::Spec,
+
+    /// The variables to leave out of observed states.
+    mask: std::collections::BTreeSet<String>,
+
+    /// Whether to fold together states of symmetric threads.
+    fold_symmetric: bool,
+
+    /// The delays with which threads enter each iteration.
+    skew: model::skew::Spec,
+
+    /// The CPUs, if any, to which to restrict the test threads.
+    cpus: Option<model::cpu::Set>,
+
+    /// The scheduling priorities at which to run the test threads.
+    priority: model::priority::Spec,
+
+    /// How to respond to test bodies that fork or spawn threads, if at all.
+    guard: Option<guard::Response>,
+
+    /// The resource limits for which runners watch.
+    limits: limit::Limits,
+
+    /// When to reset the environment.
+    reset: shared::Reset,
+
+    /// If given, time the test body on every this many iterations.
+    time_every: Option<std::num::NonZeroUsize>,
+
+    /// The rule, if any, by which to sample observations.
+    sampling: Option<model::sampling::Rule>,
+
+    /// When to check states seen for the first time.
+    checking: obs::Checking,
+
+    /// The callback, if any, to which to deliver periodic run statistics.
+    stats_sink: Option<stats::Callback<'entry>>,
+
+    /// The wall-clock time between run statistics.
+    stats_interval: std::time::Duration,
+
+    /// If given, how often, and for how long, to pause the test threads.
+    pause: Option<halt::Pause>,
+
+    /// The provenance, if any, with which to stamp reports.
+    provenance: Option<model::provenance::Provenance>,
+
+    /// The rule, if any, for checkpointing the observations.
+    checkpoint: Option<checkpoint::Rule>,
+
+    /// The synchronisers, if any, to alternate between rotations, overriding
+    /// `sync`.
+    alternation: Option<std::sync::Arc<sync::Alternation>>,
+
+    /// The order, if any, in which to run the test threads one after another
+    /// on the runner's thread, rather than concurrently.
+    sequential: Option<sequential::Order>,
 }
 
+/// Type of factories for further checkers, which can see the manifest.
+type PartFactory<'entry, E> = Box<
+    dyn Fn(
+            &E,
+            &model::manifest::Manifest,
+        ) -> Box<dyn abs::Checker<<E as abs::Entry<'entry>>::Env> + 'entry>
+        + 'entry,
+>;
+
+/// The name under which the entry's own checker appears among combined
+/// checkers.
+const ENTRY_CHECKER: &str = "test";
+
 impl<'entry, E: abs::Entry<'entry>> Builder<'entry, E> {
     /// Constructs a new builder with minimalistic defaults.
     pub fn new(entry: E) -> Self {
         Self {
             entry,
             halt_rules: vec![],
-            sync: sync::make_spinner,
+            sync: Box::new(sync::make_spinner),
             checker: abs::check::make_unknown,
-            permuter: permute::make_nop,
+            permuter: Box::new(permute::make_nop),
+            hooks: vec![],
+            fuzz: model::fuzz::Spec::new(),
+            fuzz_seed: None,
+            seed: None,
+            handle: None,
+            threads: None,
+            warmup: 0,
+            canary: false,
+            examples: false,
+            transitions: false,
+            segment_rotations: false,
+            alloc_audit: false,
+            markers: false,
+            verify_manifest: false,
+            interesting: model::interest::Spec::new(),
+            checkers: vec![],
+            check_mode: abs::check::Mode::default(),
+            check_description: None,
+            report_checkers: vec![],
+            projections: model::projection::Spec::new(),
+            mask: std::collections::BTreeSet::new(),
+            fold_symmetric: false,
+            skew: model::skew::Spec::new(),
+            cpus: None,
+            priority: model::priority::Spec::new(),
+            guard: None,
+            limits: limit::Limits::default(),
+            reset: shared::Reset::default(),
+            time_every: None,
+            sampling: None,
+            checking: obs::Checking::default(),
+            stats_sink: None,
+            stats_interval: stats::DEFAULT_INTERVAL,
+            pause: None,
+            provenance: None,
+            checkpoint: None,
+            alternation: None,
+            sequential: None,
         }
     }
 
     /// Adds the given halt rules to this builder.
+    #[must_use]
     pub fn add_halt_rules(mut self, rules: impl IntoIterator<Item = halt::Rule>) -> Self {
         self.halt_rules.extend(rules);
         self
     }
 
+    /// Adds a hook to be run after each observation, but before any reset
+    /// of the environment.
+    ///
+    /// Hooks run in the order they were added, on whichever thread is
+    /// currently observing.
+    #[must_use]
+    pub fn with_hook(
+        mut self,
+        hook: impl Fn(&mut obs::Manifested<E::Env>, &obs::Summary) + Send + Sync + 'entry,
+    ) -> Self {
+        self.hooks.push(std::sync::Arc::new(hook));
+        self
+    }
+
+    /// Overrides this builder's fuzzing spec.
+    ///
+    /// Each variable in the spec has its initial value redrawn from its
+    /// distribution on each rotation; resets within a rotation restore the
+    /// rotation's draw.
+    #[must_use]
+    pub fn with_fuzz(mut self, fuzz: model::fuzz::Spec) -> Self {
+        self.fuzz = fuzz;
+        self
+    }
+
+    /// Overrides the seed from which this builder's runners draw fuzzed
+    /// initial values; by default, each draws from a fresh seed.
+    ///
+    /// Runners built with the same seed draw the same initial values on the
+    /// same rotations.
+    #[must_use]
+    pub fn with_fuzz_seed(mut self, seed: Option<u64>) -> Self {
+        self.fuzz_seed = seed;
+        self
+    }
+
+    /// Overrides the run seed from which this builder's runners derive the
+    /// seeds of their fuzzing, unless given a fuzz seed, and of their skews
+    /// (see [`seed::derive`]); by default, each draws from fresh seeds.
+    ///
+    /// Permuters and sampling rules come with their own seeds, which callers
+    /// can derive from the same run seed.
+    #[must_use]
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
     /// Overrides this builder's checker factory.
+    #[must_use]
     pub fn with_checker(mut self, checker: abs::check::Factory<'entry, E, E::Env>) -> Self {
         self.checker = checker;
         self
     }
 
+    /// Adds a further checker, named `name`, to combine with the checker
+    /// from [`Builder::with_checker`].
+    ///
+    /// The factory receives the entry and the (possibly scaled) manifest of
+    /// the test.  If there are further checkers, each state's report records
+    /// the outcome of every checker, with the entry's own checker named
+    /// `test`, and the state's outcome combines them as set by
+    /// [`Builder::with_check_mode`].
+    #[must_use]
+    pub fn add_checker(
+        mut self,
+        name: impl Into<String>,
+        factory: impl Fn(&E, &model::manifest::Manifest) -> Box<dyn abs::Checker<E::Env> + 'entry>
+            + 'entry,
+    ) -> Self {
+        self.checkers.push((name.into(), Box::new(factory)));
+        self
+    }
+
+    /// Sets how to combine the checker from [`Builder::with_checker`] with any
+    /// from [`Builder::add_checker`].
+    #[must_use]
+    pub fn with_check_mode(mut self, mode: abs::check::Mode) -> Self {
+        self.check_mode = mode;
+        self
+    }
+
+    /// Makes runners built from this builder describe what their checkers
+    /// check as `description`, if given, rather than as the entry does.
+    ///
+    /// Reports carry the description, so that they say what they were
+    /// checked against.
+    #[must_use]
+    pub fn with_check_description(mut self, description: Option<String>) -> Self {
+        self.check_description = description;
+        self
+    }
+
+    /// Adds a checker, named `name`, that checks the whole report at the end
+    /// of each run.
+    ///
+    /// Checks run in the order they were added, each seeing the final states
+    /// of the report, and reports record their outcomes under `aggregate`
+    /// and conjoin them into the overall outcome.
+    #[must_use]
+    pub fn add_report_checker(
+        mut self,
+        name: impl Into<String>,
+        checker: impl abs::ReportChecker + 'entry,
+    ) -> Self {
+        self.report_checkers
+            .push((name.into(), std::sync::Arc::new(checker)));
+        self
+    }
+
+    /// Adds a report checker for each rule in `spec`, named after the rule.
+    #[must_use]
+    pub fn with_aggregate(self, spec: model::aggregate::Spec) -> Self {
+        spec.into_iter()
+            .fold(self, |b, (name, rule)| b.add_report_checker(name, rule))
+    }
+
+    /// Makes runners built from this builder project their reports onto
+    /// each group of variables in `spec` (see [`model::projection`]).
+    ///
+    /// Building fails if any group names a variable that the test doesn't
+    /// have.
+    #[must_use]
+    pub fn with_projections(mut self, spec: model::projection::Spec) -> Self {
+        self.projections = spec;
+        self
+    }
+
+    /// Adds a checker, named `forbidden`, that fails states matching any
+    /// state in `spec`.
+    ///
+    /// This does nothing if `spec` is empty.
+    #[must_use]
+    pub fn with_forbidden(self, spec: model::interest::Spec) -> Self {
+        if spec.is_empty() {
+            return self;
+        }
+        self.add_checker("forbidden", move |_, manifest| {
+            Box::new(abs::check::Forbidden::new(spec.clone(), manifest.clone()))
+        })
+    }
+
+    /// Makes runners built from this builder leave the variables in `mask`
+    /// out of observed states.
+    ///
+    /// States that differ only in masked variables then count as the same
+    /// state, and each state's outcome comes from its first occurrence.
+    /// Checkers still see every variable.  Building fails if `mask` names a
+    /// variable that isn't in the test.
+    #[must_use]
+    pub fn with_mask(mut self, mask: impl IntoIterator<Item = String>) -> Self {
+        self.mask = mask.into_iter().collect();
+        self
+    }
+
+    /// Sets whether runners built from this builder fold together states
+    /// that differ only by swapping the registers of symmetric threads.
+    ///
+    /// Threads are symmetric if the test says so (see
+    /// [`model::manifest::Manifest::symmetry`]), or if they replicate the
+    /// same scalable role.  Folded states appear once, under a canonical
+    /// representative, and reports list the groups of threads folded.
+    #[must_use]
+    pub fn with_fold_symmetric(mut self, fold: bool) -> Self {
+        self.fold_symmetric = fold;
+        self
+    }
+
+    /// Makes runners alternate between the synchronisers in `alternation`,
+    /// if given, overriding this builder's synchroniser factory.
+    ///
+    /// The report breaks down its states by the synchroniser active when
+    /// they occurred.  As synchronisers only change between rotations, the
+    /// halt rules must rotate the test.
+    #[must_use]
+    pub fn with_sync_alternation(mut self, alternation: Option<sync::Alternation>) -> Self {
+        self.alternation = alternation.map(std::sync::Arc::new);
+        self
+    }
+
+    /// Makes runners sequentialise the test, if `order` is given: rather
+    /// than running its threads concurrently, they run their bodies one
+    /// after another on the runner's own thread, in the order `order` gives
+    /// for each iteration.
+    ///
+    /// The states of a sequentialised run are those that sequentially
+    /// consistent interleavings of whole thread bodies can produce, which
+    /// makes them a baseline for those of concurrent runs.  Sequentialised
+    /// runs don't synchronise, so they can't alternate synchronisers, and
+    /// skew, CPU restrictions, and priorities don't apply to them.
+    #[must_use]
+    pub fn with_sequential(mut self, order: Option<sequential::Order>) -> Self {
+        self.sequential = order;
+        self
+    }
+
     /// Overrides this builder's synchroniser factory.
-    pub fn with_sync(mut self, sync: sync::Factory) -> Self {
-        self.sync = sync;
+    #[must_use]
+    pub fn with_sync(
+        mut self,
+        sync: impl Fn(std::num::NonZeroUsize) -> err::Result<std::sync::Arc<dyn sync::Synchroniser>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.sync = Box::new(sync);
         self
     }
 
     /// Overrides this builder's permuter factory.
+    #[must_use]
     pub fn with_permuter(
         mut self,
-        permuter: permute::Factory<fsa::ReadyAutomaton<'entry, E>>,
+        permuter: permute::Factory<'entry, fsa::ReadyAutomaton<'entry, E>>,
     ) -> Self {
         self.permuter = permuter;
         self
     }
 
+    /// Makes runners built from this builder use `handle`, rather than each
+    /// getting a fresh handle.
+    ///
+    /// This lets callers control a run through a handle made before the
+    /// runner exists; note that every runner built with the handle shares it.
+    #[must_use]
+    pub fn with_handle(mut self, handle: Handle) -> Self {
+        self.handle = Some(handle);
+        self
+    }
+
+    /// Makes runners built from this builder run the test with `threads`
+    /// threads, replicating its scalable role as needed.
+    ///
+    /// Building fails if the test has no scalable role (unless `threads` is
+    /// its native thread count), or `threads` is fewer than its threads.
+    #[must_use]
+    pub fn with_threads(mut self, threads: std::num::NonZeroUsize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Makes runners built from this builder run `warmup` iterations before
+    /// they start observing the test.
+    ///
+    /// Warmup iterations run the test as normal, but record nothing, run no
+    /// hooks, and count towards no iteration-based halt rules.
+    #[must_use]
+    pub fn with_warmup(mut self, warmup: usize) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Makes runners built from this builder time how long each thread
+    /// spends in the test body on every `every` iterations, if given.
+    ///
+    /// Reports then carry a histogram of the times for each thread.  Timing
+    /// costs two clock reads per timed iteration, so sparser timing disturbs
+    /// fast tests less.
+    #[must_use]
+    pub fn with_timing(mut self, every: Option<std::num::NonZeroUsize>) -> Self {
+        self.time_every = every;
+        self
+    }
+
+    /// Makes runners built from this builder only observe the iterations
+    /// that `rule` samples, if given.
+    ///
+    /// Sampling lowers the observer's overhead at the cost of exact state
+    /// counts: iteration counts, and so iteration-based halt rules, stay
+    /// exact, but states only count sampled iterations, and reports say how
+    /// they were sampled.
+    #[must_use]
+    pub fn with_sampling(mut self, rule: Option<model::sampling::Rule>) -> Self {
+        self.sampling = rule;
+        self
+    }
+
+    /// Makes runners built from this builder check the states they see for
+    /// the first time as `checking` says.
+    ///
+    /// Deferring checks takes expensive checkers off the path between
+    /// iterations; states awaiting their checks have an unknown outcome.
+    /// Building fails if checks are deferred while masking or folding
+    /// variables, or while halting on an outcome, as deferred checks can't
+    /// see masked variables and would see outcomes too late.
+    #[must_use]
+    pub fn with_checking(mut self, checking: obs::Checking) -> Self {
+        self.checking = checking;
+        self
+    }
+
+    /// Makes runners built from this builder deliver snapshots of their
+    /// progress to `sink`, periodically and as they exit.
+    ///
+    /// Snapshots come every [`stats::DEFAULT_INTERVAL`] unless overridden
+    /// by [`Self::with_stats_interval`].  The sink runs on whichever thread
+    /// is observing, holding up the test, so it should be quick.  Runners
+    /// built from the same builder share the sink.
+    #[must_use]
+    pub fn with_stats_sink(mut self, sink: impl FnMut(stats::Snapshot) + Send + 'entry) -> Self {
+        self.stats_sink = Some(std::sync::Arc::new(std::sync::Mutex::new(sink)));
+        self
+    }
+
+    /// Overrides the wall-clock time between snapshots given to any sink
+    /// set by [`Self::with_stats_sink`].
+    #[must_use]
+    pub fn with_stats_interval(mut self, interval: std::time::Duration) -> Self {
+        self.stats_interval = interval;
+        self
+    }
+
+    /// Sets whether runners built from this builder run in canary mode.
+    ///
+    /// In canary mode, non-atomic integer and float variables without initial
+    /// values start each iteration holding a canary value (see
+    /// [`model::canary`]), and reports list any torn reads of them.
+    #[must_use]
+    pub fn with_canary(mut self, canary: bool) -> Self {
+        self.canary = canary;
+        self
+    }
+
+    /// Sets whether runners built from this builder record, for each
+    /// distinct state, the context of the first iteration that showed it
+    /// (see [`model::example`]).
+    #[must_use]
+    pub fn with_examples(mut self, examples: bool) -> Self {
+        self.examples = examples;
+        self
+    }
+
+    /// Sets whether runners built from this builder record how often each
+    /// state follows each other state in successive observed iterations (see
+    /// [`model::transition`]).
+    #[must_use]
+    pub fn with_transitions(mut self, transitions: bool) -> Self {
+        self.transitions = transitions;
+        self
+    }
+
+    /// Sets whether runners built from this builder segment their reports'
+    /// histograms by rotation, giving how often each state occurred in each
+    /// rotation (see [`model::rotation`]).
+    #[must_use]
+    pub fn with_segment_rotations(mut self, segment: bool) -> Self {
+        self.segment_rotations = segment;
+        self
+    }
+
+    /// Sets whether runners built from this builder count the allocations
+    /// made while handling each observed iteration (see
+    /// [`model::allocation`]).
+    ///
+    /// Only builds with the `alloc-audit` feature can count allocations;
+    /// elsewhere, building a runner that audits them fails.
+    #[must_use]
+    pub fn with_alloc_audit(mut self, alloc_audit: bool) -> Self {
+        self.alloc_audit = alloc_audit;
+        self
+    }
+
+    /// Sets whether runners built from this builder mark the start and end
+    /// of the run, and of each rotation, for profilers (see [`marker`]).
+    ///
+    /// Only Linux has somewhere to put the markers; elsewhere, or if the
+    /// kernel's trace marker file can't be opened, building a runner that
+    /// marks fails.
+    #[must_use]
+    pub fn with_markers(mut self, markers: bool) -> Self {
+        self.markers = markers;
+        self
+    }
+
+    /// Sets whether runners built from this builder check, whenever a
+    /// rotation ends, that the test's manifest is the same as when the
+    /// runner was built (see [`verify`]).
+    ///
+    /// A runner that finds a changed manifest fails with
+    /// [`err::Error::ManifestChanged`].
+    #[must_use]
+    pub fn with_verify_manifest(mut self, verify_manifest: bool) -> Self {
+        self.verify_manifest = verify_manifest;
+        self
+    }
+
+    /// Makes runners built from this builder pause all of the test's threads
+    /// as `pause` says, if given.
+    ///
+    /// Pauses don't rotate or end the test: every thread sleeps before its
+    /// next iteration, then carries on.
+    #[must_use]
+    pub fn with_pause(mut self, pause: Option<halt::Pause>) -> Self {
+        self.pause = pause;
+        self
+    }
+
+    /// Makes runners built from this builder stamp their reports with
+    /// `provenance`.
+    ///
+    /// Each runner gets its own copy of the provenance with a fresh run ID,
+    /// so that reports of different runs never share one.
+    #[must_use]
+    pub fn with_provenance(mut self, provenance: model::provenance::Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Makes runners built from this builder checkpoint their observations
+    /// as `checkpoint` says, if given.
+    ///
+    /// Each checkpoint is a partial report, which replaces the last; the
+    /// runner removes the checkpoint once it has made its own report.  If a
+    /// dead run left a checkpoint at the same path, runners resume from it,
+    /// folding it into their checkpoints and report (see [`checkpoint`]).
+    #[must_use]
+    pub fn with_checkpoint(mut self, checkpoint: Option<checkpoint::Rule>) -> Self {
+        self.checkpoint = checkpoint;
+        self
+    }
+
+    /// Sets when runners built from this builder reset the environment.
+    ///
+    /// By default, they reset after every iteration; tests that
+    /// intentionally accumulate state across iterations can ask for resets
+    /// only between rotations, or never.
+    #[must_use]
+    pub fn with_reset(mut self, reset: shared::Reset) -> Self {
+        self.reset = reset;
+        self
+    }
+
+    /// Makes runners built from this builder look out for the interesting
+    /// states in `spec`.
+    ///
+    /// Reports then tag each state with the interesting states it matches,
+    /// and record when each interesting state was first seen; the
+    /// [`halt::Condition::AllInteresting`] condition can stop the run once
+    /// all have been seen.
+    #[must_use]
+    pub fn with_interesting(mut self, spec: model::interest::Spec) -> Self {
+        self.interesting = spec;
+        self
+    }
+
+    /// Makes runners built from this builder delay threads' entry into each
+    /// iteration according to `spec`, which maps thread IDs to delays.
+    ///
+    /// This biases interleavings towards those where the delayed threads
+    /// run late.
+    #[must_use]
+    pub fn with_skew(mut self, spec: model::skew::Spec) -> Self {
+        self.skew = spec;
+        self
+    }
+
+    /// Makes runners built from this builder restrict all of the test's
+    /// threads to the CPUs in `cpus`, if given.
+    ///
+    /// The threads share the whole set rather than each being pinned to one
+    /// CPU, so a set smaller than the thread count oversubscribes it.
+    /// Reports then record the set.
+    #[must_use]
+    pub fn with_cpus(mut self, cpus: Option<model::cpu::Set>) -> Self {
+        self.cpus = cpus;
+        self
+    }
+
+    /// Makes runners built from this builder run the test's threads at the
+    /// scheduling priorities in `spec`, which maps thread IDs (or `*`, for
+    /// every other thread) to priorities.
+    ///
+    /// Giving threads different priorities biases interleavings towards
+    /// those where the favoured threads run first.
+    #[must_use]
+    pub fn with_priority(mut self, spec: model::priority::Spec) -> Self {
+        self.priority = spec;
+        self
+    }
+
+    /// Makes runners built from this builder guard against test bodies that
+    /// fork or spawn threads of their own (see [`guard`]), responding to any
+    /// they notice with `response`.
+    ///
+    /// Reports then list any taints noticed; on [`guard::Response::Abort`],
+    /// the run fails with an [`err::Error::Tainted`] and the partial report.
+    #[must_use]
+    pub fn with_guard(mut self, response: guard::Response) -> Self {
+        self.guard = Some(response);
+        self
+    }
+
+    /// Makes runners built from this builder watch for the process exceeding
+    /// `limits` (see [`limit`]).
+    ///
+    /// This doesn't apply the limits, which cover the whole process; see
+    /// [`limit::Limits::apply`].  Runs that exceed a limit fail with an
+    /// [`err::Error::LimitExceeded`] and the partial report.
+    #[must_use]
+    pub fn with_limits(mut self, limits: limit::Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     /// Builds a test runner with the stored configuration.
     ///
     /// Building doesn't take ownership of the builder, so it can be used to
@@ -73,35 +733,332 @@ impl<'entry, E: abs::Entry<'entry>> Builder<'entry, E> {
     /// # Errors
     ///
     /// Fails if any of the intermediate stages (making a manifest, making a shared state, and so
-    /// on) fail.  Generally, this suggests that the test entry has problems.
+    /// on) fail.  Generally, this suggests that the test entry has problems.  Also fails if
+    /// the test threads can't be restricted to the requested CPUs, or given the requested
+    /// priorities.
     pub fn build(&self) -> err::Result<Runner<'entry, E>> {
-        let manifest = self.entry.make_manifest()?;
-        let shared = self.make_shared_state(manifest)?;
+        self.check_cpus()?;
+        self.check_priorities()?;
+        self.check_checking()?;
+        if self.alloc_audit && !alloc::AVAILABLE {
+            return Err(err::Error::AllocAuditUnavailable);
+        }
+        let markers = if self.markers {
+            marker::Markers::open().map_err(err::Error::MarkersUnavailable)?
+        } else {
+            marker::Markers::none()
+        };
+        let sync = self.first_sync()?;
+        let resumed = match &self.checkpoint {
+            Some(checkpoint) => {
+                checkpoint.prepare()?;
+                checkpoint::read(&checkpoint.path)?
+            }
+            None => None,
+        };
+        let loaded = self.entry.make_manifest()?;
+        let verifier = self
+            .verify_manifest
+            .then(|| verify::Verifier::new(self.entry.clone(), loaded.clone()));
+        let (manifest, roles) = self.scale_manifest(loaded)?;
+        self.check_projections(&manifest)?;
+        let handle = self.handle.clone().unwrap_or_default();
+        let mut shared = self.make_shared_state(manifest.clone(), handle.requests())?;
+
+        let capabilities = self.entry.capabilities();
+        let check_description = self
+            .check_description
+            .clone()
+            .or_else(|| self.entry.check_description());
+        let provenance = self
+            .provenance
+            .as_ref()
+            .map(model::provenance::Provenance::for_new_run);
+        shared.checkpoint = self.checkpoint.clone().map(|rule| {
+            let stamp = model::Report {
+                capabilities: capabilities.clone(),
+                check_description: check_description.clone(),
+                cpus: self.cpus.clone(),
+                roles: roles.clone(),
+                var_order: var_order(&manifest),
+                provenance: provenance.clone(),
+                ..model::Report::default()
+            };
+            checkpoint::Writer::new(rule, stamp, resumed)
+        });
 
         Ok(Runner {
-            instance: Some(instance::Instance::new(
-                self.entry.clone(),
-                self.sync,
-                shared,
-            )?),
+            instance: Some(
+                instance::Instance::new(
+                    self.entry.clone(),
+                    sync,
+                    shared,
+                    &self.skew,
+                    self.seed.map(|s| seed::derive(s, seed::Stream::Skew)),
+                    &self.priority,
+                    self.cpus.as_ref(),
+                )?
+                .with_markers(markers),
+            ),
             permuter: (self.permuter)(),
+            schedule: model::schedule::Schedule::default(),
             report: None,
+            handle,
+            manifest,
+            capabilities,
+            check_description,
+            cpus: self.cpus.clone(),
+            roles,
+            provenance,
+            report_checkers: self.report_checkers.clone(),
+            projections: self.projections.clone(),
+            alternation: self.alternation.clone(),
+            sequential: self.sequential,
+            verifier,
+        })
+    }
+
+    /// Checks that every projection names only variables in `manifest`.
+    fn check_projections(&self, manifest: &model::manifest::Manifest) -> err::Result<()> {
+        for (name, vars) in &self.projections {
+            if let Some(var) = vars.iter().find(|v| !manifest.has_var(v)) {
+                return Err(err::Error::UnknownProjectedVar {
+                    projection: name.clone(),
+                    var: var.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the synchroniser factory for the first rotation, checking that
+    /// any alternation of synchronisers suits the rest of the configuration.
+    fn first_sync(&self) -> err::Result<&sync::Factory> {
+        let Some(alternation) = &self.alternation else {
+            return Ok(&self.sync);
+        };
+        if self.sequential.is_some() {
+            return Err(err::Error::BadSyncAlternation(
+                "a sequentialised run has no synchronisers to alternate",
+            ));
+        }
+        if !self
+            .halt_rules
+            .iter()
+            .any(|r| r.halt_type == halt::Type::Rotate)
+        {
+            return Err(err::Error::BadSyncAlternation(
+                "synchronisers only change between rotations, and the run never rotates",
+            ));
+        }
+        alternation
+            .arm(0)
+            .map(|(_, factory)| factory)
+            .ok_or(err::Error::BadSyncAlternation(
+                "no synchronisers to alternate",
+            ))
+    }
+
+    /// Checks that the test threads can be restricted to the requested CPUs,
+    /// if any, which must be among those this thread may run on.
+    fn check_cpus(&self) -> err::Result<()> {
+        let Some(cpus) = &self.cpus else {
+            return Ok(());
+        };
+        let reason = match affinity::available() {
+            None => "this platform can't restrict threads to CPUs".to_owned(),
+            Some(available) if !cpus.is_subset(&available) => {
+                format!("the run may only use CPUs {available}")
+            }
+            Some(_) => return Ok(()),
+        };
+        Err(err::Error::BadCpus {
+            cpus: cpus.clone(),
+            reason,
         })
     }
 
+    /// Checks that the platform grants the requested priorities, if any.
+    ///
+    /// This tries each priority on a throwaway thread, so that a run that
+    /// needs privileges it lacks fails up front rather than running at the
+    /// wrong priorities.
+    fn check_priorities(&self) -> err::Result<()> {
+        let mut checked = std::collections::HashSet::new();
+        for (thread, &priority) in &self.priority {
+            if checked.insert(priority) {
+                priority::check(priority).map_err(|e| err::Error::BadPriority {
+                    thread: thread.clone(),
+                    reason: e.to_string(),
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the check frequency suits the rest of the configuration.
+    fn check_checking(&self) -> err::Result<()> {
+        if self.checking == obs::Checking::Immediate {
+            return Ok(());
+        }
+        let reason = if !self.mask.is_empty() {
+            "checks can't see masked variables"
+        } else if self.fold_symmetric {
+            "checks can't see which variables were folded"
+        } else if self
+            .halt_rules
+            .iter()
+            .any(|r| matches!(r.condition, halt::Condition::OnOutcome(_)))
+        {
+            "halting on an outcome needs each state checked when first seen"
+        } else {
+            return Ok(());
+        };
+        Err(err::Error::BadCheckFrequency(reason))
+    }
+
+    /// Gets the manifest of the test that runners built from this builder
+    /// will run, scaled to the requested thread count if any.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the test has no manifest, or can't scale to the requested
+    /// thread count.
+    pub fn manifest(&self) -> err::Result<model::manifest::Manifest> {
+        Ok(self.make_manifest()?.0)
+    }
+
+    /// Makes the manifest, scaling it to the requested thread count if any.
+    ///
+    /// If there is a requested thread count, this also gets the role that
+    /// each thread plays (see [`model::manifest::Manifest::roles`]).
+    fn make_manifest(&self) -> err::Result<(model::manifest::Manifest, Vec<usize>)> {
+        self.scale_manifest(self.entry.make_manifest()?)
+    }
+
+    /// Scales `manifest` to the requested thread count if any, also getting
+    /// the role that each thread plays.
+    fn scale_manifest(
+        &self,
+        manifest: model::manifest::Manifest,
+    ) -> err::Result<(model::manifest::Manifest, Vec<usize>)> {
+        let Some(threads) = self.threads else {
+            return Ok((manifest, vec![]));
+        };
+        let bad = |reason| err::Error::BadThreadCount { threads, reason };
+        let roles = manifest.roles(threads).map_err(bad)?;
+        if threads == manifest.n_threads {
+            return Ok((manifest, roles));
+        }
+        Ok((manifest.scale(threads).map_err(bad)?, roles))
+    }
+
+    fn make_checker(
+        &self,
+        manifest: &model::manifest::Manifest,
+    ) -> Box<dyn abs::Checker<E::Env> + 'entry> {
+        let checker = (self.checker)(&self.entry);
+        if self.checkers.is_empty() {
+            return checker;
+        }
+        let parts = std::iter::once((ENTRY_CHECKER.to_owned(), checker))
+            .chain(
+                self.checkers
+                    .iter()
+                    .map(|(name, f)| (name.clone(), f(&self.entry, manifest))),
+            )
+            .collect();
+        self.check_mode.combine(parts)
+    }
+
     fn make_shared_state(
         &self,
         manifest: model::manifest::Manifest,
+        requests: std::sync::Arc<halt::Signal>,
     ) -> err::Result<shared::State<'entry, E::Env>> {
         let mut env = obs::Manifested::for_manifest(manifest)?;
-        env.reset();
+        env.canary = self.canary;
+        env.set_mask(self.mask.clone())?;
+        env.set_fold_symmetric(self.fold_symmetric);
+        if !self.fuzz.is_empty() {
+            let fuzzer = fuzz::Fuzzer::new(self.fuzz.clone(), &env.manifest)?;
+            let seed = self
+                .fuzz_seed
+                .or_else(|| self.seed.map(|s| seed::derive(s, seed::Stream::Fuzz)));
+            env.fuzzer = Some(match seed {
+                Some(seed) => fuzzer.with_seed(seed),
+                None => fuzzer,
+            });
+        }
+        env.reset(true);
 
-        let observer = obs::Observer::new();
+        let mut observer = obs::Observer::with_warmup(self.warmup);
+        observer.watch(self.interesting.clone());
+        if let Some(rule) = self.sampling {
+            observer.sample(rule);
+        }
+        observer.set_checking(self.checking);
+        if env.manifest.is_empty() {
+            observer.tally_outcomes();
+        }
+        if self.examples {
+            observer.capture_examples();
+        }
+        if self.transitions {
+            observer.record_transitions();
+        }
+        if self.segment_rotations {
+            observer.segment_rotations();
+        }
+        if !env.manifest.aux_flags.is_empty() {
+            observer.track_aux_flags(env.manifest.aux_flags.clone());
+        }
+        if let Some(alternation) = &self.alternation {
+            observer.alternate(alternation.names(), alternation.every);
+        }
+        // The test threads spin while the observer works, so aggregating in
+        // the background only pays if there's a core to spare for it.  Its
+        // thread lies outside what `loom` can model.
+        let cores = std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get);
+        if env.manifest.n_threads.get() < cores && !cfg!(feature = "loom") {
+            observer.aggregate_in_background();
+        }
+        if let Some(window) = self
+            .halt_rules
+            .iter()
+            .filter_map(|r| r.condition.rate_window())
+            .max()
+        {
+            observer.track_rate(window);
+        }
+        // This comes last, so that its baseline includes the aggregator.
+        let guard = self
+            .guard
+            .map(|r| guard::Guard::new(r, env.manifest.n_threads));
+        let timing = self
+            .time_every
+            .map(|every| timing::Recorder::new(every, env.manifest.n_threads));
         Ok(shared::State {
             halt_rules: self.halt_rules.clone(),
             observer,
-            checker: (self.checker)(&self.entry),
+            checker: self.make_checker(&env.manifest),
             env,
+            hooks: self.hooks.clone(),
+            requests,
+            guard,
+            limits: self.limits,
+            exceeded: None,
+            reset: self.reset,
+            timing,
+            allocations: self.alloc_audit.then(model::allocation::Audit::default),
+            pause: self.pause,
+            paused_until: None,
+            ended_by: None,
+            checkpoint: None,
+            stats: self
+                .stats_sink
+                .clone()
+                .map(|callback| stats::Sink::new(callback, self.stats_interval)),
         })
     }
 }
@@ -110,7 +1067,36 @@ impl<'entry, E: abs::Entry<'entry>> Builder<'entry, E> {
 pub struct Runner<'entry, E: abs::Entry<'entry>> {
     instance: Option<instance::Instance<'entry, E>>,
     report: Option<model::report::Report>,
-    permuter: Box<dyn Permuter<fsa::ReadyAutomaton<'entry, E>> + 'entry>,
+    permuter: Box<dyn Permuter<fsa::ReadyAutomaton<'entry, E>> + Send + 'entry>,
+    /// The schedule of thread assignments made so far.
+    schedule: model::schedule::Schedule,
+    /// The handle through which other threads can control this run.
+    handle: Handle,
+    /// The manifest of the test, for checking that tests run alongside this
+    /// one match it.
+    manifest: model::manifest::Manifest,
+    /// The optional capabilities of the test, for the report.
+    capabilities: model::capability::Set,
+    /// The description, if any, of what the checkers check, for the report.
+    check_description: Option<String>,
+    /// The CPUs, if any, to which the test threads are restricted, for the
+    /// report.
+    cpus: Option<model::cpu::Set>,
+    /// The role that each thread plays, if the thread count was overridden,
+    /// for the report.
+    roles: Vec<usize>,
+    /// The provenance, if any, with which to stamp the report.
+    provenance: Option<model::provenance::Provenance>,
+    /// The checkers to run on the finished report.
+    report_checkers: Vec<(String, std::sync::Arc<dyn abs::ReportChecker + 'entry>)>,
+    /// The groups of variables onto which to project the finished report.
+    projections: model::projection::Spec,
+    /// The synchronisers, if any, to alternate between rotations.
+    alternation: Option<std::sync::Arc<sync::Alternation>>,
+    /// The order, if any, in which to run the test threads in sequence.
+    sequential: Option<sequential::Order>,
+    /// The check, if any, that the test hasn't changed its manifest.
+    verifier: Option<verify::Verifier<E>>,
 }
 
 impl<'entry, T: abs::Entry<'entry>> Runner<'entry, T> {
@@ -119,16 +1105,169 @@ impl<'entry, T: abs::Entry<'entry>> Runner<'entry, T> {
     /// # Errors
     ///
     /// Fails if any of the rotations of the test fail, which typically means that the test code
-    /// has done something ill-advised.
-    pub fn run(mut self) -> err::Result<model::report::Report> {
-        while let Some(am) = self.instance.take() {
-            match self.run_rotation(am)? {
-                instance::Outcome::Rotate(am) => {
-                    self.instance.replace(am);
+    /// has done something ill-advised.  If the test body panics, the error is
+    /// an [`err::Error::Aborted`] containing the observations made so far.
+    pub fn run(self) -> err::Result<model::report::Report> {
+        let handle = self.handle.clone();
+        let result = {
+            let _span = tracing::info_span!("run").entered();
+            self.run_to_exit()
+        };
+        match &result {
+            Ok(report) => tracing::info!(
+                states = report.states.len(),
+                rotations = report.schedule.len(),
+                "run finished"
+            ),
+            Err(e) => tracing::error!(error = %e, "run failed"),
+        }
+        handle.finish();
+        result
+    }
+
+    /// Runs the Runner's test on a background thread, as a [Task] that
+    /// completes with what [`Self::run`] returns.
+    ///
+    /// Cancel the run through the task, or a handle taken beforehand;
+    /// dropping the task before it completes asks the run to exit.  If the
+    /// run panics, or the thread can't start, the task completes with the
+    /// error.
+    pub fn run_async(self) -> Task
+    where
+        Self: Send + 'static,
+    {
+        Task::spawn(self.handle(), move || self.run())
+    }
+
+    /// Runs this Runner's test alongside `other`'s, alternating rotations
+    /// between them, until both exit; returns this test's report, then the
+    /// other's.
+    ///
+    /// Each rotation of `other` assigns threads in the same order as the
+    /// rotation of this test just before it, so that both tests see the same
+    /// permutations.  If this test exits first, `other` runs on alone, with
+    /// its own permuter, until its own halt rules stop it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the tests' manifests don't match (see
+    /// [`model::manifest::Manifest::mismatch`]), or as [`Self::run`] would
+    /// for either test.
+    pub fn run_alongside(
+        mut self,
+        mut other: Self,
+    ) -> err::Result<(model::report::Report, model::report::Report)> {
+        if let Some(reason) = self.manifest.mismatch(&other.manifest) {
+            return Err(err::Error::MismatchedManifests(reason));
+        }
+        let handles = [self.handle.clone(), other.handle.clone()];
+        let result = {
+            let _span = tracing::info_span!("run alongside").entered();
+            self.rotate_alongside(&mut other)
+                .and_then(|()| Ok((self.into_report()?, other.into_report()?)))
+        };
+        match &result {
+            Ok((left, right)) => tracing::info!(
+                states = left.states.len(),
+                other_states = right.states.len(),
+                "runs finished"
+            ),
+            Err(e) => tracing::error!(error = %e, "runs failed"),
+        }
+        for handle in handles {
+            handle.finish();
+        }
+        result
+    }
+
+    /// Gets a handle through which other threads can halt this run.
+    #[must_use]
+    pub fn handle(&self) -> Handle {
+        self.handle.clone()
+    }
+
+    fn run_to_exit(mut self) -> err::Result<model::report::Report> {
+        while self.rotate(None)? {}
+        self.into_report()
+    }
+
+    /// Alternates rotations between this test and `other` until both exit.
+    fn rotate_alongside(&mut self, other: &mut Self) -> err::Result<()> {
+        while self.instance.is_some() || other.instance.is_some() {
+            let running = self.instance.is_some();
+            self.rotate(None)?;
+            let assignment = running
+                .then(|| self.last_assignment())
+                .flatten()
+                .map(ToOwned::to_owned);
+            other.rotate(assignment.as_deref())?;
+        }
+        Ok(())
+    }
+
+    /// Runs one rotation of the test, if it hasn't exited, returning whether
+    /// it has more rotations to run.
+    ///
+    /// If `assignment` is given, the rotation assigns threads in its order,
+    /// rather than in the permuter's.
+    fn rotate(&mut self, assignment: Option<&[usize]>) -> err::Result<bool> {
+        let Some(am) = self.instance.take() else {
+            return Ok(false);
+        };
+        match self.run_rotation(am, assignment)? {
+            instance::Outcome::Rotate(am) => {
+                self.verify_manifest()?;
+                self.instance.replace(self.resync(am)?);
+                Ok(true)
+            }
+            instance::Outcome::Exit(state) => {
+                let taint = state.guard.as_ref().and_then(guard::Guard::abort_cause);
+                let exceeded = state.exceeded;
+                self.make_report(state);
+                match (exceeded, taint) {
+                    (Some(e), _) => Err(self.abort(err::Error::LimitExceeded(e))),
+                    (None, Some(taint)) => Err(self.abort(err::Error::Tainted(taint))),
+                    (None, None) => self
+                        .verify_manifest()
+                        .map(|()| false)
+                        .map_err(|e| self.abort(e)),
                 }
-                instance::Outcome::Exit(state) => self.make_report(state),
             }
+            instance::Outcome::Panic(mut state, panic) => {
+                state.ended_by = Some(model::ending::Cause::Panicked);
+                self.make_report(state);
+                Err(self.abort(err::Error::ThreadPanic(panic)))
+            }
+        }
+    }
+
+    /// Switches `am` to the next synchroniser, if the run alternates them
+    /// and the next rotation starts a new turn.
+    fn resync(
+        &self,
+        am: instance::Instance<'entry, T>,
+    ) -> err::Result<instance::Instance<'entry, T>> {
+        let next = self.schedule.len();
+        match &self.alternation {
+            Some(alternation) if next % alternation.every == 0 => match alternation.arm(next) {
+                Some((name, factory)) => {
+                    tracing::debug!(rotation = next, sync = %name, "switching synchroniser");
+                    am.resync(factory)
+                }
+                None => Ok(am),
+            },
+            _ => Ok(am),
         }
+    }
+
+    /// Gets the thread assignment of the last rotation run, if any.
+    fn last_assignment(&self) -> Option<&[usize]> {
+        let schedule = self.report.as_ref().map_or(&self.schedule, |r| &r.schedule);
+        schedule.0.last().map(Vec::as_slice)
+    }
+
+    /// Takes the report of a test that has exited.
+    fn into_report(self) -> err::Result<model::report::Report> {
         // TODO(@MattWindsor91): for now
         self.report.ok_or(err::Error::LockReleaseFailed)
     }
@@ -136,12 +1275,519 @@ impl<'entry, T: abs::Entry<'entry>> Runner<'entry, T> {
     fn run_rotation(
         &mut self,
         automata: instance::Instance<'entry, T>,
+        assignment: Option<&[usize]>,
     ) -> err::Result<instance::Outcome<'entry, T>> {
-        crossbeam::thread::scope(|s| automata.run(&s, &mut *self.permuter))
-            .map_err(|_| err::Error::ThreadPanic)?
+        let rotation = self.schedule.len();
+        let _span = tracing::debug_span!("rotation", rotation).entered();
+        let mut replay =
+            assignment.map(|a| permute::Replay::new(model::schedule::Schedule(vec![a.to_vec()])));
+        let inner: &mut dyn Permuter<fsa::ReadyAutomaton<'entry, T>> = match &mut replay {
+            Some(replay) => replay,
+            None => &mut *self.permuter,
+        };
+        let mut permuter = permute::Recorder {
+            inner,
+            schedule: &mut self.schedule,
+        };
+        if let Some(order) = self.sequential {
+            return automata.run(&sequential::Threader::new(order), &mut permuter);
+        }
+        crossbeam::thread::scope(|s| automata.run(&s, &mut permuter))
+            .map_err(|p| err::Error::ThreadPanic(err::Panic::from_payload(None, &*p)))?
+    }
+
+    /// Checks, if the run verifies its manifest, that the test hasn't changed it.
+    fn verify_manifest(&self) -> err::Result<()> {
+        self.verifier
+            .as_ref()
+            .map_or(Ok(()), verify::Verifier::verify)
+    }
+
+    /// Wraps `cause` up with the report made so far.
+    fn abort(&mut self, cause: err::Error) -> err::Error {
+        match self.report.take() {
+            Some(report) => err::Error::Aborted {
+                cause: Box::new(cause),
+                report: Box::new(report),
+            },
+            None => err::Error::LockReleaseFailed,
+        }
+    }
+
+    fn make_report(&mut self, mut state: shared::State<'entry, T::Env>) {
+        state
+            .observer
+            .check_pending(&mut state.env, &*state.checker);
+        let mut report = state
+            .observer
+            .into_report(std::mem::take(&mut self.schedule));
+        report.masked = state.env.mask().iter().cloned().collect();
+        report.folded = state.env.folded();
+        report.orderings = state.env.orderings();
+        report.ended_by = state.ended_by;
+        if let Some(timing) = &state.timing {
+            report.timing = timing.histograms();
+        }
+        report.allocations = state.allocations;
+        report.capabilities = std::mem::take(&mut self.capabilities);
+        report.check_description = self.check_description.take();
+        report.cpus = self.cpus.take();
+        report.roles = std::mem::take(&mut self.roles);
+        report.var_order = var_order(&self.manifest);
+        report.provenance = self.provenance.take();
+        if let Some(guard) = &state.guard {
+            report.tainted = guard.tainted().to_vec();
+        }
+        if let Some(checkpoint) = state.checkpoint {
+            report = checkpoint.finish(report);
+        }
+        for (name, checker) in &self.report_checkers {
+            let outcome = checker.check_report(&report);
+            report.record_aggregate(name.clone(), outcome);
+        }
+        report.project(&self.projections);
+        self.report.replace(report);
+    }
+}
+
+/// Gets the order in which `manifest` declares its variables, for the report.
+fn var_order(manifest: &model::manifest::Manifest) -> Vec<String> {
+    manifest.declared.iter().map(ToString::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::rust,
+        run::{
+            sync,
+            testing::{capped_builder, entry_with_i32s, every, nop_entry, x_counts_iterations},
+            thread,
+        },
+    };
+    use std::{
+        num::NonZeroUsize,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// Tests that a panicking test body produces a partial report rather than
+    /// a hang.
+    #[test]
+    fn test_panic_gives_partial_report() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let mut entry = nop_entry(2);
+        entry.test = |tid, _: &rust::Env| {
+            assert!(
+                tid != 0 || COUNT.fetch_add(1, Ordering::Relaxed) != 10,
+                "deliberate panic"
+            );
+        };
+        let result = capped_builder(entry, 1000).build().and_then(Runner::run);
+
+        let (cause, report) = result.expect_err("run should fail").into_partial();
+        assert!(matches!(
+            cause,
+            err::Error::ThreadPanic(err::Panic { tid: Some(0), .. })
+        ));
+        let report = report.expect("should have partial report");
+        let iterations: usize = report.states.iter().map(|s| s.info.occurs).sum();
+        assert_eq!(iterations, 10);
+        assert_eq!(report.ended_by, Some(model::ending::Cause::Panicked));
+    }
+
+    /// Tests that a panicking hook, which runs while observing, produces a
+    /// partial report rather than a hang.
+    #[test]
+    fn test_hook_panic_gives_partial_report() {
+        let result = Builder::new(nop_entry(2))
+            .with_sync(sync::make_barrier)
+            .with_hook(|_, summary| assert!(summary.iterations != 10, "deliberate panic"))
+            .build()
+            .and_then(Runner::run);
+
+        let (cause, report) = result.expect_err("run should fail").into_partial();
+        assert!(matches!(cause, err::Error::ThreadPanic(_)));
+        let report = report.expect("should have partial report");
+        assert_eq!(report.ended_by, Some(model::ending::Cause::Panicked));
+    }
+
+    /// A threader that spawns crossbeam threads, but panics rather than
+    /// joining them.
+    struct PanickingJoin<'scope, 'a>(&'scope crossbeam::thread::Scope<'a>);
+
+    impl<'a, 'scope> thread::Threader<'a, 'scope> for PanickingJoin<'scope, 'a> {
+        type Handle = thread::CrossbeamHandle<'scope>;
+
+        fn spawn<T: abs::Entry<'a> + 'a>(
+            &'scope self,
+            automaton: fsa::ReadyAutomaton<'a, T>,
+        ) -> err::Result<Self::Handle> {
+            thread::Threader::spawn(&self.0, automaton)
+        }
+
+        fn join(&'scope self, _: Self::Handle) -> err::Result<fsa::Done> {
+            panic!("deliberate panic")
+        }
+    }
+
+    /// Tests that a panic unwinding out of a run that has no halt rules still
+    /// stops its threads, rather than leaving them to run forever.
+    #[test]
+    fn test_panic_mid_run_stops_threads() {
+        let mut runner = Builder::new(nop_entry(2))
+            .with_sync(sync::make_barrier)
+            .build()
+            .unwrap();
+        let instance = runner.instance.take().unwrap();
+
+        // Without the instance telling them to exit as the panic drops it,
+        // the scope would wait forever to join the threads.
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            crossbeam::thread::scope(|s| {
+                let _ = instance.run(&PanickingJoin(s), &mut permute::Nop);
+            })
+        }));
+        assert!(result.is_err());
+    }
+
+    /// Tests that alternating synchronisers switches them every so many
+    /// rotations, and breaks down the report by synchroniser.
+    #[test]
+    fn test_with_sync_alternation() -> err::Result<()> {
+        let made = [(); 2].map(|()| std::sync::Arc::new(AtomicUsize::new(0)));
+        let counting = |i: usize, inner: fn(NonZeroUsize) -> err::Result<_>| {
+            let counter = made[i].clone();
+            let factory: sync::Factory = Box::new(move |n| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                inner(n)
+            });
+            factory
+        };
+        let alternation = sync::Alternation {
+            arms: vec![
+                ("spinner".to_owned(), counting(0, sync::make_spinner)),
+                ("barrier".to_owned(), counting(1, sync::make_barrier)),
+            ],
+            every: NonZeroUsize::new(2).unwrap(),
+        };
+
+        let report = Builder::new(nop_entry(2))
+            .with_sync_alternation(Some(alternation))
+            .add_halt_rules(vec![every(30).exit(), every(5).rotate()])
+            .build()?
+            .run()?;
+
+        // Rotations 0, 1, 4, and 5 spin, and rotations 2 and 3 wait at a
+        // barrier.
+        assert_eq!(report.schedule.len(), 6);
+        let made = made.map(|m| m.load(Ordering::Relaxed));
+        assert_eq!(made, [2, 1]);
+        let alternation = report.alternation.unwrap();
+        let arms: Vec<_> = alternation
+            .arms
+            .iter()
+            .map(|a| (a.sync.as_str(), a.rotations, a.iterations()))
+            .collect();
+        assert_eq!(arms, [("spinner", 4, 20), ("barrier", 2, 10)]);
+        assert!(alternation.distance().abs() < f64::EPSILON);
+        Ok(())
+    }
+
+    /// Tests that alternating synchronisers needs the run to rotate.
+    #[test]
+    fn test_with_sync_alternation_needs_rotation() {
+        let alternation = sync::Alternation {
+            arms: vec![("barrier".to_owned(), Box::new(sync::make_barrier))],
+            every: NonZeroUsize::MIN,
+        };
+        let cond = every(10);
+        let result = Builder::new(nop_entry(2))
+            .with_sync_alternation(Some(alternation))
+            .add_halt_rules(std::iter::once(cond.exit()))
+            .build();
+        assert!(matches!(result, Err(err::Error::BadSyncAlternation(_))));
+    }
+
+    /// Tests that sequentialised runs can't alternate synchronisers.
+    #[test]
+    fn test_with_sequential_rejects_alternation() {
+        let alternation = sync::Alternation {
+            arms: vec![("barrier".to_owned(), Box::new(sync::make_barrier))],
+            every: NonZeroUsize::MIN,
+        };
+        let cond = every(10);
+        let result = Builder::new(nop_entry(2))
+            .with_sync_alternation(Some(alternation))
+            .with_sequential(Some(sequential::Order::Enumerate))
+            .add_halt_rules(vec![cond.clone().exit(), cond.rotate()])
+            .build();
+        assert!(matches!(result, Err(err::Error::BadSyncAlternation(_))));
+    }
+
+    /// Tests that scaling a test gives each extra thread its own copy of the
+    /// scalable role's registers.
+    #[test]
+    fn test_with_threads_replicates_role() -> err::Result<()> {
+        let mut entry = entry_with_i32s(2, &[("1:r0", true, Some(0))]);
+        entry.manifest.scalable_role = Some(1);
+        // Replica k of role 1 has its register at slot k - 1.
+        entry.test = |tid, env: &rust::Env| {
+            if let Some(slot) = tid.checked_sub(1) {
+                env.i32s.atomic[slot].store(i32::try_from(tid).unwrap(), Ordering::Relaxed);
+            }
+        };
+        let report = capped_builder(entry, 3)
+            .with_threads(NonZeroUsize::new(4).unwrap())
+            .build()?
+            .run()?;
+        assert_eq!(report.states.len(), 1);
+        let state = &report.states[0].state;
+        for (name, want) in [("1:r0", 1), ("2:r0", 2), ("3:r0", 3)] {
+            assert_eq!(state[name], model::state::Value::I32(want));
+        }
+        assert_eq!(report.schedule.len(), 1);
+        assert_eq!(report.roles, [0, 1, 1, 1]);
+        Ok(())
+    }
+
+    /// Tests that any test can keep its own thread count, and that reports
+    /// only record roles if the thread count was overridden.
+    #[test]
+    fn test_with_threads_records_roles() -> err::Result<()> {
+        let entry = nop_entry(2);
+        let cond = every(1);
+        let builder = Builder::new(entry).add_halt_rules(std::iter::once(cond.exit()));
+        assert!(builder.build()?.run()?.roles.is_empty());
+
+        let builder = builder.with_threads(NonZeroUsize::new(2).unwrap());
+        assert_eq!(builder.build()?.run()?.roles, [0, 1]);
+        Ok(())
+    }
+
+    /// Tests that tests without a scalable role can't be scaled.
+    #[test]
+    fn test_with_threads_needs_scalable_role() {
+        let entry = nop_entry(2);
+        let result = Builder::new(entry)
+            .with_sync(sync::make_barrier)
+            .with_threads(NonZeroUsize::new(3).unwrap())
+            .build();
+        assert!(matches!(
+            result,
+            Err(err::Error::BadThreadCount {
+                reason: model::manifest::ScaleError::NotScalable(_),
+                ..
+            })
+        ));
+    }
+
+    /// Tests that priorities for threads the test doesn't have are errors.
+    #[test]
+    fn test_with_priority_needs_thread_ids() {
+        let entry = nop_entry(2);
+        let spec = [("2".to_owned(), model::priority::Priority::Nice(19))].into();
+        let result = Builder::new(entry).with_priority(spec).build();
+        assert!(matches!(result, Err(err::Error::BadPriority { thread, .. }) if thread == "2"));
+    }
+
+    /// Tests that runners from the same builder stamp their reports with
+    /// the builder's provenance, but their own run IDs.
+    #[test]
+    fn test_with_provenance_stamps_each_run() -> err::Result<()> {
+        let provenance = model::provenance::Provenance {
+            seed: Some(8),
+            ..model::provenance::Provenance::default()
+        };
+        let builder = capped_builder(nop_entry(2), 1).with_provenance(provenance.clone());
+        let stamps = [builder.build()?.run()?, builder.build()?.run()?]
+            .map(|r| r.provenance.expect("report should have provenance"));
+
+        assert!(stamps.iter().all(|p| p.seed == Some(8)));
+        assert_ne!(stamps[0].run_id, stamps[1].run_id);
+        assert_ne!(stamps[0].run_id, provenance.run_id);
+        Ok(())
+    }
+
+    /// Tests that reports carry the entry's check description, unless the
+    /// builder overrides it.
+    #[test]
+    fn test_with_check_description() -> err::Result<()> {
+        let entry = nop_entry(1);
+        let entry = rust::Static {
+            check_description: Some("from the entry"),
+            ..entry
+        };
+        let cond = every(1);
+        let builder = Builder::new(entry).add_halt_rules(std::iter::once(cond.exit()));
+        let described = |b: &Builder<'static, rust::Static>| -> err::Result<Option<String>> {
+            Ok(b.build()?.run()?.check_description)
+        };
+        assert_eq!(described(&builder)?.as_deref(), Some("from the entry"));
+
+        let builder = builder.with_check_description(Some("from the config".to_owned()));
+        assert_eq!(described(&builder)?.as_deref(), Some("from the config"));
+        Ok(())
+    }
+
+    /// Tests that report checkers see the whole report, and that their
+    /// outcomes feed into the overall outcome.
+    #[test]
+    fn test_report_checkers_judge_outcome() -> err::Result<()> {
+        fn iterations(report: &model::Report) -> model::Outcome {
+            let n: usize = report.states.iter().map(|s| s.info.occurs).sum();
+            model::Outcome::from_pass_bool(n == 10)
+        }
+        let entry = nop_entry(2);
+        let never = model::aggregate::Rule {
+            state: model::interest::Matcher(
+                [("missing".to_owned(), model::interest::Literal::Int(1))].into(),
+            ),
+            min: Some(1),
+            ..model::aggregate::Rule::default()
+        };
+        let report = capped_builder(entry, 10)
+            .with_checker(|_| Box::new(model::Outcome::Pass))
+            .add_report_checker("iterations", iterations as fn(&model::Report) -> _)
+            .with_aggregate([("never".to_owned(), never)].into())
+            .build()?
+            .run()?;
+
+        assert_eq!(report.aggregate["iterations"], model::Outcome::Pass);
+        assert_eq!(report.aggregate["never"], model::Outcome::Fail);
+        assert!(report
+            .states
+            .iter()
+            .all(|s| s.info.outcome == model::Outcome::Pass));
+        assert_eq!(report.outcome, Some(model::Outcome::Fail));
+        Ok(())
+    }
+
+    /// Tests that runs can't be restricted to CPUs they may not use.
+    #[test]
+    fn test_with_cpus_needs_available_cpus() {
+        let entry = nop_entry(2);
+        let result = Builder::new(entry)
+            .with_cpus(Some(std::iter::once(1 << 20).collect()))
+            .build();
+        assert!(matches!(result, Err(err::Error::BadCpus { .. })));
+    }
+
+    /// Tests that forbidden states combine with the test's own checker, and
+    /// that reports keep each checker's outcome.
+    #[test]
+    fn test_forbidden_combines_with_checker() -> err::Result<()> {
+        let mut entry = entry_with_i32s(1, &[("x", false, Some(0))]);
+        entry.check = Some(|_| model::Outcome::Pass);
+        let forbidden = model::interest::Spec::from([(
+            "two".to_owned(),
+            model::interest::Matcher([("x".to_owned(), model::interest::Literal::Int(2))].into()),
+        )]);
+
+        let report = capped_builder(entry, 4)
+            .with_checker(abs::Entry::checker)
+            .with_forbidden(forbidden)
+            .with_hook(x_counts_iterations)
+            .build()?
+            .run()?;
+
+        assert_eq!(report.outcome, Some(model::Outcome::Fail));
+        for s in &report.states {
+            let is_two = s.state["x"] == model::state::Value::I32(2);
+            let want = model::Outcome::from_pass_bool(!is_two);
+            assert_eq!(s.info.outcome, want);
+            assert_eq!(s.checks["test"], model::Outcome::Pass);
+            assert_eq!(s.checks["forbidden"], want);
+        }
+        Ok(())
+    }
+
+    /// Tests that tests run alongside each other see the same permutations,
+    /// and must have matching manifests.
+    #[test]
+    fn test_run_alongside() -> err::Result<()> {
+        let with_x =
+            |n_threads, initial| entry_with_i32s(n_threads, &[("x", false, Some(initial))]);
+        let build = |entry| {
+            capped_builder(entry, 8)
+                .with_permuter(Box::new(permute::make_thread_rng))
+                .add_halt_rules(std::iter::once(every(1).rotate()))
+                .build()
+        };
+
+        let (left, right) = build(with_x(3, 0))?.run_alongside(build(with_x(3, 1))?)?;
+        assert_eq!(left.schedule.len(), 8);
+        assert_eq!(left.schedule, right.schedule);
+        let x = |report: &model::Report| report.states[0].state["x"];
+        assert_eq!(x(&left), model::state::Value::I32(0));
+        assert_eq!(x(&right), model::state::Value::I32(1));
+
+        let result = build(with_x(3, 0))?.run_alongside(build(with_x(2, 0))?);
+        assert!(matches!(result, Err(err::Error::MismatchedManifests(_))));
+        Ok(())
+    }
+
+    /// Tests, under `loom`, that the automata, synchroniser, and observer of
+    /// a tiny run hand the shared state between threads soundly.
+    #[cfg(feature = "loom")]
+    #[test]
+    fn loom_instance() {
+        let mut model = loom::model::Builder::new();
+        model.preemption_bound = Some(2);
+        model.check(|| {
+            // The model's main thread has too small a stack to build runners.
+            let builder = loom::thread::Builder::new().stack_size(thread::LOOM_STACK_SIZE);
+            builder.spawn(run_tiny_instance).unwrap().join().unwrap();
+        });
+    }
+
+    /// Runs two iterations of store buffering on a `loom` model.
+    #[cfg(feature = "loom")]
+    fn run_tiny_instance() {
+        let spec = crate::api::dsl::Spec {
+            threads: vec![
+                "store x 1; r0 = load y".to_owned(),
+                "store y 1; r0 = load x".to_owned(),
+            ],
+            init: std::collections::BTreeMap::new(),
+        };
+        let cond = every(2);
+        let mut runner = Builder::new(spec.compile().unwrap())
+            .with_sync(sync::make_spinner)
+            .add_halt_rules(std::iter::once(cond.exit()))
+            .build()
+            .unwrap();
+        let instance = runner.instance.take().unwrap();
+        match instance.run(&thread::Loom, &mut *runner.permuter) {
+            Ok(instance::Outcome::Exit(state)) => {
+                let report = state
+                    .observer
+                    .into_report(model::schedule::Schedule::default());
+                let iterations: usize = report.states.iter().map(|s| s.info.occurs).sum();
+                assert_eq!(iterations, 2);
+            }
+            _ => panic!("the run should exit after two iterations"),
+        }
+    }
+
+    /// Tests that builds that can't count allocations refuse to audit them.
+    #[cfg(not(feature = "alloc-audit"))]
+    #[test]
+    fn test_alloc_audit_unavailable() {
+        let result = Builder::new(nop_entry(2)).with_alloc_audit(true).build();
+        assert!(matches!(result, Err(err::Error::AllocAuditUnavailable)));
     }
 
-    fn make_report(&mut self, state: shared::State<'entry, T::Env>) {
-        self.report.replace(state.observer.into_report());
+    /// Tests that runners refuse to project onto variables the test lacks.
+    #[test]
+    fn test_projections_unknown_var() {
+        let spec = [("flags".to_owned(), vec!["x".to_owned()])].into();
+        let result = Builder::new(nop_entry(2)).with_projections(spec).build();
+        assert!(matches!(
+            result,
+            Err(err::Error::UnknownProjectedVar { projection, var })
+                if projection == "flags" && var == "x"
+        ));
     }
 }
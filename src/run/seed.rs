@@ -0,0 +1,51 @@
+//! Seeds for the pseudo-random choices that runs make.
+//!
+//! Runs make pseudo-random choices in several places: fuzzing initial values,
+//! permuting threads, drawing skews, and sampling observations.  Rather than
+//! seed each separately, callers give a run one seed, from which [derive]
+//! gets a distinct seed for each [Stream] of choices; recording that one seed
+//! is then enough to repeat every choice.
+
+/// A stream of pseudo-random choices that a run makes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Stream {
+    /// Fuzzed initial values.
+    Fuzz,
+    /// Thread permutations.
+    Permute,
+    /// Delays drawn from skew ranges.
+    Skew,
+    /// Random sampling of observations.
+    Sample,
+}
+
+/// Draws a fresh run seed.
+///
+/// Seeds stay within the range of TOML integers, which are signed, so that
+/// configs can record them.
+#[must_use]
+pub fn fresh() -> u64 {
+    rand::random::<u64>() >> 1
+}
+
+/// Derives the seed of `stream` from the run seed `seed`.
+///
+/// This mixes the two with the `SplitMix64` finaliser, so that neither the
+/// streams of one run seed nor like streams of nearby run seeds correlate.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::run::seed::{derive, Stream};
+/// assert_eq!(derive(8, Stream::Fuzz), derive(8, Stream::Fuzz));
+/// assert_ne!(derive(8, Stream::Fuzz), derive(8, Stream::Permute));
+/// assert_ne!(derive(8, Stream::Fuzz), derive(9, Stream::Fuzz));
+/// ```
+#[must_use]
+pub fn derive(seed: u64, stream: Stream) -> u64 {
+    let stream = stream as u64 + 1;
+    let mut z = seed.wrapping_add(stream.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
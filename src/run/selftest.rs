@@ -0,0 +1,256 @@
+//! Canonical litmus tests, built in as native Rust entries.
+//!
+//! These tests (SB, MP, LB, and IRIW) each exist to expose one relaxed
+//! behaviour.  Running them checks that an installation works, and shows
+//! which of the behaviours the current machine and toolchain exhibit; they
+//! also serve as worked examples of writing tests against [`rust::Static`].
+//!
+//! Every variable, registers included, is a relaxed atomic, so the tests are
+//! free of data races; any weak behaviour comes from the hardware or the
+//! compiler's reordering of relaxed accesses.
+
+use super::{halt, sync, Builder};
+use crate::{
+    api::{abs::AtomicEnv, rust},
+    err,
+    model::{self, interest, manifest, slot},
+};
+use std::{num::NonZeroUsize, sync::atomic::Ordering};
+
+/// The name under which each test's weak behaviour appears among its
+/// interesting states.
+pub const WEAK: &str = "weak";
+
+/// A built-in litmus test.
+pub struct Test {
+    /// The conventional name of the test.
+    pub name: &'static str,
+    /// The test entry.
+    pub entry: rust::Static,
+    /// The relaxed behaviour that the test exists to expose.
+    pub weak: interest::Matcher,
+}
+
+/// The result of running a built-in test.
+#[derive(Copy, Clone, Debug)]
+pub struct Observation {
+    /// The number of iterations in which the test showed its weak behaviour.
+    pub weak: usize,
+    /// The number of iterations run.
+    pub iterations: usize,
+}
+
+impl Observation {
+    /// Gets whether the test showed its weak behaviour at all.
+    #[must_use]
+    pub fn is_weak(&self) -> bool {
+        self.weak != 0
+    }
+}
+
+impl Test {
+    /// Runs this test for `iterations` iterations, synchronising with `sync`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the runner fails to build or run.
+    pub fn run(&self, sync: sync::Factory, iterations: NonZeroUsize) -> err::Result<Observation> {
+        let spec: interest::Spec = [(WEAK.to_owned(), self.weak.clone())].into();
+        let report = Builder::new(self.entry.clone())
+            .with_sync(sync)
+            .with_interesting(spec)
+            .add_halt_rules(std::iter::once(
+                halt::Condition::EveryNIterations(iterations).exit(),
+            ))
+            .build()?
+            .run()?;
+        let weak = report
+            .states
+            .iter()
+            .filter(|s| self.weak.matches(&s.state))
+            .map(|s| s.info.occurs)
+            .sum();
+        let iterations = report.states.iter().map(|s| s.info.occurs).sum();
+        Ok(Observation { weak, iterations })
+    }
+}
+
+/// Gets the built-in tests.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::run::{selftest, sync};
+/// use std::num::NonZeroUsize;
+///
+/// let sb = selftest::tests().into_iter().find(|t| t.name == "SB").unwrap();
+/// let obs = sb.run(Box::new(sync::make_barrier), NonZeroUsize::new(100).unwrap()).unwrap();
+/// assert_eq!(obs.iterations, 100);
+/// ```
+#[must_use]
+pub fn tests() -> Vec<Test> {
+    vec![sb(), mp(), lb(), iriw()]
+}
+
+/// Store buffering: can both threads miss each other's store?
+fn sb() -> Test {
+    Test {
+        name: "SB",
+        entry: entry(2, &["x", "y", "0:r0", "1:r0"], |tid, env| {
+            let (mine, theirs) = if tid == 0 { (0, 1) } else { (1, 0) };
+            store(env, mine, 1);
+            store(env, 2 + tid, load(env, theirs));
+        }),
+        weak: matcher(&[("0:r0", 0), ("1:r0", 0)]),
+    }
+}
+
+/// Message passing: can a thread see a flag without the data behind it?
+fn mp() -> Test {
+    Test {
+        name: "MP",
+        entry: entry(2, &["data", "flag", "1:r0", "1:r1"], |tid, env| {
+            if tid == 0 {
+                store(env, 0, 1);
+                store(env, 1, 1);
+            } else {
+                store(env, 2, load(env, 1));
+                store(env, 3, load(env, 0));
+            }
+        }),
+        weak: matcher(&[("1:r0", 1), ("1:r1", 0)]),
+    }
+}
+
+/// Load buffering: can both threads read the other's later store?
+fn lb() -> Test {
+    Test {
+        name: "LB",
+        entry: entry(2, &["x", "y", "0:r0", "1:r0"], |tid, env| {
+            let (mine, theirs) = if tid == 0 { (0, 1) } else { (1, 0) };
+            store(env, 2 + tid, load(env, theirs));
+            store(env, mine, 1);
+        }),
+        weak: matcher(&[("0:r0", 1), ("1:r0", 1)]),
+    }
+}
+
+/// Independent reads of independent writes: can two readers see two
+/// unrelated writes in opposite orders?
+fn iriw() -> Test {
+    Test {
+        name: "IRIW",
+        entry: entry(
+            4,
+            &["x", "y", "2:r0", "2:r1", "3:r0", "3:r1"],
+            |tid, env| match tid {
+                0 | 1 => store(env, tid, 1),
+                _ => {
+                    let (first, second) = if tid == 2 { (0, 1) } else { (1, 0) };
+                    let regs = 2 + 2 * (tid - 2);
+                    store(env, regs, load(env, first));
+                    store(env, regs + 1, load(env, second));
+                }
+            },
+        ),
+        weak: matcher(&[("2:r0", 1), ("2:r1", 0), ("3:r0", 1), ("3:r1", 0)]),
+    }
+}
+
+/// Makes an entry with `n_threads` threads running `test`, over the atomic
+/// integer variables `vars`, all initially 0.
+///
+/// Each variable's slot, and its place in the declaration order, is its
+/// position in `vars`.
+fn entry(n_threads: usize, vars: &[&str], test: fn(usize, &rust::Env)) -> rust::Static {
+    let i32s = vars
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let record = manifest::VarRecord {
+                initial_value: Some(0),
+                slot: atomic(index),
+            };
+            ((*name).into(), record)
+        })
+        .collect();
+    rust::Static {
+        manifest: model::Manifest {
+            n_threads: NonZeroUsize::new(n_threads).unwrap_or(NonZeroUsize::MIN),
+            i32s,
+            bools: manifest::VarMap::new(),
+            f64s: manifest::VarMap::new(),
+            scalable_role: None,
+            symmetry: vec![],
+            invariants: model::invariant::Map::new(),
+            aux_flags: vec![],
+            declared: vars.iter().map(|&name| name.into()).collect(),
+        },
+        test,
+        test_at: None,
+        check: None,
+        check_description: None,
+    }
+}
+
+fn load(env: &rust::Env, index: usize) -> i32 {
+    env.load_i32(atomic(index), Ordering::Relaxed)
+}
+
+fn store(env: &rust::Env, index: usize, value: i32) {
+    env.store_i32(atomic(index), value, Ordering::Relaxed);
+}
+
+fn atomic(index: usize) -> slot::Slot {
+    slot::Slot {
+        is_atomic: true,
+        index,
+    }
+}
+
+fn matcher(vars: &[(&str, i64)]) -> interest::Matcher {
+    interest::Matcher(
+        vars.iter()
+            .map(|(name, v)| ((*name).to_owned(), interest::Literal::Int(*v)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that every built-in test runs, observes only its own variables,
+    /// and has a weak behaviour over those variables.
+    #[test]
+    fn test_tests_run() {
+        let iterations = NonZeroUsize::new(20).unwrap();
+        for test in tests() {
+            let vars: Vec<_> = test.entry.manifest.i32s.keys().collect();
+            assert!(
+                test.weak
+                    .0
+                    .keys()
+                    .all(|v| vars.iter().any(|n| *n == v.as_str())),
+                "{}",
+                test.name
+            );
+
+            let report = Builder::new(test.entry.clone())
+                .add_halt_rules(std::iter::once(
+                    halt::Condition::EveryNIterations(iterations).exit(),
+                ))
+                .build()
+                .unwrap()
+                .run()
+                .unwrap();
+            for s in &report.states {
+                assert_eq!(s.state.keys().collect::<Vec<_>>(), vars, "{}", test.name);
+            }
+
+            let obs = test.run(Box::new(sync::make_barrier), iterations).unwrap();
+            assert_eq!(obs.iterations, iterations.get(), "{}", test.name);
+            assert!(obs.weak <= obs.iterations, "{}", test.name);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Sequentialised runs, which interleave test threads on one OS thread.
+//!
+//! A sequentialised run drives every test thread's body on the runner's own
+//! thread, one after another, in an order that depends only on the iteration
+//! index.  Each iteration then shows exactly one sequentially consistent
+//! interleaving of whole thread bodies, so the states that such a run shows
+//! are a baseline against which to compare those of a concurrent run: any
+//! state that the concurrent run shows and this doesn't needs some overlap
+//! between the threads.
+//!
+//! The run bypasses the synchroniser, but observes, checks, resets, and halts
+//! just as a concurrent run does.
+
+use super::{fsa, thread};
+use crate::{api::abs, err};
+
+/// Orders in which a sequentialised run can run the test threads.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Order {
+    /// Start each iteration with the next thread along from the last
+    /// iteration's, wrapping around: `0, 1, 2`, then `1, 2, 0`, then
+    /// `2, 0, 1`, and so on.
+    RoundRobin,
+    /// Go through every permutation of the threads in lexicographic order,
+    /// one per iteration, then start again.
+    Enumerate,
+}
+
+impl Order {
+    /// Gets the order of thread IDs to run in iteration `index` of a test
+    /// with `n` threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::run::sequential::Order;
+    ///
+    /// assert_eq!(Order::RoundRobin.order(4, 3), [1, 2, 0]);
+    ///
+    /// let all: Vec<_> = (0..7).map(|i| Order::Enumerate.order(i, 3)).collect();
+    /// assert_eq!(all[0], [0, 1, 2]);
+    /// assert_eq!(all[1], [0, 2, 1]);
+    /// assert_eq!(all[5], [2, 1, 0]);
+    /// assert_eq!(all[6], all[0]);
+    /// ```
+    #[must_use]
+    pub fn order(self, index: usize, n: usize) -> Vec<usize> {
+        if n == 0 {
+            return vec![];
+        }
+        match self {
+            Self::RoundRobin => {
+                let start = index % n;
+                (start..n).chain(0..start).collect()
+            }
+            Self::Enumerate => nth_permutation(index, n),
+        }
+    }
+}
+
+/// Gets permutation number `index` (modulo `n!`), in lexicographic order, of
+/// the numbers below `n`.
+fn nth_permutation(index: usize, n: usize) -> Vec<usize> {
+    // If `n!` doesn't fit, every index is below it.
+    let mut rest = (1..=n)
+        .try_fold(1usize, usize::checked_mul)
+        .map_or(index, |count| index % count);
+    let mut left: Vec<usize> = (0..n).collect();
+    let mut perm = Vec::with_capacity(n);
+    for k in (0..n).rev() {
+        let block = (1..=k).try_fold(1usize, usize::checked_mul);
+        let pick = block.map_or(0, |b| rest / b);
+        rest = block.map_or(rest, |b| rest % b);
+        perm.push(left.remove(pick));
+    }
+    perm
+}
+
+/// A pseudo-threader that runs a rotation's automata in sequence on the
+/// current thread, rather than spawning threads for them.
+///
+/// All of the work happens on spawning; the handles are the finished
+/// automata's done states.
+pub(super) struct Threader {
+    /// The order in which to run the threads in each iteration.
+    order: Order,
+}
+
+impl Threader {
+    /// Constructs a threader that runs threads in `order`.
+    pub(super) fn new(order: Order) -> Self {
+        Self { order }
+    }
+}
+
+impl<'entry, 'scope> thread::Threader<'entry, 'scope> for Threader {
+    type Handle = fsa::Done;
+
+    fn spawn<E: abs::Entry<'entry>>(
+        &'scope self,
+        automaton: fsa::ReadyAutomaton<'entry, E>,
+    ) -> err::Result<Self::Handle> {
+        self.spawn_all(std::iter::once(automaton))?
+            .pop()
+            .ok_or(err::Error::NotEnoughThreads)
+    }
+
+    fn spawn_all<E: abs::Entry<'entry>>(
+        &'scope self,
+        automata: impl IntoIterator<Item = fsa::ReadyAutomaton<'entry, E>>,
+    ) -> err::Result<Vec<Self::Handle>> {
+        let done = fsa::run_in_sequence(automata, |index, n| self.order.order(index, n))
+            .ok_or(err::Error::NotEnoughThreads)?;
+        Ok(vec![done])
+    }
+
+    fn join(&'scope self, handle: Self::Handle) -> err::Result<fsa::Done> {
+        Ok(handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::{selftest, testing::every, Builder};
+
+    /// Enumeration visits every permutation exactly once per cycle.
+    #[test]
+    fn test_enumerate_covers_permutations() {
+        let perms: std::collections::BTreeSet<_> =
+            (0..24).map(|i| Order::Enumerate.order(i, 4)).collect();
+        assert_eq!(perms.len(), 24);
+        assert!(perms.iter().all(|p| {
+            let mut p = p.clone();
+            p.sort_unstable();
+            p == [0, 1, 2, 3]
+        }));
+        assert_eq!(Order::Enumerate.order(24, 4), [0, 1, 2, 3]);
+    }
+
+    /// Enumeration still works when the number of permutations overflows.
+    #[test]
+    fn test_enumerate_huge() {
+        let perm = Order::Enumerate.order(usize::MAX, 30);
+        assert_eq!(perm.len(), 30);
+        assert_eq!(perm[..9], (0..9).collect::<Vec<_>>()[..]);
+    }
+
+    /// Round-robin orders rotate the threads.
+    #[test]
+    fn test_round_robin() {
+        assert_eq!(Order::RoundRobin.order(0, 3), [0, 1, 2]);
+        assert_eq!(Order::RoundRobin.order(2, 3), [2, 0, 1]);
+        assert_eq!(Order::RoundRobin.order(5, 1), [0]);
+        assert!(Order::RoundRobin.order(5, 0).is_empty());
+    }
+
+    /// Tests that sequentialised runs show exactly the interleavings their
+    /// orders give, across rotations.
+    #[test]
+    fn test_with_sequential() -> err::Result<()> {
+        let sb = selftest::tests()
+            .into_iter()
+            .find(|t| t.name == "SB")
+            .unwrap();
+        let report = Builder::new(sb.entry)
+            .with_sequential(Some(Order::RoundRobin))
+            .add_halt_rules(vec![every(10).exit(), every(3).rotate()])
+            .build()?
+            .run()?;
+
+        assert_eq!(report.schedule.len(), 4);
+        // Whichever thread runs first, the second sees its store.
+        let states: Vec<_> = report
+            .states
+            .iter()
+            .map(|s| (s.state.clone(), s.info.occurs))
+            .collect();
+        assert_eq!(states.len(), 2, "{states:?}");
+        assert!(states.iter().all(|(_, occurs)| *occurs == 5), "{states:?}");
+        assert!(!report.states.iter().any(|s| sb.weak.matches(&s.state)));
+        Ok(())
+    }
+}
@@ -3,8 +3,54 @@
 //! Presently we implement this using a mutex, but future work might let the
 //! synchronisers in `sync` also synchronise access to this.
 
-use super::{halt, obs};
-use crate::api::abs;
+use super::{alloc, checkpoint, guard, halt, limit, obs, stats, timing};
+use crate::{
+    api::abs,
+    model::{allocation, ending},
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Type of hooks called after each observation, but before any reset of the
+/// environment.
+///
+/// Hooks get mutable access to the manifested environment, and can (for
+/// instance) change the initial values in the manifest that the next reset
+/// will use.  They also see the summary of the observation just made.
+pub type Hook<'a, E> = Arc<dyn Fn(&mut obs::Manifested<E>, &obs::Summary) + Send + Sync + 'a>;
+
+/// When the runner resets the environment to its initial values.
+///
+/// Resetting also refills any canary values, and, as the runner moves onto a
+/// new rotation, redraws any fuzzed initial values; the runner always resets
+/// once before the first iteration.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Reset {
+    /// Reset after every iteration, so that each starts afresh.
+    #[default]
+    PerIteration,
+    /// Reset only as the runner moves onto a new rotation, so that each
+    /// iteration sees what earlier ones in its rotation left behind.
+    PerRotation,
+    /// Never reset, so that every iteration sees what all earlier ones left
+    /// behind.
+    Never,
+}
+
+impl Reset {
+    /// Gets whether to reset after an iteration, given whether the runner
+    /// is moving onto a new rotation.
+    #[must_use]
+    pub fn resets(self, rotating: bool) -> bool {
+        match self {
+            Self::PerIteration => true,
+            Self::PerRotation => rotating,
+            Self::Never => false,
+        }
+    }
+}
 
 /// The shared state available to runner threads whenever they get promoted to
 /// observers.
@@ -17,14 +63,138 @@ pub struct State<'a, E> {
     pub halt_rules: Vec<halt::Rule>,
     /// The observer for the test.
     pub observer: obs::Observer,
+    /// Hooks to run after each observation, before the environment is reset.
+    pub hooks: Vec<Hook<'a, E>>,
+    /// Halts requested from outside the test, for instance by a
+    /// [`super::Handle`].
+    pub requests: Arc<halt::Signal>,
+    /// The guard, if any, against test bodies that fork or spawn threads.
+    pub guard: Option<guard::Guard>,
+    /// The resource limits for which to watch.
+    pub limits: limit::Limits,
+    /// How the process exceeded its limits, if it has.
+    pub exceeded: Option<limit::Exceeded>,
+    /// When to reset the environment.
+    pub reset: Reset,
+    /// The recorder, if any, of how long each thread spends in the test body.
+    pub timing: Option<timing::Recorder>,
+    /// The audit, if any, of the allocations made while handling each
+    /// observed iteration.
+    pub allocations: Option<allocation::Audit>,
+    /// The rule, if any, for pausing the test threads periodically.
+    pub pause: Option<halt::Pause>,
+    /// When the pause that the last observation began, if any, ends.
+    ///
+    /// Every thread reads this as it next enters the test body, and sleeps
+    /// until then.
+    pub paused_until: Option<Instant>,
+    /// Why the test exited, once it has.
+    pub ended_by: Option<ending::Cause>,
+    /// The writer, if any, of periodic checkpoints of the observations.
+    pub checkpoint: Option<checkpoint::Writer>,
+    /// The sink, if any, for periodic run statistics.
+    pub stats: Option<stats::Sink<'a>>,
 }
 
-impl<'a, E: abs::Env> State<'a, E> {
-    /// Handles the environment, including observing it and resetting it.
+impl<E: abs::Env> State<'_, E> {
+    /// Handles the environment, including observing it and, as the reset
+    /// policy says, resetting it.
+    ///
+    /// During warmup, this skips the hooks, and only checks halt rules that
+    /// don't depend on observations.  If auditing allocations, this counts
+    /// those it makes on any iteration past the warmup.
     pub fn observe(&mut self) -> Option<halt::Type> {
-        let summary = self.observer.observe(&mut self.env, &*self.checker);
-        self.env.reset();
-        self.exit_type(&summary)
+        if self.allocations.is_none() {
+            return self.handle();
+        }
+        let audited = !self.observer.is_warming_up();
+        let iteration = self.observer.position().index;
+        let before = alloc::allocations();
+        let exit_type = self.handle();
+        let made = alloc::allocations().wrapping_sub(before);
+        if let Some(audit) = self.allocations.as_mut().filter(|_| audited) {
+            audit.record(iteration, made);
+        }
+        exit_type
+    }
+
+    /// Handles the environment, as [`Self::observe`] does, without auditing
+    /// allocations.
+    fn handle(&mut self) -> Option<halt::Type> {
+        let Some(summary) = self.observer.observe(&mut self.env, &*self.checker) else {
+            let exit_type = self.warmup_exit_type().max(self.requests.take());
+            if exit_type == Some(halt::Type::Exit) {
+                self.ended_by = Some(self.exit_cause(None, false));
+            }
+            if exit_type == Some(halt::Type::Rotate) {
+                self.observer.rotate();
+            }
+            self.reset_after(exit_type);
+            return exit_type;
+        };
+        for hook in &self.hooks {
+            hook(&mut self.env, &summary);
+        }
+        self.write_checkpoint();
+        let abort = (self.check_guard() || self.check_limits()).then_some(halt::Type::Exit);
+        let exit_type = self
+            .exit_type(&summary)
+            .max(self.requests.take())
+            .max(abort);
+        if let Some(ty) = exit_type {
+            log_halt(ty, &summary);
+        }
+        if let Some(stats) = &mut self.stats {
+            let rotation = self.observer.position().rotation;
+            stats.record(&summary, rotation, exit_type == Some(halt::Type::Exit));
+        }
+        if exit_type == Some(halt::Type::Exit) {
+            self.ended_by = Some(self.exit_cause(Some(&summary), abort.is_some()));
+        }
+        if exit_type == Some(halt::Type::Rotate) {
+            self.observer.rotate();
+        }
+        self.paused_until = if exit_type == Some(halt::Type::Exit) {
+            None
+        } else {
+            self.pause_length(&summary)
+                .map(|length| Instant::now() + length)
+        };
+        self.reset_after(exit_type);
+        exit_type
+    }
+
+    /// Writes a checkpoint of the observations so far, if one is due.
+    fn write_checkpoint(&mut self) {
+        let Some(checkpoint) = self.checkpoint.as_mut().filter(|c| c.is_due()) else {
+            return;
+        };
+        let mut report = self.observer.partial_report();
+        report.masked = self.env.mask().iter().cloned().collect();
+        report.folded = self.env.folded();
+        report.orderings = self.env.orderings();
+        checkpoint.write(report);
+    }
+
+    /// Gets how long to pause the test threads for, if at all, after the
+    /// observation `summary`.
+    fn pause_length(&self, summary: &obs::Summary) -> Option<Duration> {
+        let length = self.pause?.length_after(summary.iterations)?;
+        tracing::debug!(
+            iterations = summary.iterations,
+            ms = length.as_millis(),
+            "pausing"
+        );
+        Some(length)
+    }
+
+    /// Resets the environment, if the policy says to after an iteration
+    /// halting with `exit_type`.
+    fn reset_after(&mut self, exit_type: Option<halt::Type>) {
+        let rotating = exit_type == Some(halt::Type::Rotate);
+        if self.reset.resets(rotating) {
+            self.env.reset(rotating);
+        }
     }
 
     /// Checks whether the test should exit now.
@@ -34,4 +204,230 @@ impl<'a, E: abs::Env> State<'a, E> {
             .filter_map(|c| c.exit_type(summary))
             .max()
     }
+
+    /// Works out why the test is exiting after the observation `summary`, if
+    /// any, given whether the guard or the limits aborted the test.
+    ///
+    /// If no exit rule fired, the exit must have come from the handle.
+    fn exit_cause(&self, summary: Option<&obs::Summary>, aborted: bool) -> ending::Cause {
+        if aborted {
+            return self.exceeded.map_or(ending::Cause::Tainted, |e| {
+                ending::Cause::LimitExceeded {
+                    resource: e.resource().to_string(),
+                }
+            });
+        }
+        self.halt_rules
+            .iter()
+            .find(|r| {
+                let ty = summary.map_or_else(|| r.unobserved_exit_type(), |s| r.exit_type(s));
+                ty == Some(halt::Type::Exit)
+            })
+            .map_or(ending::Cause::Cancelled, |r| r.condition.cause())
+    }
+
+    /// Checks the guard, if any, for taints in the iteration just observed,
+    /// returning whether the test should abort.
+    fn check_guard(&mut self) -> bool {
+        let Some(guard) = &mut self.guard else {
+            return false;
+        };
+        let position = self.observer.position();
+        guard.check(position.index.saturating_sub(1), position.rotation)
+    }
+
+    /// Samples the process's usage against its limits, if a sample is due
+    /// after the iteration just observed, returning whether the test should
+    /// abort.
+    fn check_limits(&mut self) -> bool {
+        if self.limits.is_empty() {
+            return false;
+        }
+        let iteration = self.observer.position().index.saturating_sub(1);
+        if iteration.is_multiple_of(limit::SAMPLE_EVERY) {
+            self.exceeded = self.limits.exceeded();
+        }
+        self.exceeded.is_some()
+    }
+
+    /// Checks whether the test should exit during warmup.
+    fn warmup_exit_type(&self) -> Option<halt::Type> {
+        self.halt_rules
+            .iter()
+            .filter_map(halt::Rule::unobserved_exit_type)
+            .max()
+    }
+}
+
+/// Logs a halt, with the rate of observations over the rotation it ends.
+fn log_halt(ty: halt::Type, summary: &obs::Summary) {
+    if !tracing::enabled!(tracing::Level::DEBUG) {
+        return;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let per_sec = summary.rotation_iterations as f64 / summary.rotation_elapsed.as_secs_f64();
+    tracing::debug!(
+        r#type = %ty,
+        iterations = summary.iterations,
+        rotation_iterations = summary.rotation_iterations,
+        per_sec = per_sec.round(),
+        distinct_states = summary.distinct_states,
+        "halting"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::rust,
+        err, model,
+        run::testing::{capped_builder, entry_with_i32s, every, nop_entry, x_counts_iterations},
+    };
+    use std::{
+        num::NonZeroUsize,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// Tests that hooks can change the initial values used on reset.
+    #[test]
+    fn test_hook_changes_initials() -> err::Result<()> {
+        let entry = entry_with_i32s(1, &[("x", false, Some(0))]);
+        let report = capped_builder(entry, 5)
+            .with_hook(x_counts_iterations)
+            .build()?
+            .run()?;
+
+        assert_eq!(report.states.len(), 5);
+        let discovered: Vec<_> = report
+            .discovery
+            .0
+            .iter()
+            .map(|p| (p.iteration, p.states))
+            .collect();
+        assert_eq!(discovered, [(0, 1), (1, 2), (2, 3), (3, 4), (4, 5)]);
+        Ok(())
+    }
+
+    /// Tests that warmup iterations run the test but aren't observed.
+    #[test]
+    fn test_warmup_not_observed() -> err::Result<()> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let mut entry = entry_with_i32s(1, &[("x", true, None)]);
+        entry.test = |_, env: &rust::Env| {
+            let x = COUNT.fetch_add(1, Ordering::Relaxed);
+            env.i32s.atomic[0].store(i32::try_from(x).unwrap(), Ordering::Relaxed);
+        };
+
+        let report = capped_builder(entry, 3).with_warmup(5).build()?.run()?;
+
+        let mut states = report.states;
+        states.sort_by_key(|s| s.info.iteration);
+        let seen: Vec<_> = states
+            .iter()
+            .map(|s| (s.info.iteration, s.state["x"]))
+            .collect();
+        let want: Vec<_> = [(0, 5), (1, 6), (2, 7)]
+            .into_iter()
+            .map(|(i, x)| (i, model::state::Value::I32(x)))
+            .collect();
+        assert_eq!(seen, want);
+        Ok(())
+    }
+
+    /// Tests that rotating during warmup keeps the observer's rotations in
+    /// step with the runner's.
+    #[test]
+    fn test_warmup_rotate() -> err::Result<()> {
+        let runner = capped_builder(nop_entry(2), 2).with_warmup(3).build()?;
+        runner.handle().cancel(halt::Type::Rotate);
+        let report = runner.run()?;
+
+        assert_eq!(report.schedule.len(), 2);
+        let info = report.states[0].info;
+        assert_eq!((info.rotation, info.rotations), (1, 1));
+        Ok(())
+    }
+
+    /// Tests that the reset policy decides when hook-changed initial values
+    /// take effect.
+    #[test]
+    fn test_reset_policies() -> err::Result<()> {
+        let entry = entry_with_i32s(1, &[("x", false, Some(0))]);
+        let run = |reset| {
+            capped_builder(entry.clone(), 6)
+                .add_halt_rules(std::iter::once(every(2).rotate()))
+                .with_hook(x_counts_iterations)
+                .with_reset(reset)
+                .build()?
+                .run()
+        };
+
+        let xs = |report: model::Report| -> Vec<_> {
+            report
+                .states
+                .iter()
+                .map(|s| (s.state["x"], s.info.occurs))
+                .collect()
+        };
+        let x = model::state::Value::I32;
+        assert_eq!(xs(run(Reset::PerIteration)?).len(), 6);
+        assert_eq!(
+            xs(run(Reset::PerRotation)?),
+            [(x(0), 2), (x(2), 2), (x(4), 2)]
+        );
+        assert_eq!(xs(run(Reset::Never)?), [(x(0), 6)]);
+        Ok(())
+    }
+
+    /// Tests that pauses hold up the run without ending or rotating it.
+    #[test]
+    fn test_with_pause_holds_up_run() -> err::Result<()> {
+        let pause = halt::Pause {
+            every: NonZeroUsize::new(4).unwrap(),
+            length: Duration::from_millis(20),
+        };
+        let start = Instant::now();
+        let report = capped_builder(nop_entry(2), 10)
+            .with_pause(Some(pause))
+            .build()?
+            .run()?;
+
+        // The run pauses after iterations 4 and 8.
+        assert!(Duration::from_millis(40) <= start.elapsed());
+        let iterations: usize = report.states.iter().map(|s| s.info.occurs).sum();
+        assert_eq!(iterations, 10);
+        assert_eq!(report.schedule.len(), 1);
+        Ok(())
+    }
+
+    /// Tests that, once a run has seen every state, handling an iteration
+    /// allocates nothing, even if the run fuzzes initial values.
+    #[cfg(feature = "alloc-audit")]
+    #[test]
+    fn test_alloc_audit_steady_state() -> err::Result<()> {
+        let fuzzed = capped_builder(entry_with_i32s(2, &[("x", false, Some(0))]), 1000)
+            .with_fuzz(
+                [(
+                    "x".to_owned(),
+                    model::fuzz::Distribution::Int { min: 0, max: 3 },
+                )]
+                .into(),
+            )
+            .with_fuzz_seed(Some(8));
+        for builder in [capped_builder(nop_entry(2), 1000), fuzzed] {
+            let report = builder
+                .with_alloc_audit(true)
+                .with_warmup(10)
+                .build()?
+                .run()?;
+            let audit = report
+                .allocations
+                .expect("the run should audit allocations");
+            assert_eq!(audit.iterations, 1000);
+            assert!(audit.last.is_none_or(|i| i == 0), "{audit}");
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,219 @@
+//! Minimisation of failing runs.
+//!
+//! A run that exits on its first failure may have needed millions of
+//! iterations to get there, which makes the failure slow to reproduce.  A
+//! shrink retries the test with successively halved iteration budgets, each
+//! with a few fresh seeds, keeping the smallest budget that still fails; it
+//! then tries dropping rotations, and reruns the result to see how reliably
+//! it fails.  The result is a [`model::reproduction::Reproduction`] that the
+//! failing run's report can carry.
+
+use super::{halt, Builder};
+use crate::{api::abs, model};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::num::NonZeroUsize;
+
+/// The number of seeds a shrink tries at each budget by default, and the
+/// number of times it reruns its result.
+pub const DEFAULT_ATTEMPTS: NonZeroUsize = NonZeroUsize::new(3).unwrap();
+
+/// One configuration that a shrink tries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Trial {
+    /// The iteration budget.
+    pub iterations: NonZeroUsize,
+    /// The number of iterations between rotations, if the trial rotates.
+    pub period: Option<NonZeroUsize>,
+    /// The seed from which to choose each iteration's observer.
+    pub seed: u64,
+}
+
+impl Trial {
+    /// Gets the halt rules that run the test for this trial's budget,
+    /// rotating every period.
+    #[must_use]
+    pub fn halt_rules(&self) -> Vec<halt::Rule> {
+        let exit = halt::Condition::EveryNIterations(self.iterations).exit();
+        let rotate = self
+            .period
+            .map(|p| halt::Condition::EveryNIterations(p).rotate());
+        std::iter::once(exit).chain(rotate).collect()
+    }
+
+    /// Adds this trial's halt rules to `builder`.
+    ///
+    /// The seed is left to the caller, which knows how the builder
+    /// synchronises; `builder` should have no iteration-based halt rules of
+    /// its own.
+    #[must_use]
+    pub fn apply<'entry, E: abs::Entry<'entry>>(
+        &self,
+        builder: Builder<'entry, E>,
+    ) -> Builder<'entry, E> {
+        builder.add_halt_rules(self.halt_rules())
+    }
+}
+
+/// A shrink of a failing run.
+pub struct Shrink {
+    attempts: NonZeroUsize,
+    rng: StdRng,
+}
+
+impl Default for Shrink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shrink {
+    /// Constructs a shrink that tries [`DEFAULT_ATTEMPTS`] seeds at each
+    /// budget, drawn from a fresh seed.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            attempts: DEFAULT_ATTEMPTS,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Overrides the number of seeds to try at each budget, which is also
+    /// the number of times to rerun the result.
+    #[must_use]
+    pub fn with_attempts(self, attempts: NonZeroUsize) -> Self {
+        Self { attempts, ..self }
+    }
+
+    /// Makes the shrink draw the seeds it tries from `seed`.
+    #[must_use]
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+
+    /// Shrinks the failing trial `start`.
+    ///
+    /// `step` runs a trial, returning its report; a trial fails if its
+    /// report's outcome is a failure.  `start` is taken to fail without
+    /// running it, so the result is never bigger than `start`.
+    ///
+    /// # Errors
+    ///
+    /// Fails as soon as any trial fails to run.
+    pub fn run<E>(
+        mut self,
+        start: Trial,
+        mut step: impl FnMut(&Trial) -> Result<model::Report, E>,
+    ) -> Result<model::reproduction::Reproduction, E> {
+        let mut tries = 0;
+        let mut try_trial = |trial: &Trial| {
+            tries += 1;
+            step(trial).map(|r| r.outcome == Some(model::Outcome::Fail))
+        };
+
+        let mut best = start;
+        while let Some(iterations) = NonZeroUsize::new(best.iterations.get() / 2) {
+            let mut found = None;
+            for _ in 0..self.attempts.get() {
+                let trial = Trial {
+                    iterations,
+                    period: best.period.filter(|&p| p < iterations),
+                    seed: self.rng.gen(),
+                };
+                if try_trial(&trial)? {
+                    found = Some(trial);
+                    break;
+                }
+            }
+            let Some(trial) = found else { break };
+            best = trial;
+        }
+        if best.period.is_some() {
+            let unrotated = Trial {
+                period: None,
+                ..best
+            };
+            if try_trial(&unrotated)? {
+                best = unrotated;
+            }
+        }
+
+        let mut failures = 0;
+        for _ in 0..self.attempts.get() {
+            failures += usize::from(try_trial(&best)?);
+        }
+        Ok(model::reproduction::Reproduction {
+            iterations: best.iterations,
+            period: best.period,
+            seed: best.seed,
+            tries: tries - self.attempts.get(),
+            reruns: self.attempts.get(),
+            failures,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Makes a report with the given outcome.
+    fn report(outcome: model::Outcome) -> model::Report {
+        model::Report {
+            outcome: Some(outcome),
+            ..model::Report::default()
+        }
+    }
+
+    /// Tests that shrinking halves the budget until it stops failing, and
+    /// drops rotations if the failure survives without them.
+    #[test]
+    fn test_shrink() {
+        let start = Trial {
+            iterations: NonZeroUsize::new(1000).unwrap(),
+            period: NonZeroUsize::new(100),
+            seed: 0,
+        };
+        let mut seen = vec![];
+        let repro = Shrink::new()
+            .with_seed(1)
+            .run(start, |trial| {
+                seen.push(*trial);
+                // This failure needs at least 100 iterations.
+                Ok::<_, ()>(report(if 100 <= trial.iterations.get() {
+                    model::Outcome::Fail
+                } else {
+                    model::Outcome::Pass
+                }))
+            })
+            .unwrap();
+
+        assert_eq!(repro.iterations.get(), 125);
+        assert_eq!(repro.period, None);
+        assert_eq!((repro.reruns, repro.failures), (3, 3));
+        // Three halvings that failed at once, three seeds at 62, and the
+        // unrotated trial.
+        assert_eq!(repro.tries, 7);
+        assert!(seen
+            .iter()
+            .all(|t| t.period.is_none_or(|p| p < t.iterations)));
+    }
+
+    /// Tests that a shrink that never reproduces the failure keeps the
+    /// starting trial, and says that its reruns passed.
+    #[test]
+    fn test_shrink_flaky() {
+        let start = Trial {
+            iterations: NonZeroUsize::new(4).unwrap(),
+            period: None,
+            seed: 5,
+        };
+        let repro = Shrink::new()
+            .run(start, |_| Ok::<_, ()>(report(model::Outcome::Pass)))
+            .unwrap();
+        assert_eq!((repro.iterations.get(), repro.seed), (4, 5));
+        assert_eq!((repro.tries, repro.failures), (3, 0));
+    }
+}
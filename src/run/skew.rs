@@ -0,0 +1,227 @@
+//! Per-thread iteration skew.
+//!
+//! A [Table] holds the delay, if any, with which each thread enters each
+//! iteration (see [`model::skew`]).  Delays are mostly far shorter than the
+//! scheduler's resolution, so the table spins rather than sleeping unless a
+//! delay is long.
+
+use crate::{err, model::skew};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+/// Marks a thread that hasn't drawn a delay.
+const UNDRAWN: u64 = u64::MAX;
+
+/// Delays at least this long sleep rather than spin.
+#[cfg(not(windows))]
+const SLEEP_THRESHOLD: Duration = Duration::from_millis(1);
+
+/// Delays at least this long sleep rather than spin.
+///
+/// Windows sleeps in whole scheduler ticks, which are usually 15.6ms long,
+/// so shorter sleeps would overshoot badly.
+#[cfg(windows)]
+const SLEEP_THRESHOLD: Duration = Duration::from_millis(16);
+
+/// The delays to apply to each thread, indexed by thread ID.
+///
+/// Clones of a table share its record of the delays last drawn, so that one
+/// clone can see what the others drew (see [`Table::drawn`]).
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    delays: Vec<Option<skew::Delay>>,
+    /// The delay, in nanoseconds, that each thread last drew.
+    drawn: Arc<[AtomicU64]>,
+    /// The generator from which each thread draws its delays.
+    ///
+    /// Only the thread itself draws from its generator, so the locks are
+    /// never contended.
+    rngs: Arc<[Mutex<StdRng>]>,
+}
+
+impl Table {
+    /// Builds a table from `spec` for a test with `n_threads` threads.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `spec` names a thread that isn't a thread ID of the test.
+    pub fn new(spec: &skew::Spec, n_threads: NonZeroUsize) -> err::Result<Self> {
+        let mut delays = vec![None; n_threads.get()];
+        for (thread, delay) in spec {
+            let bad = |reason: String| err::Error::BadSkew {
+                thread: thread.clone(),
+                reason,
+            };
+            let tid: usize = thread
+                .parse()
+                .map_err(|_| bad("not a thread ID".to_owned()))?;
+            let slot = delays
+                .get_mut(tid)
+                .ok_or_else(|| bad(format!("the test has only {n_threads} threads")))?;
+            *slot = Some(*delay);
+        }
+        let drawn = delays.iter().map(|_| AtomicU64::new(UNDRAWN)).collect();
+        let rngs = delays
+            .iter()
+            .map(|_| Mutex::new(StdRng::from_entropy()))
+            .collect();
+        Ok(Self {
+            delays,
+            drawn,
+            rngs,
+        })
+    }
+
+    /// Makes this table's threads draw their delays from generators seeded
+    /// from `seed`, so that tables with the same seed draw the same delays
+    /// for each thread in the same order.
+    #[must_use]
+    pub fn with_seed(self, seed: u64) -> Self {
+        let mut root = StdRng::seed_from_u64(seed);
+        let rngs = self
+            .delays
+            .iter()
+            .map(|_| Mutex::new(StdRng::seed_from_u64(root.gen())))
+            .collect();
+        Self { rngs, ..self }
+    }
+
+    /// Gets the delay for thread `tid`, if any.
+    #[must_use]
+    pub fn get(&self, tid: usize) -> Option<skew::Delay> {
+        self.delays.get(tid).copied().flatten()
+    }
+
+    /// Blocks thread `tid` for its delay, if it has one, drawing a fresh
+    /// delay from its range.
+    pub fn delay(&self, tid: usize) {
+        let Some(d) = self.get(tid) else {
+            return;
+        };
+        let d = match self.rngs.get(tid).filter(|_| !d.is_fixed()) {
+            Some(rng) => rng
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .gen_range(d.min..=d.max),
+            None => d.min,
+        };
+        if let Some(slot) = self.drawn.get(tid) {
+            let nanos = u64::try_from(d.as_nanos()).unwrap_or(UNDRAWN - 1);
+            slot.store(nanos, Ordering::Relaxed);
+        }
+        if SLEEP_THRESHOLD <= d {
+            std::thread::sleep(d);
+        } else if !d.is_zero() {
+            let until = Instant::now() + d;
+            while Instant::now() < until {
+                std::hint::spin_loop();
+            }
+        }
+    }
+
+    /// Gets the delays that each delayed thread last drew, as a spec of
+    /// fixed delays keyed by thread ID.
+    ///
+    /// This only sees the delays of threads that have finished delaying
+    /// since the caller last synchronised with them.
+    #[must_use]
+    pub fn drawn(&self) -> skew::Spec {
+        self.drawn
+            .iter()
+            .enumerate()
+            .filter_map(|(tid, slot)| {
+                let nanos = slot.load(Ordering::Relaxed);
+                (nanos != UNDRAWN).then(|| {
+                    (
+                        tid.to_string(),
+                        skew::Delay::fixed(Duration::from_nanos(nanos)),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that tables index delays by thread, and reject bad threads.
+    #[test]
+    fn test_table_new() {
+        let threads = NonZeroUsize::new(2).unwrap();
+        let d = skew::Delay::fixed(Duration::from_nanos(100));
+        let t = Table::new(&[("1".to_owned(), d)].into(), threads).unwrap();
+        assert_eq!(t.get(0), None);
+        assert_eq!(t.get(1), Some(d));
+        assert_eq!(t.get(2), None);
+
+        for bad in ["2", "x"] {
+            assert!(matches!(
+                Table::new(&[(bad.to_owned(), d)].into(), threads),
+                Err(err::Error::BadSkew { thread, .. }) if thread == bad
+            ));
+        }
+    }
+
+    /// Tests that a delayed thread waits at least its minimum delay.
+    #[test]
+    fn test_table_delay() {
+        let d = skew::Delay {
+            min: Duration::from_micros(200),
+            max: Duration::from_micros(300),
+        };
+        let t = Table::new(&[("0".to_owned(), d)].into(), NonZeroUsize::MIN).unwrap();
+        let start = Instant::now();
+        t.delay(0);
+        assert!(d.min <= start.elapsed());
+    }
+
+    /// Tests that clones of a table see the delays that each other drew.
+    #[test]
+    fn test_table_drawn() {
+        let d = skew::Delay {
+            min: Duration::from_nanos(100),
+            max: Duration::from_nanos(200),
+        };
+        let t = Table::new(&[("1".to_owned(), d)].into(), NonZeroUsize::new(2).unwrap()).unwrap();
+        let u = t.clone();
+        assert!(u.drawn().is_empty());
+        t.delay(0);
+        t.delay(1);
+        let drawn = u.drawn();
+        assert_eq!(drawn.len(), 1);
+        let got = drawn["1"];
+        assert!(got.is_fixed());
+        assert!(d.min <= got.min && got.min <= d.max);
+    }
+
+    /// Tests that tables with the same seed draw the same delays.
+    #[test]
+    fn test_table_with_seed() {
+        let d = skew::Delay {
+            min: Duration::from_nanos(1),
+            max: Duration::from_nanos(999),
+        };
+        let draws = |seed| {
+            let t = Table::new(&[("0".to_owned(), d)].into(), NonZeroUsize::MIN)
+                .unwrap()
+                .with_seed(seed);
+            (0..8)
+                .map(|_| {
+                    t.delay(0);
+                    t.drawn()["0"].min
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draws(8), draws(8));
+        assert_ne!(draws(8), draws(9));
+    }
+}
@@ -0,0 +1,240 @@
+//! Cheap snapshots of the variables that the observer records.
+//!
+//! The observer reads the environment on every iteration, so reading it
+//! shouldn't allocate.  A [Layout] fixes, once per run, which slots to read
+//! and in what order; a snapshot is then just the raw bits of each of those
+//! slots' values, in that order, read into a reusable buffer.  Only snapshots
+//! of states that the observer hasn't seen before become named
+//! [`state::State`]s.
+//!
+//! A layout can also fold symmetric threads together (see
+//! [`Layout::fold_symmetric`]), rewriting each snapshot into a canonical
+//! form before anyone looks at it.
+
+use crate::{
+    api::abs,
+    model::{
+        manifest::{Manifest, VarName},
+        slot::Slot,
+        state,
+    },
+};
+use std::collections::BTreeSet;
+
+/// The order in which snapshots hold the values of variables.
+#[derive(Clone, Default)]
+pub struct Layout {
+    /// The name, type, and slot of each variable, in snapshot order.
+    ///
+    /// The names are the manifest's own, so states built from snapshots
+    /// share them rather than allocating names of their own.
+    vars: Vec<(VarName, Type, Slot)>,
+    /// The groups of symmetric threads whose registers snapshots fold.
+    folds: Vec<Fold>,
+}
+
+/// A group of symmetric threads in a [Layout].
+#[derive(Clone)]
+struct Fold {
+    /// The thread IDs, in order.
+    threads: Vec<usize>,
+    /// For each thread, the snapshot positions of its registers, ordered by
+    /// register name.
+    regs: Vec<Vec<usize>>,
+}
+
+/// The type of a variable in a [Layout].
+#[derive(Copy, Clone)]
+enum Type {
+    I32,
+    Bool,
+    F64,
+}
+
+impl Layout {
+    /// Lays out every variable in `manifest` that isn't in `mask`.
+    #[must_use]
+    pub fn new(manifest: &Manifest, mask: &BTreeSet<String>) -> Self {
+        let i32s = manifest.i32s.iter().map(|(n, r)| (n, Type::I32, r.slot));
+        let bools = manifest.bools.iter().map(|(n, r)| (n, Type::Bool, r.slot));
+        let f64s = manifest.f64s.iter().map(|(n, r)| (n, Type::F64, r.slot));
+        let vars = i32s
+            .chain(bools)
+            .chain(f64s)
+            .filter(|(n, _, _)| !mask.contains(n.as_str()))
+            .map(|(n, ty, slot)| (n.clone(), ty, slot))
+            .collect();
+        Self {
+            vars,
+            folds: vec![],
+        }
+    }
+
+    /// Makes snapshots fold together states that differ only by swapping
+    /// the registers of threads that `manifest` declares symmetric.
+    ///
+    /// Folding sorts each group's threads by the bits of their registers
+    /// (variables named `T:name` for thread `T`), so that each set of
+    /// equivalent states has one representative.  Groups whose threads don't
+    /// all have the same unmasked registers don't fold.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::{
+    ///     model::{manifest::{Manifest, VarMap, VarRecord}, slot::Slot},
+    ///     run::snapshot::Layout,
+    /// };
+    /// use std::{collections::BTreeSet, num::NonZeroUsize};
+    ///
+    /// let var = |index| VarRecord { initial_value: None, slot: Slot { is_atomic: false, index } };
+    /// let mut i32s = VarMap::new();
+    /// i32s.insert("0:r0".into(), var(0));
+    /// i32s.insert("1:r0".into(), var(1));
+    /// let m = Manifest {
+    ///     n_threads: NonZeroUsize::new(2).unwrap(),
+    ///     i32s,
+    ///     bools: VarMap::new(),
+    ///     f64s: VarMap::new(),
+    ///     scalable_role: None,
+    ///     symmetry: vec![0, 0],
+    ///     invariants: Default::default(),
+    ///     aux_flags: vec![],
+    ///     declared: vec![],
+    /// };
+    ///
+    /// let mut layout = Layout::new(&m, &BTreeSet::new());
+    /// layout.fold_symmetric(&m);
+    /// assert_eq!(layout.folded(), [vec![0, 1]]);
+    /// let (mut a, mut b) = ([1, 0], [0, 1]);
+    /// layout.fold(&mut a);
+    /// layout.fold(&mut b);
+    /// assert_eq!(a, b);
+    /// ```
+    pub fn fold_symmetric(&mut self, manifest: &Manifest) {
+        self.folds = manifest
+            .symmetric_groups()
+            .into_iter()
+            .filter_map(|threads| self.fold_of(threads))
+            .collect();
+    }
+
+    fn fold_of(&self, threads: Vec<usize>) -> Option<Fold> {
+        let mut names = None;
+        let mut regs = Vec::with_capacity(threads.len());
+        for tid in &threads {
+            let prefix = format!("{tid}:");
+            let mut mine: Vec<_> = self
+                .vars
+                .iter()
+                .enumerate()
+                .filter_map(|(i, (n, _, _))| n.strip_prefix(&prefix).map(|r| (r, i)))
+                .collect();
+            mine.sort_unstable();
+            let (these, positions): (Vec<_>, Vec<_>) = mine.into_iter().unzip();
+            if these.is_empty() || names.get_or_insert_with(|| these.clone()) != &these {
+                return None;
+            }
+            regs.push(positions);
+        }
+        Some(Fold { threads, regs })
+    }
+
+    /// Gets the groups of threads that snapshots fold together.
+    #[must_use]
+    pub fn folded(&self) -> Vec<Vec<usize>> {
+        self.folds.iter().map(|f| f.threads.clone()).collect()
+    }
+
+    /// Rewrites `snapshot` into the canonical form of its symmetric states.
+    ///
+    /// [`Layout::read`] already does this.
+    pub fn fold(&self, snapshot: &mut [u64]) {
+        for f in &self.folds {
+            // Insertion sort, as groups are small and this mustn't allocate.
+            for i in 1..f.regs.len() {
+                let mut j = i;
+                while 0 < j && precedes(snapshot, &f.regs[j], &f.regs[j - 1]) {
+                    for (&a, &b) in f.regs[j].iter().zip(&f.regs[j - 1]) {
+                        snapshot.swap(a, b);
+                    }
+                    j -= 1;
+                }
+            }
+        }
+    }
+
+    /// Reads a snapshot of `env` into `buf`, replacing its contents.
+    ///
+    /// This is not thread-safe until all test threads are synchronised.
+    pub fn read<E: abs::Env>(&self, env: &E, buf: &mut Vec<u64>) {
+        buf.clear();
+        buf.extend(self.vars.iter().map(|(_, ty, slot)| match ty {
+            Type::I32 => u64::from(env.get_i32(*slot).cast_unsigned()),
+            Type::Bool => u64::from(env.get_bool(*slot)),
+            Type::F64 => env.get_f64(*slot).to_bits(),
+        }));
+        self.fold(buf);
+    }
+
+    /// Converts a snapshot taken with this layout into a state.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::{
+    ///     model::{manifest::{Manifest, VarMap, VarRecord}, slot::Slot, state::Value},
+    ///     run::snapshot::Layout,
+    /// };
+    /// use std::{collections::BTreeSet, num::NonZeroUsize};
+    ///
+    /// let var = |index| VarRecord { initial_value: Some(-1), slot: Slot { is_atomic: false, index } };
+    /// let mut i32s = VarMap::new();
+    /// i32s.insert("x".into(), var(0));
+    /// i32s.insert("y".into(), var(1));
+    /// let m = Manifest {
+    ///     n_threads: NonZeroUsize::new(1).unwrap(),
+    ///     i32s,
+    ///     bools: VarMap::new(),
+    ///     f64s: VarMap::new(),
+    ///     scalable_role: None,
+    ///     symmetry: vec![],
+    ///     invariants: Default::default(),
+    ///     aux_flags: vec![],
+    ///     declared: vec![],
+    /// };
+    ///
+    /// let layout = Layout::new(&m, &BTreeSet::from(["y".to_owned()]));
+    /// let state = layout.state_of(&[u64::from((-1i32).cast_unsigned())]);
+    /// assert_eq!(state.len(), 1);
+    /// assert_eq!(state["x"], Value::I32(-1));
+    /// ```
+    #[must_use]
+    pub fn state_of(&self, snapshot: &[u64]) -> state::State {
+        self.vars
+            .iter()
+            .zip(snapshot)
+            .map(|((name, ty, _), &bits)| {
+                let value = match ty {
+                    Type::I32 => {
+                        // Snapshots only ever hold 32 bits for integers.
+                        #[allow(clippy::cast_possible_truncation)]
+                        let bits = bits as u32;
+                        state::Value::I32(bits.cast_signed())
+                    }
+                    Type::Bool => state::Value::Bool(bits != 0),
+                    Type::F64 => state::Value::F64(f64::from_bits(bits)),
+                };
+                (name.clone(), value)
+            })
+            .collect()
+    }
+}
+
+/// Gets whether the registers at positions `a` come before those at `b` in
+/// `snapshot`.
+fn precedes(snapshot: &[u64], a: &[usize], b: &[usize]) -> bool {
+    a.iter()
+        .map(|&i| snapshot[i])
+        .lt(b.iter().map(|&i| snapshot[i]))
+}
@@ -0,0 +1,127 @@
+//! Periodic run statistics for embedding code.
+//!
+//! A [Sink] hands a [Snapshot] of the run's progress to a callback every so
+//! often, and once more as the run exits.  This lets harnesses that embed
+//! the runner, such as benchmarks, feed their own telemetry without going
+//! through the progress output of `ux`.
+
+use super::obs;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The default wall-clock time between snapshots.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Type of callbacks that take snapshots.
+///
+/// Callbacks live behind a mutex so that every runner built from the same
+/// builder can share them.
+pub type Callback<'a> = Arc<Mutex<dyn FnMut(Snapshot) + Send + 'a>>;
+
+/// A snapshot of a run's progress.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Snapshot {
+    /// The number of iterations observed so far, excluding warmup.
+    pub iterations: usize,
+    /// The number of rotations completed so far.
+    pub rotations: usize,
+    /// The number of distinct states seen so far.
+    pub distinct_states: usize,
+    /// The wall-clock time since the first observed iteration.
+    pub elapsed: Duration,
+    /// The iterations observed since the last snapshot, and the time they
+    /// took; `None` for the first snapshot.
+    pub rate: Option<obs::Rate>,
+    /// Whether this is the last snapshot of the run.
+    pub last: bool,
+}
+
+/// Delivers snapshots to a callback at a fixed interval.
+pub struct Sink<'a> {
+    callback: Callback<'a>,
+    interval: Duration,
+    /// When the sink saw its first observation.
+    started: Option<Instant>,
+    /// When the sink last took a snapshot, and the iterations as of then.
+    last: Option<(Instant, usize)>,
+}
+
+impl<'a> Sink<'a> {
+    /// Constructs a sink that gives `callback` a snapshot every `interval`.
+    #[must_use]
+    pub fn new(callback: Callback<'a>, interval: Duration) -> Self {
+        Self {
+            callback,
+            interval,
+            started: None,
+            last: None,
+        }
+    }
+
+    /// Records the observation summarised by `summary`, made in the given
+    /// rotation, taking a snapshot if one is due or if `last` is set.
+    pub fn record(&mut self, summary: &obs::Summary, rotation: usize, last: bool) {
+        let now = Instant::now();
+        let started = *self.started.get_or_insert(now);
+        let since = self.last.map_or(started, |(at, _)| at);
+        if !last && now.duration_since(since) < self.interval {
+            return;
+        }
+        let snapshot = Snapshot {
+            iterations: summary.iterations,
+            rotations: rotation,
+            distinct_states: summary.distinct_states,
+            elapsed: now.duration_since(started),
+            rate: self.last.map(|(at, iterations)| obs::Rate {
+                iterations: summary.iterations.saturating_sub(iterations),
+                elapsed: now.duration_since(at),
+            }),
+            last,
+        };
+        self.last = Some((now, summary.iterations));
+        // A callback that panicked once gets no more snapshots.
+        if let Ok(mut callback) = self.callback.lock() {
+            callback(snapshot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        err,
+        run::testing::{capped_builder, every, nop_entry},
+    };
+
+    /// Tests that stats sinks get a snapshot per due observation, and a last
+    /// one as the run exits.
+    #[test]
+    fn test_stats_sink() -> err::Result<()> {
+        let entry = nop_entry(2);
+        let snapshots = Arc::new(Mutex::new(vec![]));
+        let sink = snapshots.clone();
+
+        capped_builder(entry, 6)
+            .add_halt_rules(std::iter::once(every(2).rotate()))
+            .with_warmup(3)
+            .with_stats_interval(Duration::ZERO)
+            .with_stats_sink(move |s| sink.lock().unwrap().push(s))
+            .build()?
+            .run()?;
+
+        let snapshots = snapshots.lock().unwrap();
+        let iterations: Vec<_> = snapshots.iter().map(|s| s.iterations).collect();
+        assert_eq!(iterations, [1, 2, 3, 4, 5, 6]);
+        let rotations: Vec<_> = snapshots.iter().map(|s| s.rotations).collect();
+        assert_eq!(rotations, [0, 0, 1, 1, 2, 2]);
+        assert!(snapshots[0].rate.is_none());
+        assert!(snapshots[1..]
+            .iter()
+            .all(|s| s.rate.is_some_and(|r| r.iterations == 1)));
+        assert!(snapshots.iter().all(|s| s.last == (s.iterations == 6)));
+        Ok(())
+    }
+}
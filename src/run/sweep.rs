@@ -0,0 +1,348 @@
+//! Sweeps of a test across several configurations under a time budget.
+//!
+//! A sweep runs the same test under each of several configurations, in
+//! slices of wall-clock time, until a total budget runs out.  After each
+//! slice, a [Policy] picks the configuration to run next from how each has
+//! fared so far; the default [Greedy] policy gives the remaining budget to
+//! whichever configurations are discovering new states fastest.  Each
+//! configuration's slices merge into one report.
+
+use super::halt;
+use crate::model;
+use std::{
+    fmt::Display,
+    str::FromStr,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+/// The number of slices into which sweeps divide their budget by default.
+pub const DEFAULT_SLICES: u32 = 20;
+
+/// How a configuration has fared so far in a sweep.
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    /// The name of the configuration.
+    pub name: String,
+    /// The number of slices for which the configuration has run.
+    pub slices: usize,
+    /// The time for which the configuration has run.
+    pub elapsed: Duration,
+    /// The number of distinct states the configuration has discovered.
+    pub states: usize,
+    /// The rate, in new states per second, at which the configuration
+    /// discovered states in its last slice.
+    pub rate: f64,
+}
+
+/// Trait of policies for allocating sweep slices to configurations.
+pub trait Policy {
+    /// Picks the index in `stats` of the configuration to run next.
+    ///
+    /// `stats` is never empty.
+    fn choose(&mut self, stats: &[Stats]) -> usize;
+}
+
+/// A policy that runs each configuration in turn.
+#[derive(Clone, Debug, Default)]
+pub struct RoundRobin {
+    next: usize,
+}
+
+impl Policy for RoundRobin {
+    fn choose(&mut self, stats: &[Stats]) -> usize {
+        let i = self.next % stats.len();
+        self.next = i + 1;
+        i
+    }
+}
+
+/// A policy that tries every configuration once, and then runs whichever
+/// discovered new states fastest in its last slice.
+///
+/// Ties go to the configuration that has run for the least time, so once no
+/// configuration is discovering anything, the budget spreads evenly.
+#[derive(Clone, Debug, Default)]
+pub struct Greedy;
+
+impl Policy for Greedy {
+    fn choose(&mut self, stats: &[Stats]) -> usize {
+        if let Some(i) = stats.iter().position(|s| s.slices == 0) {
+            return i;
+        }
+        stats
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| {
+                a.rate
+                    .total_cmp(&b.rate)
+                    .then_with(|| b.elapsed.cmp(&a.elapsed))
+            })
+            .map_or(0, |(i, _)| i)
+    }
+}
+
+/// The available allocation policies.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// Allocate by discovery rate (see [Greedy]).
+    #[default]
+    Greedy,
+    /// Allocate evenly (see [`RoundRobin`]).
+    RoundRobin,
+}
+
+impl Strategy {
+    /// All strategies, in order.
+    pub const ALL: [Self; 2] = [Self::Greedy, Self::RoundRobin];
+
+    /// Makes a fresh policy implementing this strategy.
+    #[must_use]
+    pub fn to_policy(self) -> Box<dyn Policy> {
+        match self {
+            Self::Greedy => Box::new(Greedy),
+            Self::RoundRobin => Box::new(RoundRobin::default()),
+        }
+    }
+}
+
+impl Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Greedy => string::GREEDY,
+            Self::RoundRobin => string::ROUND_ROBIN,
+        })
+    }
+}
+
+/// Parses a strategy by applying the inverse of `Display`.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::run::sweep::Strategy;
+/// for s in Strategy::ALL {
+///     assert_eq!(s.to_string().parse::<Strategy>(), Ok(s));
+/// }
+/// assert!("fastest".parse::<Strategy>().is_err());
+/// ```
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            string::GREEDY => Ok(Self::Greedy),
+            string::ROUND_ROBIN => Ok(Self::RoundRobin),
+            s => Err(s.to_owned()),
+        }
+    }
+}
+
+/// String representations of allocation strategies.
+pub mod string {
+    /// The string representation of the greedy strategy.
+    pub const GREEDY: &str = "greedy";
+    /// The string representation of the round-robin strategy.
+    pub const ROUND_ROBIN: &str = "round-robin";
+    /// All string representations of strategies.
+    pub const ALL: &[&str] = &[GREEDY, ROUND_ROBIN];
+}
+
+/// A sweep under a time budget.
+pub struct Sweep {
+    budget: Duration,
+    slice: Duration,
+    policy: Box<dyn Policy>,
+}
+
+/// The result of a sweep: each configuration's stats and merged report, in
+/// the order the configurations were given.
+#[derive(Clone, Debug, Default)]
+pub struct Outcome {
+    /// The stats and report for each configuration that ran.
+    pub configs: Vec<(Stats, model::Report)>,
+}
+
+impl Outcome {
+    /// Collects the reports into a suite, keyed by configuration name.
+    #[must_use]
+    pub fn into_suite(self) -> model::Suite {
+        let mut suite = model::Suite::default();
+        for (stats, report) in self.configs {
+            suite.insert(&stats.name, report);
+        }
+        suite
+    }
+}
+
+impl Sweep {
+    /// Constructs a sweep with total budget `budget`, split into
+    /// [`DEFAULT_SLICES`] slices, allocated by the greedy policy.
+    #[must_use]
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            slice: budget / DEFAULT_SLICES,
+            policy: Strategy::default().to_policy(),
+        }
+    }
+
+    /// Overrides the length of each slice.
+    #[must_use]
+    pub fn with_slice(self, slice: Duration) -> Self {
+        Self { slice, ..self }
+    }
+
+    /// Overrides the allocation policy.
+    #[must_use]
+    pub fn with_policy(self, policy: Box<dyn Policy>) -> Self {
+        Self { policy, ..self }
+    }
+
+    /// Runs the sweep over the configurations named `names`.
+    ///
+    /// `step` runs one slice of the configuration at the given index; it must
+    /// add the given exit rule, which fires when the slice is up, to the run.
+    /// Slices can end early if the configuration has exit rules of its own.
+    ///
+    /// # Errors
+    ///
+    /// Fails as soon as any slice fails.
+    pub fn run<E>(
+        mut self,
+        names: impl IntoIterator<Item = String>,
+        mut step: impl FnMut(usize, halt::Rule) -> Result<model::Report, E>,
+    ) -> Result<Outcome, E> {
+        let mut stats: Vec<Stats> = names
+            .into_iter()
+            .map(|name| Stats {
+                name,
+                ..Stats::default()
+            })
+            .collect();
+        let mut reports: Vec<Option<model::Report>> = vec![None; stats.len()];
+        let start = Instant::now();
+
+        while !stats.is_empty() {
+            let Some(left) = self
+                .budget
+                .checked_sub(start.elapsed())
+                .filter(|d| !d.is_zero())
+            else {
+                break;
+            };
+            let i = self.policy.choose(&stats);
+            let slice_start = Instant::now();
+            let report = timed(self.slice.min(left), |rule| step(i, rule))?;
+            let elapsed = slice_start.elapsed();
+
+            let merged = match &mut reports[i] {
+                Some(r) => {
+                    r.merge(report);
+                    r
+                }
+                slot @ None => slot.insert(report),
+            };
+            let s = &mut stats[i];
+            let found = merged.states.len().saturating_sub(s.states);
+            s.states = merged.states.len();
+            s.slices += 1;
+            s.elapsed += elapsed;
+            #[allow(clippy::cast_precision_loss)]
+            let found = found as f64;
+            s.rate = found / elapsed.as_secs_f64().max(f64::EPSILON);
+        }
+
+        Ok(Outcome {
+            configs: stats
+                .into_iter()
+                .zip(reports)
+                .filter_map(|(s, r)| Some((s, r?)))
+                .collect(),
+        })
+    }
+}
+
+/// Runs `f` with an exit rule that fires after `duration`.
+fn timed<T>(duration: Duration, f: impl FnOnce(halt::Rule) -> T) -> T {
+    let (rule, mut stop) = halt::Rule::on_callback(halt::Type::Exit, "timeout");
+    let (done, wait) = mpsc::channel::<()>();
+    let timer = std::thread::spawn(move || {
+        // Finishing early drops the sender, which wakes the timer.
+        if wait.recv_timeout(duration) == Err(mpsc::RecvTimeoutError::Timeout) {
+            stop();
+        }
+    });
+    let result = f(rule);
+    drop(done);
+    let _ = timer.join();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(slices: usize, elapsed_ms: u64, rate: f64) -> Stats {
+        Stats {
+            slices,
+            elapsed: Duration::from_millis(elapsed_ms),
+            rate,
+            ..Stats::default()
+        }
+    }
+
+    /// Tests that the greedy policy tries untried configurations first, then
+    /// favours discovery rate, then the least-run configuration.
+    #[test]
+    fn test_greedy_choose() {
+        let mut p = Greedy;
+        assert_eq!(p.choose(&[stats(1, 5, 9.0), stats(0, 0, 0.0)]), 1);
+        assert_eq!(p.choose(&[stats(1, 5, 1.0), stats(1, 5, 9.0)]), 1);
+        assert_eq!(p.choose(&[stats(2, 10, 0.0), stats(1, 5, 0.0)]), 1);
+    }
+
+    /// Tests that round robin cycles through configurations.
+    #[test]
+    fn test_round_robin_choose() {
+        let mut p = RoundRobin::default();
+        let s = [stats(0, 0, 0.0), stats(0, 0, 0.0)];
+        let got: Vec<_> = (0..4).map(|_| p.choose(&s)).collect();
+        assert_eq!(got, [0, 1, 0, 1]);
+    }
+
+    /// Tests that sweeps favour the configuration that keeps finding states,
+    /// merging each configuration's slices.
+    #[test]
+    fn test_sweep_run() {
+        let mut calls = [0usize; 2];
+        let outcome = Sweep::new(Duration::from_millis(200))
+            .with_slice(Duration::from_millis(10))
+            .run(["dry".to_owned(), "rich".to_owned()], |i, _| {
+                calls[i] += 1;
+                std::thread::sleep(Duration::from_millis(1));
+                // The rich configuration finds a new state every slice.
+                let x = if i == 0 { 0 } else { calls[i] };
+                let state = model::report::State {
+                    state: [("x".into(), model::state::Value::I32(x.try_into().unwrap()))].into(),
+                    info: model::state::Info::new(model::Outcome::Pass, 0, 0),
+                    initials: vec![],
+                    interesting: vec![],
+                    checks: std::collections::BTreeMap::new(),
+                    aux_flags: std::collections::BTreeMap::new(),
+                    hash: String::new(),
+                };
+                let mut r = model::Report::default();
+                r.insert(state);
+                Ok::<_, ()>(r)
+            })
+            .unwrap();
+
+        let (dry, rich) = (&outcome.configs[0], &outcome.configs[1]);
+        // The dry configuration runs again at most once, on a tie.
+        assert!(dry.0.slices <= 2);
+        assert_eq!(dry.1.states.len(), 1);
+        assert!(dry.0.slices < rich.0.slices);
+        assert_eq!(rich.1.states.len(), rich.0.slices);
+    }
+}
@@ -1,11 +1,9 @@
 //! Synchronisation primitives for the test FSA.
 
+use super::atomics::{self, AtomicIsize, AtomicU32, AtomicUsize, Condvar, Mutex};
 use crate::err;
-use std::sync::{
-    atomic::{AtomicIsize, Ordering},
-    Arc, Barrier,
-};
-use std::{convert::TryFrom, num::NonZeroUsize};
+use std::sync::{atomic::Ordering, Arc, Barrier};
+use std::{convert::TryFrom, num::NonZeroUsize, time::Duration};
 
 /// Trait of things that can serve as thread synchronisers in the FSA.
 ///
@@ -19,7 +17,7 @@ use std::{convert::TryFrom, num::NonZeroUsize};
 ///
 /// 1. either all threads are about to call `run`; or,
 /// 2. precisely one is about to call `obs` and the others are about to call
-///   `wait`.
+///    `wait`.
 ///
 /// This drives the FSA workflow that, at any point, all threads are either
 /// running the concurrent test, or have elected one thread to do the
@@ -29,6 +27,10 @@ use std::{convert::TryFrom, num::NonZeroUsize};
 /// whether runners can be observers, rather than using that fact to hold data
 /// that only the observer can access.  We assume that the FSA itself does this,
 /// using the above invariant as justification.
+///
+/// # Safety
+///
+/// Implementors must uphold the invariant above.
 pub unsafe trait Synchroniser {
     /// Runner should call this after running;
     /// it returns whether the runner is an observer or not.
@@ -45,9 +47,9 @@ pub unsafe trait Synchroniser {
 
 /// Enumeration of roles that a synchroniser can hand out.
 pub enum Role {
-    /// The thread should call [Synchroniser::obs] next.
+    /// The thread should call [`Synchroniser::obs`] next.
     Observer,
-    /// The thread should call [Synchroniser::wait] next.
+    /// The thread should call [`Synchroniser::wait`] next.
     Waiter,
 }
 
@@ -97,6 +99,37 @@ unsafe impl Synchroniser for spin::Barrier {
     }
 }
 
+/// Tunable parameters for a [Spinner] synchroniser.
+///
+/// Each knob trades some of the spinner's fidelity, tight lockstep between
+/// threads, for throughput when threads outnumber cores.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SpinnerParams {
+    /// Whether to hint to the CPU that each spin is a spin (see
+    /// [`std::hint::spin_loop`]).
+    ///
+    /// This frees resources for a sibling hyperthread, but can slow down how
+    /// soon a waiting thread notices that it can proceed.
+    pub hint: bool,
+    /// If given, yield to the OS scheduler after every this many spins.
+    pub yield_after: Option<NonZeroUsize>,
+    /// If given, park after this many spins until the last thread arrives.
+    ///
+    /// Parking uses a futex on Linux; elsewhere, parked threads yield instead.
+    pub park_after: Option<NonZeroUsize>,
+}
+
+/// The default parameters spin with hints, and never yield or park.
+impl Default for SpinnerParams {
+    fn default() -> Self {
+        Self {
+            hint: true,
+            yield_after: None,
+            park_after: None,
+        }
+    }
+}
+
 /// A synchroniser based on a simple atomic counter and busy-waiting.
 ///
 /// When the spinner is positive, we're waiting for runners to finish; when it's
@@ -104,10 +137,17 @@ unsafe impl Synchroniser for spin::Barrier {
 pub struct Spinner {
     nthreads: isize,
     inner: AtomicIsize,
+    params: SpinnerParams,
+    /// Bumped on each change of phase, if parking, for parked threads to
+    /// wait on.
+    epoch: AtomicU32,
+    /// The number of threads currently parked (or about to park).
+    parked: AtomicUsize,
 }
 
 impl Spinner {
-    /// Constructs a new [Spinner] with room for `nthreads` threads.
+    /// Constructs a new [Spinner] with room for `nthreads` threads, using the
+    /// default parameters.
     ///
     /// A [Spinner] can only hold enough threads that fit inside an `isize`,
     /// for implementation reasons.
@@ -117,30 +157,88 @@ impl Spinner {
     /// Fails if the number of threads is too high to fit inside the implementation of the
     /// [Spinner].
     pub fn new(nthreads: NonZeroUsize) -> err::Result<Self> {
+        Self::with_params(nthreads, SpinnerParams::default())
+    }
+
+    /// Constructs a new [Spinner] with room for `nthreads` threads, using the
+    /// given parameters.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the number of threads is too high to fit inside an `isize`.
+    pub fn with_params(nthreads: NonZeroUsize, params: SpinnerParams) -> err::Result<Self> {
         let nthreads =
             isize::try_from(nthreads.get()).map_err(err::Error::TooManyThreadsForSpinner)?;
 
         Ok(Spinner {
             nthreads,
             inner: AtomicIsize::new(nthreads),
+            params,
+            epoch: AtomicU32::new(0),
+            parked: AtomicUsize::new(0),
         })
     }
+
+    /// Sets the counter to `value`, waking any parked threads.
+    fn release(&self, value: isize) {
+        if self.params.park_after.is_none() {
+            self.inner.store(value, Ordering::Release);
+            return;
+        }
+        self.inner.store(value, Ordering::SeqCst);
+        self.epoch.fetch_add(1, Ordering::SeqCst);
+        if self.parked.load(Ordering::SeqCst) != 0 {
+            futex::wake_all(&self.epoch);
+        }
+    }
+
+    /// Spins until `done` holds of the counter.
+    fn await_counter(&self, done: impl Fn(isize) -> bool) {
+        let mut spins: usize = 0;
+        loop {
+            // Reading the epoch first means a parker can't miss the release.
+            let epoch = self.epoch.load(Ordering::SeqCst);
+            if done(self.inner.load(Ordering::Acquire)) {
+                return;
+            }
+            spins = spins.wrapping_add(1);
+            if self.params.park_after.is_some_and(|n| n.get() <= spins) {
+                self.park(epoch, &done);
+            } else if self
+                .params
+                .yield_after
+                .is_some_and(|n| spins.is_multiple_of(n.get()))
+            {
+                atomics::yield_now();
+            } else if self.params.hint {
+                atomics::spin_loop();
+            }
+        }
+    }
+
+    /// Parks until the epoch moves on from `epoch`, unless `done` already
+    /// holds.
+    fn park(&self, epoch: u32, done: &impl Fn(isize) -> bool) {
+        self.parked.fetch_add(1, Ordering::SeqCst);
+        if !done(self.inner.load(Ordering::SeqCst)) {
+            futex::wait(&self.epoch, epoch);
+        }
+        self.parked.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 unsafe impl Synchroniser for Spinner {
     fn run(&self) -> Role {
         let count = self.inner.fetch_sub(1, Ordering::AcqRel);
-        assert!(0 < count, "count negative after run (={})", count);
+        assert!(0 < count, "count negative after run (={count})");
 
         if count == 1 {
             // We were the last thread to be waited upon.
-            self.inner.store(-self.nthreads, Ordering::Release);
+            self.release(-self.nthreads);
             Role::Observer
         } else {
             // We need to wait until the last thread runs.
-            while self.inner.load(Ordering::Acquire) >= 0 {
-                std::hint::spin_loop();
-            }
+            self.await_counter(|c| c < 0);
             Role::Waiter
         }
     }
@@ -155,22 +253,303 @@ unsafe impl Synchroniser for Spinner {
 
     fn wait(&self) {
         let count = self.inner.fetch_add(1, Ordering::AcqRel);
-        assert!(count < 0, "count positive while waiting (={})", count);
+        assert!(count < 0, "count positive while waiting (={count})");
 
         if count == -1 {
             // We were the last thread to be waited upon.
-            self.inner.store(self.nthreads, Ordering::Release);
+            self.release(self.nthreads);
         } else {
             // We need to wait until the last thread gets here.
-            while self.inner.load(Ordering::Acquire) <= 0 {
-                // busy wait
+            self.await_counter(|c| c > 0);
+        }
+    }
+}
+
+/// Parking on a 32-bit word, for [Spinner].
+///
+/// `loom` has no futexes, so under `loom`, parking yields instead.
+mod futex {
+    use super::AtomicU32;
+
+    /// Blocks until `word` is woken, unless it no longer holds `expected`.
+    ///
+    /// This can return spuriously.
+    #[cfg(all(target_os = "linux", not(feature = "loom")))]
+    pub fn wait(word: &AtomicU32, expected: u32) {
+        // Failure (including the word having moved on) just means we recheck.
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                word.as_ptr(),
+                libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+                expected,
+                std::ptr::null::<libc::timespec>(),
+            );
+        }
+    }
+
+    /// Wakes every thread blocked on `word`.
+    #[cfg(all(target_os = "linux", not(feature = "loom")))]
+    pub fn wake_all(word: &AtomicU32) {
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                word.as_ptr(),
+                libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+                i32::MAX,
+            );
+        }
+    }
+
+    /// Yields, as there is no futex to block on.
+    #[cfg(any(not(target_os = "linux"), feature = "loom"))]
+    pub fn wait(_word: &AtomicU32, _expected: u32) {
+        super::atomics::yield_now();
+    }
+
+    /// Does nothing, as nothing blocks.
+    #[cfg(any(not(target_os = "linux"), feature = "loom"))]
+    pub fn wake_all(_word: &AtomicU32) {}
+}
+
+/// Tunable parameters for a [Backoff] synchroniser.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BackoffParams {
+    /// The number of rounds of spinning before yielding; each round spins
+    /// twice as long as the last, up to a cap.
+    pub spins: u32,
+    /// The number of times to yield to the OS scheduler before parking.
+    pub yields: u32,
+    /// The longest a parked thread sleeps before rechecking the counter.
+    ///
+    /// Parked threads are usually woken as soon as the last thread arrives,
+    /// so this is a safety net rather than a polling interval.
+    pub park_timeout: Duration,
+}
+
+/// The default parameters spin briefly, then yield for a while, then park.
+impl Default for BackoffParams {
+    fn default() -> Self {
+        Self {
+            spins: 6,
+            yields: 10,
+            park_timeout: Duration::from_millis(1),
+        }
+    }
+}
+
+/// The cap on the exponent of the number of spins in one backoff round.
+const MAX_SPIN_SHIFT: u32 = 10;
+
+/// A synchroniser that uses the same counting protocol as [Spinner], but
+/// backs off adaptively while waiting.
+///
+/// A waiting thread first spins for exponentially growing rounds, then yields
+/// to the OS scheduler, and finally parks on a condition variable until the
+/// last thread arrives.  This trades some latency for not monopolising CPUs
+/// when threads outnumber cores or iterations take a long time.
+pub struct Backoff {
+    nthreads: isize,
+    inner: AtomicIsize,
+    params: BackoffParams,
+    /// The number of threads currently parked (or about to park).
+    parked: AtomicUsize,
+    lock: Mutex<()>,
+    wake: Condvar,
+}
+
+impl Backoff {
+    /// Constructs a new [Backoff] with room for `nthreads` threads, using the
+    /// given backoff parameters.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the number of threads is too high to fit inside an `isize`.
+    pub fn new(nthreads: NonZeroUsize, params: BackoffParams) -> err::Result<Self> {
+        let nthreads =
+            isize::try_from(nthreads.get()).map_err(err::Error::TooManyThreadsForSpinner)?;
+
+        Ok(Backoff {
+            nthreads,
+            inner: AtomicIsize::new(nthreads),
+            params,
+            parked: AtomicUsize::new(0),
+            lock: Mutex::new(()),
+            wake: Condvar::new(),
+        })
+    }
+
+    /// Sets the counter to `value`, waking any parked threads.
+    fn release(&self, value: isize) {
+        self.inner.store(value, Ordering::SeqCst);
+        if self.parked.load(Ordering::SeqCst) != 0 {
+            // Taking the lock ensures no parker is between its check and wait.
+            drop(self.lock.lock());
+            self.wake.notify_all();
+        }
+    }
+
+    /// Backs off until `done` holds of the counter.
+    fn await_counter(&self, done: impl Fn(isize) -> bool) {
+        let mut round: u32 = 0;
+        while !done(self.inner.load(Ordering::Acquire)) {
+            if round < self.params.spins {
+                for _ in 0..(1_u32 << round.min(MAX_SPIN_SHIFT)) {
+                    atomics::spin_loop();
+                }
+                round += 1;
+            } else if round - self.params.spins < self.params.yields {
+                atomics::yield_now();
+                round += 1;
+            } else {
+                self.park(&done);
             }
         }
     }
+
+    fn park(&self, done: &impl Fn(isize) -> bool) {
+        // A poisoned lock guards nothing, so we can safely carry on.
+        let guard = self
+            .lock
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        self.parked.fetch_add(1, Ordering::SeqCst);
+        if !done(self.inner.load(Ordering::SeqCst)) {
+            drop(self.wake.wait_timeout(guard, self.params.park_timeout));
+        }
+        self.parked.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+unsafe impl Synchroniser for Backoff {
+    fn run(&self) -> Role {
+        let count = self.inner.fetch_sub(1, Ordering::AcqRel);
+        assert!(0 < count, "count negative after run (={count})");
+
+        if count == 1 {
+            self.release(-self.nthreads);
+            Role::Observer
+        } else {
+            self.await_counter(|c| c < 0);
+            Role::Waiter
+        }
+    }
+
+    fn obs(&self) {
+        self.wait();
+    }
+
+    fn wait(&self) {
+        let count = self.inner.fetch_add(1, Ordering::AcqRel);
+        assert!(count < 0, "count positive while waiting (={count})");
+
+        if count == -1 {
+            self.release(self.nthreads);
+        } else {
+            self.await_counter(|c| c > 0);
+        }
+    }
+}
+
+/// A synchroniser that wraps another, but nominates the observer
+/// pseudo-randomly on each iteration rather than taking the inner
+/// synchroniser's choice.
+///
+/// Inner synchronisers tend to nominate the last thread to finish running,
+/// which correlates with thread identity and so can bias results.  Here,
+/// every thread takes a ticket once the inner synchroniser has seen them all
+/// finish, and the holder of a ticket drawn from the seed and iteration
+/// observes.  The draw is independent of the order in which threads take
+/// tickets, so each thread is equally likely to observe.
+///
+/// Each phase costs two phases of the inner synchroniser: one to nominate
+/// the observer, and one to hold the waiters while it observes.
+pub struct RandomObserver {
+    inner: Arc<dyn Synchroniser>,
+    nthreads: usize,
+    seed: u64,
+    /// The number of tickets taken so far; iteration `i` takes tickets
+    /// `i * nthreads` up to `(i + 1) * nthreads`.
+    tickets: AtomicUsize,
+}
+
+impl RandomObserver {
+    /// Wraps `inner`, which synchronises `nthreads` threads, choosing
+    /// observers using `seed`.
+    #[must_use]
+    pub fn new(inner: Arc<dyn Synchroniser>, nthreads: NonZeroUsize, seed: u64) -> Self {
+        Self {
+            inner,
+            nthreads: nthreads.get(),
+            seed,
+            tickets: AtomicUsize::new(0),
+        }
+    }
+
+    /// Gets the place in the ticket order of the observer for iteration
+    /// `iteration`.
+    fn chosen(&self, iteration: usize) -> usize {
+        let n = u64::try_from(self.nthreads).unwrap_or(u64::MAX);
+        let i = u64::try_from(iteration).unwrap_or(u64::MAX);
+        // The remainder is less than nthreads, so fits in a usize.
+        usize::try_from(splitmix(self.seed ^ splitmix(i)) % n).unwrap_or(0)
+    }
+
+    /// Runs one whole phase of the inner synchroniser, where this thread's
+    /// inner role is `role`.
+    fn finish(&self, role: &Role) {
+        match role {
+            Role::Observer => self.inner.obs(),
+            Role::Waiter => self.inner.wait(),
+        }
+    }
+}
+
+/// The FSA already shares every synchroniser between its threads, inner ones
+/// included, so sharing the wrapper is no less sound.
+unsafe impl Send for RandomObserver {}
+
+/// See the Send implementation.
+unsafe impl Sync for RandomObserver {}
+
+/// The `SplitMix64` finaliser, which scrambles `x` into a well-mixed hash.
+fn splitmix(x: u64) -> u64 {
+    let x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    let x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// The wrapper upholds the invariant so long as the inner synchroniser does:
+/// no thread leaves [`RandomObserver::run`] until every thread has taken its
+/// ticket for the iteration, and exactly one ticket wins.  Observers and
+/// waiters then meet at another inner phase, which only finishes once the
+/// observer arrives.
+unsafe impl Synchroniser for RandomObserver {
+    fn run(&self) -> Role {
+        let role = self.inner.run();
+        // The inner synchroniser orders tickets with respect to this phase.
+        let ticket = self.tickets.fetch_add(1, Ordering::Relaxed);
+        self.finish(&role);
+        let iteration = ticket / self.nthreads;
+        Role::from_leader(ticket % self.nthreads == self.chosen(iteration))
+    }
+
+    fn obs(&self) {
+        self.finish(&self.inner.run());
+    }
+
+    fn wait(&self) {
+        self.finish(&self.inner.run());
+    }
 }
 
 /// Type alias of functions that return fully wrapped synchronisers.
-pub type Factory = fn(NonZeroUsize) -> err::Result<Arc<dyn Synchroniser>>;
+///
+/// Factories are boxed closures, rather than function pointers, so that they
+/// can capture parameters (as [`backoff_factory`] does) that config passes in
+/// when choosing a synchroniser.
+pub type Factory = Box<dyn Fn(NonZeroUsize) -> err::Result<Arc<dyn Synchroniser>> + Send + Sync>;
 
 /// Wrapper function for making synchronisers out of barriers.
 ///
@@ -198,3 +577,299 @@ pub fn make_spin_barrier(nthreads: NonZeroUsize) -> err::Result<Arc<dyn Synchron
 pub fn make_spinner(nthreads: NonZeroUsize) -> err::Result<Arc<dyn Synchroniser>> {
     Ok(Arc::new(Spinner::new(nthreads)?))
 }
+
+/// Makes a factory for spinners with the given parameters.
+#[must_use]
+pub fn spinner_factory(params: SpinnerParams) -> Factory {
+    Box::new(move |nthreads| Ok(Arc::new(Spinner::with_params(nthreads, params)?)))
+}
+
+/// Wrapper function for making synchronisers out of backoff synchronisers
+/// with default parameters.
+///
+/// # Errors
+///
+/// Fails if the [Backoff] fails to construct (eg, the number of threads is too high).
+pub fn make_backoff(nthreads: NonZeroUsize) -> err::Result<Arc<dyn Synchroniser>> {
+    Ok(Arc::new(Backoff::new(nthreads, BackoffParams::default())?))
+}
+
+/// Makes a factory for backoff synchronisers with the given parameters.
+#[must_use]
+pub fn backoff_factory(params: BackoffParams) -> Factory {
+    Box::new(move |nthreads| Ok(Arc::new(Backoff::new(nthreads, params)?)))
+}
+
+/// Makes a factory that wraps the synchronisers from `inner` so that they
+/// choose observers pseudo-randomly from `seed` (see [`RandomObserver`]).
+#[must_use]
+pub fn random_observer_factory(inner: Factory, seed: u64) -> Factory {
+    Box::new(move |nthreads| {
+        Ok(Arc::new(RandomObserver::new(
+            inner(nthreads)?,
+            nthreads,
+            seed,
+        )))
+    })
+}
+
+/// A plan for alternating between synchronisers within one run.
+///
+/// The run starts on the first arm, and moves on to the next, wrapping
+/// around, every `every` rotations; everything else about the run stays the
+/// same, so that any difference between the states seen under each arm is
+/// down to the synchroniser.
+pub struct Alternation {
+    /// The synchronisers to alternate, each with the name under which it
+    /// appears in the report.
+    pub arms: Vec<(String, Factory)>,
+    /// The number of rotations for which each synchroniser stays active.
+    pub every: NonZeroUsize,
+}
+
+impl Alternation {
+    /// Gets the arm that should be active in the rotation with index
+    /// `rotation`, if there are any arms.
+    #[must_use]
+    pub fn arm(&self, rotation: usize) -> Option<&(String, Factory)> {
+        let n = NonZeroUsize::new(self.arms.len())?;
+        self.arms.get((rotation / self.every) % n)
+    }
+
+    /// Gets the names of the arms, in order.
+    #[must_use]
+    pub fn names(&self) -> Vec<String> {
+        self.arms.iter().map(|(name, _)| name.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model,
+        run::{
+            testing::{every, nop_entry},
+            Builder,
+        },
+    };
+
+    /// Drives `sync` through `rounds` run/observe/wait cycles on `n` threads,
+    /// returning the number of observations.
+    fn drive(sync: &(impl Synchroniser + Sync), n: usize, rounds: usize) -> usize {
+        let observations = AtomicUsize::new(0);
+        crossbeam::thread::scope(|s| {
+            for _ in 0..n {
+                s.spawn(|_| {
+                    for _ in 0..rounds {
+                        match sync.run() {
+                            Role::Observer => {
+                                observations.fetch_add(1, Ordering::Relaxed);
+                                sync.obs();
+                            }
+                            Role::Waiter => sync.wait(),
+                        }
+                    }
+                });
+            }
+        })
+        .unwrap();
+        observations.into_inner()
+    }
+
+    /// Tests that a spinner elects exactly one observer per round, whether it
+    /// yields, parks at once, or does neither.
+    #[test]
+    fn test_spinner_one_observer_per_round() -> err::Result<()> {
+        let n = NonZeroUsize::new(3).unwrap();
+        let every = NonZeroUsize::new(1);
+        for params in [
+            SpinnerParams::default(),
+            SpinnerParams {
+                hint: false,
+                yield_after: every,
+                park_after: None,
+            },
+            SpinnerParams {
+                park_after: every,
+                ..SpinnerParams::default()
+            },
+        ] {
+            assert_eq!(drive(&Spinner::with_params(n, params)?, 3, 100), 100);
+        }
+        Ok(())
+    }
+
+    /// Tests that a backoff synchroniser elects exactly one observer per round,
+    /// even when its parameters force every waiter to park immediately.
+    #[test]
+    fn test_backoff_one_observer_per_round() -> err::Result<()> {
+        let params = BackoffParams {
+            spins: 0,
+            yields: 0,
+            park_timeout: Duration::from_secs(1),
+        };
+        let n = NonZeroUsize::new(3).unwrap();
+        assert_eq!(drive(&Backoff::new(n, params)?, 3, 100), 100);
+        assert_eq!(
+            drive(&Backoff::new(n, BackoffParams::default())?, 3, 100),
+            100
+        );
+        Ok(())
+    }
+
+    /// Tests that a random observer elects exactly one observer per round,
+    /// and that every thread gets a turn.
+    #[test]
+    fn test_random_observer_spreads_observers() {
+        let n = NonZeroUsize::new(3).unwrap();
+        let sync = RandomObserver::new(Arc::new(Barrier::new(n.get())), n, 42);
+        let counts: Vec<usize> = crossbeam::thread::scope(|s| {
+            let handles: Vec<_> = (0..n.get())
+                .map(|_| {
+                    s.spawn(|_| {
+                        let mut count = 0;
+                        for _ in 0..300 {
+                            match sync.run() {
+                                Role::Observer => {
+                                    count += 1;
+                                    sync.obs();
+                                }
+                                Role::Waiter => sync.wait(),
+                            }
+                        }
+                        count
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        })
+        .unwrap();
+        assert_eq!(counts.iter().sum::<usize>(), 300);
+        assert!(counts.iter().all(|&c| c != 0), "counts: {counts:?}");
+    }
+
+    /// Tests that synchroniser factories can carry their own parameters.
+    #[test]
+    fn test_with_parameterised_sync() -> err::Result<()> {
+        let made = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let params = BackoffParams {
+            spins: 0,
+            ..BackoffParams::default()
+        };
+        let inner = backoff_factory(params);
+        let counter = made.clone();
+        let factory = move |n: NonZeroUsize| {
+            assert_eq!(n.get(), 2);
+            counter.fetch_add(1, Ordering::Relaxed);
+            inner(n)
+        };
+        let cond = every(100);
+
+        let report = Builder::new(nop_entry(2))
+            .with_sync(factory)
+            .add_halt_rules(std::iter::once(cond.exit()))
+            .build()?
+            .run()?;
+
+        let iterations: usize = report.states.iter().map(|s| s.info.occurs).sum();
+        assert_eq!(iterations, 100);
+        assert_eq!(made.load(Ordering::Relaxed), 1);
+        assert_eq!(
+            report.ended_by,
+            Some(model::ending::Cause::Iterations { iterations: 100 })
+        );
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "loom"))]
+mod loom_tests {
+    use super::*;
+    use crate::run::atomics::UnsafeCell;
+
+    /// The number of rounds that each model runs.
+    const ROUNDS: usize = 2;
+
+    /// Values that each thread writes while running, and the observer reads.
+    struct Slots([UnsafeCell<usize>; 2]);
+
+    /// Sharing the slots is sound exactly when the synchroniser under test
+    /// is, which is what `loom` checks.
+    unsafe impl Sync for Slots {}
+
+    /// Checks, under `loom`, that the synchronisers from `make` elect
+    /// exactly one observer per round on two threads, and that the observer
+    /// sees each thread's writes from that round without racing them.
+    fn check<S: Synchroniser + Send + Sync + 'static>(make: fn() -> err::Result<S>) {
+        let mut model = loom::model::Builder::new();
+        model.preemption_bound = Some(2);
+        model.check(move || {
+            let sync = Arc::new(make().unwrap());
+            let slots = Arc::new(Slots([UnsafeCell::new(0), UnsafeCell::new(0)]));
+            let observations = Arc::new(AtomicUsize::new(0));
+            let threads: Vec<_> = (0..2)
+                .map(|tid| {
+                    let (sync, slots, observations) =
+                        (sync.clone(), slots.clone(), observations.clone());
+                    loom::thread::spawn(move || {
+                        for round in 1..=ROUNDS {
+                            unsafe { slots.0[tid].with_mut(|s| *s = round) };
+                            match sync.run() {
+                                Role::Observer => {
+                                    for slot in &slots.0 {
+                                        assert_eq!(unsafe { slot.with(|s| *s) }, round);
+                                    }
+                                    observations.fetch_add(1, Ordering::Relaxed);
+                                    sync.obs();
+                                }
+                                Role::Waiter => sync.wait(),
+                            }
+                        }
+                    })
+                })
+                .collect();
+            for thread in threads {
+                thread.join().unwrap();
+            }
+            assert_eq!(observations.load(Ordering::Relaxed), ROUNDS);
+        });
+    }
+
+    const TWO: NonZeroUsize = NonZeroUsize::new(2).unwrap();
+
+    /// Model-checks a spinner that spins with hints.
+    #[test]
+    fn loom_spinner() {
+        check(|| Spinner::new(TWO));
+    }
+
+    /// Model-checks a spinner that parks at once.
+    #[test]
+    fn loom_spinner_parking() {
+        check(|| {
+            let params = SpinnerParams {
+                park_after: NonZeroUsize::new(1),
+                ..SpinnerParams::default()
+            };
+            Spinner::with_params(TWO, params)
+        });
+    }
+
+    /// Model-checks a backoff synchroniser that spins, then yields.
+    ///
+    /// This never parks: `loom` treats `SeqCst` accesses as `AcqRel`, so it
+    /// can't see that parkers and releasers always notice each other, and
+    /// its condition variables never time out as the park timeout would.
+    #[test]
+    fn loom_backoff() {
+        check(|| {
+            let params = BackoffParams {
+                spins: 1,
+                yields: u32::MAX,
+                ..BackoffParams::default()
+            };
+            Backoff::new(TWO, params)
+        });
+    }
+}
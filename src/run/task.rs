@@ -0,0 +1,184 @@
+//! Executor-agnostic asynchronous test runs.
+//!
+//! A [Task] runs a test on a background thread and yields its report as a
+//! [Future], so that phenolphthalein can be embedded in asynchronous code
+//! without depending on any particular executor.  Get one from
+//! [`super::Runner::run_async`].
+
+use super::{halt, Handle};
+use crate::{err, model};
+use std::{
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll, Waker},
+    thread,
+};
+
+/// A test run on a background thread, which completes with its report.
+///
+/// Dropping a task before it completes asks its run to exit.
+pub struct Task {
+    /// The handle used to cancel the run.
+    handle: Handle,
+    /// The slot into which the background thread puts the result.
+    slot: Arc<Mutex<Slot>>,
+}
+
+/// The result of a task, and the waker of whoever is waiting for it.
+#[derive(Default)]
+struct Slot {
+    result: Option<err::Result<model::Report>>,
+    waker: Option<Waker>,
+}
+
+impl Slot {
+    /// Fills the slot at `slot` with `result`, waking whoever is waiting.
+    fn fill(slot: &Mutex<Self>, result: err::Result<model::Report>) {
+        let mut slot = slot.lock().unwrap_or_else(PoisonError::into_inner);
+        slot.result = Some(result);
+        if let Some(w) = slot.waker.take() {
+            w.wake();
+        }
+    }
+}
+
+impl Task {
+    /// Spawns a task that calls `run` on a background thread, cancelling it
+    /// through `handle`.
+    ///
+    /// If `run` panics, or we can't spawn the thread, the task completes
+    /// with the error.
+    pub(super) fn spawn(
+        handle: Handle,
+        run: impl FnOnce() -> err::Result<model::Report> + Send + 'static,
+    ) -> Self {
+        let slot = Arc::new(Mutex::new(Slot::default()));
+
+        let (thread_handle, thread_slot) = (handle.clone(), slot.clone());
+        let spawned = thread::Builder::new()
+            .name("phph-task".to_owned())
+            .spawn(move || {
+                let result = panic::catch_unwind(AssertUnwindSafe(run)).unwrap_or_else(|p| {
+                    Err(err::Error::ThreadPanic(err::Panic::from_payload(None, &*p)))
+                });
+                thread_handle.finish();
+                Slot::fill(&thread_slot, result);
+            });
+        if let Err(e) = spawned {
+            Slot::fill(&slot, Err(e.into()));
+        }
+        Self { handle, slot }
+    }
+
+    /// Asks the run to halt with type `halt_type`.
+    ///
+    /// Cancellation is cooperative: the run picks up the request the next
+    /// time it observes an iteration, and exiting still yields a report.
+    pub fn cancel(&self, halt_type: halt::Type) {
+        self.handle.cancel(halt_type);
+    }
+}
+
+impl Future for Task {
+    type Output = err::Result<model::Report>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let Ok(mut slot) = self.slot.lock() else {
+            return Poll::Ready(Err(err::Error::LockPoisoned));
+        };
+        if let Some(result) = slot.result.take() {
+            Poll::Ready(result)
+        } else {
+            slot.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        // If the result is already in, this is harmless.
+        self.handle.cancel(halt::Type::Exit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::rust,
+        run::{sync, testing::nop_entry, Builder, Runner},
+    };
+    use std::{
+        task::Wake,
+        time::{Duration, Instant},
+    };
+
+    /// A waker that unparks the thread blocking on a future.
+    struct Unparker(thread::Thread);
+
+    impl Wake for Unparker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// A minimal executor, which blocks on `f` until it completes.
+    fn block_on<F: Future>(f: F) -> F::Output {
+        let waker = Waker::from(Arc::new(Unparker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut f = std::pin::pin!(f);
+        loop {
+            match f.as_mut().poll(&mut cx) {
+                Poll::Ready(x) => break x,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    /// Makes a runner that runs until cancelled.
+    fn endless() -> err::Result<Runner<'static, rust::Static>> {
+        Builder::new(nop_entry(2))
+            .with_sync(sync::make_barrier)
+            .build()
+    }
+
+    /// Tests that a task yields the report of a cancelled run.
+    #[test]
+    fn test_cancel() -> err::Result<()> {
+        let task = endless()?.run_async();
+        task.cancel(halt::Type::Exit);
+        let report = block_on(task)?;
+        assert_eq!(report.states.len(), 1);
+        Ok(())
+    }
+
+    /// Tests that dropping a task stops its run.
+    #[test]
+    fn test_drop_cancels() -> err::Result<()> {
+        let runner = endless()?;
+        let h = runner.handle();
+        let task = runner.run_async();
+        let start = Instant::now();
+        drop(task);
+        while !h.is_finished() {
+            assert!(start.elapsed() < Duration::from_secs(10), "run didn't stop");
+            thread::sleep(Duration::from_millis(1));
+        }
+        Ok(())
+    }
+
+    /// Tests that a task whose run panics completes with the panic, rather
+    /// than leaving its waiter hanging.
+    #[test]
+    fn test_panic_completes() {
+        let task = Task::spawn(Handle::default(), || panic!("deliberate panic"));
+        let result = block_on(task);
+        assert!(matches!(
+            result,
+            Err(err::Error::ThreadPanic(err::Panic { tid: None, message })) if message == "deliberate panic"
+        ));
+    }
+}
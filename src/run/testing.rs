@@ -0,0 +1,58 @@
+//! Fixtures shared between tests of the runner and its parts.
+
+use super::{halt, obs, sync, Builder};
+use crate::{
+    api::{abs, rust},
+    model,
+};
+use std::num::NonZeroUsize;
+
+/// Makes a condition that holds every `n` iterations.
+pub fn every(n: usize) -> halt::Condition {
+    halt::Condition::EveryNIterations(NonZeroUsize::new(n).unwrap())
+}
+
+/// Makes a no-op entry with `n_threads` threads and no variables.
+pub fn nop_entry(n_threads: usize) -> rust::Static {
+    crate::run::bench::nop_entry(NonZeroUsize::new(n_threads).unwrap())
+}
+
+/// Makes a no-op entry with `n_threads` threads and the 32-bit integer
+/// variables `vars`, each given as its name, whether it is atomic, and
+/// its initial value.
+///
+/// Each variable takes the next free slot of its kind.
+pub fn entry_with_i32s(n_threads: usize, vars: &[(&str, bool, Option<i32>)]) -> rust::Static {
+    let mut entry = nop_entry(n_threads);
+    let mut next = [0, 0];
+    for &(name, is_atomic, initial_value) in vars {
+        let index = &mut next[usize::from(is_atomic)];
+        let slot = model::slot::Slot {
+            is_atomic,
+            index: *index,
+        };
+        *index += 1;
+        let record = model::manifest::VarRecord {
+            initial_value,
+            slot,
+        };
+        entry.manifest.i32s.insert(name.into(), record);
+    }
+    entry
+}
+
+/// Makes a builder for `entry` that synchronises at a barrier and exits
+/// after `n` iterations.
+pub fn capped_builder<E: abs::Entry<'static>>(entry: E, n: usize) -> Builder<'static, E> {
+    Builder::new(entry)
+        .with_sync(sync::make_barrier)
+        .add_halt_rules(std::iter::once(every(n).exit()))
+}
+
+/// A hook that sets the initial value of `x` to the number of iterations
+/// observed so far, so that each iteration starts from a new state.
+pub fn x_counts_iterations(env: &mut obs::Manifested<rust::Env>, summary: &obs::Summary) {
+    if let Some(x) = env.manifest.i32s.get_mut("x") {
+        x.initial_value = i32::try_from(summary.iterations).ok();
+    }
+}
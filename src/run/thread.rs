@@ -1,5 +1,6 @@
-//! The Threader trait, crossbeam implementation, and support code.
-use super::{fsa, permute::HasTid};
+//! The Threader trait, crossbeam (and, under `loom`, loom) implementations,
+//! and support code.
+use super::{affinity, fsa, permute::HasTid, priority};
 use crate::{api::abs, err};
 
 /// Trait for things that can 'run' a test automaton as a thread.
@@ -43,19 +44,96 @@ pub trait Threader<'entry, 'scope> {
     }
 }
 
+/// A crossbeam thread handle, tagged with the ID of the test thread it runs.
+pub struct CrossbeamHandle<'scope> {
+    /// The test thread ID.
+    tid: usize,
+    /// The underlying handle.
+    handle: crossbeam::thread::ScopedJoinHandle<'scope, fsa::Done>,
+}
+
 /// Implementation of thread spawning and joining for crossbeam threads.
 impl<'a, 'scope> Threader<'a, 'scope> for &'scope crossbeam::thread::Scope<'a> {
-    type Handle = crossbeam::thread::ScopedJoinHandle<'scope, fsa::Done>;
+    type Handle = CrossbeamHandle<'scope>;
 
     fn spawn<T: abs::Entry<'a> + 'a>(
         &'scope self,
         automaton: fsa::ReadyAutomaton<'a, T>,
     ) -> err::Result<Self::Handle> {
-        let builder = self.builder().name(format!("P{0}", automaton.tid()));
-        Ok(builder.spawn(move |_| automaton.start().run())?)
+        let tid = automaton.tid();
+        let builder = self.builder().name(format!("P{tid}"));
+        let cpus = automaton.cpus();
+        let priority = automaton.priority();
+        let handle = builder.spawn(move |_| {
+            // The runner checked the set up front, so this rarely fails.
+            if let Some(Err(e)) = cpus.map(|cpus| affinity::restrict(&cpus)) {
+                tracing::warn!(tid, error = %e, "couldn't restrict thread to CPUs");
+            }
+            // Likewise, the runner checked that this priority is available.
+            if let Some(Err(e)) = priority.map(priority::set) {
+                tracing::warn!(tid, error = %e, "couldn't set thread priority");
+            }
+            automaton.start().run()
+        })?;
+        tracing::debug!(tid, "spawned thread");
+        Ok(CrossbeamHandle { tid, handle })
+    }
+
+    fn join(&'scope self, handle: Self::Handle) -> err::Result<fsa::Done> {
+        let CrossbeamHandle { tid, handle } = handle;
+        let done = handle
+            .join()
+            .map_err(|p| err::Error::ThreadPanic(err::Panic::from_payload(Some(tid), &*p)))?;
+        tracing::debug!(tid, "joined thread");
+        Ok(done)
+    }
+}
+
+/// The stack size of loom threads.
+///
+/// Loom threads are coroutines with small stacks by default, which observing
+/// a test overflows.
+#[cfg(all(test, feature = "loom"))]
+pub const LOOM_STACK_SIZE: usize = 1 << 22;
+
+/// A loom thread handle, tagged with the ID of the test thread it runs.
+#[cfg(all(test, feature = "loom"))]
+pub struct LoomHandle {
+    /// The test thread ID.
+    tid: usize,
+    /// The underlying handle.
+    handle: loom::thread::JoinHandle<fsa::Done>,
+}
+
+/// Spawns automata as threads of a `loom` model.
+///
+/// Loom threads can't borrow from their spawner, so this only runs entries
+/// that own everything they need.  It ignores CPU restrictions and
+/// priorities, which mean nothing inside the model.
+#[cfg(all(test, feature = "loom"))]
+pub struct Loom;
+
+/// Implementation of thread spawning and joining for loom threads.
+#[cfg(all(test, feature = "loom"))]
+impl<'scope> Threader<'static, 'scope> for Loom {
+    type Handle = LoomHandle;
+
+    fn spawn<E: abs::Entry<'static>>(
+        &'scope self,
+        automaton: fsa::ReadyAutomaton<'static, E>,
+    ) -> err::Result<Self::Handle> {
+        let tid = automaton.tid();
+        let handle = loom::thread::Builder::new()
+            .name(format!("P{tid}"))
+            .stack_size(LOOM_STACK_SIZE)
+            .spawn(move || automaton.start().run())?;
+        Ok(LoomHandle { tid, handle })
     }
 
     fn join(&'scope self, handle: Self::Handle) -> err::Result<fsa::Done> {
-        handle.join().map_err(|_| err::Error::ThreadPanic)
+        let LoomHandle { tid, handle } = handle;
+        handle
+            .join()
+            .map_err(|p| err::Error::ThreadPanic(err::Panic::from_payload(Some(tid), &*p)))
     }
 }
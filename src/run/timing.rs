@@ -0,0 +1,142 @@
+//! Timing of the test body.
+//!
+//! A [Recorder] times how long each thread spends in the test body on every
+//! so many iterations, building up one histogram per thread (see
+//! [`model::timing`]).  Every thread times the same iterations, so that their
+//! histograms are comparable; warmup iterations aren't timed.
+
+use crate::model::timing;
+use std::{
+    num::NonZeroUsize,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Records body timings for each thread of a test.
+///
+/// Each thread only ever writes to its own slot, and the histograms are only
+/// read once the threads are done, so relaxed atomics suffice.
+#[derive(Debug)]
+pub struct Recorder {
+    every: NonZeroUsize,
+    threads: Vec<Slot>,
+}
+
+/// One thread's timings.
+///
+/// Slots are aligned so that threads don't fight over cache lines.
+#[derive(Debug)]
+#[repr(align(128))]
+struct Slot {
+    total_ns: AtomicU64,
+    max_ns: AtomicU64,
+    buckets: [AtomicU64; timing::BUCKETS],
+}
+
+impl Default for Slot {
+    fn default() -> Self {
+        Self {
+            total_ns: AtomicU64::new(0),
+            max_ns: AtomicU64::new(0),
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+}
+
+impl Recorder {
+    /// Makes a recorder for `n_threads` threads that times every `every`
+    /// iterations.
+    #[must_use]
+    pub fn new(every: NonZeroUsize, n_threads: NonZeroUsize) -> Self {
+        Self {
+            every,
+            threads: (0..n_threads.get()).map(|_| Slot::default()).collect(),
+        }
+    }
+
+    /// Starts the clock, if the iteration with index `iteration` is one that
+    /// we time.
+    #[must_use]
+    pub fn start(&self, iteration: usize) -> Option<Instant> {
+        iteration
+            .is_multiple_of(self.every.get())
+            .then(Instant::now)
+    }
+
+    /// Records that thread `tid` spent `elapsed` in the test body.
+    pub fn record(&self, tid: usize, elapsed: Duration) {
+        let Some(slot) = self.threads.get(tid) else {
+            return;
+        };
+        let ns = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        slot.buckets[timing::bucket(ns)].fetch_add(1, Ordering::Relaxed);
+        slot.total_ns.fetch_add(ns, Ordering::Relaxed);
+        slot.max_ns.fetch_max(ns, Ordering::Relaxed);
+    }
+
+    /// Gets the histogram for each thread, in thread order.
+    #[must_use]
+    pub fn histograms(&self) -> Vec<timing::Histogram> {
+        self.threads
+            .iter()
+            .map(|slot| {
+                let mut buckets: Vec<_> = slot
+                    .buckets
+                    .iter()
+                    .map(|b| b.load(Ordering::Relaxed))
+                    .collect();
+                while buckets.last() == Some(&0) {
+                    buckets.pop();
+                }
+                timing::Histogram {
+                    samples: buckets.iter().sum(),
+                    total_ns: slot.total_ns.load(Ordering::Relaxed),
+                    max_ns: slot.max_ns.load(Ordering::Relaxed),
+                    buckets,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        err,
+        run::testing::{capped_builder, nop_entry},
+    };
+
+    /// Tests that recorders only time sampled iterations, and keep threads
+    /// apart.
+    #[test]
+    fn test_recorder() {
+        let r = Recorder::new(NonZeroUsize::new(4).unwrap(), NonZeroUsize::new(2).unwrap());
+        let timed: Vec<_> = (0..9).filter(|&i| r.start(i).is_some()).collect();
+        assert_eq!(timed, [0, 4, 8]);
+
+        r.record(1, Duration::from_nanos(100));
+        r.record(1, Duration::from_nanos(300));
+        r.record(2, Duration::from_nanos(1));
+        let h = r.histograms();
+        assert_eq!(h.len(), 2);
+        assert_eq!(h[0], timing::Histogram::default());
+        assert_eq!((h[1].samples, h[1].total_ns, h[1].max_ns), (2, 400, 300));
+        assert_eq!(h[1].buckets.len(), timing::bucket(300) + 1);
+    }
+
+    /// Tests that timing samples each thread's body on the same iterations,
+    /// skipping warmup.
+    #[test]
+    fn test_timing_samples_threads() -> err::Result<()> {
+        let report = capped_builder(nop_entry(2), 10)
+            .with_warmup(5)
+            .with_timing(NonZeroUsize::new(3))
+            .build()?
+            .run()?;
+
+        let samples: Vec<_> = report.timing.iter().map(|h| h.samples).collect();
+        assert_eq!(samples, [4, 4]);
+        Ok(())
+    }
+}
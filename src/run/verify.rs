@@ -0,0 +1,132 @@
+//! Checks that tests leave their manifests alone.
+//!
+//! C tests export their manifests as data in the test library, which the
+//! runner reads once, into a deep copy, when it builds the run.  Everything
+//! from then on, such as the environment's slots and the observer's
+//! variable names, comes from that copy, so the run itself never sees the
+//! test's own data again.  A test that writes over its manifest mid-run
+//! doesn't break the runner, then, but it does suggest that the test is
+//! scribbling over memory that it doesn't own, and its results are suspect.
+//!
+//! A [Verifier] keeps the copy made at load time and, at each rotation
+//! boundary, reads the manifest afresh and compares the two (see
+//! [`model::manifest::Manifest::difference`]).  Reading the manifest walks
+//! every variable, so this is for debugging misbehaving tests, not for
+//! every run.
+
+use crate::{api::abs, err, model};
+
+/// Watches the manifest of a test for changes.
+#[derive(Clone)]
+pub struct Verifier<E> {
+    /// The entry through which to read the manifest afresh.
+    entry: E,
+    /// The manifest as it was when the run was built.
+    snapshot: model::manifest::Manifest,
+}
+
+impl<'entry, E: abs::Entry<'entry>> Verifier<E> {
+    /// Makes a verifier that compares the manifest of `entry` against
+    /// `snapshot`, the manifest that the run read when it was built.
+    pub fn new(entry: E, snapshot: model::manifest::Manifest) -> Self {
+        Self { entry, snapshot }
+    }
+
+    /// Reads the manifest afresh and checks that it hasn't changed.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`err::Error::ManifestChanged`] if the manifest differs
+    /// from the snapshot, and as reading the manifest would otherwise.
+    pub fn verify(&self) -> err::Result<()> {
+        let current = self.entry.make_manifest()?;
+        match self.snapshot.difference(&current) {
+            Some(reason) => Err(err::Error::ManifestChanged(reason)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::rust,
+        run::{
+            testing::{capped_builder, every, nop_entry},
+            Runner,
+        },
+    };
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    /// An entry whose manifest gains an auxiliary flag once its test body
+    /// has run `after` times, as if the body wrote over the manifest.
+    #[derive(Clone)]
+    struct Scribbling {
+        inner: rust::Static,
+        runs: Arc<AtomicUsize>,
+        after: usize,
+    }
+
+    impl abs::Entry<'static> for Scribbling {
+        type Env = rust::Env;
+
+        fn make_manifest(&self) -> err::Result<model::Manifest> {
+            let mut manifest = self.inner.make_manifest()?;
+            if self.after <= self.runs.load(Ordering::Relaxed) {
+                manifest.aux_flags.push("scribbled".to_owned());
+            }
+            Ok(manifest)
+        }
+
+        fn run(&self, tid: usize, e: &Self::Env) {
+            self.runs.fetch_add(1, Ordering::Relaxed);
+            self.inner.run(tid, e);
+        }
+
+        fn checker(&self) -> Box<dyn abs::Checker<Self::Env>> {
+            self.inner.checker()
+        }
+
+        fn has_checker(&self) -> bool {
+            self.inner.has_checker()
+        }
+    }
+
+    /// Tests that verifying the manifest catches a test changing it, but
+    /// passes tests that leave it alone.
+    #[test]
+    fn test_with_verify_manifest() {
+        let run = |after| {
+            let entry = Scribbling {
+                inner: nop_entry(2),
+                runs: Arc::default(),
+                after,
+            };
+            capped_builder(entry, 20)
+                .with_verify_manifest(true)
+                .add_halt_rules(std::iter::once(every(5).rotate()))
+                .build()
+                .and_then(Runner::run)
+        };
+
+        let report = run(usize::MAX).expect("unchanged manifest should pass");
+        assert_eq!(report.iterations(), 20);
+
+        // Each iteration runs the body once per thread.
+        let e = run(12).expect_err("changed manifest should fail");
+        assert!(
+            matches!(&e, err::Error::ManifestChanged(reason) if reason == "auxiliary flags"),
+            "unexpected error: {e}"
+        );
+
+        // A change in the last rotation still fails, but keeps the report.
+        let e = run(32).expect_err("changed manifest should fail");
+        let (cause, report) = e.into_partial();
+        assert!(matches!(cause, err::Error::ManifestChanged(_)));
+        assert_eq!(report.expect("should have partial report").iterations(), 20);
+    }
+}
@@ -1,4 +1,10 @@
 //! Top-level module for the phenolphalein 'user experience'.
 pub mod clap;
+pub mod curve;
+pub mod diag;
+pub mod discover;
 pub mod err;
+pub mod log;
 pub mod out;
+pub mod serve;
+pub mod watch;
@@ -1,9 +1,47 @@
 //! `clap` integration for config.
 
-use std::{num::NonZeroUsize, path, str::FromStr};
+use std::{
+    num::{NonZeroU64, NonZeroUsize},
+    path,
+    str::FromStr,
+    time::Duration,
+};
 
-use super::{err, out};
-use crate::config::{self, check, io, iter, permute, sync, Config};
+use super::{curve, diag, discover, err, log, out, serve};
+use crate::{
+    config::{self, check, io, iter, limit, permute, sync, units, Config},
+    err::Coded,
+    model::conformance,
+    run,
+};
+
+/// Clap names for the various subcommands.
+pub mod cmd {
+    /// Name of the `run` subcommand.
+    pub const RUN: &str = "run";
+    /// Name of the `config` subcommand.
+    pub const CONFIG: &str = "config";
+    /// Name of the `config dump` subcommand.
+    pub const DUMP: &str = "dump";
+    /// Name of the `config path` subcommand.
+    pub const PATH: &str = "path";
+    /// Name of the `bench` subcommand.
+    pub const BENCH: &str = "bench";
+    /// Name of the `selftest` subcommand.
+    pub const SELFTEST: &str = "selftest";
+    /// Name of the `sweep` subcommand.
+    pub const SWEEP: &str = "sweep";
+    /// Name of the `diff` subcommand.
+    pub const DIFF: &str = "diff";
+    /// Name of the `inspect` subcommand.
+    pub const INSPECT: &str = "inspect";
+    /// Name of the `merge` subcommand.
+    pub const MERGE: &str = "merge";
+    /// Name of the `recover` subcommand.
+    pub const RECOVER: &str = "recover";
+    /// Name of the `header` subcommand.
+    pub const HEADER: &str = "header";
+}
 
 /// Clap names for various arguments.
 pub mod arg {
@@ -12,26 +50,156 @@ pub mod arg {
 
     /// Name of the output type file argument.
     pub const OUTPUT_TYPE: &str = "input-type";
+    /// Name of the output target argument.
+    pub const OUTPUT: &str = "output";
+    /// Name of the histogram percentage column argument.
+    pub const PERCENT: &str = "percent";
+    /// Name of the histogram cumulative percentage column argument.
+    pub const CUMULATIVE: &str = "cumulative";
+    /// Name of the state key order argument.
+    pub const KEY_ORDER: &str = "key-order";
 
-    /// Name of the dump-config argument.
-    pub const DUMP_CONFIG: &str = "dump-config";
-    /// Name of the dump-config-path argument.
-    pub const DUMP_CONFIG_PATH: &str = "dump-config-path";
     /// Name of the config argument.
     pub const CONFIG: &str = "config";
+    /// Name of the error format argument.
+    pub const ERROR_FORMAT: &str = "error-format";
+    /// Name of the profile argument.
+    pub const PROFILE: &str = "profile";
+    /// Name of the log level argument.
+    pub const LOG_LEVEL: &str = "log-level";
+    /// Name of the log format argument.
+    pub const LOG_FORMAT: &str = "log-format";
+    /// Name of the verbose load argument.
+    pub const VERBOSE_LOAD: &str = "verbose-load";
     /// Name of the `check` argument.
     pub const CHECK: &str = "check";
     /// Name of the `permute` argument.
     pub const PERMUTE: &str = "permute";
+    /// Name of the `replay` argument.
+    pub const REPLAY: &str = "replay";
     /// Name of the `sync` argument.
     pub const SYNC: &str = "sync";
+    /// Name of the `observer-seed` argument.
+    pub const OBSERVER_SEED: &str = "observer-seed";
+    /// Name of the `seed` argument.
+    pub const SEED: &str = "seed";
+    /// Name of the `alternate-sync` argument.
+    pub const ALTERNATE_SYNC: &str = "alternate-sync";
+    /// Name of the `alternate-every` argument.
+    pub const ALTERNATE_EVERY: &str = "alternate-every";
+    /// Name of the `check-description` argument.
+    pub const CHECK_DESCRIPTION: &str = "check-description";
+    /// Name of the `expect-outcome` argument.
+    pub const EXPECT_OUTCOME: &str = "expect-outcome";
+    /// Name of the `check-frequency` argument.
+    pub const CHECK_FREQUENCY: &str = "check-frequency";
 
     /// Name of the `iterations` argument.
     pub const ITERATIONS: &str = "iterations";
     /// Name of the `period` argument.
     pub const PERIOD: &str = "period";
+    /// Name of the `period-ms` argument.
+    pub const PERIOD_MS: &str = "period-ms";
+    /// Name of the `warmup` argument.
+    pub const WARMUP: &str = "warmup";
+    /// Name of the `min-rate` argument.
+    pub const MIN_RATE: &str = "min-rate";
+    /// Name of the `rate-window-ms` argument.
+    pub const RATE_WINDOW_MS: &str = "rate-window-ms";
+    /// Name of the `time-every` argument.
+    pub const TIME_EVERY: &str = "time-every";
+    /// Name of the `pause-every` argument.
+    pub const PAUSE_EVERY: &str = "pause-every";
+    /// Name of the `pause-ms` argument.
+    pub const PAUSE_MS: &str = "pause-ms";
+    /// Name of the `sample-every` argument.
+    pub const SAMPLE_EVERY: &str = "sample-every";
+    /// Name of the `sample-probability` argument.
+    pub const SAMPLE_PROBABILITY: &str = "sample-probability";
+    /// Name of the `sample-seed` argument.
+    pub const SAMPLE_SEED: &str = "sample-seed";
+    /// Name of the `canary` argument.
+    pub const CANARY: &str = "canary";
+    /// Name of the `examples` argument.
+    pub const EXAMPLES: &str = "examples";
+    /// Name of the `transitions` argument.
+    pub const TRANSITIONS: &str = "transitions";
+    /// Name of the `segment-rotations` argument.
+    pub const SEGMENT_ROTATIONS: &str = "segment-rotations";
+    /// Name of the `audit-allocations` argument.
+    pub const AUDIT_ALLOCATIONS: &str = "audit-allocations";
+    /// Name of the `markers` argument.
+    pub const MARKERS: &str = "markers";
+    /// Name of the `verify-manifest` argument.
+    pub const VERIFY_MANIFEST: &str = "verify-manifest";
+    /// Name of the `fold-symmetric` argument.
+    pub const FOLD_SYMMETRIC: &str = "fold-symmetric";
+    /// Name of the `exit-on-interesting` argument.
+    pub const EXIT_ON_INTERESTING: &str = "exit-on-interesting";
+    /// Name of the `omit-platform` argument.
+    pub const OMIT_PLATFORM: &str = "omit-platform";
+    /// Name of the `shrink` argument.
+    pub const SHRINK: &str = "shrink";
+    /// Name of the `checkpoint-ms` argument.
+    pub const CHECKPOINT_MS: &str = "checkpoint-ms";
+    /// Name of the `checkpoint-dir` argument.
+    pub const CHECKPOINT_DIR: &str = "checkpoint-dir";
+    /// Name of the `guard` argument.
+    pub const GUARD: &str = "guard";
+    /// Name of the `reset` argument.
+    pub const RESET: &str = "reset";
+    /// Name of the `sequential` argument.
+    pub const SEQUENTIAL: &str = "sequential";
+    /// Name of the `limit-cpu-secs` argument.
+    pub const LIMIT_CPU_SECS: &str = "limit-cpu-secs";
+    /// Name of the `limit-memory-bytes` argument.
+    pub const LIMIT_MEMORY_BYTES: &str = "limit-memory-bytes";
+    /// Name of the `mask` argument.
+    pub const MASK: &str = "mask";
+    /// Name of the `threads` argument.
+    pub const THREADS: &str = "threads";
+    /// Name of the `cpus` argument.
+    pub const CPUS: &str = "cpus";
+    /// Name of the `watch` argument.
+    pub const WATCH: &str = "watch";
+    /// Name of the `discovery-log` argument.
+    pub const DISCOVERY_LOG: &str = "discovery-log";
+    /// Name of the `discovery-every` argument.
+    pub const DISCOVERY_EVERY: &str = "discovery-every";
+    /// Name of the `expect` argument.
+    pub const EXPECT: &str = "expect";
+    /// Name of the `serve` argument.
+    pub const SERVE: &str = "serve";
+    /// Name of the `serve-every` argument.
+    pub const SERVE_EVERY: &str = "serve-every";
+
+    /// Name of the `bench` subcommand's `threads` argument.
+    pub const BENCH_THREADS: &str = "threads";
+    /// Name of the `bench` subcommand's `duration` argument.
+    pub const BENCH_DURATION: &str = "duration";
+
+    /// Name of the `selftest` subcommand's `iterations` argument.
+    pub const SELFTEST_ITERATIONS: &str = "iterations";
+
+    /// Name of the `sweep` subcommand's `profiles` argument.
+    pub const SWEEP_PROFILES: &str = "profiles";
+    /// Name of the `sweep` subcommand's `total-time` argument.
+    pub const SWEEP_TOTAL_TIME: &str = "total-time";
+    /// Name of the `sweep` subcommand's `slice-ms` argument.
+    pub const SWEEP_SLICE_MS: &str = "slice-ms";
+    /// Name of the `sweep` subcommand's `policy` argument.
+    pub const SWEEP_POLICY: &str = "policy";
 }
 
+/// The default number of threads used when benchmarking synchronisers.
+const DEFAULT_BENCH_THREADS: usize = 2;
+/// The default number of seconds for which each synchroniser is benchmarked.
+const DEFAULT_BENCH_DURATION: u64 = 1;
+/// The default number of iterations for which each self-test runs.
+const DEFAULT_SELFTEST_ITERATIONS: usize = 100_000;
+/// The default number of seconds for which a sweep runs.
+const DEFAULT_SWEEP_TOTAL_TIME: u64 = 60;
+
 /// Gets the config file mentioned on the command line, or the default file if
 /// no such file was named.
 ///
@@ -44,6 +212,81 @@ pub fn config_file(matches: &clap::ArgMatches) -> anyhow::Result<path::PathBuf>
         .map_or_else(|| Ok(io::default_file()), |x| Ok(x.parse()?))
 }
 
+/// Gets the config profile mentioned on the command line, if any.
+#[must_use]
+pub fn profile<'a>(matches: &'a clap::ArgMatches) -> Option<&'a str> {
+    matches.value_of(arg::PROFILE)
+}
+
+/// Gets the error format mentioned on the command line.
+///
+/// As the argument is global, this looks through every level of subcommand
+/// matches, taking the first occurrence.
+///
+/// # Errors
+///
+/// Fails if the error format can't be parsed.
+pub fn error_format(matches: &clap::ArgMatches) -> err::Result<diag::Format> {
+    global_value(matches, arg::ERROR_FORMAT).map_or_else(|| Ok(diag::Format::default()), str::parse)
+}
+
+/// Gets the log level and format requested on the command line, if logging
+/// was requested at all.
+///
+/// # Errors
+///
+/// Fails if the level or format is unknown.
+pub fn log_settings(
+    matches: &clap::ArgMatches,
+) -> err::Result<Option<(tracing::Level, log::Format)>> {
+    let level = global_value(matches, arg::LOG_LEVEL)
+        .map(log::parse_level)
+        .transpose()?;
+    let format = global_value(matches, arg::LOG_FORMAT)
+        .map(str::parse)
+        .transpose()?
+        .unwrap_or_default();
+    Ok(level.map(|l| (l, format)))
+}
+
+/// Gets whether the command line asked for probe results on loading test
+/// libraries.
+#[must_use]
+pub fn verbose_load(matches: &clap::ArgMatches) -> bool {
+    let mut level = Some(matches);
+    while let Some(m) = level {
+        if m.is_present(arg::VERBOSE_LOAD) {
+            return true;
+        }
+        level = m.subcommand().1;
+    }
+    false
+}
+
+/// Gets the value of global argument `name` from the outermost level of
+/// subcommand giving it.
+fn global_value<'a>(matches: &'a clap::ArgMatches, name: &str) -> Option<&'a str> {
+    let mut level = Some(matches);
+    while let Some(m) = level {
+        if let Some(s) = m.value_of(name) {
+            return Some(s);
+        }
+        level = m.subcommand().1;
+    }
+    None
+}
+
+/// Usage errors from clap have a code of their own.
+impl Coded for clap::Error {
+    fn code(&self) -> &'static str {
+        "ux.usage"
+    }
+
+    fn context(&self) -> Vec<(&'static str, String)> {
+        vec![("kind", format!("{:?}", self.kind))]
+    }
+}
+
 /// Trait for things that can be updated from command line arguments taken from
 /// `clap`.
 pub trait Clappable: Sized {
@@ -61,9 +304,85 @@ impl Clappable for Config {
     fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
         Ok(Self {
             check: self.check.parse_clap(matches)?,
+            check_mode: self.check_mode,
+            check_frequency: parse_or(
+                matches.value_of(arg::CHECK_FREQUENCY),
+                self.check_frequency,
+            )?,
+            check_description: matches
+                .value_of(arg::CHECK_DESCRIPTION)
+                .map(ToOwned::to_owned)
+                .or(self.check_description),
+            expect_outcome: match matches.value_of(arg::EXPECT_OUTCOME) {
+                Some(s) => Some(s.parse().map_err(config::Error::BadExpectOutcome)?),
+                None => self.expect_outcome,
+            },
+            threads: parse_threads(matches.value_of(arg::THREADS), self.threads)?,
+            cpus: match matches.value_of(arg::CPUS) {
+                Some(s) => Some(s.parse().map_err(config::Error::BadCpus)?),
+                None => self.cpus,
+            },
+            canary: self.canary || matches.is_present(arg::CANARY),
+            examples: self.examples || matches.is_present(arg::EXAMPLES),
+            transitions: self.transitions || matches.is_present(arg::TRANSITIONS),
+            segment_rotations: self.segment_rotations || matches.is_present(arg::SEGMENT_ROTATIONS),
+            audit_allocations: self.audit_allocations || matches.is_present(arg::AUDIT_ALLOCATIONS),
+            markers: self.markers || matches.is_present(arg::MARKERS),
+            verify_manifest: self.verify_manifest || matches.is_present(arg::VERIFY_MANIFEST),
+            fold_symmetric: self.fold_symmetric || matches.is_present(arg::FOLD_SYMMETRIC),
+            exit_on_interesting: self.exit_on_interesting
+                || matches.is_present(arg::EXIT_ON_INTERESTING),
+            omit_platform: self.omit_platform || matches.is_present(arg::OMIT_PLATFORM),
+            shrink: self.shrink || matches.is_present(arg::SHRINK),
+            checkpoint_ms: match matches.value_of(arg::CHECKPOINT_MS) {
+                Some(s) => {
+                    NonZeroU64::new(units::millis(s).map_err(config::Error::BadCheckpointInterval)?)
+                }
+                None => self.checkpoint_ms,
+            },
+            checkpoint_dir: matches
+                .value_of(arg::CHECKPOINT_DIR)
+                .map(path::PathBuf::from)
+                .or(self.checkpoint_dir),
+            guard: parse_or(matches.value_of(arg::GUARD), self.guard)?,
+            reset: parse_or(matches.value_of(arg::RESET), self.reset)?,
+            sequential: parse_or(matches.value_of(arg::SEQUENTIAL), self.sequential)?,
+            mask: self
+                .mask
+                .into_iter()
+                .chain(
+                    matches
+                        .values_of(arg::MASK)
+                        .into_iter()
+                        .flatten()
+                        .map(ToOwned::to_owned),
+                )
+                .collect(),
+            key_order: self.key_order,
             iter: self.iter.parse_clap(matches)?,
             sync: self.sync.parse_clap(matches)?,
+            spinner: self.spinner,
+            backoff: self.backoff,
+            alternate_sync: parse_alternate_sync(matches, self.alternate_sync)?,
+            limits: self.limits.parse_clap(matches)?,
+            observer_seed: match matches.value_of(arg::OBSERVER_SEED) {
+                Some(s) => Some(s.parse().map_err(config::Error::BadObserverSeed)?),
+                None => self.observer_seed,
+            },
+            seed: match matches.value_of(arg::SEED) {
+                Some(s) => Some(s.parse().map_err(config::Error::BadSeed)?),
+                None => self.seed,
+            },
             permute: self.permute.parse_clap(matches)?,
+            fuzz: self.fuzz,
+            format: self.format,
+            interesting: self.interesting,
+            forbidden: self.forbidden,
+            aggregate: self.aggregate,
+            projections: self.projections,
+            skew: self.skew,
+            priority: self.priority,
+            inline: self.inline,
         })
     }
 }
@@ -74,23 +393,122 @@ impl Clappable for check::Strategy {
     }
 }
 
+/// We can fill an iteration config using clap.
+impl Clappable for iter::Config {
+    fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
+        let period_ms = millis_or_else(matches.value_of(arg::PERIOD_MS), || {
+            self.period_ms.map_or(0, NonZeroU64::get)
+        })
+        .map_err(config::Error::BadPeriodDuration)?;
+        let warmup = count_or_else(matches.value_of(arg::WARMUP), || as_usize(self.warmup))
+            .map_err(config::Error::BadWarmup)?;
+        let min_rate = matches
+            .value_of(arg::MIN_RATE)
+            .map(str::parse)
+            .transpose()
+            .map_err(config::Error::BadMinRate)?
+            .or(self.min_rate);
+        let rate_window_ms = millis_or_else(matches.value_of(arg::RATE_WINDOW_MS), || {
+            self.rate_window_ms.map_or(0, NonZeroU64::get)
+        })
+        .map_err(config::Error::BadRateWindow)?;
+        let time_every = count_or_else(matches.value_of(arg::TIME_EVERY), || {
+            as_usize(self.time_every)
+        })
+        .map_err(config::Error::BadTimeEvery)?;
+        let pause_every = count_or_else(matches.value_of(arg::PAUSE_EVERY), || {
+            as_usize(self.pause_every)
+        })
+        .map_err(config::Error::BadPauseEvery)?;
+        let pause_ms = millis_or_else(matches.value_of(arg::PAUSE_MS), || {
+            self.pause_ms.map_or(0, NonZeroU64::get)
+        })
+        .map_err(config::Error::BadPauseLength)?;
+        let sample_every = count_or_else(matches.value_of(arg::SAMPLE_EVERY), || {
+            as_usize(self.sample_every)
+        })
+        .map_err(config::Error::BadSampleEvery)?;
+        let sample_probability = matches
+            .value_of(arg::SAMPLE_PROBABILITY)
+            .map(parse_probability)
+            .transpose()?
+            .or(self.sample_probability);
+        let sample_seed = matches
+            .value_of(arg::SAMPLE_SEED)
+            .map(str::parse)
+            .transpose()
+            .map_err(config::Error::BadSampleSeed)?
+            .or(self.sample_seed);
+        Ok(iter::Config {
+            strategy: self.strategy.parse_clap(matches)?,
+            period_ms: NonZeroU64::new(period_ms),
+            warmup: NonZeroUsize::new(warmup),
+            min_rate,
+            rate_window_ms: NonZeroU64::new(rate_window_ms),
+            time_every: NonZeroUsize::new(time_every),
+            pause_every: NonZeroUsize::new(pause_every),
+            pause_ms: NonZeroU64::new(pause_ms),
+            sample_every: NonZeroUsize::new(sample_every),
+            sample_probability,
+            sample_seed,
+        })
+    }
+}
+
+/// Parses a sampling probability, which must be above 0 and at most 1.
+fn parse_probability(s: &str) -> err::Result<f64> {
+    let bad = |reason: String| config::Error::BadSampleProbability(reason).into();
+    let p: f64 = s
+        .parse()
+        .map_err(|e: std::num::ParseFloatError| bad(e.to_string()))?;
+    if 0.0 < p && p <= 1.0 {
+        Ok(p)
+    } else {
+        Err(bad(format!("{p} is not above 0 and at most 1")))
+    }
+}
+
 /// We can fill an iteration strategy using clap.
 impl Clappable for iter::Strategy {
     fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
-        let iterations = parse_or_else(matches.value_of(arg::ITERATIONS), || {
+        let iterations = count_or_else(matches.value_of(arg::ITERATIONS), || {
             as_usize(self.iterations())
         })
         .map_err(config::Error::BadIterationCount)?;
-        let period = parse_or_else(matches.value_of(arg::PERIOD), || as_usize(self.period()))
+        // A period left over from the config that the new iteration count
+        // undercuts would never rotate, so we drop it rather than reject it.
+        let inherited = self.period().filter(|&p| {
+            !matches.is_present(arg::ITERATIONS) || p.get() < iterations || iterations == 0
+        });
+        let period = count_or_else(matches.value_of(arg::PERIOD), || as_usize(inherited))
             .map_err(config::Error::BadPeriod)?;
 
         Ok(iter::Strategy::from_ints(iterations, period))
     }
 }
 
+/// We can fill resource limits using clap; a limit of zero means no limit.
+impl Clappable for limit::Limits {
+    fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
+        Ok(Self {
+            cpu_secs: match matches.value_of(arg::LIMIT_CPU_SECS) {
+                Some(s) => NonZeroU64::new(units::secs(s).map_err(config::Error::BadCpuLimit)?),
+                None => self.cpu_secs,
+            },
+            memory_bytes: match matches.value_of(arg::LIMIT_MEMORY_BYTES) {
+                Some(s) => NonZeroU64::new(units::bytes(s).map_err(config::Error::BadMemoryLimit)?),
+                None => self.memory_bytes,
+            },
+        })
+    }
+}
+
 /// We can fill a thread permutation strategy using clap.
 impl Clappable for permute::Strategy {
     fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
+        if let Some(path) = matches.value_of(arg::REPLAY) {
+            return Ok(Self::Replay(path.into()));
+        }
         Ok(parse_or(matches.value_of(arg::PERMUTE), self)?)
     }
 }
@@ -102,6 +520,27 @@ impl Clappable for sync::Strategy {
     }
 }
 
+/// Fills in a request to alternate synchronisers using clap.
+///
+/// `--alternate-sync` replaces the strategy of any alternation in the config,
+/// keeping its period unless `--alternate-every` gives a new one.
+fn parse_alternate_sync(
+    matches: &clap::ArgMatches,
+    alternate: Option<sync::Alternate>,
+) -> err::Result<Option<sync::Alternate>> {
+    let Some(with) = matches.value_of(arg::ALTERNATE_SYNC) else {
+        return Ok(alternate);
+    };
+    let every = match matches.value_of(arg::ALTERNATE_EVERY) {
+        Some(s) => units::nonzero_count(s).map_err(config::Error::BadAlternateEvery)?,
+        None => alternate.map_or(NonZeroUsize::MIN, |a| a.every),
+    };
+    Ok(Some(sync::Alternate {
+        with: with.parse()?,
+        every,
+    }))
+}
+
 /// We can fill an output choice using clap.
 impl Clappable for out::Choice {
     fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
@@ -110,11 +549,44 @@ impl Clappable for out::Choice {
 }
 
 /// We can fill an output config using clap.
+///
+/// Any `--output` arguments replace the existing targets; otherwise,
+/// `--output-type` replaces them with a single stdout target.  The histogram
+/// column flags add to any columns already on, and `--key-order` replaces
+/// any key order.
 impl Clappable for out::Config {
-    fn parse_clap(mut self, matches: &clap::ArgMatches) -> err::Result<Self> {
-        self.choice = self.choice.parse_clap(matches)?;
-        // TODO(@MattWindsor91): outputs other than stdout
-        Ok(self)
+    fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
+        let columns = out::histo::Columns {
+            percent: self.columns.percent || matches.is_present(arg::PERCENT),
+            cumulative: self.columns.cumulative || matches.is_present(arg::CUMULATIVE),
+        };
+        let key_order = match matches.value_of(arg::KEY_ORDER) {
+            Some(s) => Some(s.parse().map_err(config::Error::BadKeyOrder)?),
+            None => self.key_order,
+        };
+        let config = out::Config {
+            key_order,
+            ..self.with_columns(columns)
+        };
+        parse_targets(config, matches)
+    }
+}
+
+/// Fills the targets of the output config `config` using clap.
+fn parse_targets(config: out::Config, matches: &clap::ArgMatches) -> err::Result<out::Config> {
+    if let Some(specs) = matches.values_of(arg::OUTPUT) {
+        let targets = specs
+            .map(out::Target::from_spec)
+            .collect::<out::err::Result<_>>()?;
+        Ok(out::Config { targets, ..config })
+    } else if matches.is_present(arg::OUTPUT_TYPE) {
+        let choice = out::Choice::default().parse_clap(matches)?;
+        Ok(out::Config {
+            targets: vec![out::Target::stdout(choice)],
+            ..config
+        })
+    } else {
+        Ok(config)
     }
 }
 
@@ -138,25 +610,379 @@ fn parse_or_else<T: FromStr>(
     int_str.map_or_else(|| Ok(default()), str::parse)
 }
 
-/// Actions that can be specified on the command line.
+/// Parses a count override, falling back to `default`.
+fn count_or_else(
+    s: Option<&str>,
+    default: impl FnOnce() -> usize,
+) -> std::result::Result<usize, units::Error> {
+    s.map_or_else(|| Ok(default()), units::count)
+}
+
+/// Parses a millisecond duration override, falling back to `default`.
+fn millis_or_else(
+    s: Option<&str>,
+    default: impl FnOnce() -> u64,
+) -> std::result::Result<u64, units::Error> {
+    s.map_or_else(|| Ok(default()), units::millis)
+}
+
+/// Parses a duration in which a bare number means seconds.
+fn secs(s: &str) -> std::result::Result<Duration, units::Error> {
+    units::duration(s, Duration::from_secs(1))
+}
+
+/// Parses a thread count override, falling back to `default`.
+fn parse_threads(
+    s: Option<&str>,
+    default: Option<NonZeroUsize>,
+) -> err::Result<Option<NonZeroUsize>> {
+    let threads = parse_or_else(s, || as_usize(default)).map_err(config::Error::BadThreadCount)?;
+    Ok(NonZeroUsize::new(threads))
+}
+
+/// Actions that can be specified on the command line, one per subcommand.
 pub enum Action {
-    /// Asks to run the test with a given path.
-    RunTest(path::PathBuf, out::Config),
-    /// Asks to dump the config.
-    DumpConfig,
-    /// Asks to dump the path to the config.
-    DumpConfigPath,
-}
-
-impl Clappable for Action {
-    fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
-        Ok(if matches.is_present(arg::DUMP_CONFIG) {
-            Self::DumpConfig
-        } else if matches.is_present(arg::DUMP_CONFIG_PATH) {
-            Self::DumpConfigPath
-        } else {
-            let input = matches.value_of(arg::INPUT).ok_or(err::Error::NoInput)?;
-            Self::RunTest(input.parse()?, clap_or_default(matches)?)
+    /// Asks to run a test (`run`).
+    Run(Run),
+    /// Asks to do something with the config (`config`).
+    Config(ConfigAction),
+    /// Asks to benchmark each synchroniser (`bench`).
+    Bench(Bench),
+    /// Asks to run the built-in litmus tests (`selftest`).
+    Selftest(Selftest),
+    /// Asks to sweep a test across profiles under a time budget (`sweep`).
+    Sweep(Sweep),
+    /// Asks to run two builds of a test alongside each other (`diff`).
+    Diff(Diff),
+    /// Asks to describe a test without running it (`inspect`).
+    Inspect(Inspect),
+    /// Asks to merge reports from several runs (`merge`).
+    Merge(Merge),
+    /// Asks to output the checkpoint left by a run that died (`recover`).
+    Recover(Inspect),
+    /// Asks to print the C ABI header (`header`).
+    Header,
+}
+
+impl Action {
+    /// Parses an action from the top-level clap matches.
+    ///
+    /// Returns the action alongside the matches for its innermost subcommand,
+    /// from which the config should be parsed.
+    ///
+    /// # Errors
+    ///
+    /// Fails if no subcommand was given, or the subcommand's arguments are
+    /// malformed.
+    pub fn from_clap<'a>(
+        matches: &'a clap::ArgMatches<'a>,
+    ) -> err::Result<(Self, &'a clap::ArgMatches<'a>)> {
+        match matches.subcommand() {
+            (cmd::RUN, Some(sub)) => Ok((Self::Run(clap_or_default(sub)?), sub)),
+            (cmd::CONFIG, Some(sub)) => match sub.subcommand() {
+                (cmd::DUMP, Some(sub)) => Ok((Self::Config(ConfigAction::Dump), sub)),
+                (cmd::PATH, Some(sub)) => Ok((Self::Config(ConfigAction::Path), sub)),
+                _ => Err(err::Error::NoAction),
+            },
+            (cmd::BENCH, Some(sub)) => Ok((Self::Bench(clap_or_default(sub)?), sub)),
+            (cmd::SELFTEST, Some(sub)) => Ok((Self::Selftest(clap_or_default(sub)?), sub)),
+            (cmd::SWEEP, Some(sub)) => Ok((Self::Sweep(clap_or_default(sub)?), sub)),
+            (cmd::DIFF, Some(sub)) => Ok((Self::Diff(clap_or_default(sub)?), sub)),
+            (cmd::INSPECT, Some(sub)) => Ok((Self::Inspect(clap_or_default(sub)?), sub)),
+            (cmd::MERGE, Some(sub)) => Ok((Self::Merge(clap_or_default(sub)?), sub)),
+            (cmd::RECOVER, Some(sub)) => Ok((Self::Recover(clap_or_default(sub)?), sub)),
+            (cmd::HEADER, Some(sub)) => Ok((Self::Header, sub)),
+            _ => Err(err::Error::NoAction),
+        }
+    }
+}
+
+/// Arguments to the `run` subcommand.
+#[derive(Default)]
+pub struct Run {
+    /// The paths to the tests to run, after expanding directories and
+    /// patterns.
+    pub inputs: Vec<path::PathBuf>,
+    /// Whether the inputs form a suite, rather than a single test.
+    ///
+    /// This is true if there were several inputs, or any input was a
+    /// directory or pattern, even if only one test resulted.
+    pub suite: bool,
+    /// The output configuration.
+    pub output: out::Config,
+    /// The log, if any, into which to sample discovery curves while running.
+    pub discovery_log: Option<curve::Log>,
+    /// Whether to run the inputs afresh whenever they change.
+    pub watch: bool,
+    /// The states that a model allows, if any, against which to compare the
+    /// observed states.
+    pub expected: Option<conformance::Expected>,
+    /// The server, if any, to which to stream progress and reports.
+    pub serve: Option<serve::Server>,
+}
+
+impl Clappable for Run {
+    fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
+        let raw: Vec<&str> = matches
+            .values_of(arg::INPUT)
+            .ok_or(err::Error::NoInput)?
+            .collect();
+        let mut inputs = vec![];
+        let mut suite = 1 < raw.len();
+        for input in raw {
+            let (tests, many) = discover::expand(path::Path::new(input))?;
+            inputs.extend(tests);
+            suite |= many;
+        }
+        Ok(Self {
+            inputs,
+            suite,
+            output: self.output.parse_clap(matches)?,
+            discovery_log: parse_discovery_log(matches)?.or(self.discovery_log),
+            watch: self.watch || matches.is_present(arg::WATCH),
+            expected: parse_expected(matches)?.or(self.expected),
+            serve: parse_serve(matches)?.or(self.serve),
+        })
+    }
+}
+
+/// Starts the server requested in `matches`, if any.
+fn parse_serve(matches: &clap::ArgMatches) -> err::Result<Option<serve::Server>> {
+    let Some(address) = matches.value_of(arg::SERVE) else {
+        return Ok(None);
+    };
+    let address: serve::Address = address.parse().map_err(err::Error::BadServeAddress)?;
+    let every = matches
+        .value_of(arg::SERVE_EVERY)
+        .map_or(Ok(serve::DEFAULT_EVERY), units::nonzero_count)
+        .map_err(err::Error::BadServeEvery)?;
+    let server = serve::Server::bind(&address, every)
+        .map_err(|e| err::Error::ServeIo(address.to_string(), e))?;
+    Ok(Some(server))
+}
+
+/// Reads the model expectations requested in `matches`, if any.
+fn parse_expected(matches: &clap::ArgMatches) -> err::Result<Option<conformance::Expected>> {
+    let Some(path) = matches.value_of(arg::EXPECT) else {
+        return Ok(None);
+    };
+    let path = path::Path::new(path);
+    let text =
+        std::fs::read_to_string(path).map_err(|e| err::Error::ExpectIo(path.to_owned(), e))?;
+    let expected = text
+        .parse()
+        .map_err(|e| err::Error::BadExpect(path.to_owned(), e))?;
+    Ok(Some(expected))
+}
+
+/// Opens the discovery log requested in `matches`, if any.
+fn parse_discovery_log(matches: &clap::ArgMatches) -> err::Result<Option<curve::Log>> {
+    let Some(path) = matches.value_of(arg::DISCOVERY_LOG) else {
+        return Ok(None);
+    };
+    let every = matches
+        .value_of(arg::DISCOVERY_EVERY)
+        .map_or(Ok(curve::DEFAULT_EVERY), units::nonzero_count)
+        .map_err(err::Error::BadDiscoveryEvery)?;
+    let path = path::Path::new(path);
+    let log = curve::Log::open(path, every)
+        .map_err(|e| err::Error::BadDiscoveryLog(path.to_owned(), e))?;
+    Ok(Some(log))
+}
+
+/// Arguments to the `inspect` and `recover` subcommands, which each take a
+/// single test.
+#[derive(Default)]
+pub struct Inspect {
+    /// The path to the test.
+    pub input: path::PathBuf,
+    /// The output configuration.
+    pub output: out::Config,
+}
+
+impl Clappable for Inspect {
+    fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
+        let input = matches.value_of(arg::INPUT).ok_or(err::Error::NoInput)?;
+        Ok(Self {
+            input: input.parse()?,
+            output: self.output.parse_clap(matches)?,
+        })
+    }
+}
+
+/// Arguments to the `merge` subcommand.
+#[derive(Default)]
+pub struct Merge {
+    /// The paths to the JSON reports to merge.
+    pub inputs: Vec<path::PathBuf>,
+    /// The output configuration.
+    pub output: out::Config,
+}
+
+impl Clappable for Merge {
+    fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
+        let inputs = matches
+            .values_of(arg::INPUT)
+            .ok_or(err::Error::NoInput)?
+            .map(path::PathBuf::from)
+            .collect();
+        Ok(Self {
+            inputs,
+            output: self.output.parse_clap(matches)?,
+        })
+    }
+}
+
+/// Arguments to the `diff` subcommand.
+#[derive(Default)]
+pub struct Diff {
+    /// The path to the first build of the test.
+    pub left: path::PathBuf,
+    /// The path to the second build of the test.
+    pub right: path::PathBuf,
+    /// The output configuration.
+    pub output: out::Config,
+}
+
+impl Clappable for Diff {
+    fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
+        let mut inputs = matches
+            .values_of(arg::INPUT)
+            .ok_or(err::Error::NoInput)?
+            .map(path::PathBuf::from);
+        let (Some(left), Some(right)) = (inputs.next(), inputs.next()) else {
+            return Err(err::Error::NoInput);
+        };
+        Ok(Self {
+            left,
+            right,
+            output: self.output.parse_clap(matches)?,
+        })
+    }
+}
+
+/// Subcommands of the `config` subcommand.
+pub enum ConfigAction {
+    /// Asks to dump the config (`config dump`).
+    Dump,
+    /// Asks to dump the path to the config (`config path`).
+    Path,
+}
+
+/// Arguments to the `bench` subcommand.
+pub struct Bench {
+    /// The number of threads to benchmark with.
+    pub threads: NonZeroUsize,
+    /// The duration for which to benchmark each synchroniser.
+    pub duration: Duration,
+}
+
+/// The default benchmark uses `DEFAULT_BENCH_THREADS` and
+/// `DEFAULT_BENCH_DURATION`.
+impl Default for Bench {
+    fn default() -> Self {
+        Self {
+            threads: NonZeroUsize::new(DEFAULT_BENCH_THREADS).unwrap_or(NonZeroUsize::MIN),
+            duration: Duration::from_secs(DEFAULT_BENCH_DURATION),
+        }
+    }
+}
+
+impl Clappable for Bench {
+    fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
+        let threads = parse_or(matches.value_of(arg::BENCH_THREADS), self.threads)
+            .map_err(err::Error::BadBenchThreads)?;
+        let duration = match matches.value_of(arg::BENCH_DURATION) {
+            Some(s) => secs(s).map_err(err::Error::BadBenchDuration)?,
+            None => self.duration,
+        };
+        Ok(Self { threads, duration })
+    }
+}
+
+/// Arguments to the `selftest` subcommand.
+pub struct Selftest {
+    /// The number of iterations for which to run each built-in test.
+    pub iterations: NonZeroUsize,
+}
+
+/// The default self-test uses `DEFAULT_SELFTEST_ITERATIONS`.
+impl Default for Selftest {
+    fn default() -> Self {
+        Self {
+            iterations: NonZeroUsize::new(DEFAULT_SELFTEST_ITERATIONS).unwrap_or(NonZeroUsize::MIN),
+        }
+    }
+}
+
+impl Clappable for Selftest {
+    fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
+        let iterations = matches
+            .value_of(arg::SELFTEST_ITERATIONS)
+            .map_or(Ok(self.iterations), units::nonzero_count)
+            .map_err(err::Error::BadSelftestIterations)?;
+        Ok(Self { iterations })
+    }
+}
+
+/// Arguments to the `sweep` subcommand.
+pub struct Sweep {
+    /// The path to the test to sweep.
+    pub input: path::PathBuf,
+    /// The profiles across which to sweep; empty means every profile in the
+    /// config file.
+    pub profiles: Vec<String>,
+    /// The total time for which to sweep.
+    pub total_time: Duration,
+    /// The length of each slice, if not the default fraction of the total.
+    pub slice: Option<Duration>,
+    /// The policy by which to allocate slices to profiles.
+    pub policy: run::sweep::Strategy,
+    /// The output configuration.
+    pub output: out::Config,
+}
+
+/// The default sweep lasts `DEFAULT_SWEEP_TOTAL_TIME` seconds.
+impl Default for Sweep {
+    fn default() -> Self {
+        Self {
+            input: path::PathBuf::default(),
+            profiles: vec![],
+            total_time: Duration::from_secs(DEFAULT_SWEEP_TOTAL_TIME),
+            slice: None,
+            policy: run::sweep::Strategy::default(),
+            output: out::Config::default(),
+        }
+    }
+}
+
+impl Clappable for Sweep {
+    fn parse_clap(self, matches: &clap::ArgMatches) -> err::Result<Self> {
+        let input = matches.value_of(arg::INPUT).ok_or(err::Error::NoInput)?;
+        let profiles = matches
+            .values_of(arg::SWEEP_PROFILES)
+            .map_or(self.profiles, |ps| ps.map(str::to_owned).collect());
+        let total_time = match matches.value_of(arg::SWEEP_TOTAL_TIME) {
+            Some(s) => secs(s).map_err(err::Error::BadSweepTotalTime)?,
+            None => self.total_time,
+        };
+        let slice = match matches.value_of(arg::SWEEP_SLICE_MS) {
+            Some(s) => Some(
+                units::duration(s, Duration::from_millis(1)).map_err(err::Error::BadSweepSlice)?,
+            ),
+            None => self.slice,
+        };
+        let policy = match matches.value_of(arg::SWEEP_POLICY) {
+            Some(s) => s.parse().map_err(err::Error::BadSweepPolicy)?,
+            None => self.policy,
+        };
+        Ok(Self {
+            input: input.parse()?,
+            profiles,
+            total_time,
+            slice,
+            policy,
+            output: self.output.parse_clap(matches)?,
         })
     }
 }
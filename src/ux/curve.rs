@@ -0,0 +1,144 @@
+//! Periodic dumps of distinct-state discovery curves.
+//!
+//! Reports carry the exact discovery curve of each test, but only once the
+//! test finishes; a [Log] instead samples the curve while tests run, as
+//! tab-separated `test`, `iteration`, and `states` columns suitable for
+//! plotting.
+
+use crate::run::obs;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    num::NonZeroUsize,
+    path::Path,
+    sync::Mutex,
+};
+
+/// The default number of iterations between samples.
+pub const DEFAULT_EVERY: NonZeroUsize = NonZeroUsize::new(10_000).unwrap();
+
+/// A log that samples distinct-state counts every so many iterations.
+pub struct Log {
+    every: NonZeroUsize,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    w: Box<dyn Write + Send>,
+    /// The first write error, if any; sampling stops once this is set.
+    error: Option<io::Error>,
+}
+
+impl Log {
+    /// Constructs a log that writes a sample to `w` every `every` iterations.
+    ///
+    /// # Errors
+    ///
+    /// Fails if we can't write the column header.
+    pub fn new(mut w: impl Write + Send + 'static, every: NonZeroUsize) -> io::Result<Self> {
+        writeln!(w, "test\titeration\tstates")?;
+        Ok(Self {
+            every,
+            inner: Mutex::new(Inner {
+                w: Box::new(w),
+                error: None,
+            }),
+        })
+    }
+
+    /// Opens a log writing to the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if we can't create the file or write its header.
+    pub fn open(path: &Path, every: NonZeroUsize) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), every)
+    }
+
+    /// Records a sample for the test named `test`, if `summary` falls on a
+    /// sampling iteration.
+    ///
+    /// Write errors are held until [`Log::finish`].
+    pub fn record(&self, test: &str, summary: &obs::Summary) {
+        if !summary.iterations.is_multiple_of(self.every.get()) {
+            return;
+        }
+        if let Ok(mut inner) = self.inner.lock() {
+            if inner.error.is_some() {
+                return;
+            }
+            if let Err(e) = writeln!(
+                inner.w,
+                "{test}\t{}\t{}",
+                summary.iterations, summary.distinct_states
+            ) {
+                inner.error = Some(e);
+            }
+        }
+    }
+
+    /// Flushes the log, reporting the first error that occurred while
+    /// writing it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any write to the log failed.
+    pub fn finish(&self) -> io::Result<()> {
+        let mut inner = self
+            .inner
+            .lock()
+            .map_err(|_| io::Error::other("discovery log poisoned"))?;
+        if let Some(e) = inner.error.take() {
+            return Err(e);
+        }
+        inner.w.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{state::Info, Outcome};
+    use std::{sync::Arc, time::Duration};
+
+    /// A writer that appends to a shared buffer.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn summary(iterations: usize, distinct_states: usize) -> obs::Summary {
+        obs::Summary {
+            iterations,
+            rotation_iterations: iterations,
+            rotation_elapsed: Duration::ZERO,
+            distinct_states,
+            interesting_left: 0,
+            rate: None,
+            info: Some(Info::new(Outcome::Pass, 0, 0)),
+        }
+    }
+
+    /// Tests that the log only samples every `every` iterations.
+    #[test]
+    fn test_record() -> io::Result<()> {
+        let buf = Shared::default();
+        let log = Log::new(buf.clone(), NonZeroUsize::new(2).unwrap())?;
+        for i in 1..=5 {
+            log.record("sb", &summary(i, i.min(3)));
+        }
+        log.finish()?;
+
+        let got = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(got, "test\titeration\tstates\nsb\t2\t2\nsb\t4\t3\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+//! Reporting errors to the user, either as text or as structured JSON.
+
+use super::{err, out};
+use crate::{config, err::Coded};
+use serde::Serialize;
+use std::{collections::BTreeMap, io::Write, str::FromStr};
+
+/// Strings used when mapping error formats to command-line arguments.
+pub mod string {
+    /// The string representation for human-readable errors.
+    pub const HUMAN: &str = "human";
+    /// The string representation for JSON errors.
+    pub const JSON: &str = "json";
+
+    /// List of all possible string representations of error formats.
+    pub const ALL: &[&str] = &[HUMAN, JSON];
+}
+
+/// The code given to errors that don't carry a code of their own.
+pub const UNKNOWN_CODE: &str = "unknown";
+
+/// Enumeration of formats in which errors can be reported.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Free-form, human-readable text.
+    Human,
+    /// A JSON [Diagnostic].
+    Json,
+}
+
+/// The default format is human-readable.
+impl Default for Format {
+    fn default() -> Self {
+        Self::Human
+    }
+}
+
+impl FromStr for Format {
+    type Err = err::Error;
+
+    fn from_str(s: &str) -> err::Result<Self> {
+        match s {
+            string::HUMAN => Ok(Self::Human),
+            string::JSON => Ok(Self::Json),
+            s => Err(err::Error::BadErrorFormat(s.to_owned())),
+        }
+    }
+}
+
+/// A machine-readable description of an error.
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    /// The stable code of the outermost coded error in the chain.
+    pub code: &'static str,
+    /// The human-readable message of the outermost error.
+    pub message: String,
+    /// The messages of each error that caused this one, outermost first.
+    pub causes: Vec<String>,
+    /// Structured context fields for the coded error.
+    pub context: BTreeMap<&'static str, String>,
+}
+
+impl Diagnostic {
+    /// Builds a diagnostic from an error chain.
+    ///
+    /// The code and context come from the outermost error in the chain whose
+    /// type is known to be [Coded].
+    #[must_use]
+    pub fn of_error(e: &anyhow::Error) -> Self {
+        let coded = e.chain().find_map(as_coded);
+        Self {
+            code: coded.map_or(UNKNOWN_CODE, Coded::code),
+            message: e.to_string(),
+            causes: e.chain().skip(1).map(ToString::to_string).collect(),
+            context: coded
+                .map(Coded::context)
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+fn as_coded<'a>(e: &'a (dyn std::error::Error + 'static)) -> Option<&'a dyn Coded> {
+    if let Some(e) = e.downcast_ref::<crate::err::Error>() {
+        Some(e)
+    } else if let Some(e) = e.downcast_ref::<config::Error>() {
+        Some(e)
+    } else if let Some(e) = e.downcast_ref::<err::Error>() {
+        Some(e)
+    } else if let Some(e) = e.downcast_ref::<out::err::Error>() {
+        Some(e)
+    } else if let Some(e) = e.downcast_ref::<clap::Error>() {
+        Some(e)
+    } else {
+        None
+    }
+}
+
+/// Reports `e` to `w` in the given format.
+///
+/// # Errors
+///
+/// Fails if writing to `w` fails.
+pub fn report(format: Format, e: &anyhow::Error, mut w: impl Write) -> std::io::Result<()> {
+    match format {
+        Format::Human => writeln!(w, "{e:#}"),
+        Format::Json => {
+            serde_json::to_writer(&mut w, &Diagnostic::of_error(e))?;
+            writeln!(w)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that diagnostics find codes beneath context wrappers.
+    #[test]
+    fn test_diagnostic_of_wrapped_error() {
+        let e = anyhow::Error::new(err::Error::Config(config::Error::UnknownProfile(
+            "fast".to_owned(),
+        )))
+        .context("couldn't load config");
+        let d = Diagnostic::of_error(&e);
+        assert_eq!(d.code, "config.unknown-profile");
+        assert_eq!(d.message, "couldn't load config");
+        assert_eq!(d.causes.len(), 2);
+        assert_eq!(d.context.get("profile").map(String::as_str), Some("fast"));
+    }
+
+    /// Tests that uncoded errors get the unknown code.
+    #[test]
+    fn test_diagnostic_of_uncoded_error() {
+        let d = Diagnostic::of_error(&anyhow::anyhow!("oops"));
+        assert_eq!(d.code, UNKNOWN_CODE);
+        assert!(d.context.is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+//! Discovery of test libraries from command-line inputs.
+//!
+//! An input can name a test library directly, name a directory (in which case
+//! every library inside it is a test), or contain a glob pattern in its last
+//! component (such as `tests/*.so`).  An input of the form `inline:NAME`
+//! names the test `NAME` written inline in the config.
+
+use super::err;
+use std::path::{Path, PathBuf};
+
+/// File extensions that mark a file as a test library when scanning
+/// directories.
+pub const EXTENSIONS: &[&str] = &["so", "dylib", "dll"];
+
+/// The prefix that marks an input as naming an inline test.
+pub const INLINE_PREFIX: &str = "inline:";
+
+/// Characters that make an input a glob pattern.
+const GLOB_CHARS: &[char] = &['*', '?'];
+
+/// Expands `input` into the test libraries it names, in name order.
+///
+/// Returns the libraries alongside whether `input` was a directory or
+/// pattern (and so could name any number of tests).
+///
+/// # Errors
+///
+/// Fails if a directory can't be read, or a directory or pattern matches no
+/// tests.
+pub fn expand(input: &Path) -> err::Result<(Vec<PathBuf>, bool)> {
+    if inline_name(input).is_some() {
+        return Ok((vec![input.to_owned()], false));
+    }
+    let file_name = input.file_name().and_then(|x| x.to_str());
+    let tests = if input.is_dir() {
+        scan(input, has_test_extension)?
+    } else if let Some(pattern) = file_name.filter(|n| n.contains(GLOB_CHARS)) {
+        let dir = match input.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        scan(dir, |p| {
+            p.file_name()
+                .and_then(|x| x.to_str())
+                .is_some_and(|n| matches_glob(pattern, n))
+        })?
+    } else {
+        return Ok((vec![input.to_owned()], false));
+    };
+
+    if tests.is_empty() {
+        return Err(err::Error::NoTestsFound(input.to_owned()));
+    }
+    Ok((tests, true))
+}
+
+/// Gets the name of the inline test that `input` names, if it names one.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::ux::discover::inline_name;
+/// use std::path::Path;
+/// assert_eq!(inline_name(Path::new("inline:sb")), Some("sb"));
+/// assert_eq!(inline_name(Path::new("sb.so")), None);
+/// ```
+#[must_use]
+pub fn inline_name(input: &Path) -> Option<&str> {
+    input.to_str()?.strip_prefix(INLINE_PREFIX)
+}
+
+/// Gets a display name for the test at `path`, namely its file stem, or its
+/// name if it is an inline test.
+#[must_use]
+pub fn test_name(path: &Path) -> String {
+    if let Some(name) = inline_name(path) {
+        return name.to_owned();
+    }
+    path.file_stem().map_or_else(
+        || path.to_string_lossy().into_owned(),
+        |x| x.to_string_lossy().into_owned(),
+    )
+}
+
+fn scan(dir: &Path, keep: impl Fn(&Path) -> bool) -> err::Result<Vec<PathBuf>> {
+    let bad_dir = |e| err::Error::BadTestDir(dir.to_owned(), e);
+    let mut tests = vec![];
+    for entry in std::fs::read_dir(dir).map_err(bad_dir)? {
+        let path = entry.map_err(bad_dir)?.path();
+        if path.is_file() && keep(&path) {
+            tests.push(path);
+        }
+    }
+    tests.sort();
+    Ok(tests)
+}
+
+fn has_test_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| EXTENSIONS.contains(&x))
+}
+
+/// Checks whether `name` matches the glob `pattern`.
+///
+/// Patterns support `*` (any run of characters) and `?` (any one character).
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::ux::discover::matches_glob;
+/// assert!(matches_glob("*.so", "sb.so"));
+/// assert!(matches_glob("s?.so", "sb.so"));
+/// assert!(matches_glob("*", ""));
+/// assert!(!matches_glob("*.so", "sb.dylib"));
+/// assert!(!matches_glob("s?.so", "s.so"));
+/// ```
+#[must_use]
+pub fn matches_glob(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Classic greedy matching with backtracking to the most recent star.
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    p = sp + 1;
+                    n = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Makes a fresh scratch directory containing the given empty files.
+    fn scratch(name: &str, files: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("phph-discover-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for f in files {
+            std::fs::write(dir.join(f), b"").unwrap();
+        }
+        dir
+    }
+
+    /// Tests expanding directories and patterns.
+    #[test]
+    fn test_expand() -> err::Result<()> {
+        let dir = scratch("expand", &["b.so", "a.so", "c.dylib", "notes.txt"]);
+
+        let (tests, many) = expand(&dir)?;
+        let names: Vec<_> = tests.iter().map(|p| test_name(p)).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert!(many);
+
+        let (tests, _) = expand(&dir.join("*.so"))?;
+        assert_eq!(tests, [dir.join("a.so"), dir.join("b.so")]);
+
+        assert!(matches!(
+            expand(&dir.join("*.dll")),
+            Err(err::Error::NoTestsFound(_))
+        ));
+
+        let (tests, many) = expand(&dir.join("plain.so"))?;
+        assert_eq!(tests, [dir.join("plain.so")]);
+        assert!(!many);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+}
@@ -2,7 +2,7 @@
 use std::convert::Infallible;
 
 use super::out;
-use crate::config;
+use crate::{config, err::Coded};
 use thiserror::Error;
 
 /// A configuration error.
@@ -16,9 +16,103 @@ pub enum Error {
     #[error("output error")]
     Output(#[from] out::err::Error),
 
+    /// A test directory or pattern didn't match any tests.
+    #[error("no tests found at {0}")]
+    NoTestsFound(std::path::PathBuf),
+
+    /// We couldn't read a directory while looking for tests.
+    #[error("couldn't read test directory {0}")]
+    BadTestDir(std::path::PathBuf, #[source] std::io::Error),
+
+    /// No subcommand was given.
+    #[error("no subcommand given")]
+    NoAction,
+
     /// We expected a test, but none was given.
     #[error("no input test given")]
     NoInput,
+
+    /// The user asked for an error format that doesn't exist.
+    #[error("unknown error format: {0}")]
+    BadErrorFormat(String),
+
+    /// The user asked for a log level that doesn't exist.
+    #[error("unknown log level: {0}")]
+    BadLogLevel(String),
+
+    /// The user asked for a log format that doesn't exist.
+    #[error("unknown log format: {0}")]
+    BadLogFormat(String),
+
+    /// We couldn't read a report to merge.
+    #[error("couldn't read report {0}")]
+    ReportIo(std::path::PathBuf, #[source] std::io::Error),
+    /// A report to merge wasn't valid JSON report output.
+    #[error("couldn't parse report {0}")]
+    BadReport(std::path::PathBuf, #[source] serde_json::Error),
+    /// A report to merge follows a newer schema than this build understands.
+    #[error(
+        "report {0} has schema version {1}, but this build only understands up to {}",
+        crate::model::report::schema::VERSION
+    )]
+    NewerReport(std::path::PathBuf, u32),
+
+    /// There was no checkpoint to recover at this path.
+    #[error("no checkpoint to recover at {0}")]
+    NoCheckpoint(std::path::PathBuf),
+    /// We couldn't remove a checkpoint after recovering it.
+    #[error("couldn't remove recovered checkpoint {0}")]
+    CheckpointIo(std::path::PathBuf, #[source] std::io::Error),
+
+    /// We couldn't create the discovery log at this path.
+    #[error("couldn't open discovery log {0}")]
+    BadDiscoveryLog(std::path::PathBuf, #[source] std::io::Error),
+    /// The user supplied a bad discovery log sampling interval.
+    #[error("couldn't parse discovery log interval: {0}")]
+    BadDiscoveryEvery(crate::config::units::Error),
+
+    /// The user asked to serve on an address we don't understand.
+    #[error("couldn't parse serve address {0}; expected tcp:HOST:PORT or unix:PATH")]
+    BadServeAddress(String),
+    /// We couldn't start serving on this address.
+    #[error("couldn't serve on {0}")]
+    ServeIo(String, #[source] std::io::Error),
+    /// The user supplied a bad progress sampling interval for serving.
+    #[error("couldn't parse serve interval: {0}")]
+    BadServeEvery(crate::config::units::Error),
+
+    /// We couldn't read a list of model-allowed states.
+    #[error("couldn't read expected states {0}")]
+    ExpectIo(std::path::PathBuf, #[source] std::io::Error),
+    /// We couldn't read a test library to hash it for its report's
+    /// provenance.
+    #[error("couldn't hash test library {0}")]
+    InputIo(std::path::PathBuf, #[source] std::io::Error),
+    /// A list of model-allowed states was malformed.
+    #[error("couldn't parse expected states {0}: {1}")]
+    BadExpect(std::path::PathBuf, String),
+
+    /// The user supplied a bad thread count for synchroniser benchmarking.
+    #[error("couldn't parse benchmark thread count: {0}")]
+    BadBenchThreads(std::num::ParseIntError),
+    /// The user supplied a bad duration for synchroniser benchmarking.
+    #[error("couldn't parse benchmark duration: {0}")]
+    BadBenchDuration(crate::config::units::Error),
+    /// The user supplied a bad iteration count for the self-test.
+    #[error("couldn't parse self-test iteration count: {0}")]
+    BadSelftestIterations(crate::config::units::Error),
+    /// The user supplied a bad total time for a sweep.
+    #[error("couldn't parse sweep total time: {0}")]
+    BadSweepTotalTime(crate::config::units::Error),
+    /// The user supplied a bad slice length for a sweep.
+    #[error("couldn't parse sweep slice length: {0}")]
+    BadSweepSlice(crate::config::units::Error),
+    /// The user supplied an unknown sweep allocation policy.
+    #[error("unknown sweep policy: {0}")]
+    BadSweepPolicy(String),
+    /// A sweep had no profiles to sweep across.
+    #[error("no profiles to sweep across; give some with --profiles, or add them to the config")]
+    NoSweepProfiles,
 }
 
 impl From<Infallible> for Error {
@@ -27,5 +121,89 @@ impl From<Infallible> for Error {
     }
 }
 
+/// Wrapped errors forward their codes and context.
+impl Coded for Error {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Config(e) => e.code(),
+            Self::Output(e) => e.code(),
+            Self::NoAction => "ux.no-action",
+            Self::NoInput => "ux.no-input",
+            Self::NoTestsFound(_) => "ux.no-tests-found",
+            Self::BadTestDir(..) => "ux.bad-test-dir",
+            Self::BadErrorFormat(_) => "ux.bad-error-format",
+            Self::BadLogLevel(_) => "ux.bad-log-level",
+            Self::BadLogFormat(_) => "ux.bad-log-format",
+            Self::ReportIo(..) => "ux.report-io",
+            Self::BadReport(..) => "ux.bad-report",
+            Self::NewerReport(..) => "ux.newer-report",
+            Self::NoCheckpoint(_) => "ux.no-checkpoint",
+            Self::CheckpointIo(..) => "ux.checkpoint-io",
+            Self::BadDiscoveryLog(..) => "ux.bad-discovery-log",
+            Self::BadDiscoveryEvery(_) => "ux.bad-discovery-every",
+            Self::BadServeAddress(_) => "ux.bad-serve-address",
+            Self::ServeIo(..) => "ux.serve-io",
+            Self::BadServeEvery(_) => "ux.bad-serve-every",
+            Self::ExpectIo(..) => "ux.expect-io",
+            Self::InputIo(..) => "ux.input-io",
+            Self::BadExpect(..) => "ux.bad-expect",
+            Self::BadBenchThreads(_) => "ux.bad-bench-threads",
+            Self::BadBenchDuration(_) => "ux.bad-bench-duration",
+            Self::BadSelftestIterations(_) => "ux.bad-selftest-iterations",
+            Self::BadSweepTotalTime(_) => "ux.bad-sweep-total-time",
+            Self::BadSweepSlice(_) => "ux.bad-sweep-slice",
+            Self::BadSweepPolicy(_) => "ux.bad-sweep-policy",
+            Self::NoSweepProfiles => "ux.no-sweep-profiles",
+        }
+    }
+
+    fn context(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::Config(e) => e.context(),
+            Self::Output(e) => e.context(),
+            Self::BadBenchThreads(e) => vec![("reason", e.to_string())],
+            Self::BadDiscoveryEvery(e)
+            | Self::BadServeEvery(e)
+            | Self::BadBenchDuration(e)
+            | Self::BadSelftestIterations(e)
+            | Self::BadSweepTotalTime(e)
+            | Self::BadSweepSlice(e) => vec![("reason", e.to_string())],
+            Self::BadErrorFormat(s)
+            | Self::BadLogLevel(s)
+            | Self::BadLogFormat(s)
+            | Self::BadSweepPolicy(s)
+            | Self::BadServeAddress(s) => {
+                vec![("input", s.clone())]
+            }
+            Self::ServeIo(a, e) => vec![("address", a.clone()), ("reason", e.to_string())],
+            Self::NoTestsFound(p) | Self::NoCheckpoint(p) => {
+                vec![("path", p.to_string_lossy().into_owned())]
+            }
+            Self::BadTestDir(p, e)
+            | Self::BadDiscoveryLog(p, e)
+            | Self::ReportIo(p, e)
+            | Self::CheckpointIo(p, e)
+            | Self::ExpectIo(p, e)
+            | Self::InputIo(p, e) => vec![
+                ("path", p.to_string_lossy().into_owned()),
+                ("reason", e.to_string()),
+            ],
+            Self::BadReport(p, e) => vec![
+                ("path", p.to_string_lossy().into_owned()),
+                ("reason", e.to_string()),
+            ],
+            Self::NewerReport(p, version) => vec![
+                ("path", p.to_string_lossy().into_owned()),
+                ("version", version.to_string()),
+            ],
+            Self::BadExpect(p, e) => vec![
+                ("path", p.to_string_lossy().into_owned()),
+                ("reason", e.clone()),
+            ],
+            Self::NoAction | Self::NoInput | Self::NoSweepProfiles => vec![],
+        }
+    }
+}
+
 /// Results over [Error].
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,109 @@
+//! Logging runner events to stderr, either as text or as JSON lines.
+//!
+//! The runner emits its events and spans through `tracing`; this module
+//! installs the `tracing_subscriber` that writes them out.
+
+use super::err;
+use std::{io::IsTerminal, str::FromStr};
+use tracing_subscriber::fmt::format::FmtSpan;
+
+/// Strings used when mapping log levels and formats to command-line
+/// arguments.
+pub mod string {
+    /// List of all possible string representations of log levels, from least
+    /// to most verbose.
+    pub const LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+    /// The string representation for text logs.
+    pub const TEXT: &str = "text";
+    /// The string representation for JSON logs.
+    pub const JSON: &str = "json";
+
+    /// List of all possible string representations of log formats.
+    pub const FORMATS: &[&str] = &[TEXT, JSON];
+}
+
+/// Enumeration of formats in which to log runner events.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// One line of human-readable text per event.
+    Text,
+    /// One JSON object per line per event.
+    Json,
+}
+
+/// The default format is text.
+impl Default for Format {
+    fn default() -> Self {
+        Self::Text
+    }
+}
+
+impl FromStr for Format {
+    type Err = err::Error;
+
+    fn from_str(s: &str) -> err::Result<Self> {
+        match s {
+            string::TEXT => Ok(Self::Text),
+            string::JSON => Ok(Self::Json),
+            s => Err(err::Error::BadLogFormat(s.to_owned())),
+        }
+    }
+}
+
+/// Parses the log level `s`.
+///
+/// # Errors
+///
+/// Fails if `s` isn't one of [`string::LEVELS`].
+pub fn parse_level(s: &str) -> err::Result<tracing::Level> {
+    string::LEVELS
+        .contains(&s)
+        .then(|| s.parse().ok())
+        .flatten()
+        .ok_or_else(|| err::Error::BadLogLevel(s.to_owned()))
+}
+
+/// Starts logging runner events up to `level` to stderr in `format`.
+///
+/// Spans, such as each run and rotation, log their elapsed time when they
+/// close.  This does nothing if something else has already installed a
+/// global subscriber.
+pub fn init(level: tracing::Level, format: Format) {
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_thread_names(true)
+        .with_span_events(FmtSpan::CLOSE);
+    // Logging must never stop the run, so we ignore an existing subscriber.
+    let _ = match format {
+        Format::Text => builder.try_init(),
+        Format::Json => builder.json().try_init(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that every level string parses, in order of verbosity.
+    #[test]
+    fn test_parse_level() {
+        let levels: Vec<_> = string::LEVELS
+            .iter()
+            .map(|s| parse_level(s).unwrap())
+            .collect();
+        assert!(levels.windows(2).all(|w| w[0] < w[1]));
+        assert!(parse_level("loud").is_err());
+        assert!(parse_level("3").is_err());
+    }
+
+    /// Tests that every format string parses.
+    #[test]
+    fn test_format_from_str() {
+        assert_eq!(string::TEXT.parse::<Format>().unwrap(), Format::Text);
+        assert_eq!(string::JSON.parse::<Format>().unwrap(), Format::Json);
+        assert!("xml".parse::<Format>().is_err());
+    }
+}
@@ -2,13 +2,19 @@
 //!
 //! Generally, one will use [Choice] to make a selection (eg via command line)
 //! of an [Outputter] to use, then instantiate it against a writer, then output
-//! through it.
+//! through it.  Library users can add their own choices through the
+//! [registry].
 
 pub mod abs;
 pub mod config;
+pub mod dot;
 pub mod err;
 pub mod histo;
 pub mod json;
+pub mod litmus;
+pub mod registry;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 
 pub use abs::Outputter;
-pub use config::{Choice, Config, Outputtable};
+pub use config::{Choice, Config, Outputtable, Sink, Target};
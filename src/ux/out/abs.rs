@@ -14,4 +14,28 @@ pub trait Outputter {
     ///
     /// Propagates any I/O errors from outputting the report.
     fn output(self: Box<Self>, r: model::report::Report) -> err::Result<()>;
+
+    /// Outputs the suite of reports `s`, flushing and returning any errors
+    /// arising.
+    ///
+    /// # Errors
+    ///
+    /// Propagates any I/O errors from outputting the suite.
+    fn output_suite(self: Box<Self>, s: model::Suite) -> err::Result<()>;
+
+    /// Outputs the test inspection `i`, flushing and returning any errors
+    /// arising.
+    ///
+    /// # Errors
+    ///
+    /// Propagates any I/O errors from outputting the inspection.
+    fn output_inspection(self: Box<Self>, i: model::inspect::Inspection) -> err::Result<()>;
+
+    /// Outputs the comparison between two builds `c`, flushing and returning
+    /// any errors arising.
+    ///
+    /// # Errors
+    ///
+    /// Propagates any I/O errors from outputting the comparison.
+    fn output_comparison(self: Box<Self>, c: model::comparison::Comparison) -> err::Result<()>;
 }
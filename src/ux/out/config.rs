@@ -1,50 +1,67 @@
-//! Enumerated selection of outputs, for use in command-line selection.
+//! Selection of outputs by name, for use in command-line selection.
 
-use super::{abs, err, histo, json};
-use crate::model::Report;
-use std::{io::Write, str::FromStr};
+use super::{abs, err, histo, litmus, registry};
+use crate::model::{comparison::Comparison, inspect::Inspection, state, Report, Suite};
+use std::{io::Write, path::PathBuf, str::FromStr};
 
-/// Enumeration of outputter choices.
+/// A choice of outputter, from those in the [registry].
 ///
 /// This is not (yet) serialisable or deserialisable as it is not stored in
-/// tester config.
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
-#[non_exhaustive]
-pub enum Choice {
-    /// Selects the histogram outputter.
-    Histogram,
-    /// Selects the JSON outputter.
-    Json,
-}
+/// tester config.  Choices compare by name.
+#[derive(Clone)]
+pub struct Choice(registry::Entry);
 
 impl Choice {
-    /// Constructs the appropriate outputter for the choice, using the given
-    /// writer.
-    pub fn into_outputter<'a, W: Write + 'a>(self, writer: W) -> Box<dyn abs::Outputter + 'a> {
-        match self {
-            Self::Histogram => Box::new(histo::Histogram::new(writer)),
-            Self::Json => Box::new(json::Json::new(writer)),
-        }
+    /// Gets whether this choice outputs to a database file, rather than to a
+    /// stream.
+    #[must_use]
+    pub fn is_database(&self) -> bool {
+        self.0.is_database()
+    }
+
+    /// Gets the string representation of this choice.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        self.0.name
+    }
+}
+
+impl PartialEq for Choice {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+    }
+}
+
+impl Eq for Choice {}
+
+impl std::fmt::Debug for Choice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Choice").field(&self.name()).finish()
     }
 }
 
-/// Strings used when mapping outputter choicers to command-line arguments.
+/// Strings used when mapping the built-in outputter choices to command-line
+/// arguments.
+///
+/// See [`registry::names`] for the names of every outputter, including those
+/// registered by library users.
 pub mod string {
     /// The string representation for the histogram outputter.
     pub const HISTOGRAM: &str = "histogram";
     /// The string representation for the JSON outputter.
     pub const JSON: &str = "json";
-
-    // TODO(@MattWindsor91): test this lines up properly
-
-    /// List of all possible string representations of outputter choices.
-    pub const ALL: &[&str] = &[HISTOGRAM, JSON];
+    /// The string representation for the litmus7 log outputter.
+    pub const LITMUS: &str = "litmus";
+    /// The string representation for the Graphviz DOT outputter.
+    pub const DOT: &str = "dot";
+    /// The string representation for the SQLite outputter.
+    pub const SQLITE: &str = "sqlite";
 }
 
 /// The default outputter is the histogram.
 impl Default for Choice {
     fn default() -> Self {
-        Self::Histogram
+        Self(registry::histogram())
     }
 }
 
@@ -53,54 +70,376 @@ impl FromStr for Choice {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let su = s.to_lowercase();
-        match &*su {
-            string::HISTOGRAM => Ok(Self::Histogram),
-            string::JSON => Ok(Self::Json),
-            _ => Err(Self::Err::BadOutputter(su)),
-        }
+        registry::lookup(&su)
+            .map(Self)
+            .ok_or(Self::Err::BadOutputter(su))
     }
 }
 
-/// A complete definition of how to select an output.
-pub struct Config {
+/// A single output: an outputter choice paired with a destination.
+pub struct Target {
     /// The choice of outputter.
     pub choice: Choice,
-    /// The choice of writer.
-    pub writer: Box<dyn std::io::Write>,
+    /// The destination.
+    pub sink: Sink,
+}
+
+/// The destination of a [Target].
+pub enum Sink {
+    /// A writer, for outputters that output to streams.
+    Writer(Box<dyn Write>),
+    /// The path to a database file, for outputters that append to
+    /// databases.
+    Database(PathBuf),
+}
+
+impl Target {
+    /// Constructs a target writing to stdout.
+    #[must_use]
+    pub fn stdout(choice: Choice) -> Self {
+        Self {
+            choice,
+            sink: Sink::Writer(Box::new(std::io::stdout())),
+        }
+    }
+
+    /// Constructs a target from a spec of the form `TYPE[:PATH]`, or
+    /// `PATH` alone if it contains a `:` (see [`split_spec`]).
+    ///
+    /// If there is no path, the target writes to stdout; otherwise, it
+    /// creates (or truncates) the file at the path.  Database outputters
+    /// need a path, and append to the database there rather than truncating
+    /// it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the type is not a valid outputter choice, a database
+    /// outputter has no path, or the file can't be created.
+    pub fn from_spec(spec: &str) -> err::Result<Self> {
+        let (choice, path) = split_spec(spec)?;
+        Ok(match path {
+            None if choice.is_database() => {
+                return Err(err::Error::NeedsPath(choice.name().to_owned()))
+            }
+            None => Self::stdout(choice),
+            Some(path) if choice.is_database() => Self {
+                choice,
+                sink: Sink::Database(path.into()),
+            },
+            Some(path) => {
+                let file = std::fs::File::create(path)
+                    .map_err(|e| err::Error::BadOutputPath(path.into(), e))?;
+                Self {
+                    choice,
+                    sink: Sink::Writer(Box::new(std::io::BufWriter::new(file))),
+                }
+            }
+        })
+    }
+
+    /// Constructs the appropriate outputter for the target, configuring it
+    /// with `context`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the outputter can't output to the target's sink.
+    pub fn into_outputter<'a>(
+        self,
+        context: &registry::Context,
+    ) -> err::Result<Box<dyn abs::Outputter + 'a>> {
+        match (&self.choice.0.factory, self.sink) {
+            (registry::Factory::Stream(f), Sink::Writer(w)) => Ok(f(w, context)),
+            (registry::Factory::Database(f), Sink::Database(path)) => Ok(f(path, context)),
+            _ => Err(err::Error::BadSink(self.choice.name().to_owned())),
+        }
+    }
+}
+
+/// Splits a target spec into its outputter choice and path, if any.
+///
+/// Only a known outputter name, alone or before the first `:`, is a type.
+/// Otherwise, a spec containing a `:` is all path, for the default
+/// outputter, so that paths such as `C:\out.json` don't parse as type `C`.
+fn split_spec(spec: &str) -> err::Result<(Choice, Option<&str>)> {
+    let Some((choice, path)) = spec.split_once(':') else {
+        return Ok((spec.parse()?, None));
+    };
+    Ok(match choice.parse() {
+        Ok(choice) => (choice, Some(path)),
+        Err(_) => (Choice::default(), Some(spec)),
+    })
+}
+
+/// A complete definition of how to output: a list of targets on which every
+/// item is output in turn.
+pub struct Config {
+    /// The targets.
+    pub targets: Vec<Target>,
+    /// Display formats for variables, keyed by variable.
+    pub formats: state::Formats,
+    /// The test name and condition for litmus7 logs.
+    pub litmus: litmus::Metadata,
+    /// The optional columns for histograms.
+    pub columns: histo::Columns,
+    /// The order in which to show the variables of states, if not the
+    /// default.
+    pub key_order: Option<state::KeyOrder>,
 }
 
 impl Config {
-    /// Constructs the appropriate outputter for the spec.
+    /// Replaces the variable display formats in this config.
     #[must_use]
-    pub fn into_outputter<'a>(self) -> Box<dyn abs::Outputter + 'a> {
-        self.choice.into_outputter(self.writer)
+    pub fn with_formats(self, formats: state::Formats) -> Self {
+        Self { formats, ..self }
+    }
+
+    /// Replaces the litmus7 log metadata in this config.
+    #[must_use]
+    pub fn with_litmus(self, litmus: litmus::Metadata) -> Self {
+        Self { litmus, ..self }
+    }
+
+    /// Replaces the optional histogram columns in this config.
+    #[must_use]
+    pub fn with_columns(self, columns: histo::Columns) -> Self {
+        Self { columns, ..self }
+    }
+
+    /// Sets the order in which to show the variables of states, unless this
+    /// config already has one.
+    #[must_use]
+    pub fn or_key_order(self, key_order: state::KeyOrder) -> Self {
+        Self {
+            key_order: self.key_order.or(Some(key_order)),
+            ..self
+        }
+    }
+
+    /// Makes a config with the same outputter choices and formats as this
+    /// one, but with every target writing to stdout.
+    ///
+    /// Unlike the config itself, this can be made as often as needed, as it
+    /// opens no files.
+    #[must_use]
+    pub fn to_stdout(&self) -> Self {
+        Self {
+            targets: self
+                .targets
+                .iter()
+                .map(|t| Target::stdout(t.choice.clone()))
+                .collect(),
+            formats: self.formats.clone(),
+            litmus: self.litmus.clone(),
+            columns: self.columns,
+            key_order: self.key_order,
+        }
+    }
+
+    /// Outputs `item` on every target, using `f` to output on each.
+    ///
+    /// Every target is attempted even if earlier ones fail; the first error is
+    /// returned.
+    fn fan_out<T: Clone>(
+        self,
+        item: T,
+        f: impl Fn(Box<dyn abs::Outputter>, T) -> err::Result<()>,
+    ) -> err::Result<()> {
+        let context = registry::Context {
+            formats: self.formats,
+            litmus: self.litmus,
+            columns: self.columns,
+            key_order: self.key_order.unwrap_or_default(),
+        };
+        let mut result = Ok(());
+        let mut targets = self.targets.into_iter().peekable();
+        let mut item = Some(item);
+        while let Some(target) = targets.next() {
+            // Avoid a clone for the last target, which is usually the only one.
+            let this = if targets.peek().is_some() {
+                item.clone()
+            } else {
+                item.take()
+            };
+            if let Some(this) = this {
+                let r = target.into_outputter(&context).and_then(|o| f(o, this));
+                result = result.and(r);
+            }
+        }
+        result
     }
 }
 
-/// The default config uses the default outputter choice, and stdout.
+/// The default config outputs one default outputter choice to stdout.
 impl Default for Config {
     fn default() -> Self {
         Config {
-            choice: Choice::default(),
-            writer: Box::new(std::io::stdout()),
+            targets: vec![Target::stdout(Choice::default())],
+            formats: state::Formats::default(),
+            litmus: litmus::Metadata::default(),
+            columns: histo::Columns::default(),
+            key_order: None,
         }
     }
 }
 
 /// Trait used to add inline outputter methods to reports.
 pub trait Outputtable {
-    /// Outputs this item onto the outputter chosen by `on`.
-    /// `on`.
+    /// Outputs this item onto every target chosen by `on`.
     ///
     /// # Errors
     ///
     /// Generally carries any errors caused by trying to `output` to the
-    /// outputter given by `on`.
+    /// outputters given by `on`.
     fn output(self, on: Config) -> err::Result<()>;
 }
 
 impl Outputtable for Report {
     fn output(self, on: Config) -> err::Result<()> {
-        on.into_outputter().output(self)
+        on.fan_out(self, abs::Outputter::output)
+    }
+}
+
+impl Outputtable for Inspection {
+    fn output(self, on: Config) -> err::Result<()> {
+        on.fan_out(self, abs::Outputter::output_inspection)
+    }
+}
+
+impl Outputtable for Suite {
+    fn output(self, on: Config) -> err::Result<()> {
+        on.fan_out(self, abs::Outputter::output_suite)
+    }
+}
+
+impl Outputtable for Comparison {
+    fn output(self, on: Config) -> err::Result<()> {
+        on.fan_out(self, abs::Outputter::output_comparison)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests parsing target specs.
+    #[test]
+    fn test_target_from_spec() {
+        let t = Target::from_spec("json").unwrap();
+        assert_eq!(t.choice.name(), string::JSON);
+
+        let path = std::env::temp_dir().join(format!("phph-target-{}.json", std::process::id()));
+        let t = Target::from_spec(&format!("histogram:{}", path.display())).unwrap();
+        assert_eq!(t.choice, Choice::default());
+        assert!(path.exists());
+        std::fs::remove_file(path).unwrap();
+
+        assert!(matches!(
+            Target::from_spec("nope"),
+            Err(err::Error::BadOutputter(_))
+        ));
+    }
+
+    /// Tests that specs only have types if they start with known outputter
+    /// names, and are otherwise paths.
+    #[test]
+    fn test_split_spec() {
+        let split = |spec| {
+            let (choice, path) = split_spec(spec).unwrap();
+            (choice.name(), path)
+        };
+        assert_eq!(split("json"), (string::JSON, None));
+        assert_eq!(
+            split(r"json:C:\out.json"),
+            (string::JSON, Some(r"C:\out.json"))
+        );
+        assert_eq!(
+            split(r"C:\out.json"),
+            (string::HISTOGRAM, Some(r"C:\out.json"))
+        );
+    }
+
+    /// Tests that registered outputters can be chosen by spec, and get the
+    /// config's formats and metadata.
+    #[test]
+    fn test_registered_outputter() {
+        use std::sync::{Arc, Mutex};
+
+        struct Custom(Arc<Mutex<Vec<String>>>, String);
+        impl abs::Outputter for Custom {
+            fn output(self: Box<Self>, _: Report) -> err::Result<()> {
+                self.0.lock().unwrap().push(self.1);
+                Ok(())
+            }
+            fn output_suite(self: Box<Self>, _: Suite) -> err::Result<()> {
+                Err(err::Error::Unsupported {
+                    outputter: "test-custom",
+                    item: "suites",
+                })
+            }
+            fn output_inspection(self: Box<Self>, _: Inspection) -> err::Result<()> {
+                Err(err::Error::Unsupported {
+                    outputter: "test-custom",
+                    item: "inspections",
+                })
+            }
+            fn output_comparison(self: Box<Self>, _: Comparison) -> err::Result<()> {
+                Err(err::Error::Unsupported {
+                    outputter: "test-custom",
+                    item: "comparisons",
+                })
+            }
+        }
+
+        let seen = Arc::new(Mutex::new(vec![]));
+        let sink = seen.clone();
+        registry::register(registry::Entry::stream("test-custom", move |_, context| {
+            Box::new(Custom(
+                sink.clone(),
+                context.litmus.name.clone().unwrap_or_default(),
+            ))
+        }))
+        .unwrap();
+
+        let config = Config {
+            targets: vec![
+                Target::from_spec("TEST-CUSTOM").unwrap(),
+                Target::from_spec("test-custom").unwrap(),
+            ],
+            ..Config::default()
+        }
+        .with_litmus(litmus::Metadata {
+            name: Some("foo".to_owned()),
+            ..litmus::Metadata::default()
+        });
+        Report::default().output(config).unwrap();
+        assert_eq!(*seen.lock().unwrap(), ["foo", "foo"]);
+
+        // Database entries can't take writers.
+        registry::register(registry::Entry::database("test-db", move |_, _| {
+            Box::new(Custom(seen.clone(), String::new()))
+        }))
+        .unwrap();
+        let target = Target::stdout("test-db".parse().unwrap());
+        assert!(matches!(
+            target.into_outputter(&registry::Context::default()),
+            Err(err::Error::BadSink(_))
+        ));
+    }
+
+    /// Tests that database targets need a path, and don't create the file up
+    /// front.
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_database_target_from_spec() {
+        assert!(matches!(
+            Target::from_spec("sqlite"),
+            Err(err::Error::NeedsPath(_))
+        ));
+
+        let path = std::env::temp_dir().join(format!("phph-target-{}.db", std::process::id()));
+        let t = Target::from_spec(&format!("sqlite:{}", path.display())).unwrap();
+        assert_eq!(t.choice.name(), string::SQLITE);
+        assert!(matches!(t.sink, Sink::Database(p) if p == path));
+        assert!(!path.exists());
     }
 }
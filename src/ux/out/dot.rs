@@ -0,0 +1,201 @@
+//! The Graphviz DOT outputter.
+//!
+//! This draws the states of reports as the nodes of a directed graph, and
+//! the transitions between them (see [`model::transition`]) as its edges, so
+//! that tools such as `dot` can lay them out.  Each node gives its state's
+//! valuation and occurrence count, failing states are red, and states with
+//! unknown outcomes are dashed; each edge gives its transition's count.
+//! Reports from runs that didn't record transitions draw as bare nodes.
+//! Suites draw as one graph per test, named after the test.
+
+use super::{abs::Outputter, config::string, err};
+use crate::model;
+use std::io::Write;
+
+/// An outputter that draws reports as DOT graphs.
+pub struct Dot<W> {
+    w: W,
+    /// Display formats for variables.
+    formats: model::state::Formats,
+    /// The order in which to list the variables of valuations.
+    key_order: model::state::KeyOrder,
+}
+
+impl<W: Write> Outputter for Dot<W> {
+    fn output(mut self: Box<Self>, report: model::Report) -> err::Result<()> {
+        self.dump_report(None, &report)?;
+        self.w.flush()?;
+        Ok(())
+    }
+
+    fn output_suite(mut self: Box<Self>, suite: model::Suite) -> err::Result<()> {
+        for (name, report) in &suite.reports {
+            self.dump_report(Some(name), report)?;
+        }
+        self.w.flush()?;
+        Ok(())
+    }
+
+    fn output_inspection(self: Box<Self>, _: model::inspect::Inspection) -> err::Result<()> {
+        Err(err::Error::Unsupported {
+            outputter: string::DOT,
+            item: "inspections",
+        })
+    }
+
+    fn output_comparison(self: Box<Self>, _: model::comparison::Comparison) -> err::Result<()> {
+        Err(err::Error::Unsupported {
+            outputter: string::DOT,
+            item: "comparisons",
+        })
+    }
+}
+
+impl<W: Write> Dot<W> {
+    /// Constructs a new DOT writer.
+    pub fn new(w: W) -> Self {
+        Self {
+            w,
+            formats: model::state::Formats::default(),
+            key_order: model::state::KeyOrder::default(),
+        }
+    }
+
+    /// Sets the display formats to use for variables.
+    #[must_use]
+    pub fn with_formats(self, formats: model::state::Formats) -> Self {
+        Self { formats, ..self }
+    }
+
+    /// Sets the order in which to list the variables of valuations.
+    #[must_use]
+    pub fn with_key_order(self, key_order: model::state::KeyOrder) -> Self {
+        Self { key_order, ..self }
+    }
+
+    fn dump_report(&mut self, name: Option<&str>, report: &model::Report) -> std::io::Result<()> {
+        match name {
+            Some(name) => writeln!(self.w, "digraph \"{}\" {{", escape(name))?,
+            None => writeln!(self.w, "digraph {{")?,
+        }
+        writeln!(self.w, "  node [shape=box];")?;
+        for (i, s) in report.states.iter().enumerate() {
+            let style = match s.info.outcome {
+                model::Outcome::Pass => "",
+                model::Outcome::Fail => ", color=red",
+                model::Outcome::Unknown => ", style=dashed",
+            };
+            writeln!(
+                self.w,
+                "  s{i} [label=\"{}\\n({})\"{style}];",
+                self.stringify_state(&s.state, report),
+                s.info.occurs
+            )?;
+        }
+        for t in &report.transitions {
+            writeln!(
+                self.w,
+                "  s{} -> s{} [label=\"{}\"];",
+                t.from, t.to, t.occurs
+            )?;
+        }
+        writeln!(self.w, "}}")
+    }
+
+    /// Converts `valuation`, a state of `report`, to an escaped label with
+    /// one line per variable.
+    fn stringify_state(&self, valuation: &model::state::State, report: &model::Report) -> String {
+        let vars: Vec<_> = self
+            .key_order
+            .entries(valuation, &report.var_order)
+            .into_iter()
+            .map(|(k, v)| {
+                let format = self.formats.get(k.as_str()).copied().unwrap_or_default();
+                escape(&format!("{k}={}", v.display(format)))
+            })
+            .collect();
+        vars.join("\\n")
+    }
+}
+
+/// Escapes `s` for use inside a quoted DOT string.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(x: i32, outcome: model::Outcome, occurs: usize) -> model::report::State {
+        let mut info = model::state::Info::new(outcome, 0, 0);
+        info.occurs = occurs;
+        model::report::State {
+            state: [
+                ("x".into(), model::state::Value::I32(x)),
+                ("y".into(), model::state::Value::Bool(true)),
+            ]
+            .into(),
+            info,
+            initials: vec![],
+            interesting: vec![],
+            checks: std::collections::BTreeMap::new(),
+            aux_flags: std::collections::BTreeMap::new(),
+            hash: String::new(),
+        }
+    }
+
+    /// Tests that reports draw their states as nodes and their transitions
+    /// as edges.
+    #[test]
+    fn test_output() {
+        let report = model::Report {
+            states: vec![
+                state(0, model::Outcome::Pass, 3),
+                state(1, model::Outcome::Fail, 2),
+            ],
+            transitions: vec![
+                model::transition::Transition {
+                    from: 0,
+                    to: 1,
+                    occurs: 2,
+                },
+                model::transition::Transition {
+                    from: 1,
+                    to: 0,
+                    occurs: 2,
+                },
+            ],
+            ..model::Report::default()
+        };
+        let mut buf = vec![];
+        Box::new(Dot::new(&mut buf)).output(report).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            r#"digraph {
+  node [shape=box];
+  s0 [label="x=0\ny=true\n(3)"];
+  s1 [label="x=1\ny=true\n(2)", color=red];
+  s0 -> s1 [label="2"];
+  s1 -> s0 [label="2"];
+}
+"#
+        );
+    }
+
+    /// Tests that suites draw one graph per test, with escaped names.
+    #[test]
+    fn test_output_suite() {
+        let mut suite = model::Suite::default();
+        for name in ["a", "b\"c"] {
+            suite
+                .reports
+                .insert(name.to_owned(), model::Report::default());
+        }
+        let mut buf = vec![];
+        Box::new(Dot::new(&mut buf)).output_suite(suite).unwrap();
+        let got = String::from_utf8(buf).unwrap();
+        assert!(got.starts_with("digraph \"a\" {\n"), "{got}");
+        assert!(got.contains("}\ndigraph \"b\\\"c\" {\n"), "{got}");
+    }
+}
@@ -1,4 +1,5 @@
 //! Error types for outputting reports.
+use crate::err::Coded;
 use thiserror::Error;
 
 /// Enumeration of possible outputting errors.
@@ -8,6 +9,52 @@ pub enum Error {
     #[error("unknown outputter: {0}")]
     BadOutputter(String),
 
+    /// A library user tried to register an outputter under a name that's
+    /// already taken.
+    #[error("an outputter named {0} is already registered")]
+    DuplicateOutputter(String),
+
+    /// We couldn't create a file to which to output.
+    #[error("couldn't create output file {0}")]
+    BadOutputPath(std::path::PathBuf, #[source] std::io::Error),
+
+    /// The user gave no path for an outputter that needs one.
+    #[error("outputter {0} needs a path to output to")]
+    NeedsPath(String),
+
+    /// An outputter was paired with a destination it can't use.
+    #[error("outputter {0} can't output to this destination")]
+    BadSink(String),
+
+    /// An outputter can't output this sort of item.
+    #[error("outputter {outputter} can't output {item}")]
+    Unsupported {
+        /// The outputter.
+        outputter: &'static str,
+        /// A description of the item.
+        item: &'static str,
+    },
+
+    /// A database path couldn't be passed to SQLite.
+    #[cfg(feature = "sqlite")]
+    #[error("bad database path {0}")]
+    BadDatabasePath(std::path::PathBuf),
+
+    /// SQLite reported an error.
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error {code}: {message}")]
+    Sqlite {
+        /// The SQLite result code.
+        code: std::os::raw::c_int,
+        /// SQLite's description of the error.
+        message: String,
+    },
+
+    /// A database has a schema from a later version of phenolphthalein.
+    #[cfg(feature = "sqlite")]
+    #[error("database schema version {0} is newer than this version supports")]
+    DatabaseTooNew(i64),
+
     /// A general I/O error.
     #[error("I/O error")]
     Io(#[from] std::io::Error),
@@ -17,5 +64,57 @@ pub enum Error {
     Json(#[from] serde_json::Error),
 }
 
+impl Coded for Error {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::BadOutputter(_) => "out.bad-outputter",
+            Self::DuplicateOutputter(_) => "out.duplicate-outputter",
+            Self::BadOutputPath(..) => "out.bad-output-path",
+            Self::NeedsPath(_) => "out.needs-path",
+            Self::BadSink(_) => "out.bad-sink",
+            Self::Unsupported { .. } => "out.unsupported",
+            #[cfg(feature = "sqlite")]
+            Self::BadDatabasePath(_) => "out.bad-database-path",
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite { .. } => "out.sqlite",
+            #[cfg(feature = "sqlite")]
+            Self::DatabaseTooNew(_) => "out.database-too-new",
+            Self::Io(_) => "out.io",
+            Self::Json(_) => "out.json",
+        }
+    }
+
+    fn context(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::BadOutputter(s)
+            | Self::DuplicateOutputter(s)
+            | Self::NeedsPath(s)
+            | Self::BadSink(s) => {
+                vec![("input", s.clone())]
+            }
+            Self::Unsupported { outputter, item } => {
+                vec![
+                    ("outputter", (*outputter).to_owned()),
+                    ("item", (*item).to_owned()),
+                ]
+            }
+            #[cfg(feature = "sqlite")]
+            Self::BadDatabasePath(p) => vec![("path", p.to_string_lossy().into_owned())],
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite { code, message } => {
+                vec![("code", code.to_string()), ("reason", message.clone())]
+            }
+            #[cfg(feature = "sqlite")]
+            Self::DatabaseTooNew(v) => vec![("version", v.to_string())],
+            Self::BadOutputPath(p, e) => vec![
+                ("path", p.to_string_lossy().into_owned()),
+                ("reason", e.to_string()),
+            ],
+            Self::Io(e) => vec![("reason", e.to_string())],
+            Self::Json(e) => vec![("reason", e.to_string())],
+        }
+    }
+}
+
 /// Shorthand for a result over [Error]s.
 pub type Result<T> = std::result::Result<T, Error>;
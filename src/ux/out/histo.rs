@@ -14,40 +14,831 @@ use std::{
 /// An outputter that provides Litmus-style histograms.
 pub struct Histogram<W> {
     w: tabwriter::TabWriter<W>,
+    formats: model::state::Formats,
+    columns: Columns,
+    key_order: model::state::KeyOrder,
+}
+
+/// Optional columns for the histogram, after each state's count.
+///
+/// With either column, the histogram lists states from most to least
+/// often seen, rather than in the order in which they were first seen.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Columns {
+    /// Whether to show each state's percentage of all occurrences.
+    pub percent: bool,
+    /// Whether to show the running total of those percentages.
+    pub cumulative: bool,
+}
+
+impl Columns {
+    /// Gets whether any optional column is on.
+    #[must_use]
+    pub fn any(self) -> bool {
+        self.percent || self.cumulative
+    }
 }
 
 impl<W: Write> Outputter for Histogram<W> {
     fn output(mut self: Box<Self>, report: Report) -> err::Result<()> {
-        self.dump_states(report.states)?;
+        self.dump_report(&report)?;
+        self.w.flush()?;
+        Ok(())
+    }
+
+    fn output_suite(mut self: Box<Self>, suite: model::Suite) -> err::Result<()> {
+        self.dump_summary(&suite)?;
+        for (name, report) in suite.reports {
+            writeln!(self.w, "\n== {name} ==")?;
+            self.dump_report(&report)?;
+        }
+        self.w.flush()?;
+        Ok(())
+    }
+
+    fn output_inspection(mut self: Box<Self>, i: model::inspect::Inspection) -> err::Result<()> {
+        writeln!(self.w, "threads\t{}", i.threads)?;
+        writeln!(self.w, "checker\t{}", yes_no(i.has_checker))?;
+        if let Some(description) = &i.check_description {
+            writeln!(self.w, "checking\t{description}")?;
+        }
+        if let Some(role) = i.scalable_role {
+            writeln!(self.w, "scalable role\t{role}")?;
+        }
+        if !i.symmetric.is_empty() {
+            writeln!(self.w, "symmetric\t{}", stringify_groups(&i.symmetric))?;
+        }
+        if !i.aux_flags.is_empty() {
+            writeln!(self.w, "aux flags\t{}", i.aux_flags.join(" "))?;
+        }
+        writeln!(self.w)?;
+        self.dump_capabilities(&i.capabilities)?;
+        writeln!(self.w)?;
+        let has_bounds = i.vars.iter().any(|v| v.bounds.is_some());
+        let bounds_header = if has_bounds { "\tbounds" } else { "" };
+        writeln!(self.w, "var\ttype\tatomic\tinitial{bounds_header}")?;
+        for v in i.vars {
+            let initial = v.initial_value.map_or_else(
+                || "-".to_owned(),
+                |x| x.display(format_of(&self.formats, &v.name)),
+            );
+            let bounds = match v.bounds {
+                Some(b) => format!("\t{b}"),
+                None if has_bounds => "\t-".to_owned(),
+                None => String::new(),
+            };
+            writeln!(
+                self.w,
+                "{}\t{}\t{}\t{initial}{bounds}",
+                v.name,
+                v.ty,
+                yes_no(v.is_atomic)
+            )?;
+        }
+        self.w.flush()?;
+        Ok(())
+    }
+
+    fn output_comparison(mut self: Box<Self>, c: model::comparison::Comparison) -> err::Result<()> {
+        self.dump_comparison(&c)?;
         self.w.flush()?;
         Ok(())
     }
 }
 
+fn yes_no(b: bool) -> &'static str {
+    if b {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
 impl<W: Write> Histogram<W> {
     /// Constructs a new histogram writer.
     pub fn new(writer: W) -> Self {
         Self {
             w: tabwriter::TabWriter::new(writer).padding(1),
+            formats: model::state::Formats::default(),
+            columns: Columns::default(),
+            key_order: model::state::KeyOrder::default(),
+        }
+    }
+
+    /// Sets the display formats to use for variables.
+    #[must_use]
+    pub fn with_formats(self, formats: model::state::Formats) -> Self {
+        Self { formats, ..self }
+    }
+
+    /// Sets the optional columns to show.
+    #[must_use]
+    pub fn with_columns(self, columns: Columns) -> Self {
+        Self { columns, ..self }
+    }
+
+    /// Sets the order in which to show the variables of states.
+    #[must_use]
+    pub fn with_key_order(self, key_order: model::state::KeyOrder) -> Self {
+        Self { key_order, ..self }
+    }
+
+    /// Converts `valuation`, a state of `report`, to a string, joining
+    /// variables with `sep`.
+    fn stringify_state(
+        &self,
+        valuation: &model::state::State,
+        report: &Report,
+        sep: &str,
+    ) -> String {
+        stringify_valuation(
+            self.key_order.entries(valuation, &report.var_order),
+            &self.formats,
+            sep,
+        )
+    }
+
+    fn dump_summary(&mut self, suite: &model::Suite) -> io::Result<()> {
+        writeln!(self.w, "test\toutcome\tstates\titerations")?;
+        for row in suite.summary() {
+            let outcome = row
+                .outcome
+                .map_or_else(|| "-".to_owned(), |o| o.to_string());
+            writeln!(
+                self.w,
+                "{}\t{outcome}\t{}\t{}",
+                row.name, row.states, row.iterations
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Dumps a table of how many states each build showed, then the states
+    /// that only one build showed.
+    fn dump_comparison(&mut self, c: &model::comparison::Comparison) -> io::Result<()> {
+        writeln!(self.w, "build\titerations\tstates\tonly here")?;
+        for side in c.sides() {
+            writeln!(
+                self.w,
+                "{}\t{}\t{}\t{}",
+                side.name,
+                side.report.sampling_or_exact().iterations,
+                side.report.states.len(),
+                side.only.len()
+            )?;
+        }
+        if c.is_same() {
+            writeln!(self.w, "\nboth builds showed the same states")?;
+        }
+        for side in c.sides() {
+            if side.only.is_empty() {
+                continue;
+            }
+            writeln!(self.w, "\n== only in {} ==", side.name)?;
+            for state in &side.only {
+                self.dump_state(state, "", &side.report)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn dump_report(&mut self, report: &Report) -> io::Result<()> {
+        if let Some(description) = &report.check_description {
+            writeln!(self.w, "checking: {description}\n")?;
+        }
+        self.dump_sampling(report.sampling.as_ref())?;
+        if let Some(tally) = &report.outcomes {
+            self.dump_tally(report, tally)?;
+        } else {
+            self.dump_states(report)?;
+            self.dump_discovery(&report.discovery)?;
+        }
+        self.dump_ended_by(report.ended_by.as_ref())?;
+        self.dump_reproduction(report.reproduction.as_ref())?;
+        self.dump_masked(&report.masked)?;
+        self.dump_folded(&report.folded)?;
+        self.dump_orderings(&report.orderings)?;
+        self.dump_cpus(report.cpus.as_ref())?;
+        self.dump_roles(&report.roles)?;
+        self.dump_interesting(&report.interesting)?;
+        self.dump_torn(&report.torn)?;
+        self.dump_violations(&report.violations)?;
+        self.dump_tainted(&report.tainted)?;
+        self.dump_timing(&report.timing)?;
+        self.dump_allocations(report.allocations.as_ref())?;
+        self.dump_permutations(report)?;
+        self.dump_alternation(report)?;
+        self.dump_rotations(report)?;
+        self.dump_examples(report)?;
+        self.dump_transitions(report)?;
+        self.dump_projections(report)?;
+        self.dump_conformance(report)?;
+        self.dump_aggregate(&report.aggregate)?;
+        self.dump_platform(report.platform.as_ref())?;
+        self.dump_provenance(report.provenance.as_ref())
+    }
+
+    /// Dumps the outcomes of the end-of-run checks on the whole report, if
+    /// any.
+    fn dump_aggregate(
+        &mut self,
+        aggregate: &std::collections::BTreeMap<String, model::Outcome>,
+    ) -> io::Result<()> {
+        if aggregate.is_empty() {
+            return Ok(());
+        }
+        writeln!(self.w, "\nend-of-run checks:")?;
+        for (name, outcome) in aggregate {
+            writeln!(self.w, "{name}\t{outcome}")?;
+        }
+        Ok(())
+    }
+
+    /// Dumps how the states compare against a model's predictions, if any,
+    /// listing the forbidden observed states and the unobserved allowed ones.
+    fn dump_conformance(&mut self, report: &Report) -> io::Result<()> {
+        use model::conformance::Verdict;
+        let Some(c) = &report.conformance else {
+            return Ok(());
+        };
+        writeln!(
+            self.w,
+            "\nmodel conformance: {} allowed, {} forbidden, {} unobserved",
+            c.count(Verdict::Allowed),
+            c.count(Verdict::Forbidden),
+            c.unobserved.len()
+        )?;
+        for (state, verdict) in report.states.iter().zip(&c.verdicts) {
+            if *verdict == Verdict::Forbidden {
+                let valuation = self.stringify_state(&state.state, report, "\t");
+                writeln!(self.w, "{}\tforbidden>\t{valuation}", state.info.occurs)?;
+            }
+        }
+        for m in &c.unobserved {
+            let m = stringify_matcher(m, self.key_order, &report.var_order);
+            writeln!(self.w, "-\tunobserved>\t{m}")?;
+        }
+        Ok(())
+    }
+
+    /// Dumps which optional capabilities a test has, and what happens in lieu
+    /// of those it lacks.
+    fn dump_capabilities(&mut self, caps: &model::capability::Set) -> io::Result<()> {
+        writeln!(self.w, "capability\tpresent\tif absent")?;
+        for cap in model::capability::Capability::ALL {
+            let present = caps.contains(&cap);
+            let fallback = if present { "" } else { cap.fallback() };
+            writeln!(self.w, "{cap}\t{}\t{fallback}", yes_no(present))?;
+        }
+        Ok(())
+    }
+
+    /// Dumps the machine on which the test ran, if recorded.
+    fn dump_platform(&mut self, platform: Option<&model::platform::Platform>) -> io::Result<()> {
+        let Some(p) = platform else {
+            return Ok(());
+        };
+        writeln!(self.w, "\nplatform:")?;
+        if p.family.is_empty() {
+            writeln!(self.w, "os\t{}", p.os)?;
+        } else {
+            writeln!(self.w, "os\t{} ({})", p.os, p.family)?;
+        }
+        writeln!(self.w, "arch\t{}", p.arch)?;
+        writeln!(self.w, "cpus\t{}", p.cpus)?;
+        if let Some(t) = &p.topology {
+            if let Some(model) = &t.model {
+                writeln!(self.w, "model\t{model}")?;
+            }
+            writeln!(
+                self.w,
+                "topology\t{} packages, {} cores, {} logical ({} per core)",
+                t.packages,
+                t.cores,
+                t.logical,
+                t.threads_per_core()
+            )?;
         }
+        Ok(())
     }
 
-    fn dump_states(&mut self, states: std::vec::Vec<State>) -> err::Result<()> {
-        for state in states {
-            self.dump_state(state)?;
+    /// Dumps where the run came from, if recorded.
+    ///
+    /// This leaves out the config snapshot, which is as long as the config.
+    fn dump_provenance(
+        &mut self,
+        provenance: Option<&model::provenance::Provenance>,
+    ) -> io::Result<()> {
+        let Some(p) = provenance else {
+            return Ok(());
+        };
+        writeln!(self.w, "\nprovenance:")?;
+        writeln!(self.w, "run\t{}", p.run_id)?;
+        writeln!(self.w, "version\t{}", p.version)?;
+        if let Some(allocator) = &p.allocator {
+            writeln!(self.w, "allocator\t{allocator}")?;
+        }
+        if let Some(seed) = p.seed {
+            writeln!(self.w, "seed\t{seed}")?;
+        }
+        if let Some(input) = &p.input {
+            writeln!(self.w, "input\t{} (sha256 {})", input.path, input.sha256)?;
         }
         Ok(())
     }
 
-    fn dump_state(&mut self, State { state, info }: State) -> io::Result<()> {
+    /// Warns that the counts below are approximate, if the run sampled its
+    /// observations.
+    fn dump_sampling(&mut self, sampling: Option<&model::sampling::Sampling>) -> io::Result<()> {
+        let Some(sampling) = sampling else {
+            return Ok(());
+        };
+        let note = format!("sampled {sampling}; counts are approximate");
+        writeln!(self.w, "{}\n", note.yellow())
+    }
+
+    /// Notes why the run ended, if known.
+    fn dump_ended_by(&mut self, cause: Option<&model::ending::Cause>) -> io::Result<()> {
+        let Some(cause) = cause else {
+            return Ok(());
+        };
+        writeln!(self.w, "ended: {cause}")
+    }
+
+    /// Notes how to reproduce the run's failure quickly, if the run was
+    /// shrunk.
+    fn dump_reproduction(
+        &mut self,
+        repro: Option<&model::reproduction::Reproduction>,
+    ) -> io::Result<()> {
+        let Some(repro) = repro else {
+            return Ok(());
+        };
+        writeln!(self.w, "reproduce with: {repro}")
+    }
+
+    /// Notes any variables left out of the states above.
+    fn dump_masked(&mut self, masked: &[String]) -> io::Result<()> {
+        if masked.is_empty() {
+            return Ok(());
+        }
+        writeln!(self.w, "masked variables: {}", masked.join(", "))
+    }
+
+    /// Notes any symmetric threads whose states the states above fold.
+    fn dump_folded(&mut self, folded: &[Vec<usize>]) -> io::Result<()> {
+        if folded.is_empty() {
+            return Ok(());
+        }
         writeln!(
             self.w,
-            "{occ}\t{sigil}>\t{state}\t(iter {iter})",
+            "folded symmetric threads: {}",
+            stringify_groups(folded)
+        )
+    }
+
+    /// Notes the orderings with which the test accessed its atomic variables,
+    /// if recorded.
+    fn dump_orderings(
+        &mut self,
+        orderings: &std::collections::BTreeMap<String, model::ordering::Set>,
+    ) -> io::Result<()> {
+        if orderings.is_empty() {
+            return Ok(());
+        }
+        let vars: Vec<_> = orderings
+            .iter()
+            .map(|(var, orders)| {
+                let orders: Vec<_> = orders.iter().map(ToString::to_string).collect();
+                format!("{var} {}", orders.join(", "))
+            })
+            .collect();
+        writeln!(self.w, "orderings: {}", vars.join("; "))
+    }
+
+    /// Notes the CPUs to which the test threads were restricted, if any.
+    fn dump_cpus(&mut self, cpus: Option<&model::cpu::Set>) -> io::Result<()> {
+        let Some(cpus) = cpus else {
+            return Ok(());
+        };
+        writeln!(self.w, "cpus: {cpus}")
+    }
+
+    /// Notes which declared thread's role each thread played, if the run
+    /// overrode the thread count.
+    fn dump_roles(&mut self, roles: &[usize]) -> io::Result<()> {
+        if roles.is_empty() {
+            return Ok(());
+        }
+        let roles: Vec<_> = roles
+            .iter()
+            .enumerate()
+            .map(|(tid, role)| format!("{tid}:{role}"))
+            .collect();
+        writeln!(self.w, "roles: {}", roles.join(" "))
+    }
+
+    /// Dumps when each interesting state was first seen, if any.
+    fn dump_interesting(&mut self, sightings: &[model::interest::Sighting]) -> io::Result<()> {
+        if sightings.is_empty() {
+            return Ok(());
+        }
+        writeln!(self.w, "\ninteresting states:")?;
+        for s in sightings {
+            let seen = s.iteration.map_or_else(
+                || "never seen".to_owned(),
+                |i| format!("first seen at iter {i}"),
+            );
+            writeln!(self.w, "{}\t{seen}", s.name)?;
+        }
+        Ok(())
+    }
+
+    /// Dumps any torn reads, showing integers in hex to expose the canary.
+    fn dump_torn(&mut self, torn: &[model::canary::TornRead]) -> io::Result<()> {
+        if torn.is_empty() {
+            return Ok(());
+        }
+        writeln!(self.w, "\ntorn reads:")?;
+        for t in torn {
+            writeln!(
+                self.w,
+                "{}\t!>\t{}={}\t(first at iter {}, rot {})",
+                t.occurs,
+                t.var,
+                t.value.display(model::state::Format::Hex),
+                t.iteration,
+                t.rotation
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Dumps any invariant violations.
+    fn dump_violations(&mut self, violations: &[model::invariant::Violation]) -> io::Result<()> {
+        if violations.is_empty() {
+            return Ok(());
+        }
+        writeln!(self.w, "\ninvariant violations:")?;
+        for v in violations {
+            writeln!(
+                self.w,
+                "{}\t!>\t{}={}\t(outside {}; first at iter {}, rot {})",
+                v.occurs,
+                v.var,
+                v.value.display(format_of(&self.formats, &v.var)),
+                v.bounds,
+                v.iteration,
+                v.rotation
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Dumps any signs that the test broke the runner's assumptions.
+    fn dump_tainted(&mut self, tainted: &[model::taint::Taint]) -> io::Result<()> {
+        if tainted.is_empty() {
+            return Ok(());
+        }
+        writeln!(self.w, "\n{}", "tainted; states may be unreliable:".red())?;
+        for t in tainted {
+            writeln!(self.w, "!\t{t}")?;
+        }
+        Ok(())
+    }
+
+    /// Dumps the distribution of the time each thread spent in the test
+    /// body, if timed, with each thread's share of the total.
+    fn dump_timing(&mut self, timing: &[model::timing::Histogram]) -> io::Result<()> {
+        let Some(shares) = model::timing::shares(timing) else {
+            return Ok(());
+        };
+        writeln!(self.w, "\nbody time (ns):")?;
+        writeln!(self.w, "thread\tsamples\tmean\tp50\tp99\tmax\tshare")?;
+        for (tid, (h, share)) in timing.iter().zip(shares).enumerate() {
+            let quantile = |q| {
+                h.quantile_ns(q)
+                    .map_or_else(|| "-".to_owned(), |n| n.to_string())
+            };
+            writeln!(
+                self.w,
+                "{tid}\t{}\t{}\t{}\t{}\t{}\t{:.0}%",
+                h.samples,
+                h.mean_ns()
+                    .map_or_else(|| "-".to_owned(), |n| format!("{n:.0}")),
+                quantile(0.5),
+                quantile(0.99),
+                h.max_ns,
+                share * 100.0
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Notes how many allocations the runner made handling iterations, if it
+    /// audited them.
+    fn dump_allocations(&mut self, audit: Option<&model::allocation::Audit>) -> io::Result<()> {
+        let Some(audit) = audit else {
+            return Ok(());
+        };
+        writeln!(self.w, "\nallocations: {audit}")
+    }
+
+    /// Dumps the per-permutation breakdown, if there was more than one
+    /// permutation to break down.
+    fn dump_permutations(&mut self, report: &Report) -> io::Result<()> {
+        if report.permutations.len() < 2 {
+            return Ok(());
+        }
+        for p in &report.permutations {
+            let outcome = p.outcome.map_or_else(|| "-".to_owned(), |o| o.to_string());
+            writeln!(
+                self.w,
+                "\npermutation{} ({} rot, {outcome})",
+                stringify_permutation(Some(&p.assignment)),
+                p.rotations
+            )?;
+            for o in &p.states {
+                if let Some(state) = report.states.get(o.state) {
+                    let valuation = self.stringify_state(&state.state, report, "\t");
+                    writeln!(
+                        self.w,
+                        "{}\t{}>\t{valuation}",
+                        o.occurs,
+                        check_sigil(state.info.outcome),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Dumps how often each state occurred under each alternated
+    /// synchroniser, as a fraction of that synchroniser's iterations, if the
+    /// run alternated synchronisers.
+    fn dump_alternation(&mut self, report: &Report) -> io::Result<()> {
+        let Some(a) = &report.alternation else {
+            return Ok(());
+        };
+        writeln!(
+            self.w,
+            "\nsynchronisers (switching every {} rot, distance {:.4})",
+            a.every,
+            a.distance()
+        )?;
+        for arm in &a.arms {
+            write!(self.w, "{} ({} rot)\t", arm.sync, arm.rotations)?;
+        }
+        writeln!(self.w, "state")?;
+        for (i, state) in report.states.iter().enumerate() {
+            for arm in &a.arms {
+                match arm.frequency(i) {
+                    Some(f) => write!(self.w, "{f:.4}\t")?,
+                    None => write!(self.w, "-\t")?,
+                }
+            }
+            let valuation = self.stringify_state(&state.state, report, "\t");
+            writeln!(self.w, "{}>\t{valuation}", check_sigil(state.info.outcome))?;
+        }
+        Ok(())
+    }
+
+    /// Dumps the states seen in each rotation, if the run segmented its
+    /// histogram by rotation.
+    fn dump_rotations(&mut self, report: &Report) -> io::Result<()> {
+        for r in &report.rotations {
+            let outcome = r.outcome.map_or_else(|| "-".to_owned(), |o| o.to_string());
+            writeln!(
+                self.w,
+                "\nrotation {}{} ({} iter, {outcome})",
+                r.rotation,
+                stringify_permutation((!r.assignment.is_empty()).then_some(&r.assignment[..])),
+                r.iterations()
+            )?;
+            for o in &r.states {
+                if let Some(state) = report.states.get(o.state) {
+                    let valuation = self.stringify_state(&state.state, report, "\t");
+                    writeln!(
+                        self.w,
+                        "{}\t{}>\t{valuation}",
+                        o.occurs,
+                        check_sigil(state.info.outcome),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Dumps the context of the first iteration to show each state, if the
+    /// run captured examples.
+    fn dump_examples(&mut self, report: &Report) -> io::Result<()> {
+        if report.examples.is_empty() {
+            return Ok(());
+        }
+        writeln!(self.w, "\nexamples:")?;
+        for e in &report.examples {
+            let Some(state) = report.states.get(e.state) else {
+                continue;
+            };
+            let mut context = format!(
+                "iter {}, rot {}{}",
+                e.iteration,
+                e.rotation,
+                stringify_permutation((!e.permutation.is_empty()).then_some(&e.permutation[..]))
+            );
+            if !e.initials.is_empty() {
+                context.push_str("; initials ");
+                context.push_str(&self.stringify_state(&e.initials, report, " "));
+            }
+            if !e.skew.is_empty() {
+                let skew: Vec<_> = e.skew.iter().map(|(t, d)| format!("{t}={d}")).collect();
+                context.push_str("; skew ");
+                context.push_str(&skew.join(" "));
+            }
+            let valuation = self.stringify_state(&state.state, report, "\t");
+            writeln!(
+                self.w,
+                "{}>\t{valuation}\t({context})",
+                check_sigil(state.info.outcome)
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Dumps how often each state followed each other state, if the run
+    /// recorded transitions.
+    fn dump_transitions(&mut self, report: &Report) -> io::Result<()> {
+        if report.transitions.is_empty() {
+            return Ok(());
+        }
+        writeln!(self.w, "\ntransitions:")?;
+        for t in &report.transitions {
+            let (Some(from), Some(to)) = (report.states.get(t.from), report.states.get(t.to))
+            else {
+                continue;
+            };
+            writeln!(
+                self.w,
+                "{}\t{}\t->\t{}",
+                t.occurs,
+                self.stringify_state(&from.state, report, " "),
+                self.stringify_state(&to.state, report, " ")
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Dumps the marginal histogram of each projection the report has.
+    fn dump_projections(&mut self, report: &Report) -> io::Result<()> {
+        for p in &report.projections {
+            writeln!(self.w, "\nprojection {} ({}):", p.name, p.vars.join(", "))?;
+            for m in &p.states {
+                writeln!(
+                    self.w,
+                    "{}\t{}>\t{}",
+                    m.occurs,
+                    check_sigil(m.outcome),
+                    self.stringify_state(&m.state, report, "\t")
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn dump_discovery(&mut self, curve: &model::discovery::Curve) -> io::Result<()> {
+        if let Some(last) = curve.last() {
+            writeln!(
+                self.w,
+                "\n{} distinct states; last new state at iteration {}",
+                last.states, last.iteration
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Dumps the outcomes of a report from a test without variables in
+    /// place of its one, empty, state.
+    fn dump_tally(&mut self, report: &Report, tally: &model::outcome::Tally) -> io::Result<()> {
+        writeln!(
+            self.w,
+            "no variables; outcomes of {} iterations:",
+            tally.total()
+        )?;
+        for (outcome, count) in [
+            (model::Outcome::Pass, tally.pass),
+            (model::Outcome::Fail, tally.fail),
+            (model::Outcome::Unknown, tally.unknown),
+        ] {
+            if count != 0 {
+                writeln!(self.w, "{count}\t{}>\t{outcome}", check_sigil(outcome))?;
+            }
+        }
+        for state in &report.states {
+            let tags = stringify_checks(&state.checks) + &stringify_aux_flags(&state.aux_flags);
+            if let Some(tags) = tags.strip_prefix('\t') {
+                writeln!(self.w, "{}", tags.replace('\t', "; "))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn dump_states(&mut self, report: &Report) -> io::Result<()> {
+        if !self.columns.any() {
+            for state in &report.states {
+                self.dump_state(state, "", report)?;
+            }
+            return Ok(());
+        }
+        let total = report.iterations();
+        for (state, running) in report.ranked() {
+            let mut shares = String::new();
+            if self.columns.percent {
+                shares.push('\t');
+                shares.push_str(&percentage(state.info.occurs, total));
+            }
+            if self.columns.cumulative {
+                shares.push('\t');
+                shares.push_str(&percentage(running, total));
+            }
+            self.dump_state(state, &shares, report)?;
+        }
+        Ok(())
+    }
+
+    fn dump_state(
+        &mut self,
+        State {
+            state,
+            info,
+            initials,
+            interesting,
+            checks,
+            aux_flags,
+            hash: _,
+        }: &State,
+        shares: &str,
+        report: &Report,
+    ) -> io::Result<()> {
+        let valuation = self.stringify_state(state, report, "\t");
+        writeln!(
+            self.w,
+            "{occ}{shares}\t{sigil}>\t{state}\t(iter {iter}..{last}, {rots} rot, first in rot {rot}{perm}){tags}",
             occ = info.occurs,
             sigil = check_sigil(info.outcome),
-            state = stringify_valuation(&state),
+            state = valuation,
             iter = info.iteration,
-        )
+            last = info.last_iteration,
+            rots = info.rotations,
+            rot = info.rotation,
+            perm = stringify_permutation(report.schedule.get(info.rotation)),
+            tags = stringify_interesting(interesting)
+                + &stringify_checks(checks)
+                + &stringify_aux_flags(aux_flags),
+        )?;
+        for initial in initials {
+            let initial = self.stringify_state(initial, report, " ");
+            writeln!(self.w, "\t\tfrom {initial}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats `n` as a percentage of `total`, to two decimal places.
+fn percentage(n: usize, total: usize) -> String {
+    if total == 0 {
+        return "-".to_owned();
+    }
+    #[allow(clippy::cast_precision_loss)] // This is only for display.
+    let share = 100.0 * n as f64 / total as f64;
+    format!("{share:.2}%")
+}
+
+/// Produces a tag listing the outcomes of combined checkers, if any.
+fn stringify_checks(checks: &BTreeMap<String, model::Outcome>) -> String {
+    if checks.is_empty() {
+        return String::new();
+    }
+    let checks: Vec<_> = checks.iter().map(|(k, v)| format!("{k}={v}")).collect();
+    format!("\tchecks: {}", checks.join(", "))
+}
+
+/// Produces a tag listing the auxiliary flags a state's iterations raised,
+/// if any, with their counts.
+fn stringify_aux_flags(flags: &BTreeMap<String, usize>) -> String {
+    if flags.is_empty() {
+        return String::new();
+    }
+    let flags: Vec<_> = flags.iter().map(|(k, v)| format!("{k}={v}")).collect();
+    format!("\tflags: {}", flags.join(", "))
+}
+
+/// Produces a tag listing the interesting states a state matches, if any.
+fn stringify_interesting(names: &[String]) -> String {
+    if names.is_empty() {
+        String::new()
+    } else {
+        format!("\tinteresting: {}", names.join(", "))
     }
 }
 
@@ -60,13 +851,371 @@ fn check_sigil(r: model::Outcome) -> colored::ColoredString {
     }
 }
 
-/// Converts a state valuation to a stirng.
-fn stringify_valuation(valuation: &BTreeMap<String, model::state::Value>) -> String {
+/// Converts the thread permutation for a rotation, if known, to a string.
+fn stringify_permutation(assignment: Option<&[usize]>) -> String {
+    assignment.map_or_else(String::new, |tids| {
+        let tids: Vec<_> = tids.iter().map(ToString::to_string).collect();
+        format!(" [{}]", tids.join(" "))
+    })
+}
+
+/// Converts groups of thread IDs to a string, bracketing each group.
+fn stringify_groups(groups: &[Vec<usize>]) -> String {
+    let groups: Vec<_> = groups
+        .iter()
+        .map(|g| {
+            let tids: Vec<_> = g.iter().map(ToString::to_string).collect();
+            format!("[{}]", tids.join(" "))
+        })
+        .collect();
+    groups.join(" ")
+}
+
+/// Gets the display format for the variable `var`.
+fn format_of(formats: &model::state::Formats, var: &str) -> model::state::Format {
+    formats.get(var).copied().unwrap_or_default()
+}
+
+/// Converts a matcher to a string, with one tab-separated binding per
+/// variable, in the order `key_order` (see [`model::state::KeyOrder::key`]).
+fn stringify_matcher(
+    m: &model::interest::Matcher,
+    key_order: model::state::KeyOrder,
+    declared: &[String],
+) -> String {
+    let mut bindings: Vec<_> = m.0.iter().collect();
+    bindings.sort_by_cached_key(|(k, _)| key_order.key(k, declared));
+    bindings
+        .into_iter()
+        .map(|(k, lit)| match lit {
+            model::interest::Literal::Bool(b) => format!("{k}={b}"),
+            model::interest::Literal::Int(i) => format!("{k}={i}"),
+            model::interest::Literal::Float(f) => format!("{k}={f}"),
+        })
+        .collect::<Vec<_>>()
+        .join("\t")
+}
+
+/// Converts the entries of a state valuation to a string, displaying each
+/// variable in its format from `formats` and joining variables with `sep`.
+fn stringify_valuation(
+    valuation: Vec<(&model::manifest::VarName, &model::state::Value)>,
+    formats: &model::state::Formats,
+    sep: &str,
+) -> String {
     /* TODO(@MattWindsor91): this should really be a Display impl, but
     valuations have no defined type off which to hang it. */
     valuation
-        .iter()
-        .map(|(k, v)| format!("{}={}", k, v))
+        .into_iter()
+        .map(|(k, v)| format!("{k}={}", v.display(format_of(formats, k))))
         .collect::<Vec<_>>()
-        .join("\t")
+        .join(sep)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Makes a report in which each `x` was seen `occurs` times.
+    fn report_of(xs: &[(i32, usize)]) -> Report {
+        let mut report = Report::default();
+        for &(x, occurs) in xs {
+            let mut info = model::state::Info::new(model::Outcome::Pass, 0, 0);
+            info.occurs = occurs;
+            report.insert(State {
+                state: [("x".into(), model::state::Value::I32(x))].into(),
+                info,
+                initials: vec![],
+                interesting: vec![],
+                checks: BTreeMap::new(),
+                aux_flags: BTreeMap::new(),
+                hash: String::new(),
+            });
+        }
+        report
+    }
+
+    /// Tests that the optional columns rank states by count, with their
+    /// shares of all occurrences.
+    #[test]
+    fn test_columns() {
+        let report = report_of(&[(0, 1), (1, 6), (2, 3)]);
+        let dump = |columns| {
+            let mut h = Histogram::new(vec![]).with_columns(columns);
+            h.dump_states(&report).unwrap();
+            String::from_utf8(h.w.into_inner().unwrap()).unwrap()
+        };
+        let plain = dump(Columns::default());
+        assert!(
+            plain.starts_with("1 "),
+            "plain histograms keep report order"
+        );
+
+        let lines: Vec<_> = dump(Columns {
+            percent: true,
+            cumulative: true,
+        })
+        .lines()
+        .map(|l| l.split_whitespace().take(4).collect::<Vec<_>>().join(" "))
+        .collect();
+        assert_eq!(
+            lines,
+            [
+                "6 60.00% 60.00% *>",
+                "3 30.00% 90.00% *>",
+                "1 10.00% 100.00% *>"
+            ]
+        );
+    }
+
+    /// Tests that states show their variables in the key order, using the
+    /// report's declaration order.
+    #[test]
+    fn test_key_order() {
+        use model::state::KeyOrder;
+        let mut report = Report::default();
+        report.insert(State {
+            state: [
+                ("1:r0".into(), model::state::Value::I32(1)),
+                ("x".into(), model::state::Value::I32(2)),
+                ("0:r0".into(), model::state::Value::I32(3)),
+            ]
+            .into(),
+            info: model::state::Info::new(model::Outcome::Pass, 0, 0),
+            initials: vec![],
+            interesting: vec![],
+            checks: BTreeMap::new(),
+            aux_flags: BTreeMap::new(),
+            hash: String::new(),
+        });
+        report.var_order = vec!["x".to_owned(), "1:r0".to_owned(), "0:r0".to_owned()];
+        let dump = |key_order| {
+            let mut h = Histogram::new(vec![]).with_key_order(key_order);
+            h.dump_states(&report).unwrap();
+            let out = String::from_utf8(h.w.into_inner().unwrap()).unwrap();
+            out.split_whitespace()
+                .skip(2)
+                .take(3)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        assert_eq!(dump(KeyOrder::Lexical), "0:r0=3 1:r0=1 x=2");
+        assert_eq!(dump(KeyOrder::Declared), "x=2 1:r0=1 0:r0=3");
+        assert_eq!(dump(KeyOrder::Thread), "0:r0=3 1:r0=1 x=2");
+    }
+
+    /// Tests that comparisons list only the states that one build alone
+    /// showed.
+    #[test]
+    fn test_comparison() {
+        let c = model::comparison::Comparison::new(
+            ("O0".to_owned(), report_of(&[(0, 2), (1, 2)])),
+            ("O3".to_owned(), report_of(&[(1, 3), (2, 1)])),
+        );
+        let mut h = Histogram::new(vec![]);
+        h.dump_comparison(&c).unwrap();
+        let out = String::from_utf8(h.w.into_inner().unwrap()).unwrap();
+        let lines: Vec<_> = out
+            .lines()
+            .map(|l| l.split_whitespace().take(4).collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "build iterations states only",
+                "O0 4 2 1",
+                "O3 4 2 1",
+                "",
+                "== only in O0",
+                "2 *> x=0 (iter",
+                "",
+                "== only in O3",
+                "1 *> x=2 (iter",
+            ]
+        );
+    }
+
+    /// Tests that per-rotation sections list each rotation's states, and
+    /// show rotations that saw nothing.
+    #[test]
+    fn test_rotations() {
+        let mut report = report_of(&[(0, 4), (1, 2)]);
+        let counts = [BTreeMap::from([(0, 3), (2, 1)]), BTreeMap::from([(2, 2)])];
+        report.schedule = model::schedule::Schedule(vec![vec![0, 1], vec![1, 0], vec![0, 1]]);
+        report.rotations = model::rotation::segment(&counts, &report.schedule, &report.states);
+        let mut h = Histogram::new(vec![]);
+        h.dump_rotations(&report).unwrap();
+        let out = String::from_utf8(h.w.into_inner().unwrap()).unwrap();
+        let lines: Vec<_> = out
+            .lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "",
+                "rotation 0 [0 1] (3 iter, pass)",
+                "3 *> x=0",
+                "",
+                "rotation 1 [1 0] (0 iter, -)",
+                "",
+                "rotation 2 [0 1] (3 iter, pass)",
+                "1 *> x=0",
+                "2 *> x=1",
+            ]
+        );
+    }
+
+    /// Tests that alternations list each state's frequency under each
+    /// synchroniser.
+    #[test]
+    fn test_alternation() {
+        let mut report = report_of(&[(0, 4), (1, 4)]);
+        let counts = [
+            BTreeMap::from([(0, 3), (1, 1)]),
+            BTreeMap::from([(0, 1), (1, 3)]),
+        ];
+        report.alternation = Some(model::alternation::Alternation::new(
+            vec!["spinner".to_owned(), "barrier".to_owned()],
+            std::num::NonZeroUsize::MIN,
+            2,
+            &counts,
+        ));
+        let mut h = Histogram::new(vec![]);
+        h.dump_alternation(&report).unwrap();
+        let out = String::from_utf8(h.w.into_inner().unwrap()).unwrap();
+        let lines: Vec<_> = out
+            .lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "",
+                "synchronisers (switching every 1 rot, distance 0.5000)",
+                "spinner (1 rot) barrier (1 rot) state",
+                "0.7500 0.2500 *> x=0",
+                "0.2500 0.7500 *> x=1",
+            ]
+        );
+    }
+
+    /// Tests that examples list each state's first context, leaving out the
+    /// parts the run didn't record.
+    #[test]
+    fn test_examples() {
+        let mut report = report_of(&[(0, 4), (1, 4)]);
+        let example = |state, iteration| model::example::Example {
+            state,
+            iteration,
+            rotation: 0,
+            permutation: vec![],
+            initials: model::state::State::new(),
+            skew: model::skew::Spec::new(),
+        };
+        let mut first = example(0, 0);
+        first.permutation = vec![1, 0];
+        first.initials = [("x".into(), model::state::Value::I32(2))].into();
+        first.skew = [(
+            "1".to_owned(),
+            model::skew::Delay::fixed(std::time::Duration::from_nanos(15)),
+        )]
+        .into();
+        report.examples = vec![first, example(1, 3)];
+        let mut h = Histogram::new(vec![]);
+        h.dump_examples(&report).unwrap();
+        let out = String::from_utf8(h.w.into_inner().unwrap()).unwrap();
+        let lines: Vec<_> = out
+            .lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "",
+                "examples:",
+                "*> x=0 (iter 0, rot 0 [1 0]; initials x=2; skew 1=15ns)",
+                "*> x=1 (iter 3, rot 0)",
+            ]
+        );
+    }
+
+    /// Tests that transitions list each pair of states with its count.
+    #[test]
+    fn test_transitions() {
+        let mut report = report_of(&[(0, 4), (1, 4)]);
+        let t = |from, to, occurs| model::transition::Transition { from, to, occurs };
+        report.transitions = vec![t(0, 1, 4), t(1, 0, 3), t(1, 1, 1)];
+        let mut h = Histogram::new(vec![]);
+        h.dump_transitions(&report).unwrap();
+        let out = String::from_utf8(h.w.into_inner().unwrap()).unwrap();
+        let lines: Vec<_> = out
+            .lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "",
+                "transitions:",
+                "4 x=0 -> x=1",
+                "3 x=1 -> x=0",
+                "1 x=1 -> x=1"
+            ]
+        );
+    }
+
+    /// Tests that projections dump as one marginal histogram each.
+    #[test]
+    fn test_projections() {
+        let mut report = report_of(&[(0, 4), (1, 3), (0, 2)]);
+        report.project(&[("xs".to_owned(), vec!["x".to_owned()])].into());
+        let mut h = Histogram::new(vec![]);
+        h.dump_projections(&report).unwrap();
+        let out = String::from_utf8(h.w.into_inner().unwrap()).unwrap();
+        let lines: Vec<_> = out
+            .lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(lines, ["", "projection xs (x):", "6 *> x=0", "3 *> x=1"]);
+    }
+
+    /// Tests that reports from tests without variables show their tallied
+    /// outcomes rather than a state table.
+    #[test]
+    fn test_tally() {
+        let mut report = Report::default();
+        let mut info = model::state::Info::new(model::Outcome::Fail, 0, 0);
+        info.occurs = 5;
+        report.insert(State {
+            state: model::state::State::new(),
+            info,
+            initials: vec![],
+            interesting: vec![],
+            checks: BTreeMap::new(),
+            aux_flags: [("odd".to_owned(), 2)].into(),
+            hash: String::new(),
+        });
+        report.outcomes = Some(model::outcome::Tally {
+            pass: 4,
+            fail: 1,
+            unknown: 0,
+        });
+        let mut h = Histogram::new(vec![]);
+        h.dump_report(&report).unwrap();
+        let out = String::from_utf8(h.w.into_inner().unwrap()).unwrap();
+        let lines: Vec<_> = out
+            .lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "no variables; outcomes of 5 iterations:",
+                "4 *> pass",
+                "1 :> fail",
+                "flags: odd=2"
+            ]
+        );
+    }
 }
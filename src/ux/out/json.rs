@@ -5,21 +5,344 @@ use crate::model;
 use std::io::Write;
 
 /// An outputter that dumps reports as JSON.
+///
+/// If any variables have display formats, each of their values gains a
+/// `display` string alongside its `type` and `value`.  Valuations list their
+/// variables in the outputter's key order.
 pub struct Json<W: Write> {
     /// The writer.
     writer: W,
+    /// Display formats for variables.
+    formats: model::state::Formats,
+    /// The order in which to list the variables of valuations.
+    key_order: model::state::KeyOrder,
 }
 
 impl<W: Write> Outputter for Json<W> {
     fn output(self: Box<Self>, report: model::Report) -> err::Result<()> {
-        serde_json::to_writer_pretty(self.writer, &report)?;
-        Ok(())
+        self.write(&report, annotate_report)
+    }
+
+    fn output_suite(self: Box<Self>, suite: model::Suite) -> err::Result<()> {
+        let summary: Vec<_> = suite.summary().collect();
+        let doc = SuiteDoc {
+            reports: &suite.reports,
+            summary,
+        };
+        self.write(&doc, annotate_suite)
+    }
+
+    fn output_inspection(self: Box<Self>, i: model::inspect::Inspection) -> err::Result<()> {
+        self.write(&i, annotate_inspection)
+    }
+
+    fn output_comparison(self: Box<Self>, c: model::comparison::Comparison) -> err::Result<()> {
+        self.write(&c, annotate_comparison)
     }
 }
 
+/// Serialisation shape for suites, which adds the summary table.
+#[derive(serde::Serialize)]
+struct SuiteDoc<'a> {
+    reports: &'a std::collections::BTreeMap<String, model::Report>,
+    summary: Vec<model::suite::Summary<'a>>,
+}
+
 impl<W: Write> Json<W> {
     /// Constructs a new JSON writer.
     pub fn new(writer: W) -> Self {
-        Self { writer }
+        Self {
+            writer,
+            formats: model::state::Formats::default(),
+            key_order: model::state::KeyOrder::default(),
+        }
+    }
+
+    /// Sets the display formats to use for variables.
+    #[must_use]
+    pub fn with_formats(self, formats: model::state::Formats) -> Self {
+        Self { formats, ..self }
+    }
+
+    /// Sets the order in which to list the variables of valuations.
+    #[must_use]
+    pub fn with_key_order(self, key_order: model::state::KeyOrder) -> Self {
+        Self { key_order, ..self }
+    }
+
+    /// Writes `item` as JSON, using `annotate` to add display strings to
+    /// its values, and flushing so that any write errors surface here rather
+    /// than being swallowed on drop.
+    fn write(mut self, item: &impl serde::Serialize, annotate: Annotator) -> err::Result<()> {
+        // States already list their variables in lexical order.
+        if self.formats.is_empty() && self.key_order == model::state::KeyOrder::Lexical {
+            serde_json::to_writer_pretty(&mut self.writer, item)?;
+        } else {
+            let mut doc = serde_json::to_value(item)?;
+            annotate(&mut doc, &self.formats);
+            let doc = Ordered {
+                value: &doc,
+                key_order: self.key_order,
+                declared: &[],
+            };
+            serde_json::to_writer_pretty(&mut self.writer, &doc)?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Type of functions that add `display` strings to every formatted
+/// variable's values in a serialised document.
+///
+/// Each kind of document has its own annotator, which only visits the
+/// fields that hold values; annotators never guess at values from the names
+/// of keys.
+type Annotator = fn(&mut serde_json::Value, &model::state::Formats);
+
+/// Annotates a serialised [`model::Report`].
+///
+/// Reports hold valuations in their states (both the states themselves and
+/// the fuzzed initial valuations behind them), their examples, and their
+/// projections.
+fn annotate_report(doc: &mut serde_json::Value, formats: &model::state::Formats) {
+    for_each(doc, "states", |s| annotate_state(s, formats));
+    for_each(doc, "examples", |e| annotate_field(e, "initials", formats));
+    for_each(doc, "projections", |p| {
+        for_each(p, "states", |m| annotate_field(m, "state", formats));
+    });
+}
+
+/// Annotates a serialised suite, whose reports are keyed by name.
+fn annotate_suite(doc: &mut serde_json::Value, formats: &model::state::Formats) {
+    if let Some(serde_json::Value::Object(reports)) = doc.get_mut("reports") {
+        for report in reports.values_mut() {
+            annotate_report(report, formats);
+        }
+    }
+}
+
+/// Annotates a serialised [`model::comparison::Comparison`].
+fn annotate_comparison(doc: &mut serde_json::Value, formats: &model::state::Formats) {
+    for side in ["left", "right"] {
+        if let Some(side) = doc.get_mut(side) {
+            if let Some(report) = side.get_mut("report") {
+                annotate_report(report, formats);
+            }
+            for_each(side, "only", |s| annotate_state(s, formats));
+        }
+    }
+}
+
+/// Annotates a serialised [`model::inspect::Inspection`], whose variables
+/// each carry their name alongside any initial value.
+fn annotate_inspection(doc: &mut serde_json::Value, formats: &model::state::Formats) {
+    for_each(doc, "vars", |var| {
+        let fmt = var
+            .get("name")
+            .and_then(serde_json::Value::as_str)
+            .and_then(|name| formats.get(name));
+        if let (Some(&fmt), Some(value)) = (fmt, var.get_mut("initial_value")) {
+            add_display(value, fmt);
+        }
+    });
+}
+
+/// Annotates a serialised [`model::report::State`].
+fn annotate_state(state: &mut serde_json::Value, formats: &model::state::Formats) {
+    annotate_field(state, "state", formats);
+    for_each(state, "initials", |v| annotate_valuation(v, formats));
+}
+
+/// Annotates the valuation, if any, at `key` in `doc`.
+fn annotate_field(doc: &mut serde_json::Value, key: &str, formats: &model::state::Formats) {
+    if let Some(valuation) = doc.get_mut(key) {
+        annotate_valuation(valuation, formats);
+    }
+}
+
+/// Annotates a serialised valuation, which maps variable names to values.
+fn annotate_valuation(valuation: &mut serde_json::Value, formats: &model::state::Formats) {
+    if let serde_json::Value::Object(map) = valuation {
+        for (var, value) in map.iter_mut() {
+            if let Some(&fmt) = formats.get(var) {
+                add_display(value, fmt);
+            }
+        }
+    }
+}
+
+/// Calls `f` on each element of the array, if any, at `key` in `doc`.
+fn for_each(doc: &mut serde_json::Value, key: &str, f: impl FnMut(&mut serde_json::Value)) {
+    if let Some(serde_json::Value::Array(xs)) = doc.get_mut(key) {
+        xs.iter_mut().for_each(f);
+    }
+}
+
+/// Adds a `display` string to `v` if it is a serialised value.
+fn add_display(v: &mut serde_json::Value, fmt: model::state::Format) {
+    let Ok(value) = serde_json::from_value::<model::state::Value>(v.clone()) else {
+        return;
+    };
+    if let serde_json::Value::Object(map) = v {
+        map.insert("display".to_owned(), value.display(fmt).into());
+    }
+}
+
+/// A JSON value that serialises the variables of each valuation in it in a
+/// key order.
+///
+/// Reports in the value carry the order in which their tests declared their
+/// variables, as `var_order`; each valuation follows that of the innermost
+/// report around it.  Serialising a `serde_json::Value` directly would list
+/// every object's keys in lexical order.
+struct Ordered<'a> {
+    /// The value to serialise.
+    value: &'a serde_json::Value,
+    /// The order in which to list the variables of valuations.
+    key_order: model::state::KeyOrder,
+    /// The `var_order` of the innermost report around the value.
+    declared: &'a [serde_json::Value],
+}
+
+impl Ordered<'_> {
+    /// Wraps `value`, inside a report whose `var_order` is `declared`.
+    fn within<'b>(
+        &self,
+        value: &'b serde_json::Value,
+        declared: &'b [serde_json::Value],
+    ) -> Ordered<'b> {
+        Ordered {
+            value,
+            key_order: self.key_order,
+            declared,
+        }
+    }
+}
+
+impl serde::Serialize for Ordered<'_> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match self.value {
+            serde_json::Value::Object(map) => {
+                let declared = match map.get("var_order") {
+                    Some(serde_json::Value::Array(names)) => names,
+                    _ => self.declared,
+                };
+                let mut entries: Vec<_> = map.iter().collect();
+                if is_valuation(map) {
+                    let names: Vec<_> = declared
+                        .iter()
+                        .filter_map(serde_json::Value::as_str)
+                        .collect();
+                    entries.sort_by_cached_key(|(k, _)| self.key_order.key(k, &names));
+                }
+                s.collect_map(
+                    entries
+                        .into_iter()
+                        .map(|(k, v)| (k, self.within(v, declared))),
+                )
+            }
+            serde_json::Value::Array(xs) => {
+                s.collect_seq(xs.iter().map(|x| self.within(x, self.declared)))
+            }
+            v => v.serialize(s),
+        }
+    }
+}
+
+/// Gets whether `map` is a valuation: an object mapping variables to
+/// serialised values.
+fn is_valuation(map: &serde_json::Map<String, serde_json::Value>) -> bool {
+    !map.is_empty()
+        && map
+            .values()
+            .all(|v| v.get("type").is_some() && v.get("value").is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that formatted values in a report's valuations get display
+    /// strings, and nothing else does, even under keys named like variables.
+    #[test]
+    fn test_annotate_report() {
+        let value = |x| serde_json::json!({ "type": "i32", "value": x });
+        let mut doc = serde_json::json!({
+            "states": [{
+                "state": { "x": value(255), "y": value(255) },
+                "initials": [{ "x": value(1) }]
+            }],
+            "examples": [{ "state": 0, "initials": { "x": value(2) } }],
+            "projections": [{ "name": "p", "states": [{ "state": { "x": value(3) } }] }],
+            "torn": [{ "var": "x", "value": value(4) }]
+        });
+        let formats = model::state::Formats::from([
+            ("x".to_owned(), model::state::Format::Hex),
+            ("value".to_owned(), model::state::Format::Hex),
+        ]);
+        annotate_report(&mut doc, &formats);
+
+        assert_eq!(doc["states"][0]["state"]["x"]["display"], "0xff");
+        assert!(doc["states"][0]["state"]["y"].get("display").is_none());
+        assert_eq!(doc["states"][0]["initials"][0]["x"]["display"], "0x1");
+        assert_eq!(doc["examples"][0]["initials"]["x"]["display"], "0x2");
+        assert_eq!(
+            doc["projections"][0]["states"][0]["state"]["x"]["display"],
+            "0x3"
+        );
+        assert!(doc["torn"][0]["value"].get("display").is_none());
+    }
+
+    /// Tests that formatted initial values in inspections get display
+    /// strings.
+    #[test]
+    fn test_annotate_inspection() {
+        let mut doc = serde_json::json!({
+            "vars": [
+                { "name": "x", "initial_value": { "type": "i32", "value": 10 } },
+                { "name": "y", "initial_value": { "type": "i32", "value": 10 } }
+            ]
+        });
+        let formats = model::state::Formats::from([("x".to_owned(), model::state::Format::Hex)]);
+        annotate_inspection(&mut doc, &formats);
+
+        assert_eq!(doc["vars"][0]["initial_value"]["display"], "0xa");
+        assert!(doc["vars"][1]["initial_value"].get("display").is_none());
+    }
+
+    /// Tests that valuations follow the key order, using the declaration
+    /// order of the report around them, and nothing else moves.
+    #[test]
+    fn test_ordered() {
+        let value = |x| serde_json::json!({ "type": "i32", "value": x });
+        let doc = serde_json::json!({
+            "states": [{ "state": { "0:r0": value(1), "1:r0": value(2), "x": value(3) } }],
+            "var_order": ["x", "1:r0", "0:r0"]
+        });
+        let dump = |key_order| {
+            let ordered = Ordered {
+                value: &doc,
+                key_order,
+                declared: &[],
+            };
+            serde_json::to_string(&ordered).unwrap()
+        };
+        let positions = |json: &str| {
+            [
+                "\"0:r0\"",
+                "\"1:r0\"",
+                "\"x\"",
+                "\"states\"",
+                "\"var_order\"",
+            ]
+            .map(|k| json.find(k).unwrap())
+        };
+
+        let [r0, r1, x, states, var_order] = positions(&dump(model::state::KeyOrder::Declared));
+        assert!(x < r1 && r1 < r0);
+        assert!(states < var_order);
+        let [r0, r1, x, ..] = positions(&dump(model::state::KeyOrder::Thread));
+        assert!(r0 < r1 && r1 < x);
     }
 }
@@ -0,0 +1,356 @@
+//! The litmus7 outputter.
+//!
+//! This emits reports in the classic text format of litmus7 logs, so that
+//! herd tooling (such as `mcompare`) can read phenolphthalein results
+//! directly.  Litmus logs judge each test against a condition; we take this
+//! from the tester config's interesting states if there are any, and from the
+//! test's checker otherwise (see [Condition]).
+
+use super::{abs::Outputter, config::string, err};
+use crate::model::{
+    self,
+    interest::{self, Literal},
+    state::Value,
+};
+use std::io::Write;
+
+/// The test name used for single reports when none is given.
+pub const DEFAULT_NAME: &str = "test";
+
+/// The condition against which a litmus log judges tests.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Condition {
+    /// The test should pass its checker on every state.
+    ///
+    /// This appears as a `Required` test, and the positive states are those
+    /// that pass (sigil `*`, as in histograms).  Its `forall` condition is the
+    /// report's check description, if it has one; otherwise, we don't know
+    /// the checker's proposition, and the log leaves out the condition.
+    #[default]
+    Checker,
+    /// The test should be able to reach at least one of these states.
+    ///
+    /// This appears as an `Allowed` test with an `exists` condition, and the
+    /// positive states are those matching any of the matchers.
+    Exists(Vec<interest::Matcher>),
+}
+
+impl Condition {
+    /// Makes an `exists` condition over the interesting states in `spec`,
+    /// falling back to the checker if there are none.
+    #[must_use]
+    pub fn from_interesting(spec: &interest::Spec) -> Self {
+        if spec.is_empty() {
+            Self::Checker
+        } else {
+            Self::Exists(spec.values().cloned().collect())
+        }
+    }
+
+    /// Gets the litmus kind of a test judged by this condition.
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Checker => "Required",
+            Self::Exists(_) => "Allowed",
+        }
+    }
+
+    /// Gets whether `state` satisfies this condition's proposition.
+    fn is_positive(&self, state: &model::report::State) -> bool {
+        match self {
+            Self::Checker => state.info.outcome == model::Outcome::Pass,
+            Self::Exists(ms) => ms.iter().any(|m| m.matches(&state.state)),
+        }
+    }
+
+    /// Gets whether the condition holds, given the positive and negative
+    /// occurrence counts.
+    fn is_validated(&self, positive: usize, negative: usize) -> bool {
+        match self {
+            Self::Checker => negative == 0,
+            Self::Exists(_) => positive != 0,
+        }
+    }
+
+    /// Renders this condition in litmus syntax, if it has a known
+    /// proposition.
+    ///
+    /// Checker conditions have no proposition of their own; see
+    /// [`Self::Checker`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use phenolphthalein::{model::interest::{Literal, Matcher}, ux::out::litmus::Condition};
+    ///
+    /// let weak = Matcher([("0:r0".to_owned(), Literal::Int(0)), ("1:r0".to_owned(), Literal::Int(0))].into());
+    /// assert_eq!(Condition::Exists(vec![weak]).to_litmus().unwrap(), "exists (0:r0=0 /\\ 1:r0=0)");
+    /// assert_eq!(Condition::Checker.to_litmus(), None);
+    /// ```
+    #[must_use]
+    pub fn to_litmus(&self) -> Option<String> {
+        match self {
+            Self::Checker => None,
+            Self::Exists(ms) => {
+                let ms: Vec<_> = ms.iter().map(matcher_to_litmus).collect();
+                Some(if ms.len() == 1 {
+                    format!("exists ({})", ms[0])
+                } else {
+                    let ms: Vec<_> = ms.iter().map(|m| format!("({m})")).collect();
+                    format!("exists ({})", ms.join(" \\/ "))
+                })
+            }
+        }
+    }
+}
+
+/// Renders a matcher as a litmus conjunction of equalities.
+fn matcher_to_litmus(m: &interest::Matcher) -> String {
+    if m.0.is_empty() {
+        return "true".to_owned();
+    }
+    let eqs: Vec<_> =
+        m.0.iter()
+            .map(|(var, lit)| {
+                let lit = match lit {
+                    Literal::Bool(b) => u8::from(*b).to_string(),
+                    Literal::Int(i) => i.to_string(),
+                    Literal::Float(f) => f.to_string(),
+                };
+                format!("{var}={lit}")
+            })
+            .collect();
+    eqs.join(" /\\ ")
+}
+
+/// What a litmus log needs to know about tests beyond their reports.
+#[derive(Clone, Debug, Default)]
+pub struct Metadata {
+    /// The name of the test behind a single report; suites name their own
+    /// tests.
+    pub name: Option<String>,
+    /// The condition against which to judge tests.
+    pub condition: Condition,
+}
+
+/// An outputter that emits litmus7-style logs.
+pub struct Litmus<W> {
+    w: W,
+    metadata: Metadata,
+}
+
+impl<W: Write> Outputter for Litmus<W> {
+    fn output(mut self: Box<Self>, report: model::Report) -> err::Result<()> {
+        let name = self.metadata.name.take();
+        self.dump_report(name.as_deref().unwrap_or(DEFAULT_NAME), &report)?;
+        self.w.flush()?;
+        Ok(())
+    }
+
+    fn output_suite(mut self: Box<Self>, suite: model::Suite) -> err::Result<()> {
+        for (i, (name, report)) in suite.reports.iter().enumerate() {
+            if i != 0 {
+                writeln!(self.w)?;
+            }
+            self.dump_report(name, report)?;
+        }
+        self.w.flush()?;
+        Ok(())
+    }
+
+    fn output_inspection(self: Box<Self>, _: model::inspect::Inspection) -> err::Result<()> {
+        Err(err::Error::Unsupported {
+            outputter: string::LITMUS,
+            item: "inspections",
+        })
+    }
+
+    fn output_comparison(self: Box<Self>, _: model::comparison::Comparison) -> err::Result<()> {
+        Err(err::Error::Unsupported {
+            outputter: string::LITMUS,
+            item: "comparisons",
+        })
+    }
+}
+
+impl<W: Write> Litmus<W> {
+    /// Constructs a new litmus writer.
+    pub fn new(w: W) -> Self {
+        Self {
+            w,
+            metadata: Metadata::default(),
+        }
+    }
+
+    /// Sets the test name and condition to use.
+    #[must_use]
+    pub fn with_metadata(self, metadata: Metadata) -> Self {
+        Self { metadata, ..self }
+    }
+
+    fn dump_report(&mut self, name: &str, report: &model::Report) -> std::io::Result<()> {
+        let cond = &self.metadata.condition;
+        writeln!(self.w, "Test {name} {}", cond.kind())?;
+        writeln!(self.w, "Histogram ({} states)", report.states.len())?;
+        let (mut positive, mut negative) = (0usize, 0usize);
+        for s in &report.states {
+            let is_positive = cond.is_positive(s);
+            let count = if is_positive {
+                &mut positive
+            } else {
+                &mut negative
+            };
+            *count = count.saturating_add(s.info.occurs);
+            let sigil = if is_positive { '*' } else { ':' };
+            writeln!(
+                self.w,
+                "{:<6}{sigil}>{}",
+                s.info.occurs,
+                stringify_state(&s.state)
+            )?;
+        }
+        let validated = cond.is_validated(positive, negative);
+        // Checker conditions are what the checker checks, if known.
+        let condition = match (cond, &report.check_description) {
+            (Condition::Checker, Some(description)) => Some(format!("forall ({description})")),
+            _ => cond.to_litmus(),
+        };
+        writeln!(self.w, "{}", if validated { "Ok" } else { "No" })?;
+        writeln!(self.w, "\nWitnesses")?;
+        writeln!(self.w, "Positive: {positive}, Negative: {negative}")?;
+        if let Some(condition) = condition {
+            writeln!(
+                self.w,
+                "Condition {condition} is {}validated",
+                if validated { "" } else { "NOT " }
+            )?;
+        }
+        let observation = match (positive, negative) {
+            (0, _) => "Never",
+            (_, 0) => "Always",
+            _ => "Sometimes",
+        };
+        writeln!(
+            self.w,
+            "Observation {name} {observation} {positive} {negative}"
+        )?;
+        // Litmus tooling skips lines it doesn't know, so this is safe to add.
+        if let Some(cause) = &report.ended_by {
+            writeln!(self.w, "Ended {name} {cause}")?;
+        }
+        if let Some(sampling) = &report.sampling {
+            writeln!(self.w, "Sampled {name} {sampling}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders a state as litmus does: each variable as `name=value;`, with
+/// Booleans as integers.
+fn stringify_state(state: &model::state::State) -> String {
+    let vars: Vec<_> = state
+        .iter()
+        .map(|(var, value)| match value {
+            Value::Bool(b) => format!("{var}={};", u8::from(*b)),
+            v => format!("{var}={v};"),
+        })
+        .collect();
+    vars.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(x: i32, outcome: model::Outcome, occurs: usize) -> model::report::State {
+        let mut info = model::state::Info::new(outcome, 0, 0);
+        info.occurs = occurs;
+        model::report::State {
+            state: [("x".into(), Value::I32(x)), ("y".into(), Value::Bool(true))].into(),
+            info,
+            initials: vec![],
+            interesting: vec![],
+            checks: std::collections::BTreeMap::new(),
+            aux_flags: std::collections::BTreeMap::new(),
+            hash: String::new(),
+        }
+    }
+
+    fn output(metadata: Metadata) -> String {
+        let report = model::Report {
+            states: vec![
+                state(0, model::Outcome::Fail, 3),
+                state(1, model::Outcome::Pass, 5),
+            ],
+            ..model::Report::default()
+        };
+        let mut buf = vec![];
+        Box::new(Litmus::new(&mut buf).with_metadata(metadata))
+            .output(report)
+            .unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    /// Tests the log for a test judged by its checker, which has no
+    /// condition line without a check description.
+    #[test]
+    fn test_output_checker() {
+        let got = output(Metadata {
+            name: Some("SB".to_owned()),
+            condition: Condition::Checker,
+        });
+        assert_eq!(
+            got,
+            "Test SB Required
+Histogram (2 states)
+3     :>x=0; y=1;
+5     *>x=1; y=1;
+No
+
+Witnesses
+Positive: 5, Negative: 3
+Observation SB Sometimes 5 3
+"
+        );
+    }
+
+    /// Tests the log for a test judged by interesting states.
+    #[test]
+    fn test_output_exists() {
+        let weak = interest::Matcher([("x".to_owned(), Literal::Int(0))].into());
+        let got = output(Metadata {
+            name: None,
+            condition: Condition::Exists(vec![weak]),
+        });
+        assert!(got.starts_with("Test test Allowed\n"));
+        assert!(got.contains("3     *>x=0; y=1;\n5     :>x=1; y=1;\nOk\n"));
+        assert!(got.contains("Condition exists (x=0) is validated\n"));
+        assert!(got.ends_with("Observation test Sometimes 3 5\n"));
+    }
+
+    /// Tests that checker conditions use the check description, if known.
+    #[test]
+    fn test_output_check_description() {
+        let report = model::Report {
+            check_description: Some("~(x=0)".to_owned()),
+            ..model::Report::default()
+        };
+        let mut buf = vec![];
+        Box::new(Litmus::new(&mut buf)).output(report).unwrap();
+        let got = String::from_utf8(buf).unwrap();
+        assert!(got.contains("Condition forall (~(x=0)) is validated\n"));
+    }
+
+    /// Tests that logs say why the run ended, if known.
+    #[test]
+    fn test_output_ended_by() {
+        let report = model::Report {
+            ended_by: Some(model::ending::Cause::Iterations { iterations: 8 }),
+            ..model::Report::default()
+        };
+        let mut buf = vec![];
+        Box::new(Litmus::new(&mut buf)).output(report).unwrap();
+        let got = String::from_utf8(buf).unwrap();
+        assert!(got.ends_with("Observation test Never 0 0\nEnded test reached 8 iterations\n"));
+    }
+}
@@ -0,0 +1,192 @@
+//! The registry of outputters that can be chosen by name.
+//!
+//! The registry starts out holding the built-in outputters.  Library users
+//! can register their own, which then parse as [`super::Choice`]s (and so
+//! work in `--output` specs) just as the built-ins do.  Registrations last
+//! for the rest of the process.
+
+#[cfg(feature = "sqlite")]
+use super::sqlite;
+use super::{abs, config::string, dot, err, histo, json, litmus};
+use crate::model::state;
+use std::{
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, OnceLock, PoisonError, RwLock},
+};
+
+crate::features::register! { "outputters" => names() }
+
+/// What outputter factories get to configure their outputters with.
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    /// Display formats for variables, keyed by variable.
+    pub formats: state::Formats,
+    /// The test name and condition for litmus7 logs.
+    pub litmus: litmus::Metadata,
+    /// The optional columns for histograms.
+    pub columns: histo::Columns,
+    /// The order in which to show the variables of states.
+    pub key_order: state::KeyOrder,
+}
+
+/// Type of factories for outputters that output to streams.
+pub type StreamFactory = dyn Fn(Box<dyn Write>, &Context) -> Box<dyn abs::Outputter> + Send + Sync;
+
+/// Type of factories for outputters that append to database files.
+pub type DatabaseFactory = dyn Fn(PathBuf, &Context) -> Box<dyn abs::Outputter> + Send + Sync;
+
+/// A factory for outputters, tagged by the sort of destination they need.
+#[derive(Clone)]
+pub enum Factory {
+    /// The outputters output to streams, such as stdout or a file.
+    Stream(Arc<StreamFactory>),
+    /// The outputters append to the database file at a path.
+    Database(Arc<DatabaseFactory>),
+}
+
+/// A registered outputter.
+#[derive(Clone)]
+pub struct Entry {
+    /// The name under which the outputter is registered.
+    pub name: &'static str,
+    /// The factory for the outputter.
+    pub factory: Factory,
+}
+
+impl Entry {
+    /// Makes an entry for an outputter that outputs to streams.
+    pub fn stream(
+        name: &'static str,
+        factory: impl Fn(Box<dyn Write>, &Context) -> Box<dyn abs::Outputter> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name,
+            factory: Factory::Stream(Arc::new(factory)),
+        }
+    }
+
+    /// Makes an entry for an outputter that appends to database files.
+    pub fn database(
+        name: &'static str,
+        factory: impl Fn(PathBuf, &Context) -> Box<dyn abs::Outputter> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name,
+            factory: Factory::Database(Arc::new(factory)),
+        }
+    }
+
+    /// Gets whether this entry's outputters append to database files.
+    #[must_use]
+    pub fn is_database(&self) -> bool {
+        matches!(self.factory, Factory::Database(_))
+    }
+}
+
+/// Registers `entry`, making it selectable by its name.
+///
+/// Names are matched case-insensitively against lower-case input, so should
+/// be lower case themselves.
+///
+/// # Errors
+///
+/// Fails if there is already an outputter with the same name.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::ux::out::{self, registry};
+///
+/// registry::register(registry::Entry::stream("quiet", |w, context| {
+///     Box::new(out::json::Json::new(w).with_formats(context.formats.clone()))
+/// }))
+/// .unwrap();
+/// let choice: out::Choice = "quiet".parse().unwrap();
+/// assert_eq!(choice.name(), "quiet");
+/// assert!(registry::stream_names().contains(&"quiet"));
+///
+/// // Names are unique, and the built-ins are already registered.
+/// assert!(registry::register(registry::Entry::stream("json", |w, _| {
+///     Box::new(out::json::Json::new(w))
+/// }))
+/// .is_err());
+/// ```
+pub fn register(entry: Entry) -> err::Result<()> {
+    let mut entries = registry().write().unwrap_or_else(PoisonError::into_inner);
+    if entries.iter().any(|e| e.name == entry.name) {
+        return Err(err::Error::DuplicateOutputter(entry.name.to_owned()));
+    }
+    entries.push(entry);
+    Ok(())
+}
+
+/// Looks up the outputter registered under `name`, if any.
+#[must_use]
+pub fn lookup(name: &str) -> Option<Entry> {
+    let entries = registry().read().unwrap_or_else(PoisonError::into_inner);
+    entries.iter().find(|e| e.name == name).cloned()
+}
+
+/// Gets the names of every registered outputter, in registration order.
+#[must_use]
+pub fn names() -> Vec<&'static str> {
+    names_where(|_| true)
+}
+
+/// Gets the names of every registered outputter that can output to a
+/// stream, such as stdout, in registration order.
+#[must_use]
+pub fn stream_names() -> Vec<&'static str> {
+    names_where(|e| !e.is_database())
+}
+
+fn names_where(f: impl Fn(&Entry) -> bool) -> Vec<&'static str> {
+    let entries = registry().read().unwrap_or_else(PoisonError::into_inner);
+    entries.iter().filter(|e| f(e)).map(|e| e.name).collect()
+}
+
+fn registry() -> &'static RwLock<Vec<Entry>> {
+    static REGISTRY: OnceLock<RwLock<Vec<Entry>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(builtins()))
+}
+
+/// Gets the entry for the histogram outputter, which is the default.
+pub(super) fn histogram() -> Entry {
+    Entry::stream(string::HISTOGRAM, |w, context| {
+        Box::new(
+            histo::Histogram::new(w)
+                .with_formats(context.formats.clone())
+                .with_columns(context.columns)
+                .with_key_order(context.key_order),
+        )
+    })
+}
+
+/// Gets the entries for the built-in outputters.
+fn builtins() -> Vec<Entry> {
+    vec![
+        histogram(),
+        Entry::stream(string::JSON, |w, context| {
+            Box::new(
+                json::Json::new(w)
+                    .with_formats(context.formats.clone())
+                    .with_key_order(context.key_order),
+            )
+        }),
+        Entry::stream(string::LITMUS, |w, context| {
+            Box::new(litmus::Litmus::new(w).with_metadata(context.litmus.clone()))
+        }),
+        Entry::stream(string::DOT, |w, context| {
+            Box::new(
+                dot::Dot::new(w)
+                    .with_formats(context.formats.clone())
+                    .with_key_order(context.key_order),
+            )
+        }),
+        #[cfg(feature = "sqlite")]
+        Entry::database(string::SQLITE, |path, _| {
+            Box::new(sqlite::Sqlite::new(path))
+        }),
+    ]
+}
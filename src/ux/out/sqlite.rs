@@ -0,0 +1,627 @@
+//! The SQLite outputter, which appends reports to a result database.
+//!
+//! Unlike the other outputters, this one writes to a database file rather
+//! than a stream, and keeps what was there before: each report becomes a new
+//! row in `runs`, with one row per observed state in `states` and any
+//! descriptive key-value pairs in `metadata`.  Each run also keeps its full
+//! report as JSON, so nothing is lost to the relational schema.
+//!
+//! We talk to the system's SQLite library directly, through the handful of
+//! functions we need; enabling the `sqlite` feature links against it.
+
+use super::{abs::Outputter, config::string, err};
+use crate::model;
+use std::{
+    ffi::{CStr, CString},
+    os::raw::c_int,
+    path::PathBuf,
+    ptr::{self, NonNull},
+    time::SystemTime,
+};
+
+/// The version of the database schema that this outputter writes.
+///
+/// We store this in SQLite's `user_version`, and refuse to append to
+/// databases written by later versions.
+pub const SCHEMA_VERSION: i64 = 1;
+
+/// The schema, created if not already present.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    recorded_at INTEGER NOT NULL,
+    test TEXT,
+    outcome TEXT,
+    iterations INTEGER NOT NULL,
+    distinct_states INTEGER NOT NULL,
+    report TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS states (
+    run INTEGER NOT NULL REFERENCES runs (id),
+    state TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    occurs INTEGER NOT NULL,
+    first_iteration INTEGER NOT NULL,
+    last_iteration INTEGER NOT NULL,
+    rotations INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS metadata (
+    run INTEGER NOT NULL REFERENCES runs (id),
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (run, key)
+);
+CREATE INDEX IF NOT EXISTS states_by_run ON states (run);
+";
+
+/// How long, in milliseconds, to wait for other writers to release the
+/// database before giving up.
+const BUSY_TIMEOUT_MS: c_int = 5000;
+
+/// An outputter that appends reports to a SQLite database.
+///
+/// The database file is created if it doesn't exist.  Variable display
+/// formats don't apply, as the database stores the values themselves.
+pub struct Sqlite {
+    /// The path to the database file.
+    path: PathBuf,
+}
+
+impl Sqlite {
+    /// Constructs an outputter appending to the database at `path`.
+    #[must_use]
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Opens the database and inserts `reports`, each with an optional test
+    /// name, in one transaction.
+    fn insert_all<'a>(
+        &self,
+        reports: impl IntoIterator<Item = (Option<&'a str>, &'a model::Report)>,
+    ) -> err::Result<()> {
+        let db = Database::open(&self.path)?;
+        db.execute_batch("BEGIN IMMEDIATE")?;
+        let recorded_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| to_i64(d.as_secs()));
+        for (test, report) in reports {
+            insert(&db, recorded_at, test, report)?;
+        }
+        db.execute_batch("COMMIT")
+    }
+}
+
+impl Outputter for Sqlite {
+    fn output(self: Box<Self>, r: model::Report) -> err::Result<()> {
+        self.insert_all(std::iter::once((None, &r)))
+    }
+
+    fn output_suite(self: Box<Self>, s: model::Suite) -> err::Result<()> {
+        self.insert_all(s.reports.iter().map(|(name, r)| (Some(name.as_str()), r)))
+    }
+
+    fn output_inspection(self: Box<Self>, _: model::inspect::Inspection) -> err::Result<()> {
+        Err(err::Error::Unsupported {
+            outputter: string::SQLITE,
+            item: "inspections",
+        })
+    }
+
+    fn output_comparison(self: Box<Self>, _: model::comparison::Comparison) -> err::Result<()> {
+        Err(err::Error::Unsupported {
+            outputter: string::SQLITE,
+            item: "comparisons",
+        })
+    }
+}
+
+/// Inserts `report` as a new run, along with its states and metadata.
+fn insert(
+    db: &Database,
+    recorded_at: i64,
+    test: Option<&str>,
+    report: &model::Report,
+) -> err::Result<()> {
+    let iterations = report
+        .states
+        .iter()
+        .fold(0usize, |n, s| n.saturating_add(s.info.occurs));
+    let outcome = report.outcome.map(|o| o.to_string());
+    db.prepare(
+        "INSERT INTO runs (recorded_at, test, outcome, iterations, distinct_states, report)
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )?
+    .execute(&[
+        Param::Int(recorded_at),
+        test.into(),
+        outcome.as_deref().into(),
+        Param::Int(to_i64(iterations)),
+        Param::Int(to_i64(report.states.len())),
+        Param::Text(&serde_json::to_string(report)?),
+    ])?;
+    let run = Param::Int(db.last_insert_rowid());
+
+    let mut states = db.prepare(
+        "INSERT INTO states
+         (run, state, outcome, occurs, first_iteration, last_iteration, rotations)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )?;
+    for s in &report.states {
+        states.execute(&[
+            run,
+            Param::Text(&serde_json::to_string(&s.state)?),
+            Param::Text(&s.info.outcome.to_string()),
+            Param::Int(to_i64(s.info.occurs)),
+            Param::Int(to_i64(s.info.iteration)),
+            Param::Int(to_i64(s.info.last_iteration)),
+            Param::Int(to_i64(s.info.rotations)),
+        ])?;
+    }
+
+    let mut metadata = db.prepare("INSERT INTO metadata (run, key, value) VALUES (?, ?, ?)")?;
+    for (key, value) in metadata_of(report) {
+        metadata.execute(&[run, Param::Text(key), Param::Text(&value)])?;
+    }
+    Ok(())
+}
+
+/// Key-value metadata recorded for a report.
+type Metadata = Vec<(&'static str, String)>;
+
+/// Gets the key-value metadata to record for `report`.
+fn metadata_of(report: &model::Report) -> Metadata {
+    let mut m = vec![("version", env!("CARGO_PKG_VERSION").to_owned())];
+    push_run_options(&mut m, report);
+    push_breakdowns(&mut m, report);
+    if let Some(p) = &report.platform {
+        push_platform(&mut m, p);
+    }
+    if let Some(p) = &report.provenance {
+        push_provenance(&mut m, p);
+    }
+    m
+}
+
+/// Records the options and outcomes of the run that made `report`.
+fn push_run_options(m: &mut Metadata, report: &model::Report) {
+    if let Some(description) = &report.check_description {
+        m.push(("check_description", description.clone()));
+    }
+    if let Some(cause) = &report.ended_by {
+        m.push(("ended_by", cause.to_string()));
+    }
+    if let Some(repro) = &report.reproduction {
+        m.push(("reproduction", repro.args().join(" ")));
+    }
+    if let Some(sampling) = &report.sampling {
+        m.push(("sampling", sampling.to_string()));
+    }
+    if !report.states_hash.is_empty() {
+        m.push(("states_hash", report.states_hash.clone()));
+    }
+    if !report.masked.is_empty() {
+        m.push(("masked", report.masked.join(",")));
+    }
+    if !report.var_order.is_empty() {
+        m.push(("var_order", report.var_order.join(",")));
+    }
+    if let Some(cpus) = &report.cpus {
+        m.push(("cpus", cpus.to_string()));
+    }
+    if let Some(tally) = &report.outcomes {
+        m.push(("outcomes", tally.to_string()));
+    }
+    if !report.tainted.is_empty() {
+        let taints: Vec<_> = report.tainted.iter().map(|t| t.cause.to_string()).collect();
+        m.push(("tainted", taints.join("; ")));
+    }
+}
+
+/// Records, as JSON, the breakdowns of `report` that don't fit the schema.
+fn push_breakdowns(m: &mut Metadata, report: &model::Report) {
+    if !report.folded.is_empty() {
+        push_json(m, "folded", &report.folded);
+    }
+    if let Some(alternation) = &report.alternation {
+        push_json(m, "alternation", alternation);
+    }
+    if !report.rotations.is_empty() {
+        push_json(m, "rotations", &report.rotations);
+    }
+    if !report.examples.is_empty() {
+        push_json(m, "examples", &report.examples);
+    }
+    if !report.transitions.is_empty() {
+        push_json(m, "transitions", &report.transitions);
+    }
+    if !report.projections.is_empty() {
+        push_json(m, "projections", &report.projections);
+    }
+    if !report.orderings.is_empty() {
+        push_json(m, "orderings", &report.orderings);
+    }
+    if !report.roles.is_empty() {
+        push_json(m, "roles", &report.roles);
+    }
+    if !report.aggregate.is_empty() {
+        push_json(m, "aggregate", &report.aggregate);
+    }
+    if !report.timing.is_empty() {
+        push_json(m, "timing", &report.timing);
+    }
+    if let Some(audit) = &report.allocations {
+        push_json(m, "allocations", audit);
+    }
+}
+
+/// Records `value` as JSON under `key`, skipping it if it won't serialise.
+fn push_json(m: &mut Metadata, key: &'static str, value: &impl serde::Serialize) {
+    if let Ok(json) = serde_json::to_string(value) {
+        m.push((key, json));
+    }
+}
+
+/// Records the platform on which the run happened.
+fn push_platform(m: &mut Metadata, p: &model::Platform) {
+    m.push(("platform.os", p.os.clone()));
+    if !p.family.is_empty() {
+        m.push(("platform.family", p.family.clone()));
+    }
+    m.push(("platform.arch", p.arch.clone()));
+    m.push(("platform.cpus", p.cpus.to_string()));
+    if let Some(t) = &p.topology {
+        if let Some(model) = &t.model {
+            m.push(("platform.model", model.clone()));
+        }
+        m.push(("platform.packages", t.packages.to_string()));
+        m.push(("platform.cores", t.cores.to_string()));
+        m.push((
+            "platform.threads_per_core",
+            t.threads_per_core().to_string(),
+        ));
+    }
+}
+
+/// Records where the run came from.
+fn push_provenance(m: &mut Metadata, p: &model::provenance::Provenance) {
+    m.push(("provenance.run_id", p.run_id.clone()));
+    m.push(("provenance.version", p.version.clone()));
+    if let Some(allocator) = &p.allocator {
+        m.push(("provenance.allocator", allocator.clone()));
+    }
+    if let Some(seed) = p.seed {
+        m.push(("provenance.seed", seed.to_string()));
+    }
+    if let Some(input) = &p.input {
+        m.push(("provenance.input.path", input.path.clone()));
+        m.push(("provenance.input.sha256", input.sha256.clone()));
+    }
+    if let Some(config) = &p.config {
+        m.push(("provenance.config", config.clone()));
+    }
+}
+
+/// Converts a count to a SQLite integer, saturating.
+fn to_i64(n: impl TryInto<i64>) -> i64 {
+    n.try_into().unwrap_or(i64::MAX)
+}
+
+/// A parameter to a SQL statement.
+#[derive(Clone, Copy)]
+enum Param<'a> {
+    /// An integer.
+    Int(i64),
+    /// A string.
+    Text(&'a str),
+    /// A null.
+    Null,
+}
+
+impl<'a> From<Option<&'a str>> for Param<'a> {
+    fn from(s: Option<&'a str>) -> Self {
+        s.map_or(Self::Null, Self::Text)
+    }
+}
+
+/// An open connection to a SQLite database.
+struct Database(NonNull<ffi::Sqlite3>);
+
+impl Database {
+    /// Opens, creating if needed, the database at `path`, making sure it has
+    /// our schema.
+    fn open(path: &std::path::Path) -> err::Result<Self> {
+        let name = CString::new(path.as_os_str().as_encoded_bytes())
+            .map_err(|_| err::Error::BadDatabasePath(path.to_owned()))?;
+        let mut handle = ptr::null_mut();
+        // SAFETY: `name` is a valid C string, and SQLite sets `handle` to a
+        // connection (even on failure, so that we can get the message).
+        let code = unsafe {
+            ffi::sqlite3_open_v2(
+                name.as_ptr(),
+                &raw mut handle,
+                ffi::OPEN_READWRITE | ffi::OPEN_CREATE,
+                ptr::null(),
+            )
+        };
+        let db = Self(NonNull::new(handle).ok_or(err::Error::Sqlite {
+            code,
+            message: "out of memory".to_owned(),
+        })?);
+        db.check(code)?;
+        // SAFETY: the connection is open.
+        db.check(unsafe { ffi::sqlite3_busy_timeout(db.0.as_ptr(), BUSY_TIMEOUT_MS) })?;
+
+        let version = db.prepare("PRAGMA user_version")?.query_i64()?;
+        if SCHEMA_VERSION < version {
+            return Err(err::Error::DatabaseTooNew(version));
+        }
+        db.execute_batch(SCHEMA)?;
+        db.execute_batch(&format!("PRAGMA user_version = {SCHEMA_VERSION}"))?;
+        Ok(db)
+    }
+
+    /// Executes `sql`, which may contain several statements.
+    fn execute_batch(&self, sql: &str) -> err::Result<()> {
+        let sql = CString::new(sql).map_err(|_| err::Error::Sqlite {
+            code: ffi::MISUSE,
+            message: "SQL contains a null byte".to_owned(),
+        })?;
+        // SAFETY: the connection is open and `sql` is a valid C string; we
+        // pass no callback and ask for no error string.
+        let code = unsafe {
+            ffi::sqlite3_exec(
+                self.0.as_ptr(),
+                sql.as_ptr(),
+                ptr::null(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        self.check(code)
+    }
+
+    /// Prepares the single statement `sql`.
+    fn prepare(&self, sql: &str) -> err::Result<Statement<'_>> {
+        let len = c_int::try_from(sql.len()).map_err(|_| too_big())?;
+        let mut stmt = ptr::null_mut();
+        // SAFETY: the connection is open, and `sql` is valid for `len` bytes.
+        let code = unsafe {
+            ffi::sqlite3_prepare_v2(
+                self.0.as_ptr(),
+                sql.as_ptr().cast(),
+                len,
+                &raw mut stmt,
+                ptr::null_mut(),
+            )
+        };
+        self.check(code)?;
+        let stmt = NonNull::new(stmt).ok_or(err::Error::Sqlite {
+            code: ffi::MISUSE,
+            message: "empty SQL statement".to_owned(),
+        })?;
+        Ok(Statement { db: self, stmt })
+    }
+
+    /// Gets the row ID of the most recent insertion.
+    fn last_insert_rowid(&self) -> i64 {
+        // SAFETY: the connection is open.
+        unsafe { ffi::sqlite3_last_insert_rowid(self.0.as_ptr()) }
+    }
+
+    /// Turns a non-OK result code into an error with the connection's
+    /// message.
+    fn check(&self, code: c_int) -> err::Result<()> {
+        if code == ffi::OK {
+            return Ok(());
+        }
+        // SAFETY: the connection is open, and SQLite owns the message, which
+        // we copy before doing anything else with the connection.
+        let message = unsafe { CStr::from_ptr(ffi::sqlite3_errmsg(self.0.as_ptr())) };
+        Err(err::Error::Sqlite {
+            code,
+            message: message.to_string_lossy().into_owned(),
+        })
+    }
+}
+
+/// Makes the error for strings too long to pass to SQLite.
+fn too_big() -> err::Error {
+    err::Error::Sqlite {
+        code: ffi::TOOBIG,
+        message: "string or statement too big".to_owned(),
+    }
+}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        // SAFETY: every statement borrows the connection, so all have been
+        // finalised by now.
+        unsafe {
+            ffi::sqlite3_close(self.0.as_ptr());
+        }
+    }
+}
+
+/// A prepared statement.
+struct Statement<'db> {
+    db: &'db Database,
+    stmt: NonNull<ffi::Stmt>,
+}
+
+impl Statement<'_> {
+    /// Runs the statement to completion with `params`.
+    fn execute(&mut self, params: &[Param]) -> err::Result<()> {
+        self.bind(params)?;
+        match self.step() {
+            ffi::DONE | ffi::ROW => Ok(()),
+            code => self.db.check(code),
+        }
+    }
+
+    /// Runs the statement, which takes no parameters, and gets the first
+    /// column of its first row as an integer.
+    fn query_i64(&mut self) -> err::Result<i64> {
+        self.bind(&[])?;
+        match self.step() {
+            // SAFETY: the statement has a row ready.
+            ffi::ROW => Ok(unsafe { ffi::sqlite3_column_int64(self.stmt.as_ptr(), 0) }),
+            ffi::DONE => Ok(0),
+            code => self.db.check(code).map(|()| 0),
+        }
+    }
+
+    fn bind(&mut self, params: &[Param]) -> err::Result<()> {
+        let stmt = self.stmt.as_ptr();
+        // SAFETY: the statement is live; resetting it lets us rebind it.
+        unsafe { ffi::sqlite3_reset(stmt) };
+        for (i, p) in (1..).zip(params) {
+            // SAFETY: the statement is live, and SQLite copies any text.
+            let code = match *p {
+                Param::Int(n) => unsafe { ffi::sqlite3_bind_int64(stmt, i, n) },
+                Param::Null => unsafe { ffi::sqlite3_bind_null(stmt, i) },
+                Param::Text(s) => {
+                    let len = c_int::try_from(s.len()).map_err(|_| too_big())?;
+                    unsafe {
+                        ffi::sqlite3_bind_text(stmt, i, s.as_ptr().cast(), len, ffi::TRANSIENT)
+                    }
+                }
+            };
+            self.db.check(code)?;
+        }
+        Ok(())
+    }
+
+    fn step(&mut self) -> c_int {
+        // SAFETY: the statement is live and bound.
+        unsafe { ffi::sqlite3_step(self.stmt.as_ptr()) }
+    }
+}
+
+impl Drop for Statement<'_> {
+    fn drop(&mut self) {
+        // SAFETY: the statement is live, and isn't used again.
+        unsafe {
+            ffi::sqlite3_finalize(self.stmt.as_ptr());
+        }
+    }
+}
+
+/// Bindings to the parts of the SQLite C API that we use.
+mod ffi {
+    use std::os::raw::{c_char, c_int, c_void};
+
+    /// An opaque SQLite connection.
+    #[repr(C)]
+    pub struct Sqlite3 {
+        _private: [u8; 0],
+    }
+
+    /// An opaque SQLite prepared statement.
+    #[repr(C)]
+    pub struct Stmt {
+        _private: [u8; 0],
+    }
+
+    pub const OK: c_int = 0;
+    pub const MISUSE: c_int = 21;
+    pub const TOOBIG: c_int = 18;
+    pub const ROW: c_int = 100;
+    pub const DONE: c_int = 101;
+
+    pub const OPEN_READWRITE: c_int = 0x2;
+    pub const OPEN_CREATE: c_int = 0x4;
+
+    /// `SQLITE_TRANSIENT`, the destructor telling SQLite to copy bound data.
+    ///
+    /// It's the function pointer `-1`, which we pass as an integer of the
+    /// same width.
+    pub const TRANSIENT: isize = -1;
+
+    #[link(name = "sqlite3")]
+    extern "C" {
+        pub fn sqlite3_open_v2(
+            filename: *const c_char,
+            db: *mut *mut Sqlite3,
+            flags: c_int,
+            vfs: *const c_char,
+        ) -> c_int;
+        pub fn sqlite3_close(db: *mut Sqlite3) -> c_int;
+        pub fn sqlite3_busy_timeout(db: *mut Sqlite3, ms: c_int) -> c_int;
+        pub fn sqlite3_errmsg(db: *mut Sqlite3) -> *const c_char;
+        pub fn sqlite3_exec(
+            db: *mut Sqlite3,
+            sql: *const c_char,
+            callback: *const c_void,
+            arg: *mut c_void,
+            errmsg: *mut *mut c_char,
+        ) -> c_int;
+        pub fn sqlite3_prepare_v2(
+            db: *mut Sqlite3,
+            sql: *const c_char,
+            len: c_int,
+            stmt: *mut *mut Stmt,
+            tail: *mut *const c_char,
+        ) -> c_int;
+        pub fn sqlite3_bind_int64(stmt: *mut Stmt, index: c_int, value: i64) -> c_int;
+        pub fn sqlite3_bind_null(stmt: *mut Stmt, index: c_int) -> c_int;
+        pub fn sqlite3_bind_text(
+            stmt: *mut Stmt,
+            index: c_int,
+            value: *const c_char,
+            len: c_int,
+            destructor: isize,
+        ) -> c_int;
+        pub fn sqlite3_step(stmt: *mut Stmt) -> c_int;
+        pub fn sqlite3_reset(stmt: *mut Stmt) -> c_int;
+        pub fn sqlite3_finalize(stmt: *mut Stmt) -> c_int;
+        pub fn sqlite3_column_int64(stmt: *mut Stmt, column: c_int) -> i64;
+        pub fn sqlite3_last_insert_rowid(db: *mut Sqlite3) -> i64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{state, Outcome};
+
+    fn count(db: &Database, sql: &str) -> i64 {
+        db.prepare(sql).unwrap().query_i64().unwrap()
+    }
+
+    /// Tests that reports append to the database across outputs.
+    #[test]
+    fn test_output_appends() -> err::Result<()> {
+        let path = std::env::temp_dir().join(format!("phph-sqlite-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut report = model::Report::default();
+        for x in 0..2 {
+            report.insert(model::report::State {
+                state: state::State::from([("x".into(), state::Value::I32(x))]),
+                info: state::Info::new(Outcome::Pass, 0, 0),
+                initials: vec![],
+                interesting: vec![],
+                checks: std::collections::BTreeMap::new(),
+                aux_flags: std::collections::BTreeMap::new(),
+                hash: String::new(),
+            });
+        }
+        report.masked = vec!["r0".to_owned()];
+        Box::new(Sqlite::new(path.clone())).output(report.clone())?;
+        Box::new(Sqlite::new(path.clone())).output(report)?;
+
+        let db = Database::open(&path)?;
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM runs"), 2);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM states"), 4);
+        assert_eq!(
+            count(&db, "SELECT COUNT(*) FROM metadata WHERE key = 'masked'"),
+            2
+        );
+        assert_eq!(count(&db, "SELECT MAX(iterations) FROM runs"), 2);
+        assert_eq!(count(&db, "PRAGMA user_version"), SCHEMA_VERSION);
+        drop(db);
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,370 @@
+//! Live streaming of run progress and reports over a local socket.
+//!
+//! A [Server] listens on a TCP or Unix socket, and sends every client that
+//! attaches a stream of messages as tests run: progress samples every so
+//! many iterations, the report of each test that finishes, and an end
+//! marker once the whole run is over.  Clients can attach at any time, and
+//! only see messages sent after they attach.
+//!
+//! Each message is a JSON object (see [Message]) preceded by its length in
+//! bytes, as a 32-bit big-endian integer.  Clients that can't keep up, or go
+//! away, are dropped rather than holding up the test.
+
+use crate::{model, run::obs};
+use serde::Serialize;
+use std::{
+    io::{self, Write},
+    net::{TcpListener, TcpStream},
+    num::NonZeroUsize,
+    str::FromStr,
+    sync::Mutex,
+    time::Duration,
+};
+
+/// The default number of iterations between progress samples.
+pub const DEFAULT_EVERY: NonZeroUsize = NonZeroUsize::new(10_000).unwrap();
+
+/// How long a write to one client can take before we drop the client.
+const WRITE_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// An address on which to serve.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Address {
+    /// A TCP address, as `host:port`.
+    Tcp(String),
+    /// The path of a Unix domain socket.
+    Unix(std::path::PathBuf),
+}
+
+/// Parses an address of the form `tcp:HOST:PORT` or `unix:PATH`.
+///
+/// # Errors
+///
+/// Fails, returning the input, if it has neither prefix.
+///
+/// # Examples
+///
+/// ```
+/// use phenolphthalein::ux::serve::Address;
+/// assert_eq!(
+///     "unix:/tmp/phph.sock".parse::<Address>().unwrap(),
+///     Address::Unix("/tmp/phph.sock".into())
+/// );
+/// assert_eq!(
+///     "tcp:127.0.0.1:4000".parse::<Address>().unwrap(),
+///     Address::Tcp("127.0.0.1:4000".to_owned())
+/// );
+/// assert!("http://localhost".parse::<Address>().is_err());
+/// ```
+impl FromStr for Address {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("tcp", addr)) if !addr.is_empty() => Ok(Self::Tcp(addr.to_owned())),
+            Some(("unix", path)) if !path.is_empty() => Ok(Self::Unix(path.into())),
+            _ => Err(s.to_owned()),
+        }
+    }
+}
+
+/// Addresses display in the form they parse from.
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "tcp:{addr}"),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// A message sent to clients.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message<'a> {
+    /// A sample of a test's progress.
+    Progress {
+        /// The name of the test.
+        test: &'a str,
+        /// The number of iterations observed so far.
+        iterations: usize,
+        /// The number of distinct states observed so far.
+        distinct_states: usize,
+        /// The number of interesting states not yet seen.
+        interesting_left: usize,
+        /// The recent iteration rate, if the run is measuring one.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        iterations_per_sec: Option<f64>,
+    },
+    /// The final report of a test.
+    Report {
+        /// The name of the test.
+        test: &'a str,
+        /// The report.
+        report: &'a model::Report,
+    },
+    /// The end of the run; no more messages follow.
+    End,
+}
+
+/// A server streaming messages to every attached client.
+pub struct Server {
+    every: NonZeroUsize,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    listener: Listener,
+    clients: Vec<Stream>,
+    /// The buffer into which messages are serialised.
+    buf: Vec<u8>,
+}
+
+impl Server {
+    /// Starts serving on `address`, sampling progress every `every`
+    /// iterations.
+    ///
+    /// # Errors
+    ///
+    /// Fails if we can't bind to the address; Unix sockets aren't available
+    /// on every platform, and their paths mustn't already exist.
+    pub fn bind(address: &Address, every: NonZeroUsize) -> io::Result<Self> {
+        let listener = Listener::bind(address)?;
+        Ok(Self {
+            every,
+            inner: Mutex::new(Inner {
+                listener,
+                clients: vec![],
+                buf: vec![],
+            }),
+        })
+    }
+
+    /// Gets the address on which the server is listening.
+    ///
+    /// This differs from the address it was bound to if that was a TCP
+    /// address with port 0.
+    ///
+    /// # Errors
+    ///
+    /// Fails if we can't get the address of a TCP listener.
+    pub fn address(&self) -> io::Result<Address> {
+        let inner = self.lock()?;
+        match &inner.listener {
+            Listener::Tcp(l) => Ok(Address::Tcp(l.local_addr()?.to_string())),
+            #[cfg(unix)]
+            Listener::Unix(_, path) => Ok(Address::Unix(path.clone())),
+        }
+    }
+
+    /// Sends a progress sample for the test named `test`, if `summary` falls
+    /// on a sampling iteration.
+    pub fn record(&self, test: &str, summary: &obs::Summary) {
+        if !summary.iterations.is_multiple_of(self.every.get()) {
+            return;
+        }
+        self.send(&Message::Progress {
+            test,
+            iterations: summary.iterations,
+            distinct_states: summary.distinct_states,
+            interesting_left: summary.interesting_left,
+            iterations_per_sec: summary.rate.map(|r| r.per_sec()),
+        });
+    }
+
+    /// Sends the final report of the test named `test`.
+    pub fn report(&self, test: &str, report: &model::Report) {
+        self.send(&Message::Report { test, report });
+    }
+
+    /// Tells clients that the run is over.
+    pub fn finish(&self) {
+        self.send(&Message::End);
+    }
+
+    /// Sends `message` to every client, first attaching any new clients.
+    ///
+    /// Clients that fail to take the message are dropped.
+    pub fn send(&self, message: &Message) {
+        let Ok(mut inner) = self.lock() else {
+            return;
+        };
+        let inner = &mut *inner;
+        inner.accept();
+        if inner.clients.is_empty() {
+            return;
+        }
+        inner.buf.clear();
+        inner.buf.extend_from_slice(&[0; 4]);
+        if serde_json::to_writer(&mut inner.buf, message).is_err() {
+            return;
+        }
+        let Ok(len) = u32::try_from(inner.buf.len() - 4) else {
+            return;
+        };
+        inner.buf[..4].copy_from_slice(&len.to_be_bytes());
+        let buf = &inner.buf;
+        inner
+            .clients
+            .retain_mut(|c| c.write_all(buf).and_then(|()| c.flush()).is_ok());
+    }
+
+    fn lock(&self) -> io::Result<std::sync::MutexGuard<'_, Inner>> {
+        self.inner
+            .lock()
+            .map_err(|_| io::Error::other("server poisoned"))
+    }
+}
+
+impl Inner {
+    /// Attaches every client waiting to connect.
+    fn accept(&mut self) {
+        while let Some(client) = self.listener.accept() {
+            self.clients.push(client);
+        }
+    }
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixListener, std::path::PathBuf),
+}
+
+impl Listener {
+    fn bind(address: &Address) -> io::Result<Self> {
+        let listener = match address {
+            Address::Tcp(addr) => Self::Tcp(TcpListener::bind(addr)?),
+            #[cfg(unix)]
+            Address::Unix(path) => {
+                Self::Unix(std::os::unix::net::UnixListener::bind(path)?, path.clone())
+            }
+            #[cfg(not(unix))]
+            Address::Unix(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Unix sockets aren't available on this platform",
+                ))
+            }
+        };
+        // We poll for clients whenever we send, rather than waiting on them.
+        match &listener {
+            Self::Tcp(l) => l.set_nonblocking(true)?,
+            #[cfg(unix)]
+            Self::Unix(l, _) => l.set_nonblocking(true)?,
+        }
+        Ok(listener)
+    }
+
+    /// Accepts a waiting client, if there is one.
+    ///
+    /// Clients that connect but can't be set up are ignored.
+    fn accept(&self) -> Option<Stream> {
+        match self {
+            Self::Tcp(l) => {
+                let (s, _) = l.accept().ok()?;
+                s.set_nonblocking(false).ok()?;
+                s.set_write_timeout(Some(WRITE_TIMEOUT)).ok()?;
+                Some(Stream::Tcp(s))
+            }
+            #[cfg(unix)]
+            Self::Unix(l, _) => {
+                let (s, _) = l.accept().ok()?;
+                s.set_nonblocking(false).ok()?;
+                s.set_write_timeout(Some(WRITE_TIMEOUT)).ok()?;
+                Some(Stream::Unix(s))
+            }
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Self::Unix(_, path) = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixStream),
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(s) => s.write(buf),
+            #[cfg(unix)]
+            Self::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Tcp(s) => s.flush(),
+            #[cfg(unix)]
+            Self::Unix(s) => s.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{state::Info, Outcome};
+    use std::io::Read;
+
+    fn summary(iterations: usize) -> obs::Summary {
+        obs::Summary {
+            iterations,
+            rotation_iterations: iterations,
+            rotation_elapsed: Duration::ZERO,
+            distinct_states: 2,
+            interesting_left: 0,
+            rate: None,
+            info: Some(Info::new(Outcome::Pass, 0, 0)),
+        }
+    }
+
+    fn read_message(r: &mut impl Read) -> io::Result<serde_json::Value> {
+        let mut len = [0; 4];
+        r.read_exact(&mut len)?;
+        let mut buf = vec![0; u32::from_be_bytes(len) as usize];
+        r.read_exact(&mut buf)?;
+        Ok(serde_json::from_slice(&buf)?)
+    }
+
+    /// Tests that attached clients get framed progress samples, reports, and
+    /// an end marker.
+    #[test]
+    fn test_serve_tcp() -> io::Result<()> {
+        let every = NonZeroUsize::new(2).unwrap();
+        let server = Server::bind(&Address::Tcp("127.0.0.1:0".to_owned()), every)?;
+        let Address::Tcp(addr) = server.address()? else {
+            unreachable!("bound to TCP")
+        };
+        // Nobody is listening yet, so this goes nowhere.
+        server.record("sb", &summary(2));
+
+        let mut client = TcpStream::connect(addr)?;
+        for i in 3..=4 {
+            server.record("sb", &summary(i));
+        }
+        server.report("sb", &model::Report::default());
+        server.finish();
+
+        let progress = read_message(&mut client)?;
+        assert_eq!(progress["type"], "progress");
+        assert_eq!(progress["test"], "sb");
+        assert_eq!(progress["iterations"], 4);
+        assert_eq!(progress["distinct_states"], 2);
+        let report = read_message(&mut client)?;
+        assert_eq!(report["type"], "report");
+        assert!(report["report"]["states"].is_array());
+        assert_eq!(read_message(&mut client)?["type"], "end");
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+//! Watching test libraries for changes, for the `--watch` mode of `phph run`.
+//!
+//! We have no filesystem notification, so we poll modification times; this
+//! is cheap at the rates a person rebuilds a test.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// The default interval between polls of the watched files.
+pub const DEFAULT_PERIOD: Duration = Duration::from_millis(250);
+
+/// A watcher over a set of files.
+pub struct Watcher {
+    /// The watched files.
+    paths: Vec<PathBuf>,
+    /// The last modification times seen for each file, if it existed.
+    stamps: Vec<Option<SystemTime>>,
+    /// The interval between polls.
+    period: Duration,
+}
+
+impl Watcher {
+    /// Starts watching `paths`, taking their current state as unchanged.
+    #[must_use]
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let stamps = paths.iter().map(|p| stamp(p)).collect();
+        Self {
+            paths,
+            stamps,
+            period: DEFAULT_PERIOD,
+        }
+    }
+
+    /// Sets the interval between polls.
+    #[must_use]
+    pub fn with_period(self, period: Duration) -> Self {
+        Self { period, ..self }
+    }
+
+    /// Gets whether any watched file has changed since the last time we took
+    /// their state as unchanged.
+    #[must_use]
+    pub fn has_changed(&self) -> bool {
+        self.paths
+            .iter()
+            .map(|p| stamp(p))
+            .ne(self.stamps.iter().copied())
+    }
+
+    /// Blocks until a watched file changes and then stays unchanged for one
+    /// polling period (so that we don't load a half-written library), or
+    /// until `stop` is set.
+    ///
+    /// Returns whether a file changed; if so, the watcher takes the new state
+    /// of the files as unchanged.
+    pub fn wait(&mut self, stop: &AtomicBool) -> bool {
+        while !self.has_changed() {
+            if stop.load(Ordering::Acquire) {
+                return false;
+            }
+            thread::sleep(self.period);
+        }
+        let mut stamps = self.current();
+        loop {
+            thread::sleep(self.period);
+            if stop.load(Ordering::Acquire) {
+                return false;
+            }
+            let now = self.current();
+            if now == stamps {
+                break;
+            }
+            stamps = now;
+        }
+        self.stamps = stamps;
+        true
+    }
+
+    /// Blocks, setting `changed`, as soon as a watched file changes or
+    /// `done` is set.
+    ///
+    /// This is for running on a separate thread while a test runs, with
+    /// `changed` also serving as the signal of a halt rule.
+    pub fn notify(&self, changed: &AtomicBool, done: &AtomicBool) {
+        while !done.load(Ordering::Acquire) {
+            if self.has_changed() {
+                changed.store(true, Ordering::Release);
+                return;
+            }
+            thread::sleep(self.period);
+        }
+    }
+
+    fn current(&self) -> Vec<Option<SystemTime>> {
+        self.paths.iter().map(|p| stamp(p)).collect()
+    }
+}
+
+/// Gets the modification time of `path`, if it exists.
+fn stamp(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the watcher sees a file's modification and then settles.
+    #[test]
+    fn test_watcher_sees_modification() -> std::io::Result<()> {
+        let path = std::env::temp_dir().join(format!("phph-watch-{}", std::process::id()));
+        std::fs::write(&path, "old")?;
+        let file = std::fs::File::options().write(true).open(&path)?;
+        let mut watcher = Watcher::new(vec![path.clone()]).with_period(Duration::from_millis(1));
+        assert!(!watcher.has_changed());
+
+        file.set_modified(SystemTime::UNIX_EPOCH)?;
+        assert!(watcher.has_changed());
+        assert!(watcher.wait(&AtomicBool::new(false)));
+        assert!(!watcher.has_changed());
+
+        std::fs::remove_file(&path)?;
+        assert!(watcher.has_changed());
+        Ok(())
+    }
+
+    /// Tests that waiting gives up once asked to stop.
+    #[test]
+    fn test_watcher_stops() {
+        let mut watcher = Watcher::new(vec![]).with_period(Duration::from_millis(1));
+        assert!(!watcher.wait(&AtomicBool::new(true)));
+    }
+}
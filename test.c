@@ -76,3 +76,11 @@ check(const struct env *e)
     if (x == 1 && y == 1 && t0r0 == 1 && t1r0 == 0) return true;
     return false;
 }
+
+/* Optionally, `check_description` says in words what `check` checks, so that
+   reports of the test say what it was checked against. */
+const char *
+check_description(void)
+{
+    return "forbid 0:r0=1 /\\ 1:r0=1";
+}